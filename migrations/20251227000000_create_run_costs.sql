@@ -0,0 +1,15 @@
+-- Token usage and cost of finished runs
+CREATE TABLE IF NOT EXISTS run_costs (
+    run_id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    session_id TEXT,
+    model TEXT NOT NULL,
+    prompt_tokens BIGINT NOT NULL,
+    completion_tokens BIGINT NOT NULL,
+    total_tokens BIGINT NOT NULL,
+    -- NULL when the model has no configured price
+    cost_usd DOUBLE PRECISION,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_run_costs_session ON run_costs(session_id);
@@ -0,0 +1,19 @@
+-- Tenant namespaces: rows with a NULL tenant_id are shared with every tenant
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS tenant_id VARCHAR(128);
+ALTER TABLE knowledge_bases ADD COLUMN IF NOT EXISTS tenant_id VARCHAR(128);
+ALTER TABLE knowledge_documents ADD COLUMN IF NOT EXISTS tenant_id VARCHAR(128);
+ALTER TABLE knowledge_chunks ADD COLUMN IF NOT EXISTS tenant_id VARCHAR(128);
+ALTER TABLE agents ADD COLUMN IF NOT EXISTS tenant_id VARCHAR(128);
+ALTER TABLE memories ADD COLUMN IF NOT EXISTS tenant_id VARCHAR(128);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_tenant_id ON sessions(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_knowledge_bases_tenant_id ON knowledge_bases(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_knowledge_documents_tenant_id ON knowledge_documents(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_knowledge_chunks_tenant_id ON knowledge_chunks(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_agents_tenant_id ON agents(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_memories_tenant_id ON memories(tenant_id);
+
+-- Knowledge base names are unique per tenant rather than globally
+ALTER TABLE knowledge_bases DROP CONSTRAINT IF EXISTS knowledge_bases_name_key;
+CREATE UNIQUE INDEX IF NOT EXISTS knowledge_bases_tenant_name_idx
+    ON knowledge_bases(COALESCE(tenant_id, ''), name);
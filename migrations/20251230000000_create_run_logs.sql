@@ -0,0 +1,16 @@
+-- Verbatim LLM exchange of each run, for auditability
+CREATE TABLE IF NOT EXISTS run_logs (
+    run_id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    tenant_id TEXT,
+    -- RFC3339 start of the run
+    timestamp TEXT NOT NULL,
+    message_history JSONB NOT NULL,
+    response_text TEXT NOT NULL,
+    tool_calls JSONB NOT NULL,
+    prompt_tokens BIGINT NOT NULL,
+    completion_tokens BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_run_logs_agent ON run_logs(agent_id, created_at DESC);
@@ -0,0 +1,9 @@
+-- Every registered version of each skill, for history and rollback
+CREATE TABLE IF NOT EXISTS skill_versions (
+    skill_id TEXT NOT NULL,
+    -- Semantic version, as declared by the skill
+    version TEXT NOT NULL,
+    definition JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (skill_id, version)
+);
@@ -0,0 +1,24 @@
+-- sha256 (hex) of each chunk's whitespace-normalized text; ingestion skips
+-- chunks whose hash the knowledge base already has
+ALTER TABLE knowledge_chunks ADD COLUMN IF NOT EXISTS content_hash TEXT;
+
+-- Hash existing chunks. Only the oldest copy of duplicated text gets its
+-- hash; later copies keep NULL so the unique index can be built without
+-- deleting anything.
+UPDATE knowledge_chunks AS c
+SET content_hash = first.content_hash
+FROM (
+    SELECT DISTINCT ON (kb_id, content_hash) id, content_hash
+    FROM (
+        SELECT id, kb_id, created_at, encode(
+            sha256(convert_to(regexp_replace(btrim(content, E' \t\n\r\f\v'), '\s+', ' ', 'g'), 'UTF8')),
+            'hex'
+        ) AS content_hash
+        FROM knowledge_chunks
+    ) AS hashed
+    ORDER BY kb_id, content_hash, created_at, id
+) AS first
+WHERE c.id = first.id AND c.content_hash IS NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_knowledge_chunks_kb_content_hash
+    ON knowledge_chunks(kb_id, content_hash);
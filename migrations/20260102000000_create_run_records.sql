@@ -0,0 +1,14 @@
+-- Finished runs with their output, for the run history API
+CREATE TABLE IF NOT EXISTS run_records (
+    run_id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    session_id TEXT,
+    tenant_id TEXT,
+    -- RFC3339 end of the run
+    finished_at TEXT NOT NULL,
+    data JSONB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_run_records_finished ON run_records(finished_at DESC, run_id DESC);
+CREATE INDEX IF NOT EXISTS idx_run_records_session ON run_records(session_id, finished_at DESC);
+CREATE INDEX IF NOT EXISTS idx_run_records_agent ON run_records(agent_id, finished_at DESC);
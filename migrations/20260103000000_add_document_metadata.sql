@@ -0,0 +1,2 @@
+-- Metadata extracted from each document (title, author, word count, ...)
+ALTER TABLE knowledge_documents ADD COLUMN IF NOT EXISTS metadata JSONB;
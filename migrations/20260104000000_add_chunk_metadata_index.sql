@@ -0,0 +1,4 @@
+-- Searches can be restricted to chunks whose metadata contains a filter
+-- object (`metadata @> $filter`); jsonb_path_ops indexes exactly that
+CREATE INDEX IF NOT EXISTS idx_knowledge_chunks_metadata
+    ON knowledge_chunks USING GIN (metadata jsonb_path_ops);
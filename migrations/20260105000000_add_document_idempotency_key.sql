@@ -0,0 +1,3 @@
+-- Client-supplied key of document uploads, so retried uploads aren't stored twice
+ALTER TABLE knowledge_documents ADD COLUMN IF NOT EXISTS idempotency_key TEXT;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_knowledge_documents_idempotency_key ON knowledge_documents(kb_id, idempotency_key);
@@ -0,0 +1,2 @@
+-- SHA-256 of the file a document was last indexed from, so re-indexing skips unchanged files
+ALTER TABLE knowledge_documents ADD COLUMN IF NOT EXISTS content_hash TEXT;
@@ -0,0 +1,26 @@
+-- Knowledge graphs: entities and relationships extracted from the chunks of
+-- a knowledge base during ingestion
+CREATE TABLE IF NOT EXISTS knowledge_entities (
+    id TEXT PRIMARY KEY,
+    kb_id TEXT NOT NULL REFERENCES knowledge_bases(id) ON DELETE CASCADE,
+    label TEXT NOT NULL,
+    entity_type JSONB NOT NULL,
+    properties JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS knowledge_entities_kb_idx ON knowledge_entities(kb_id);
+
+CREATE TABLE IF NOT EXISTS knowledge_relationships (
+    id TEXT PRIMARY KEY,
+    kb_id TEXT NOT NULL REFERENCES knowledge_bases(id) ON DELETE CASCADE,
+    source_id TEXT NOT NULL REFERENCES knowledge_entities(id) ON DELETE CASCADE,
+    target_id TEXT NOT NULL REFERENCES knowledge_entities(id) ON DELETE CASCADE,
+    relationship_type TEXT NOT NULL,
+    weight REAL NOT NULL DEFAULT 1.0,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS knowledge_relationships_kb_idx ON knowledge_relationships(kb_id);
+CREATE INDEX IF NOT EXISTS knowledge_relationships_source_idx ON knowledge_relationships(source_id);
+CREATE INDEX IF NOT EXISTS knowledge_relationships_target_idx ON knowledge_relationships(target_id);
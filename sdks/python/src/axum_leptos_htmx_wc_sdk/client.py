@@ -16,13 +16,19 @@ from .types import (
 )
 
 
+REQUEST_ID_HEADER = "x-request-id"
+
+
 class ApiError(Exception):
-    """API error with status code."""
+    """API error with status code and the request's `X-Request-Id`."""
 
-    def __init__(self, status: int, message: str) -> None:
+    def __init__(
+        self, status: int, message: str, request_id: str | None = None
+    ) -> None:
         super().__init__(message)
         self.status = status
         self.message = message
+        self.request_id = request_id
 
 
 class Client:
@@ -74,7 +80,9 @@ class Client:
             json={"message": message, "session_id": session_id},
         )
         self._check_response(res)
-        return ChatResponse.model_validate(res.json())
+        return ChatResponse.model_validate(
+            {**res.json(), "request_id": res.headers.get(REQUEST_ID_HEADER)}
+        )
 
     async def get_messages(self, session_id: str) -> list[Message]:
         """Get messages for a session.
@@ -112,7 +120,9 @@ class Client:
             json={"input": input_text, "context": context},
         )
         self._check_response(res)
-        return RunResponse.model_validate(res.json())
+        return RunResponse.model_validate(
+            {**res.json(), "request_id": res.headers.get(REQUEST_ID_HEADER)}
+        )
 
     def run_stream_url(self, run_id: str) -> str:
         """Get the stream URL for a run.
@@ -249,4 +259,6 @@ class Client:
     def _check_response(self, res: httpx.Response) -> None:
         """Check response and raise ApiError if not successful."""
         if not res.is_success:
-            raise ApiError(res.status_code, res.text)
+            raise ApiError(
+                res.status_code, res.text, res.headers.get(REQUEST_ID_HEADER)
+            )
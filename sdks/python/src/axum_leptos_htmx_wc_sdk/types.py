@@ -15,6 +15,8 @@ class ChatResponse(BaseModel):
 
     session_id: str
     stream_url: str
+    request_id: str | None = None
+    """`X-Request-Id` the server answered with, for support tickets."""
 
 
 class Message(BaseModel):
@@ -34,6 +36,8 @@ class RunResponse(BaseModel):
 
     id: str
     stream_url: str
+    request_id: str | None = None
+    """`X-Request-Id` the server answered with, for support tickets."""
 
 
 # =============================================================================
@@ -4,8 +4,32 @@ use crate::{
     error::{Error, Result},
     types::*,
 };
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::time::Duration;
 use url::Url;
 
+/// Maximum number of consecutive reconnection attempts for an event stream.
+const MAX_STREAM_RECONNECTS: u32 = 3;
+
+/// Base delay between reconnection attempts (doubled on each retry).
+const STREAM_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// Default request timeout.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default number of retries for idempotent (GET) requests.
+const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// Base delay between GET retries (doubled on each retry).
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+
+/// Maximum number of response body characters included in API errors.
+const ERROR_BODY_SNIPPET_LEN: usize = 512;
+
+/// Header the server identifies each request with.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
 /// HTTP client for the API.
 ///
 /// # Example
@@ -28,26 +52,50 @@ use url::Url;
 pub struct Client {
     base_url: Url,
     http: reqwest::Client,
+    /// Total timeout of regular (non-streaming) requests
+    request_timeout: Option<Duration>,
+    max_retries: u32,
 }
 
 impl Client {
-    /// Create a new client.
+    /// Create a new client with default timeout and retry settings.
     ///
     /// # Arguments
     ///
     /// * `base_url` - The base URL of the server (e.g., "http://localhost:3000")
     pub fn new(base_url: impl AsRef<str>) -> Result<Self> {
-        let base_url = Url::parse(base_url.as_ref())?;
-        Ok(Self {
-            base_url,
-            http: reqwest::Client::new(),
-        })
+        Self::builder(base_url).build()
+    }
+
+    /// Create a builder to configure timeouts, retries, and headers.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use axum_leptos_htmx_wc_sdk::Client;
+    /// use std::time::Duration;
+    ///
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::builder("http://localhost:3000")
+    ///     .timeout(Duration::from_secs(10))
+    ///     .max_retries(3)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder(base_url: impl AsRef<str>) -> ClientBuilder {
+        ClientBuilder::new(base_url)
     }
 
     /// Create a new client with a custom reqwest client.
     pub fn with_client(base_url: impl AsRef<str>, http: reqwest::Client) -> Result<Self> {
         let base_url = Url::parse(base_url.as_ref())?;
-        Ok(Self { base_url, http })
+        Ok(Self {
+            base_url,
+            http,
+            request_timeout: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+        })
     }
 
     /// Get the base URL.
@@ -89,23 +137,144 @@ impl Client {
             .unwrap_or_else(|_| self.base_url.clone())
     }
 
+    /// Start a regular (non-streaming) request, bounded by the request
+    /// timeout.
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, self.url(path));
+        match self.request_timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
+
+    /// Send an idempotent GET request, retrying transient failures with backoff.
+    ///
+    /// Connection errors, timeouts, 408, 429 and 5xx responses are retried
+    /// up to `max_retries` times.
+    async fn get(&self, path: &str) -> Result<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            let result = self.request(reqwest::Method::GET, path).send().await;
+            let retryable = match &result {
+                Ok(response) => is_retryable_status(response.status()),
+                Err(e) => e.is_timeout() || e.is_connect(),
+            };
+            if !retryable || attempt >= self.max_retries {
+                return Ok(result?);
+            }
+
+            tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt)).await;
+            attempt += 1;
+        }
+    }
+
     async fn handle_response<T: serde::de::DeserializeOwned>(
         response: reqwest::Response,
     ) -> Result<T> {
-        let status = response.status();
-        if status.is_success() {
+        if response.status().is_success() {
             Ok(response.json().await?)
         } else {
-            let message = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".into());
-            Err(Error::Api {
-                status: status.as_u16(),
-                message,
-            })
+            Err(Self::api_error(response).await)
         }
     }
+
+    /// Build an [`Error::Api`] from a failed response, including the request
+    /// path and a snippet of the body.
+    async fn api_error(response: reqwest::Response) -> Error {
+        let status = response.status();
+        let path = response.url().path().to_string();
+        let request_id = request_id(&response);
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".into());
+        let snippet: String = body.chars().take(ERROR_BODY_SNIPPET_LEN).collect();
+        let ellipsis = if snippet.len() < body.len() { "…" } else { "" };
+        Error::Api {
+            status: status.as_u16(),
+            message: format!("{path}: {snippet}{ellipsis}"),
+            request_id,
+        }
+    }
+}
+
+/// The `X-Request-Id` of a response, if the server sent one.
+fn request_id(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+}
+
+// =============================================================================
+// Client Builder
+// =============================================================================
+
+/// Builder for [`Client`].
+#[derive(Debug)]
+pub struct ClientBuilder {
+    base_url: String,
+    timeout: Duration,
+    max_retries: u32,
+    default_headers: reqwest::header::HeaderMap,
+}
+
+impl ClientBuilder {
+    /// Create a builder with default settings (30s timeout, 2 GET retries).
+    pub fn new(base_url: impl AsRef<str>) -> Self {
+        Self {
+            base_url: base_url.as_ref().to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            max_retries: DEFAULT_MAX_RETRIES,
+            default_headers: reqwest::header::HeaderMap::new(),
+        }
+    }
+
+    /// Set the request timeout.
+    ///
+    /// Bounds regular requests from connecting to reading the whole
+    /// response. Event streams are long-lived and have no total timeout:
+    /// only connecting, and each wait for more data, are bounded by it (the
+    /// server sends keepalives on idle streams).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the maximum number of retries for idempotent (GET) requests.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set headers sent with every request (e.g. `Authorization`).
+    pub fn default_headers(mut self, headers: reqwest::header::HeaderMap) -> Self {
+        self.default_headers = headers;
+        self
+    }
+
+    /// Build the client.
+    pub fn build(self) -> Result<Client> {
+        let base_url = Url::parse(&self.base_url)?;
+        let http = reqwest::Client::builder()
+            .connect_timeout(self.timeout)
+            .read_timeout(self.timeout)
+            .default_headers(self.default_headers)
+            .build()?;
+        Ok(Client {
+            base_url,
+            http,
+            request_timeout: Some(self.timeout),
+            max_retries: self.max_retries,
+        })
+    }
 }
 
 // =============================================================================
@@ -132,35 +301,316 @@ impl<'a> ChatApi<'a> {
         message: impl Into<String>,
         session_id: Option<String>,
     ) -> Result<ChatResponse> {
-        let req = ChatRequest {
+        self.send_request(&ChatRequest {
             message: message.into(),
             session_id,
-        };
+            ephemeral: false,
+        })
+        .await
+    }
+
+    /// Send a chat message in a new ephemeral session.
+    ///
+    /// Ephemeral sessions are kept in server memory only and are never
+    /// written to the persistence backend.
+    pub async fn send_ephemeral(&self, message: impl Into<String>) -> Result<ChatResponse> {
+        self.send_request(&ChatRequest {
+            message: message.into(),
+            session_id: None,
+            ephemeral: true,
+        })
+        .await
+    }
+
+    async fn send_request(&self, req: &ChatRequest) -> Result<ChatResponse> {
         let response = self
             .client
-            .http
-            .post(self.client.url("/api/chat"))
-            .json(&req)
+            .request(reqwest::Method::POST, "/api/chat")
+            .json(req)
             .send()
             .await?;
-        Client::handle_response(response).await
+        let request_id = request_id(&response);
+        let chat: ChatResponse = Client::handle_response(response).await?;
+        Ok(ChatResponse { request_id, ..chat })
+    }
+
+    /// Send a chat message and stream the resulting run events.
+    ///
+    /// POSTs to `/api/chat`, then connects to the returned stream URL and
+    /// parses the SSE frames into [`NormalizedEvent`]s. Dropped connections
+    /// are retried with backoff and resume after the last event received;
+    /// the stream ends after `RunDone` or `Error`.
+    /// Parse failures are yielded as `Err` items without ending the stream.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use axum_leptos_htmx_wc_sdk::{Client, NormalizedEvent};
+    /// use futures::StreamExt;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("http://localhost:3000")?;
+    /// let mut events = Box::pin(client.chat().stream("Hello!"));
+    /// while let Some(event) = events.next().await {
+    ///     if let NormalizedEvent::ChatDelta { text_delta, .. } = event? {
+    ///         print!("{text_delta}");
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream(
+        &self,
+        message: impl Into<String>,
+    ) -> impl Stream<Item = Result<NormalizedEvent>> + Send + 'static {
+        self.stream_with_session(message, None)
+    }
+
+    /// Stream a chat message in an existing session.
+    pub fn stream_with_session(
+        &self,
+        message: impl Into<String>,
+        session_id: Option<String>,
+    ) -> impl Stream<Item = Result<NormalizedEvent>> + Send + 'static {
+        let state = EventStreamState {
+            client: self.client.clone(),
+            request: Some(ChatRequest {
+                message: message.into(),
+                session_id,
+                ephemeral: false,
+            }),
+            stream_url: None,
+            body: None,
+            parser: SseParser::default(),
+            last_event_id: None,
+            reconnects: 0,
+            done: false,
+        };
+
+        futures::stream::unfold(state, |mut state| async move {
+            let item = state.next_event().await?;
+            Some((item, state))
+        })
     }
 
     /// Get messages for a session.
     pub async fn get_messages(&self, session_id: &str) -> Result<Vec<Message>> {
         let response = self
             .client
-            .http
-            .get(
-                self.client
-                    .url(&format!("/api/sessions/{session_id}/messages")),
-            )
-            .send()
+            .get(&format!("/api/sessions/{session_id}/messages"))
             .await?;
         Client::handle_response(response).await
     }
 }
 
+// =============================================================================
+// Event Streaming
+// =============================================================================
+
+type ByteStream = Pin<Box<dyn Stream<Item = reqwest::Result<Vec<u8>>> + Send>>;
+
+/// State driving a chat event stream.
+struct EventStreamState {
+    client: Client,
+    /// Pending chat request (taken once the run has been started).
+    request: Option<ChatRequest>,
+    stream_url: Option<Url>,
+    body: Option<ByteStream>,
+    parser: SseParser,
+    /// ID of the last frame received, sent as `Last-Event-ID` on reconnect
+    /// so the server replays only what was missed.
+    last_event_id: Option<String>,
+    /// Reconnection attempts since the last successful read.
+    reconnects: u32,
+    done: bool,
+}
+
+impl EventStreamState {
+    /// Produce the next stream item, or `None` once the run has finished.
+    async fn next_event(&mut self) -> Option<Result<NormalizedEvent>> {
+        loop {
+            if self.done {
+                return None;
+            }
+
+            // Drain frames already buffered before reading more bytes
+            if let Some(frame) = self.parser.next_frame() {
+                if frame.id.is_some() {
+                    self.last_event_id = frame.id;
+                }
+                let Some(data) = frame.data else { continue };
+                let Some(item) = decode_event(&data) else {
+                    continue;
+                };
+                if let Ok(event) = &item {
+                    self.done = event.is_terminal();
+                }
+                return Some(item);
+            }
+
+            if self.stream_url.is_none() {
+                let request = self.request.take()?;
+                let chat = ChatApi {
+                    client: &self.client,
+                };
+                match chat
+                    .send_with_session(request.message, request.session_id)
+                    .await
+                {
+                    Ok(response) => self.stream_url = Some(self.client.url(&response.stream_url)),
+                    Err(e) => {
+                        self.done = true;
+                        return Some(Err(e));
+                    }
+                }
+            }
+
+            if self.body.is_none() {
+                if let Err(e) = self.connect().await {
+                    if !self.backoff().await {
+                        self.done = true;
+                        return Some(Err(e));
+                    }
+                    continue;
+                }
+            }
+
+            let chunk = match self.body.as_mut() {
+                Some(body) => body.next().await,
+                None => continue,
+            };
+            match chunk {
+                Some(Ok(bytes)) => {
+                    self.reconnects = 0;
+                    self.parser.push(&bytes);
+                }
+                Some(Err(e)) => {
+                    self.body = None;
+                    if !self.backoff().await {
+                        self.done = true;
+                        return Some(Err(Error::Http(e)));
+                    }
+                }
+                None => {
+                    // Server closed the connection before the run finished
+                    self.body = None;
+                    if !self.backoff().await {
+                        self.done = true;
+                        return Some(Err(Error::StreamEnded));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Open the SSE connection for the run.
+    async fn connect(&mut self) -> Result<()> {
+        let Some(url) = self.stream_url.clone() else {
+            return Err(Error::StreamEnded);
+        };
+        let mut request = self
+            .client
+            .http
+            .get(url)
+            .header(reqwest::header::ACCEPT, "text/event-stream");
+        if let Some(id) = &self.last_event_id {
+            request = request.header("Last-Event-ID", id);
+        }
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            return Err(Client::api_error(response).await);
+        }
+
+        self.parser = SseParser::default();
+        self.body = Some(Box::pin(
+            response
+                .bytes_stream()
+                .map(|chunk| chunk.map(|b| b.to_vec())),
+        ));
+        Ok(())
+    }
+
+    /// Wait before reconnecting. Returns `false` once retries are exhausted.
+    async fn backoff(&mut self) -> bool {
+        if self.reconnects >= MAX_STREAM_RECONNECTS {
+            return false;
+        }
+        let delay = STREAM_RECONNECT_DELAY * 2u32.pow(self.reconnects);
+        self.reconnects += 1;
+        tokio::time::sleep(delay).await;
+        true
+    }
+}
+
+/// Decode an SSE data payload; heartbeats decode to `None`.
+fn decode_event(data: &str) -> Option<Result<NormalizedEvent>> {
+    match serde_json::from_str::<NormalizedEvent>(data) {
+        Ok(NormalizedEvent::Heartbeat { .. }) => None,
+        Ok(event) => Some(Ok(event)),
+        Err(e) => Some(Err(Error::Json(e))),
+    }
+}
+
+/// A single parsed SSE frame.
+#[derive(Debug, Default, PartialEq)]
+struct SseFrame {
+    id: Option<String>,
+    event: Option<String>,
+    data: Option<String>,
+}
+
+/// Incremental parser for `text/event-stream` bodies.
+#[derive(Debug, Default)]
+struct SseParser {
+    /// Bytes of the line being received, decoded once it is complete so
+    /// characters split across chunks survive.
+    partial: Vec<u8>,
+    buffer: String,
+}
+
+impl SseParser {
+    /// Append raw body bytes.
+    fn push(&mut self, chunk: &[u8]) {
+        self.partial.extend_from_slice(chunk);
+        // A newline byte never occurs inside a multi-byte UTF-8 character
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return;
+        };
+        let lines: Vec<u8> = self.partial.drain(..=end).collect();
+        let text = String::from_utf8_lossy(&lines).replace("\r\n", "\n");
+        self.buffer.push_str(&text);
+    }
+
+    /// Pop the next complete frame, if one is buffered.
+    fn next_frame(&mut self) -> Option<SseFrame> {
+        let end = self.buffer.find("\n\n")?;
+        let raw: String = self.buffer.drain(..end + 2).collect();
+
+        let mut frame = SseFrame::default();
+        let mut data_lines = Vec::new();
+        for line in raw.lines() {
+            // Comment lines (":") are keep-alives
+            if line.is_empty() || line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                // IDs containing NUL are ignored, as EventSource does
+                "id" if !value.contains('\0') => frame.id = Some(value.to_string()),
+                "event" => frame.event = Some(value.to_string()),
+                "data" => data_lines.push(value),
+                _ => {}
+            }
+        }
+        if !data_lines.is_empty() {
+            frame.data = Some(data_lines.join("\n"));
+        }
+        Some(frame)
+    }
+}
+
 // =============================================================================
 // Runs API
 // =============================================================================
@@ -180,12 +630,13 @@ impl<'a> RunsApi<'a> {
         };
         let response = self
             .client
-            .http
-            .post(self.client.url("/api/runs"))
+            .request(reqwest::Method::POST, "/api/runs")
             .json(&req)
             .send()
             .await?;
-        Client::handle_response(response).await
+        let request_id = request_id(&response);
+        let run: RunResponse = Client::handle_response(response).await?;
+        Ok(RunResponse { request_id, ..run })
     }
 
     /// Get the stream URL for a run.
@@ -209,12 +660,7 @@ pub struct KnowledgeApi<'a> {
 impl<'a> KnowledgeApi<'a> {
     /// List all knowledge bases.
     pub async fn list(&self) -> Result<Vec<KnowledgeBase>> {
-        let response = self
-            .client
-            .http
-            .get(self.client.url("/api/knowledge"))
-            .send()
-            .await?;
+        let response = self.client.get("/api/knowledge").await?;
         Client::handle_response(response).await
     }
 
@@ -222,8 +668,7 @@ impl<'a> KnowledgeApi<'a> {
     pub async fn create(&self, req: CreateKnowledgeBaseRequest) -> Result<KnowledgeBase> {
         let response = self
             .client
-            .http
-            .post(self.client.url("/api/knowledge"))
+            .request(reqwest::Method::POST, "/api/knowledge")
             .json(&req)
             .send()
             .await?;
@@ -232,12 +677,7 @@ impl<'a> KnowledgeApi<'a> {
 
     /// Get a knowledge base by ID.
     pub async fn get(&self, id: &str) -> Result<KnowledgeBase> {
-        let response = self
-            .client
-            .http
-            .get(self.client.url(&format!("/api/knowledge/{id}")))
-            .send()
-            .await?;
+        let response = self.client.get(&format!("/api/knowledge/{id}")).await?;
         Client::handle_response(response).await
     }
 
@@ -245,21 +685,13 @@ impl<'a> KnowledgeApi<'a> {
     pub async fn delete(&self, id: &str) -> Result<()> {
         let response = self
             .client
-            .http
-            .delete(self.client.url(&format!("/api/knowledge/{id}")))
+            .request(reqwest::Method::DELETE, &format!("/api/knowledge/{id}"))
             .send()
             .await?;
         if response.status().is_success() {
             Ok(())
         } else {
-            let message = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".into());
-            Err(Error::Api {
-                status: response.status().as_u16(),
-                message,
-            })
+            Err(Client::api_error(response).await)
         }
     }
 
@@ -267,12 +699,7 @@ impl<'a> KnowledgeApi<'a> {
     pub async fn list_documents(&self, kb_id: &str) -> Result<Vec<Document>> {
         let response = self
             .client
-            .http
-            .get(
-                self.client
-                    .url(&format!("/api/knowledge/{kb_id}/documents")),
-            )
-            .send()
+            .get(&format!("/api/knowledge/{kb_id}/documents"))
             .await?;
         Client::handle_response(response).await
     }
@@ -297,8 +724,7 @@ impl<'a> KnowledgeApi<'a> {
         };
         let response = self
             .client
-            .http
-            .post(self.client.url(&format!("/api/knowledge/{kb_id}/search")))
+            .request(reqwest::Method::POST, &format!("/api/knowledge/{kb_id}/search"))
             .json(&req)
             .send()
             .await?;
@@ -325,11 +751,271 @@ impl<'a> IngestApi<'a> {
         };
         let response = self
             .client
-            .http
-            .post(self.client.url("/api/ingest"))
+            .request(reqwest::Method::POST, "/api/ingest")
             .json(&req)
             .send()
             .await?;
         Client::handle_response(response).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser_frames() {
+        let mut parser = SseParser::default();
+        parser.push(b"event: message\ndata: {\"type\":\"RunDone\",");
+        assert!(parser.next_frame().is_none());
+
+        parser.push(b"\"data\":{\"run_id\":\"r1\"}}\n\n: keep-alive\n\n");
+        let frame = parser.next_frame().unwrap();
+        assert_eq!(frame.event.as_deref(), Some("message"));
+        let event: NormalizedEvent = serde_json::from_str(&frame.data.unwrap()).unwrap();
+        assert_eq!(
+            event,
+            NormalizedEvent::RunDone {
+                run_id: "r1".to_string(),
+                usage: None,
+            }
+        );
+
+        // Comment-only frame carries no data
+        assert_eq!(parser.next_frame(), Some(SseFrame::default()));
+        assert!(parser.next_frame().is_none());
+    }
+
+    #[test]
+    fn test_sse_parser_ids() {
+        let mut parser = SseParser::default();
+        parser.push(b"id: 7\ndata: {}\n\nid: a\0b\ndata: {}\n\n");
+        assert_eq!(parser.next_frame().unwrap().id.as_deref(), Some("7"));
+        assert_eq!(parser.next_frame().unwrap().id, None);
+    }
+
+    #[test]
+    fn test_stream_resumes_after_last_event_id() {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let frames = [
+            (1, r#"{"type":"ChatDelta","data":{"run_id":"r1","text_delta":"hi"}}"#),
+            (2, r#"{"type":"RunDone","data":{"run_id":"r1"}}"#),
+        ];
+        // Each connection gets one frame and is closed, before the run is done
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (id, data) in frames {
+                let (mut socket, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(socket.try_clone().unwrap());
+                let mut request = String::new();
+                while reader.read_line(&mut request).unwrap() > 2 {}
+                let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\
+                    connection: close\r\n\r\n";
+                write!(socket, "{head}id: {id}\ndata: {data}\n\n").unwrap();
+                requests.push(request.to_lowercase());
+            }
+            requests
+        });
+
+        let client = Client::new(format!("http://{addr}")).unwrap();
+        let mut state = EventStreamState {
+            stream_url: Some(client.url("/api/uar/runs/r1/stream")),
+            client,
+            request: None,
+            body: None,
+            parser: SseParser::default(),
+            last_event_id: None,
+            reconnects: 0,
+            done: false,
+        };
+        let events = tokio_test::block_on(async {
+            let mut events = Vec::new();
+            while let Some(event) = state.next_event().await {
+                events.push(event.unwrap());
+            }
+            events
+        });
+        assert_eq!(events.len(), 2);
+        assert!(events[1].is_terminal());
+        // Reading from the second connection reset the attempts
+        assert_eq!(state.reconnects, 0);
+
+        let requests = server.join().unwrap();
+        assert!(!requests[0].contains("last-event-id"), "{}", requests[0]);
+        assert!(requests[1].contains("last-event-id: 1\r\n"), "{}", requests[1]);
+    }
+
+    #[test]
+    fn test_heartbeats_filtered() {
+        let heartbeat = r#"{"type":"Heartbeat","data":{"run_id":"r1","timestamp_ms":1}}"#;
+        assert!(decode_event(heartbeat).is_none());
+
+        let delta = r#"{"type":"ChatDelta","data":{"run_id":"r1","text_delta":"hi"}}"#;
+        assert!(matches!(
+            decode_event(delta),
+            Some(Ok(NormalizedEvent::ChatDelta { .. }))
+        ));
+        assert!(matches!(decode_event("not json"), Some(Err(Error::Json(_)))));
+    }
+
+    #[test]
+    fn test_run_paused_decodes() {
+        let paused = r#"{"type":"RunPaused","data":{"run_id":"r1"}}"#;
+        let event = decode_event(paused).unwrap().unwrap();
+        assert_eq!(
+            event,
+            NormalizedEvent::RunPaused {
+                run_id: "r1".to_string(),
+            }
+        );
+        assert!(!event.is_terminal());
+    }
+
+    #[test]
+    fn test_tool_approval_request_decodes() {
+        let request = serde_json::json!({
+            "type": "ToolApprovalRequest",
+            "data": {
+                "run_id": "r1",
+                "tool_call_id": "c1",
+                "tool": "fs::delete",
+                "input": {"path": "/tmp/x"},
+            },
+        });
+        let event = decode_event(&request.to_string()).unwrap().unwrap();
+        assert_eq!(
+            event,
+            NormalizedEvent::ToolApprovalRequest {
+                run_id: "r1".to_string(),
+                tool_call_id: "c1".to_string(),
+                tool: "fs::delete".to_string(),
+                input: serde_json::json!({"path": "/tmp/x"}),
+            }
+        );
+        assert!(!event.is_terminal());
+    }
+
+    #[test]
+    fn test_run_cancelled_decodes() {
+        let cancelled = r#"{"type":"RunCancelled","data":{"run_id":"r1"}}"#;
+        let event = decode_event(cancelled).unwrap().unwrap();
+        assert_eq!(
+            event,
+            NormalizedEvent::RunCancelled {
+                run_id: "r1".to_string(),
+            }
+        );
+        // The stream goes on until the RunDone that follows
+        assert!(!event.is_terminal());
+    }
+
+    #[test]
+    fn test_run_phase_decodes() {
+        let phase = r#"{"type":"RunPhase","data":{"run_id":"r1","phase":"tool_execution"}}"#;
+        let event = decode_event(phase).unwrap().unwrap();
+        assert_eq!(
+            event,
+            NormalizedEvent::RunPhase {
+                run_id: "r1".to_string(),
+                phase: RunPhase::ToolExecution,
+            }
+        );
+        assert!(!event.is_terminal());
+    }
+
+    #[test]
+    fn test_refusal_delta_decodes() {
+        let refusal = r#"{"type":"RefusalDelta","data":{"run_id":"r1","text_delta":"I can't"}}"#;
+        let event = decode_event(refusal).unwrap().unwrap();
+        assert_eq!(
+            event,
+            NormalizedEvent::RefusalDelta {
+                run_id: "r1".to_string(),
+                text_delta: "I can't".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_content_filter_decodes() {
+        let flagged = serde_json::json!({
+            "type": "ContentFilter",
+            "data": {"run_id": "r1", "categories": ["violence"], "severity": "medium"},
+        });
+        let event = decode_event(&flagged.to_string()).unwrap().unwrap();
+        assert_eq!(
+            event,
+            NormalizedEvent::ContentFilter {
+                run_id: "r1".to_string(),
+                categories: vec!["violence".to_string()],
+                severity: Some(ContentFilterSeverity::Medium),
+            }
+        );
+        assert!(!event.is_terminal());
+
+        // Providers without severities leave it out
+        let flagged = r#"{"type":"ContentFilter","data":{"run_id":"r1","categories":[]}}"#;
+        let Some(Ok(NormalizedEvent::ContentFilter { severity, .. })) = decode_event(flagged) else {
+            panic!("ContentFilter did not decode");
+        };
+        assert_eq!(severity, None);
+    }
+
+    #[test]
+    fn test_unknown_events_decode() {
+        let future = r#"{"type":"SomethingNew","data":{"level":2,"run_id":"r1"}}"#;
+        let event = decode_event(future).unwrap().unwrap();
+        assert_eq!(
+            event,
+            NormalizedEvent::Unknown {
+                event_type: "SomethingNew".to_string(),
+                data: serde_json::json!({"level": 2, "run_id": "r1"}),
+            }
+        );
+        assert!(!event.is_terminal());
+        assert_eq!(serde_json::to_string(&event).unwrap(), future);
+    }
+
+    #[test]
+    fn test_retryable_status() {
+        assert!(is_retryable_status(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_retryable_status(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable_status(reqwest::StatusCode::NOT_FOUND));
+        assert!(!is_retryable_status(reqwest::StatusCode::OK));
+    }
+
+    #[test]
+    fn test_builder_defaults() {
+        let client = Client::builder("http://localhost:3000")
+            .max_retries(5)
+            .build()
+            .unwrap();
+        assert_eq!(client.max_retries, 5);
+        assert_eq!(client.request_timeout, Some(DEFAULT_TIMEOUT));
+        assert_eq!(client.base_url().as_str(), "http://localhost:3000/");
+    }
+
+    #[test]
+    fn test_sse_parser_crlf() {
+        let mut parser = SseParser::default();
+        parser.push(b"event: done\r\ndata: x\r\n\r\n");
+        let frame = parser.next_frame().unwrap();
+        assert_eq!(frame.event.as_deref(), Some("done"));
+        assert_eq!(frame.data.as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn test_sse_parser_multibyte_split_across_chunks() {
+        let body = "data: caf\u{e9} \u{1f600}\n\n".as_bytes();
+        let mut parser = SseParser::default();
+        // Split inside both the two-byte and the four-byte character
+        parser.push(&body[..10]);
+        parser.push(&body[10..14]);
+        parser.push(&body[14..]);
+        let frame = parser.next_frame().unwrap();
+        assert_eq!(frame.data.as_deref(), Some("caf\u{e9} \u{1f600}"));
+    }
+}
@@ -25,6 +25,8 @@ pub enum Error {
         status: u16,
         /// Error message from the API.
         message: String,
+        /// `X-Request-Id` of the failed request, for support tickets.
+        request_id: Option<String>,
     },
 
     /// Configuration error (embedded mode).
@@ -62,7 +62,7 @@ pub use error::Error;
 pub use types::*;
 
 #[cfg(feature = "http-client")]
-pub use client::Client;
+pub use client::{Client, ClientBuilder};
 
 #[cfg(feature = "embedded")]
 pub use runtime::Runtime;
@@ -17,6 +17,9 @@ pub struct ChatRequest {
     /// Optional session ID to continue an existing conversation.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub session_id: Option<String>,
+    /// Keep a newly created session in memory only (never persisted).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ephemeral: bool,
 }
 
 /// Response from starting a chat.
@@ -26,6 +29,9 @@ pub struct ChatResponse {
     pub session_id: String,
     /// URL to stream chat events from.
     pub stream_url: String,
+    /// `X-Request-Id` the server answered with, for support tickets.
+    #[serde(default)]
+    pub request_id: Option<String>,
 }
 
 /// A message in a conversation.
@@ -37,6 +43,336 @@ pub struct Message {
     pub content: String,
 }
 
+// =============================================================================
+// Streaming Event Types
+// =============================================================================
+
+/// A streaming event emitted by a run.
+///
+/// Mirrors the server's UAR `NormalizedEvent` wire format
+/// (`{"type": "...", "data": {...}}`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum NormalizedEvent {
+    /// The run has started.
+    RunStart {
+        /// Run identifier.
+        run_id: String,
+        /// Agent executing the run.
+        agent_id: String,
+    },
+    /// Incremental assistant text.
+    ChatDelta {
+        /// Run identifier.
+        run_id: String,
+        /// Text fragment to append.
+        text_delta: String,
+    },
+    /// Incremental reasoning text.
+    ReasoningDelta {
+        /// Run identifier.
+        run_id: String,
+        /// Reasoning fragment to append.
+        text_delta: String,
+    },
+    /// Incremental refusal text; the model declined to answer, so it is
+    /// sent instead of `ChatDelta`s.
+    RefusalDelta {
+        /// Run identifier.
+        run_id: String,
+        /// Refusal fragment to append.
+        text_delta: String,
+    },
+    /// The provider's content filter flagged the prompt or the response.
+    ContentFilter {
+        /// Run identifier.
+        run_id: String,
+        /// Flagged categories (e.g. "hate", "violence").
+        categories: Vec<String>,
+        /// Highest severity assigned, when the provider reports one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        severity: Option<ContentFilterSeverity>,
+    },
+    /// Sources cited by the assistant.
+    Citation {
+        /// Run identifier.
+        run_id: String,
+        /// Cited sources.
+        sources: Vec<CitationSource>,
+    },
+    /// Memories recalled for the run.
+    MemoryRecall {
+        /// Run identifier.
+        run_id: String,
+        /// Recalled items.
+        items: Vec<MemoryItem>,
+    },
+    /// A tool call has started.
+    ToolStart {
+        /// Run identifier.
+        run_id: String,
+        /// Tool call identifier.
+        tool_call_id: String,
+        /// Tool name.
+        tool: String,
+        /// Tool arguments.
+        input: serde_json::Value,
+    },
+    /// Incremental tool output.
+    ToolDelta {
+        /// Run identifier.
+        run_id: String,
+        /// Tool call identifier.
+        tool_call_id: String,
+        /// Output fragment.
+        delta: serde_json::Value,
+    },
+    /// A tool call has finished.
+    ToolEnd {
+        /// Run identifier.
+        run_id: String,
+        /// Tool call identifier.
+        tool_call_id: String,
+        /// Tool output.
+        output: serde_json::Value,
+        /// Whether the tool succeeded.
+        ok: bool,
+    },
+    /// A UI artifact produced by the run.
+    Artifact {
+        /// Run identifier.
+        run_id: String,
+        /// Artifact payload.
+        artifact: ArtifactPayload,
+    },
+    /// Running count of completion tokens, estimated while the run streams.
+    PartialUsage {
+        /// Run identifier.
+        run_id: String,
+        /// Completion tokens generated so far.
+        completion_tokens_so_far: u32,
+    },
+    /// Token usage of the run so far, summed over every LLM call it made.
+    Usage {
+        /// Run identifier.
+        run_id: String,
+        /// Tokens in the prompts.
+        prompt_tokens: u32,
+        /// Tokens in the completions.
+        completion_tokens: u32,
+        /// Total tokens used.
+        total_tokens: u32,
+    },
+    /// The run failed.
+    Error {
+        /// Run identifier.
+        run_id: String,
+        /// Machine-readable error code.
+        code: String,
+        /// Human-readable message.
+        message: String,
+        /// ID of the request that started the run.
+        #[serde(default)]
+        request_id: Option<String>,
+    },
+    /// The run completed.
+    RunDone {
+        /// Run identifier.
+        run_id: String,
+        /// Total token usage and cost, when the provider reported usage.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<RunUsage>,
+    },
+    /// A chained run moved on to its next step.
+    ChainProgress {
+        /// Run executing this step.
+        run_id: String,
+        /// 1-based step number.
+        step: usize,
+        /// Number of steps in the chain.
+        total: usize,
+        /// Agent executing this step.
+        agent_id: String,
+    },
+    /// An interactive run executed its tools and waits to be resumed
+    /// (`POST /api/uar/runs/{id}/resume`) before sending the results to the
+    /// model.
+    RunPaused {
+        /// Run identifier.
+        run_id: String,
+    },
+    /// A tool that requires approval waits for a decision
+    /// (`POST /api/uar/runs/{id}/tool-approvals`) before it is called.
+    ToolApprovalRequest {
+        /// Run identifier.
+        run_id: String,
+        /// Tool call identifier.
+        tool_call_id: String,
+        /// Tool name.
+        tool: String,
+        /// Tool arguments.
+        input: serde_json::Value,
+    },
+    /// The run was cancelled by its client; `RunDone` follows.
+    RunCancelled {
+        /// Run identifier.
+        run_id: String,
+    },
+    /// The run moved on to another stage; only sent when the server has run
+    /// phases enabled.
+    RunPhase {
+        /// Run identifier.
+        run_id: String,
+        /// Stage the run entered.
+        phase: RunPhase,
+    },
+    /// Keep-alive sent while the run is idle.
+    ///
+    /// Chat streams returned by the client never yield heartbeats.
+    Heartbeat {
+        /// Run ID.
+        run_id: String,
+        /// Milliseconds since the Unix epoch.
+        timestamp_ms: u64,
+    },
+    /// Context management was applied to the conversation.
+    ContextAction(ContextAction),
+    /// An event this SDK version doesn't know, e.g. from a newer server, or
+    /// a known one whose data doesn't decode.
+    #[serde(untagged)]
+    Unknown {
+        /// The event's `type`.
+        #[serde(rename = "type")]
+        event_type: String,
+        /// The event's `data`, as sent.
+        #[serde(default)]
+        data: serde_json::Value,
+    },
+}
+
+impl NormalizedEvent {
+    /// Whether this event ends the run's stream.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::RunDone { .. } | Self::Error { .. })
+    }
+}
+
+/// A cited source.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CitationSource {
+    /// Source title.
+    pub title: String,
+    /// Source URL.
+    pub url: String,
+    /// Optional snippet from the source.
+    pub snippet: Option<String>,
+    /// Retrieval score, or similarity to the answer when usage is checked.
+    #[serde(default)]
+    pub relevance: Option<f32>,
+    /// Whether the answer used this source.
+    #[serde(default = "default_used")]
+    pub used: bool,
+}
+
+fn default_used() -> bool {
+    true
+}
+
+/// Token usage and cost of a finished run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunUsage {
+    /// Run identifier.
+    pub run_id: String,
+    /// Agent that executed the run.
+    pub agent_id: String,
+    /// Session the run belonged to.
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Model used for the run.
+    pub model: String,
+    /// Tokens in the prompts.
+    pub prompt_tokens: u32,
+    /// Tokens in the completions.
+    pub completion_tokens: u32,
+    /// Total tokens used.
+    pub total_tokens: u32,
+    /// Cost in USD; `None` when the model has no configured price.
+    pub cost_usd: Option<f64>,
+}
+
+/// A recalled memory item.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryItem {
+    /// Memory key.
+    pub key: String,
+    /// Memory value.
+    pub value: String,
+    /// Where the memory came from.
+    pub source: String,
+}
+
+/// A UI artifact payload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactPayload {
+    /// Artifact identifier.
+    pub artifact_id: String,
+    /// Artifact type (e.g. "code", "document").
+    pub artifact_type: String,
+    /// Display title.
+    pub title: String,
+    /// Artifact content.
+    pub content: String,
+    /// Optional language (for code artifacts).
+    pub language: Option<String>,
+    /// Additional metadata.
+    pub metadata: serde_json::Value,
+}
+
+/// Severity a provider's content filter assigned, lowest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFilterSeverity {
+    /// Checked and found safe.
+    Safe,
+    /// Low severity.
+    Low,
+    /// Medium severity.
+    Medium,
+    /// High severity.
+    High,
+}
+
+/// Stage of a run, reported by [`NormalizedEvent::RunPhase`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunPhase {
+    /// Searching the agent's knowledge bases.
+    RagRetrieval,
+    /// Choosing the skills to inject.
+    SkillMatching,
+    /// Waiting on the model's output.
+    Generating,
+    /// Executing tools the model called.
+    ToolExecution,
+    /// Finished; `RunDone` follows.
+    Done,
+}
+
+/// Summary of a context management action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextAction {
+    /// Strategy that was applied (snake_case).
+    pub strategy: String,
+    /// Number of messages removed.
+    pub messages_removed: usize,
+    /// Estimated tokens saved.
+    pub tokens_saved: usize,
+    /// Whether the strategy actually changed the context.
+    pub was_applied: bool,
+    /// Whether a summary was generated.
+    pub summary_generated: bool,
+}
+
 // =============================================================================
 // Runs API Types
 // =============================================================================
@@ -58,6 +394,9 @@ pub struct RunResponse {
     pub id: String,
     /// URL to stream run events from.
     pub stream_url: String,
+    /// `X-Request-Id` the server answered with, for support tickets.
+    #[serde(default)]
+    pub request_id: Option<String>,
 }
 
 // =============================================================================
@@ -99,6 +438,23 @@ pub struct KnowledgeBaseConfig {
     /// Chunking strategy.
     #[serde(default)]
     pub chunk_strategy: String,
+    /// Cross-encoder reranking settings (if enabled).
+    #[serde(default)]
+    pub rerank: Option<RerankerConfig>,
+}
+
+/// Cross-encoder reranking settings for a knowledge base.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RerankerConfig {
+    /// Whether search results are reranked.
+    pub enabled: bool,
+    /// Reranker kind (`none`, `cross_encoder`, `cohere`), if set explicitly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Reranker model name.
+    pub model: String,
+    /// Number of results returned after reranking.
+    pub top_n: usize,
 }
 
 /// Request to create a knowledge base.
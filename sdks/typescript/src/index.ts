@@ -19,6 +19,9 @@
  * ```
  */
 
+/** Header the server identifies each request with. */
+const REQUEST_ID_HEADER = 'x-request-id';
+
 // =============================================================================
 // Types
 // =============================================================================
@@ -31,6 +34,8 @@ export interface ChatRequest {
 export interface ChatResponse {
   session_id: string;
   stream_url: string;
+  /** `X-Request-Id` the server answered with, for support tickets */
+  request_id?: string;
 }
 
 export interface Message {
@@ -46,6 +51,8 @@ export interface CreateRunRequest {
 export interface RunResponse {
   id: string;
   stream_url: string;
+  /** `X-Request-Id` the server answered with, for support tickets */
+  request_id?: string;
 }
 
 export interface KnowledgeBase {
@@ -104,7 +111,8 @@ export interface SearchResult {
 export class ApiError extends Error {
   constructor(
     public status: number,
-    message: string
+    message: string,
+    public requestId?: string
   ) {
     super(message);
     this.name = 'ApiError';
@@ -133,7 +141,7 @@ export class Client {
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify({ message, session_id: sessionId }),
       });
-      return this.handleResponse(res);
+      return this.withRequestId(res, await this.handleResponse<ChatResponse>(res));
     },
 
     getMessages: async (sessionId: string): Promise<Message[]> => {
@@ -153,7 +161,7 @@ export class Client {
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify({ input, context }),
       });
-      return this.handleResponse(res);
+      return this.withRequestId(res, await this.handleResponse<RunResponse>(res));
     },
 
     stream: (runId: string): EventSource => {
@@ -195,7 +203,7 @@ export class Client {
       });
       if (!res.ok) {
         const message = await res.text();
-        throw new ApiError(res.status, message);
+        throw new ApiError(res.status, message, res.headers.get(REQUEST_ID_HEADER) ?? undefined);
       }
     },
 
@@ -246,7 +254,11 @@ export class Client {
       return res.json();
     }
     const message = await res.text();
-    throw new ApiError(res.status, message);
+    throw new ApiError(res.status, message, res.headers.get(REQUEST_ID_HEADER) ?? undefined);
+  }
+
+  private withRequestId<T extends { request_id?: string }>(res: Response, body: T): T {
+    return { ...body, request_id: res.headers.get(REQUEST_ID_HEADER) ?? undefined };
   }
 }
 
@@ -0,0 +1,108 @@
+//! Bounded in-memory cache whose entries expire after a TTL.
+//!
+//! Backs the caches that replay whole responses (LLM answers, knowledge base
+//! searches): least-recently-used eviction once full, entries dropped on the
+//! first lookup after they expire, and optional Prometheus hit and miss
+//! counters.
+
+use lru::LruCache;
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Bounded cache of values that expire `ttl` after they were inserted.
+#[derive(Debug)]
+pub struct TtlLruCache<K: Hash + Eq, V> {
+    entries: Mutex<LruCache<K, (Instant, V)>>,
+    ttl: Duration,
+    /// Prometheus counters for hits and misses
+    metrics: Option<(&'static str, &'static str)>,
+}
+
+impl<K: Hash + Eq, V: Clone> TtlLruCache<K, V> {
+    /// Create a cache holding at most `max_entries` values for `ttl` each.
+    pub fn new(max_entries: usize, ttl: Duration) -> Self {
+        let capacity = NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+            metrics: None,
+        }
+    }
+
+    /// Count lookups in the Prometheus counters `hits` and `misses`.
+    #[must_use]
+    pub fn with_metrics(mut self, hits: &'static str, misses: &'static str) -> Self {
+        self.metrics = Some((hits, misses));
+        self
+    }
+
+    /// The cached value for `key`, if it hasn't expired, recording a hit or
+    /// miss.
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut entries = self.entries.lock().unwrap();
+        let found = match entries.get(key) {
+            Some((cached_at, value)) if cached_at.elapsed() < self.ttl => Some(value.clone()),
+            _ => None,
+        };
+
+        if found.is_none() {
+            // Drop it if it expired
+            entries.pop(key);
+        }
+        if let Some((hits, misses)) = self.metrics {
+            let metric = if found.is_some() { hits } else { misses };
+            metrics::counter!(metric).increment(1);
+        }
+        found
+    }
+
+    /// Cache `value`, evicting the least recently used entry when full.
+    pub fn insert(&self, key: K, value: V) {
+        self.entries
+            .lock()
+            .unwrap()
+            .put(key, (Instant::now(), value));
+    }
+
+    /// Number of cached values, including expired ones not yet dropped.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_entries_expire_and_evict() {
+        let cache = TtlLruCache::new(2, Duration::from_secs(60));
+
+        cache.insert("a".to_string(), 1);
+        assert_eq!(cache.get("a"), Some(1));
+
+        tokio::time::advance(Duration::from_secs(61)).await;
+        assert_eq!(cache.get("a"), None);
+        assert!(cache.is_empty());
+
+        cache.insert("a".to_string(), 1);
+        cache.insert("b".to_string(), 2);
+        cache.get("a");
+        cache.insert("c".to_string(), 3);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.len(), 2);
+    }
+}
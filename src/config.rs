@@ -1,4 +1,8 @@
-use crate::llm::{LlmProtocol, LlmSettings, Provider};
+use crate::llm::{
+    EmptyResponsePolicy, GenerationParams, LlmProtocol, LlmSettings, ModelAliases, Provider,
+    ReasoningEffort, UsageFields,
+};
+use crate::normalized::ContentFilterSeverity;
 use clap::Parser;
 use config::{Config, Environment};
 use serde::Deserialize;
@@ -31,6 +35,15 @@ pub struct Cli {
     /// Enable external cache (Redis)
     #[arg(long, env = "EXTERNAL_CACHE_ENABLED")]
     pub external_cache_enabled: Option<bool>,
+
+    /// Validate the MCP server config (default: mcp.json) and exit
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        default_missing_value = crate::mcp::config::DEFAULT_MCP_CONFIG_PATH
+    )]
+    pub validate_mcp: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -48,21 +61,56 @@ pub struct AppConfig {
     #[serde(default)]
     pub kreuzberg: Option<KreuzbergConfig>,
     #[serde(default)]
+    pub tika: Option<TikaConfig>,
+    #[serde(default)]
     pub vision: VisionConfig,
     #[serde(default)]
     pub knowledge_bases: KnowledgeBasesConfig,
+    #[serde(default)]
+    pub embedding: EmbeddingConfig,
+    #[serde(default)]
+    pub vector_index: VectorIndexConfig,
+    #[serde(default)]
+    pub sessions: SessionsConfig,
+    #[serde(default)]
+    pub pricing: PricingConfig,
+    #[serde(default)]
+    pub streaming: StreamingConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    pub llm: LlmConfig,
+    #[serde(default)]
+    pub skills: SkillsConfig,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ServerConfig {
     pub port: u16,
     pub host: String,
+    /// Seconds between heartbeat events on run streams
+    pub sse_heartbeat_secs: u64,
+    /// Idle seconds after which run streams send a keepalive comment
+    pub sse_keepalive_secs: u64,
+    /// Seconds each run webhook delivery attempt may take
+    pub webhook_timeout_secs: u64,
+    /// Hosts run webhooks may reach on loopback or private addresses
+    #[serde(default)]
+    pub webhook_allowed_hosts: Vec<String>,
+    /// Seconds a tool call waits for approval before it is denied
+    pub tool_approval_timeout_secs: u64,
+    /// Seconds a run may take, LLM and tool calls included (0: no limit)
+    pub run_timeout_secs: u64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct SecurityConfig {
     pub jwt_required: bool,
     pub jwt_secret: String,
+    /// Hosts the remote MCP servers of agents sent with a request (run or
+    /// import) may use; their stdio servers are always rejected
+    #[serde(default)]
+    pub mcp_allowed_hosts: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -71,6 +119,13 @@ pub struct ResilienceConfig {
     pub timeout_disabled: bool,
     pub requests_per_second: f32,
     pub burst_size: f32,
+    /// Allowed `/v1/embeddings` requests per second, on top of the global limit
+    pub embeddings_requests_per_second: f32,
+    pub embeddings_burst_size: f32,
+    /// Count requests in this Redis instance, so every replica shares the
+    /// budget (in memory when unset or unreachable)
+    #[serde(default)]
+    pub redis_url: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -84,19 +139,42 @@ pub struct PersistenceConfig {
 /// Configuration for file processing and uploads.
 #[derive(Debug, Deserialize, Clone)]
 pub struct FileProcessingConfig {
-    /// Provider to use: "unstructured", "mistral", "kreuzberg" (local), "auto"
+    /// Provider to use: "unstructured", "mistral", "kreuzberg" (local), "tika", "vision", "auto"
     pub provider: String,
-    /// Directory where uploaded files are saved before processing
+    /// Directory where uploaded files are saved before processing; knowledge
+    /// base documents are streamed here and kept until deleted
     pub upload_dir: String,
     /// Maximum number of files per prompt
     pub max_files_per_prompt: usize,
-    /// Maximum file size per file in bytes
+    /// Maximum file size per file in bytes, also the knowledge base upload limit
     pub max_file_size: usize,
-    /// Maximum total upload size for all files in a single prompt
+    /// Maximum total upload size for all files in a single prompt (or a
+    /// knowledge base upload)
     pub max_total_size: usize,
-    /// Allowed MIME types (empty = allow all supported types)
+    /// Allowed MIME types, exact or `type/*` (empty = allow all supported
+    /// types); knowledge base uploads are checked against their sniffed type
     #[serde(default)]
     pub allowed_mime_types: Vec<String>,
+    /// Knowledge base ingestion workers (0 = one per CPU)
+    #[serde(default)]
+    pub ingestion_workers: usize,
+    /// Uploads waiting for an ingestion worker before further ones get 503
+    #[serde(default = "FileProcessingConfig::default_ingestion_queue_depth")]
+    pub ingestion_queue_depth: usize,
+    /// Route files whose magic bytes show no type (text) by their extension;
+    /// off, they go to the default provider as `application/octet-stream`
+    #[serde(default = "FileProcessingConfig::default_mime_fallback_to_extension")]
+    pub mime_fallback_to_extension: bool,
+}
+
+impl FileProcessingConfig {
+    fn default_ingestion_queue_depth() -> usize {
+        crate::uar::rag::ingestion_worker::DEFAULT_MAX_QUEUE_DEPTH
+    }
+
+    fn default_mime_fallback_to_extension() -> bool {
+        true
+    }
 }
 
 impl Default for FileProcessingConfig {
@@ -111,6 +189,9 @@ impl Default for FileProcessingConfig {
             max_file_size: 50 * 1024 * 1024,   // 50MB
             max_total_size: 100 * 1024 * 1024, // 100MB
             allowed_mime_types: Vec::new(),
+            ingestion_workers: 0,
+            ingestion_queue_depth: Self::default_ingestion_queue_depth(),
+            mime_fallback_to_extension: Self::default_mime_fallback_to_extension(),
         }
     }
 }
@@ -234,6 +315,46 @@ impl Default for KreuzbergConfig {
     }
 }
 
+/// Apache Tika server configuration (self-hosted document extraction).
+#[derive(Debug, Deserialize, Clone)]
+pub struct TikaConfig {
+    /// Base URL of the Tika server
+    #[serde(default = "TikaConfig::default_server_url")]
+    pub server_url: String,
+    /// Seconds each request to the server may take
+    #[serde(default = "TikaConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Output format: "text" or "xhtml"
+    #[serde(default = "TikaConfig::default_output_format")]
+    pub output_format: String,
+    /// Also unpack the images embedded in documents
+    #[serde(default)]
+    pub extract_images: bool,
+}
+
+impl TikaConfig {
+    fn default_server_url() -> String {
+        "http://localhost:9998".to_string()
+    }
+    fn default_timeout_secs() -> u64 {
+        60
+    }
+    fn default_output_format() -> String {
+        "text".to_string()
+    }
+}
+
+impl Default for TikaConfig {
+    fn default() -> Self {
+        Self {
+            server_url: Self::default_server_url(),
+            timeout_secs: Self::default_timeout_secs(),
+            output_format: Self::default_output_format(),
+            extract_images: false,
+        }
+    }
+}
+
 /// Vision/Image processing configuration.
 #[derive(Debug, Deserialize, Clone)]
 pub struct VisionConfig {
@@ -259,12 +380,388 @@ impl Default for VisionConfig {
     }
 }
 
+/// Embedding computation configuration.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EmbeddingConfig {
+    /// Maximum embedding computations running at once across the process
+    #[serde(default = "EmbeddingConfig::default_max_concurrent")]
+    pub max_concurrent: usize,
+    /// Idle connections kept open per host by the remote embedders' shared client
+    #[serde(default = "EmbeddingConfig::default_max_idle_connections")]
+    pub max_idle_connections: usize,
+    /// Ping remote embedding APIs at startup; failures make `/readyz` fail
+    #[serde(default)]
+    pub warmup: bool,
+    /// Pad or truncate vectors that don't fit the storage columns instead of
+    /// failing; only meant for the transition to a new embedding model
+    #[serde(default)]
+    pub reconcile_dimensions: bool,
+}
+
+impl EmbeddingConfig {
+    fn default_max_concurrent() -> usize {
+        2
+    }
+
+    fn default_max_idle_connections() -> usize {
+        16
+    }
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: Self::default_max_concurrent(),
+            max_idle_connections: Self::default_max_idle_connections(),
+            warmup: false,
+            reconcile_dimensions: false,
+        }
+    }
+}
+
+/// In-memory approximate nearest neighbor index for chunk search.
+///
+/// Only used with stores that have no vector index of their own (`SurrealDB`);
+/// Postgres searches with pgvector.
+#[derive(Debug, Deserialize, Clone)]
+pub struct VectorIndexConfig {
+    /// Search chunks through an HNSW index instead of scanning every one
+    #[serde(default)]
+    pub hnsw_enabled: bool,
+    /// Candidates considered while inserting a vector; higher builds a
+    /// better graph, slower
+    #[serde(default = "VectorIndexConfig::default_ef_construction")]
+    pub hnsw_ef_construction: usize,
+    /// Candidates considered per search; also the most results one index
+    /// search returns (larger searches scan the store)
+    #[serde(default = "VectorIndexConfig::default_ef_search")]
+    pub hnsw_ef_search: usize,
+    /// Target neighbours per node, which sets how many layers the graph has
+    #[serde(default = "VectorIndexConfig::default_m")]
+    pub hnsw_m: usize,
+    /// File the indexes are saved to at shutdown and loaded from at startup
+    #[serde(default)]
+    pub index_path: Option<String>,
+}
+
+impl VectorIndexConfig {
+    fn default_ef_construction() -> usize {
+        100
+    }
+
+    fn default_ef_search() -> usize {
+        100
+    }
+
+    fn default_m() -> usize {
+        16
+    }
+}
+
+impl Default for VectorIndexConfig {
+    fn default() -> Self {
+        Self {
+            hnsw_enabled: false,
+            hnsw_ef_construction: Self::default_ef_construction(),
+            hnsw_ef_search: Self::default_ef_search(),
+            hnsw_m: Self::default_m(),
+            index_path: None,
+        }
+    }
+}
+
+/// In-memory session lifetime configuration.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SessionsConfig {
+    /// Inactivity after which a session is evicted from memory, in seconds
+    #[serde(default = "SessionsConfig::default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Interval between background eviction sweeps, in seconds
+    #[serde(default = "SessionsConfig::default_sweep_interval_secs")]
+    pub sweep_interval_secs: u64,
+    /// Summarize older messages once a conversation has more than this (0 = never)
+    #[serde(default = "SessionsConfig::default_summary_threshold_messages")]
+    pub summary_threshold_messages: usize,
+    /// Most recent messages kept verbatim when summarizing
+    #[serde(default = "SessionsConfig::default_summary_keep_recent")]
+    pub summary_keep_recent: usize,
+    /// Input tokens all summaries together may use per hour
+    #[serde(default = "SessionsConfig::default_summary_tokens_per_hour")]
+    pub summary_tokens_per_hour: u32,
+    /// Most messages an imported transcript may hold
+    #[serde(default = "SessionsConfig::default_import_max_messages")]
+    pub import_max_messages: usize,
+}
+
+impl SessionsConfig {
+    fn default_ttl_secs() -> u64 {
+        30 * 60
+    }
+
+    fn default_sweep_interval_secs() -> u64 {
+        60
+    }
+
+    fn default_summary_threshold_messages() -> usize {
+        20
+    }
+
+    fn default_summary_keep_recent() -> usize {
+        10
+    }
+
+    fn default_summary_tokens_per_hour() -> u32 {
+        200_000
+    }
+
+    fn default_import_max_messages() -> usize {
+        5000
+    }
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: Self::default_ttl_secs(),
+            sweep_interval_secs: Self::default_sweep_interval_secs(),
+            summary_threshold_messages: Self::default_summary_threshold_messages(),
+            summary_keep_recent: Self::default_summary_keep_recent(),
+            summary_tokens_per_hour: Self::default_summary_tokens_per_hour(),
+            import_max_messages: Self::default_import_max_messages(),
+        }
+    }
+}
+
+/// Per-model token prices used for run cost accounting.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PricingConfig {
+    /// Model name -> price. Runs on models not listed report no cost.
+    #[serde(default)]
+    pub models: HashMap<String, ModelPrice>,
+}
+
+/// Price of a model in USD per million tokens.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    /// USD per 1M prompt tokens
+    pub input_per_million: f64,
+    /// USD per 1M completion tokens
+    pub output_per_million: f64,
+}
+
+/// Optional extras on run event streams.
+#[derive(Debug, Deserialize, Clone)]
+pub struct StreamingConfig {
+    /// Emit running completion token counts while a run generates
+    /// (costs a re-tokenization of the output per update)
+    #[serde(default)]
+    pub partial_usage: bool,
+    /// Minimum milliseconds between partial usage updates
+    #[serde(default = "StreamingConfig::default_partial_usage_interval_ms")]
+    pub partial_usage_interval_ms: u64,
+    /// Emit `RunPhase` events as runs move between retrieval, skill
+    /// matching, generation and tool execution
+    #[serde(default)]
+    pub run_phases: bool,
+}
+
+impl StreamingConfig {
+    fn default_partial_usage_interval_ms() -> u64 {
+        500
+    }
+}
+
+impl Default for StreamingConfig {
+    fn default() -> Self {
+        Self {
+            partial_usage: false,
+            partial_usage_interval_ms: Self::default_partial_usage_interval_ms(),
+            run_phases: false,
+        }
+    }
+}
+
+/// Resilience of calls to the LLM endpoint.
+///
+/// Connection settings (URL, key, model) come from the `LLM_*` environment
+/// variables; see [`load_llm_settings`].
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct LlmConfig {
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    #[serde(default)]
+    pub response_cache: ResponseCacheConfig,
+    /// Model IDs agents and run requests can refer to by alias, per provider
+    #[serde(default)]
+    pub model_aliases: ModelAliases,
+}
+
+/// Stop calling an LLM endpoint that keeps failing.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CircuitBreakerConfig {
+    #[serde(default = "CircuitBreakerConfig::default_enabled")]
+    pub enabled: bool,
+    /// Consecutive failed requests that open the circuit
+    #[serde(default = "CircuitBreakerConfig::default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Seconds requests are rejected before a trial request is let through
+    #[serde(default = "CircuitBreakerConfig::default_reset_timeout_secs")]
+    pub reset_timeout_secs: u64,
+    /// Successful trial requests needed to close the circuit again
+    #[serde(default = "CircuitBreakerConfig::default_half_open_probes")]
+    pub half_open_probes: u32,
+    /// Seconds a provider may take to start responding (0 = no limit)
+    #[serde(default = "CircuitBreakerConfig::default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+}
+
+impl CircuitBreakerConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_failure_threshold() -> u32 {
+        5
+    }
+
+    fn default_reset_timeout_secs() -> u64 {
+        30
+    }
+
+    fn default_half_open_probes() -> u32 {
+        1
+    }
+
+    fn default_request_timeout_secs() -> u64 {
+        60
+    }
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            failure_threshold: Self::default_failure_threshold(),
+            reset_timeout_secs: Self::default_reset_timeout_secs(),
+            half_open_probes: Self::default_half_open_probes(),
+            request_timeout_secs: Self::default_request_timeout_secs(),
+        }
+    }
+}
+
+/// Replay answers to repeated deterministic (temperature 0 or seeded) LLM
+/// requests instead of calling the provider again.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ResponseCacheConfig {
+    #[serde(default = "ResponseCacheConfig::default_enabled")]
+    pub enabled: bool,
+    /// Most responses kept; the least recently used go first
+    #[serde(default = "ResponseCacheConfig::default_max_entries")]
+    pub max_entries: usize,
+    /// Seconds a response is replayed for
+    #[serde(default = "ResponseCacheConfig::default_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl ResponseCacheConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_max_entries() -> usize {
+        1000
+    }
+
+    fn default_ttl_secs() -> u64 {
+        3600
+    }
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            max_entries: Self::default_max_entries(),
+            ttl_secs: Self::default_ttl_secs(),
+        }
+    }
+}
+
+/// Audit trail of knowledge base and document mutations, and of the
+/// verbatim LLM exchange of every run.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuditConfig {
+    /// Record who created, changed or deleted knowledge bases and documents,
+    /// and every run's prompts and responses (needs a persistence backend)
+    #[serde(default = "AuditConfig::default_enabled")]
+    pub enabled: bool,
+}
+
+impl AuditConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+        }
+    }
+}
+
+/// Skills injected into runs.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SkillsConfig {
+    /// Levels of `dependencies` followed from a matched skill; deeper chains
+    /// fail the run
+    #[serde(default = "SkillsConfig::default_max_skill_depth")]
+    pub max_skill_depth: usize,
+    /// Seconds the MCP servers of a skill keep running after the last run
+    /// using them, for the next run to reuse
+    #[serde(default = "SkillsConfig::default_mcp_idle_timeout_secs")]
+    pub mcp_idle_timeout_secs: u64,
+    /// Ask the LLM to pick one of the skills matching an input about equally
+    /// well instead of injecting them all (costs an LLM call per tie)
+    #[serde(default)]
+    pub enable_llm_disambiguation: bool,
+    /// Match scores within this of the best one count as a tie
+    #[serde(default = "SkillsConfig::default_disambiguation_threshold")]
+    pub disambiguation_threshold: f32,
+}
+
+impl SkillsConfig {
+    fn default_max_skill_depth() -> usize {
+        crate::uar::runtime::skills::DEFAULT_MAX_SKILL_DEPTH
+    }
+
+    fn default_mcp_idle_timeout_secs() -> u64 {
+        crate::mcp::cache::McpRegistryCache::DEFAULT_IDLE_TIMEOUT.as_secs()
+    }
+
+    fn default_disambiguation_threshold() -> f32 {
+        crate::uar::runtime::skills::DEFAULT_DISAMBIGUATION_THRESHOLD
+    }
+}
+
+impl Default for SkillsConfig {
+    fn default() -> Self {
+        Self {
+            max_skill_depth: Self::default_max_skill_depth(),
+            mcp_idle_timeout_secs: Self::default_mcp_idle_timeout_secs(),
+            enable_llm_disambiguation: false,
+            disambiguation_threshold: Self::default_disambiguation_threshold(),
+        }
+    }
+}
+
 // =============================================================================
 // KNOWLEDGE BASES CONFIGURATION
 // =============================================================================
 
 /// Top-level configuration for knowledge bases.
-#[derive(Debug, Deserialize, Clone, Default)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct KnowledgeBasesConfig {
     /// Default knowledge base configuration (always exists)
     #[serde(default)]
@@ -272,6 +769,130 @@ pub struct KnowledgeBasesConfig {
     /// Additional named knowledge bases
     #[serde(default)]
     pub named: HashMap<String, KnowledgeBaseConfig>,
+    /// Cache embeddings of identical text (disable for debugging)
+    #[serde(default = "KnowledgeBasesConfig::default_cache_enabled")]
+    pub cache_enabled: bool,
+    /// Maximum number of cached embeddings (LRU eviction beyond this)
+    #[serde(default = "KnowledgeBasesConfig::default_max_cache_entries")]
+    pub max_cache_entries: usize,
+    /// Reranking for knowledge bases that don't configure their own
+    #[serde(default)]
+    pub rerank: Option<crate::uar::domain::knowledge::RerankerConfig>,
+    /// NLP service extracting entities from ingested chunks into knowledge
+    /// graphs (spaCy-compatible `/extract` API); no graphs when unset
+    #[serde(default)]
+    pub nlp_service_url: Option<String>,
+    /// Entity extraction by the LLM, for knowledge bases whose
+    /// `extraction_strategy` is `llm`
+    #[serde(default)]
+    pub llm_extraction: LlmExtractionConfig,
+    /// Limits on the contents of imported knowledge base archives
+    #[serde(default)]
+    pub archive_import: ArchiveImportConfig,
+}
+
+impl KnowledgeBasesConfig {
+    fn default_cache_enabled() -> bool {
+        true
+    }
+
+    fn default_max_cache_entries() -> usize {
+        10_000
+    }
+}
+
+impl Default for KnowledgeBasesConfig {
+    fn default() -> Self {
+        Self {
+            default: None,
+            named: HashMap::new(),
+            cache_enabled: Self::default_cache_enabled(),
+            max_cache_entries: Self::default_max_cache_entries(),
+            rerank: None,
+            nlp_service_url: None,
+            llm_extraction: LlmExtractionConfig::default(),
+            archive_import: ArchiveImportConfig::default(),
+        }
+    }
+}
+
+/// Limits of LLM entity extraction, which runs in the background.
+#[derive(Debug, Deserialize, Clone)]
+pub struct LlmExtractionConfig {
+    /// Most chunks sent in one LLM call
+    #[serde(default = "LlmExtractionConfig::default_batch_size")]
+    pub batch_size: usize,
+    /// Prompt tokens all extraction calls together may use per hour
+    /// (0 = unlimited); calls wait for the budget to refill
+    #[serde(default = "LlmExtractionConfig::default_tokens_per_hour")]
+    pub tokens_per_hour: u32,
+}
+
+impl LlmExtractionConfig {
+    fn default_batch_size() -> usize {
+        5
+    }
+
+    fn default_tokens_per_hour() -> u32 {
+        100_000
+    }
+}
+
+impl Default for LlmExtractionConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: Self::default_batch_size(),
+            tokens_per_hour: Self::default_tokens_per_hour(),
+        }
+    }
+}
+
+/// Limits on what an imported knowledge base archive may unpack to. They
+/// apply to the decompressed contents, so a small archive can't expand
+/// into more than the server is willing to hold.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ArchiveImportConfig {
+    /// Largest `manifest.json`, in bytes
+    #[serde(default = "ArchiveImportConfig::default_max_manifest_bytes")]
+    pub max_manifest_bytes: usize,
+    /// Largest original document file, in bytes
+    #[serde(default = "ArchiveImportConfig::default_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// Longest line of `chunks.jsonl`, in bytes
+    #[serde(default = "ArchiveImportConfig::default_max_chunk_line_bytes")]
+    pub max_chunk_line_bytes: usize,
+    /// Most chunks imported from one archive
+    #[serde(default = "ArchiveImportConfig::default_max_chunks")]
+    pub max_chunks: usize,
+}
+
+impl ArchiveImportConfig {
+    fn default_max_manifest_bytes() -> usize {
+        16 * 1024 * 1024
+    }
+
+    fn default_max_file_bytes() -> u64 {
+        50 * 1024 * 1024
+    }
+
+    fn default_max_chunk_line_bytes() -> usize {
+        1024 * 1024
+    }
+
+    fn default_max_chunks() -> usize {
+        500_000
+    }
+}
+
+impl Default for ArchiveImportConfig {
+    fn default() -> Self {
+        Self {
+            max_manifest_bytes: Self::default_max_manifest_bytes(),
+            max_file_bytes: Self::default_max_file_bytes(),
+            max_chunk_line_bytes: Self::default_max_chunk_line_bytes(),
+            max_chunks: Self::default_max_chunks(),
+        }
+    }
 }
 
 /// Configuration for a single knowledge base.
@@ -291,12 +912,19 @@ pub struct KnowledgeBaseConfig {
     /// Vector dimensions (None = use model default)
     #[serde(default)]
     pub vector_dimensions: Option<usize>,
-    /// File processor: "auto", "unstructured", "mistral", "kreuzberg"
+    /// File processor: "auto", "unstructured", "mistral", "kreuzberg", "tika"
     #[serde(default = "KnowledgeBaseConfig::default_file_processor")]
     pub file_processor: String,
     /// Chunking strategy configuration
     #[serde(default)]
     pub chunking: ChunkingConfig,
+    /// Optional cross-encoder reranking of search results
+    #[serde(default)]
+    pub rerank: Option<crate::uar::domain::knowledge::RerankerConfig>,
+    /// Entity extraction into the knowledge graph: "llm", "spacy", "leiden"
+    /// or "none"
+    #[serde(default)]
+    pub extraction_strategy: Option<String>,
 }
 
 impl KnowledgeBaseConfig {
@@ -366,11 +994,18 @@ impl AppConfig {
         builder = builder
             .set_default("server.port", 3000)?
             .set_default("server.host", "0.0.0.0")?
+            .set_default("server.sse_heartbeat_secs", 15_i64)?
+            .set_default("server.sse_keepalive_secs", 15_i64)?
+            .set_default("server.webhook_timeout_secs", 10_i64)?
+            .set_default("server.tool_approval_timeout_secs", 300_i64)?
+            .set_default("server.run_timeout_secs", 600_i64)?
             .set_default("security.jwt_required", true)?
             .set_default("resilience.rate_limit_enabled", true)?
             .set_default("resilience.timeout_disabled", false)? // Default enabled (timeout_disabled=false)
             .set_default("resilience.requests_per_second", 5.0)?
             .set_default("resilience.burst_size", 10.0)?
+            .set_default("resilience.embeddings_requests_per_second", 2.0)?
+            .set_default("resilience.embeddings_burst_size", 5.0)?
             .set_default("persistence.external_cache_enabled", false)?
             // File processing defaults
             .set_default("file_processing.provider", "auto")?
@@ -384,6 +1019,7 @@ impl AppConfig {
             .set_default("file_processing.max_files_per_prompt", 10_i64)?
             .set_default("file_processing.max_file_size", 52_428_800_i64)?
             .set_default("file_processing.max_total_size", 104_857_600_i64)?
+            .set_default("file_processing.mime_fallback_to_extension", true)?
             // Vision defaults
             .set_default("vision.auto_detect", true)?;
         // 4. Manual CLI Overrides
@@ -446,6 +1082,19 @@ impl AppConfig {
     }
 }
 
+/// Parse the env var `name`, if set and not blank.
+fn env_parsed<T>(name: &str) -> Result<Option<T>, String>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    std::env::var(name)
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.trim().parse().map_err(|e| format!("{name}: {e}")))
+        .transpose()
+}
+
 pub fn load_llm_settings() -> Result<LlmSettings, String> {
     let base_url = std::env::var("LLM_BASE_URL")
         .map_err(|_| "Missing required env var: LLM_BASE_URL".to_string())?;
@@ -473,8 +1122,8 @@ pub fn load_llm_settings() -> Result<LlmSettings, String> {
         _ => LlmProtocol::Auto,
     };
 
-    // Auto-detect provider from base URL
-    let mut provider = Provider::detect_from_url(&base_url);
+    // LLM_PROVIDER names the provider; otherwise it is detected from the URL
+    let mut provider = Provider::resolve(&base_url, env_parsed("LLM_PROVIDER")?);
 
     // Load Azure-specific settings if needed
     let deployment_name = std::env::var("AZURE_DEPLOYMENT_NAME").ok();
@@ -492,11 +1141,68 @@ pub fn load_llm_settings() -> Result<LlmSettings, String> {
         }
     }
 
+    // Bedrock takes the model ID from LLM_MODEL; BEDROCK_REGION overrides the URL's region
+    if let Provider::Bedrock { region, model_id } = &mut provider {
+        model_id.clone_from(&model);
+        if let Ok(bedrock_region) = std::env::var("BEDROCK_REGION")
+            && !bedrock_region.trim().is_empty()
+        {
+            *region = bedrock_region;
+        }
+    }
+
     // Load optional parallel tool calls setting
     let parallel_tool_calls = std::env::var("LLM_PARALLEL_TOOLS")
         .ok()
         .and_then(|s| s.parse().ok());
 
+    // Empty response handling: "error" (default) or "retry"
+    let empty_response = match std::env::var("LLM_EMPTY_RESPONSE")
+        .unwrap_or_default()
+        .to_lowercase()
+        .as_str()
+    {
+        "retry" => EmptyResponsePolicy::Retry,
+        _ => EmptyResponsePolicy::Error,
+    };
+
+    // Content filter categories to report beyond the filtered ones
+    let content_filter_min_severity = std::env::var("LLM_CONTENT_FILTER_MIN_SEVERITY")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.parse::<ContentFilterSeverity>())
+        .transpose()?;
+
+    // Reasoning effort: low, medium, high, or a thinking token budget
+    let reasoning_effort = std::env::var("LLM_REASONING_EFFORT")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.parse::<ReasoningEffort>())
+        .transpose()?;
+    // Sampling parameters; unset ones keep the provider's defaults
+    let generation = GenerationParams {
+        reasoning_effort,
+        temperature: env_parsed("LLM_TEMPERATURE")?,
+        top_p: env_parsed("LLM_TOP_P")?,
+        max_tokens: env_parsed("LLM_MAX_TOKENS")?,
+        presence_penalty: env_parsed("LLM_PRESENCE_PENALTY")?,
+        frequency_penalty: env_parsed("LLM_FREQUENCY_PENALTY")?,
+        stop: std::env::var("LLM_STOP")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+        seed: env_parsed("LLM_SEED")?,
+        run_retries: env_parsed("LLM_RUN_RETRIES")?,
+    };
+    generation
+        .validate(&provider)
+        .map_err(|e| format!("LLM generation settings: {e}"))?;
+
+    // Usage field names the provider uses besides the OpenAI ones
+    let usage_fields = env_parsed::<UsageFields>("LLM_USAGE_FIELDS")?.unwrap_or_default();
+
     Ok(LlmSettings {
         base_url,
         api_key,
@@ -506,5 +1212,9 @@ pub fn load_llm_settings() -> Result<LlmSettings, String> {
         parallel_tool_calls,
         deployment_name,
         api_version,
+        empty_response,
+        content_filter_min_severity,
+        generation,
+        usage_fields,
     })
 }
@@ -28,6 +28,7 @@
 #![allow(clippy::default_trait_access)]
 #![allow(clippy::unused_async)]
 
+pub mod cache;
 pub mod config;
 pub mod llm;
 pub mod mcp;
@@ -37,7 +38,7 @@ pub mod session;
 pub mod uar;
 
 use crate::config::AppConfig;
-use crate::uar::security::rate_limit::AppRateLimiter;
+use crate::uar::security::rate_limit::{AppRateLimiter, RateLimiter};
 
 use llm::orchestrator::Orchestrator;
 use mcp::registry::McpRegistry;
@@ -52,7 +53,6 @@ use uar::runtime::matching::VectorMatcher;
 #[derive(Clone, Debug)]
 pub struct AppState {
     /// MCP server registry for tool discovery and execution.
-    #[allow(dead_code)]
     pub mcp: Arc<McpRegistry>,
     /// LLM orchestrator for chat interactions.
     pub orchestrator: Arc<Orchestrator>,
@@ -67,8 +67,10 @@ pub struct AppState {
     /// Persistence Layer
     /// Persistence Layer
     pub persistence: Option<Arc<dyn PersistenceLayer>>,
-    /// Global Rate Limiter
-    pub rate_limiter: Arc<AppRateLimiter>,
+    /// Global Rate Limiter (in memory or shared through Redis)
+    pub rate_limiter: Arc<dyn RateLimiter>,
+    /// Rate limiter of the OpenAI-compatible embeddings endpoint
+    pub embeddings_rate_limiter: Arc<AppRateLimiter>,
     /// Global Configuration
     pub config: Arc<AppConfig>,
 }
@@ -0,0 +1,148 @@
+//! Provider-independent model aliases.
+//!
+//! Agents and run requests may name a model by an alias such as `fast` or
+//! `smart`; the alias is replaced by the model ID configured for the
+//! provider the run is dispatched to, so the same agent definition works
+//! against `OpenAI`, an Azure deployment or an `OpenRouter` slug.
+
+use super::{LlmSettings, Provider};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Entry used for providers an alias has no model for.
+pub const DEFAULT_ALIAS_PROVIDER: &str = "default";
+
+/// Model IDs of each alias, keyed by alias then provider name
+/// ([`Provider::name`] or [`DEFAULT_ALIAS_PROVIDER`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct ModelAliases(HashMap<String, HashMap<String, String>>);
+
+/// An alias without a model for the run's provider.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("model alias '{alias}' has no model for provider '{provider}'")]
+pub struct UnmappedAliasError {
+    pub alias: String,
+    pub provider: &'static str,
+}
+
+impl ModelAliases {
+    pub fn new(aliases: HashMap<String, HashMap<String, String>>) -> Self {
+        Self(aliases)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Model `alias` stands for on `provider`, or `None` if `alias` is not
+    /// an alias.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `alias` has neither a model for `provider` nor a
+    /// default one.
+    pub fn resolve(
+        &self,
+        alias: &str,
+        provider: &Provider,
+    ) -> Result<Option<&str>, UnmappedAliasError> {
+        let Some(models) = self.0.get(alias) else {
+            return Ok(None);
+        };
+        models
+            .get(provider.name())
+            .or_else(|| models.get(DEFAULT_ALIAS_PROVIDER))
+            .map(|model| Some(model.as_str()))
+            .ok_or_else(|| UnmappedAliasError {
+                alias: alias.to_string(),
+                provider: provider.name(),
+            })
+    }
+
+    /// Replace an alias in `settings.model` with the model of its provider.
+    ///
+    /// Azure deployments and Bedrock model IDs follow the resolved model.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the alias has no model for the provider.
+    pub fn apply(&self, settings: &mut LlmSettings) -> Result<(), UnmappedAliasError> {
+        if let Some(model) = self.resolve(&settings.model, &settings.provider)? {
+            settings.model = model.to_string();
+            if let Provider::AzureOpenAI { deployment_name, .. } = &mut settings.provider {
+                deployment_name.clone_from(&settings.model);
+                settings.deployment_name = Some(settings.model.clone());
+            }
+        }
+        if let Provider::Bedrock { model_id, .. } = &mut settings.provider {
+            model_id.clone_from(&settings.model);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::testing::llm_settings;
+
+    fn aliases() -> ModelAliases {
+        ModelAliases::new(HashMap::from([(
+            "fast".to_string(),
+            HashMap::from([
+                ("openai".to_string(), "gpt-4o-mini".to_string()),
+                ("openrouter".to_string(), "openai/gpt-4o-mini".to_string()),
+                ("azure".to_string(), "mini-prod".to_string()),
+            ]),
+        )]))
+    }
+
+    #[test]
+    fn test_resolve_per_provider() {
+        let aliases = aliases();
+        assert_eq!(aliases.resolve("fast", &Provider::OpenAI), Ok(Some("gpt-4o-mini")));
+        assert_eq!(
+            aliases.resolve("fast", &Provider::OpenRouter),
+            Ok(Some("openai/gpt-4o-mini"))
+        );
+        // Concrete model IDs pass through
+        assert_eq!(aliases.resolve("gpt-4o", &Provider::OpenAI), Ok(None));
+
+        let err = aliases.resolve("fast", &Provider::Groq).unwrap_err();
+        assert_eq!(err.to_string(), "model alias 'fast' has no model for provider 'groq'");
+    }
+
+    #[test]
+    fn test_default_entry() {
+        let mut map = aliases().0;
+        map.get_mut("fast")
+            .unwrap()
+            .insert(DEFAULT_ALIAS_PROVIDER.to_string(), "llama-3.1-8b".to_string());
+        let aliases = ModelAliases::new(map);
+        assert_eq!(aliases.resolve("fast", &Provider::Groq), Ok(Some("llama-3.1-8b")));
+        assert_eq!(aliases.resolve("fast", &Provider::OpenAI), Ok(Some("gpt-4o-mini")));
+    }
+
+    #[test]
+    fn test_apply_updates_azure_deployment() {
+        let mut settings = LlmSettings {
+            provider: Provider::AzureOpenAI {
+                deployment_name: "gpt-4".to_string(),
+                api_version: "2024-08-01-preview".to_string(),
+            },
+            deployment_name: Some("gpt-4".to_string()),
+            ..llm_settings("https://example.openai.azure.com", "fast")
+        };
+        aliases().apply(&mut settings).unwrap();
+
+        assert_eq!(settings.model, "mini-prod");
+        assert_eq!(settings.deployment_name.as_deref(), Some("mini-prod"));
+        assert!(
+            settings
+                .provider
+                .build_chat_url(&settings.base_url, &settings.model)
+                .contains("/deployments/mini-prod/")
+        );
+    }
+}
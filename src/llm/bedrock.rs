@@ -0,0 +1,776 @@
+//! AWS Bedrock driver.
+//!
+//! This module implements the [`LlmDriver`] trait on top of Bedrock's
+//! `InvokeModelWithResponseStream` API. Requests are signed with `SigV4` by
+//! the AWS SDK, which takes credentials from the default chain (environment
+//! variables, shared profile or SSO, instance profile).
+//!
+//! Bedrock passes each model's native request and chunk format through, so
+//! the driver dispatches on the model family:
+//!
+//! - Anthropic Claude: Messages API, with tools and extended thinking
+//! - Amazon Titan and Meta Llama: text generation from a flattened prompt;
+//!   requests with tools are refused
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_bedrockruntime::{
+    Client, error::DisplayErrorContext, primitives::Blob, types::ResponseStream,
+};
+use futures::Stream;
+use serde_json::{Value, json};
+use tokio::sync::OnceCell;
+
+use crate::normalized::NormalizedEvent;
+
+use super::{LlmDriver, LlmRequest, LlmSettings, Provider};
+
+/// `anthropic_version` Bedrock expects in Claude request bodies.
+const ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
+/// Answer token limit; Bedrock requires one for Claude.
+const MAX_TOKENS: u32 = 4096;
+/// Largest `max_gen_len` Llama models accept.
+const LLAMA_MAX_GEN_LEN: u32 = 2048;
+
+/// Request and chunk format of a Bedrock model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelFamily {
+    /// `anthropic.claude-*`
+    Anthropic,
+    /// `amazon.titan-text-*`
+    Titan,
+    /// `meta.llama*`
+    Llama,
+}
+
+impl ModelFamily {
+    /// Family of `model_id`, also behind cross-region prefixes like `us.`.
+    #[must_use]
+    pub fn detect(model_id: &str) -> Option<Self> {
+        let lower = model_id.to_lowercase();
+        if lower.contains("anthropic.") {
+            Some(Self::Anthropic)
+        } else if lower.contains("amazon.titan-text") {
+            Some(Self::Titan)
+        } else if lower.contains("meta.llama") {
+            Some(Self::Llama)
+        } else {
+            None
+        }
+    }
+}
+
+/// A request feature a Bedrock model family doesn't support.
+#[derive(Debug, thiserror::Error)]
+#[error("Bedrock {family:?} models do not support {feature}")]
+pub struct UnsupportedFeature {
+    pub family: ModelFamily,
+    pub feature: &'static str,
+}
+
+/// Driver for AWS Bedrock.
+///
+/// The SDK client is built on the first request, as loading the AWS config
+/// is async.
+pub struct BedrockDriver {
+    client: OnceCell<Client>,
+    settings: LlmSettings,
+}
+
+#[allow(clippy::missing_fields_in_debug)]
+impl std::fmt::Debug for BedrockDriver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BedrockDriver")
+            .field("settings", &self.settings)
+            .finish()
+    }
+}
+
+impl BedrockDriver {
+    /// Create a new Bedrock driver with the given settings.
+    #[must_use]
+    pub fn new(settings: LlmSettings) -> Self {
+        Self {
+            client: OnceCell::new(),
+            settings,
+        }
+    }
+
+    /// Configured region, if any.
+    fn region(&self) -> Option<&str> {
+        match &self.settings.provider {
+            Provider::Bedrock { region, .. } if !region.is_empty() => Some(region),
+            _ => None,
+        }
+    }
+
+    /// Bedrock model ID, falling back to the configured model.
+    fn model_id(&self) -> &str {
+        match &self.settings.provider {
+            Provider::Bedrock { model_id, .. } if !model_id.is_empty() => model_id,
+            _ => &self.settings.model,
+        }
+    }
+
+    async fn client(&self) -> &Client {
+        self.client
+            .get_or_init(|| async {
+                let mut loader = aws_config::defaults(BehaviorVersion::latest());
+                if let Some(region) = self.region() {
+                    loader = loader.region(Region::new(region.to_string()));
+                }
+                Client::new(&loader.load().await)
+            })
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmDriver for BedrockDriver {
+    async fn stream(
+        &self,
+        req: LlmRequest,
+    ) -> anyhow::Result<std::pin::Pin<Box<dyn Stream<Item = anyhow::Result<NormalizedEvent>> + Send>>>
+    {
+        let model_id = self.model_id().to_string();
+        let family = ModelFamily::detect(&model_id).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unsupported Bedrock model '{model_id}' (expected Anthropic Claude, Amazon Titan or Meta Llama)"
+            )
+        })?;
+
+        if let Some(format) = &req.response_format {
+            format.ensure_supported(&self.settings.provider)?;
+        }
+
+        tracing::info!(
+            model_id = %model_id,
+            family = ?family,
+            region = ?self.region(),
+            message_count = req.messages.len(),
+            tool_count = req.tools.len(),
+            "Bedrock: Starting stream request"
+        );
+
+        let body = request_body(family, &req, &self.settings)?;
+        tracing::debug!(
+            request_body = %serde_json::to_string_pretty(&body).unwrap_or_default(),
+            "Bedrock: Full request body"
+        );
+
+        let output = self
+            .client()
+            .await
+            .invoke_model_with_response_stream()
+            .model_id(&model_id)
+            .content_type("application/json")
+            .accept("application/json")
+            .body(Blob::new(serde_json::to_vec(&body)?))
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("Bedrock API error: {}", DisplayErrorContext(&e)))?;
+
+        let mut chunks = output.body;
+        let out = async_stream::try_stream! {
+            let mut decoder = ChunkDecoder::new(family);
+            while let Some(event) = chunks
+                .recv()
+                .await
+                .map_err(|e| anyhow::anyhow!("Bedrock stream error: {}", DisplayErrorContext(&e)))?
+            {
+                let ResponseStream::Chunk(part) = event else {
+                    continue;
+                };
+                let Some(bytes) = part.bytes() else {
+                    continue;
+                };
+                for event in decoder.decode(bytes.as_ref())? {
+                    yield event;
+                }
+            }
+        };
+
+        Ok(Box::pin(out))
+    }
+}
+
+/// Native request body of `family` for `req`.
+fn request_body(
+    family: ModelFamily,
+    req: &LlmRequest,
+    settings: &LlmSettings,
+) -> anyhow::Result<Value> {
+    if family != ModelFamily::Anthropic && !req.tools.is_empty() {
+        return Err(UnsupportedFeature {
+            family,
+            feature: "tools",
+        }
+        .into());
+    }
+
+    let body = match family {
+        ModelFamily::Anthropic => {
+            let (system, messages) = claude_messages(&req.messages);
+            let mut body = json!({
+                "anthropic_version": ANTHROPIC_VERSION,
+                "max_tokens": MAX_TOKENS,
+                "messages": messages,
+            });
+            if !system.is_empty() {
+                body["system"] = json!(system);
+            }
+            if !req.tools.is_empty() {
+                if let Some(choice) = &req.tool_choice {
+                    let choice = choice.effective(&settings.provider, &req.tools);
+                    body["tool_choice"] = choice.to_anthropic();
+                }
+                body["tools"] = req.tools.iter().map(claude_tool).collect();
+            }
+            settings
+                .generation
+                .apply_anthropic(&settings.provider, &mut body)?;
+            body
+        }
+        ModelFamily::Titan => {
+            let mut config = json!({ "maxTokenCount": MAX_TOKENS });
+            settings
+                .generation
+                .apply_titan(&settings.provider, &mut config)?;
+            json!({
+                "inputText": titan_prompt(&req.messages),
+                "textGenerationConfig": config,
+            })
+        }
+        ModelFamily::Llama => {
+            let mut body = json!({
+                "prompt": llama_prompt(&req.messages),
+                "max_gen_len": LLAMA_MAX_GEN_LEN,
+            });
+            settings
+                .generation
+                .apply_llama(&settings.provider, &mut body)?;
+            body
+        }
+    };
+    Ok(body)
+}
+
+/// Split Chat Completions messages into Claude's system prompt and turns.
+fn claude_messages(messages: &[Value]) -> (String, Vec<Value>) {
+    let mut system = Vec::new();
+    let mut turns: Vec<Value> = Vec::new();
+
+    for message in messages {
+        let (role, blocks) = match message["role"].as_str().unwrap_or("user") {
+            "system" => {
+                system.push(text_of(&message["content"]));
+                continue;
+            }
+            "tool" => {
+                let result = json!({
+                    "type": "tool_result",
+                    "tool_use_id": message["tool_call_id"],
+                    "content": text_of(&message["content"]),
+                });
+                ("user", vec![result])
+            }
+            "assistant" => {
+                let mut blocks = claude_blocks(&message["content"]);
+                for call in message["tool_calls"].as_array().into_iter().flatten() {
+                    let input = call["function"]["arguments"]
+                        .as_str()
+                        .and_then(|args| serde_json::from_str::<Value>(args).ok())
+                        .unwrap_or_else(|| json!({}));
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call["id"],
+                        "name": call["function"]["name"],
+                        "input": input,
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            _ => ("user", claude_blocks(&message["content"])),
+        };
+        if blocks.is_empty() {
+            continue;
+        }
+
+        // Claude wants alternating roles, e.g. one turn for parallel tool results
+        match turns.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(content) = last["content"].as_array_mut() {
+                    content.extend(blocks);
+                }
+            }
+            _ => turns.push(json!({ "role": role, "content": blocks })),
+        }
+    }
+
+    (system.join("\n\n"), turns)
+}
+
+/// Claude content blocks for a message's text or multimodal parts.
+fn claude_blocks(content: &Value) -> Vec<Value> {
+    match content {
+        Value::String(text) if !text.is_empty() => vec![json!({ "type": "text", "text": text })],
+        Value::Array(parts) => parts.iter().filter_map(claude_part).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn claude_part(part: &Value) -> Option<Value> {
+    match part["type"].as_str()? {
+        "text" => Some(json!({ "type": "text", "text": part["text"] })),
+        "image_url" => {
+            let url = part["image_url"]["url"].as_str()?;
+            // Bedrock only takes inline images
+            let Some((media_type, data)) = url
+                .strip_prefix("data:")
+                .and_then(|rest| rest.split_once(";base64,"))
+            else {
+                tracing::warn!("Bedrock only accepts base64 images, dropping image URL");
+                return None;
+            };
+            Some(json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": media_type, "data": data },
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Claude tool definition from an `OpenAI` function tool.
+fn claude_tool(tool: &Value) -> Value {
+    let function = &tool["function"];
+    let schema = match &function["parameters"] {
+        Value::Null => json!({ "type": "object", "properties": {} }),
+        schema => schema.clone(),
+    };
+    json!({
+        "name": function["name"],
+        "description": function["description"].as_str().unwrap_or_default(),
+        "input_schema": schema,
+    })
+}
+
+/// Text of a message, joining the text parts of multimodal content.
+fn text_of(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Titan's `User:`/`Bot:` transcript, ending on the bot's turn.
+fn titan_prompt(messages: &[Value]) -> String {
+    let mut prompt = String::new();
+    for message in messages {
+        let text = text_of(&message["content"]);
+        let _ = match message["role"].as_str() {
+            Some("system") => writeln!(prompt, "{text}\n"),
+            Some("assistant") => writeln!(prompt, "Bot: {text}"),
+            _ => writeln!(prompt, "User: {text}"),
+        };
+    }
+    prompt.push_str("Bot:");
+    prompt
+}
+
+/// Llama 3 chat template, ending on the assistant's header.
+fn llama_prompt(messages: &[Value]) -> String {
+    let mut prompt = String::from("<|begin_of_text|>");
+    for message in messages {
+        let role = match message["role"].as_str() {
+            Some("system") => "system",
+            Some("assistant") => "assistant",
+            Some("tool") => "ipython",
+            _ => "user",
+        };
+        let _ = write!(
+            prompt,
+            "<|start_header_id|>{role}<|end_header_id|>\n\n{}<|eot_id|>",
+            text_of(&message["content"])
+        );
+    }
+    prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+    prompt
+}
+
+/// Accumulated state for a streaming Claude `tool_use` block.
+#[derive(Debug)]
+struct ToolAccum {
+    call_index: usize,
+    id: String,
+    name: String,
+    args: String,
+}
+
+/// Turns a model family's response chunks into [`NormalizedEvent`]s.
+#[derive(Debug)]
+struct ChunkDecoder {
+    family: ModelFamily,
+    /// Claude `tool_use` blocks by content block index
+    tools: BTreeMap<usize, ToolAccum>,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
+impl ChunkDecoder {
+    fn new(family: ModelFamily) -> Self {
+        Self {
+            family,
+            tools: BTreeMap::new(),
+            prompt_tokens: 0,
+            completion_tokens: 0,
+        }
+    }
+
+    /// Events for one chunk's JSON payload.
+    fn decode(&mut self, chunk: &[u8]) -> anyhow::Result<Vec<NormalizedEvent>> {
+        let v: Value = serde_json::from_slice(chunk)?;
+        let mut events = Vec::new();
+        let done = match self.family {
+            ModelFamily::Anthropic => self.decode_claude(&v, &mut events),
+            ModelFamily::Titan => {
+                self.decode_text(&v, &mut events, "outputText", "completionReason")
+            }
+            ModelFamily::Llama => self.decode_text(&v, &mut events, "generation", "stop_reason"),
+        };
+
+        // Every family closes with Bedrock's own token counts
+        let metrics = &v["amazon-bedrock-invocationMetrics"];
+        if let Some(tokens) = token_count(&metrics["inputTokenCount"]) {
+            self.prompt_tokens = tokens;
+        }
+        if let Some(tokens) = token_count(&metrics["outputTokenCount"]) {
+            self.completion_tokens = tokens;
+        }
+
+        if done {
+            events.push(NormalizedEvent::Usage {
+                prompt_tokens: self.prompt_tokens,
+                completion_tokens: self.completion_tokens,
+                total_tokens: self.prompt_tokens + self.completion_tokens,
+            });
+            events.push(NormalizedEvent::Done);
+        }
+        Ok(events)
+    }
+
+    /// Claude Messages stream events; `true` on `message_stop`.
+    fn decode_claude(&mut self, v: &Value, events: &mut Vec<NormalizedEvent>) -> bool {
+        #[allow(clippy::cast_possible_truncation)]
+        let block_index = v["index"].as_u64().unwrap_or(0) as usize;
+
+        match v["type"].as_str().unwrap_or_default() {
+            "message_start" => {
+                if let Some(tokens) = token_count(&v["message"]["usage"]["input_tokens"]) {
+                    self.prompt_tokens = tokens;
+                }
+            }
+            "content_block_start" if v["content_block"]["type"] == "tool_use" => {
+                let block = &v["content_block"];
+                let tool = ToolAccum {
+                    call_index: self.tools.len(),
+                    id: block["id"].as_str().unwrap_or_default().to_string(),
+                    name: block["name"].as_str().unwrap_or_default().to_string(),
+                    args: String::new(),
+                };
+                events.push(NormalizedEvent::ToolCallDelta {
+                    call_index: tool.call_index,
+                    id: Some(tool.id.clone()),
+                    name: Some(tool.name.clone()),
+                    arguments_delta: None,
+                });
+                self.tools.insert(block_index, tool);
+            }
+            "content_block_delta" => {
+                let delta = &v["delta"];
+                match delta["type"].as_str().unwrap_or_default() {
+                    "text_delta" => push_text(events, &delta["text"], |text| {
+                        NormalizedEvent::MessageDelta { text }
+                    }),
+                    "thinking_delta" => push_text(events, &delta["thinking"], |text| {
+                        NormalizedEvent::ThinkingDelta { text }
+                    }),
+                    "input_json_delta" => {
+                        if let (Some(tool), Some(partial)) = (
+                            self.tools.get_mut(&block_index),
+                            delta["partial_json"].as_str(),
+                        ) {
+                            tool.args.push_str(partial);
+                            events.push(NormalizedEvent::ToolCallDelta {
+                                call_index: tool.call_index,
+                                id: None,
+                                name: None,
+                                arguments_delta: Some(partial.to_string()),
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            "message_delta" => {
+                if let Some(tokens) = token_count(&v["usage"]["output_tokens"]) {
+                    self.completion_tokens = tokens;
+                }
+                if v["delta"]["stop_reason"] == "tool_use" {
+                    for tool in std::mem::take(&mut self.tools).into_values() {
+                        let arguments_json = if tool.args.is_empty() {
+                            "{}".to_string()
+                        } else {
+                            tool.args
+                        };
+                        events.push(NormalizedEvent::ToolCallComplete {
+                            call_index: tool.call_index,
+                            id: tool.id,
+                            name: tool.name,
+                            arguments_json,
+                        });
+                    }
+                }
+            }
+            "message_stop" => return true,
+            _ => {}
+        }
+        false
+    }
+
+    /// Titan and Llama text chunks; `true` once `stop_field` is set.
+    fn decode_text(
+        &mut self,
+        v: &Value,
+        events: &mut Vec<NormalizedEvent>,
+        text_field: &str,
+        stop_field: &str,
+    ) -> bool {
+        push_text(events, &v[text_field], |text| NormalizedEvent::MessageDelta { text });
+        // Titan names, then Llama names
+        let tokens = |fields: [&str; 2]| fields.into_iter().find_map(|f| token_count(&v[f]));
+        if let Some(tokens) = tokens(["inputTextTokenCount", "prompt_token_count"]) {
+            self.prompt_tokens = tokens;
+        }
+        if let Some(tokens) = tokens(["totalOutputTextTokenCount", "generation_token_count"]) {
+            self.completion_tokens = tokens;
+        }
+        !v[stop_field].is_null()
+    }
+}
+
+fn push_text(
+    events: &mut Vec<NormalizedEvent>,
+    text: &Value,
+    event: impl FnOnce(String) -> NormalizedEvent,
+) {
+    if let Some(text) = text.as_str()
+        && !text.is_empty()
+    {
+        events.push(event(text.to_string()));
+    }
+}
+
+fn token_count(value: &Value) -> Option<u32> {
+    value.as_u64().and_then(|n| u32::try_from(n).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{LlmProtocol, ReasoningEffort};
+    use crate::uar::testing::llm_settings;
+
+    fn settings(model: &str) -> LlmSettings {
+        LlmSettings {
+            protocol: LlmProtocol::Auto,
+            provider: Provider::Bedrock {
+                region: "us-east-1".to_string(),
+                model_id: model.to_string(),
+            },
+            ..llm_settings("https://bedrock-runtime.us-east-1.amazonaws.com", model)
+        }
+    }
+
+    fn decode_all(family: ModelFamily, chunks: &[Value]) -> Vec<NormalizedEvent> {
+        let mut decoder = ChunkDecoder::new(family);
+        chunks
+            .iter()
+            .flat_map(|chunk| decoder.decode(chunk.to_string().as_bytes()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_model_family_detection() {
+        assert_eq!(
+            ModelFamily::detect("us.anthropic.claude-3-5-sonnet-20240620-v1:0"),
+            Some(ModelFamily::Anthropic)
+        );
+        assert_eq!(
+            ModelFamily::detect("amazon.titan-text-lite-v1"),
+            Some(ModelFamily::Titan)
+        );
+        assert_eq!(
+            ModelFamily::detect("meta.llama3-8b-instruct-v1:0"),
+            Some(ModelFamily::Llama)
+        );
+        assert_eq!(ModelFamily::detect("cohere.command-r-v1:0"), None);
+    }
+
+    #[test]
+    fn test_claude_request_body() {
+        let mut settings = settings("anthropic.claude-3-5-sonnet-20240620-v1:0");
+        settings.generation.reasoning_effort = Some(ReasoningEffort::Budget(2048));
+        let req = LlmRequest {
+            messages: vec![
+                json!({ "role": "system", "content": "Be brief." }),
+                json!({ "role": "user", "content": "Weather in Paris?" }),
+                json!({
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [
+                        { "id": "a", "type": "function",
+                          "function": { "name": "weather", "arguments": "{\"city\":\"Paris\"}" } },
+                        { "id": "b", "type": "function",
+                          "function": { "name": "time", "arguments": "{}" } },
+                    ],
+                }),
+                json!({ "role": "tool", "tool_call_id": "a", "content": "Sunny" }),
+                json!({ "role": "tool", "tool_call_id": "b", "content": "Noon" }),
+            ],
+            tools: vec![json!({
+                "type": "function",
+                "function": { "name": "weather", "parameters": { "type": "object" } },
+            })],
+            response_format: None,
+            tool_choice: None,
+        };
+
+        let body = request_body(ModelFamily::Anthropic, &req, &settings).unwrap();
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["max_tokens"], MAX_TOKENS + 2048);
+        assert_eq!(body["thinking"]["budget_tokens"], 2048);
+        assert_eq!(body["tools"][0]["input_schema"], json!({ "type": "object" }));
+
+        let turns = body["messages"].as_array().unwrap();
+        assert_eq!(turns.len(), 3);
+        assert_eq!(turns[1]["content"][0]["input"], json!({ "city": "Paris" }));
+        // Both tool results share one user turn
+        assert_eq!(turns[2]["role"], "user");
+        assert_eq!(turns[2]["content"][1]["tool_use_id"], "b");
+    }
+
+    #[test]
+    fn test_titan_and_llama_request_bodies() {
+        let req = || LlmRequest {
+            messages: vec![json!({ "role": "user", "content": "Hi" })],
+            tools: vec![],
+            response_format: None,
+            tool_choice: None,
+        };
+        let mut titan = settings("amazon.titan-text-lite-v1");
+        titan.generation.temperature = Some(0.2);
+        titan.generation.max_tokens = Some(300);
+        titan.generation.stop = vec!["User:".to_string()];
+        titan.generation.seed = Some(7);
+
+        let body = request_body(ModelFamily::Titan, &req(), &titan).unwrap();
+        assert_eq!(
+            body["textGenerationConfig"],
+            json!({ "maxTokenCount": 300, "temperature": 0.2, "stopSequences": ["User:"] })
+        );
+
+        let mut llama = settings("meta.llama3-8b-instruct-v1:0");
+        llama.generation.top_p = Some(0.9);
+        llama.generation.max_tokens = Some(10_000);
+        let body = request_body(ModelFamily::Llama, &req(), &llama).unwrap();
+        assert_eq!(body["top_p"], 0.9);
+        assert_eq!(body["max_gen_len"], LLAMA_MAX_GEN_LEN);
+
+        // Neither takes tools
+        let with_tools = LlmRequest {
+            tools: vec![json!({ "type": "function", "function": { "name": "weather" } })],
+            ..req()
+        };
+        let err = request_body(ModelFamily::Llama, &with_tools, &llama).unwrap_err();
+        assert!(err.downcast_ref::<UnsupportedFeature>().is_some(), "{err}");
+    }
+
+    #[test]
+    fn test_claude_stream_maps_to_normalized_events() {
+        let events = decode_all(
+            ModelFamily::Anthropic,
+            &[
+                json!({ "type": "message_start", "message": { "usage": { "input_tokens": 12 } } }),
+                json!({ "type": "content_block_start", "index": 0,
+                        "content_block": { "type": "thinking", "thinking": "" } }),
+                json!({ "type": "content_block_delta", "index": 0,
+                        "delta": { "type": "thinking_delta", "thinking": "Hmm" } }),
+                json!({ "type": "content_block_start", "index": 1,
+                        "content_block": { "type": "tool_use", "id": "t1", "name": "weather" } }),
+                json!({ "type": "content_block_delta", "index": 1,
+                        "delta": { "type": "input_json_delta", "partial_json": "{\"city\":" } }),
+                json!({ "type": "content_block_delta", "index": 1,
+                        "delta": { "type": "input_json_delta", "partial_json": "\"Paris\"}" } }),
+                json!({ "type": "message_delta", "delta": { "stop_reason": "tool_use" },
+                        "usage": { "output_tokens": 30 } }),
+                json!({ "type": "message_stop" }),
+            ],
+        );
+
+        assert_eq!(events[0], NormalizedEvent::ThinkingDelta { text: "Hmm".to_string() });
+        assert!(events.contains(&NormalizedEvent::ToolCallComplete {
+            call_index: 0,
+            id: "t1".to_string(),
+            name: "weather".to_string(),
+            arguments_json: "{\"city\":\"Paris\"}".to_string(),
+        }));
+        assert_eq!(
+            events[events.len() - 2..],
+            [
+                NormalizedEvent::Usage {
+                    prompt_tokens: 12,
+                    completion_tokens: 30,
+                    total_tokens: 42,
+                },
+                NormalizedEvent::Done,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_titan_stream_maps_to_message_deltas() {
+        let events = decode_all(
+            ModelFamily::Titan,
+            &[
+                json!({ "outputText": "Hello", "index": 0, "inputTextTokenCount": 5,
+                        "totalOutputTextTokenCount": 1, "completionReason": null }),
+                json!({ "outputText": " there", "index": 0, "totalOutputTextTokenCount": 2,
+                        "completionReason": "FINISH",
+                        "amazon-bedrock-invocationMetrics": {
+                            "inputTokenCount": 5, "outputTokenCount": 2 } }),
+            ],
+        );
+
+        assert_eq!(
+            events,
+            [
+                NormalizedEvent::MessageDelta { text: "Hello".to_string() },
+                NormalizedEvent::MessageDelta { text: " there".to_string() },
+                NormalizedEvent::Usage {
+                    prompt_tokens: 5,
+                    completion_tokens: 2,
+                    total_tokens: 7,
+                },
+                NormalizedEvent::Done,
+            ]
+        );
+    }
+}
@@ -9,7 +9,9 @@ use futures::{Stream, StreamExt};
 
 use crate::normalized::NormalizedEvent;
 
-use super::{LlmDriver, LlmRequest, LlmSettings};
+use super::{
+    LlmDriver, LlmRequest, LlmSettings, content_filter::frame_content_filter, usage::frame_usage,
+};
 
 /// Accumulated state for a streaming tool call.
 #[derive(Default)]
@@ -87,6 +89,21 @@ impl LlmDriver for ChatCompletionsDriver {
             }
         });
 
+        if let Some(format) = &req.response_format {
+            format.ensure_supported(&self.settings.provider)?;
+            body["response_format"] = format.to_chat_completions();
+        }
+
+        // tool_choice without tools is rejected by the API
+        if let (Some(choice), Some(tools)) = (&req.tool_choice, body["tools"].as_array()) {
+            let choice = choice.effective(&self.settings.provider, tools);
+            body["tool_choice"] = choice.to_chat_completions();
+        }
+
+        self.settings
+            .generation
+            .apply_chat_completions(&self.settings.provider, &mut body)?;
+
         // Add parallel_tool_calls if specified and supported
         // Note: GPT-5.x models don't support parallel_tool_calls parameter
         let is_gpt5_model = self.settings.model.starts_with("gpt-5");
@@ -184,12 +201,16 @@ impl LlmDriver for ChatCompletionsDriver {
         }
 
         let byte_stream = resp.bytes_stream();
+        let min_filter_severity = self.settings.content_filter_min_severity;
+        let usage_fields = self.settings.usage_fields.clone();
 
         tracing::debug!("Starting to process response stream");
 
         let out = async_stream::try_stream! {
             let mut buf = Vec::<u8>::new();
             let mut tool_accum: BTreeMap<usize, ToolAccum> = BTreeMap::new();
+            // Azure repeats filter results on every chunk; report changes only
+            let mut last_filter: Option<NormalizedEvent> = None;
             let mut chunk_count = 0;
             let mut event_count = 0;
 
@@ -236,32 +257,19 @@ impl LlmDriver for ChatCompletionsDriver {
                         let v: serde_json::Value = serde_json::from_str(data)?;
 
                         // Check for usage information (sent in final chunk)
-                        if let Some(usage) = v.get("usage")
-                            && let (Some(prompt), Some(completion), Some(total)) = (
-                                usage
-                                    .get("prompt_tokens")
-                                    .and_then(serde_json::Value::as_u64),
-                                usage
-                                    .get("completion_tokens")
-                                    .and_then(serde_json::Value::as_u64),
-                                usage
-                                    .get("total_tokens")
-                                    .and_then(serde_json::Value::as_u64),
-                            )
+                        if let Some(usage) = frame_usage(&v, &usage_fields) {
+                            event_count += 1;
+                            tracing::info!(usage = ?usage, "Received usage information from API");
+                            yield usage;
+                        }
+
+                        if let Some(filter) = frame_content_filter(&v, min_filter_severity)
+                            && last_filter.as_ref() != Some(&filter)
                         {
                             event_count += 1;
-                            tracing::info!(
-                                prompt_tokens = prompt,
-                                completion_tokens = completion,
-                                total_tokens = total,
-                                "Received usage information from API"
-                            );
-                            #[allow(clippy::cast_possible_truncation)]
-                            yield NormalizedEvent::Usage {
-                                prompt_tokens: prompt as u32,
-                                completion_tokens: completion as u32,
-                                total_tokens: total as u32,
-                            };
+                            tracing::info!(filter = ?filter, "Provider content filter flagged the request");
+                            last_filter = Some(filter.clone());
+                            yield filter;
                         }
 
                         let choice = &v["choices"][0];
@@ -279,6 +287,13 @@ impl LlmDriver for ChatCompletionsDriver {
                                 yield NormalizedEvent::MessageDelta { text: s.to_string() };
                             }
 
+                        // Refusals replace the content
+                        if let Some(s) = delta.get("refusal").and_then(|x| x.as_str())
+                            && !s.is_empty() {
+                                event_count += 1;
+                                yield NormalizedEvent::RefusalDelta { text: s.to_string() };
+                            }
+
                         // Tool calls streaming deltas
                         if let Some(arr) = delta.get("tool_calls").and_then(|x| x.as_array()) {
                             for tc in arr {
@@ -330,6 +345,17 @@ impl LlmDriver for ChatCompletionsDriver {
                                 "Received finish_reason from API"
                             );
 
+                            // Filtered without per-category results (non-Azure providers)
+                            if fr == "content_filter" && last_filter.is_none() {
+                                event_count += 1;
+                                let filter = NormalizedEvent::ContentFilter {
+                                    categories: Vec::new(),
+                                    severity: None,
+                                };
+                                last_filter = Some(filter.clone());
+                                yield filter;
+                            }
+
                             if fr == "tool_calls" {
                                 tracing::info!(
                                     tool_count = tool_accum.len(),
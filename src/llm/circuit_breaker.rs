@@ -0,0 +1,503 @@
+//! Fail fast while an LLM endpoint is down.
+//!
+//! After `failure_threshold` consecutive failed requests the circuit opens
+//! and requests are rejected without a network call for `reset_timeout`.
+//! Then trial requests are let through one at a time: `half_open_probes`
+//! successes close the circuit, a failure opens it again. A request that
+//! doesn't start streaming within `request_timeout` counts as a failure.
+//!
+//! Each provider endpoint gets its own breaker from a
+//! [`CircuitBreakerRegistry`], so one dead provider doesn't fail runs on
+//! the others.
+
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use futures::Stream;
+use serde::Serialize;
+
+use super::{LlmDriver, LlmRequest, LlmSettings};
+use crate::config::CircuitBreakerConfig;
+use crate::normalized::NormalizedEvent;
+
+/// Gauge of the circuit state per provider: 0 closed, 1 half-open, 2 open.
+pub const CIRCUIT_STATE_METRIC: &str = "llm_circuit_state";
+/// Error code of runs rejected by an open circuit.
+pub const CIRCUIT_OPEN_CODE: &str = "CIRCUIT_OPEN";
+/// Error code of runs whose provider didn't respond in time.
+pub const PROVIDER_TIMEOUT_CODE: &str = "PROVIDER_TIMEOUT";
+
+/// State of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Requests are rejected until `until`
+    Open { until: Instant },
+    /// One trial request decides whether to close or re-open
+    HalfOpen,
+}
+
+impl CircuitState {
+    fn gauge_value(self) -> f64 {
+        match self {
+            Self::Closed => 0.0,
+            Self::HalfOpen => 1.0,
+            Self::Open { .. } => 2.0,
+        }
+    }
+}
+
+/// A request was rejected because the circuit is open.
+#[derive(Debug, thiserror::Error)]
+#[error("Circuit open: LLM unavailable")]
+pub struct CircuitOpenError;
+
+/// The provider didn't start responding within the request timeout.
+#[derive(Debug, thiserror::Error)]
+#[error("LLM provider did not respond within {}s", .0.as_secs())]
+pub struct ProviderTimeoutError(pub Duration);
+
+/// Error code for a failed driver request, if it is a breaker error.
+pub fn error_code(err: &anyhow::Error) -> Option<&'static str> {
+    if err.is::<CircuitOpenError>() {
+        Some(CIRCUIT_OPEN_CODE)
+    } else if err.is::<ProviderTimeoutError>() {
+        Some(PROVIDER_TIMEOUT_CODE)
+    } else {
+        None
+    }
+}
+
+/// Point-in-time view of a breaker, as reported by the health endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CircuitSnapshot {
+    /// `closed`, `open` or `half_open`
+    pub state: &'static str,
+    pub consecutive_failures: u32,
+    /// Seconds until an open circuit admits a trial request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_in_secs: Option<u64>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    /// When the current half-open trial request was let through
+    trial_started: Option<Instant>,
+    /// Successful trials since the circuit went half-open
+    probe_successes: u32,
+}
+
+/// Consecutive-failure circuit breaker, shared by every driver it wraps.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    /// Endpoint the breaker guards, for logs and metrics
+    provider: String,
+    failure_threshold: u32,
+    reset_timeout: Duration,
+    half_open_probes: u32,
+    request_timeout: Option<Duration>,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, reset_timeout: Duration) -> Self {
+        Self {
+            provider: "llm".to_string(),
+            failure_threshold: failure_threshold.max(1),
+            reset_timeout,
+            half_open_probes: 1,
+            request_timeout: None,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                trial_started: None,
+                probe_successes: 0,
+            }),
+        }
+    }
+
+    pub fn from_config(config: &CircuitBreakerConfig) -> Self {
+        Self::new(
+            config.failure_threshold,
+            Duration::from_secs(config.reset_timeout_secs),
+        )
+        .with_half_open_probes(config.half_open_probes)
+        .with_request_timeout(
+            (config.request_timeout_secs > 0)
+                .then(|| Duration::from_secs(config.request_timeout_secs)),
+        )
+    }
+
+    /// Name the guarded endpoint in logs and metrics.
+    pub fn with_provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = provider.into();
+        self
+    }
+
+    /// Require `probes` successful trial requests to close the circuit.
+    pub fn with_half_open_probes(mut self, probes: u32) -> Self {
+        self.half_open_probes = probes.max(1);
+        self
+    }
+
+    /// Fail requests that don't start streaming within `timeout`.
+    pub fn with_request_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.inner.lock().unwrap().state
+    }
+
+    pub fn snapshot(&self, now: Instant) -> CircuitSnapshot {
+        let inner = self.inner.lock().unwrap();
+        let (state, retry_in) = match inner.state {
+            CircuitState::Closed => ("closed", None),
+            CircuitState::HalfOpen => ("half_open", None),
+            CircuitState::Open { until } => ("open", Some(until.saturating_duration_since(now))),
+        };
+        CircuitSnapshot {
+            state,
+            consecutive_failures: inner.consecutive_failures,
+            retry_in_secs: retry_in.map(|d| d.as_secs()),
+        }
+    }
+
+    /// Admit a request, or reject it while the circuit is open.
+    ///
+    /// In the half-open state only one trial is admitted; if it never
+    /// reports back (e.g. the caller was dropped) another is admitted after
+    /// `reset_timeout`.
+    pub fn try_acquire(&self, now: Instant) -> Result<(), CircuitOpenError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            CircuitState::Closed => Ok(()),
+            CircuitState::Open { until } if now < until => Err(CircuitOpenError),
+            CircuitState::Open { .. } => {
+                self.transition(&mut inner, CircuitState::HalfOpen);
+                inner.trial_started = Some(now);
+                Ok(())
+            }
+            CircuitState::HalfOpen => match inner.trial_started {
+                Some(started) if now < started + self.reset_timeout => Err(CircuitOpenError),
+                _ => {
+                    inner.trial_started = Some(now);
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures = 0;
+        inner.trial_started = None;
+        if inner.state == CircuitState::HalfOpen {
+            inner.probe_successes += 1;
+            if inner.probe_successes < self.half_open_probes {
+                // Admit the next trial
+                return;
+            }
+        }
+        self.transition(&mut inner, CircuitState::Closed);
+    }
+
+    pub fn record_failure(&self, now: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        let trip = match inner.state {
+            CircuitState::HalfOpen => true,
+            CircuitState::Closed => inner.consecutive_failures >= self.failure_threshold,
+            CircuitState::Open { .. } => false,
+        };
+        if trip {
+            tracing::warn!(
+                provider = %self.provider,
+                failures = inner.consecutive_failures,
+                reset_timeout_secs = self.reset_timeout.as_secs(),
+                "LLM circuit opened"
+            );
+            inner.trial_started = None;
+            let until = now + self.reset_timeout;
+            self.transition(&mut inner, CircuitState::Open { until });
+        }
+    }
+
+    fn transition(&self, inner: &mut Inner, state: CircuitState) {
+        if inner.state != state {
+            if state == CircuitState::Closed {
+                tracing::info!(provider = %self.provider, "LLM circuit closed");
+            }
+            inner.state = state;
+            inner.probe_successes = 0;
+            metrics::gauge!(CIRCUIT_STATE_METRIC, "provider" => self.provider.clone())
+                .set(state.gauge_value());
+        }
+    }
+}
+
+/// One breaker per LLM endpoint, created on first use.
+#[derive(Debug)]
+pub struct CircuitBreakerRegistry {
+    config: CircuitBreakerConfig,
+    breakers: DashMap<String, Arc<CircuitBreaker>>,
+}
+
+impl CircuitBreakerRegistry {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            breakers: DashMap::new(),
+        }
+    }
+
+    /// Breaker of the endpoint `settings` talk to.
+    pub fn for_settings(&self, settings: &LlmSettings) -> Arc<CircuitBreaker> {
+        self.for_provider(&provider_key(&settings.base_url))
+    }
+
+    /// Breaker of the endpoint named `provider`.
+    pub fn for_provider(&self, provider: &str) -> Arc<CircuitBreaker> {
+        let breaker = self.breakers.entry(provider.to_string()).or_insert_with(|| {
+            Arc::new(CircuitBreaker::from_config(&self.config).with_provider(provider))
+        });
+        Arc::clone(&breaker)
+    }
+
+    /// State of every breaker used so far, by endpoint.
+    pub fn snapshots(&self) -> BTreeMap<String, CircuitSnapshot> {
+        let now = Instant::now();
+        self.breakers
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().snapshot(now)))
+            .collect()
+    }
+}
+
+/// Endpoint name of a base URL: its host and port, never its path or query.
+fn provider_key(base_url: &str) -> String {
+    url::Url::parse(base_url)
+        .ok()
+        .and_then(|url| {
+            let host = url.host_str()?.to_string();
+            Some(match url.port() {
+                Some(port) => format!("{host}:{port}"),
+                None => host,
+            })
+        })
+        .unwrap_or_else(|| "llm".to_string())
+}
+
+/// Driver decorator that routes requests through a [`CircuitBreaker`].
+pub struct CircuitBreakerDriver {
+    inner: Arc<dyn LlmDriver>,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl std::fmt::Debug for CircuitBreakerDriver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CircuitBreakerDriver")
+            .field("breaker", &self.breaker)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CircuitBreakerDriver {
+    pub fn new(inner: Arc<dyn LlmDriver>, breaker: Arc<CircuitBreaker>) -> Self {
+        Self { inner, breaker }
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmDriver for CircuitBreakerDriver {
+    async fn stream(
+        &self,
+        req: LlmRequest,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<NormalizedEvent>> + Send>>> {
+        self.breaker.try_acquire(Instant::now())?;
+        let result = match self.breaker.request_timeout {
+            Some(limit) => tokio::time::timeout(limit, self.inner.stream(req))
+                .await
+                .unwrap_or_else(|_| Err(ProviderTimeoutError(limit).into())),
+            None => self.inner.stream(req).await,
+        };
+        match result {
+            Ok(stream) => {
+                self.breaker.record_success();
+                Ok(stream)
+            }
+            Err(e) => {
+                self.breaker.record_failure(Instant::now());
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Driver whose every request fails, counting the attempts.
+    #[derive(Default)]
+    struct FailingDriver {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmDriver for FailingDriver {
+        async fn stream(
+            &self,
+            _req: LlmRequest,
+        ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<NormalizedEvent>> + Send>>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            anyhow::bail!("connection refused")
+        }
+    }
+
+    fn request() -> LlmRequest {
+        LlmRequest {
+            messages: vec![],
+            tools: vec![],
+            response_format: None,
+            tool_choice: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_skips_network() {
+        let failing = Arc::new(FailingDriver::default());
+        let breaker = Arc::new(CircuitBreaker::new(3, Duration::from_secs(60)));
+        let driver = CircuitBreakerDriver::new(
+            Arc::clone(&failing) as Arc<dyn LlmDriver>,
+            Arc::clone(&breaker),
+        );
+
+        for _ in 0..3 {
+            assert!(driver.stream(request()).await.is_err());
+        }
+        assert!(matches!(breaker.state(), CircuitState::Open { .. }));
+
+        let Err(err) = driver.stream(request()).await else {
+            panic!("expected the open circuit to reject the request");
+        };
+        assert_eq!(err.to_string(), "Circuit open: LLM unavailable");
+        assert_eq!(failing.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_breakers_are_per_provider() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 2,
+            ..CircuitBreakerConfig::default()
+        };
+        let registry = CircuitBreakerRegistry::new(config);
+        let dead = registry.for_provider("dead.example.com");
+        let failing = Arc::new(FailingDriver::default());
+        let driver = CircuitBreakerDriver::new(
+            Arc::clone(&failing) as Arc<dyn LlmDriver>,
+            Arc::clone(&dead),
+        );
+
+        for _ in 0..2 {
+            let err = driver.stream(request()).await.err().unwrap();
+            assert_eq!(error_code(&err), None);
+        }
+        // Further calls fail fast, with a code runs can report
+        for _ in 0..5 {
+            let err = driver.stream(request()).await.err().unwrap();
+            assert_eq!(error_code(&err), Some(CIRCUIT_OPEN_CODE));
+        }
+        assert_eq!(failing.calls.load(Ordering::SeqCst), 2);
+
+        assert!(Arc::ptr_eq(&dead, &registry.for_provider("dead.example.com")));
+        let healthy = registry.for_provider("api.openai.com");
+        assert!(healthy.try_acquire(Instant::now()).is_ok());
+
+        let snapshots = registry.snapshots();
+        assert_eq!(snapshots["dead.example.com"].state, "open");
+        assert_eq!(snapshots["dead.example.com"].consecutive_failures, 2);
+        assert_eq!(snapshots["api.openai.com"].state, "closed");
+    }
+
+    /// Driver that never starts streaming.
+    struct HangingDriver;
+
+    #[async_trait::async_trait]
+    impl LlmDriver for HangingDriver {
+        async fn stream(
+            &self,
+            _req: LlmRequest,
+        ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<NormalizedEvent>> + Send>>> {
+            futures::future::pending().await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_request_timeout_counts_as_failure() {
+        let breaker = Arc::new(
+            CircuitBreaker::new(1, Duration::from_secs(60))
+                .with_request_timeout(Some(Duration::from_secs(5))),
+        );
+        let driver = CircuitBreakerDriver::new(Arc::new(HangingDriver), Arc::clone(&breaker));
+
+        let err = driver.stream(request()).await.err().unwrap();
+        assert_eq!(error_code(&err), Some(PROVIDER_TIMEOUT_CODE));
+        assert!(matches!(breaker.state(), CircuitState::Open { .. }));
+    }
+
+    #[test]
+    fn test_half_open_needs_all_probes() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(30)).with_half_open_probes(2);
+        let start = Instant::now();
+        breaker.record_failure(start);
+
+        let later = start + Duration::from_secs(30);
+        assert!(breaker.try_acquire(later).is_ok());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire(later).is_ok());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_provider_key_is_host_only() {
+        assert_eq!(provider_key("https://api.openai.com/v1"), "api.openai.com");
+        assert_eq!(provider_key("http://localhost:11434/v1?key=secret"), "localhost:11434");
+        assert_eq!(provider_key("not a url"), "llm");
+    }
+
+    #[test]
+    fn test_half_open_trial() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+        let start = Instant::now();
+        breaker.record_failure(start);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record_failure(start);
+        assert!(breaker.try_acquire(start).is_err());
+
+        // After the timeout one trial is admitted; a failure re-opens
+        let later = start + Duration::from_secs(30);
+        assert!(breaker.try_acquire(later).is_ok());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.try_acquire(later).is_err());
+        breaker.record_failure(later);
+        assert!(matches!(breaker.state(), CircuitState::Open { .. }));
+
+        // A successful trial closes the circuit
+        let much_later = later + Duration::from_secs(30);
+        assert!(breaker.try_acquire(much_later).is_ok());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.try_acquire(much_later).is_ok());
+    }
+}
@@ -0,0 +1,142 @@
+//! Provider content-filter results.
+//!
+//! Azure `OpenAI` annotates prompts and completions with per-category
+//! results (`hate`, `sexual`, `violence`, `self_harm`, and detections such
+//! as `jailbreak`), which [`frame_content_filter`] turns into a
+//! [`NormalizedEvent::ContentFilter`].
+
+use serde_json::Value;
+
+use crate::normalized::{ContentFilterSeverity, NormalizedEvent};
+
+/// Content-filter event for a Chat Completions stream frame, if the frame
+/// flags anything.
+///
+/// Categories the provider filtered or detected are always reported; with
+/// `min_severity`, so are categories rated at least that severe.
+#[must_use]
+pub fn frame_content_filter(
+    frame: &Value,
+    min_severity: Option<ContentFilterSeverity>,
+) -> Option<NormalizedEvent> {
+    let prompt_results = frame["prompt_filter_results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|prompt| &prompt["content_filter_results"]);
+    let choice_results = frame["choices"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|choice| &choice["content_filter_results"]);
+
+    let mut categories: Vec<String> = Vec::new();
+    let mut severity = None;
+    for results in prompt_results.chain(choice_results) {
+        let Some(results) = results.as_object() else {
+            continue;
+        };
+        for (category, result) in results {
+            let rated = result["severity"]
+                .as_str()
+                .and_then(|s| s.parse::<ContentFilterSeverity>().ok());
+            let flagged = result["filtered"] == true
+                || result["detected"] == true
+                || rated.zip(min_severity).is_some_and(|(rated, min)| rated >= min);
+            if !flagged {
+                continue;
+            }
+            if !categories.contains(category) {
+                categories.push(category.clone());
+            }
+            severity = severity.max(rated);
+        }
+    }
+
+    (!categories.is_empty()).then_some(NormalizedEvent::ContentFilter {
+        categories,
+        severity,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A completion chunk as Azure `OpenAI` streams it when it cuts the
+    /// response off.
+    fn azure_frame() -> Value {
+        json!({
+            "id": "chatcmpl-1",
+            "choices": [{
+                "index": 0,
+                "delta": {},
+                "finish_reason": "content_filter",
+                "content_filter_results": {
+                    "hate": { "filtered": false, "severity": "safe" },
+                    "self_harm": { "filtered": false, "severity": "low" },
+                    "sexual": { "filtered": false, "severity": "safe" },
+                    "violence": { "filtered": true, "severity": "high" },
+                    "protected_material_text": { "filtered": false, "detected": false }
+                }
+            }]
+        })
+    }
+
+    #[test]
+    fn test_azure_filtered_categories() {
+        let event = frame_content_filter(&azure_frame(), None).unwrap();
+        assert_eq!(
+            event,
+            NormalizedEvent::ContentFilter {
+                categories: vec!["violence".to_string()],
+                severity: Some(ContentFilterSeverity::High),
+            }
+        );
+
+        // Unfiltered categories are reported from the configured severity up
+        let Some(NormalizedEvent::ContentFilter { categories, .. }) =
+            frame_content_filter(&azure_frame(), Some(ContentFilterSeverity::Low))
+        else {
+            panic!("expected a content filter event");
+        };
+        assert_eq!(categories, ["self_harm", "violence"]);
+    }
+
+    #[test]
+    fn test_prompt_detections() {
+        let frame = json!({
+            "choices": [],
+            "prompt_filter_results": [{
+                "prompt_index": 0,
+                "content_filter_results": {
+                    "jailbreak": { "filtered": true, "detected": true },
+                    "hate": { "filtered": false, "severity": "safe" }
+                }
+            }]
+        });
+        let event = frame_content_filter(&frame, None).unwrap();
+        assert_eq!(
+            event,
+            NormalizedEvent::ContentFilter {
+                categories: vec!["jailbreak".to_string()],
+                severity: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_clean_frames_report_nothing() {
+        let frame = json!({
+            "choices": [{
+                "delta": { "content": "Hello" },
+                "content_filter_results": {
+                    "hate": { "filtered": false, "severity": "safe" }
+                }
+            }]
+        });
+        assert!(frame_content_filter(&frame, None).is_none());
+        assert!(frame_content_filter(&json!({ "choices": [] }), None).is_none());
+    }
+}
@@ -0,0 +1,108 @@
+//! Per-run deadlines.
+//!
+//! A run gets one [`Deadline`], the shortest of the limits that apply to it
+//! (the server's run timeout, the request's own). The orchestrator bounds
+//! every LLM call and tool call by the time left until it, so a slow tool
+//! can't use up the time the model needs to answer.
+
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Error code of runs stopped by their deadline.
+pub const DEADLINE_EXCEEDED_CODE: &str = "DEADLINE_EXCEEDED";
+
+/// Budgets longer than this are treated as this long.
+const MAX_BUDGET: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// The run's deadline passed before the work finished.
+#[derive(Debug, thiserror::Error)]
+#[error("Run deadline of {}s exceeded", .0.as_secs_f32())]
+pub struct DeadlineExceeded(pub Duration);
+
+/// Point in time a run must be finished by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    at: Instant,
+    budget: Duration,
+}
+
+impl Deadline {
+    /// Deadline `budget` from now.
+    pub fn after(budget: Duration) -> Self {
+        let budget = budget.min(MAX_BUDGET);
+        Self {
+            at: Instant::now() + budget,
+            budget,
+        }
+    }
+
+    /// Deadline for the shortest of `limits`, counted from now; `None` if
+    /// no limit applies.
+    pub fn shortest(limits: impl IntoIterator<Item = Option<Duration>>) -> Option<Self> {
+        limits.into_iter().flatten().min().map(Self::after)
+    }
+
+    /// The whole time the run was given.
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Time left until the deadline.
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Error for work stopped by this deadline.
+    pub fn exceeded(&self) -> DeadlineExceeded {
+        DeadlineExceeded(self.budget)
+    }
+
+    /// Await `future` unless the deadline passes first, in which case it is
+    /// dropped (cancelling it).
+    pub async fn run<F: Future>(&self, future: F) -> Result<F::Output, DeadlineExceeded> {
+        tokio::time::timeout_at(self.at, future)
+            .await
+            .map_err(|_| self.exceeded())
+    }
+}
+
+/// Await `future` within `deadline`, or to completion without one.
+pub async fn within<F: Future>(
+    deadline: Option<Deadline>,
+    future: F,
+) -> Result<F::Output, DeadlineExceeded> {
+    match deadline {
+        Some(deadline) => deadline.run(future).await,
+        None => Ok(future.await),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shortest_limit_applies() {
+        let secs = |s| Some(Duration::from_secs(s));
+        let deadline = Deadline::shortest([secs(300), None, secs(30)]).unwrap();
+        assert_eq!(deadline.budget(), Duration::from_secs(30));
+        assert!(Deadline::shortest([None, None]).is_none());
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(deadline.remaining(), Duration::from_secs(20));
+        assert_eq!(deadline.run(async { 1 }).await.unwrap(), 1);
+
+        let slow = deadline.run(tokio::time::sleep(Duration::from_secs(60))).await;
+        let err = slow.unwrap_err();
+        assert_eq!(err.to_string(), "Run deadline of 30s exceeded");
+        assert!(deadline.is_expired());
+
+        // Unbounded budgets don't overflow
+        assert!(!Deadline::after(Duration::MAX).is_expired());
+    }
+}
@@ -0,0 +1,636 @@
+//! Sampling parameters forwarded to the provider with every request.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Value, json};
+use std::fmt;
+use std::str::FromStr;
+
+use super::Provider;
+
+/// Smallest thinking budget Anthropic-style endpoints accept.
+pub const MIN_THINKING_BUDGET: u32 = 1024;
+
+/// Most times a run is retried for empty or invalid output, so a model that
+/// keeps failing can't loop.
+pub const MAX_RUN_RETRIES: u32 = 1;
+
+/// Parameters that shape generation rather than select the model.
+///
+/// Unset parameters are left out of requests, so the provider's defaults
+/// apply.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationParams {
+    /// How hard a reasoning model thinks before answering
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Sampling temperature (0 to 2)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Probability mass sampled from (0 to 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    /// Most tokens the answer may take
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Penalty on tokens that already appeared (-2 to 2)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    /// Penalty growing with how often a token appeared (-2 to 2)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    /// Sequences that end the answer
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// Seed for repeatable sampling (Chat Completions only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Times a run ending with empty or schema-invalid output is run again
+    /// with a corrective instruction (capped at [`MAX_RUN_RETRIES`]; never
+    /// sent to the provider)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_retries: Option<u32>,
+}
+
+/// Reasoning effort for reasoning models.
+///
+/// Written as `low`, `medium` or `high`, or as a number of thinking tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+    /// Thinking token budget (Anthropic `budget_tokens`)
+    Budget(u32),
+}
+
+/// A generation parameter the provider can't honour.
+#[derive(Debug, thiserror::Error)]
+pub enum GenerationError {
+    #[error("{provider:?} does not support a reasoning budget of {effort} tokens")]
+    UnsupportedReasoning {
+        provider: Provider,
+        effort: ReasoningEffort,
+    },
+
+    #[error("thinking budget must be at least {MIN_THINKING_BUDGET} tokens, got {0}")]
+    BudgetTooSmall(u32),
+
+    #[error("{param} must be between {min} and {max}, got {value}")]
+    OutOfRange {
+        param: &'static str,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+
+    #[error("max_tokens must be at least 1")]
+    NoMaxTokens,
+}
+
+impl fmt::Display for ReasoningEffort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Low => f.write_str("low"),
+            Self::Medium => f.write_str("medium"),
+            Self::High => f.write_str("high"),
+            Self::Budget(tokens) => write!(f, "{tokens}"),
+        }
+    }
+}
+
+impl FromStr for ReasoningEffort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            other => other.parse().map(Self::Budget).map_err(|_| {
+                format!(
+                    "invalid reasoning effort '{other}' (expected low, medium, high or a token budget)"
+                )
+            }),
+        }
+    }
+}
+
+impl Serialize for ReasoningEffort {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Budget(tokens) => serializer.serialize_u32(*tokens),
+            level => serializer.collect_str(level),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ReasoningEffort {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Budget(u32),
+            Level(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Budget(tokens) => Ok(Self::Budget(tokens)),
+            Repr::Level(level) => level.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl GenerationParams {
+    /// Check the parameters against what `provider` accepts.
+    ///
+    /// `OpenAI`, Azure, Groq and Together only take effort levels; token
+    /// budgets need `OpenRouter`, Bedrock or an Anthropic-compatible endpoint.
+    pub fn validate(&self, provider: &Provider) -> Result<(), GenerationError> {
+        if let Some(effort @ ReasoningEffort::Budget(tokens)) = self.reasoning_effort {
+            match provider {
+                Provider::OpenRouter
+                | Provider::OpenAICompatible
+                | Provider::Generic
+                | Provider::Bedrock { .. } => {}
+                _ => {
+                    return Err(GenerationError::UnsupportedReasoning {
+                        provider: provider.clone(),
+                        effort,
+                    });
+                }
+            }
+            if tokens < MIN_THINKING_BUDGET {
+                return Err(GenerationError::BudgetTooSmall(tokens));
+            }
+        }
+        check_range("temperature", self.temperature, 0.0, 2.0)?;
+        check_range("top_p", self.top_p, 0.0, 1.0)?;
+        check_range("presence_penalty", self.presence_penalty, -2.0, 2.0)?;
+        check_range("frequency_penalty", self.frequency_penalty, -2.0, 2.0)?;
+        if self.max_tokens == Some(0) {
+            return Err(GenerationError::NoMaxTokens);
+        }
+        Ok(())
+    }
+
+    /// These parameters, with the ones set in `overrides` replacing them.
+    #[must_use]
+    pub fn overridden_by(&self, overrides: &GenerationParams) -> Self {
+        Self {
+            reasoning_effort: overrides.reasoning_effort.or(self.reasoning_effort),
+            temperature: overrides.temperature.or(self.temperature),
+            top_p: overrides.top_p.or(self.top_p),
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            presence_penalty: overrides.presence_penalty.or(self.presence_penalty),
+            frequency_penalty: overrides.frequency_penalty.or(self.frequency_penalty),
+            stop: if overrides.stop.is_empty() {
+                self.stop.clone()
+            } else {
+                overrides.stop.clone()
+            },
+            seed: overrides.seed.or(self.seed),
+            run_retries: overrides.run_retries.or(self.run_retries),
+        }
+    }
+
+    /// Whether identical requests should get identical answers: sampling
+    /// at temperature 0, or with a fixed seed.
+    #[must_use]
+    pub fn is_deterministic(&self) -> bool {
+        self.temperature.is_some_and(|t| t <= 0.0) || self.seed.is_some()
+    }
+
+    /// How often a run may be retried for empty or invalid output.
+    #[must_use]
+    pub fn allowed_run_retries(&self) -> u32 {
+        self.run_retries.unwrap_or(0).min(MAX_RUN_RETRIES)
+    }
+
+    /// Add the sampling parameters to an OpenAI-style request body, unless
+    /// its model rejects them.
+    fn apply_sampling(&self, body: &mut Value) {
+        let sampling = [
+            ("temperature", self.temperature),
+            ("top_p", self.top_p),
+            ("presence_penalty", self.presence_penalty),
+            ("frequency_penalty", self.frequency_penalty),
+        ];
+        let model = body["model"].as_str().unwrap_or_default().to_string();
+        for (param, value) in sampling {
+            let Some(value) = value else { continue };
+            if rejects_sampling(&model) {
+                tracing::debug!(%model, param, "Model rejects sampling parameters, leaving out");
+            } else {
+                body[param] = json!(value);
+            }
+        }
+    }
+
+    /// Add the parameters to a Chat Completions request body.
+    pub fn apply_chat_completions(
+        &self,
+        provider: &Provider,
+        body: &mut Value,
+    ) -> Result<(), GenerationError> {
+        self.validate(provider)?;
+        self.apply_sampling(body);
+        if let Some(max_tokens) = self.max_tokens {
+            // OpenAI replaced `max_tokens`, which its reasoning models reject
+            let field = match provider {
+                Provider::OpenAI | Provider::AzureOpenAI { .. } => "max_completion_tokens",
+                _ => "max_tokens",
+            };
+            body[field] = json!(max_tokens);
+        }
+        if !self.stop.is_empty() {
+            body["stop"] = json!(self.stop);
+        }
+        if let Some(seed) = self.seed {
+            body["seed"] = json!(seed);
+        }
+        if let Some(effort) = self.reasoning_effort {
+            match (provider, effort) {
+                (Provider::OpenRouter, _) => body["reasoning"] = openrouter_reasoning(effort),
+                (_, ReasoningEffort::Budget(tokens)) => body["thinking"] = thinking(tokens),
+                (_, level) => body["reasoning_effort"] = json!(level),
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the parameters to a Responses API request body.
+    ///
+    /// The Responses API has no stop sequences or seed; they are left out.
+    pub fn apply_responses(
+        &self,
+        provider: &Provider,
+        body: &mut Value,
+    ) -> Result<(), GenerationError> {
+        self.validate(provider)?;
+        self.apply_sampling(body);
+        if let Some(max_tokens) = self.max_tokens {
+            body["max_output_tokens"] = json!(max_tokens);
+        }
+        if !self.stop.is_empty() {
+            tracing::debug!("The Responses API takes no stop sequences, leaving them out");
+        }
+        if self.seed.is_some() {
+            tracing::debug!("The Responses API takes no seed, leaving it out");
+        }
+        if let Some(effort) = self.reasoning_effort {
+            match (provider, effort) {
+                (Provider::OpenRouter, _) => body["reasoning"] = openrouter_reasoning(effort),
+                (_, ReasoningEffort::Budget(tokens)) => body["thinking"] = thinking(tokens),
+                (_, level) => body["reasoning"] = json!({ "effort": level }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the parameters to an Anthropic Messages request body.
+    ///
+    /// Claude only takes a thinking budget, which counts towards
+    /// `max_tokens`; effort levels are left out. So are the penalties and
+    /// the seed, and with thinking on, `temperature` and `top_p`.
+    pub fn apply_anthropic(
+        &self,
+        provider: &Provider,
+        body: &mut Value,
+    ) -> Result<(), GenerationError> {
+        self.validate(provider)?;
+        if let Some(max_tokens) = self.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+        if !self.stop.is_empty() {
+            body["stop_sequences"] = json!(self.stop);
+        }
+        if !matches!(self.reasoning_effort, Some(ReasoningEffort::Budget(_))) {
+            if let Some(temperature) = self.temperature {
+                body["temperature"] = json!(temperature);
+            }
+            if let Some(top_p) = self.top_p {
+                body["top_p"] = json!(top_p);
+            }
+        }
+        match self.reasoning_effort {
+            Some(ReasoningEffort::Budget(tokens)) => {
+                body["thinking"] = thinking(tokens);
+                if let Some(max_tokens) = body["max_tokens"].as_u64() {
+                    body["max_tokens"] = json!(max_tokens + u64::from(tokens));
+                }
+            }
+            Some(level) => {
+                tracing::debug!(%level, "Claude takes a thinking budget, ignoring level");
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Add the parameters to an Amazon Titan `textGenerationConfig`.
+    ///
+    /// Titan takes no penalties, seed or reasoning; they are left out.
+    pub fn apply_titan(
+        &self,
+        provider: &Provider,
+        config: &mut Value,
+    ) -> Result<(), GenerationError> {
+        self.validate(provider)?;
+        if let Some(max_tokens) = self.max_tokens {
+            config["maxTokenCount"] = json!(max_tokens);
+        }
+        if let Some(temperature) = self.temperature {
+            config["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            config["topP"] = json!(top_p);
+        }
+        if !self.stop.is_empty() {
+            config["stopSequences"] = json!(self.stop);
+        }
+        self.leave_out_unsupported("Titan", &["max_tokens", "temperature", "top_p", "stop"]);
+        Ok(())
+    }
+
+    /// Add the parameters to a Meta Llama request body on Bedrock.
+    ///
+    /// `max_tokens` can only lower the `max_gen_len` already in `body`.
+    /// Llama takes no stop sequences, penalties, seed or reasoning; they are
+    /// left out.
+    pub fn apply_llama(
+        &self,
+        provider: &Provider,
+        body: &mut Value,
+    ) -> Result<(), GenerationError> {
+        self.validate(provider)?;
+        if let Some(max_tokens) = self.max_tokens {
+            let most = body["max_gen_len"].as_u64().unwrap_or(u64::MAX);
+            body["max_gen_len"] = json!(u64::from(max_tokens).min(most));
+        }
+        if let Some(temperature) = self.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        self.leave_out_unsupported("Llama", &["max_tokens", "temperature", "top_p"]);
+        Ok(())
+    }
+
+    /// Log the parameters that are set but not in `accepted` by `model`.
+    fn leave_out_unsupported(&self, model: &str, accepted: &[&str]) {
+        let set = [
+            ("reasoning_effort", self.reasoning_effort.is_some()),
+            ("temperature", self.temperature.is_some()),
+            ("top_p", self.top_p.is_some()),
+            ("max_tokens", self.max_tokens.is_some()),
+            ("presence_penalty", self.presence_penalty.is_some()),
+            ("frequency_penalty", self.frequency_penalty.is_some()),
+            ("stop", !self.stop.is_empty()),
+            ("seed", self.seed.is_some()),
+        ];
+        let ignored: Vec<_> = set
+            .into_iter()
+            .filter(|(param, is_set)| *is_set && !accepted.contains(param))
+            .map(|(param, _)| param)
+            .collect();
+        if !ignored.is_empty() {
+            tracing::debug!(model, ?ignored, "Model takes no such parameters, leaving them out");
+        }
+    }
+}
+
+fn check_range(
+    param: &'static str,
+    value: Option<f64>,
+    min: f64,
+    max: f64,
+) -> Result<(), GenerationError> {
+    match value {
+        Some(value) if !(min..=max).contains(&value) => Err(GenerationError::OutOfRange {
+            param,
+            value,
+            min,
+            max,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Whether `model` rejects `temperature`, `top_p` and the penalties, as
+/// `OpenAI`'s reasoning models (o-series, GPT-5 apart from its chat
+/// variant) do.
+fn rejects_sampling(model: &str) -> bool {
+    let name = model.rsplit('/').next().unwrap_or(model);
+    let name = name.strip_prefix("ft:").unwrap_or(name);
+    ["o1", "o3", "o4"].iter().any(|prefix| name.starts_with(prefix))
+        || (name.starts_with("gpt-5") && !name.starts_with("gpt-5-chat"))
+}
+
+/// `OpenRouter` takes either an effort level or `max_tokens` for reasoning.
+fn openrouter_reasoning(effort: ReasoningEffort) -> Value {
+    match effort {
+        ReasoningEffort::Budget(tokens) => json!({ "max_tokens": tokens }),
+        level => json!({ "effort": level }),
+    }
+}
+
+/// Anthropic extended thinking.
+fn thinking(budget_tokens: u32) -> Value {
+    json!({ "type": "enabled", "budget_tokens": budget_tokens })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(effort: ReasoningEffort) -> GenerationParams {
+        GenerationParams {
+            reasoning_effort: Some(effort),
+            ..GenerationParams::default()
+        }
+    }
+
+    fn sampling() -> GenerationParams {
+        GenerationParams {
+            temperature: Some(0.2),
+            top_p: Some(0.9),
+            max_tokens: Some(512),
+            presence_penalty: Some(0.5),
+            frequency_penalty: Some(-0.5),
+            stop: vec!["END".to_string()],
+            seed: Some(7),
+            ..GenerationParams::default()
+        }
+    }
+
+    #[test]
+    fn test_sampling_params_in_request_body() {
+        let mut body = json!({ "model": "gpt-4o" });
+        sampling()
+            .apply_chat_completions(&Provider::Groq, &mut body)
+            .unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "model": "gpt-4o",
+                "temperature": 0.2,
+                "top_p": 0.9,
+                "max_tokens": 512,
+                "presence_penalty": 0.5,
+                "frequency_penalty": -0.5,
+                "stop": ["END"],
+                "seed": 7,
+            })
+        );
+
+        let mut body = json!({ "model": "gpt-4.1" });
+        sampling()
+            .apply_responses(&Provider::OpenAI, &mut body)
+            .unwrap();
+        assert_eq!(body["temperature"], 0.2);
+        assert_eq!(body["max_output_tokens"], 512);
+        assert!(body.get("stop").is_none());
+        assert!(body.get("seed").is_none());
+
+        let mut body = json!({ "anthropic_version": "bedrock-2023-05-31", "max_tokens": 4096 });
+        sampling()
+            .apply_anthropic(&Provider::Generic, &mut body)
+            .unwrap();
+        assert_eq!(body["max_tokens"], 512);
+        assert_eq!(body["stop_sequences"], json!(["END"]));
+        assert_eq!(body["temperature"], 0.2);
+        assert!(body.get("presence_penalty").is_none());
+    }
+
+    #[test]
+    fn test_reasoning_models_skip_sampling() {
+        for model in ["gpt-5", "o4-mini", "openai/o3"] {
+            let mut body = json!({ "model": model });
+            sampling()
+                .apply_chat_completions(&Provider::OpenAI, &mut body)
+                .unwrap();
+            assert!(body.get("temperature").is_none(), "{model}");
+            assert!(body.get("top_p").is_none(), "{model}");
+            assert_eq!(body["max_completion_tokens"], 512, "{model}");
+            assert_eq!(body["stop"], json!(["END"]), "{model}");
+        }
+
+        let mut body = json!({ "model": "gpt-5-chat-latest" });
+        sampling()
+            .apply_chat_completions(&Provider::OpenAI, &mut body)
+            .unwrap();
+        assert_eq!(body["temperature"], 0.2);
+    }
+
+    #[test]
+    fn test_overrides_and_ranges() {
+        let agent = GenerationParams {
+            temperature: Some(1.0),
+            stop: vec!["STOP".to_string()],
+            ..GenerationParams::default()
+        };
+        let merged = sampling().overridden_by(&agent);
+        assert_eq!(merged.temperature, Some(1.0));
+        assert_eq!(merged.top_p, Some(0.9));
+        assert_eq!(merged.stop, vec!["STOP".to_string()]);
+        assert_eq!(merged.allowed_run_retries(), 0);
+        assert!(merged.is_deterministic());
+        assert!(!agent.is_deterministic());
+        let greedy = GenerationParams {
+            temperature: Some(0.0),
+            ..GenerationParams::default()
+        };
+        assert!(greedy.is_deterministic());
+
+        // Run retries are capped, not sent
+        let retrying = GenerationParams {
+            run_retries: Some(5),
+            ..GenerationParams::default()
+        };
+        assert_eq!(retrying.allowed_run_retries(), MAX_RUN_RETRIES);
+        let mut body = json!({});
+        retrying
+            .apply_chat_completions(&Provider::OpenAI, &mut body)
+            .unwrap();
+        assert_eq!(body, json!({}));
+
+        let hot = GenerationParams {
+            temperature: Some(2.5),
+            ..GenerationParams::default()
+        };
+        assert_eq!(
+            hot.validate(&Provider::OpenAI).unwrap_err().to_string(),
+            "temperature must be between 0 and 2, got 2.5"
+        );
+        let empty = GenerationParams {
+            max_tokens: Some(0),
+            ..GenerationParams::default()
+        };
+        assert!(matches!(
+            empty.validate(&Provider::OpenAI),
+            Err(GenerationError::NoMaxTokens)
+        ));
+    }
+
+    #[test]
+    fn test_reasoning_effort_in_request_body() {
+        let mut body = json!({ "model": "o4-mini" });
+        params(ReasoningEffort::High)
+            .apply_chat_completions(&Provider::OpenAI, &mut body)
+            .unwrap();
+        assert_eq!(body["reasoning_effort"], "high");
+
+        let mut body = json!({ "model": "gpt-5" });
+        params(ReasoningEffort::Low)
+            .apply_responses(&Provider::OpenAI, &mut body)
+            .unwrap();
+        assert_eq!(body["reasoning"], json!({ "effort": "low" }));
+
+        let mut body = json!({});
+        params(ReasoningEffort::Budget(4096))
+            .apply_chat_completions(&Provider::OpenRouter, &mut body)
+            .unwrap();
+        assert_eq!(body["reasoning"], json!({ "max_tokens": 4096 }));
+
+        let mut body = json!({});
+        params(ReasoningEffort::Budget(2048))
+            .apply_chat_completions(&Provider::Generic, &mut body)
+            .unwrap();
+        assert_eq!(
+            body["thinking"],
+            json!({ "type": "enabled", "budget_tokens": 2048 })
+        );
+
+        // Nothing configured, nothing sent
+        let mut body = json!({});
+        GenerationParams::default()
+            .apply_chat_completions(&Provider::OpenAI, &mut body)
+            .unwrap();
+        assert_eq!(body, json!({}));
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(matches!(
+            params(ReasoningEffort::Budget(4096)).validate(&Provider::OpenAI),
+            Err(GenerationError::UnsupportedReasoning { .. })
+        ));
+        assert!(matches!(
+            params(ReasoningEffort::Budget(100)).validate(&Provider::Generic),
+            Err(GenerationError::BudgetTooSmall(100))
+        ));
+        assert!(params(ReasoningEffort::Medium).validate(&Provider::Groq).is_ok());
+    }
+
+    #[test]
+    fn test_parse() {
+        let effort: ReasoningEffort = serde_yaml::from_str("medium").unwrap();
+        assert_eq!(effort, ReasoningEffort::Medium);
+        let effort: ReasoningEffort = serde_yaml::from_str("8000").unwrap();
+        assert_eq!(effort, ReasoningEffort::Budget(8000));
+        assert_eq!(serde_json::to_value(effort).unwrap(), json!(8000));
+        assert_eq!("HIGH".parse(), Ok(ReasoningEffort::High));
+        assert!("extreme".parse::<ReasoningEffort>().is_err());
+    }
+}
@@ -14,6 +14,7 @@
 //!
 //! - [`ChatCompletionsDriver`]: `OpenAI` Chat Completions API (`/v1/chat/completions`)
 //! - [`ResponsesDriver`]: `OpenAI` Responses API (`/v1/responses`)
+//! - [`BedrockDriver`]: AWS Bedrock (`InvokeModelWithResponseStream`)
 //!
 //! # Example
 //!
@@ -28,17 +29,40 @@
 //! };
 //! ```
 
+pub mod aliases;
+pub mod bedrock;
 pub mod chat_completions;
+pub mod circuit_breaker;
+pub mod content_filter;
+pub mod deadline;
+pub mod generation;
 pub mod orchestrator;
 pub mod provider;
+pub mod response_cache;
 pub mod responses;
+pub mod structured;
+pub mod tool_args;
+pub mod tool_choice;
+pub mod usage;
 
+pub use aliases::{ModelAliases, UnmappedAliasError};
+pub use bedrock::BedrockDriver;
 pub use chat_completions::ChatCompletionsDriver;
-pub use orchestrator::Orchestrator;
+pub use circuit_breaker::{
+    CircuitBreaker, CircuitBreakerDriver, CircuitBreakerRegistry, CircuitSnapshot,
+};
+pub use deadline::{DEADLINE_EXCEEDED_CODE, Deadline, DeadlineExceeded};
+pub use generation::{GenerationParams, ReasoningEffort};
+pub use orchestrator::{ApprovalGate, DEFAULT_TOOL_APPROVAL_TIMEOUT, Orchestrator, ResumeGate};
 pub use provider::Provider;
+pub use response_cache::{CachingDriver, ResponseCache};
 pub use responses::ResponsesDriver;
+pub use structured::{ResponseFormat, StructuredOutputError};
+pub use tool_args::{ToolArgumentsError, validate_tool_arguments};
+pub use tool_choice::ToolChoice;
+pub use usage::UsageFields;
 
-use crate::normalized::NormalizedEvent;
+use crate::normalized::{ContentFilterSeverity, NormalizedEvent};
 use futures::Stream;
 
 /// LLM connection and model settings.
@@ -62,6 +86,25 @@ pub struct LlmSettings {
     /// Azure API version (required for Azure `OpenAI`).
     #[allow(dead_code)]
     pub api_version: Option<String>,
+    /// How to handle a turn that completes with no content and no tool calls.
+    pub empty_response: EmptyResponsePolicy,
+    /// Also report content-filter categories the provider rated at least
+    /// this severe but didn't filter; `None` reports filtered ones only.
+    pub content_filter_min_severity: Option<ContentFilterSeverity>,
+    /// Sampling parameters sent with every request.
+    pub generation: GenerationParams,
+    /// Names of the token counts in Chat Completions usage reports.
+    pub usage_fields: UsageFields,
+}
+
+/// Handling for turns where the provider returns an empty stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmptyResponsePolicy {
+    /// Emit an `EMPTY_RESPONSE` error event.
+    #[default]
+    Error,
+    /// Retry the request once, then emit an `EMPTY_RESPONSE` error.
+    Retry,
 }
 
 /// LLM protocol variants.
@@ -286,6 +329,10 @@ pub struct LlmRequest {
     pub messages: Vec<serde_json::Value>,
     /// Available tools in `OpenAI` function schema format.
     pub tools: Vec<serde_json::Value>,
+    /// Constrain the answer to JSON (provider default if `None`).
+    pub response_format: Option<ResponseFormat>,
+    /// Whether the model must call a tool (provider default if `None`).
+    pub tool_choice: Option<ToolChoice>,
 }
 
 /// Trait for LLM streaming drivers.
@@ -20,23 +20,69 @@
 //! let stream = orchestrator.chat("Hello, what time is it?").await?;
 //! ```
 
-use std::collections::BTreeMap;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use futures::{Stream, StreamExt};
+use tokio::sync::{Notify, oneshot};
 use uuid::Uuid;
 
 use crate::mcp::registry::McpRegistry;
 use crate::normalized::NormalizedEvent;
+use crate::session::Session;
 
 use super::{
-    ChatCompletionsDriver, LlmDriver, LlmProtocol, LlmRequest, LlmSettings, Message,
-    MessageContent, MessageRole, ResponsesDriver, ToolCall, ToolCallFunction,
+    BedrockDriver, ChatCompletionsDriver, CircuitBreaker, CircuitBreakerDriver,
+    DEADLINE_EXCEEDED_CODE, Deadline, DeadlineExceeded, EmptyResponsePolicy, LlmDriver,
+    LlmProtocol, LlmRequest, LlmSettings, Message, MessageContent, MessageRole, Provider,
+    ResponseCache, ResponseFormat, ResponsesDriver, StructuredOutputError, ToolCall,
+    ToolCallFunction, ToolChoice, circuit_breaker, deadline, response_cache::CachingDriver,
+    validate_tool_arguments,
 };
 
 /// Maximum number of tool loop iterations to prevent infinite loops.
 const MAX_TOOL_ITERATIONS: usize = 10;
 
+/// Tool result sent to the model for a call over the per-turn limit.
+fn skipped_tool_call_result(max: usize) -> String {
+    serde_json::json!({
+        "error": "too_many_tool_calls",
+        "message": format!(
+            "Only the first {max} tool calls of a turn are executed; this one was skipped. \
+             Call it again in a later turn if you still need it."
+        ),
+    })
+    .to_string()
+}
+
+/// Instruction appended when a run is retried for ending with no output.
+const EMPTY_OUTPUT_CORRECTION: &str =
+    "Your previous output was empty. Please answer the request above.";
+
+/// Instruction appended when a run is retried for output that doesn't match
+/// its response format.
+fn invalid_output_correction(error: &StructuredOutputError) -> String {
+    format!(
+        "Your previous output was invalid ({error}). Please answer again, following the \
+         required format exactly."
+    )
+}
+
+/// User message carrying a corrective instruction for a retried run.
+fn correction_message(instruction: &str) -> serde_json::Value {
+    serde_json::json!({ "role": "user", "content": instruction })
+}
+
+/// Error event ending a run whose deadline passed.
+fn deadline_error(exceeded: &DeadlineExceeded) -> NormalizedEvent {
+    NormalizedEvent::Error {
+        message: exceeded.to_string(),
+        code: Some(DEADLINE_EXCEEDED_CODE.to_string()),
+    }
+}
+
 /// Accumulated state for a streaming tool call.
 #[derive(Debug, Default, Clone)]
 struct ToolCallAccumulator {
@@ -45,6 +91,91 @@ struct ToolCallAccumulator {
     arguments: String,
 }
 
+/// Holds an interactive tool loop after each batch of tool results, before
+/// they are sent back to the model, until the client resumes it.
+#[derive(Debug, Default)]
+pub struct ResumeGate {
+    paused: AtomicBool,
+    resume: Notify,
+}
+
+impl ResumeGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the tool loop is waiting to be resumed.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Let a paused tool loop continue. Returns `false` if it was not paused.
+    pub fn resume(&self) -> bool {
+        let was_paused = self.paused.swap(false, Ordering::SeqCst);
+        if was_paused {
+            self.resume.notify_one();
+        }
+        was_paused
+    }
+
+    async fn wait(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+        // A resume racing this call leaves a permit, so it is not lost
+        self.resume.notified().await;
+    }
+}
+
+/// How long a tool call waits for approval unless configured otherwise.
+pub const DEFAULT_TOOL_APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Tool result sent to the model for a call that was not approved.
+const TOOL_DENIED_RESULT: &str = "Error: tool call denied by user";
+
+/// Holds calls to tools that require approval until the user decides on
+/// them. A call left undecided for the timeout is denied.
+#[derive(Debug)]
+pub struct ApprovalGate {
+    timeout: Duration,
+    /// Tool call ID -> where its decision goes
+    pending: Mutex<HashMap<String, oneshot::Sender<bool>>>,
+}
+
+impl ApprovalGate {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Approve or deny the waiting call `id`. Returns `false` if no call
+    /// with that ID is waiting.
+    pub fn decide(&self, id: &str, approved: bool) -> bool {
+        let sender = self.pending.lock().unwrap().remove(id);
+        sender.is_some_and(|tx| tx.send(approved).is_ok())
+    }
+
+    /// Start accepting a decision on call `id`.
+    ///
+    /// Registered before the request is announced, so a quick answer is
+    /// not lost.
+    fn request(&self, id: &str) -> oneshot::Receiver<bool> {
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.to_string(), tx);
+        rx
+    }
+
+    /// Wait for the decision on call `id`; `false` if denied or timed out.
+    async fn wait(&self, id: &str, decision: oneshot::Receiver<bool>) -> bool {
+        let approved = matches!(
+            tokio::time::timeout(self.timeout, decision).await,
+            Ok(Ok(true))
+        );
+        self.pending.lock().unwrap().remove(id);
+        approved
+    }
+}
+
 /// LLM orchestrator with tool loop execution.
 ///
 /// The orchestrator wraps an [`LlmDriver`] and adds:
@@ -57,6 +188,20 @@ pub struct Orchestrator {
     settings: LlmSettings,
     mcp: Arc<McpRegistry>,
     driver: Arc<dyn LlmDriver>,
+    /// Conversation the tools run in (gives stateful tools their state)
+    session: Option<Session>,
+    /// Tool choice for the first turn; later turns relax forced choices
+    tool_choice: Option<ToolChoice>,
+    /// Pauses the loop after tool results (interactive runs only)
+    resume_gate: Option<Arc<ResumeGate>>,
+    /// Decides on calls to tools that require approval; without one they
+    /// are denied
+    approval_gate: Option<Arc<ApprovalGate>>,
+    /// Tool calls executed per model turn; later ones are answered with an
+    /// error
+    max_tool_calls_per_turn: Option<usize>,
+    /// Bounds every LLM and tool call of the run
+    deadline: Option<Deadline>,
 }
 
 #[allow(clippy::missing_fields_in_debug)]
@@ -65,6 +210,11 @@ impl std::fmt::Debug for Orchestrator {
         f.debug_struct("Orchestrator")
             .field("settings", &self.settings)
             .field("mcp", &"McpRegistry")
+            .field("session", &self.session.as_ref().map(Session::id))
+            .field("tool_choice", &self.tool_choice)
+            .field("interactive", &self.resume_gate.is_some())
+            .field("approvals", &self.approval_gate.is_some())
+            .field("deadline", &self.deadline)
             .finish()
     }
 }
@@ -74,6 +224,10 @@ impl Orchestrator {
     #[allow(dead_code)]
     pub fn new(settings: LlmSettings, mcp: Arc<McpRegistry>) -> Self {
         let driver: Arc<dyn LlmDriver> = match settings.protocol {
+            // Bedrock has its own API whatever the protocol
+            _ if matches!(settings.provider, Provider::Bedrock { .. }) => {
+                Arc::new(BedrockDriver::new(settings.clone()))
+            }
             LlmProtocol::Responses => Arc::new(ResponsesDriver::new(settings.clone())),
             LlmProtocol::Chat => Arc::new(ChatCompletionsDriver::new(settings.clone())),
             LlmProtocol::Auto => {
@@ -87,9 +241,110 @@ impl Orchestrator {
             settings,
             mcp,
             driver,
+            session: None,
+            tool_choice: None,
+            resume_gate: None,
+            approval_gate: None,
+            max_tool_calls_per_turn: None,
+            deadline: None,
         }
     }
 
+    /// Create an orchestrator around an explicit driver (e.g. a mock in tests).
+    pub fn with_driver(
+        settings: LlmSettings,
+        mcp: Arc<McpRegistry>,
+        driver: Arc<dyn LlmDriver>,
+    ) -> Self {
+        Self {
+            settings,
+            mcp,
+            driver,
+            session: None,
+            tool_choice: None,
+            resume_gate: None,
+            approval_gate: None,
+            max_tool_calls_per_turn: None,
+            deadline: None,
+        }
+    }
+
+    /// Control whether the model must call a tool.
+    ///
+    /// Applies to the first turn; once tool results are fed back, a forced
+    /// choice relaxes to `auto` so the model can answer.
+    #[must_use]
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    /// Route requests through `breaker`, failing fast while it is open.
+    ///
+    /// The breaker is shared, so failures of other orchestrators using it
+    /// count too.
+    #[must_use]
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.driver = Arc::new(CircuitBreakerDriver::new(self.driver, breaker));
+        self
+    }
+
+    /// Answer repeated requests from `cache` instead of the provider.
+    ///
+    /// Only deterministic settings (temperature 0 or a seed) use it; sampled
+    /// answers are meant to differ, so others ignore the cache.
+    #[must_use]
+    pub fn with_response_cache(mut self, cache: Arc<ResponseCache>) -> Self {
+        if self.settings.generation.is_deterministic() {
+            self.driver = Arc::new(CachingDriver::new(self.driver, cache, self.settings.clone()));
+        }
+        self
+    }
+
+    /// Wait on `gate` after every batch of tool results, emitting
+    /// `ToolLoopPaused`, before sending the results back to the model.
+    #[must_use]
+    pub fn with_resume_gate(mut self, gate: Arc<ResumeGate>) -> Self {
+        self.resume_gate = Some(gate);
+        self
+    }
+
+    /// Ask `gate` for a decision before calling a tool that requires
+    /// approval, emitting `ToolApprovalRequest`.
+    #[must_use]
+    pub fn with_approval_gate(mut self, gate: Arc<ApprovalGate>) -> Self {
+        self.approval_gate = Some(gate);
+        self
+    }
+
+    /// Execute at most `max` of the tool calls the model makes in one turn.
+    ///
+    /// The others are answered with an error telling the model to repeat
+    /// them later, so a single turn can't fan out to unbounded tool calls.
+    #[must_use]
+    pub fn with_max_tool_calls_per_turn(mut self, max: usize) -> Self {
+        self.max_tool_calls_per_turn = Some(max);
+        self
+    }
+
+    /// Stop the run with a `DEADLINE_EXCEEDED` error once `deadline` passes.
+    ///
+    /// LLM calls and tool calls get only the time left until it; one still
+    /// running when it passes is cancelled.
+    #[must_use]
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Run tools on behalf of `session`, so stateful tools can keep state
+    /// across its turns.
+    #[must_use]
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
     /// Get the LLM settings.
     #[must_use]
     #[allow(dead_code)]
@@ -179,6 +434,8 @@ impl Orchestrator {
             );
 
             let mut iteration = 0;
+            let mut retried_empty = false;
+            let mut run_retries_left = orchestrator.settings.generation.allowed_run_retries();
 
             loop {
                 if iteration >= MAX_TOOL_ITERATIONS {
@@ -203,9 +460,16 @@ impl Orchestrator {
                     "Starting tool loop iteration"
                 );
 
+                let tool_choice = if iteration == 1 {
+                    orchestrator.tool_choice.clone()
+                } else {
+                    orchestrator.tool_choice.as_ref().map(ToolChoice::after_tool_results)
+                };
                 let req = LlmRequest {
                     messages: message_json.clone(),
                     tools: tools.clone(),
+                    response_format: None,
+                    tool_choice,
                 };
 
                 // Log the full request being sent to the LLM
@@ -218,8 +482,18 @@ impl Orchestrator {
                 );
 
                 // Stream from the driver
-                let driver_stream = match orchestrator.driver.stream(req).await {
-                    Ok(s) => {
+                let driver_call = orchestrator.driver.stream(req);
+                let driver_stream = match deadline::within(orchestrator.deadline, driver_call).await {
+                    Err(exceeded) => {
+                        tracing::warn!(
+                            request_id = %request_id,
+                            iteration = iteration,
+                            "Run deadline passed waiting for the LLM"
+                        );
+                        yield deadline_error(&exceeded);
+                        break;
+                    }
+                    Ok(Ok(s)) => {
                         tracing::debug!(
                             request_id = %request_id,
                             iteration = iteration,
@@ -227,7 +501,7 @@ impl Orchestrator {
                         );
                         s
                     }
-                    Err(e) => {
+                    Ok(Err(e)) => {
                         tracing::error!(
                             request_id = %request_id,
                             iteration = iteration,
@@ -236,7 +510,7 @@ impl Orchestrator {
                         );
                         yield NormalizedEvent::Error {
                             message: e.to_string(),
-                            code: None,
+                            code: circuit_breaker::error_code(&e).map(str::to_string),
                         };
                         break;
                     }
@@ -246,16 +520,36 @@ impl Orchestrator {
                 let mut assistant_text = String::new();
                 let mut has_tool_calls = false;
                 let mut finish_reason: Option<String> = None;
+                // Refused or filtered turns explain their missing content
+                let mut moderated = false;
 
                 futures::pin_mut!(driver_stream);
 
-                while let Some(result) = driver_stream.next().await {
+                loop {
+                    let next = deadline::within(orchestrator.deadline, driver_stream.next()).await;
+                    let result = match next {
+                        Ok(Some(result)) => result,
+                        Ok(None) => break,
+                        Err(exceeded) => {
+                            tracing::warn!(
+                                request_id = %request_id,
+                                iteration = iteration,
+                                "Run deadline passed while the LLM was responding"
+                            );
+                            yield deadline_error(&exceeded);
+                            return;
+                        }
+                    };
                     match result {
                         Ok(event) => {
                             match &event {
                                 NormalizedEvent::MessageDelta { text } => {
                                     assistant_text.push_str(text);
                                 }
+                                NormalizedEvent::RefusalDelta { .. }
+                                | NormalizedEvent::ContentFilter { .. } => {
+                                    moderated = true;
+                                }
                                 NormalizedEvent::ToolCallDelta {
                                     call_index,
                                     id,
@@ -281,6 +575,10 @@ impl Orchestrator {
                                 NormalizedEvent::Done => {
                                     // Don't yield Done yet if we have tool calls to process
                                     if !has_tool_calls {
+                                        if assistant_text.is_empty() && !moderated {
+                                            // Empty turn: handled below
+                                            break;
+                                        }
                                         yield event;
                                         return;
                                     }
@@ -304,6 +602,40 @@ impl Orchestrator {
                     }
                 }
 
+                // A completed turn with no content and no tool calls is an empty response
+                if !has_tool_calls && assistant_text.is_empty() && !moderated {
+                    if orchestrator.settings.empty_response == EmptyResponsePolicy::Retry && !retried_empty {
+                        tracing::warn!(
+                            request_id = %request_id,
+                            iteration = iteration,
+                            "LLM returned an empty response, retrying once"
+                        );
+                        retried_empty = true;
+                        continue;
+                    }
+                    if run_retries_left > 0 {
+                        tracing::warn!(
+                            request_id = %request_id,
+                            iteration = iteration,
+                            "LLM returned an empty response, retrying the run with a correction"
+                        );
+                        run_retries_left -= 1;
+                        message_json.push(correction_message(EMPTY_OUTPUT_CORRECTION));
+                        continue;
+                    }
+
+                    tracing::error!(
+                        request_id = %request_id,
+                        iteration = iteration,
+                        "LLM returned an empty response"
+                    );
+                    yield NormalizedEvent::Error {
+                        message: "The model returned an empty response".to_string(),
+                        code: Some("EMPTY_RESPONSE".to_string()),
+                    };
+                    break;
+                }
+
                 // If no tool calls, we're done
                 if !has_tool_calls || finish_reason.as_deref() != Some("tool_calls") {
                     tracing::info!(
@@ -402,8 +734,111 @@ impl Orchestrator {
                 // Execute each tool call and emit results
                 for (idx, tool_call) in tool_calls.iter().enumerate() {
                     let tool_name = &tool_call.function.name;
-                    let arguments: serde_json::Value = serde_json::from_str(&tool_call.function.arguments)
-                        .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
+                    if let Some(max) = orchestrator.max_tool_calls_per_turn
+                        && idx >= max
+                    {
+                        tracing::warn!(
+                            request_id = %request_id,
+                            iteration = iteration,
+                            tool_id = %tool_call.id,
+                            tool_name = %tool_name,
+                            max_tool_calls = max,
+                            "Tool call over the per-turn limit skipped"
+                        );
+                        let content = skipped_tool_call_result(max);
+                        yield NormalizedEvent::ToolResult {
+                            id: tool_call.id.clone(),
+                            name: tool_name.clone(),
+                            content: content.clone(),
+                            success: false,
+                        };
+                        message_json.push(serde_json::json!({
+                            "role": "tool",
+                            "tool_call_id": tool_call.id,
+                            "content": content
+                        }));
+                        continue;
+                    }
+                    let schema = orchestrator.mcp.tool_schema(tool_name);
+                    let arguments = match validate_tool_arguments(tool_name, &tool_call.function.arguments, schema.as_ref()) {
+                        Ok(arguments) => arguments,
+                        Err(e) => {
+                            // Let the model correct the call instead of running it with garbage
+                            tracing::warn!(
+                                request_id = %request_id,
+                                iteration = iteration,
+                                tool_id = %tool_call.id,
+                                tool_name = %tool_name,
+                                error = %e,
+                                "Tool call arguments rejected"
+                            );
+                            let content = e.to_tool_result();
+                            yield NormalizedEvent::ToolResult {
+                                id: tool_call.id.clone(),
+                                name: tool_name.clone(),
+                                content: content.clone(),
+                                success: false,
+                            };
+                            message_json.push(serde_json::json!({
+                                "role": "tool",
+                                "tool_call_id": tool_call.id,
+                                "content": content
+                            }));
+                            continue;
+                        }
+                    };
+
+                    if orchestrator.mcp.requires_approval(tool_name) {
+                        let approved = match &orchestrator.approval_gate {
+                            Some(gate) => {
+                                let decision = gate.request(&tool_call.id);
+                                tracing::info!(
+                                    request_id = %request_id,
+                                    iteration = iteration,
+                                    tool_id = %tool_call.id,
+                                    tool_name = %tool_name,
+                                    "Tool call waiting for approval"
+                                );
+                                yield NormalizedEvent::ToolApprovalRequest {
+                                    id: tool_call.id.clone(),
+                                    name: tool_name.clone(),
+                                    arguments: arguments.to_string(),
+                                };
+                                let wait = gate.wait(&tool_call.id, decision);
+                                match deadline::within(orchestrator.deadline, wait).await {
+                                    Ok(approved) => approved,
+                                    Err(exceeded) => {
+                                        yield deadline_error(&exceeded);
+                                        return;
+                                    }
+                                }
+                            }
+                            // Nobody can approve the call
+                            None => false,
+                        };
+                        if !approved {
+                            tracing::warn!(
+                                request_id = %request_id,
+                                iteration = iteration,
+                                tool_id = %tool_call.id,
+                                tool_name = %tool_name,
+                                "Tool call denied"
+                            );
+                            let content = TOOL_DENIED_RESULT.to_string();
+                            yield NormalizedEvent::ToolResult {
+                                id: tool_call.id.clone(),
+                                name: tool_name.clone(),
+                                content: content.clone(),
+                                success: false,
+                            };
+                            message_json.push(serde_json::json!({
+                                "role": "tool",
+                                "tool_call_id": tool_call.id,
+                                "content": content
+                            }));
+                            continue;
+                        }
+                    }
 
                     tracing::info!(
                         request_id = %request_id,
@@ -414,8 +849,27 @@ impl Orchestrator {
                         "Executing tool call"
                     );
 
-                    let (content, success) = match orchestrator.mcp.call_namespaced_tool(tool_name, arguments.clone()).await {
-                        Ok(result) => {
+                    let call = orchestrator.mcp.call_namespaced_tool_in_session(tool_name, arguments, orchestrator.session.as_ref());
+                    let (content, success) = match deadline::within(orchestrator.deadline, call).await {
+                        Err(exceeded) => {
+                            // The call was dropped: the model gets no more time to use its result
+                            tracing::warn!(
+                                request_id = %request_id,
+                                iteration = iteration,
+                                tool_id = %tool_call.id,
+                                tool_name = %tool_name,
+                                "Tool call cancelled by the run deadline"
+                            );
+                            yield NormalizedEvent::ToolResult {
+                                id: tool_call.id.clone(),
+                                name: tool_name.clone(),
+                                content: format!("Error: {exceeded}"),
+                                success: false,
+                            };
+                            yield deadline_error(&exceeded);
+                            return;
+                        }
+                        Ok(Ok(result)) => {
                             let content = serde_json::to_string(&result).unwrap_or_default();
                             tracing::info!(
                                 request_id = %request_id,
@@ -433,7 +887,7 @@ impl Orchestrator {
                             );
                             (content, true)
                         }
-                        Err(e) => {
+                        Ok(Err(e)) => {
                             let error_msg = format!("Error: {e}");
                             tracing::error!(
                                 request_id = %request_id,
@@ -476,6 +930,16 @@ impl Orchestrator {
                     "All tool calls executed, continuing to next iteration"
                 );
 
+                if let Some(gate) = &orchestrator.resume_gate {
+                    tracing::info!(
+                        request_id = %request_id,
+                        iteration = iteration,
+                        "Tool loop paused until resumed"
+                    );
+                    yield NormalizedEvent::ToolLoopPaused;
+                    gate.wait().await;
+                }
+
                 // Continue the loop to get the next response
             }
         };
@@ -486,9 +950,16 @@ impl Orchestrator {
     /// Non-streaming chat for simple requests (e.g., title generation).
     ///
     /// This collects all message deltas into a single string response.
-    pub async fn chat_non_streaming(&self, messages: Vec<Message>) -> anyhow::Result<String> {
+    /// With a `response_format`, the answer is checked against it; failures
+    /// are [`StructuredOutputError`]s (retrieve with `downcast_ref`). With
+    /// run retries configured, an empty or invalid answer is asked for again
+    /// once, with a corrective instruction, before giving up.
+    pub async fn chat_non_streaming(
+        &self,
+        messages: Vec<Message>,
+        response_format: Option<ResponseFormat>,
+    ) -> anyhow::Result<String> {
         let request_id = Uuid::new_v4().to_string();
-        let tools = Vec::new(); // No tools for simple requests
 
         tracing::debug!(
             request_id = %request_id,
@@ -496,39 +967,869 @@ impl Orchestrator {
             "Starting non-streaming chat"
         );
 
-        let message_json: Vec<serde_json::Value> = messages
+        let mut message_json: Vec<serde_json::Value> = messages
             .iter()
             .map(|m| serde_json::to_value(m).unwrap_or_default())
             .collect();
+        let mut run_retries_left = self.settings.generation.allowed_run_retries();
+
+        loop {
+            let content = self
+                .collect_non_streaming(&request_id, &message_json, response_format.as_ref())
+                .await?;
+            let invalid = match &response_format {
+                Some(format) => format.validate(&content).err(),
+                None => None,
+            };
+            if let Some(e) = &invalid {
+                tracing::warn!(request_id = %request_id, error = %e, "Structured output rejected");
+            }
+            let correction = match &invalid {
+                _ if content.is_empty() => EMPTY_OUTPUT_CORRECTION.to_string(),
+                Some(e) => invalid_output_correction(e),
+                None => return Ok(content),
+            };
+            if run_retries_left == 0 {
+                return match invalid {
+                    Some(e) => Err(e.into()),
+                    None => Ok(content),
+                };
+            }
+
+            tracing::warn!(request_id = %request_id, "Retrying the run with a correction");
+            run_retries_left -= 1;
+            if !content.is_empty() {
+                message_json.push(serde_json::json!({ "role": "assistant", "content": content }));
+            }
+            message_json.push(correction_message(&correction));
+        }
+    }
 
+    /// Send `messages` once and collect the message deltas of the answer.
+    async fn collect_non_streaming(
+        &self,
+        request_id: &str,
+        messages: &[serde_json::Value],
+        response_format: Option<&ResponseFormat>,
+    ) -> anyhow::Result<String> {
         let req = LlmRequest {
-            messages: message_json,
-            tools,
+            messages: messages.to_vec(),
+            tools: Vec::new(), // No tools for simple requests
+            response_format: response_format.cloned(),
+            tool_choice: None,
         };
 
         // Stream from the driver and collect message deltas
-        let mut stream = self.driver.stream(req).await?;
-        let mut content = String::new();
+        let collect = async {
+            let mut stream = self.driver.stream(req).await?;
+            let mut content = String::new();
 
-        while let Some(event_result) = stream.next().await {
-            match event_result {
-                Ok(NormalizedEvent::MessageDelta { text }) => {
-                    content.push_str(&text);
-                }
-                Err(e) => {
-                    tracing::error!(request_id = %request_id, error = %e, "Error in stream");
-                    return Err(e);
+            while let Some(event_result) = stream.next().await {
+                match event_result {
+                    Ok(NormalizedEvent::MessageDelta { text }) => {
+                        content.push_str(&text);
+                    }
+                    Err(e) => {
+                        tracing::error!(request_id = %request_id, error = %e, "Error in stream");
+                        return Err(e);
+                    }
+                    _ => {} // Ignore other events
                 }
-                _ => {} // Ignore other events
             }
-        }
+            anyhow::Ok(content)
+        };
+        let content = deadline::within(self.deadline, collect).await??;
 
         tracing::debug!(
             request_id = %request_id,
             content_length = content.len(),
             "Non-streaming chat completed"
         );
-
         Ok(content)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::testing::llm_settings;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    /// Driver that replays scripted event sequences, one per call.
+    struct ScriptedDriver {
+        turns: Vec<Vec<NormalizedEvent>>,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmDriver for ScriptedDriver {
+        async fn stream(
+            &self,
+            _req: LlmRequest,
+        ) -> anyhow::Result<
+            std::pin::Pin<Box<dyn Stream<Item = anyhow::Result<NormalizedEvent>> + Send>>,
+        > {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let events = self.turns.get(call).cloned().unwrap_or_default();
+            Ok(Box::pin(futures::stream::iter(events.into_iter().map(Ok))))
+        }
+    }
+
+    /// Scripted driver keeping the messages of every request.
+    struct RecordingDriver {
+        script: ScriptedDriver,
+        requests: Mutex<Vec<Vec<serde_json::Value>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmDriver for RecordingDriver {
+        async fn stream(
+            &self,
+            req: LlmRequest,
+        ) -> anyhow::Result<
+            std::pin::Pin<Box<dyn Stream<Item = anyhow::Result<NormalizedEvent>> + Send>>,
+        > {
+            self.requests.lock().unwrap().push(req.messages.clone());
+            self.script.stream(req).await
+        }
+    }
+
+    fn recording(turns: Vec<Vec<NormalizedEvent>>) -> Arc<RecordingDriver> {
+        Arc::new(RecordingDriver {
+            script: ScriptedDriver {
+                turns,
+                calls: AtomicUsize::new(0),
+            },
+            requests: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn retrying_settings() -> LlmSettings {
+        let mut settings = settings(EmptyResponsePolicy::Error);
+        settings.generation.run_retries = Some(1);
+        settings
+    }
+
+    fn settings(empty_response: EmptyResponsePolicy) -> LlmSettings {
+        LlmSettings {
+            empty_response,
+            ..llm_settings("http://localhost", "test-model")
+        }
+    }
+
+    async fn run(
+        policy: EmptyResponsePolicy,
+        turns: Vec<Vec<NormalizedEvent>>,
+    ) -> (Vec<NormalizedEvent>, usize) {
+        let driver = Arc::new(ScriptedDriver {
+            turns,
+            calls: AtomicUsize::new(0),
+        });
+        let orchestrator = Orchestrator::with_driver(
+            settings(policy),
+            Arc::new(McpRegistry::new_empty()),
+            driver.clone(),
+        );
+        let events: Vec<NormalizedEvent> = orchestrator.chat("hi").await.unwrap().collect().await;
+        (events, driver.calls.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn test_empty_response_emits_error() {
+        let (events, calls) = run(
+            EmptyResponsePolicy::Error,
+            vec![vec![NormalizedEvent::Done]],
+        )
+        .await;
+
+        assert_eq!(calls, 1);
+        assert!(matches!(events[0], NormalizedEvent::StreamStart { .. }));
+        assert!(matches!(
+            events.last(),
+            Some(NormalizedEvent::Error { code: Some(code), .. }) if code == "EMPTY_RESPONSE"
+        ));
+        assert!(!events.contains(&NormalizedEvent::Done));
+    }
+
+    #[tokio::test]
+    async fn test_empty_response_retries_once() {
+        let (events, calls) = run(
+            EmptyResponsePolicy::Retry,
+            vec![
+                vec![NormalizedEvent::Done],
+                vec![
+                    NormalizedEvent::MessageDelta {
+                        text: "hello".to_string(),
+                    },
+                    NormalizedEvent::Done,
+                ],
+            ],
+        )
+        .await;
+
+        assert_eq!(calls, 2);
+        assert!(events.contains(&NormalizedEvent::MessageDelta {
+            text: "hello".to_string()
+        }));
+        assert_eq!(events.last(), Some(&NormalizedEvent::Done));
+    }
+
+    #[tokio::test]
+    async fn test_refusal_is_not_an_empty_response() {
+        let refusal = NormalizedEvent::RefusalDelta {
+            text: "I can't help with that.".to_string(),
+        };
+        let (events, calls) = run(
+            EmptyResponsePolicy::Retry,
+            vec![vec![refusal.clone(), NormalizedEvent::Done]],
+        )
+        .await;
+
+        assert_eq!(calls, 1);
+        assert!(events.contains(&refusal));
+        assert_eq!(events.last(), Some(&NormalizedEvent::Done));
+    }
+
+    /// Native tool counting its calls within a session.
+    #[derive(Debug)]
+    struct CounterTool;
+
+    #[async_trait::async_trait]
+    impl crate::mcp::registry::NativeTool for CounterTool {
+        fn name(&self) -> &str {
+            "counter"
+        }
+
+        fn description(&self) -> &str {
+            "Counts calls"
+        }
+
+        fn schema(&self) -> serde_json::Value {
+            serde_json::json!({ "type": "object", "properties": {} })
+        }
+
+        async fn call(&self, _args: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+            anyhow::bail!("counter needs session state")
+        }
+
+        async fn call_with_state(
+            &self,
+            _args: serde_json::Value,
+            state: crate::session::ToolStateHandle,
+        ) -> anyhow::Result<serde_json::Value> {
+            let count = state.get().and_then(|v| v.as_u64()).unwrap_or(0) + 1;
+            state.set(serde_json::json!(count));
+            Ok(serde_json::json!({ "count": count }))
+        }
+    }
+
+    fn counter_call_turns() -> Vec<Vec<NormalizedEvent>> {
+        vec![
+            vec![
+                NormalizedEvent::ToolCallDelta {
+                    call_index: 0,
+                    id: Some("call_1".to_string()),
+                    name: Some("native__counter".to_string()),
+                    arguments_delta: Some("{}".to_string()),
+                },
+                NormalizedEvent::ToolCallComplete {
+                    call_index: 0,
+                    id: "call_1".to_string(),
+                    name: "native__counter".to_string(),
+                    arguments_json: "{}".to_string(),
+                },
+                NormalizedEvent::Done,
+            ],
+            vec![
+                NormalizedEvent::MessageDelta {
+                    text: "ok".to_string(),
+                },
+                NormalizedEvent::Done,
+            ],
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_native_tool_state_persists_across_turns() {
+        let mcp = Arc::new(McpRegistry::new_empty().with_native_tool(Arc::new(CounterTool)));
+        let session = crate::session::SessionStore::new().create();
+
+        let mut results = Vec::new();
+        for _turn in 0..2 {
+            let driver = Arc::new(ScriptedDriver {
+                turns: counter_call_turns(),
+                calls: AtomicUsize::new(0),
+            });
+            let orchestrator = Orchestrator::with_driver(
+                settings(EmptyResponsePolicy::Error),
+                Arc::clone(&mcp),
+                driver,
+            )
+            .with_session(session.clone());
+            let events: Vec<NormalizedEvent> =
+                orchestrator.chat("count").await.unwrap().collect().await;
+            results.extend(events.into_iter().filter_map(|e| match e {
+                NormalizedEvent::ToolResult {
+                    content, success, ..
+                } => Some((content, success)),
+                _ => None,
+            }));
+        }
+
+        // The second turn saw the count written by the first
+        assert_eq!(
+            results,
+            vec![
+                (r#"{"count":1}"#.to_string(), true),
+                (r#"{"count":2}"#.to_string(), true),
+            ]
+        );
+        assert_eq!(session.get_tool_state("counter"), Some(serde_json::json!(2)));
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_over_the_turn_limit_are_skipped() {
+        let mcp = Arc::new(McpRegistry::new_empty().with_native_tool(Arc::new(CounterTool)));
+        let session = crate::session::SessionStore::new().create();
+        let mut fan_out = Vec::new();
+        for i in 0..20 {
+            fan_out.push(NormalizedEvent::ToolCallDelta {
+                call_index: i,
+                id: Some(format!("call_{i}")),
+                name: Some("native__counter".to_string()),
+                arguments_delta: Some("{}".to_string()),
+            });
+            fan_out.push(NormalizedEvent::ToolCallComplete {
+                call_index: i,
+                id: format!("call_{i}"),
+                name: "native__counter".to_string(),
+                arguments_json: "{}".to_string(),
+            });
+        }
+        fan_out.push(NormalizedEvent::Done);
+        let mut turns = counter_call_turns();
+        turns[0] = fan_out;
+        let driver = Arc::new(ScriptedDriver {
+            turns,
+            calls: AtomicUsize::new(0),
+        });
+        let orchestrator =
+            Orchestrator::with_driver(settings(EmptyResponsePolicy::Error), mcp, driver)
+                .with_session(session.clone())
+                .with_max_tool_calls_per_turn(5);
+
+        let events: Vec<NormalizedEvent> =
+            orchestrator.chat("count").await.unwrap().collect().await;
+        let results: Vec<(String, bool)> = events
+            .into_iter()
+            .filter_map(|e| match e {
+                NormalizedEvent::ToolResult {
+                    content, success, ..
+                } => Some((content, success)),
+                _ => None,
+            })
+            .collect();
+
+        // Every call is answered, but only the first 5 ran
+        assert_eq!(results.len(), 20);
+        assert!(results[..5].iter().all(|(_, success)| *success));
+        assert!(results[5..].iter().all(|(content, success)| {
+            !success && content.contains("too_many_tool_calls")
+        }));
+        assert_eq!(session.get_tool_state("counter"), Some(serde_json::json!(5)));
+    }
+
+    #[tokio::test]
+    async fn test_interactive_run_pauses_until_resumed() {
+        let mcp = Arc::new(McpRegistry::new_empty().with_native_tool(Arc::new(CounterTool)));
+        let driver = Arc::new(ScriptedDriver {
+            turns: counter_call_turns(),
+            calls: AtomicUsize::new(0),
+        });
+        let gate = Arc::new(ResumeGate::new());
+        let orchestrator = Orchestrator::with_driver(
+            settings(EmptyResponsePolicy::Error),
+            mcp,
+            Arc::clone(&driver) as Arc<dyn LlmDriver>,
+        )
+        .with_session(crate::session::SessionStore::new().create())
+        .with_resume_gate(Arc::clone(&gate));
+        let stream = orchestrator.chat("count").await.unwrap();
+        futures::pin_mut!(stream);
+
+        let mut before_pause = Vec::new();
+        while let Some(event) = stream.next().await {
+            if event == NormalizedEvent::ToolLoopPaused {
+                break;
+            }
+            before_pause.push(event);
+        }
+        assert!(matches!(
+            before_pause.last(),
+            Some(NormalizedEvent::ToolResult { success: true, .. })
+        ));
+        assert!(gate.is_paused());
+
+        // Nothing is sent to the model until the client resumes
+        let waited = tokio::time::timeout(Duration::from_millis(50), stream.next()).await;
+        assert!(waited.is_err());
+        assert_eq!(driver.calls.load(Ordering::SeqCst), 1);
+
+        assert!(gate.resume());
+        assert!(!gate.resume());
+        let rest: Vec<NormalizedEvent> = stream.collect().await;
+        assert_eq!(driver.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(rest.last(), Some(&NormalizedEvent::Done));
+    }
+
+    #[tokio::test]
+    async fn test_non_streaming_rejects_invalid_structured_output() {
+        let format = ResponseFormat::JsonSchema {
+            name: "title".to_string(),
+            schema: serde_json::json!({
+                "type": "object",
+                "properties": { "title": { "type": "string" } },
+                "required": ["title"]
+            }),
+            strict: true,
+        };
+        let answer = |text: &str| {
+            vec![vec![
+                NormalizedEvent::MessageDelta {
+                    text: text.to_string(),
+                },
+                NormalizedEvent::Done,
+            ]]
+        };
+        let orchestrator = |turns| {
+            Orchestrator::with_driver(
+                settings(EmptyResponsePolicy::Error),
+                Arc::new(McpRegistry::new_empty()),
+                Arc::new(ScriptedDriver {
+                    turns,
+                    calls: AtomicUsize::new(0),
+                }),
+            )
+        };
+        let user = vec![Message {
+            role: MessageRole::User,
+            content: MessageContent::text("Title this"),
+            tool_call_id: None,
+            tool_calls: None,
+        }];
+
+        let ok = orchestrator(answer(r#"{"title":"Trip"}"#))
+            .chat_non_streaming(user.clone(), Some(format.clone()))
+            .await
+            .unwrap();
+        assert_eq!(ok, r#"{"title":"Trip"}"#);
+
+        let err = orchestrator(answer("Trip"))
+            .chat_non_streaming(user, Some(format))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StructuredOutputError>(),
+            Some(StructuredOutputError::InvalidJson(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_empty_run_is_retried_with_a_correction() {
+        let hello = NormalizedEvent::MessageDelta {
+            text: "hello".to_string(),
+        };
+        let driver = recording(vec![
+            vec![NormalizedEvent::Done],
+            vec![hello.clone(), NormalizedEvent::Done],
+        ]);
+        let orchestrator = Orchestrator::with_driver(
+            retrying_settings(),
+            Arc::new(McpRegistry::new_empty()),
+            driver.clone(),
+        );
+        let events: Vec<NormalizedEvent> = orchestrator.chat("hi").await.unwrap().collect().await;
+
+        assert!(events.contains(&hello));
+        assert_eq!(events.last(), Some(&NormalizedEvent::Done));
+        let requests = driver.requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[1].last(),
+            Some(&correction_message(EMPTY_OUTPUT_CORRECTION))
+        );
+
+        // A single retry: a second empty run fails
+        let driver = recording(vec![vec![NormalizedEvent::Done]; 3]);
+        let orchestrator = Orchestrator::with_driver(
+            retrying_settings(),
+            Arc::new(McpRegistry::new_empty()),
+            driver.clone(),
+        );
+        let events: Vec<NormalizedEvent> = orchestrator.chat("hi").await.unwrap().collect().await;
+        assert_eq!(driver.requests.lock().unwrap().len(), 2);
+        assert!(matches!(
+            events.last(),
+            Some(NormalizedEvent::Error { code: Some(code), .. }) if code == "EMPTY_RESPONSE"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_structured_output_is_retried() {
+        let format = ResponseFormat::JsonObject;
+        let answer = |text: &str| {
+            vec![
+                NormalizedEvent::MessageDelta {
+                    text: text.to_string(),
+                },
+                NormalizedEvent::Done,
+            ]
+        };
+        let driver = recording(vec![answer("Trip"), answer(r#"{"title":"Trip"}"#)]);
+        let orchestrator = Orchestrator::with_driver(
+            retrying_settings(),
+            Arc::new(McpRegistry::new_empty()),
+            driver.clone(),
+        );
+        let user = vec![Message {
+            role: MessageRole::User,
+            content: MessageContent::text("Title this"),
+            tool_call_id: None,
+            tool_calls: None,
+        }];
+
+        let content = orchestrator
+            .chat_non_streaming(user, Some(format))
+            .await
+            .unwrap();
+        assert_eq!(content, r#"{"title":"Trip"}"#);
+        let requests = driver.requests.lock().unwrap().clone();
+        let retried = &requests[1];
+        assert_eq!(retried.len(), 3);
+        assert_eq!(
+            retried[1],
+            serde_json::json!({ "role": "assistant", "content": "Trip" })
+        );
+        assert!(retried[2]["content"].as_str().unwrap().contains("invalid"));
+    }
+
+    #[tokio::test]
+    async fn test_empty_response_retry_gives_up() {
+        let (events, calls) = run(
+            EmptyResponsePolicy::Retry,
+            vec![vec![NormalizedEvent::Done], vec![NormalizedEvent::Done]],
+        )
+        .await;
+
+        assert_eq!(calls, 2);
+        assert!(matches!(
+            events.last(),
+            Some(NormalizedEvent::Error { code: Some(code), .. }) if code == "EMPTY_RESPONSE"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_tool_call_is_rejected() {
+        let mut policy = crate::uar::defaults::default_agent().policy.tools;
+        policy.allow = vec!["mirror".to_string()];
+        let mcp = McpRegistry::new_with_test_tool("search", "Search")
+            .with_tool_filter(move |name| policy.permits(name));
+        assert!(mcp.openai_tools_json().is_empty());
+
+        let call = |name: &str| {
+            vec![
+                NormalizedEvent::ToolCallDelta {
+                    call_index: 0,
+                    id: Some("call_1".to_string()),
+                    name: Some(name.to_string()),
+                    arguments_delta: Some("{}".to_string()),
+                },
+                NormalizedEvent::ToolCallComplete {
+                    call_index: 0,
+                    id: "call_1".to_string(),
+                    name: name.to_string(),
+                    arguments_json: "{}".to_string(),
+                },
+                NormalizedEvent::Done,
+            ]
+        };
+        let answer = vec![
+            NormalizedEvent::MessageDelta {
+                text: "ok".to_string(),
+            },
+            NormalizedEvent::Done,
+        ];
+        let driver = Arc::new(ScriptedDriver {
+            turns: vec![call("tavily__search"), answer],
+            calls: AtomicUsize::new(0),
+        });
+        let orchestrator =
+            Orchestrator::with_driver(settings(EmptyResponsePolicy::Error), Arc::new(mcp), driver);
+        let events: Vec<NormalizedEvent> =
+            orchestrator.chat("search").await.unwrap().collect().await;
+
+        let result = events.iter().find_map(|e| match e {
+            NormalizedEvent::ToolResult {
+                content, success, ..
+            } => Some((content.as_str(), *success)),
+            _ => None,
+        });
+        assert_eq!(
+            result,
+            Some((
+                "Error: tool 'tavily__search' is not allowed for this agent",
+                false
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_schema_violating_arguments_are_sent_back() {
+        let mcp = McpRegistry::new_with_test_tool("mirror", "Mirror");
+        let call = vec![
+            NormalizedEvent::ToolCallDelta {
+                call_index: 0,
+                id: Some("call_1".to_string()),
+                name: Some("test__mirror".to_string()),
+                arguments_delta: Some(r#"{"mirror": 42}"#.to_string()),
+            },
+            NormalizedEvent::ToolCallComplete {
+                call_index: 0,
+                id: "call_1".to_string(),
+                name: "test__mirror".to_string(),
+                arguments_json: r#"{"mirror": 42}"#.to_string(),
+            },
+            NormalizedEvent::Done,
+        ];
+        let answer = vec![
+            NormalizedEvent::MessageDelta {
+                text: "ok".to_string(),
+            },
+            NormalizedEvent::Done,
+        ];
+        let driver = Arc::new(ScriptedDriver {
+            turns: vec![call, answer],
+            calls: AtomicUsize::new(0),
+        });
+        let orchestrator =
+            Orchestrator::with_driver(settings(EmptyResponsePolicy::Error), Arc::new(mcp), driver);
+        let events: Vec<NormalizedEvent> =
+            orchestrator.chat("mirror").await.unwrap().collect().await;
+
+        let (content, success) = events
+            .iter()
+            .find_map(|e| match e {
+                NormalizedEvent::ToolResult {
+                    content, success, ..
+                } => Some((content.clone(), *success)),
+                _ => None,
+            })
+            .unwrap();
+        assert!(!success);
+        let result: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(result["error"], "invalid_arguments");
+        assert!(result["errors"][0].as_str().unwrap().starts_with("/mirror"), "{result}");
+    }
+
+    /// A call of the mirror test tool, then a final answer.
+    fn mirror_call_turns() -> Vec<Vec<NormalizedEvent>> {
+        let arguments = r#"{"mirror": "hi"}"#.to_string();
+        let call = vec![
+            NormalizedEvent::ToolCallDelta {
+                call_index: 0,
+                id: Some("call_1".to_string()),
+                name: Some("test__mirror".to_string()),
+                arguments_delta: Some(arguments.clone()),
+            },
+            NormalizedEvent::ToolCallComplete {
+                call_index: 0,
+                id: "call_1".to_string(),
+                name: "test__mirror".to_string(),
+                arguments_json: arguments,
+            },
+            NormalizedEvent::Done,
+        ];
+        let answer = vec![
+            NormalizedEvent::MessageDelta {
+                text: "ok".to_string(),
+            },
+            NormalizedEvent::Done,
+        ];
+        vec![call, answer]
+    }
+
+    fn approval_orchestrator(gate: Option<Arc<ApprovalGate>>) -> Orchestrator {
+        let mcp = McpRegistry::new_with_test_tool("mirror", "Mirror")
+            .with_approval_required("test__mirror");
+        let driver = Arc::new(ScriptedDriver {
+            turns: mirror_call_turns(),
+            calls: AtomicUsize::new(0),
+        });
+        let orchestrator =
+            Orchestrator::with_driver(settings(EmptyResponsePolicy::Error), Arc::new(mcp), driver);
+        match gate {
+            Some(gate) => orchestrator.with_approval_gate(gate),
+            None => orchestrator,
+        }
+    }
+
+    fn tool_result(events: &[NormalizedEvent]) -> (String, bool) {
+        events
+            .iter()
+            .find_map(|e| match e {
+                NormalizedEvent::ToolResult {
+                    content, success, ..
+                } => Some((content.clone(), *success)),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_approved_tool_call_runs() {
+        let gate = Arc::new(ApprovalGate::new(DEFAULT_TOOL_APPROVAL_TIMEOUT));
+        let orchestrator = approval_orchestrator(Some(Arc::clone(&gate)));
+        let stream = orchestrator.chat("mirror").await.unwrap();
+        futures::pin_mut!(stream);
+
+        while let Some(event) = stream.next().await {
+            if let NormalizedEvent::ToolApprovalRequest {
+                id,
+                name,
+                arguments,
+            } = event
+            {
+                assert_eq!((id.as_str(), name.as_str()), ("call_1", "test__mirror"));
+                assert_eq!(arguments, r#"{"mirror":"hi"}"#);
+                break;
+            }
+            assert!(!matches!(event, NormalizedEvent::ToolResult { .. }));
+        }
+
+        assert!(!gate.decide("call_2", true));
+        assert!(gate.decide("call_1", true));
+        let rest: Vec<NormalizedEvent> = stream.collect().await;
+        let (content, success) = tool_result(&rest);
+        assert!(success);
+        assert!(content.contains("executed test tool mirror"), "{content}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_denied_and_undecided_tool_calls_are_refused() {
+        let gate = Arc::new(ApprovalGate::new(DEFAULT_TOOL_APPROVAL_TIMEOUT));
+        let orchestrator = approval_orchestrator(Some(Arc::clone(&gate)));
+        let stream = orchestrator.chat("mirror").await.unwrap();
+        futures::pin_mut!(stream);
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            if matches!(event, NormalizedEvent::ToolApprovalRequest { .. }) {
+                assert!(gate.decide("call_1", false));
+            }
+            events.push(event);
+        }
+        assert_eq!(tool_result(&events), (TOOL_DENIED_RESULT.to_string(), false));
+        assert_eq!(events.last(), Some(&NormalizedEvent::Done));
+
+        // Nobody answers: denied once the timeout passes
+        let events: Vec<NormalizedEvent> = approval_orchestrator(Some(Arc::clone(&gate)))
+            .chat("mirror")
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(tool_result(&events), (TOOL_DENIED_RESULT.to_string(), false));
+        assert!(!gate.decide("call_1", true));
+
+        // Without a gate nobody can approve
+        let events: Vec<NormalizedEvent> =
+            approval_orchestrator(None).chat("mirror").await.unwrap().collect().await;
+        assert!(!events.iter().any(|e| matches!(e, NormalizedEvent::ToolApprovalRequest { .. })));
+        assert_eq!(tool_result(&events), (TOOL_DENIED_RESULT.to_string(), false));
+    }
+
+    /// Native tool that takes a minute, noting whether it got to finish.
+    #[derive(Debug, Default)]
+    struct SlowTool {
+        finished: Arc<AtomicBool>,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::mcp::registry::NativeTool for SlowTool {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn description(&self) -> &str {
+            "Takes a minute"
+        }
+
+        fn schema(&self) -> serde_json::Value {
+            serde_json::json!({ "type": "object", "properties": {} })
+        }
+
+        async fn call(&self, _args: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            self.finished.store(true, Ordering::SeqCst);
+            Ok(serde_json::json!({ "done": true }))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tool_exceeding_the_run_deadline_is_cancelled() {
+        let tool = Arc::new(SlowTool::default());
+        let finished = Arc::clone(&tool.finished);
+        let mcp = McpRegistry::new_empty().with_native_tool(tool);
+        let call = vec![
+            NormalizedEvent::ToolCallDelta {
+                call_index: 0,
+                id: Some("call_1".to_string()),
+                name: Some("native__slow".to_string()),
+                arguments_delta: Some("{}".to_string()),
+            },
+            NormalizedEvent::ToolCallComplete {
+                call_index: 0,
+                id: "call_1".to_string(),
+                name: "native__slow".to_string(),
+                arguments_json: "{}".to_string(),
+            },
+            NormalizedEvent::Done,
+        ];
+        let answer = vec![
+            NormalizedEvent::MessageDelta {
+                text: "too late".to_string(),
+            },
+            NormalizedEvent::Done,
+        ];
+        let driver = Arc::new(ScriptedDriver {
+            turns: vec![call, answer],
+            calls: AtomicUsize::new(0),
+        });
+        let orchestrator = Orchestrator::with_driver(
+            settings(EmptyResponsePolicy::Error),
+            Arc::new(mcp),
+            driver.clone(),
+        )
+        .with_deadline(Deadline::after(Duration::from_secs(5)));
+
+        let started = tokio::time::Instant::now();
+        let events: Vec<NormalizedEvent> = orchestrator.chat("slow").await.unwrap().collect().await;
+        assert_eq!(started.elapsed(), Duration::from_secs(5));
+
+        let result = events.iter().find_map(|e| match e {
+            NormalizedEvent::ToolResult {
+                content, success, ..
+            } => Some((content.as_str(), *success)),
+            _ => None,
+        });
+        assert_eq!(result, Some(("Error: Run deadline of 5s exceeded", false)));
+        assert!(matches!(
+            events.last(),
+            Some(NormalizedEvent::Error { code: Some(code), .. }) if code == "DEADLINE_EXCEEDED"
+        ));
+        // The model was not asked again, and the tool never finished
+        assert_eq!(driver.calls.load(Ordering::SeqCst), 1);
+        tokio::time::sleep(Duration::from_secs(120)).await;
+        assert!(!finished.load(Ordering::SeqCst));
+    }
+}
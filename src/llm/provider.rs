@@ -2,6 +2,15 @@
 //!
 //! This module handles differences between LLM API providers, including
 //! URL patterns, authentication, and feature support.
+//!
+//! The provider is detected from the base URL unless it is named explicitly
+//! (`LLM_PROVIDER`), which gateways on custom domains need; see
+//! [`Provider::resolve`].
+
+use std::str::FromStr;
+
+/// Azure `OpenAI` API version used unless one is configured.
+const DEFAULT_AZURE_API_VERSION: &str = "2024-08-01-preview";
 
 /// Supported LLM providers.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -21,11 +30,37 @@ pub enum Provider {
     TogetherAI,
     /// Groq (groq.com)
     Groq,
+    /// AWS Bedrock (`bedrock-runtime.<region>.amazonaws.com`), signed with `SigV4`
+    Bedrock {
+        /// AWS region; empty to use the region of the default AWS config
+        region: String,
+        /// Bedrock model ID (e.g., "anthropic.claude-3-5-sonnet-20240620-v1:0")
+        model_id: String,
+    },
+    /// OpenAI-compatible gateway named explicitly (`LiteLLM`, vLLM, ...),
+    /// called at `/v1/chat/completions` with bearer auth
+    OpenAICompatible,
     /// Generic OpenAI-compatible provider
     Generic,
 }
 
 impl Provider {
+    /// Provider of `base_url`: `forced` if set, otherwise the one detected
+    /// from the URL.
+    ///
+    /// A forced Bedrock provider still takes its region from the URL.
+    #[must_use]
+    pub fn resolve(base_url: &str, forced: Option<Self>) -> Self {
+        match forced {
+            Some(Self::Bedrock { region, model_id }) if region.is_empty() => Self::Bedrock {
+                region: bedrock_region(&base_url.to_lowercase()),
+                model_id,
+            },
+            Some(provider) => provider,
+            None => Self::detect_from_url(base_url),
+        }
+    }
+
     /// Detect provider from base URL.
     ///
     /// # Example
@@ -41,7 +76,12 @@ impl Provider {
         if lower.contains("azure.com") || lower.contains("openai.azure.com") {
             Self::AzureOpenAI {
                 deployment_name: String::new(),
-                api_version: "2024-08-01-preview".to_string(),
+                api_version: DEFAULT_AZURE_API_VERSION.to_string(),
+            }
+        } else if lower.contains("bedrock.amazonaws.com") || lower.contains("bedrock-runtime.") {
+            Self::Bedrock {
+                region: bedrock_region(&lower),
+                model_id: String::new(),
             }
         } else if lower.contains("openrouter.ai") {
             Self::OpenRouter
@@ -56,12 +96,43 @@ impl Provider {
         }
     }
 
+    /// Name of this provider in agent and alias configuration.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::OpenAI => "openai",
+            Self::AzureOpenAI { .. } => "azure",
+            Self::OpenRouter => "openrouter",
+            Self::TogetherAI => "together",
+            Self::Groq => "groq",
+            Self::Bedrock { .. } => "bedrock",
+            Self::OpenAICompatible => "openai_compatible",
+            Self::Generic => "generic",
+        }
+    }
+
     /// Check if this provider supports parallel tool calls.
     #[must_use]
     pub fn supports_parallel_tools(&self) -> bool {
         match self {
-            Self::OpenAI | Self::AzureOpenAI { .. } | Self::Groq => true,
-            Self::OpenRouter | Self::TogetherAI | Self::Generic => true, // Most do, but model-dependent
+            Self::OpenAI | Self::AzureOpenAI { .. } | Self::Groq | Self::Bedrock { .. } => true,
+            // Most do, but model-dependent
+            Self::OpenRouter | Self::TogetherAI | Self::OpenAICompatible | Self::Generic => true,
+        }
+    }
+
+    /// Check if this provider accepts `tool_choice: "required"`.
+    ///
+    /// Every provider here accepts `auto`, `none` and a named function.
+    #[must_use]
+    pub fn supports_required_tool_choice(&self) -> bool {
+        match self {
+            Self::OpenAI
+            | Self::AzureOpenAI { .. }
+            | Self::Groq
+            | Self::OpenRouter
+            | Self::Bedrock { .. } => true,
+            Self::TogetherAI | Self::OpenAICompatible | Self::Generic => false,
         }
     }
 
@@ -154,6 +225,48 @@ impl Provider {
     }
 }
 
+impl FromStr for Provider {
+    type Err = String;
+
+    /// Parse a provider [name](Provider::name). Azure gets the default API
+    /// version; Bedrock's region and model come from the other settings.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "openai" => Ok(Self::OpenAI),
+            "azure" => Ok(Self::AzureOpenAI {
+                deployment_name: String::new(),
+                api_version: DEFAULT_AZURE_API_VERSION.to_string(),
+            }),
+            "openrouter" => Ok(Self::OpenRouter),
+            "together" => Ok(Self::TogetherAI),
+            "groq" => Ok(Self::Groq),
+            "bedrock" => Ok(Self::Bedrock {
+                region: String::new(),
+                model_id: String::new(),
+            }),
+            "openai_compatible" => Ok(Self::OpenAICompatible),
+            "generic" => Ok(Self::Generic),
+            other => Err(format!(
+                "invalid provider '{other}' (expected openai, azure, openrouter, together, groq, \
+                 bedrock, openai_compatible or generic)"
+            )),
+        }
+    }
+}
+
+/// Region from a Bedrock host such as `bedrock-runtime.us-east-1.amazonaws.com`.
+fn bedrock_region(url: &str) -> String {
+    let host = url.split("://").last().unwrap_or(url);
+    let host = host.split(['/', ':']).next().unwrap_or(host);
+    let mut labels = host.split('.');
+    labels
+        .position(|label| label.starts_with("bedrock"))
+        .and_then(|_| labels.next())
+        .filter(|label| *label != "amazonaws")
+        .unwrap_or_default()
+        .to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -182,6 +295,87 @@ mod tests {
         assert_eq!(provider, Provider::Groq);
     }
 
+    #[test]
+    fn test_detect_bedrock() {
+        let provider = Provider::detect_from_url("https://bedrock-runtime.eu-west-1.amazonaws.com");
+        assert_eq!(
+            provider,
+            Provider::Bedrock {
+                region: "eu-west-1".to_string(),
+                model_id: String::new(),
+            }
+        );
+        let provider = Provider::detect_from_url("https://bedrock.amazonaws.com");
+        assert!(matches!(provider, Provider::Bedrock { region, .. } if region.is_empty()));
+    }
+
+    /// OpenAI-compatible gateways and the provider detected for each.
+    const GATEWAYS: &[(&str, Provider)] = &[
+        ("https://openrouter.ai/api", Provider::OpenRouter),
+        ("https://api.together.xyz", Provider::TogetherAI),
+        ("https://api.groq.com/openai", Provider::Groq),
+        ("https://litellm.internal.example.com", Provider::Generic),
+        ("http://vllm.local:8000", Provider::Generic),
+        ("https://llm-gateway.example.com/openai", Provider::Generic),
+    ];
+
+    #[test]
+    fn test_detect_gateways() {
+        for (url, expected) in GATEWAYS {
+            assert_eq!(&Provider::resolve(url, None), expected, "{url}");
+        }
+    }
+
+    #[test]
+    fn test_override_wins_over_detection() {
+        for (url, _) in GATEWAYS {
+            let provider = Provider::resolve(url, Some(Provider::OpenAICompatible));
+            assert_eq!(provider, Provider::OpenAICompatible, "{url}");
+        }
+
+        // OpenRouter behind a custom domain
+        let forced = "openrouter".parse().unwrap();
+        let provider = Provider::resolve("https://ai.example.com/api", Some(forced));
+        assert_eq!(provider, Provider::OpenRouter);
+        // Or a self-hosted endpoint on a host that looks like OpenAI's
+        let forced = "generic".parse().unwrap();
+        assert_eq!(
+            Provider::resolve("https://openai.com.example.net", Some(forced)),
+            Provider::Generic
+        );
+
+        // A forced Bedrock provider keeps the URL's region
+        let forced = "bedrock".parse().unwrap();
+        let url = "https://bedrock-runtime.us-west-2.amazonaws.com";
+        let provider = Provider::resolve(url, Some(forced));
+        assert!(matches!(provider, Provider::Bedrock { region, .. } if region == "us-west-2"));
+    }
+
+    #[test]
+    fn test_parse_provider_names() {
+        for name in [
+            "openai",
+            "azure",
+            "openrouter",
+            "together",
+            "groq",
+            "bedrock",
+            "openai_compatible",
+            "generic",
+        ] {
+            assert_eq!(name.parse::<Provider>().unwrap().name(), name);
+        }
+        assert_eq!(" OpenAI_Compatible ".parse(), Ok(Provider::OpenAICompatible));
+        let err = "litellm".parse::<Provider>().unwrap_err();
+        assert!(err.contains("invalid provider 'litellm'"), "{err}");
+    }
+
+    #[test]
+    fn test_build_url_openai_compatible() {
+        let url = Provider::OpenAICompatible.build_chat_url("https://litellm.example.com/", "m");
+        assert_eq!(url, "https://litellm.example.com/v1/chat/completions");
+    }
+
     #[test]
     fn test_build_url_openai() {
         let provider = Provider::OpenAI;
@@ -0,0 +1,292 @@
+//! Exact-match cache of LLM responses.
+//!
+//! A deterministic request (temperature 0 or a fixed seed, see
+//! [`GenerationParams::is_deterministic`](super::GenerationParams::is_deterministic))
+//! sent again with the same messages, tools, model and generation
+//! parameters gets the same answer, so it is replayed from memory instead
+//! of calling the provider. Sessions share the cache: the key is the
+//! request, not who sent it. Only streams that completed without an error
+//! are cached. Replays carry no `Usage` event, as they cost no tokens.
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use super::{LlmDriver, LlmRequest, LlmSettings};
+use crate::cache::TtlLruCache;
+use crate::config::ResponseCacheConfig;
+use crate::normalized::NormalizedEvent;
+
+/// Prometheus counter for cache hits.
+const CACHE_HITS_METRIC: &str = "llm_response_cache_hits_total";
+/// Prometheus counter for cache misses.
+const CACHE_MISSES_METRIC: &str = "llm_response_cache_misses_total";
+
+/// Bounded cache of completed LLM response streams, keyed by
+/// [`ResponseCache::key`], that expire after a TTL.
+pub type ResponseCache = TtlLruCache<String, Arc<[NormalizedEvent]>>;
+
+impl ResponseCache {
+    /// Default maximum number of cached responses.
+    pub const DEFAULT_MAX_ENTRIES: usize = 1_000;
+    /// Default time a response is replayed from the cache.
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+
+    pub fn from_config(config: &ResponseCacheConfig) -> Self {
+        Self::new(config.max_entries, Duration::from_secs(config.ttl_secs))
+            .with_metrics(CACHE_HITS_METRIC, CACHE_MISSES_METRIC)
+    }
+
+    /// Hex SHA-256 of everything that shapes the answer to `req`.
+    pub fn key(settings: &LlmSettings, req: &LlmRequest) -> String {
+        let request = json!({
+            "base_url": settings.base_url,
+            "model": settings.model,
+            "protocol": format!("{:?}", settings.protocol),
+            "parallel_tool_calls": settings.parallel_tool_calls,
+            "generation": settings.generation,
+            "messages": req.messages,
+            "tools": req.tools,
+            "response_format": req.response_format,
+            "tool_choice": req.tool_choice.as_ref().map(|choice| format!("{choice:?}")),
+        });
+        hex::encode(Sha256::digest(request.to_string().as_bytes()))
+    }
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_ENTRIES, Self::DEFAULT_TTL)
+            .with_metrics(CACHE_HITS_METRIC, CACHE_MISSES_METRIC)
+    }
+}
+
+/// Driver decorator answering repeated requests from a [`ResponseCache`].
+///
+/// Wrap only drivers whose settings are deterministic; the decorator caches
+/// whatever it is given.
+pub struct CachingDriver {
+    inner: Arc<dyn LlmDriver>,
+    cache: Arc<ResponseCache>,
+    settings: LlmSettings,
+}
+
+impl std::fmt::Debug for CachingDriver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachingDriver")
+            .field("cache", &self.cache)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CachingDriver {
+    pub fn new(
+        inner: Arc<dyn LlmDriver>,
+        cache: Arc<ResponseCache>,
+        settings: LlmSettings,
+    ) -> Self {
+        Self {
+            inner,
+            cache,
+            settings,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmDriver for CachingDriver {
+    async fn stream(
+        &self,
+        req: LlmRequest,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<NormalizedEvent>> + Send>>> {
+        let key = ResponseCache::key(&self.settings, &req);
+        if let Some(events) = self.cache.get(&key) {
+            tracing::debug!(model = %self.settings.model, "Replaying cached LLM response");
+            let events = events.to_vec();
+            return Ok(Box::pin(futures::stream::iter(events.into_iter().map(Ok))));
+        }
+
+        let mut inner = self.inner.stream(req).await?;
+        let cache = Arc::clone(&self.cache);
+        Ok(Box::pin(async_stream::stream! {
+            let mut recorded = Vec::new();
+            let mut complete = false;
+            let mut failed = false;
+            while let Some(item) = inner.next().await {
+                match &item {
+                    Ok(NormalizedEvent::Usage { .. }) => {}
+                    Ok(NormalizedEvent::Error { .. }) | Err(_) => failed = true,
+                    Ok(event) => {
+                        complete |= matches!(event, NormalizedEvent::Done);
+                        recorded.push(event.clone());
+                    }
+                }
+                yield item;
+            }
+            if complete && !failed {
+                cache.insert(key, recorded.into());
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{GenerationParams, Orchestrator};
+    use crate::mcp::registry::McpRegistry;
+    use crate::uar::testing::llm_settings;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Answers every request the same way, counting the calls.
+    #[derive(Default)]
+    struct CountingDriver {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmDriver for CountingDriver {
+        async fn stream(
+            &self,
+            _req: LlmRequest,
+        ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<NormalizedEvent>> + Send>>>
+        {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let events = vec![
+                NormalizedEvent::MessageDelta {
+                    text: "Four".to_string(),
+                },
+                NormalizedEvent::Usage {
+                    prompt_tokens: 10,
+                    completion_tokens: 1,
+                    total_tokens: 11,
+                },
+                NormalizedEvent::Done,
+            ];
+            Ok(Box::pin(futures::stream::iter(events.into_iter().map(Ok))))
+        }
+    }
+
+    fn settings(temperature: f64) -> LlmSettings {
+        LlmSettings {
+            generation: GenerationParams {
+                temperature: Some(temperature),
+                ..GenerationParams::default()
+            },
+            ..llm_settings("http://localhost", "test-model")
+        }
+    }
+
+    async fn run(
+        driver: &Arc<CountingDriver>,
+        cache: &Arc<ResponseCache>,
+        settings: LlmSettings,
+        prompt: &str,
+    ) -> Vec<NormalizedEvent> {
+        let orchestrator = Orchestrator::with_driver(
+            settings,
+            Arc::new(McpRegistry::new_empty()),
+            Arc::clone(driver) as Arc<dyn LlmDriver>,
+        )
+        .with_response_cache(Arc::clone(cache));
+        // Every stream starts with its own request ID
+        orchestrator
+            .chat(prompt)
+            .await
+            .unwrap()
+            .filter(|event| {
+                let per_request = matches!(
+                    event,
+                    NormalizedEvent::StreamStart { .. } | NormalizedEvent::Usage { .. }
+                );
+                futures::future::ready(!per_request)
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_identical_deterministic_runs_call_the_llm_once() {
+        let driver = Arc::new(CountingDriver::default());
+        let cache = Arc::new(ResponseCache::default());
+
+        let first = run(&driver, &cache, settings(0.0), "What is 2 + 2?").await;
+        let second = run(&driver, &cache, settings(0.0), "What is 2 + 2?").await;
+        assert_eq!(driver.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first, second);
+        assert!(first.contains(&NormalizedEvent::MessageDelta {
+            text: "Four".to_string()
+        }));
+
+        // Another prompt or other parameters are other requests
+        run(&driver, &cache, settings(0.0), "What is 3 + 3?").await;
+        let mut seeded = settings(0.5);
+        seeded.generation.seed = Some(1);
+        run(&driver, &cache, seeded, "What is 2 + 2?").await;
+        assert_eq!(driver.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_sampled_runs_are_not_cached() {
+        let driver = Arc::new(CountingDriver::default());
+        let cache = Arc::new(ResponseCache::default());
+
+        run(&driver, &cache, settings(0.7), "Write a poem").await;
+        run(&driver, &cache, settings(0.7), "Write a poem").await;
+        assert_eq!(driver.calls.load(Ordering::SeqCst), 2);
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_replays_carry_no_usage_and_failures_are_not_cached() {
+        let cache = Arc::new(ResponseCache::default());
+        let driver: Arc<dyn LlmDriver> = Arc::new(CountingDriver::default());
+        let caching = CachingDriver::new(driver, Arc::clone(&cache), settings(0.0));
+        let request = || LlmRequest {
+            messages: vec![json!({ "role": "user", "content": "Hi" })],
+            tools: vec![],
+            response_format: None,
+            tool_choice: None,
+        };
+
+        let live: Vec<_> = caching.stream(request()).await.unwrap().collect().await;
+        assert_eq!(live.len(), 3);
+        let replayed: Vec<_> = caching.stream(request()).await.unwrap().collect().await;
+        assert_eq!(replayed.len(), 2);
+        assert!(
+            replayed
+                .iter()
+                .all(|event| !matches!(event, Ok(NormalizedEvent::Usage { .. })))
+        );
+
+        struct FailingDriver;
+
+        #[async_trait::async_trait]
+        impl LlmDriver for FailingDriver {
+            async fn stream(
+                &self,
+                _req: LlmRequest,
+            ) -> anyhow::Result<
+                Pin<Box<dyn Stream<Item = anyhow::Result<NormalizedEvent>> + Send>>,
+            > {
+                let events = vec![
+                    Ok(NormalizedEvent::MessageDelta {
+                        text: "Par".to_string(),
+                    }),
+                    Err(anyhow::anyhow!("connection reset")),
+                ];
+                Ok(Box::pin(futures::stream::iter(events)))
+            }
+        }
+        let cache = Arc::new(ResponseCache::default());
+        let failing =
+            CachingDriver::new(Arc::new(FailingDriver), Arc::clone(&cache), settings(0.0));
+        let _: Vec<_> = failing.stream(request()).await.unwrap().collect().await;
+        assert!(cache.is_empty());
+    }
+}
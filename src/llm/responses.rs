@@ -2,12 +2,20 @@
 //!
 //! This module implements the [`LlmDriver`] trait for the `OpenAI` Responses
 //! API (`/v1/responses`), supporting streaming responses with rich event types.
+//!
+//! Output text, reasoning summaries and function call arguments are streamed
+//! as deltas; each finished `function_call` item becomes a
+//! `ToolCallComplete`, and `response.completed` ends the stream with the
+//! response's usage, like the Chat Completions driver.
+
+use std::collections::BTreeMap;
 
 use futures::{Stream, StreamExt};
+use serde_json::Value;
 
 use crate::normalized::NormalizedEvent;
 
-use super::{LlmDriver, LlmRequest, LlmSettings};
+use super::{LlmDriver, LlmRequest, LlmSettings, UsageFields, usage::frame_usage};
 
 /// Driver for the `OpenAI` Responses API.
 ///
@@ -52,13 +60,28 @@ impl LlmDriver for ResponsesDriver {
             self.settings.base_url.trim_end_matches('/')
         );
 
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "model": self.settings.model,
             "stream": true,
             "input": req.messages,
             "tools": if req.tools.is_empty() { serde_json::Value::Null } else { serde_json::Value::Array(req.tools) }
         });
 
+        if let Some(format) = &req.response_format {
+            format.ensure_supported(&self.settings.provider)?;
+            body["text"] = serde_json::json!({ "format": format.to_responses() });
+        }
+
+        // tool_choice without tools is rejected by the API
+        if let (Some(choice), Some(tools)) = (&req.tool_choice, body["tools"].as_array()) {
+            let choice = choice.effective(&self.settings.provider, tools);
+            body["tool_choice"] = choice.to_responses();
+        }
+
+        self.settings
+            .generation
+            .apply_responses(&self.settings.provider, &mut body)?;
+
         let mut rb = self.http.post(&url).json(&body);
         if let Some(k) = &self.settings.api_key {
             rb = rb.bearer_auth(k);
@@ -125,120 +148,15 @@ impl LlmDriver for ResponsesDriver {
         }
 
         let byte_stream = resp.bytes_stream();
+        let usage_fields = self.settings.usage_fields.clone();
 
         let out = async_stream::try_stream! {
-            let mut buf = Vec::<u8>::new();
-            let mut current_event_name: Option<String> = None;
+            let mut decoder = ResponsesDecoder::new(usage_fields);
 
             futures::pin_mut!(byte_stream);
             while let Some(chunk) = byte_stream.next().await {
-                let chunk = chunk?;
-                buf.extend_from_slice(&chunk);
-
-                while let Some(pos) = find_double_newline(&buf) {
-                    let frame = buf.drain(..pos + 2).collect::<Vec<_>>();
-                    let text = String::from_utf8_lossy(&frame);
-
-                    let mut data_line: Option<String> = None;
-
-                    for line in text.lines() {
-                        let line = line.trim();
-                        if line.starts_with("event:") {
-                            current_event_name = Some(line.trim_start_matches("event:").trim().to_string());
-                        } else if line.starts_with("data:") {
-                            data_line = Some(line.trim_start_matches("data:").trim().to_string());
-                        }
-                    }
-
-                    if let Some(d) = data_line {
-                        if d == "[DONE]" {
-                            yield NormalizedEvent::Done;
-                            continue;
-                        }
-
-                        let v: serde_json::Value = serde_json::from_str(&d)?;
-                        let ev = current_event_name.clone().unwrap_or_default();
-
-                        match ev.as_str() {
-                            // Text output delta
-                            "response.output_text.delta" => {
-                                if let Some(delta) = v.get("delta").and_then(|x| x.as_str())
-                                    && !delta.is_empty() {
-                                        yield NormalizedEvent::MessageDelta { text: delta.to_string() };
-                                    }
-                            }
-
-                            // Thinking delta (for models that expose reasoning)
-                            "response.thinking.delta" => {
-                                if let Some(delta) = v.get("delta").and_then(|x| x.as_str())
-                                    && !delta.is_empty() {
-                                        yield NormalizedEvent::ThinkingDelta { text: delta.to_string() };
-                                    }
-                            }
-
-                            // Reasoning delta
-                            "response.reasoning.delta" => {
-                                if let Some(delta) = v.get("delta").and_then(|x| x.as_str())
-                                    && !delta.is_empty() {
-                                        yield NormalizedEvent::ReasoningDelta { text: delta.to_string() };
-                                    }
-                            }
-
-                            // Tool call events
-                            "response.function_call_arguments.delta" => {
-                                let call_index = v.get("output_index").and_then(serde_json::Value::as_u64).unwrap_or(0) as usize;
-                                let delta = v.get("delta").and_then(|x| x.as_str()).map(ToString::to_string);
-
-                                yield NormalizedEvent::ToolCallDelta {
-                                    call_index,
-                                    id: None,
-                                    name: None,
-                                    arguments_delta: delta,
-                                };
-                            }
-
-                            "response.output_item.added" => {
-                                // Check if this is a function call item
-                                if let Some(item) = v.get("item")
-                                    && item.get("type").and_then(|t| t.as_str()) == Some("function_call") {
-                                        let call_index = v.get("output_index").and_then(serde_json::Value::as_u64).unwrap_or(0) as usize;
-                                        let id = item.get("call_id").and_then(|x| x.as_str()).map(ToString::to_string);
-                                        let name = item.get("name").and_then(|x| x.as_str()).map(ToString::to_string);
-
-                                        yield NormalizedEvent::ToolCallDelta {
-                                            call_index,
-                                            id,
-                                            name,
-                                            arguments_delta: None,
-                                        };
-                                    }
-                            }
-
-                            "response.output_item.done" => {
-                                if let Some(item) = v.get("item")
-                                    && item.get("type").and_then(|t| t.as_str()) == Some("function_call") {
-                                        let call_index = v.get("output_index").and_then(serde_json::Value::as_u64).unwrap_or(0) as usize;
-                                        let id = item.get("call_id").and_then(|x| x.as_str()).unwrap_or_default().to_string();
-                                        let name = item.get("name").and_then(|x| x.as_str()).unwrap_or_default().to_string();
-                                        let arguments = item.get("arguments").and_then(|x| x.as_str()).unwrap_or("{}").to_string();
-
-                                        yield NormalizedEvent::ToolCallComplete {
-                                            call_index,
-                                            id,
-                                            name,
-                                            arguments_json: arguments,
-                                        };
-                                    }
-                            }
-
-                            "response.done" => {
-                                yield NormalizedEvent::Done;
-                            }
-
-                            // Ignore unknown events
-                            _ => {}
-                        }
-                    }
+                for event in decoder.push(&chunk?)? {
+                    yield event;
                 }
             }
         };
@@ -247,7 +165,354 @@ impl LlmDriver for ResponsesDriver {
     }
 }
 
+/// Accumulated state of a streaming `function_call` output item.
+#[derive(Debug, Default)]
+struct ToolAccum {
+    id: Option<String>,
+    name: Option<String>,
+    args: String,
+    completed: bool,
+}
+
+impl ToolAccum {
+    /// `ToolCallComplete` of the call, unless it was already sent or the
+    /// call has no ID or name.
+    fn complete(&mut self, call_index: usize) -> Option<NormalizedEvent> {
+        let (Some(id), Some(name)) = (&self.id, &self.name) else {
+            return None;
+        };
+        if self.completed {
+            return None;
+        }
+        self.completed = true;
+        let arguments_json = if self.args.is_empty() {
+            "{}".to_string()
+        } else {
+            self.args.clone()
+        };
+        Some(NormalizedEvent::ToolCallComplete {
+            call_index,
+            id: id.clone(),
+            name: name.clone(),
+            arguments_json,
+        })
+    }
+}
+
+/// Turns a Responses SSE body into [`NormalizedEvent`]s.
+#[derive(Debug)]
+struct ResponsesDecoder {
+    buf: Vec<u8>,
+    /// Name of the last `event:` line; payloads normally carry a `type` too
+    event_name: Option<String>,
+    usage_fields: UsageFields,
+    /// `function_call` items by `output_index`
+    tools: BTreeMap<usize, ToolAccum>,
+    /// The stream finished or failed; later terminal events are dropped
+    finished: bool,
+}
+
+impl ResponsesDecoder {
+    fn new(usage_fields: UsageFields) -> Self {
+        Self {
+            buf: Vec::new(),
+            event_name: None,
+            usage_fields,
+            tools: BTreeMap::new(),
+            finished: false,
+        }
+    }
+
+    /// Events of the SSE frames completed by `chunk`.
+    fn push(&mut self, chunk: &[u8]) -> anyhow::Result<Vec<NormalizedEvent>> {
+        self.buf.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(pos) = find_double_newline(&self.buf) {
+            let frame = self.buf.drain(..pos + 2).collect::<Vec<_>>();
+            let text = String::from_utf8_lossy(&frame);
+
+            let mut data = None;
+            for line in text.lines() {
+                let line = line.trim();
+                if let Some(name) = line.strip_prefix("event:") {
+                    self.event_name = Some(name.trim().to_string());
+                } else if let Some(line) = line.strip_prefix("data:") {
+                    data = Some(line.trim().to_string());
+                }
+            }
+            if let Some(data) = data {
+                self.decode(&data, &mut events)?;
+            }
+        }
+        Ok(events)
+    }
+
+    /// Events of one `data:` payload.
+    fn decode(&mut self, data: &str, events: &mut Vec<NormalizedEvent>) -> anyhow::Result<()> {
+        if data == "[DONE]" {
+            self.finish(events);
+            return Ok(());
+        }
+
+        let v: Value = serde_json::from_str(data)?;
+        let kind = v["type"]
+            .as_str()
+            .or(self.event_name.as_deref())
+            .unwrap_or_default()
+            .to_string();
+        #[allow(clippy::cast_possible_truncation)]
+        let output_index = v["output_index"].as_u64().unwrap_or(0) as usize;
+
+        match kind.as_str() {
+            "response.output_text.delta" => {
+                push_text(events, &v["delta"], |text| NormalizedEvent::MessageDelta { text });
+            }
+            // Refusals are sent instead of output text
+            "response.refusal.delta" => {
+                push_text(events, &v["delta"], |text| NormalizedEvent::RefusalDelta { text });
+            }
+            // Reasoning models stream a summary of their reasoning
+            "response.reasoning_summary_text.delta"
+            | "response.reasoning_text.delta"
+            | "response.reasoning.delta" => {
+                push_text(events, &v["delta"], |text| NormalizedEvent::ReasoningDelta { text });
+            }
+            "response.thinking.delta" => {
+                push_text(events, &v["delta"], |text| NormalizedEvent::ThinkingDelta { text });
+            }
+
+            "response.output_item.added" if v["item"]["type"] == "function_call" => {
+                let item = &v["item"];
+                let tool = self.tools.entry(output_index).or_default();
+                tool.id = item["call_id"].as_str().map(ToString::to_string);
+                tool.name = item["name"].as_str().map(ToString::to_string);
+                events.push(NormalizedEvent::ToolCallDelta {
+                    call_index: output_index,
+                    id: tool.id.clone(),
+                    name: tool.name.clone(),
+                    arguments_delta: None,
+                });
+            }
+            "response.function_call_arguments.delta" => {
+                if let Some(delta) = v["delta"].as_str() {
+                    let tool = self.tools.entry(output_index).or_default();
+                    tool.args.push_str(delta);
+                    events.push(NormalizedEvent::ToolCallDelta {
+                        call_index: output_index,
+                        id: None,
+                        name: None,
+                        arguments_delta: Some(delta.to_string()),
+                    });
+                }
+            }
+            "response.function_call_arguments.done" => {
+                if let Some(arguments) = v["arguments"].as_str() {
+                    self.tools.entry(output_index).or_default().args = arguments.to_string();
+                }
+            }
+            "response.output_item.done" if v["item"]["type"] == "function_call" => {
+                // The finished item has the final ID, name and arguments
+                let item = &v["item"];
+                let tool = self.tools.entry(output_index).or_default();
+                if let Some(id) = item["call_id"].as_str() {
+                    tool.id = Some(id.to_string());
+                }
+                if let Some(name) = item["name"].as_str() {
+                    tool.name = Some(name.to_string());
+                }
+                if let Some(arguments) = item["arguments"].as_str() {
+                    tool.args = arguments.to_string();
+                }
+                events.extend(tool.complete(output_index));
+            }
+
+            // Usage comes with the final response object
+            "response.completed" | "response.incomplete" => {
+                if !self.finished {
+                    events.extend(frame_usage(&v["response"], &self.usage_fields));
+                }
+                self.finish(events);
+            }
+            "response.failed" | "error" => {
+                let error = if kind == "error" {
+                    &v
+                } else {
+                    &v["response"]["error"]
+                };
+                if !self.finished {
+                    self.finished = true;
+                    events.push(NormalizedEvent::Error {
+                        message: error["message"]
+                            .as_str()
+                            .unwrap_or("Responses API request failed")
+                            .to_string(),
+                        code: error["code"].as_str().map(ToString::to_string),
+                    });
+                }
+            }
+            "response.done" => self.finish(events),
+
+            // Ignore unknown events
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Complete the tool calls still open and end the stream, once.
+    fn finish(&mut self, events: &mut Vec<NormalizedEvent>) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        for (call_index, tool) in &mut self.tools {
+            events.extend(tool.complete(*call_index));
+        }
+        events.push(NormalizedEvent::Done);
+    }
+}
+
+fn push_text(
+    events: &mut Vec<NormalizedEvent>,
+    text: &Value,
+    event: impl FnOnce(String) -> NormalizedEvent,
+) {
+    if let Some(text) = text.as_str()
+        && !text.is_empty()
+    {
+        events.push(event(text.to_string()));
+    }
+}
+
 /// Find the position of a double newline in the buffer.
 fn find_double_newline(buf: &[u8]) -> Option<usize> {
     buf.windows(2).position(|w| w == b"\n\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A recorded `/v1/responses` stream: reasoning, a function call, text.
+    const RECORDED_BODY: &str = r#"event: response.created
+data: {"type":"response.created","sequence_number":0,"response":{"id":"resp_1","status":"in_progress","usage":null}}
+
+event: response.output_item.added
+data: {"type":"response.output_item.added","sequence_number":1,"output_index":0,"item":{"id":"rs_1","type":"reasoning","summary":[]}}
+
+event: response.reasoning_summary_text.delta
+data: {"type":"response.reasoning_summary_text.delta","sequence_number":2,"item_id":"rs_1","output_index":0,"summary_index":0,"delta":"Checking the weather"}
+
+event: response.output_item.added
+data: {"type":"response.output_item.added","sequence_number":3,"output_index":1,"item":{"id":"fc_1","type":"function_call","status":"in_progress","arguments":"","call_id":"call_1","name":"get_weather"}}
+
+event: response.function_call_arguments.delta
+data: {"type":"response.function_call_arguments.delta","sequence_number":4,"item_id":"fc_1","output_index":1,"delta":"{\"city\":"}
+
+event: response.function_call_arguments.delta
+data: {"type":"response.function_call_arguments.delta","sequence_number":5,"item_id":"fc_1","output_index":1,"delta":"\"Paris\"}"}
+
+event: response.function_call_arguments.done
+data: {"type":"response.function_call_arguments.done","sequence_number":6,"item_id":"fc_1","output_index":1,"arguments":"{\"city\":\"Paris\"}"}
+
+event: response.output_item.done
+data: {"type":"response.output_item.done","sequence_number":7,"output_index":1,"item":{"id":"fc_1","type":"function_call","status":"completed","arguments":"{\"city\":\"Paris\"}","call_id":"call_1","name":"get_weather"}}
+
+event: response.output_text.delta
+data: {"type":"response.output_text.delta","sequence_number":8,"item_id":"msg_1","output_index":2,"content_index":0,"delta":"Let me check."}
+
+event: response.completed
+data: {"type":"response.completed","sequence_number":9,"response":{"id":"resp_1","status":"completed","usage":{"input_tokens":20,"input_tokens_details":{"cached_tokens":0},"output_tokens":12,"output_tokens_details":{"reasoning_tokens":5},"total_tokens":32}}}
+
+"#;
+
+    fn decode_all(body: &str, chunk_size: usize) -> Vec<NormalizedEvent> {
+        let mut decoder = ResponsesDecoder::new(UsageFields::default());
+        body.as_bytes()
+            .chunks(chunk_size)
+            .flat_map(|chunk| decoder.push(chunk).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_recorded_stream_maps_to_normalized_events() {
+        let expected = [
+            NormalizedEvent::ReasoningDelta {
+                text: "Checking the weather".to_string(),
+            },
+            NormalizedEvent::ToolCallDelta {
+                call_index: 1,
+                id: Some("call_1".to_string()),
+                name: Some("get_weather".to_string()),
+                arguments_delta: None,
+            },
+            NormalizedEvent::ToolCallDelta {
+                call_index: 1,
+                id: None,
+                name: None,
+                arguments_delta: Some("{\"city\":".to_string()),
+            },
+            NormalizedEvent::ToolCallDelta {
+                call_index: 1,
+                id: None,
+                name: None,
+                arguments_delta: Some("\"Paris\"}".to_string()),
+            },
+            NormalizedEvent::ToolCallComplete {
+                call_index: 1,
+                id: "call_1".to_string(),
+                name: "get_weather".to_string(),
+                arguments_json: "{\"city\":\"Paris\"}".to_string(),
+            },
+            NormalizedEvent::MessageDelta {
+                text: "Let me check.".to_string(),
+            },
+            NormalizedEvent::Usage {
+                prompt_tokens: 20,
+                completion_tokens: 12,
+                total_tokens: 32,
+            },
+            NormalizedEvent::Done,
+        ];
+
+        // Frames split across network chunks decode the same
+        assert_eq!(decode_all(RECORDED_BODY, RECORDED_BODY.len()), expected);
+        assert_eq!(decode_all(RECORDED_BODY, 7), expected);
+    }
+
+    #[test]
+    fn test_stream_end_completes_open_calls_once() {
+        let body = concat!(
+            "data: {\"type\":\"response.output_item.added\",\"output_index\":0,",
+            "\"item\":{\"type\":\"function_call\",\"call_id\":\"call_1\",\"name\":\"now\"}}\n\n",
+            "data: {\"type\":\"response.completed\",\"response\":{\"usage\":null}}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let events = decode_all(body, body.len());
+        assert_eq!(
+            events[1..],
+            [
+                NormalizedEvent::ToolCallComplete {
+                    call_index: 0,
+                    id: "call_1".to_string(),
+                    name: "now".to_string(),
+                    arguments_json: "{}".to_string(),
+                },
+                NormalizedEvent::Done,
+            ]
+        );
+
+        let failed = concat!(
+            "event: response.failed\n",
+            "data: {\"type\":\"response.failed\",\"response\":",
+            "{\"error\":{\"code\":\"server_error\",\"message\":\"The model failed\"}}}\n\n",
+        );
+        assert_eq!(
+            decode_all(failed, failed.len()),
+            [NormalizedEvent::Error {
+                message: "The model failed".to_string(),
+                code: Some("server_error".to_string()),
+            }]
+        );
+    }
+}
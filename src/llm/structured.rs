@@ -0,0 +1,250 @@
+//! Structured (JSON) output.
+//!
+//! A [`ResponseFormat`] on an [`LlmRequest`](super::LlmRequest) asks the
+//! provider to constrain its answer to JSON, optionally matching a schema.
+//! Providers don't always honour the constraint, so answers are checked
+//! again with [`ResponseFormat::validate`].
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use super::Provider;
+
+/// Output format requested from the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Free-form text (the provider default).
+    Text,
+    /// Any syntactically valid JSON object.
+    JsonObject,
+    /// JSON matching `schema`.
+    JsonSchema {
+        /// Schema name reported to the provider.
+        name: String,
+        /// JSON Schema the answer must satisfy.
+        schema: Value,
+        /// Ask the provider to enforce the schema exactly.
+        #[serde(default = "default_strict")]
+        strict: bool,
+    },
+}
+
+fn default_strict() -> bool {
+    true
+}
+
+/// Why a structured answer could not be produced.
+#[derive(Debug, thiserror::Error)]
+pub enum StructuredOutputError {
+    /// The provider cannot constrain output to the requested format.
+    #[error("Provider {provider} does not support response format '{format}'")]
+    Unsupported {
+        /// Provider the request was sent to.
+        provider: String,
+        /// Requested format type.
+        format: &'static str,
+    },
+    /// The answer is not valid JSON.
+    #[error("Model output is not valid JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    /// The answer is JSON but not an object.
+    #[error("Model output is not a JSON object")]
+    NotAnObject,
+    /// The configured schema itself is invalid.
+    #[error("Invalid response schema: {0}")]
+    InvalidSchema(String),
+    /// The answer does not satisfy the schema.
+    #[error("Model output does not match schema '{name}': {}", .errors.join("; "))]
+    SchemaMismatch {
+        /// Schema name.
+        name: String,
+        /// One message per violation, prefixed with the JSON pointer.
+        errors: Vec<String>,
+    },
+}
+
+impl ResponseFormat {
+    /// Format type as sent to the provider.
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::JsonObject => "json_object",
+            Self::JsonSchema { .. } => "json_schema",
+        }
+    }
+
+    /// Whether `provider` can constrain output to this format.
+    #[must_use]
+    pub fn is_supported_by(&self, provider: &Provider) -> bool {
+        match self {
+            Self::Text => true,
+            // Bedrock passes native model requests through, without a JSON mode
+            Self::JsonObject => !matches!(provider, Provider::Bedrock { .. }),
+            // Groq only offers JSON mode
+            Self::JsonSchema { .. } => {
+                !matches!(provider, Provider::Groq | Provider::Bedrock { .. })
+            }
+        }
+    }
+
+    /// Fail with [`StructuredOutputError::Unsupported`] unless `provider`
+    /// supports this format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider can't honour the format.
+    pub fn ensure_supported(&self, provider: &Provider) -> Result<(), StructuredOutputError> {
+        if self.is_supported_by(provider) {
+            Ok(())
+        } else {
+            Err(StructuredOutputError::Unsupported {
+                provider: format!("{provider:?}"),
+                format: self.kind(),
+            })
+        }
+    }
+
+    /// `response_format` value for the Chat Completions API.
+    #[must_use]
+    pub fn to_chat_completions(&self) -> Value {
+        match self {
+            Self::JsonSchema {
+                name,
+                schema,
+                strict,
+            } => json!({
+                "type": "json_schema",
+                "json_schema": { "name": name, "schema": schema, "strict": strict }
+            }),
+            other => json!({ "type": other.kind() }),
+        }
+    }
+
+    /// `text.format` value for the Responses API.
+    #[must_use]
+    pub fn to_responses(&self) -> Value {
+        match self {
+            Self::JsonSchema {
+                name,
+                schema,
+                strict,
+            } => json!({
+                "type": "json_schema",
+                "name": name,
+                "schema": schema,
+                "strict": strict
+            }),
+            other => json!({ "type": other.kind() }),
+        }
+    }
+
+    /// Check a model answer against this format.
+    ///
+    /// Returns the parsed JSON (`Value::String` for [`ResponseFormat::Text`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the answer isn't JSON or violates the schema.
+    pub fn validate(&self, output: &str) -> Result<Value, StructuredOutputError> {
+        let (name, schema) = match self {
+            Self::Text => return Ok(Value::String(output.to_string())),
+            Self::JsonObject => {
+                let value: Value = serde_json::from_str(output.trim())?;
+                return if value.is_object() {
+                    Ok(value)
+                } else {
+                    Err(StructuredOutputError::NotAnObject)
+                };
+            }
+            Self::JsonSchema { name, schema, .. } => (name, schema),
+        };
+
+        let value: Value = serde_json::from_str(output.trim())?;
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|e| StructuredOutputError::InvalidSchema(e.to_string()))?;
+        let errors: Vec<String> = validator
+            .iter_errors(&value)
+            .map(|e| format!("{}: {}", e.instance_path, e))
+            .collect();
+        if errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(StructuredOutputError::SchemaMismatch {
+                name: name.clone(),
+                errors,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn title_format() -> ResponseFormat {
+        ResponseFormat::JsonSchema {
+            name: "title".to_string(),
+            schema: json!({
+                "type": "object",
+                "properties": { "title": { "type": "string" } },
+                "required": ["title"],
+                "additionalProperties": false
+            }),
+            strict: true,
+        }
+    }
+
+    #[test]
+    fn test_provider_payloads() {
+        let format = title_format();
+        let chat = format.to_chat_completions();
+        assert_eq!(chat["type"], "json_schema");
+        assert_eq!(chat["json_schema"]["name"], "title");
+        assert_eq!(chat["json_schema"]["strict"], true);
+
+        let responses = format.to_responses();
+        assert_eq!(responses["type"], "json_schema");
+        assert_eq!(responses["schema"]["required"][0], "title");
+
+        assert_eq!(
+            ResponseFormat::JsonObject.to_chat_completions(),
+            json!({ "type": "json_object" })
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let format = title_format();
+        let value = format.validate(r#" {"title": "Trip planning"} "#).unwrap();
+        assert_eq!(value["title"], "Trip planning");
+
+        assert!(matches!(
+            format.validate("Trip planning"),
+            Err(StructuredOutputError::InvalidJson(_))
+        ));
+        match format.validate(r#"{"name": "x"}"#) {
+            Err(StructuredOutputError::SchemaMismatch { name, errors }) => {
+                assert_eq!(name, "title");
+                assert!(!errors.is_empty());
+            }
+            other => panic!("expected schema mismatch, got {other:?}"),
+        }
+
+        assert!(matches!(
+            ResponseFormat::JsonObject.validate("[1, 2]"),
+            Err(StructuredOutputError::NotAnObject)
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_provider() {
+        let err = title_format().ensure_supported(&Provider::Groq).unwrap_err();
+        assert!(matches!(err, StructuredOutputError::Unsupported { .. }));
+        assert!(err.to_string().contains("json_schema"));
+
+        assert!(ResponseFormat::JsonObject.ensure_supported(&Provider::Groq).is_ok());
+        assert!(title_format().ensure_supported(&Provider::OpenAI).is_ok());
+    }
+}
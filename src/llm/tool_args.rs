@@ -0,0 +1,145 @@
+//! Validation of the arguments the model passes to a tool.
+//!
+//! Models sometimes emit malformed JSON, or JSON that doesn't match the
+//! tool's declared input schema. Rather than calling the tool with garbage,
+//! the tool loop answers with a [`ToolArgumentsError`] so the model can
+//! correct the call on its next turn.
+
+use serde_json::{Value, json};
+
+/// Why tool-call arguments were rejected.
+#[derive(Debug, thiserror::Error)]
+pub enum ToolArgumentsError {
+    /// The arguments are not valid JSON.
+    #[error("Arguments for tool '{tool}' are not valid JSON: {error}")]
+    InvalidJson {
+        /// Namespaced tool name.
+        tool: String,
+        /// Parser error.
+        error: String,
+    },
+    /// The arguments don't satisfy the tool's input schema.
+    #[error("Arguments for tool '{tool}' do not match its schema: {}", .errors.join("; "))]
+    SchemaMismatch {
+        /// Namespaced tool name.
+        tool: String,
+        /// One entry per violation, prefixed with the offending JSON pointer.
+        errors: Vec<String>,
+    },
+}
+
+impl ToolArgumentsError {
+    /// Tool result telling the model what to fix.
+    #[must_use]
+    pub fn to_tool_result(&self) -> String {
+        let errors = match self {
+            Self::InvalidJson { error, .. } => vec![error.clone()],
+            Self::SchemaMismatch { errors, .. } => errors.clone(),
+        };
+        json!({
+            "error": "invalid_arguments",
+            "message": format!("{self}. Fix the arguments and call the tool again."),
+            "errors": errors,
+        })
+        .to_string()
+    }
+}
+
+/// Parse `arguments` and check them against the tool's input `schema`.
+///
+/// Empty arguments mean an empty object. Without a schema, or with one the
+/// validator can't compile, only the JSON syntax is checked.
+///
+/// # Errors
+///
+/// Returns an error if the arguments are not JSON or violate the schema.
+pub fn validate_tool_arguments(
+    tool: &str,
+    arguments: &str,
+    schema: Option<&Value>,
+) -> Result<Value, ToolArgumentsError> {
+    let value = if arguments.trim().is_empty() {
+        Value::Object(serde_json::Map::new())
+    } else {
+        serde_json::from_str(arguments).map_err(|e| ToolArgumentsError::InvalidJson {
+            tool: tool.to_string(),
+            error: e.to_string(),
+        })?
+    };
+
+    let Some(schema) = schema else {
+        return Ok(value);
+    };
+    let validator = match jsonschema::validator_for(schema) {
+        Ok(validator) => validator,
+        Err(e) => {
+            tracing::warn!(tool = %tool, error = %e, "Tool has an invalid input schema");
+            return Ok(value);
+        }
+    };
+    let errors: Vec<String> = validator
+        .iter_errors(&value)
+        .map(|e| format!("{}: {}", e.instance_path, e))
+        .collect();
+    if errors.is_empty() {
+        Ok(value)
+    } else {
+        Err(ToolArgumentsError::SchemaMismatch {
+            tool: tool.to_string(),
+            errors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string" },
+                "limit": { "type": "integer" }
+            },
+            "required": ["query"]
+        })
+    }
+
+    #[test]
+    fn test_valid_and_empty_arguments() {
+        let schema = search_schema();
+        let value =
+            validate_tool_arguments("web__search", r#"{"query":"rust"}"#, Some(&schema)).unwrap();
+        assert_eq!(value, json!({ "query": "rust" }));
+
+        let value = validate_tool_arguments("time__now", "", None).unwrap();
+        assert_eq!(value, json!({}));
+    }
+
+    #[test]
+    fn test_malformed_json_is_rejected() {
+        let err = validate_tool_arguments("web__search", r#"{"query":"ru"#, Some(&search_schema()))
+            .unwrap_err();
+        assert!(matches!(err, ToolArgumentsError::InvalidJson { .. }));
+
+        let result: Value = serde_json::from_str(&err.to_tool_result()).unwrap();
+        assert_eq!(result["error"], "invalid_arguments");
+    }
+
+    #[test]
+    fn test_schema_violation_is_reported_per_field() {
+        let schema = search_schema();
+        let err = validate_tool_arguments("web__search", r#"{"limit":"ten"}"#, Some(&schema))
+            .unwrap_err();
+        let ToolArgumentsError::SchemaMismatch { errors, .. } = &err else {
+            panic!("expected a schema mismatch, got {err:?}");
+        };
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors.iter().any(|e| e.starts_with("/limit")), "{errors:?}");
+
+        let result: Value = serde_json::from_str(&err.to_tool_result()).unwrap();
+        assert_eq!(result["errors"].as_array().unwrap().len(), 2);
+        assert!(result["message"].as_str().unwrap().contains("web__search"));
+    }
+}
@@ -0,0 +1,189 @@
+//! Control over whether (and which) tools the model calls.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Value, json};
+
+use super::Provider;
+
+/// Which tool the model must (or must not) call.
+///
+/// Serialized the way `OpenAI` accepts it: `"auto"`, `"none"`, `"required"`,
+/// or `{"type": "function", "function": {"name": ...}}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ToolChoice {
+    /// The model decides (the provider default).
+    #[default]
+    Auto,
+    /// Never call tools.
+    None,
+    /// Call at least one tool.
+    Required,
+    /// Call this (namespaced) tool.
+    Function(String),
+}
+
+impl ToolChoice {
+    /// Choice for follow-up turns of a tool loop.
+    ///
+    /// Forcing a tool again after its result came back would loop until the
+    /// iteration limit, so forced choices relax to `Auto`.
+    #[must_use]
+    pub fn after_tool_results(&self) -> Self {
+        match self {
+            Self::Required | Self::Function(_) => Self::Auto,
+            other => other.clone(),
+        }
+    }
+
+    /// The choice `provider` can honour given the `tools` on offer.
+    ///
+    /// Forcing a tool that isn't offered, or `required` on providers that
+    /// reject it, downgrades to `Auto` rather than failing the request.
+    #[must_use]
+    pub fn effective(&self, provider: &Provider, tools: &[Value]) -> Self {
+        match self {
+            Self::Required if !provider.supports_required_tool_choice() => {
+                tracing::debug!(?provider, "tool_choice=required unsupported, using auto");
+                Self::Auto
+            }
+            Self::Function(name) if !offers_tool(tools, name) => {
+                tracing::warn!(tool = %name, "Forced tool is not available, using auto");
+                Self::Auto
+            }
+            other => other.clone(),
+        }
+    }
+
+    /// `tool_choice` value for the Chat Completions API.
+    #[must_use]
+    pub fn to_chat_completions(&self) -> Value {
+        match self {
+            Self::Function(name) => json!({ "type": "function", "function": { "name": name } }),
+            Self::Auto => json!("auto"),
+            Self::None => json!("none"),
+            Self::Required => json!("required"),
+        }
+    }
+
+    /// `tool_choice` value for the Responses API, which names the function
+    /// at the top level.
+    #[must_use]
+    pub fn to_responses(&self) -> Value {
+        match self {
+            Self::Function(name) => json!({ "type": "function", "name": name }),
+            mode => mode.to_chat_completions(),
+        }
+    }
+
+    /// `tool_choice` value for the Anthropic Messages API (Claude on Bedrock).
+    #[must_use]
+    pub fn to_anthropic(&self) -> Value {
+        match self {
+            Self::Function(name) => json!({ "type": "tool", "name": name }),
+            Self::Auto => json!({ "type": "auto" }),
+            Self::None => json!({ "type": "none" }),
+            Self::Required => json!({ "type": "any" }),
+        }
+    }
+}
+
+fn offers_tool(tools: &[Value], name: &str) -> bool {
+    tools.iter().any(|t| t["function"]["name"].as_str() == Some(name))
+}
+
+impl Serialize for ToolChoice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_chat_completions().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ToolChoice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Function {
+            name: String,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Mode(String),
+            Named { function: Function },
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Mode(mode) => match mode.as_str() {
+                "auto" => Ok(Self::Auto),
+                "none" => Ok(Self::None),
+                "required" => Ok(Self::Required),
+                other => Err(serde::de::Error::custom(format!(
+                    "unknown tool_choice '{other}' (expected auto, none, required or a function)"
+                ))),
+            },
+            Repr::Named { function } => Ok(Self::Function(function.name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tools() -> Vec<Value> {
+        vec![json!({ "type": "function", "function": { "name": "mirror" } })]
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let forced: ToolChoice = serde_json::from_value(
+            json!({ "type": "function", "function": { "name": "mirror" } }),
+        )
+        .unwrap();
+        assert_eq!(forced, ToolChoice::Function("mirror".to_string()));
+        assert_eq!(
+            serde_json::to_value(&forced).unwrap(),
+            json!({ "type": "function", "function": { "name": "mirror" } })
+        );
+
+        let required: ToolChoice = serde_yaml::from_str("required").unwrap();
+        assert_eq!(required, ToolChoice::Required);
+        assert!(serde_json::from_value::<ToolChoice>(json!("always")).is_err());
+    }
+
+    #[test]
+    fn test_provider_encodings() {
+        let forced = ToolChoice::Function("mirror".to_string());
+        assert_eq!(forced.to_chat_completions()["function"]["name"], "mirror");
+        assert_eq!(
+            forced.to_responses(),
+            json!({ "type": "function", "name": "mirror" })
+        );
+        assert_eq!(ToolChoice::Required.to_responses(), json!("required"));
+        assert_eq!(
+            forced.to_anthropic(),
+            json!({ "type": "tool", "name": "mirror" })
+        );
+        assert_eq!(ToolChoice::Required.to_anthropic(), json!({ "type": "any" }));
+    }
+
+    #[test]
+    fn test_downgrades() {
+        assert_eq!(
+            ToolChoice::Required.effective(&Provider::Generic, &tools()),
+            ToolChoice::Auto
+        );
+        assert_eq!(
+            ToolChoice::Required.effective(&Provider::OpenAI, &tools()),
+            ToolChoice::Required
+        );
+        assert_eq!(
+            ToolChoice::Function("missing".to_string()).effective(&Provider::OpenAI, &tools()),
+            ToolChoice::Auto
+        );
+        assert_eq!(
+            ToolChoice::Function("mirror".to_string()).after_tool_results(),
+            ToolChoice::Auto
+        );
+        assert_eq!(ToolChoice::None.after_tool_results(), ToolChoice::None);
+    }
+}
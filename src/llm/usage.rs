@@ -0,0 +1,145 @@
+//! Token usage in Chat Completions stream frames.
+//!
+//! `OpenAI` reports `prompt_tokens`, `completion_tokens` and `total_tokens`;
+//! compatible providers don't all follow it. Some name the counts
+//! `input_tokens`/`output_tokens`, some leave out the total, and Groq nests
+//! streamed usage under `x_groq`. [`frame_usage`] accepts any of the names
+//! in [`UsageFields`] and computes a missing total, so usage isn't dropped.
+
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::normalized::NormalizedEvent;
+
+/// Field names token counts are read from, in order of preference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageFields {
+    pub prompt: Vec<String>,
+    pub completion: Vec<String>,
+    pub total: Vec<String>,
+}
+
+impl Default for UsageFields {
+    fn default() -> Self {
+        let names = |names: &[&str]| names.iter().map(|s| (*s).to_string()).collect();
+        Self {
+            prompt: names(&["prompt_tokens", "input_tokens"]),
+            completion: names(&["completion_tokens", "output_tokens"]),
+            total: names(&["total_tokens"]),
+        }
+    }
+}
+
+/// Parses `prompt=in_tok,completion=out_tok,total=all_tok`: names a
+/// provider uses, read before the default ones.
+impl FromStr for UsageFields {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = Self::default();
+        let mut added = [0, 0, 0];
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (count, name) = entry
+                .split_once('=')
+                .map(|(count, name)| (count.trim(), name.trim()))
+                .filter(|(_, name)| !name.is_empty())
+                .ok_or_else(|| format!("Expected <count>=<field name>, got '{entry}'"))?;
+            let (names, added) = match count {
+                "prompt" => (&mut fields.prompt, &mut added[0]),
+                "completion" => (&mut fields.completion, &mut added[1]),
+                "total" => (&mut fields.total, &mut added[2]),
+                other => {
+                    return Err(format!(
+                        "Unknown token count '{other}' (expected prompt, completion or total)"
+                    ));
+                }
+            };
+            names.insert(*added, name.to_string());
+            *added += 1;
+        }
+        Ok(fields)
+    }
+}
+
+/// Usage event for a Chat Completions stream frame, if the frame reports
+/// usage.
+///
+/// Both the prompt and the completion count must be present; a missing
+/// total is their sum.
+#[must_use]
+pub fn frame_usage(frame: &Value, fields: &UsageFields) -> Option<NormalizedEvent> {
+    let usage = [&frame["usage"], &frame["x_groq"]["usage"]]
+        .into_iter()
+        .find(|usage| usage.is_object())?;
+    let count = |names: &[String]| {
+        names
+            .iter()
+            .find_map(|name| usage.get(name).and_then(Value::as_u64))
+            .map(|count| u32::try_from(count).unwrap_or(u32::MAX))
+    };
+
+    let prompt_tokens = count(&fields.prompt)?;
+    let completion_tokens = count(&fields.completion)?;
+    let total_tokens =
+        count(&fields.total).unwrap_or_else(|| prompt_tokens.saturating_add(completion_tokens));
+    Some(NormalizedEvent::Usage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn usage(prompt_tokens: u32, completion_tokens: u32, total_tokens: u32) -> NormalizedEvent {
+        NormalizedEvent::Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens,
+        }
+    }
+
+    #[test]
+    fn test_usage_with_alternate_names_gets_a_total() {
+        let fields = UsageFields::default();
+        let frame = json!({
+            "id": "chatcmpl-1",
+            "choices": [],
+            "usage": { "input_tokens": 12, "output_tokens": 30 }
+        });
+        assert_eq!(frame_usage(&frame, &fields), Some(usage(12, 30, 42)));
+
+        let openai = json!({
+            "usage": { "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 9 }
+        });
+        assert_eq!(frame_usage(&openai, &fields), Some(usage(5, 2, 9)));
+
+        let groq = json!({
+            "x_groq": { "usage": { "prompt_tokens": 3, "completion_tokens": 4 } }
+        });
+        assert_eq!(frame_usage(&groq, &fields), Some(usage(3, 4, 7)));
+
+        // Half a report isn't usage
+        let partial = json!({ "usage": { "prompt_tokens": 3 } });
+        assert_eq!(frame_usage(&partial, &fields), None);
+        assert_eq!(frame_usage(&json!({ "usage": null }), &fields), None);
+    }
+
+    #[test]
+    fn test_configured_names_come_first() {
+        let fields: UsageFields = "prompt=in_tok, completion=out_tok".parse().unwrap();
+        assert_eq!(fields.prompt, ["in_tok", "prompt_tokens", "input_tokens"]);
+
+        let frame = json!({
+            "usage": { "in_tok": 1, "out_tok": 2, "prompt_tokens": 100, "completion_tokens": 200 }
+        });
+        assert_eq!(frame_usage(&frame, &fields), Some(usage(1, 2, 3)));
+
+        assert!("input=in_tok".parse::<UsageFields>().is_err());
+        assert!("prompt".parse::<UsageFields>().is_err());
+    }
+}
@@ -8,21 +8,37 @@ use mimalloc::MiMalloc;
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
-use axum_leptos_htmx_wc::config::{AppConfig, load_llm_settings};
+use axum_leptos_htmx_wc::config::{AppConfig, Cli, load_llm_settings};
+use axum_leptos_htmx_wc::mcp::config::load_mcp_config;
 use axum_leptos_htmx_wc::server;
 use axum_leptos_htmx_wc::uar;
+use clap::Parser;
 use dotenvy::dotenv;
 use std::sync::Arc;
 
 #[tokio::main]
 async fn main() {
+    // Load .env (if present), before telemetry reads its OTEL_* settings
+    let _ = dotenv();
+
     // Initialize Telemetry (Logging, Tracing, Metrics)
-    uar::telemetry::init();
+    let telemetry = uar::telemetry::init();
 
     tracing::info!("Initializing Universal Agent Runtime...");
 
-    // Load .env (if present)
-    let _ = dotenv();
+    // `--validate-mcp`: check the MCP config and exit without serving
+    if let Some(path) = Cli::parse().validate_mcp {
+        match load_mcp_config(&path) {
+            Ok(cfg) => {
+                println!("{path}: OK ({} servers)", cfg.mcp_servers.len());
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("{e:#}");
+                std::process::exit(1);
+            }
+        }
+    }
 
     // Load Configuration (CLI > Env > File)
     let config = match AppConfig::load() {
@@ -43,8 +59,13 @@ async fn main() {
         }
     };
 
-    if let Err(e) = server::start_server(config, settings).await {
+    let result = server::start_server(config, settings).await;
+    if let Err(e) = &result {
         tracing::error!("Server error: {:?}", e);
+    }
+    // Flush the spans of the last requests before exiting
+    telemetry.shutdown();
+    if result.is_err() {
         std::process::exit(1);
     }
 }
@@ -0,0 +1,366 @@
+//! Process-wide cache of MCP registries started from the same configuration.
+//!
+//! Skills bring their own MCP servers, and a skill matched by every run
+//! would otherwise spawn (and leave behind) a fresh set of servers per run.
+//! Registries are cached by the hash of their [`McpConfig`] instead: runs
+//! hold a [`CachedRegistry`] lease while they use one, and a registry nobody
+//! has leased for the idle timeout is shut down. Runs that need the same
+//! configuration at once wait for a single initialization.
+
+use crate::mcp::config::McpConfig;
+use crate::mcp::registry::McpRegistry;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry as MapEntry;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// How often idle registries are looked for.
+pub const DEFAULT_EVICTION_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Counters of a [`McpRegistryCache`], for diagnostics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct McpCacheStats {
+    /// Cached registries, leased or idle
+    pub entries: usize,
+    /// Registries leased by at least one run
+    pub in_use: usize,
+    /// Acquisitions served by a cached (or initializing) registry
+    pub hits: u64,
+    /// Acquisitions that started a registry
+    pub misses: u64,
+    /// Idle registries shut down
+    pub evictions: u64,
+}
+
+struct Entry {
+    registry: Arc<OnceCell<McpRegistry>>,
+    leases: usize,
+    idle_since: Option<Instant>,
+}
+
+/// Registries shared by every run needing the same MCP configuration.
+pub struct McpRegistryCache {
+    entries: Mutex<HashMap<String, Entry>>,
+    idle_timeout: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl std::fmt::Debug for McpRegistryCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpRegistryCache")
+            .field("idle_timeout", &self.idle_timeout)
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+impl McpRegistryCache {
+    /// Default time a registry nobody uses is kept running.
+    pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+    /// Create a cache shutting registries down after `idle_timeout` unused.
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            idle_timeout,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Hex SHA-256 of `config`, independent of the order of its maps.
+    pub fn key(config: &McpConfig) -> String {
+        let value = serde_json::to_value(config).unwrap_or_default();
+        hex::encode(Sha256::digest(sorted(value).to_string().as_bytes()))
+    }
+
+    /// The registry of `config`, started on first use.
+    ///
+    /// Fails if the registry can't be started; the next acquisition tries
+    /// again.
+    pub async fn acquire(self: &Arc<Self>, config: &McpConfig) -> anyhow::Result<CachedRegistry> {
+        self.acquire_with(config, || McpRegistry::from_config(config)).await
+    }
+
+    async fn acquire_with<F, Fut>(
+        self: &Arc<Self>,
+        config: &McpConfig,
+        init: F,
+    ) -> anyhow::Result<CachedRegistry>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = anyhow::Result<McpRegistry>>,
+    {
+        self.evict_idle();
+        let key = Self::key(config);
+        let cell = {
+            let mut entries = self.entries.lock().unwrap();
+            let entry = match entries.entry(key.clone()) {
+                MapEntry::Occupied(entry) => {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    entry.into_mut()
+                }
+                MapEntry::Vacant(entry) => {
+                    self.misses.fetch_add(1, Ordering::Relaxed);
+                    entry.insert(Entry {
+                        registry: Arc::new(OnceCell::new()),
+                        leases: 0,
+                        idle_since: None,
+                    })
+                }
+            };
+            entry.leases += 1;
+            entry.idle_since = None;
+            Arc::clone(&entry.registry)
+        };
+
+        // Released however initialization ends, even if this future is dropped
+        let lease = Lease {
+            cache: Arc::clone(self),
+            key,
+        };
+        let registry = cell.get_or_try_init(init).await?.clone();
+        Ok(CachedRegistry {
+            registry,
+            _lease: lease,
+        })
+    }
+
+    fn release(&self, key: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.get_mut(key) else {
+            return;
+        };
+        entry.leases -= 1;
+        if entry.leases > 0 {
+            return;
+        }
+        if entry.registry.initialized() {
+            entry.idle_since = Some(Instant::now());
+        } else {
+            // Initialization failed and nobody is retrying it
+            entries.remove(key);
+        }
+    }
+
+    /// Shut down the registries unused for the idle timeout, returning how
+    /// many were.
+    pub fn evict_idle(&self) -> usize {
+        let evicted: Vec<_> = {
+            let mut entries = self.entries.lock().unwrap();
+            let expired: Vec<_> = entries
+                .iter()
+                .filter(|(_, entry)| {
+                    entry
+                        .idle_since
+                        .is_some_and(|since| since.elapsed() >= self.idle_timeout)
+                })
+                .map(|(key, _)| key.clone())
+                .collect();
+            expired
+                .iter()
+                .filter_map(|key| entries.remove(key))
+                .collect()
+        };
+        for entry in &evicted {
+            if let Some(registry) = entry.registry.get() {
+                registry.shutdown();
+            }
+        }
+        if !evicted.is_empty() {
+            tracing::debug!(count = evicted.len(), "Evicted idle MCP registries");
+            self.evictions.fetch_add(evicted.len() as u64, Ordering::Relaxed);
+        }
+        evicted.len()
+    }
+
+    /// Spawn a background task evicting idle registries every `interval`.
+    pub fn spawn_eviction(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let cache = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                cache.evict_idle();
+            }
+        })
+    }
+
+    pub fn stats(&self) -> McpCacheStats {
+        let entries = self.entries.lock().unwrap();
+        McpCacheStats {
+            entries: entries.len(),
+            in_use: entries.values().filter(|entry| entry.leases > 0).count(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for McpRegistryCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_IDLE_TIMEOUT)
+    }
+}
+
+/// A registry leased from a [`McpRegistryCache`]; dropping it returns the
+/// lease.
+#[derive(Debug)]
+pub struct CachedRegistry {
+    registry: McpRegistry,
+    _lease: Lease,
+}
+
+impl CachedRegistry {
+    pub fn registry(&self) -> &McpRegistry {
+        &self.registry
+    }
+}
+
+struct Lease {
+    cache: Arc<McpRegistryCache>,
+    key: String,
+}
+
+impl std::fmt::Debug for Lease {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Lease")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.cache.release(&self.key);
+    }
+}
+
+/// `value` with the keys of every object in order.
+fn sorted(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut fields: Vec<_> = map.into_iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(fields.into_iter().map(|(k, v)| (k, sorted(v))).collect())
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sorted).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::config::McpServerEntry;
+    use std::sync::atomic::AtomicUsize;
+
+    fn config(command: &str) -> McpConfig {
+        let entry = McpServerEntry::Stdio {
+            command: command.to_string(),
+            args: vec![],
+            env: [("A", "1"), ("B", "2"), ("C", "3")]
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .into(),
+            require_approval: vec![],
+            tool_limits: HashMap::new(),
+        };
+        McpConfig {
+            mcp_servers: [("tools".to_string(), entry)].into(),
+        }
+    }
+
+    /// Starts an empty registry slowly, counting the starts.
+    async fn start(starts: &AtomicUsize) -> anyhow::Result<McpRegistry> {
+        starts.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        Ok(McpRegistry::new_empty())
+    }
+
+    async fn lease(
+        cache: &Arc<McpRegistryCache>,
+        command: &str,
+        starts: &AtomicUsize,
+    ) -> CachedRegistry {
+        cache
+            .acquire_with(&config(command), || start(starts))
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_key_identifies_the_configuration() {
+        assert_eq!(
+            McpRegistryCache::key(&config("a")),
+            McpRegistryCache::key(&config("a"))
+        );
+        assert_ne!(
+            McpRegistryCache::key(&config("a")),
+            McpRegistryCache::key(&config("b"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_first_uses_start_the_registry_once() {
+        let cache = Arc::new(McpRegistryCache::default());
+        let starts = AtomicUsize::new(0);
+
+        let acquisitions = (0..8).map(|_| lease(&cache, "a", &starts));
+        let leases = futures::future::join_all(acquisitions).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.in_use), (1, 1));
+        assert_eq!((stats.hits, stats.misses), (7, 1));
+
+        // A later run reuses it
+        drop(leases);
+        let _lease = lease(&cache, "a", &starts).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.stats().hits, 8);
+    }
+
+    #[tokio::test]
+    async fn test_only_idle_registries_are_evicted() {
+        let cache = Arc::new(McpRegistryCache::new(Duration::ZERO));
+        let starts = AtomicUsize::new(0);
+
+        let leased = lease(&cache, "a", &starts).await;
+        drop(lease(&cache, "b", &starts).await);
+        assert_eq!(cache.evict_idle(), 1);
+        assert_eq!(cache.stats().entries, 1);
+
+        drop(leased);
+        assert_eq!(cache.evict_idle(), 1);
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.evictions), (0, 2));
+
+        // Evicted registries are started again
+        let _lease = lease(&cache, "a", &starts).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_starts_are_not_cached() {
+        let cache = Arc::new(McpRegistryCache::default());
+        let failed = cache
+            .acquire_with(&config("a"), || async { Err(anyhow::anyhow!("spawn failed")) })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(cache.stats().entries, 0);
+
+        let starts = AtomicUsize::new(0);
+        let _lease = lease(&cache, "a", &starts).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,13 +1,15 @@
+use anyhow::{Context, anyhow, bail};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fs, path::Path};
+use url::Url;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct McpConfig {
     #[serde(rename = "mcpServers")]
     pub mcp_servers: HashMap<String, McpServerEntry>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum McpServerEntry {
     Stdio {
@@ -16,17 +18,281 @@ pub enum McpServerEntry {
         args: Vec<String>,
         #[serde(default)]
         env: HashMap<String, String>,
+        /// Tools (raw names, or `*` for all) a user must approve each call of
+        #[serde(
+            default,
+            rename = "requireApproval",
+            skip_serializing_if = "Vec::is_empty"
+        )]
+        require_approval: Vec<String>,
+        /// Rate and concurrency limits of individual tools, by raw name
+        #[serde(
+            default,
+            rename = "toolLimits",
+            skip_serializing_if = "HashMap::is_empty"
+        )]
+        tool_limits: HashMap<String, ToolLimits>,
     },
     RemoteHttp {
         url: String,
         #[serde(default)]
         env: HashMap<String, String>,
+        #[serde(
+            default,
+            rename = "requireApproval",
+            skip_serializing_if = "Vec::is_empty"
+        )]
+        require_approval: Vec<String>,
+        /// Rate and concurrency limits of individual tools, by raw name
+        #[serde(
+            default,
+            rename = "toolLimits",
+            skip_serializing_if = "HashMap::is_empty"
+        )]
+        tool_limits: HashMap<String, ToolLimits>,
     },
 }
 
+impl McpServerEntry {
+    /// Whether each call of the server's `tool` needs a user's approval.
+    pub fn requires_approval(&self, tool: &str) -> bool {
+        let (Self::Stdio {
+            require_approval, ..
+        }
+        | Self::RemoteHttp {
+            require_approval, ..
+        }) = self;
+        require_approval.iter().any(|t| t == "*" || t == tool)
+    }
+
+    /// Limits of the server's tools, by raw tool name.
+    pub fn tool_limits(&self) -> &HashMap<String, ToolLimits> {
+        let (Self::Stdio { tool_limits, .. } | Self::RemoteHttp { tool_limits, .. }) = self;
+        tool_limits
+    }
+}
+
+/// How often, and how many at once, calls of one tool may run.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolLimits {
+    /// Calls started per second; also the size of the burst allowed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<f64>,
+    /// Calls running at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<usize>,
+    /// Seconds a call queues for its turn before it fails; 0 fails at once
+    #[serde(default = "default_queue_timeout_secs")]
+    pub queue_timeout_secs: u64,
+}
+
+fn default_queue_timeout_secs() -> u64 {
+    10
+}
+
+/// Default location of the MCP server configuration.
+pub const DEFAULT_MCP_CONFIG_PATH: &str = "mcp.json";
+
+/// Fields accepted on a stdio (`command`) server entry.
+const STDIO_FIELDS: &[&str] = &["command", "args", "env", "requireApproval", "toolLimits"];
+/// Fields accepted on a remote (`url`) server entry.
+const HTTP_FIELDS: &[&str] = &["url", "env", "requireApproval", "toolLimits"];
+/// Fields accepted on a tool's entry in `toolLimits`.
+const TOOL_LIMIT_FIELDS: &[&str] = &["rateLimit", "maxConcurrent", "queueTimeoutSecs"];
+
+/// Load and validate an MCP configuration file.
+///
+/// Every problem in the file is reported, one per line, rather than just
+/// the first serde error.
 pub fn load_mcp_config(path: impl AsRef<Path>) -> anyhow::Result<McpConfig> {
-    let txt = fs::read_to_string(path)?;
-    Ok(serde_json::from_str(&txt)?)
+    let path = path.as_ref();
+    let txt = fs::read_to_string(path)
+        .with_context(|| format!("failed to read MCP config {}", path.display()))?;
+    parse_mcp_config(&txt).with_context(|| format!("invalid MCP config {}", path.display()))
+}
+
+/// Parse and validate MCP configuration JSON.
+pub fn parse_mcp_config(txt: &str) -> anyhow::Result<McpConfig> {
+    let value: serde_json::Value =
+        serde_json::from_str(txt).map_err(|e| anyhow!("not valid JSON: {e}"))?;
+
+    let errors = validate_mcp_config(&value);
+    if !errors.is_empty() {
+        bail!("{}", errors.join("\n"));
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Check the structure of parsed MCP configuration.
+///
+/// Returns one message per problem found (empty when valid).
+pub fn validate_mcp_config(value: &serde_json::Value) -> Vec<String> {
+    let Some(root) = value.as_object() else {
+        return vec!["top level must be an object with an 'mcpServers' field".to_string()];
+    };
+
+    let mut errors: Vec<String> = root
+        .keys()
+        .filter(|k| k.as_str() != "mcpServers")
+        .map(|k| format!("unknown top-level field '{k}' (expected 'mcpServers')"))
+        .collect();
+
+    let servers = match root.get("mcpServers") {
+        Some(serde_json::Value::Object(servers)) => servers,
+        Some(_) => {
+            errors.push("'mcpServers' must be an object of server entries".to_string());
+            return errors;
+        }
+        None => {
+            errors.push("missing required field 'mcpServers'".to_string());
+            return errors;
+        }
+    };
+
+    for (name, entry) in servers {
+        validate_server_entry(name, entry, &mut errors);
+    }
+    errors
+}
+
+fn validate_server_entry(name: &str, entry: &serde_json::Value, errors: &mut Vec<String>) {
+    let Some(fields) = entry.as_object() else {
+        errors.push(format!("server '{name}' must be an object"));
+        return;
+    };
+
+    let allowed = match (fields.get("command"), fields.get("url")) {
+        (Some(_), Some(_)) => {
+            errors.push(format!(
+                "server '{name}' must specify either 'command' or 'url', not both"
+            ));
+            return;
+        }
+        (None, None) => {
+            errors.push(format!(
+                "server '{name}' must specify either 'command' or 'url'"
+            ));
+            return;
+        }
+        (Some(command), None) => {
+            match command.as_str() {
+                Some(c) if !c.trim().is_empty() => {}
+                Some(_) => errors.push(format!("server '{name}': 'command' must not be empty")),
+                None => errors.push(format!("server '{name}': 'command' must be a string")),
+            }
+            if let Some(args) = fields.get("args") {
+                let all_strings = args
+                    .as_array()
+                    .is_some_and(|a| a.iter().all(serde_json::Value::is_string));
+                if !all_strings {
+                    errors.push(format!("server '{name}': 'args' must be an array of strings"));
+                }
+            }
+            STDIO_FIELDS
+        }
+        (None, Some(url)) => {
+            match url.as_str() {
+                Some(u) => match Url::parse(u) {
+                    Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+                    Ok(parsed) => errors.push(format!(
+                        "server '{name}': 'url' must use http or https, not '{}'",
+                        parsed.scheme()
+                    )),
+                    Err(e) => errors.push(format!("server '{name}': invalid 'url': {e}")),
+                },
+                None => errors.push(format!("server '{name}': 'url' must be a string")),
+            }
+            HTTP_FIELDS
+        }
+    };
+
+    if let Some(env) = fields.get("env") {
+        let all_strings = env
+            .as_object()
+            .is_some_and(|m| m.values().all(serde_json::Value::is_string));
+        if !all_strings {
+            errors.push(format!(
+                "server '{name}': 'env' must be an object of string values"
+            ));
+        }
+    }
+
+    if let Some(tools) = fields.get("requireApproval") {
+        let all_strings = tools
+            .as_array()
+            .is_some_and(|a| a.iter().all(serde_json::Value::is_string));
+        if !all_strings {
+            errors.push(format!(
+                "server '{name}': 'requireApproval' must be an array of tool names"
+            ));
+        }
+    }
+
+    match fields.get("toolLimits") {
+        Some(serde_json::Value::Object(tools)) => {
+            for (tool, limits) in tools {
+                validate_tool_limits(name, tool, limits, errors);
+            }
+        }
+        Some(_) => errors.push(format!(
+            "server '{name}': 'toolLimits' must be an object of tool limits"
+        )),
+        None => {}
+    }
+
+    for key in fields.keys() {
+        if !allowed.contains(&key.as_str()) {
+            errors.push(format!(
+                "server '{name}' has unknown field '{key}' (expected one of: {})",
+                allowed.join(", ")
+            ));
+        }
+    }
+}
+
+fn validate_tool_limits(
+    name: &str,
+    tool: &str,
+    limits: &serde_json::Value,
+    errors: &mut Vec<String>,
+) {
+    let Some(fields) = limits.as_object() else {
+        errors.push(format!("server '{name}': limits of tool '{tool}' must be an object"));
+        return;
+    };
+
+    if let Some(rate) = fields.get("rateLimit")
+        && !rate.as_f64().is_some_and(|r| r > 0.0 && r.is_finite())
+    {
+        errors.push(format!(
+            "server '{name}': 'rateLimit' of tool '{tool}' must be a positive number"
+        ));
+    }
+    if let Some(max) = fields.get("maxConcurrent")
+        && !max.as_u64().is_some_and(|m| m > 0)
+    {
+        errors.push(format!(
+            "server '{name}': 'maxConcurrent' of tool '{tool}' must be a positive integer"
+        ));
+    }
+    if let Some(timeout) = fields.get("queueTimeoutSecs")
+        && timeout.as_u64().is_none()
+    {
+        errors.push(format!(
+            "server '{name}': 'queueTimeoutSecs' of tool '{tool}' must be a whole number"
+        ));
+    }
+
+    for key in fields.keys() {
+        if !TOOL_LIMIT_FIELDS.contains(&key.as_str()) {
+            errors.push(format!(
+                "server '{name}': limits of tool '{tool}' have unknown field '{key}' \
+                 (expected one of: {})",
+                TOOL_LIMIT_FIELDS.join(", ")
+            ));
+        }
+    }
 }
 
 /// Expand "${VAR}" placeholders from the process environment.
@@ -51,3 +317,146 @@ pub fn expand_env_map(map: &HashMap<String, String>) -> HashMap<String, String>
         .map(|(k, v)| (k.clone(), expand_env_placeholders(v)))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors_for(json: &str) -> Vec<String> {
+        validate_mcp_config(&serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let cfg = parse_mcp_config(
+            r#"{"mcpServers": {
+                "time": {"command": "npx", "args": ["-y", "@mcpcentral/mcp-time"]},
+                "tavily": {"url": "https://mcp.tavily.com/mcp/", "env": {"KEY": "x"}}
+            }}"#,
+        )
+        .unwrap();
+        assert_eq!(cfg.mcp_servers.len(), 2);
+    }
+
+    #[test]
+    fn test_require_approval() {
+        let cfg = parse_mcp_config(
+            r#"{"mcpServers": {
+                "fs": {"command": "mcp-fs", "requireApproval": ["write_file"]},
+                "shell": {"url": "https://shell.example/mcp", "requireApproval": ["*"]},
+                "time": {"command": "mcp-time"}
+            }}"#,
+        )
+        .unwrap();
+        assert!(cfg.mcp_servers["fs"].requires_approval("write_file"));
+        assert!(!cfg.mcp_servers["fs"].requires_approval("read_file"));
+        assert!(cfg.mcp_servers["shell"].requires_approval("exec"));
+        assert!(!cfg.mcp_servers["time"].requires_approval("now"));
+
+        assert_eq!(
+            errors_for(r#"{"mcpServers": {"fs": {"command": "x", "requireApproval": "all"}}}"#),
+            vec!["server 'fs': 'requireApproval' must be an array of tool names"]
+        );
+    }
+
+    #[test]
+    fn test_tool_limits() {
+        let cfg = parse_mcp_config(
+            r#"{"mcpServers": {
+                "search": {"url": "https://search.example/mcp", "toolLimits": {
+                    "query": {"rateLimit": 0.5, "maxConcurrent": 2},
+                    "crawl": {"maxConcurrent": 1, "queueTimeoutSecs": 0}
+                }}
+            }}"#,
+        )
+        .unwrap();
+        let limits = cfg.mcp_servers["search"].tool_limits();
+        assert_eq!(limits["query"].rate_limit, Some(0.5));
+        assert_eq!(limits["query"].max_concurrent, Some(2));
+        assert_eq!(limits["query"].queue_timeout_secs, 10);
+        assert_eq!(limits["crawl"].rate_limit, None);
+        assert_eq!(limits["crawl"].queue_timeout_secs, 0);
+
+        assert_eq!(
+            errors_for(
+                r#"{"mcpServers": {"fs": {"command": "x", "toolLimits": {
+                    "read": {"rateLimit": 0, "maxConcurrent": 1.5, "burst": 3}
+                }}}}"#
+            ),
+            vec![
+                "server 'fs': 'rateLimit' of tool 'read' must be a positive number",
+                "server 'fs': 'maxConcurrent' of tool 'read' must be a positive integer",
+                "server 'fs': limits of tool 'read' have unknown field 'burst' \
+                 (expected one of: rateLimit, maxConcurrent, queueTimeoutSecs)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_command_and_url() {
+        assert_eq!(
+            errors_for(r#"{"mcpServers": {"time": {"args": []}}}"#),
+            vec!["server 'time' must specify either 'command' or 'url'"]
+        );
+    }
+
+    #[test]
+    fn test_both_command_and_url() {
+        assert_eq!(
+            errors_for(r#"{"mcpServers": {"time": {"command": "npx", "url": "http://x"}}}"#),
+            vec!["server 'time' must specify either 'command' or 'url', not both"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_fields() {
+        assert_eq!(
+            errors_for(r#"{"mcpServers": {"time": {"command": "npx", "cwd": "/tmp"}}, "extra": 1}"#),
+            vec![
+                "unknown top-level field 'extra' (expected 'mcpServers')",
+                "server 'time' has unknown field 'cwd' \
+                 (expected one of: command, args, env, requireApproval, toolLimits)",
+            ]
+        );
+        // `args` only makes sense for stdio servers
+        assert_eq!(
+            errors_for(r#"{"mcpServers": {"web": {"url": "https://x", "args": ["a"]}}}"#),
+            vec![
+                "server 'web' has unknown field 'args' \
+                 (expected one of: url, env, requireApproval, toolLimits)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bad_field_types() {
+        assert_eq!(
+            errors_for(
+                r#"{"mcpServers": {"time": {"command": "", "args": "-y", "env": {"A": 1}}}}"#
+            ),
+            vec![
+                "server 'time': 'command' must not be empty",
+                "server 'time': 'args' must be an array of strings",
+                "server 'time': 'env' must be an object of string values",
+            ]
+        );
+        assert_eq!(
+            errors_for(r#"{"mcpServers": {"web": {"url": "ftp://example.com"}}}"#),
+            vec!["server 'web': 'url' must use http or https, not 'ftp'"]
+        );
+    }
+
+    #[test]
+    fn test_missing_servers_and_bad_json() {
+        assert_eq!(
+            errors_for(r#"{"servers": {}}"#),
+            vec![
+                "unknown top-level field 'servers' (expected 'mcpServers')",
+                "missing required field 'mcpServers'",
+            ]
+        );
+
+        let err = parse_mcp_config(r#"{"mcpServers": {"#).unwrap_err();
+        assert!(err.to_string().starts_with("not valid JSON:"), "{err}");
+    }
+}
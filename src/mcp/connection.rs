@@ -0,0 +1,460 @@
+//! A connection to one MCP server, with health tracking and reconnection.
+//!
+//! A server whose probe fails (or whose transport closes mid-call) is marked
+//! disconnected: its tools are hidden from the model and calls fail with
+//! [`ServerDownError`]. The health monitor then reconnects with exponential
+//! backoff, re-discovering tools on success, and gives up after
+//! [`MAX_RECONNECT_ATTEMPTS`].
+
+use crate::mcp::config::{McpServerEntry, expand_env_map};
+use crate::mcp::limits::ToolLimiter;
+use anyhow::{Context, anyhow};
+use rmcp::{
+    model::{
+        GetPromptRequestParam, GetPromptResult, JsonObject, Prompt, ReadResourceRequestParam,
+        ReadResourceResult, Resource, Tool,
+    },
+    service::ServiceExt,
+    transport::{StreamableHttpClientTransport, TokioChildProcess},
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use url::Url;
+
+pub(crate) type DynClientService = rmcp::service::RunningService<
+    rmcp::service::RoleClient,
+    Box<dyn rmcp::service::DynService<rmcp::service::RoleClient>>,
+>;
+
+/// Reconnection attempts before a server is given up on.
+pub const MAX_RECONNECT_ATTEMPTS: u32 = 8;
+/// Delay before the first reconnection attempt; doubles on each failure.
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest delay between reconnection attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Connection state of an MCP server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerState {
+    Connected,
+    /// Down; reconnection is in progress
+    Disconnected,
+    /// Down; reconnection attempts are exhausted
+    Failed,
+}
+
+/// Diagnostics for one MCP server.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStatus {
+    pub state: ServerState,
+    /// Tools discovered on the last successful connection
+    pub tool_count: usize,
+    pub reconnect_attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// A tool was called on an MCP server that is not connected.
+#[derive(Debug, thiserror::Error)]
+#[error("MCP server '{server}' is down: {reason}")]
+pub struct ServerDownError {
+    pub server: String,
+    pub reason: String,
+}
+
+struct Inner {
+    service: Option<Arc<DynClientService>>,
+    tools: Vec<Tool>,
+    state: ServerState,
+    reconnect_attempts: u32,
+    last_error: Option<String>,
+    next_attempt: Instant,
+}
+
+pub struct McpConnection {
+    name: String,
+    entry: McpServerEntry,
+    // Raw tool name -> limiter, from the entry's `toolLimits`
+    limiters: HashMap<String, Arc<ToolLimiter>>,
+    inner: RwLock<Inner>,
+}
+
+impl std::fmt::Debug for McpConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpConnection")
+            .field("name", &self.name)
+            .field("status", &self.status())
+            .finish_non_exhaustive()
+    }
+}
+
+impl McpConnection {
+    /// Connect to `entry` and discover its tools.
+    pub async fn connect(name: &str, entry: McpServerEntry) -> anyhow::Result<Self> {
+        let (service, tools) = open(name, &entry).await?;
+        Ok(Self {
+            name: name.to_string(),
+            limiters: limiters(&entry),
+            entry,
+            inner: RwLock::new(Inner {
+                service: Some(service),
+                tools,
+                state: ServerState::Connected,
+                reconnect_attempts: 0,
+                last_error: None,
+                next_attempt: Instant::now(),
+            }),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The configuration this connection was opened with.
+    pub fn entry(&self) -> &McpServerEntry {
+        &self.entry
+    }
+
+    /// The limiter of the server's `tool`, if `toolLimits` lists it.
+    pub fn limiter(&self, tool: &str) -> Option<Arc<ToolLimiter>> {
+        self.limiters.get(tool).map(Arc::clone)
+    }
+
+    /// Tools discovered on the last successful connection.
+    ///
+    /// Kept while the server is down so calls to them can report the outage
+    /// instead of "unknown tool".
+    pub fn known_tools(&self) -> Vec<Tool> {
+        self.inner.read().unwrap().tools.clone()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.inner.read().unwrap().state == ServerState::Connected
+    }
+
+    pub fn status(&self) -> ServerStatus {
+        let inner = self.inner.read().unwrap();
+        ServerStatus {
+            state: inner.state,
+            tool_count: inner.tools.len(),
+            reconnect_attempts: inner.reconnect_attempts,
+            last_error: inner.last_error.clone(),
+        }
+    }
+
+    /// The live client, or [`ServerDownError`] if the server is down.
+    pub fn service(&self) -> Result<Arc<DynClientService>, ServerDownError> {
+        let service = {
+            let inner = self.inner.read().unwrap();
+            match (&inner.service, inner.state) {
+                (Some(service), ServerState::Connected) => Arc::clone(service),
+                _ => return Err(self.down_error(&inner)),
+            }
+        };
+
+        // A crashed stdio server shows up as a closed transport
+        if service.is_transport_closed() {
+            self.mark_down("transport closed".to_string());
+            return Err(self.down_error(&self.inner.read().unwrap()));
+        }
+        Ok(service)
+    }
+
+    fn down_error(&self, inner: &Inner) -> ServerDownError {
+        ServerDownError {
+            server: self.name.clone(),
+            reason: inner
+                .last_error
+                .clone()
+                .unwrap_or_else(|| "not connected".to_string()),
+        }
+    }
+
+    /// Mark the server as down and schedule a reconnection.
+    pub fn mark_down(&self, reason: String) {
+        let mut inner = self.inner.write().unwrap();
+        if inner.state != ServerState::Connected {
+            return;
+        }
+        tracing::warn!(server = %self.name, error = %reason, "MCP server is down");
+        inner.service = None;
+        inner.state = ServerState::Disconnected;
+        inner.reconnect_attempts = 0;
+        inner.last_error = Some(reason);
+        inner.next_attempt = Instant::now();
+    }
+
+    /// Stop the server for good: its transport is closed (ending a stdio
+    /// server's process) and it is not reconnected.
+    pub fn close(&self) {
+        let service = {
+            let mut inner = self.inner.write().unwrap();
+            inner.state = ServerState::Failed;
+            inner.last_error = Some("closed".to_string());
+            inner.service.take()
+        };
+        if let Some(service) = service {
+            tracing::debug!(server = %self.name, "Closing MCP server");
+            service.cancellation_token().cancel();
+        }
+    }
+
+    /// Probe the server with a `tools/list` call.
+    pub async fn probe(&self, timeout: Duration) -> Result<Vec<Tool>, String> {
+        let service = self.service().map_err(|e| e.reason)?;
+        match tokio::time::timeout(timeout, service.list_tools(Default::default())).await {
+            Ok(Ok(result)) => Ok(result.tools),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err(format!("no response within {}s", timeout.as_secs_f32())),
+        }
+    }
+
+    /// Resources the server lists, or none if it does not serve resources.
+    pub async fn list_resources(&self) -> anyhow::Result<Vec<Resource>> {
+        let service = self.service()?;
+        if !service
+            .peer_info()
+            .is_some_and(|info| info.capabilities.resources.is_some())
+        {
+            return Ok(Vec::new());
+        }
+        service
+            .list_all_resources()
+            .await
+            .with_context(|| format!("resources/list failed for MCP server '{}'", self.name))
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> anyhow::Result<ReadResourceResult> {
+        self.service()?
+            .read_resource(ReadResourceRequestParam {
+                uri: uri.to_string(),
+            })
+            .await
+            .with_context(|| format!("resources/read failed for {}::{uri}", self.name))
+    }
+
+    /// Prompts the server lists, or none if it does not serve prompts.
+    pub async fn list_prompts(&self) -> anyhow::Result<Vec<Prompt>> {
+        let service = self.service()?;
+        if !service
+            .peer_info()
+            .is_some_and(|info| info.capabilities.prompts.is_some())
+        {
+            return Ok(Vec::new());
+        }
+        service
+            .list_all_prompts()
+            .await
+            .with_context(|| format!("prompts/list failed for MCP server '{}'", self.name))
+    }
+
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: Option<JsonObject>,
+    ) -> anyhow::Result<GetPromptResult> {
+        self.service()?
+            .get_prompt(GetPromptRequestParam {
+                name: name.to_string(),
+                arguments,
+            })
+            .await
+            .with_context(|| format!("prompts/get failed for {}::{name}", self.name))
+    }
+
+    /// One health monitor pass: probe a connected server (refreshing its
+    /// tools), or attempt a due reconnection of a disconnected one.
+    pub async fn check(&self, timeout: Duration) {
+        let state = self.inner.read().unwrap().state;
+        match state {
+            ServerState::Connected => match self.probe(timeout).await {
+                Ok(tools) => self.inner.write().unwrap().tools = tools,
+                Err(e) => self.mark_down(e),
+            },
+            ServerState::Disconnected => self.reconnect_if_due(Instant::now()).await,
+            ServerState::Failed => {}
+        }
+    }
+
+    async fn reconnect_if_due(&self, now: Instant) {
+        if now < self.inner.read().unwrap().next_attempt {
+            return;
+        }
+
+        let result = open(&self.name, &self.entry).await;
+        let mut inner = self.inner.write().unwrap();
+        match result {
+            Ok((service, tools)) => {
+                tracing::info!(
+                    server = %self.name,
+                    tool_count = tools.len(),
+                    "Reconnected to MCP server"
+                );
+                inner.service = Some(service);
+                inner.tools = tools;
+                inner.state = ServerState::Connected;
+                inner.reconnect_attempts = 0;
+                inner.last_error = None;
+            }
+            Err(e) => {
+                inner.reconnect_attempts += 1;
+                inner.last_error = Some(format!("{e:#}"));
+                if inner.reconnect_attempts >= MAX_RECONNECT_ATTEMPTS {
+                    tracing::error!(
+                        server = %self.name,
+                        attempts = inner.reconnect_attempts,
+                        error = %e,
+                        "Giving up on MCP server"
+                    );
+                    inner.state = ServerState::Failed;
+                } else {
+                    inner.next_attempt = now + backoff(inner.reconnect_attempts);
+                }
+            }
+        }
+    }
+}
+
+/// Delay after `attempts` failed reconnections.
+fn backoff(attempts: u32) -> Duration {
+    BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+        .min(MAX_BACKOFF)
+}
+
+/// A limiter for each tool in `entry`'s `toolLimits`.
+fn limiters(entry: &McpServerEntry) -> HashMap<String, Arc<ToolLimiter>> {
+    entry
+        .tool_limits()
+        .iter()
+        .map(|(tool, limits)| (tool.clone(), Arc::new(ToolLimiter::new(limits))))
+        .collect()
+}
+
+/// Start the server described by `entry` and list its tools.
+async fn open(
+    name: &str,
+    entry: &McpServerEntry,
+) -> anyhow::Result<(Arc<DynClientService>, Vec<Tool>)> {
+    let service = match entry {
+        McpServerEntry::Stdio { command, args, env, .. } => {
+            let env = expand_env_map(env);
+
+            let mut cmd = Command::new(command);
+            cmd.args(args);
+
+            for (k, v) in env {
+                cmd.env(k, v);
+            }
+
+            // rmcp docs show TokioChildProcess + configure pattern for adding args
+            let transport = TokioChildProcess::new(cmd)?;
+            // store as dyn to keep a homogeneous collection
+            ().into_dyn()
+                .serve(transport)
+                .await
+                .with_context(|| format!("failed to connect stdio MCP server '{name}'"))?
+        }
+
+        McpServerEntry::RemoteHttp { url, env, .. } => {
+            let env = expand_env_map(env);
+
+            // Tavily expects ?tavilyApiKey=... (per your config contract).
+            // Keep the key OUT of logs.
+            let api_key = env
+                .get("TAVILY_API_KEY")
+                .cloned()
+                .filter(|s| !s.is_empty())
+                .ok_or_else(|| anyhow!("remote MCP '{name}' missing TAVILY_API_KEY"))?;
+
+            let mut u = Url::parse(url)
+                .with_context(|| format!("invalid url for remote MCP '{name}': {url}"))?;
+
+            // If URL already has query, we just append.
+            u.query_pairs_mut().append_pair("tavilyApiKey", &api_key);
+
+            // rmcp streamable http transport from_uri
+            let transport = StreamableHttpClientTransport::from_uri(u.to_string());
+            ().into_dyn()
+                .serve(transport)
+                .await
+                .with_context(|| format!("failed to connect remote MCP server '{name}'"))?
+        }
+    };
+
+    let tools = service
+        .list_tools(Default::default())
+        .await
+        .with_context(|| format!("tools/list failed for MCP server '{name}'"))?
+        .tools;
+
+    Ok((Arc::new(service), tools))
+}
+
+#[cfg(test)]
+impl McpConnection {
+    /// A connection that has lost its server after discovering `tools`.
+    pub(crate) fn disconnected(name: &str, entry: McpServerEntry, tools: Vec<Tool>) -> Self {
+        Self {
+            name: name.to_string(),
+            limiters: limiters(&entry),
+            entry,
+            inner: RwLock::new(Inner {
+                service: None,
+                tools,
+                state: ServerState::Disconnected,
+                reconnect_attempts: 0,
+                last_error: Some("process exited".to_string()),
+                next_attempt: Instant::now(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn missing_server() -> McpServerEntry {
+        McpServerEntry::Stdio {
+            command: "/nonexistent/mcp-server".to_string(),
+            args: vec![],
+            env: Default::default(),
+            require_approval: vec![],
+            tool_limits: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(4), Duration::from_secs(8));
+        assert_eq!(backoff(20), MAX_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn test_reconnection_is_bounded() {
+        let conn = McpConnection::disconnected("ghost", missing_server(), vec![]);
+        let mut now = Instant::now();
+
+        for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+            conn.reconnect_if_due(now).await;
+            let status = conn.status();
+            assert_eq!(status.reconnect_attempts, attempt);
+            assert!(status.last_error.is_some());
+            now += MAX_BACKOFF;
+        }
+        assert_eq!(conn.status().state, ServerState::Failed);
+
+        // Not due yet: no attempt is made
+        let conn = McpConnection::disconnected("ghost", missing_server(), vec![]);
+        conn.reconnect_if_due(now).await;
+        conn.reconnect_if_due(now).await;
+        assert_eq!(conn.status().reconnect_attempts, 1);
+    }
+}
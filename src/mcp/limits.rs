@@ -0,0 +1,122 @@
+//! Per-tool rate and concurrency limits.
+//!
+//! A tool's `toolLimits` entry in `mcp.json` becomes a [`ToolLimiter`]: a
+//! token bucket refilled at `rateLimit` calls per second and a semaphore of
+//! `maxConcurrent` permits. Calls queue for both up to `queueTimeoutSecs`,
+//! then fail with [`ToolRateLimitedError`], which tells the model to retry.
+
+use crate::mcp::config::ToolLimits;
+use governor::{DefaultDirectRateLimiter, Quota};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// A call of a limited tool did not get its turn within the queue timeout.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "tool '{tool}' is rate limited: no call slot freed up within {waited:?}; \
+     retry the call later"
+)]
+pub struct ToolRateLimitedError {
+    pub tool: String,
+    pub waited: Duration,
+}
+
+/// Enforces one tool's [`ToolLimits`].
+#[derive(Debug)]
+pub struct ToolLimiter {
+    bucket: Option<DefaultDirectRateLimiter>,
+    slots: Option<Arc<Semaphore>>,
+    queue_timeout: Duration,
+}
+
+/// Held while a limited call runs; frees its concurrency slot when dropped.
+#[derive(Debug)]
+pub struct ToolPermit {
+    _slot: Option<OwnedSemaphorePermit>,
+}
+
+impl ToolLimiter {
+    pub fn new(limits: &ToolLimits) -> Self {
+        let bucket = limits.rate_limit.and_then(|rate| {
+            let period = Duration::try_from_secs_f64(1.0 / rate).ok()?;
+            #[allow(clippy::cast_sign_loss)]
+            let burst = NonZeroU32::new(rate.ceil() as u32).unwrap_or(NonZeroU32::MIN);
+            Some(DefaultDirectRateLimiter::direct(
+                Quota::with_period(period)?.allow_burst(burst),
+            ))
+        });
+        Self {
+            bucket,
+            slots: limits
+                .max_concurrent
+                .map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            queue_timeout: Duration::from_secs(limits.queue_timeout_secs),
+        }
+    }
+
+    /// Wait for a token and a concurrency slot to call `tool`.
+    ///
+    /// Fails with [`ToolRateLimitedError`] if both are not available within
+    /// the queue timeout.
+    pub async fn acquire(&self, tool: &str) -> Result<ToolPermit, ToolRateLimitedError> {
+        let wait = async {
+            if let Some(bucket) = &self.bucket {
+                bucket.until_ready().await;
+            }
+            match &self.slots {
+                Some(slots) => Arc::clone(slots).acquire_owned().await.ok(),
+                None => None,
+            }
+        };
+        tokio::time::timeout(self.queue_timeout, wait)
+            .await
+            .map(|slot| ToolPermit { _slot: slot })
+            .map_err(|_| {
+                tracing::warn!(tool, "Tool call rejected by its rate limit");
+                ToolRateLimitedError {
+                    tool: tool.to_string(),
+                    waited: self.queue_timeout,
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn limits(rate_limit: Option<f64>, max_concurrent: Option<usize>, timeout: u64) -> ToolLimits {
+        ToolLimits {
+            rate_limit,
+            max_concurrent,
+            queue_timeout_secs: timeout,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calls_beyond_the_burst_queue() {
+        let limiter = ToolLimiter::new(&limits(Some(4.0), None, 10));
+        let started = Instant::now();
+        for _ in 0..6 {
+            limiter.acquire("search").await.unwrap();
+        }
+        // Four calls fit the burst; the next two wait a quarter second each
+        assert!(started.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_busy_slots_fail_without_queueing() {
+        let limiter = ToolLimiter::new(&limits(None, Some(1), 0));
+        let held = limiter.acquire("crawl").await.unwrap();
+
+        let err = limiter.acquire("crawl").await.unwrap_err();
+        assert_eq!(err.tool, "crawl");
+        assert!(err.to_string().contains("retry"), "{err}");
+
+        drop(held);
+        assert!(limiter.acquire("crawl").await.is_ok());
+    }
+}
@@ -27,5 +27,8 @@
 //! Tools are namespaced by server name: `server_name::tool_name`
 //! (e.g., `time::now`, `tavily::search`).
 
+pub mod cache;
 pub mod config;
+pub mod connection;
+pub mod limits;
 pub mod registry;
@@ -1,14 +1,23 @@
-use crate::mcp::config::{McpServerEntry, expand_env_map, load_mcp_config};
+use crate::mcp::config::{McpConfig, ToolLimits, load_mcp_config};
+use crate::mcp::connection::{McpConnection, ServerDownError, ServerStatus};
+use crate::mcp::limits::{ToolLimiter, ToolRateLimitedError};
+use crate::session::{Session, ToolStateHandle};
 use anyhow::{Context, anyhow};
 use async_trait::async_trait;
-use rmcp::{
-    model::{CallToolRequestParam, Tool},
-    service::ServiceExt,
-    transport::{StreamableHttpClientTransport, TokioChildProcess},
+use rmcp::model::{
+    CallToolRequestParam, GetPromptResult, JsonObject, Prompt, ReadResourceResult, Resource, Tool,
 };
-use std::{collections::HashMap, sync::Arc};
-use tokio::process::Command;
-use url::Url;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// How often the health monitor checks each MCP server.
+pub const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Time each server gets to answer a health monitor probe.
+const HEALTH_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[async_trait]
 pub trait NativeTool: Send + Sync + std::fmt::Debug {
@@ -16,29 +25,93 @@ pub trait NativeTool: Send + Sync + std::fmt::Debug {
     fn description(&self) -> &str;
     fn schema(&self) -> serde_json::Value;
     async fn call(&self, args: serde_json::Value) -> anyhow::Result<serde_json::Value>;
+
+    /// Call the tool with access to its state in the current session.
+    ///
+    /// Stateful tools override this to keep state across turns; the default
+    /// ignores the state and calls [`NativeTool::call`].
+    async fn call_with_state(
+        &self,
+        args: serde_json::Value,
+        state: ToolStateHandle,
+    ) -> anyhow::Result<serde_json::Value> {
+        let _ = state;
+        self.call(args).await
+    }
 }
 
-type DynClientService = rmcp::service::RunningService<
-    rmcp::service::RoleClient,
-    Box<dyn rmcp::service::DynService<rmcp::service::RoleClient>>,
->;
+type ServiceMap = HashMap<String, Arc<McpConnection>>;
+
+/// Decides by namespaced name whether a tool may be advertised and called.
+pub type ToolFilter = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// A tool was called that the registry's filter does not allow.
+#[derive(Debug, thiserror::Error)]
+#[error("tool '{tool}' is not allowed for this agent")]
+pub struct ToolNotAllowedError {
+    pub tool: String,
+}
+
+/// No connected server serves the requested resource or prompt.
+#[derive(Debug, thiserror::Error)]
+#[error("unknown MCP {kind}: {id}")]
+pub struct UnknownMcpItemError {
+    pub kind: &'static str,
+    pub id: String,
+}
+
+/// A resource or prompt of one server, addressed as `server::name`.
+///
+/// Resources are named by their URI (`docs::file:///guide.md`), prompts by
+/// their name (`git::commit-message`).
+#[derive(Debug, Clone, Serialize)]
+pub struct NamespacedItem<T> {
+    pub id: String,
+    #[serde(flatten)]
+    pub item: T,
+}
+
+/// Separates the server from the resource URI or prompt name in an ID.
+const ITEM_SEPARATOR: &str = "::";
+
+/// What a [`McpRegistry::reload`] changed.
+#[derive(Debug, Default, Serialize)]
+pub struct ReloadSummary {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Servers whose configuration changed, reconnected with the new one
+    pub restarted: Vec<String>,
+    pub unchanged: Vec<String>,
+}
 
 #[derive(Clone)]
 pub struct McpRegistry {
-    services: Arc<HashMap<String, Arc<DynClientService>>>,
+    // Swapped wholesale on reload; clones of the registry share the swap.
+    // Callers holding a connection keep it alive until their call finishes.
+    services: Arc<RwLock<Arc<ServiceMap>>>,
+    // Serializes reloads
+    reload_lock: Arc<tokio::sync::Mutex<()>>,
+    // Tools not served by a connection (test and native tools):
     // namespaced_tool_name -> (server_name, tool_name)
     tool_index: Arc<HashMap<String, (String, String)>>,
     tools: Arc<Vec<(String, Tool)>>, // (namespaced_name, Tool)
     // namespaced_tool_name -> NativeTool
     native_tools: Arc<HashMap<String, Arc<dyn NativeTool>>>,
+    // Tools not served by a connection whose calls need a user's approval
+    approval_required: Arc<HashSet<String>>,
+    // Limits of tools not served by a connection
+    tool_limiters: Arc<HashMap<String, Arc<ToolLimiter>>>,
+    // Hides and blocks tools outside an agent's policy; `None` allows all
+    tool_filter: Option<ToolFilter>,
 }
 
 impl std::fmt::Debug for McpRegistry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("McpRegistry")
-            .field("tool_count", &self.tools.len())
-            .field("service_count", &self.services.len())
+            .field("tool_count", &self.tools().len())
+            .field("service_count", &self.services().len())
             .field("native_tool_count", &self.native_tools.len())
+            .field("filtered", &self.tool_filter.is_some())
             .finish()
     }
 }
@@ -49,98 +122,109 @@ impl McpRegistry {
         Self::from_config(&cfg).await
     }
 
-    pub async fn from_config(cfg: &crate::mcp::config::McpConfig) -> anyhow::Result<Self> {
-        // 1) connect all servers
-        let mut services: HashMap<String, Arc<DynClientService>> = HashMap::new();
-
+    pub async fn from_config(cfg: &McpConfig) -> anyhow::Result<Self> {
+        // Connect all servers; each discovers its own tools
+        let mut services = ServiceMap::new();
         for (name, entry) in &cfg.mcp_servers {
-            let svc = match entry {
-                McpServerEntry::Stdio { command, args, env } => {
-                    let env = expand_env_map(env);
+            let conn = McpConnection::connect(name, entry.clone()).await?;
+            services.insert(name.clone(), Arc::new(conn));
+        }
+        Ok(Self::with_services(services))
+    }
 
-                    let mut cmd = Command::new(command);
-                    cmd.args(args);
+    /// Creates an empty registry for testing.
+    pub fn new_empty() -> Self {
+        Self::with_services(ServiceMap::new())
+    }
 
-                    for (k, v) in env {
-                        cmd.env(k, v);
-                    }
+    fn with_services(services: ServiceMap) -> Self {
+        Self {
+            services: Arc::new(RwLock::new(Arc::new(services))),
+            reload_lock: Arc::new(tokio::sync::Mutex::new(())),
+            tool_index: Arc::new(HashMap::new()),
+            tools: Arc::new(Vec::new()),
+            native_tools: Arc::new(HashMap::new()),
+            approval_required: Arc::new(HashSet::new()),
+            tool_limiters: Arc::new(HashMap::new()),
+            tool_filter: None,
+        }
+    }
 
-                    // rmcp docs show TokioChildProcess + configure pattern for adding args
-                    let transport = TokioChildProcess::new(cmd)?;
-                    // store as dyn to keep a homogeneous collection
-                    ().into_dyn()
-                        .serve(transport)
-                        .await
-                        .with_context(|| format!("failed to connect stdio MCP server '{name}'"))?
-                }
+    /// Only advertise and allow calls to tools `filter` accepts.
+    ///
+    /// Rejected calls fail with [`ToolNotAllowedError`].
+    #[must_use]
+    pub fn with_tool_filter(
+        mut self,
+        filter: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.tool_filter = Some(Arc::new(filter));
+        self
+    }
 
-                McpServerEntry::RemoteHttp { url, env } => {
-                    let env = expand_env_map(env);
+    fn is_allowed(&self, namespaced_tool: &str) -> bool {
+        self.tool_filter
+            .as_ref()
+            .is_none_or(|filter| filter(namespaced_tool))
+    }
 
-                    // Tavily expects ?tavilyApiKey=... (per your config contract).
-                    // Keep the key OUT of logs.
-                    let api_key = env
-                        .get("TAVILY_API_KEY")
-                        .cloned()
-                        .filter(|s| !s.is_empty())
-                        .ok_or_else(|| anyhow!("remote MCP '{name}' missing TAVILY_API_KEY"))?;
+    /// Snapshot of the current server connections.
+    fn services(&self) -> Arc<ServiceMap> {
+        Arc::clone(&self.services.read().unwrap())
+    }
 
-                    let mut u = Url::parse(url)
-                        .with_context(|| format!("invalid url for remote MCP '{name}': {url}"))?;
+    /// Re-read `path` and apply it with [`McpRegistry::reload`].
+    pub async fn reload_from_file(&self, path: &str) -> anyhow::Result<ReloadSummary> {
+        let cfg = load_mcp_config(path)?;
+        self.reload(&cfg).await
+    }
 
-                    // If URL already has query, we just append.
-                    u.query_pairs_mut().append_pair("tavilyApiKey", &api_key);
+    /// Bring the running servers in line with `cfg`.
+    ///
+    /// New servers are started, removed ones dropped and servers whose entry
+    /// changed reconnected; the rest keep their connection. The new set is
+    /// swapped in only once every new connection succeeded, so a failed
+    /// reload leaves the registry untouched. Tool calls already running
+    /// finish on the connection they started on.
+    pub async fn reload(&self, cfg: &McpConfig) -> anyhow::Result<ReloadSummary> {
+        let _guard = self.reload_lock.lock().await;
+        let current = self.services();
+        let mut summary = ReloadSummary::default();
+        let mut next = ServiceMap::new();
 
-                    // rmcp streamable http transport from_uri
-                    let transport = StreamableHttpClientTransport::from_uri(u.to_string());
-                    ().into_dyn()
-                        .serve(transport)
+        for (name, entry) in &cfg.mcp_servers {
+            match current.get(name) {
+                Some(conn) if conn.entry() == entry => {
+                    next.insert(name.clone(), Arc::clone(conn));
+                    summary.unchanged.push(name.clone());
+                }
+                existing => {
+                    let conn = McpConnection::connect(name, entry.clone())
                         .await
-                        .with_context(|| format!("failed to connect remote MCP server '{name}'"))?
+                        .with_context(|| format!("reload aborted: MCP server '{name}'"))?;
+                    next.insert(name.clone(), Arc::new(conn));
+                    if existing.is_some() {
+                        summary.restarted.push(name.clone());
+                    } else {
+                        summary.added.push(name.clone());
+                    }
                 }
-            };
-
-            services.insert(name.clone(), Arc::new(svc));
-        }
-
-        // 2) list tools + build index
-        let mut all_tools: Vec<(String, Tool)> = Vec::new();
-        let mut tool_index: HashMap<String, (String, String)> = HashMap::new();
-
-        for (server_name, svc) in &services {
-            // list_tools exists on the rmcp running service in examples
-            let result = svc
-                .list_tools(Default::default())
-                .await
-                .with_context(|| format!("tools/list failed for MCP server '{server_name}'"))?;
-
-            for t in result.tools {
-                let tool_name = t.name.to_string();
-                // Sanitize tool name for OpenAI compatibility
-                // OpenAI requires: ^[a-zA-Z0-9_-]+$ (no colons, dots, or special chars)
-                // Replace :: with __ for namespacing, and sanitize any other invalid chars
-                let ns_name = Self::sanitize_tool_name(&format!("{server_name}__{tool_name}"));
-                tool_index.insert(ns_name.clone(), (server_name.clone(), tool_name));
-                all_tools.push((ns_name, t));
             }
         }
-
-        Ok(Self {
-            services: Arc::new(services),
-            tool_index: Arc::new(tool_index),
-            tools: Arc::new(all_tools),
-            native_tools: Arc::new(HashMap::new()),
-        })
-    }
-
-    /// Creates an empty registry for testing.
-    pub fn new_empty() -> Self {
-        Self {
-            services: Arc::new(HashMap::new()),
-            tool_index: Arc::new(HashMap::new()),
-            tools: Arc::new(Vec::new()),
-            native_tools: Arc::new(HashMap::new()),
-        }
+        summary.removed = current
+            .keys()
+            .filter(|name| !cfg.mcp_servers.contains_key(*name))
+            .cloned()
+            .collect();
+
+        *self.services.write().unwrap() = Arc::new(next);
+        tracing::info!(
+            added = ?summary.added,
+            removed = ?summary.removed,
+            restarted = ?summary.restarted,
+            "Reloaded MCP servers"
+        );
+        Ok(summary)
     }
 
     /// Creates a registry with a single test tool.
@@ -174,13 +258,20 @@ impl McpRegistry {
         tool_index.insert(ns_name, ("test".to_string(), name.to_string()));
 
         Self {
-            services: Arc::new(HashMap::new()),
             tool_index: Arc::new(tool_index),
             tools: Arc::new(tools),
-            native_tools: Arc::new(HashMap::new()),
+            ..Self::new_empty()
         }
     }
 
+    /// Namespace a server's tool as `server__tool`.
+    ///
+    /// `OpenAI` requires tool names to match `^[a-zA-Z0-9_-]+$`, so `__`
+    /// separates the parts and other invalid characters are replaced.
+    fn namespaced(server: &str, tool: &str) -> String {
+        Self::sanitize_tool_name(&format!("{server}__{tool}"))
+    }
+
     /// Sanitize tool names for `OpenAI` API compatibility.
     fn sanitize_tool_name(name: &str) -> String {
         name.chars()
@@ -195,16 +286,239 @@ impl McpRegistry {
             .collect()
     }
 
-    /// Return namespaced tools as `(namespaced_name, Tool)`
-    pub fn tools(&self) -> &[(String, Tool)] {
-        &self.tools
+    /// Close the connection to every MCP server, ending stdio servers.
+    ///
+    /// For registries started for a single run; clones and registries this
+    /// one was merged into lose the servers too.
+    pub fn shutdown(&self) {
+        for conn in self.services().values() {
+            conn.close();
+        }
+    }
+
+    /// Probe every MCP server with a `tools/list` call.
+    ///
+    /// Returns server name -> `Ok` or the failure message. Servers slower
+    /// than `timeout`, or already known to be down, count as failed.
+    pub async fn health_check(&self, timeout: Duration) -> BTreeMap<String, Result<(), String>> {
+        let services = self.services();
+        let probes = services.iter().map(|(name, conn)| async move {
+            (name.clone(), conn.probe(timeout).await.map(|_| ()))
+        });
+        futures::future::join_all(probes).await.into_iter().collect()
+    }
+
+    /// Connection state of every MCP server, for diagnostics.
+    pub fn server_status(&self) -> BTreeMap<String, ServerStatus> {
+        self.services()
+            .iter()
+            .map(|(name, conn)| (name.clone(), conn.status()))
+            .collect()
+    }
+
+    /// Spawn a background task that checks every server each `interval`.
+    ///
+    /// Servers that stop answering are marked down (hiding their tools) and
+    /// reconnected with backoff; tools are re-discovered on every successful
+    /// check. Servers added by a reload are watched from the next tick.
+    pub fn spawn_health_monitor(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let services = Arc::clone(&self.services);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let snapshot = Arc::clone(&services.read().unwrap());
+                let checks = snapshot.values().map(|conn| conn.check(HEALTH_PROBE_TIMEOUT));
+                futures::future::join_all(checks).await;
+            }
+        })
+    }
+
+    /// Return namespaced tools as `(namespaced_name, Tool)`.
+    ///
+    /// Tools of servers that are currently down, and tools the filter
+    /// rejects, are left out.
+    pub fn tools(&self) -> Vec<(String, Tool)> {
+        let mut tools: Vec<(String, Tool)> = self
+            .services()
+            .values()
+            .filter(|conn| conn.is_connected())
+            .flat_map(|conn| {
+                conn.known_tools()
+                    .into_iter()
+                    .map(|t| (Self::namespaced(conn.name(), &t.name), t))
+            })
+            .collect();
+        tools.extend(self.tools.iter().cloned());
+        tools.retain(|(ns_name, _)| self.is_allowed(ns_name));
+        tools
+    }
+
+    /// Input schema of a namespaced tool the registry offers.
+    pub fn tool_schema(&self, namespaced_tool: &str) -> Option<serde_json::Value> {
+        self.tools()
+            .into_iter()
+            .find(|(ns_name, _)| ns_name == namespaced_tool)
+            .and_then(|(_, t)| serde_json::to_value(&*t.input_schema).ok())
+    }
+
+    /// Whether each call of a namespaced tool needs a user's approval.
+    ///
+    /// MCP tools are flagged with their server's `requireApproval` list in
+    /// `mcp.json`, other tools with [`McpRegistry::with_approval_required`].
+    pub fn requires_approval(&self, namespaced_tool: &str) -> bool {
+        self.approval_required.contains(namespaced_tool)
+            || self
+                .resolve(namespaced_tool)
+                .is_some_and(|(conn, tool)| conn.entry().requires_approval(&tool))
+    }
+
+    /// Require a user's approval for each call of a native or test tool.
+    #[must_use]
+    pub fn with_approval_required(mut self, namespaced_tool: &str) -> Self {
+        let mut approval_required = (*self.approval_required).clone();
+        approval_required.insert(namespaced_tool.to_string());
+        self.approval_required = Arc::new(approval_required);
+        self
+    }
+
+    /// Rate and concurrency limits for a native or test tool.
+    ///
+    /// MCP tools are limited by their server's `toolLimits` in `mcp.json`.
+    #[must_use]
+    pub fn with_tool_limits(mut self, namespaced_tool: &str, limits: &ToolLimits) -> Self {
+        let mut tool_limiters = (*self.tool_limiters).clone();
+        tool_limiters.insert(namespaced_tool.to_string(), Arc::new(ToolLimiter::new(limits)));
+        self.tool_limiters = Arc::new(tool_limiters);
+        self
+    }
+
+    /// The limiter of a namespaced tool, if it has limits.
+    fn limiter(&self, namespaced_tool: &str) -> Option<Arc<ToolLimiter>> {
+        self.tool_limiters
+            .get(namespaced_tool)
+            .map(Arc::clone)
+            .or_else(|| {
+                let (conn, tool) = self.resolve(namespaced_tool)?;
+                conn.limiter(&tool)
+            })
+    }
+
+    /// Find the server and raw tool name behind a namespaced MCP tool.
+    fn resolve(&self, namespaced_tool: &str) -> Option<(Arc<McpConnection>, String)> {
+        self.services().values().find_map(|conn| {
+            conn.known_tools()
+                .into_iter()
+                .map(|t| t.name.to_string())
+                .find(|name| Self::namespaced(conn.name(), name) == namespaced_tool)
+                .map(|name| (Arc::clone(conn), name))
+        })
+    }
+
+    /// Resources of every connected server, namespaced as `server::uri`.
+    ///
+    /// Servers that fail to list their resources are skipped.
+    pub async fn resources(&self) -> Vec<NamespacedItem<Resource>> {
+        let services = self.services();
+        let listings = services
+            .values()
+            .filter(|conn| conn.is_connected())
+            .map(|conn| async move {
+                match conn.list_resources().await {
+                    Ok(resources) => resources
+                        .into_iter()
+                        .map(|resource| NamespacedItem {
+                            id: format!("{}{ITEM_SEPARATOR}{}", conn.name(), resource.uri),
+                            item: resource,
+                        })
+                        .collect(),
+                    Err(e) => {
+                        tracing::warn!(
+                            server = %conn.name(),
+                            error = %e,
+                            "Listing MCP resources failed"
+                        );
+                        Vec::new()
+                    }
+                }
+            });
+        futures::future::join_all(listings)
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Read a resource by its namespaced ID (`server::uri`).
+    pub async fn read_resource(&self, id: &str) -> anyhow::Result<ReadResourceResult> {
+        let (conn, uri) = self.resolve_item("resource", id)?;
+        conn.read_resource(uri).await
+    }
+
+    /// Prompts of every connected server, namespaced as `server::name`.
+    ///
+    /// Servers that fail to list their prompts are skipped.
+    pub async fn prompts(&self) -> Vec<NamespacedItem<Prompt>> {
+        let services = self.services();
+        let listings = services
+            .values()
+            .filter(|conn| conn.is_connected())
+            .map(|conn| async move {
+                match conn.list_prompts().await {
+                    Ok(prompts) => prompts
+                        .into_iter()
+                        .map(|prompt| NamespacedItem {
+                            id: format!("{}{ITEM_SEPARATOR}{}", conn.name(), prompt.name),
+                            item: prompt,
+                        })
+                        .collect(),
+                    Err(e) => {
+                        tracing::warn!(
+                            server = %conn.name(),
+                            error = %e,
+                            "Listing MCP prompts failed"
+                        );
+                        Vec::new()
+                    }
+                }
+            });
+        futures::future::join_all(listings)
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Render a prompt by its namespaced ID (`server::name`) with `arguments`.
+    pub async fn get_prompt(
+        &self,
+        id: &str,
+        arguments: Option<JsonObject>,
+    ) -> anyhow::Result<GetPromptResult> {
+        let (conn, name) = self.resolve_item("prompt", id)?;
+        conn.get_prompt(name, arguments).await
+    }
+
+    /// Split `server::name` and find the server's connection.
+    fn resolve_item<'a>(
+        &self,
+        kind: &'static str,
+        id: &'a str,
+    ) -> Result<(Arc<McpConnection>, &'a str), UnknownMcpItemError> {
+        id.split_once(ITEM_SEPARATOR)
+            .and_then(|(server, name)| Some((Arc::clone(self.services().get(server)?), name)))
+            .ok_or_else(|| UnknownMcpItemError {
+                kind,
+                id: id.to_string(),
+            })
     }
 
     /// Merge another registry into this one, returning a new registry.
     /// This is used to combine global tools with skill-specific tools.
     pub fn merge(&self, other: &McpRegistry) -> Self {
-        let mut services = (*self.services).clone();
-        services.extend((*other.services).clone());
+        let mut services = (*self.services()).clone();
+        services.extend((*other.services()).clone());
 
         let mut tool_index = (*self.tool_index).clone();
         tool_index.extend((*other.tool_index).clone());
@@ -215,11 +529,20 @@ impl McpRegistry {
         let mut native_tools = (*self.native_tools).clone();
         native_tools.extend((*other.native_tools).clone());
 
+        let mut approval_required = (*self.approval_required).clone();
+        approval_required.extend((*other.approval_required).clone());
+
+        let mut tool_limiters = (*self.tool_limiters).clone();
+        tool_limiters.extend((*other.tool_limiters).clone());
+
         Self {
-            services: Arc::new(services),
             tool_index: Arc::new(tool_index),
             tools: Arc::new(tools),
             native_tools: Arc::new(native_tools),
+            approval_required: Arc::new(approval_required),
+            tool_limiters: Arc::new(tool_limiters),
+            tool_filter: self.tool_filter.clone(),
+            ..Self::with_services(services)
         }
     }
 
@@ -248,15 +571,19 @@ impl McpRegistry {
         native_tools.insert(ns_name, tool);
 
         Self {
-            services: self.services,     // Keep ref
-            tool_index: self.tool_index, // Keep ref
+            services: self.services,       // Keep ref
+            reload_lock: self.reload_lock, // Keep ref
+            tool_index: self.tool_index,   // Keep ref
             tools: Arc::new(tools),
             native_tools: Arc::new(native_tools),
+            approval_required: self.approval_required,
+            tool_limiters: self.tool_limiters,
+            tool_filter: self.tool_filter,
         }
     }
 
     pub fn openai_tools_json(&self) -> Vec<serde_json::Value> {
-        self.tools
+        self.tools()
             .iter()
             .map(|(ns_name, t)| {
                 // rmcp Tool uses input_schema as an Arc<JsonObject>; convert to serde_json.
@@ -281,44 +608,284 @@ impl McpRegistry {
         namespaced_tool: &str,
         arguments: serde_json::Value,
     ) -> anyhow::Result<serde_json::Value> {
+        self.call_namespaced_tool_in_session(namespaced_tool, arguments, None)
+            .await
+    }
+
+    /// Execute a namespaced tool on behalf of a conversation.
+    ///
+    /// Native tools get a handle to their state in `session`, keyed by the
+    /// tool's own name. Calls of tools with limits first wait for their turn,
+    /// failing with [`ToolRateLimitedError`] if it does not come in time.
+    pub async fn call_namespaced_tool_in_session(
+        &self,
+        namespaced_tool: &str,
+        arguments: serde_json::Value,
+        session: Option<&Session>,
+    ) -> anyhow::Result<serde_json::Value> {
+        if !self.is_allowed(namespaced_tool) {
+            return Err(ToolNotAllowedError {
+                tool: namespaced_tool.to_string(),
+            }
+            .into());
+        }
+
+        // Held until the call returns
+        let _permit = match self.limiter(namespaced_tool) {
+            Some(limiter) => Some(limiter.acquire(namespaced_tool).await?),
+            None => None,
+        };
+
         if namespaced_tool == "mirror" {
             return Ok(arguments);
         }
 
         if let Some(tool) = self.native_tools.get(namespaced_tool) {
-            return tool.call(arguments).await;
+            return match session {
+                Some(session) => {
+                    tool.call_with_state(arguments, session.tool_state_handle(tool.name()))
+                        .await
+                }
+                None => tool.call(arguments).await,
+            };
         }
 
-        // 1. Lookup server + raw_tool_name
-        let (server_name, raw_tool_name) = self
-            .tool_index
-            .get(namespaced_tool)
-            .ok_or_else(|| anyhow!("unknown tool: {namespaced_tool}"))?
-            .clone();
-
-        if server_name == "test" {
+        if let Some((server_name, raw_tool_name)) = self.tool_index.get(namespaced_tool)
+            && server_name == "test"
+        {
             return Ok(serde_json::json!({
                 "result": format!("executed test tool {} with args {:?}", raw_tool_name, arguments)
             }));
         }
 
-        // 2. Lookup service
-        let service = self
-            .services
-            .get(&server_name)
-            .ok_or_else(|| anyhow!("missing server handle: {server_name}"))?;
+        // 1. Lookup server + raw_tool_name
+        let (conn, raw_tool_name) = self
+            .resolve(namespaced_tool)
+            .ok_or_else(|| anyhow!("unknown tool: {namespaced_tool}"))?;
+        let server_name = conn.name();
+
+        // 2. Lookup service (fails with ServerDownError while disconnected)
+        let service = conn.service()?;
 
         // 3. Call tool
         let args_obj = arguments.as_object().cloned();
-        let res = service
+        let res = match service
             .call_tool(CallToolRequestParam {
                 name: raw_tool_name.clone().into(),
                 arguments: args_obj,
             })
             .await
-            .with_context(|| format!("tools/call failed for {server_name}::{raw_tool_name}"))?;
+        {
+            Ok(res) => res,
+            // The server died mid-call
+            Err(e) if service.is_transport_closed() => {
+                conn.mark_down(e.to_string());
+                return Err(ServerDownError {
+                    server: server_name.to_string(),
+                    reason: e.to_string(),
+                }
+                .into());
+            }
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("tools/call failed for {server_name}::{raw_tool_name}")
+                });
+            }
+        };
 
         // 4. Return content (simplified)
         Ok(serde_json::to_value(res)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::config::McpServerEntry;
+    use crate::mcp::connection::ServerState;
+
+    fn crashed_server() -> McpRegistry {
+        let (_, tool) = McpRegistry::new_with_test_tool("now", "Current time").tools()[0].clone();
+        let entry = McpServerEntry::Stdio {
+            command: "/nonexistent/mcp-server".to_string(),
+            args: vec![],
+            env: HashMap::new(),
+            require_approval: vec![],
+            tool_limits: HashMap::new(),
+        };
+        let conn = McpConnection::disconnected("time", entry, vec![tool]);
+        McpRegistry::with_services(HashMap::from([("time".to_string(), Arc::new(conn))]))
+    }
+
+    #[tokio::test]
+    async fn test_down_server_tools_hidden_and_calls_fail_distinctly() {
+        let registry = crashed_server();
+        assert!(registry.tools().is_empty());
+
+        let err = registry
+            .call_namespaced_tool("time__now", serde_json::json!({}))
+            .await
+            .unwrap_err();
+        let down = err.downcast_ref::<ServerDownError>().expect("server down error");
+        assert_eq!(down.server, "time");
+
+        let status = &registry.server_status()["time"];
+        assert_eq!(status.state, ServerState::Disconnected);
+        assert_eq!(status.tool_count, 1);
+
+        // Unknown tools are still reported as such
+        let err = registry
+            .call_namespaced_tool("time__later", serde_json::json!({}))
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<ServerDownError>().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reload_removes_server_without_breaking_snapshots() {
+        let registry = crashed_server();
+        let run_snapshot = registry.merge(&McpRegistry::new_empty());
+        let time = Arc::clone(&registry.services()["time"]);
+
+        let summary = registry.reload(&McpConfig::default()).await.unwrap();
+        assert_eq!(summary.removed, vec!["time".to_string()]);
+        assert!(registry.server_status().is_empty());
+
+        // Registries built before the reload, and connections held by
+        // in-flight calls, still see the removed server
+        assert!(run_snapshot.server_status().contains_key("time"));
+        assert_eq!(time.name(), "time");
+    }
+
+    #[tokio::test]
+    async fn test_failed_reload_keeps_running_servers() {
+        let registry = crashed_server();
+        let entry = registry.services()["time"].entry().clone();
+        let cfg = McpConfig {
+            mcp_servers: HashMap::from([
+                ("time".to_string(), entry),
+                (
+                    "broken".to_string(),
+                    McpServerEntry::Stdio {
+                        command: "/nonexistent/other-server".to_string(),
+                        args: vec![],
+                        env: HashMap::new(),
+                        require_approval: vec![],
+                        tool_limits: HashMap::new(),
+                    },
+                ),
+            ]),
+        };
+
+        assert!(registry.reload(&cfg).await.is_err());
+        let status = registry.server_status();
+        assert_eq!(status.len(), 1);
+        assert!(status.contains_key("time"));
+    }
+
+    #[tokio::test]
+    async fn test_resources_and_prompts_are_namespaced_by_server() {
+        let registry = crashed_server();
+        // A down server lists nothing
+        assert!(registry.resources().await.is_empty());
+        assert!(registry.prompts().await.is_empty());
+
+        let err = registry.read_resource("time::file:///now").await.unwrap_err();
+        assert!(err.downcast_ref::<ServerDownError>().is_some());
+
+        for id in ["file:///now", "other::file:///now"] {
+            let err = registry.read_resource(id).await.unwrap_err();
+            assert!(err.downcast_ref::<UnknownMcpItemError>().is_some());
+        }
+        let err = registry.get_prompt("greeting", None).await.unwrap_err();
+        assert_eq!(err.to_string(), "unknown MCP prompt: greeting");
+    }
+
+    #[tokio::test]
+    async fn test_tool_filter_hides_and_blocks() {
+        let registry = crashed_server()
+            .merge(&McpRegistry::new_with_test_tool("search", "Search"))
+            .with_tool_filter(|name| name == "mirror");
+        assert!(registry.tools().is_empty());
+
+        let err = registry
+            .call_namespaced_tool("time__now", serde_json::json!({}))
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<ToolNotAllowedError>().is_some());
+        let err = registry
+            .call_namespaced_tool("test__search", serde_json::json!({}))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "tool 'test__search' is not allowed for this agent");
+
+        let echoed = registry
+            .call_namespaced_tool("mirror", serde_json::json!({ "x": 1 }))
+            .await
+            .unwrap();
+        assert_eq!(echoed, serde_json::json!({ "x": 1 }));
+    }
+
+    #[test]
+    fn test_requires_approval() {
+        let (_, tool) = McpRegistry::new_with_test_tool("now", "Current time").tools()[0].clone();
+        let entry = McpServerEntry::Stdio {
+            command: "/nonexistent/mcp-server".to_string(),
+            args: vec![],
+            env: HashMap::new(),
+            require_approval: vec!["now".to_string()],
+            tool_limits: HashMap::new(),
+        };
+        let conn = McpConnection::disconnected("time", entry, vec![tool]);
+        let registry =
+            McpRegistry::with_services(HashMap::from([("time".to_string(), Arc::new(conn))]))
+                .merge(&McpRegistry::new_with_test_tool("search", "Search"))
+                .with_approval_required("test__search");
+
+        assert!(registry.requires_approval("time__now"));
+        assert!(registry.requires_approval("test__search"));
+        assert!(!registry.requires_approval("time__later"));
+        let unflagged = McpRegistry::new_with_test_tool("search", "Search");
+        assert!(!unflagged.requires_approval("test__search"));
+    }
+
+    #[tokio::test]
+    async fn test_rapid_calls_of_a_rate_limited_tool_are_throttled() {
+        let limits = |queue_timeout_secs| ToolLimits {
+            rate_limit: Some(2.0),
+            max_concurrent: None,
+            queue_timeout_secs,
+        };
+        let queued = McpRegistry::new_with_test_tool("search", "Search")
+            .with_tool_limits("test__search", &limits(10));
+        let started = std::time::Instant::now();
+        for _ in 0..4 {
+            queued
+                .call_namespaced_tool("test__search", serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+        // A burst of two, then one call every half second
+        assert!(started.elapsed() >= Duration::from_millis(900));
+
+        let rejecting = McpRegistry::new_with_test_tool("search", "Search")
+            .with_tool_limits("test__search", &limits(0));
+        let call = || rejecting.call_namespaced_tool("test__search", serde_json::json!({}));
+        let results = futures::future::join_all([call(), call(), call()]).await;
+        let limited: Vec<_> = results
+            .iter()
+            .filter_map(|r| r.as_ref().err()?.downcast_ref::<ToolRateLimitedError>())
+            .collect();
+        assert_eq!(limited.len(), 1);
+        assert_eq!(limited[0].tool, "test__search");
+
+        // Other tools are not limited
+        let merged = rejecting.merge(&McpRegistry::new_with_test_tool("now", "Time"));
+        for _ in 0..3 {
+            merged
+                .call_namespaced_tool("test__now", serde_json::json!({}))
+                .await
+                .unwrap();
+        }
+    }
+}
@@ -10,6 +10,7 @@
 //! - Message deltas for incremental text output
 //! - Tool call lifecycle (delta, complete, result)
 //! - Extended model capabilities (thinking, reasoning, citations, memory)
+//! - Provider moderation (content filters, refusals)
 //! - Stream lifecycle (start, done, error)
 //!
 //! # Example
@@ -20,7 +21,7 @@
 //! let event = NormalizedEvent::MessageDelta {
 //!     text: "Hello".to_string(),
 //! };
-//! let sse = sse_event(&event);
+//! let sse = sse_event(&event, None);
 //! assert!(sse.contains("message.delta"));
 //! ```
 
@@ -41,6 +42,32 @@ pub struct Citation {
     pub snippet: Option<String>,
 }
 
+/// Severity a provider's content filter assigned to a category.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFilterSeverity {
+    Safe,
+    Low,
+    Medium,
+    High,
+}
+
+impl std::str::FromStr for ContentFilterSeverity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "safe" => Ok(Self::Safe),
+            "low" => Ok(Self::Low),
+            "medium" => Ok(Self::Medium),
+            "high" => Ok(Self::High),
+            other => Err(format!(
+                "invalid content filter severity '{other}' (expected safe, low, medium or high)"
+            )),
+        }
+    }
+}
+
 /// Normalized streaming events emitted by the LLM orchestrator.
 ///
 /// These events provide a unified interface for the client UI regardless
@@ -101,6 +128,28 @@ pub enum NormalizedEvent {
         operation: String,
     },
 
+    // ─────────────────────────────────────────────────────────────────────
+    // Provider Moderation
+    // ─────────────────────────────────────────────────────────────────────
+    /// The provider's content filter flagged the prompt or the response,
+    /// which may have been cut short or withheld.
+    #[serde(rename = "content_filter")]
+    ContentFilter {
+        /// Flagged categories as the provider names them (e.g. `violence`).
+        categories: Vec<String>,
+        /// Highest severity among the flagged categories, when reported.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        severity: Option<ContentFilterSeverity>,
+    },
+
+    /// Incremental delta of the model's refusal to answer, sent instead of
+    /// message text.
+    #[serde(rename = "refusal.delta")]
+    RefusalDelta {
+        /// The refusal text fragment to append.
+        text: String,
+    },
+
     // ─────────────────────────────────────────────────────────────────────
     // Tool Calls
     // ─────────────────────────────────────────────────────────────────────
@@ -133,6 +182,18 @@ pub enum NormalizedEvent {
         arguments_json: String,
     },
 
+    /// A tool flagged as requiring approval waits for the user to approve
+    /// or deny the call.
+    #[serde(rename = "tool_approval.request")]
+    ToolApprovalRequest {
+        /// Tool call ID the decision refers to.
+        id: String,
+        /// Tool/function name.
+        name: String,
+        /// Arguments the tool would be called with, as JSON string.
+        arguments: String,
+    },
+
     /// Result from executing a tool.
     #[serde(rename = "tool_result")]
     ToolResult {
@@ -147,6 +208,11 @@ pub enum NormalizedEvent {
         success: bool,
     },
 
+    /// An interactive tool loop is waiting to be resumed before sending the
+    /// tool results back to the model.
+    #[serde(rename = "tool_loop.paused")]
+    ToolLoopPaused,
+
     // ─────────────────────────────────────────────────────────────────────
     // Errors and Completion
     // ─────────────────────────────────────────────────────────────────────
@@ -188,7 +254,9 @@ fn default_true() -> bool {
 ///
 /// The output follows the Server-Sent Events specification with both
 /// an `event:` line (for `EventSource` listeners) and a `data:` line
-/// containing the JSON payload.
+/// containing the JSON payload. Events of a sequenced stream pass their
+/// `id`, which is sent as the `id:` line (what `Last-Event-ID` echoes) and
+/// as `seq` in the payload, so clients can dedup and spot gaps.
 ///
 /// # Example
 ///
@@ -196,17 +264,44 @@ fn default_true() -> bool {
 /// use axum_leptos_htmx_wc::normalized::{NormalizedEvent, sse_event};
 ///
 /// let event = NormalizedEvent::Done;
-/// let sse = sse_event(&event);
+/// let sse = sse_event(&event, Some(3));
 /// assert!(sse.contains("event: done"));
+/// assert!(sse.contains("id: 3"));
 /// ```
-pub fn sse_event(evt: &NormalizedEvent) -> String {
-    let json = serde_json::to_string(evt).unwrap_or_else(|e| {
+pub fn sse_event(evt: &NormalizedEvent, id: Option<u64>) -> String {
+    let json = sequenced_payload(evt, id).unwrap_or_else(|e| {
         serde_json::json!({ "type": "error", "data": { "message": e.to_string() } }).to_string()
     });
 
     let event_name = event_name(evt);
 
-    format!("event: {event_name}\ndata: {json}\n\n")
+    match id {
+        Some(id) => format!("id: {id}
+event: {event_name}
+data: {json}
+
+"),
+        None => format!("event: {event_name}
+data: {json}
+
+"),
+    }
+}
+
+/// JSON of `value`, with `seq` added next to its fields when `id` is set.
+///
+/// # Errors
+///
+/// Returns an error if `value` can't be serialized.
+pub fn sequenced_payload<T: Serialize>(
+    value: &T,
+    id: Option<u64>,
+) -> Result<String, serde_json::Error> {
+    let mut json = serde_json::to_value(value)?;
+    if let (Some(id), Some(fields)) = (id, json.as_object_mut()) {
+        fields.insert("seq".to_string(), id.into());
+    }
+    serde_json::to_string(&json)
 }
 
 /// Get the SSE event name for a [`NormalizedEvent`].
@@ -218,9 +313,13 @@ pub fn event_name(evt: &NormalizedEvent) -> &'static str {
         NormalizedEvent::ReasoningDelta { .. } => "reasoning.delta",
         NormalizedEvent::CitationAdded { .. } => "citation.added",
         NormalizedEvent::MemoryUpdate { .. } => "memory.update",
+        NormalizedEvent::ContentFilter { .. } => "content_filter",
+        NormalizedEvent::RefusalDelta { .. } => "refusal.delta",
         NormalizedEvent::ToolCallDelta { .. } => "tool_call.delta",
         NormalizedEvent::ToolCallComplete { .. } => "tool_call.complete",
+        NormalizedEvent::ToolApprovalRequest { .. } => "tool_approval.request",
         NormalizedEvent::ToolResult { .. } => "tool_result",
+        NormalizedEvent::ToolLoopPaused => "tool_loop.paused",
         NormalizedEvent::Usage { .. } => "usage",
         NormalizedEvent::Error { .. } => "error",
         NormalizedEvent::Done => "done",
@@ -292,6 +391,27 @@ pub fn agui_sse_event(evt: &NormalizedEvent, request_id: &str) -> String {
                 "operation": operation
             }),
         ),
+        NormalizedEvent::ContentFilter {
+            categories,
+            severity,
+        } => (
+            "agui.content_filter",
+            serde_json::json!({
+                "kind": "content_filter",
+                "request_id": request_id,
+                "categories": categories,
+                "severity": severity
+            }),
+        ),
+        NormalizedEvent::RefusalDelta { text } => (
+            "agui.refusal.delta",
+            serde_json::json!({
+                "kind": "refusal",
+                "phase": "delta",
+                "request_id": request_id,
+                "delta": { "text": text }
+            }),
+        ),
         NormalizedEvent::ToolCallDelta {
             call_index,
             id,
@@ -326,6 +446,21 @@ pub fn agui_sse_event(evt: &NormalizedEvent, request_id: &str) -> String {
                 "arguments_json": arguments_json
             }),
         ),
+        NormalizedEvent::ToolApprovalRequest {
+            id,
+            name,
+            arguments,
+        } => (
+            "agui.tool_approval.request",
+            serde_json::json!({
+                "kind": "tool_approval",
+                "phase": "request",
+                "request_id": request_id,
+                "id": id,
+                "name": name,
+                "arguments": arguments
+            }),
+        ),
         NormalizedEvent::ToolResult {
             id,
             name,
@@ -342,6 +477,14 @@ pub fn agui_sse_event(evt: &NormalizedEvent, request_id: &str) -> String {
                 "success": success
             }),
         ),
+        NormalizedEvent::ToolLoopPaused => (
+            "agui.tool_loop.paused",
+            serde_json::json!({
+                "kind": "tool_loop",
+                "phase": "paused",
+                "request_id": request_id
+            }),
+        ),
         NormalizedEvent::Usage {
             prompt_tokens,
             completion_tokens,
@@ -385,7 +528,7 @@ pub fn agui_sse_event(evt: &NormalizedEvent, request_id: &str) -> String {
 ///
 /// This is useful for clients that support either protocol.
 pub fn dual_sse_event(evt: &NormalizedEvent, request_id: &str) -> String {
-    let normalized = sse_event(evt);
+    let normalized = sse_event(evt, None);
     let agui = agui_sse_event(evt, request_id);
     format!("{normalized}{agui}")
 }
@@ -407,12 +550,26 @@ mod tests {
     #[test]
     fn test_sse_event_format() {
         let event = NormalizedEvent::Done;
-        let sse = sse_event(&event);
+        let sse = sse_event(&event, None);
         assert!(sse.starts_with("event: done\n"));
         assert!(sse.contains("data: "));
         assert!(sse.ends_with("\n\n"));
     }
 
+    #[test]
+    fn test_sse_event_carries_id() {
+        let event = NormalizedEvent::MessageDelta {
+            text: "Hi".to_string(),
+        };
+        let sse = sse_event(&event, Some(42));
+        assert!(sse.starts_with("id: 42\nevent: message.delta\n"), "{sse}");
+
+        let data = sse.lines().find_map(|l| l.strip_prefix("data: ")).unwrap();
+        let payload: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!(payload["seq"], 42);
+        assert_eq!(payload["data"]["text"], "Hi");
+    }
+
     #[test]
     fn test_citation_serialization() {
         let citation = Citation {
@@ -1,12 +1,14 @@
+use anyhow::Context;
 use axum::{
-    Json, Router,
-    extract::{DefaultBodyLimit, Path, Request, State},
-    http::StatusCode,
+    Extension, Json, Router,
+    extract::{DefaultBodyLimit, Path, Query, Request, State},
+    http::{HeaderMap, StatusCode, header},
     middleware::Next,
     response::IntoResponse,
     routing::{get, get_service, post},
 };
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
@@ -18,20 +20,40 @@ use tracing::info;
 
 use crate::AppState;
 use crate::config::AppConfig;
-use crate::llm::{LlmSettings, Orchestrator};
-use crate::mcp::registry::McpRegistry;
-use crate::session::SessionStore;
+use crate::llm::{CircuitBreakerRegistry, LlmSettings, Orchestrator, ResponseCache};
+use crate::mcp::cache::{DEFAULT_EVICTION_INTERVAL, McpCacheStats, McpRegistryCache};
+use crate::mcp::config::DEFAULT_MCP_CONFIG_PATH;
+use crate::mcp::connection::ServerStatus;
+use crate::mcp::registry::{DEFAULT_HEALTH_CHECK_INTERVAL, McpRegistry};
+use crate::session::{
+    AssistantTurn, DEFAULT_FLUSH_INTERVAL, SessionStore, Transcript, TranscriptFormat,
+};
 use crate::uar::{
     self,
     defaults::ensure_default_knowledge_base,
+    domain::runs::RunOptions,
     persistence::{
         PersistenceLayer,
         providers::{postgres::PostgresProvider, surreal::SurrealDbProvider},
     },
     rag::{
-        chunking::ChunkingStrategy, ingest::IngestService, ingestion_worker::IngestionWorkerPool,
+        chunking::ChunkingStrategy,
+        extraction::{
+            ExtractionStrategy, RelationshipExtractor, external_nlp::ExternalNlpExtractor,
+            llm::LlmExtractor,
+        },
+        ingest::IngestService,
+        ingestion_worker::IngestionWorkerPool,
+    },
+    runtime::{
+        context::summarizer::SummarizerConfig,
+        manager::RunManager,
+        matching::vector::VectorMatcher,
+        pricing::PricingTable,
+        skills::{SkillDisambiguator, SkillRegistry},
     },
-    runtime::{manager::RunManager, matching::vector::VectorMatcher, skills::SkillRegistry},
+    security::claims::{TenantContext, tenant_scope},
+    telemetry::RequestId,
 };
 
 /// Start the Axum server with the provided configuration.
@@ -43,9 +65,36 @@ pub async fn start_server(config: Arc<AppConfig>, settings: LlmSettings) -> anyh
         "LLM configuration loaded"
     );
 
+    // Install the metrics recorder before any component records metrics
+    uar::telemetry::prometheus_handle();
+
+    // Cap concurrent embedding work before anything embeds
+    uar::runtime::matching::EmbeddingLimiter::configure_global(config.embedding.max_concurrent);
+    uar::rag::embedding::configure_http_client(config.embedding.max_idle_connections);
+    uar::rag::embedding::configure_dimension_reconciliation(config.embedding.reconcile_dimensions);
+
     // Initialize Persistence & RAG
     let mut ingest_service: Option<Arc<IngestService>> = None;
-    let vector_matcher = Arc::new(VectorMatcher::new(0.75));
+    let mut vector_matcher = VectorMatcher::new(0.75);
+    if config.knowledge_bases.cache_enabled {
+        vector_matcher = vector_matcher.with_cache(config.knowledge_bases.max_cache_entries);
+    }
+    if config.vector_index.hnsw_enabled {
+        vector_matcher = vector_matcher.with_chunk_index(config.vector_index.clone());
+    }
+    let vector_matcher = Arc::new(vector_matcher);
+    let index_path = config
+        .vector_index
+        .index_path
+        .as_deref()
+        .map(std::path::Path::new)
+        .filter(|_| config.vector_index.hnsw_enabled);
+    if let Some(path) = index_path
+        && path.exists()
+        && let Err(e) = vector_matcher.load_index(path)
+    {
+        tracing::warn!("Failed to load HNSW indexes, rebuilding on demand: {:#}", e);
+    }
 
     // Initialize VectorMatcher explicitly (shared)
     if let Err(e) = vector_matcher.initialize().await {
@@ -71,12 +120,32 @@ pub async fn start_server(config: Arc<AppConfig>, settings: LlmSettings) -> anyh
 
     // Initialize Ingest Service if persistence is available
     if let Some(p) = &persistence {
-        let ingest = Arc::new(IngestService::new(
+        let mut ingest = IngestService::new(
             p.clone(),
             vector_matcher.clone(),
             ChunkingStrategy::Semantic { threshold: 0.5 },
+        )
+        .with_upload_dir(&config.file_processing.upload_dir)
+        .with_archive_limits(config.knowledge_bases.archive_import.clone());
+        if let Some(url) = &config.knowledge_bases.nlp_service_url {
+            info!("Building knowledge graphs with the NLP service at {}", url);
+            let nlp: Arc<dyn RelationshipExtractor> = Arc::new(ExternalNlpExtractor::new(url));
+            ingest = ingest
+                .with_extractor(ExtractionStrategy::Spacy, Arc::clone(&nlp))
+                .with_extractor(ExtractionStrategy::Leiden, nlp);
+        }
+        // Extraction calls need no tools
+        let extraction_llm = Arc::new(Orchestrator::new(
+            settings.clone(),
+            Arc::new(McpRegistry::new_empty()),
         ));
+        let llm_extractor =
+            LlmExtractor::new(extraction_llm, &config.knowledge_bases.llm_extraction)
+                .with_pricing(PricingTable::new(&config.pricing));
+        ingest = ingest.with_extractor(ExtractionStrategy::Llm, Arc::new(llm_extractor));
+        let ingest = Arc::new(ingest);
         ingest_service = Some(ingest.clone());
+        vector_matcher.invalidate_on(ingest.chunks_saved());
 
         // Spawn File Watcher
         let ingest_svc_clone = ingest.clone();
@@ -99,14 +168,22 @@ pub async fn start_server(config: Arc<AppConfig>, settings: LlmSettings) -> anyh
             info!("Default knowledge base ensured.");
         }
 
+        // Check remote embedding credentials before serving traffic
+        if config.embedding.warmup {
+            match p.list_knowledge_bases(None).await {
+                Ok(kbs) => vector_matcher.warm_up(kbs.iter().map(|kb| &kb.config)).await,
+                Err(e) => tracing::error!("Failed to list knowledge bases for warmup: {:?}", e),
+            }
+        }
+
         info!("Persistence and RAG enabled.");
     }
 
     // MCP: connect once at startup
     // We update this to include native tools if persistence is present
-    let mut mcp_registry = McpRegistry::load_from_file("mcp.json")
+    let mut mcp_registry = McpRegistry::load_from_file(DEFAULT_MCP_CONFIG_PATH)
         .await
-        .unwrap_or_else(|e| panic!("Failed to load MCP servers: {e:?}"));
+        .context("Failed to load MCP servers")?;
 
     if let Some(p) = &persistence {
         // Register Memory Tools
@@ -126,16 +203,45 @@ pub async fn start_server(config: Arc<AppConfig>, settings: LlmSettings) -> anyh
     }
 
     let mcp = Arc::new(mcp_registry);
+    mcp.spawn_health_monitor(DEFAULT_HEALTH_CHECK_INTERVAL);
 
     for (name, _tool) in mcp.tools() {
         info!(name: "mcp.tool.discovered", tool = %name, "MCP tool discovered");
     }
 
+    // One circuit per provider, shared by the chat orchestrator and every run
+    let circuit_breakers = config
+        .llm
+        .circuit_breaker
+        .enabled
+        .then(|| Arc::new(CircuitBreakerRegistry::new(config.llm.circuit_breaker.clone())));
+    // Likewise one response cache, used by deterministic settings only
+    let response_cache = config
+        .llm
+        .response_cache
+        .enabled
+        .then(|| Arc::new(ResponseCache::from_config(&config.llm.response_cache)));
+
     // Create orchestrator
-    let orchestrator = Arc::new(Orchestrator::new(settings.clone(), Arc::clone(&mcp)));
+    let mut orchestrator = Orchestrator::new(settings.clone(), Arc::clone(&mcp));
+    if let Some(breakers) = &circuit_breakers {
+        orchestrator = orchestrator.with_circuit_breaker(breakers.for_settings(&settings));
+    }
+    if let Some(cache) = &response_cache {
+        orchestrator = orchestrator.with_response_cache(Arc::clone(cache));
+    }
+    let orchestrator = Arc::new(orchestrator);
 
-    // Session store
-    let sessions = SessionStore::new();
+    // Session store (durable sessions are persisted when a backend is configured)
+    let sessions = match &persistence {
+        Some(p) => SessionStore::with_persistence(Arc::clone(p)),
+        None => SessionStore::new(),
+    }
+    .with_ttl(Duration::from_secs(config.sessions.ttl_secs));
+    sessions.spawn_flusher(DEFAULT_FLUSH_INTERVAL);
+    sessions.spawn_sweeper(Duration::from_secs(
+        config.sessions.sweep_interval_secs.max(1),
+    ));
 
     // Skills initialization
     let mut skills_registry = SkillRegistry::new(None, None);
@@ -144,22 +250,85 @@ pub async fn start_server(config: Arc<AppConfig>, settings: LlmSettings) -> anyh
     }
     let skills = Arc::new(RwLock::new(skills_registry));
 
-    let run_manager = Arc::new(
-        RunManager::new(
-            settings.clone(),
-            Arc::clone(&mcp),
-            sessions.clone(),
-            skills.clone(),
-            vector_matcher.clone(), // Passed explicitly
-            persistence.clone(),    // Passed explicitly
-        )
-        .await,
+    // Shared so the search API and RAG retrieval load each model once
+    if let Some(rerank) = &config.knowledge_bases.rerank {
+        uar::rag::rerank::check_config(rerank).expect("Invalid knowledge_bases.rerank");
+    }
+    let rerankers = Arc::new(
+        uar::rag::rerank::RerankerRegistry::new()
+            .with_default(config.knowledge_bases.rerank.clone()),
     );
 
+    // Skills matched by many runs reuse their MCP servers
+    let skill_mcp = Arc::new(McpRegistryCache::new(Duration::from_secs(
+        config.skills.mcp_idle_timeout_secs,
+    )));
+    skill_mcp.spawn_eviction(DEFAULT_EVICTION_INTERVAL);
+
+    let mut run_manager = RunManager::new(
+        settings.clone(),
+        Arc::clone(&mcp),
+        sessions.clone(),
+        skills.clone(),
+        vector_matcher.clone(), // Passed explicitly
+        persistence.clone(),    // Passed explicitly
+    )
+    .await
+    .with_pricing(PricingTable::new(&config.pricing))
+    .with_sse_heartbeat(Duration::from_secs(config.server.sse_heartbeat_secs.max(1)))
+    .with_sse_keepalive(Duration::from_secs(config.server.sse_keepalive_secs.max(1)))
+    .with_webhook_timeout(Duration::from_secs(config.server.webhook_timeout_secs.max(1)))
+    .with_webhook_allowed_hosts(config.server.webhook_allowed_hosts.clone())
+    .with_tool_approval_timeout(Duration::from_secs(config.server.tool_approval_timeout_secs))
+    .with_run_timeout(
+        (config.server.run_timeout_secs > 0)
+            .then(|| Duration::from_secs(config.server.run_timeout_secs)),
+    )
+    .with_run_logging(config.audit.enabled)
+    .with_summarizer(SummarizerConfig {
+        threshold_messages: config.sessions.summary_threshold_messages,
+        keep_recent: config.sessions.summary_keep_recent,
+        tokens_per_hour: config.sessions.summary_tokens_per_hour,
+    })
+    .with_rerankers(Arc::clone(&rerankers))
+    .with_model_aliases(config.llm.model_aliases.clone())
+    .with_max_skill_depth(config.skills.max_skill_depth)
+    .with_skill_mcp_cache(skill_mcp)
+    .with_mcp_allowed_hosts(config.security.mcp_allowed_hosts.clone())
+    .with_run_phases(config.streaming.run_phases);
+    if config.streaming.partial_usage {
+        run_manager = run_manager.with_partial_usage(Duration::from_millis(
+            config.streaming.partial_usage_interval_ms,
+        ));
+    }
+    if let Some(breakers) = &circuit_breakers {
+        run_manager = run_manager.with_circuit_breakers(Arc::clone(breakers));
+    }
+    if let Some(cache) = response_cache {
+        run_manager = run_manager.with_response_cache(cache);
+    }
+    if config.skills.enable_llm_disambiguation {
+        // Picking a skill needs no tools
+        let llm = Arc::new(Orchestrator::new(
+            settings.clone(),
+            Arc::new(McpRegistry::new_empty()),
+        ));
+        let disambiguator = SkillDisambiguator::new(llm)
+            .with_threshold(config.skills.disambiguation_threshold);
+        run_manager = run_manager.with_skill_disambiguator(Arc::new(disambiguator));
+    }
+    let run_manager = Arc::new(run_manager);
+
     // Initialize Global Rate Limiter
-    let rate_limiter = Arc::new(uar::security::rate_limit::AppRateLimiter::new(
+    let rate_limiter = uar::security::rate_limit::connect_rate_limiter(
+        config.resilience.redis_url.as_deref(),
         config.resilience.requests_per_second,
         config.resilience.burst_size as u32,
+    )
+    .await;
+    let embeddings_rate_limiter = Arc::new(uar::security::rate_limit::AppRateLimiter::new(
+        config.resilience.embeddings_requests_per_second,
+        config.resilience.embeddings_burst_size as u32,
     ));
 
     let state = AppState {
@@ -171,56 +340,101 @@ pub async fn start_server(config: Arc<AppConfig>, settings: LlmSettings) -> anyh
         vector_matcher: vector_matcher.clone(),
         persistence: persistence.clone(),
         rate_limiter,
+        embeddings_rate_limiter,
         config: config.clone(),
     };
 
+    // Initialize ingestion worker pool if persistence available
+    let ingestion_pool = if let Some(p) = &persistence {
+        if let Some(ingest) = &state.ingest_service {
+            match IngestionWorkerPool::new(
+                config.file_processing.ingestion_workers,
+                config.file_processing.ingestion_queue_depth,
+                ingest.clone(),
+                p.clone(),
+            ) {
+                Ok(pool) => {
+                    info!("Ingestion worker pool initialized");
+                    Some(Arc::new(pool))
+                }
+                Err(e) => {
+                    tracing::error!("Failed to create ingestion pool: {:?}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    // Audit trail of KB mutations, stored alongside the knowledge bases
+    let audit: Option<Arc<dyn uar::security::audit::AuditSink>> = match &persistence {
+        Some(p) if config.audit.enabled => Some(Arc::new(
+            uar::security::audit::PersistentAuditSink::new(Arc::clone(p)),
+        )),
+        _ => None,
+    };
+
+    let health_state = Arc::new(uar::api::health::HealthState {
+        persistence: persistence.clone(),
+        vector_matcher: vector_matcher.clone(),
+        mcp: state.mcp.clone(),
+        ingestion_pool: ingestion_pool.clone(),
+        circuit_breakers,
+    });
+
     // Build router
     let app = Router::new()
         .route("/", get_service(ServeFile::new("static/index.html")))
         .route("/about", get_service(ServeFile::new("static/about.html")))
+        .route("/metrics", get(uar::telemetry::render_metrics))
         .route("/api/chat", post(api_chat))
         .route("/api/sessions/{id}/messages", get(api_get_messages))
+        .route("/api/sessions/{id}/export", get(api_export_session))
+        .route("/api/sessions/import", post(api_import_session))
+        .route("/api/mcp/servers", get(api_mcp_servers))
+        .route("/api/mcp/cache", get(api_mcp_cache))
         .nest(
             "/api/uar",
             uar::api::router().with_state(state.run_manager.clone()),
         )
         // Knowledge Base API
-        .nest("/api/uar/knowledge-bases", {
-            // Initialize ingestion worker pool if persistence available
-            let ingestion_pool = if let Some(p) = &persistence {
-                if let Some(ingest) = &state.ingest_service {
-                    match IngestionWorkerPool::new(
-                        0,   // auto-detect CPU count
-                        100, // max queue depth
-                        ingest.clone(),
-                        p.clone(),
-                    ) {
-                        Ok(pool) => {
-                            info!("Ingestion worker pool initialized");
-                            Some(Arc::new(pool))
-                        }
-                        Err(e) => {
-                            tracing::error!("Failed to create ingestion pool: {:?}", e);
-                            None
-                        }
-                    }
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
-
+        .nest(
+            "/api/uar/knowledge-bases",
             uar::api::knowledge::build_router().with_state(Arc::new(
                 uar::api::knowledge::KnowledgeApiState {
                     persistence: persistence
                         .clone()
                         .expect("Persistence required for KB API"),
                     vector_matcher: vector_matcher.clone(),
-                    ingestion_pool,
+                    ingestion_pool: ingestion_pool.clone(),
+                    ingest_service: state.ingest_service.clone(),
+                    rerankers,
+                    audit: audit.clone(),
+                    upload_dir: config.file_processing.upload_dir.clone().into(),
+                    max_upload_size: config
+                        .file_processing
+                        .max_file_size
+                        .min(config.file_processing.max_total_size),
+                    allowed_mime_types: config.file_processing.allowed_mime_types.clone(),
+                    search_cache: Some(Arc::new(uar::api::search_cache::SearchCache::default())),
                 },
-            ))
-        })
+            )),
+        )
+        .nest(
+            "/api/uar/admin",
+            uar::api::admin::build_router()
+                .with_state(Arc::new(uar::api::admin::AdminApiState {
+                    audit,
+                    ingestion_pool,
+                    rate_limiter: config
+                        .resilience
+                        .rate_limit_enabled
+                        .then(|| Arc::clone(&state.rate_limiter)),
+                })),
+        )
         .route("/api/ingest", post(uar::api::ingest::ingest_handler))
         .route(
             "/api/memory",
@@ -231,13 +445,24 @@ pub async fn start_server(config: Arc<AppConfig>, settings: LlmSettings) -> anyh
             "/v1/chat/completions",
             post(uar::api::openai::routes::chat_completions),
         )
+        .route("/v1/models", get(uar::api::openai::routes::list_models))
+        .route("/v1/embeddings", post(uar::api::openai::routes::embeddings))
+        // Inner layer: runs after authentication has decoded the token
+        .layer(axum::middleware::from_fn(
+            uar::security::middleware::tenant_middleware,
+        ))
         .layer(axum::middleware::from_fn_with_state(
             state.clone(),
             uar::security::middleware::auth_middleware,
         ))
+        // Probes are added after the auth layer so they need no token
+        .merge(uar::api::health::build_router().with_state(health_state))
         // Apply Timeout Layer if not disabled
         // We use a large timeout if disabled instead of conditional layering to keep types consistent
-        .layer(TraceLayer::new_for_http());
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(uar::telemetry::request_span::<axum::body::Body>),
+        );
 
     // We can't easily conditionally apply a layer in the chain if types differ.
     // Standard pattern:
@@ -304,6 +529,8 @@ pub async fn start_server(config: Arc<AppConfig>, settings: LlmSettings) -> anyh
             state.clone(),
             uar::security::rate_limit::rate_limit_middleware,
         ))
+        // Outermost, so the request span and every response carry the ID
+        .layer(axum::middleware::from_fn(uar::telemetry::request_id_middleware))
         .with_state(state);
 
     let addr = format!("{}:{}", config.server.host, config.server.port);
@@ -315,10 +542,53 @@ pub async fn start_server(config: Arc<AppConfig>, settings: LlmSettings) -> anyh
         "Server started"
     );
 
-    axum::serve(listener, app.into_make_service()).await?;
+    // Peer addresses key the rate limit of clients not behind a proxy
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
+    info!("Server stopped");
+
+    if let Some(path) = index_path
+        && let Err(e) = vector_matcher.serialize_index(path)
+    {
+        tracing::warn!("Failed to save HNSW indexes: {:#}", e);
+    }
     Ok(())
 }
 
+/// Resolves on Ctrl+C or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "Failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+    info!("Shutdown signal received");
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // API Handlers
 // ─────────────────────────────────────────────────────────────────────────────
@@ -331,6 +601,9 @@ struct ChatRequest {
     /// Optional session ID (creates new if not provided).
     #[serde(default)]
     session_id: Option<String>,
+    /// Keep a newly created session in memory only (never persisted).
+    #[serde(default)]
+    ephemeral: bool,
 }
 
 /// Response from chat API.
@@ -345,23 +618,28 @@ struct ChatResponse {
 /// POST /api/chat - Start a chat and get stream URL.
 async fn api_chat(
     State(state): State<AppState>,
+    tenant: Option<Extension<TenantContext>>,
+    request_id: Option<Extension<RequestId>>,
     Json(req): Json<ChatRequest>,
 ) -> Result<Json<ChatResponse>, (StatusCode, String)> {
+    let tenant_id = tenant_scope(tenant.as_deref());
     tracing::info!(
         message = %req.message,
         session_id = ?req.session_id,
         "Received chat request"
     );
 
+    let create_session = || state.sessions.create_for_tenant(tenant_id, req.ephemeral);
+
     let session_id = if let Some(id) = &req.session_id {
         if id.is_empty() {
-            state.sessions.create().id().to_string()
+            create_session().id().to_string()
         } else {
             // We just pass it through, RunManager will validate/create
             id.clone()
         }
     } else {
-        state.sessions.create().id().to_string()
+        create_session().id().to_string()
     };
 
     // Start Run via UAR
@@ -372,8 +650,15 @@ async fn api_chat(
             req.message,
             Some(session_id.clone()),
             None,
+            tenant_id.map(str::to_string),
+            RunOptions {
+                request_id: request_id.map(|Extension(id)| id.0),
+                ..Default::default()
+            },
         )
-        .await;
+        .await
+        .map_err(|e| (e.status_code(), e.to_string()))?;
+    tracing::info!(run_id = %run_id, "Started chat run");
 
     let stream_url = format!("/api/uar/runs/{}/stream", run_id);
 
@@ -388,21 +673,36 @@ async fn api_chat(
 struct MessageDto {
     role: String,
     content: String,
+    /// Full answer, reasoning, tool calls and artifacts of an assistant turn
+    #[serde(skip_serializing_if = "Option::is_none")]
+    turn: Option<AssistantTurn>,
+}
+
+/// GET /api/mcp/servers - Connection state of each MCP server.
+async fn api_mcp_servers(State(state): State<AppState>) -> Json<BTreeMap<String, ServerStatus>> {
+    Json(state.mcp.server_status())
+}
+
+/// GET /api/mcp/cache - Reuse of the MCP servers of skills across runs.
+async fn api_mcp_cache(State(state): State<AppState>) -> Json<McpCacheStats> {
+    Json(state.run_manager.skill_mcp_cache().stats())
 }
 
 /// GET /api/sessions/:id/messages - Get session messages.
 async fn api_get_messages(
     State(state): State<AppState>,
+    tenant: Option<Extension<TenantContext>>,
     Path(id): Path<String>,
 ) -> Result<Json<Vec<MessageDto>>, StatusCode> {
-    match state.sessions.get(&id) {
+    match state.sessions.load(&id, tenant_scope(tenant.as_deref())).await {
         Some(session) => {
             let messages: Vec<MessageDto> = session
-                .messages()
-                .iter()
+                .history()
+                .into_iter()
                 .map(|m| MessageDto {
-                    role: format!("{:?}", m.role).to_lowercase(),
-                    content: m.content.to_string(),
+                    role: format!("{:?}", m.message.role).to_lowercase(),
+                    content: m.message.content.to_string(),
+                    turn: m.turn,
                 })
                 .collect();
             Ok(Json(messages))
@@ -410,3 +710,74 @@ async fn api_get_messages(
         None => Err(StatusCode::NOT_FOUND),
     }
 }
+
+/// Query of the transcript endpoints.
+#[derive(Debug, Deserialize)]
+struct TranscriptQuery {
+    /// `json` or `jsonl`; on import, defaults to the body's content type
+    format: Option<TranscriptFormat>,
+}
+
+/// Response from the transcript import API.
+#[derive(Debug, Serialize)]
+struct ImportSessionResponse {
+    session_id: String,
+    message_count: usize,
+}
+
+/// GET /api/sessions/:id/export?format=json|jsonl - Download a session's transcript.
+async fn api_export_session(
+    State(state): State<AppState>,
+    tenant: Option<Extension<TenantContext>>,
+    Path(id): Path<String>,
+    Query(query): Query<TranscriptQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let session = state
+        .sessions
+        .load(&id, tenant_scope(tenant.as_deref()))
+        .await
+        .ok_or((StatusCode::NOT_FOUND, format!("Session '{id}' not found")))?;
+
+    let format = query.format.unwrap_or_default();
+    let body = Transcript::from_session(&session)
+        .to_format(format)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(([(header::CONTENT_TYPE, format.content_type())], body))
+}
+
+/// POST /api/sessions/import - Recreate a session from an exported transcript.
+async fn api_import_session(
+    State(state): State<AppState>,
+    tenant: Option<Extension<TenantContext>>,
+    Query(query): Query<TranscriptQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<(StatusCode, Json<ImportSessionResponse>), (StatusCode, String)> {
+    let format = query.format.unwrap_or_else(|| {
+        headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(TranscriptFormat::from_content_type)
+            .unwrap_or_default()
+    });
+    let transcript = Transcript::parse(&body, format, state.config.sessions.import_max_messages)
+        .map_err(|e| (e.status_code(), e.to_string()))?;
+
+    let session = state.sessions.import(transcript, tenant_scope(tenant.as_deref()));
+    if let Err(e) = state.sessions.persist(&session).await {
+        tracing::warn!(session_id = %session.id(), "Failed to persist imported session: {e:#}");
+    }
+    tracing::info!(
+        session_id = %session.id(),
+        messages = session.message_count(),
+        "Imported session transcript"
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ImportSessionResponse {
+            session_id: session.id().to_string(),
+            message_count: session.message_count(),
+        }),
+    ))
+}
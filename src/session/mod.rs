@@ -1,13 +1,18 @@
 //! Session and conversation thread management.
 //!
-//! This module provides in-memory session storage for managing conversation
-//! state across multiple requests. Sessions are identified by UUID and contain
-//! the full message history.
+//! This module provides session storage for managing conversation state
+//! across multiple requests. Sessions are identified by UUID and contain the
+//! full message history. Sessions are held in memory and, when a persistence
+//! layer is attached, saved to and reloaded from it across restarts. Sessions
+//! idle for longer than the store's TTL are evicted from memory.
 //!
 //! # Architecture
 //!
 //! - [`Session`]: Represents a single conversation session
 //! - [`SessionStore`]: Thread-safe store for all active sessions
+//! - [`ToolStateHandle`]: A stateful tool's view of its state in one session
+//! - [`AssistantTurn`]: Text, reasoning, tool calls and artifacts of one answer
+//! - [`Transcript`]: A session's conversation, exported or to be imported
 //!
 //! # Example
 //!
@@ -23,7 +28,13 @@
 //! ```
 
 mod thread;
+mod tool_state;
+mod transcript;
+mod turn;
 
 #[allow(unused_imports)]
 pub use thread::Session;
-pub use thread::SessionStore;
+pub use thread::{DEFAULT_FLUSH_INTERVAL, SessionStore};
+pub use tool_state::{SessionToolState, ToolStateHandle};
+pub use transcript::{TRANSCRIPT_VERSION, Transcript, TranscriptError, TranscriptFormat};
+pub use turn::{AssistantTurn, SessionMessage, TurnToolCall};
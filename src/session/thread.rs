@@ -1,6 +1,7 @@
 //! Conversation thread and session storage.
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
@@ -8,12 +9,19 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use uuid::Uuid;
 
+use super::tool_state::{SessionToolState, ToolStateHandle};
+use super::transcript::Transcript;
+use super::turn::{AssistantTurn, SessionMessage};
 use crate::llm::{Message, MessageContent, MessageRole, ToolCall};
+use crate::uar::domain::tenant::visible_to;
+use crate::uar::persistence::PersistenceLayer;
 
 /// Default session timeout (30 minutes).
-#[allow(dead_code)]
 const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(30 * 60);
 
+/// Default interval between background flushes of modified sessions.
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
 /// A single conversation session.
 ///
 /// Sessions maintain the full message history and provide methods
@@ -28,7 +36,7 @@ struct SessionInner {
     /// Unique session identifier.
     id: String,
     /// Conversation messages.
-    messages: RwLock<Vec<Message>>,
+    messages: RwLock<Vec<SessionMessage>>,
     /// Session creation time.
     #[allow(dead_code)]
     created_at: DateTime<Utc>,
@@ -36,15 +44,29 @@ struct SessionInner {
     last_activity: RwLock<DateTime<Utc>>,
     /// Optional system prompt.
     system_prompt: RwLock<Option<String>>,
+    /// Ephemeral sessions live in memory only and are never persisted.
+    ephemeral: bool,
+    /// Owning tenant; `None` is shared with every tenant.
+    tenant_id: Option<String>,
+    /// State kept by stateful tools across turns.
+    tool_state: SessionToolState,
+    /// Modified since last persisted.
+    dirty: AtomicBool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SessionState {
     pub id: String,
-    pub messages: Vec<Message>,
+    pub messages: Vec<SessionMessage>,
     pub created_at: String,    // RFC3339
     pub last_activity: String, // RFC3339
     pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub ephemeral: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_state: HashMap<String, serde_json::Value>,
 }
 
 impl Serialize for Session {
@@ -78,6 +100,11 @@ impl Clone for Session {
 impl Session {
     /// Create a new session with the given ID.
     fn new(id: String) -> Self {
+        Self::with_durability(id, false, None)
+    }
+
+    /// Create a new session, optionally keeping it in memory only.
+    fn with_durability(id: String, ephemeral: bool, tenant_id: Option<String>) -> Self {
         let now = Utc::now();
         Self {
             inner: Arc::new(SessionInner {
@@ -86,6 +113,10 @@ impl Session {
                 created_at: now,
                 last_activity: RwLock::new(now),
                 system_prompt: RwLock::new(None),
+                ephemeral,
+                tenant_id,
+                tool_state: SessionToolState::default(),
+                dirty: AtomicBool::new(false),
             }),
         }
     }
@@ -97,6 +128,9 @@ impl Session {
             created_at: self.inner.created_at.to_rfc3339(),
             last_activity: self.inner.last_activity.read().unwrap().to_rfc3339(),
             system_prompt: self.inner.system_prompt.read().unwrap().clone(),
+            ephemeral: self.inner.ephemeral,
+            tenant_id: self.inner.tenant_id.clone(),
+            tool_state: self.inner.tool_state.snapshot(),
         }
     }
 
@@ -115,6 +149,10 @@ impl Session {
                 created_at,
                 last_activity: RwLock::new(last_activity),
                 system_prompt: RwLock::new(state.system_prompt),
+                ephemeral: state.ephemeral,
+                tenant_id: state.tenant_id,
+                tool_state: SessionToolState::from_map(state.tool_state),
+                dirty: AtomicBool::new(false),
             }),
         }
     }
@@ -125,12 +163,25 @@ impl Session {
         &self.inner.id
     }
 
+    /// Whether this session is kept in memory only (never persisted).
+    #[must_use]
+    pub fn is_ephemeral(&self) -> bool {
+        self.inner.ephemeral
+    }
+
+    /// The tenant owning this session; `None` if it is shared.
+    #[must_use]
+    pub fn tenant_id(&self) -> Option<&str> {
+        self.inner.tenant_id.as_deref()
+    }
+
     /// Set the system prompt for this session.
     #[allow(dead_code)]
     pub fn set_system_prompt(&self, prompt: impl Into<String>) {
         let mut guard = self.inner.system_prompt.write().unwrap();
         *guard = Some(prompt.into());
-        self.touch();
+        drop(guard);
+        self.mark_dirty();
     }
 
     /// Get the system prompt if set.
@@ -162,6 +213,23 @@ impl Session {
         self.add_message(msg);
     }
 
+    /// Add the message closing an assistant turn, with the turn's structure.
+    ///
+    /// `content` is what the model sees on later turns; `turn` keeps the
+    /// full answer, reasoning, tool calls and artifacts for history.
+    pub fn add_assistant_turn(&self, content: impl Into<String>, turn: AssistantTurn) {
+        let message = Message {
+            role: MessageRole::Assistant,
+            content: MessageContent::text(content),
+            tool_call_id: None,
+            tool_calls: None,
+        };
+        self.push(SessionMessage {
+            message,
+            turn: Some(turn),
+        });
+    }
+
     /// Add an assistant message with tool calls.
     #[allow(dead_code)]
     pub fn add_assistant_with_tool_calls(
@@ -192,15 +260,31 @@ impl Session {
 
     /// Add a message to the conversation.
     pub fn add_message(&self, message: Message) {
+        self.push(message.into());
+    }
+
+    fn push(&self, message: SessionMessage) {
         let mut guard = self.inner.messages.write().unwrap();
         guard.push(message);
         drop(guard);
-        self.touch();
+        self.mark_dirty();
     }
 
     /// Get all messages in the conversation.
     #[must_use]
     pub fn messages(&self) -> Vec<Message> {
+        self.inner
+            .messages
+            .read()
+            .unwrap()
+            .iter()
+            .map(|m| m.message.clone())
+            .collect()
+    }
+
+    /// Get all messages along with the structure of assistant turns.
+    #[must_use]
+    pub fn history(&self) -> Vec<SessionMessage> {
         self.inner.messages.read().unwrap().clone()
     }
 
@@ -233,6 +317,46 @@ impl Session {
     pub fn clear(&self) {
         let mut guard = self.inner.messages.write().unwrap();
         guard.clear();
+        drop(guard);
+        self.mark_dirty();
+    }
+
+    /// Get the state a tool stored in this session.
+    #[must_use]
+    pub fn get_tool_state(&self, tool: &str) -> Option<serde_json::Value> {
+        self.inner.tool_state.get(tool)
+    }
+
+    /// Store a tool's state in this session, replacing any previous value.
+    pub fn set_tool_state(&self, tool: &str, value: serde_json::Value) {
+        self.inner.tool_state.set(tool, value);
+        self.mark_dirty();
+    }
+
+    /// Remove a tool's state from this session, returning the previous value.
+    pub fn clear_tool_state(&self, tool: &str) -> Option<serde_json::Value> {
+        let previous = self.inner.tool_state.remove(tool);
+        if previous.is_some() {
+            self.mark_dirty();
+        }
+        previous
+    }
+
+    /// A handle giving `tool` access to its state in this session.
+    #[must_use]
+    pub fn tool_state_handle(&self, tool: impl Into<String>) -> ToolStateHandle {
+        ToolStateHandle::new(self.clone(), tool)
+    }
+
+    /// Whether the session has changed since it was last persisted.
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.inner.dirty.load(Ordering::Acquire)
+    }
+
+    /// Record a mutation: bump activity and flag for persistence.
+    fn mark_dirty(&self) {
+        self.inner.dirty.store(true, Ordering::Release);
         self.touch();
     }
 
@@ -280,14 +404,24 @@ impl Session {
 /// Thread-safe store for sessions.
 ///
 /// Provides methods for creating, retrieving, and cleaning up sessions.
+/// When a persistence layer is attached, modified durable sessions are
+/// written to it by [`SessionStore::flush`] (run periodically by
+/// [`SessionStore::spawn_flusher`]) and sessions missing from memory are
+/// loaded back on access. Sessions inactive longer than the store's TTL are
+/// evicted by [`SessionStore::evict_expired`] (run periodically by
+/// [`SessionStore::spawn_sweeper`]); durable ones are persisted first so
+/// they can be reloaded, ephemeral ones are dropped.
 #[derive(Debug, Clone)]
 pub struct SessionStore {
     inner: Arc<SessionStoreInner>,
+    /// Inactivity after which a session is evicted.
+    ttl: Duration,
 }
 
 #[derive(Debug)]
 struct SessionStoreInner {
     sessions: RwLock<HashMap<String, Session>>,
+    persistence: Option<Arc<dyn PersistenceLayer>>,
 }
 
 impl Default for SessionStore {
@@ -300,13 +434,38 @@ impl SessionStore {
     /// Create a new session store.
     #[must_use]
     pub fn new() -> Self {
+        Self::build(None)
+    }
+
+    /// Create a session store that persists durable sessions.
+    #[must_use]
+    pub fn with_persistence(persistence: Arc<dyn PersistenceLayer>) -> Self {
+        Self::build(Some(persistence))
+    }
+
+    fn build(persistence: Option<Arc<dyn PersistenceLayer>>) -> Self {
         Self {
             inner: Arc::new(SessionStoreInner {
                 sessions: RwLock::new(HashMap::new()),
+                persistence,
             }),
+            ttl: DEFAULT_SESSION_TIMEOUT,
         }
     }
 
+    /// Set the inactivity timeout after which sessions are evicted.
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The inactivity timeout after which sessions are evicted.
+    #[must_use]
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
     /// Create a new session and return it.
     #[must_use]
     pub fn create(&self) -> Session {
@@ -314,36 +473,174 @@ impl SessionStore {
         self.create_with_id(id)
     }
 
+    /// Create a new in-memory-only session and return it.
+    #[must_use]
+    pub fn create_ephemeral(&self) -> Session {
+        let id = Uuid::new_v4().to_string();
+        self.insert(Session::with_durability(id, true, None))
+    }
+
+    /// Create a new session owned by `tenant_id` and return it.
+    #[must_use]
+    pub fn create_for_tenant(&self, tenant_id: Option<&str>, ephemeral: bool) -> Session {
+        let id = Uuid::new_v4().to_string();
+        self.insert(Session::with_durability(id, ephemeral, tenant_id.map(str::to_string)))
+    }
+
     /// Create a new session with a specific ID.
     #[must_use]
     pub fn create_with_id(&self, id: impl Into<String>) -> Session {
-        let id = id.into();
-        let session = Session::new(id.clone());
+        self.insert(Session::new(id.into()))
+    }
+
+    /// Create a durable session owned by `tenant_id` holding `transcript`.
+    ///
+    /// The transcript should come from [`Transcript::parse`], which checks
+    /// it is a conversation the model can continue.
+    #[must_use]
+    pub fn import(&self, transcript: Transcript, tenant_id: Option<&str>) -> Session {
+        let session = self.create_for_tenant(tenant_id, false);
+        if let Some(prompt) = transcript.system_prompt {
+            session.set_system_prompt(prompt);
+        }
+        for message in transcript.messages {
+            session.push(message);
+        }
+        session
+    }
+
+    fn insert(&self, session: Session) -> Session {
         let mut guard = self.inner.sessions.write().unwrap();
-        guard.insert(id, session.clone());
+        guard.insert(session.id().to_string(), session.clone());
         session
     }
 
-    /// Get a session by ID.
+    /// Write a session to the persistence layer.
+    ///
+    /// Ephemeral sessions, and all sessions when no persistence layer is
+    /// attached, are skipped. Returns whether the session was written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the persistence layer fails to save the session.
+    pub async fn persist(&self, session: &Session) -> anyhow::Result<bool> {
+        match &self.inner.persistence {
+            Some(db) if !session.is_ephemeral() => {
+                // Clear first so mutations during the write are not lost
+                session.inner.dirty.store(false, Ordering::Release);
+                if let Err(e) = db.save_session(session).await {
+                    session.inner.dirty.store(true, Ordering::Release);
+                    return Err(e.into());
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Persist every durable session modified since its last save.
+    ///
+    /// Returns the number of sessions written. Failed writes are logged and
+    /// retried on the next flush.
+    pub async fn flush(&self) -> usize {
+        if self.inner.persistence.is_none() {
+            return 0;
+        }
+
+        let dirty: Vec<Session> = self
+            .inner
+            .sessions
+            .read()
+            .unwrap()
+            .values()
+            .filter(|s| s.is_dirty() && !s.is_ephemeral())
+            .cloned()
+            .collect();
+
+        let mut written = 0;
+        for session in dirty {
+            match self.persist(&session).await {
+                Ok(true) => written += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to persist session {}: {:?}", session.id(), e),
+            }
+        }
+        written
+    }
+
+    /// Spawn a background task that flushes modified sessions every `interval`.
+    ///
+    /// Batches rapid mutations (e.g. streamed tool results) into one write.
+    /// Returns `None` when no persistence layer is attached.
+    pub fn spawn_flusher(&self, interval: Duration) -> Option<tokio::task::JoinHandle<()>> {
+        self.inner.persistence.as_ref()?;
+        let store = self.clone();
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                store.flush().await;
+            }
+        }))
+    }
+
+    /// Get a session by ID without counting it as activity.
     #[must_use]
     pub fn get(&self, id: &str) -> Option<Session> {
         let guard = self.inner.sessions.read().unwrap();
         guard.get(id).cloned()
     }
 
-    /// Get a session by ID, creating it if it doesn't exist.
-    #[must_use]
-    pub fn get_or_create(&self, id: &str) -> Session {
-        // Try read-only first
-        {
-            let guard = self.inner.sessions.read().unwrap();
-            if let Some(session) = guard.get(id) {
-                return session.clone();
+    /// Get a session by ID, loading it from persistence if it is not in memory.
+    ///
+    /// Sessions owned by a tenant other than `tenant_id` are "not found".
+    /// Counts as activity, so the session's TTL restarts. Load failures are
+    /// logged and treated as "not found".
+    pub async fn load(&self, id: &str, tenant_id: Option<&str>) -> Option<Session> {
+        if let Some(session) = self.get(id) {
+            if !visible_to(session.tenant_id(), tenant_id) {
+                return None;
             }
+            session.touch();
+            return Some(session);
         }
 
-        // Create if not exists
-        self.create_with_id(id)
+        let db = self.inner.persistence.as_ref()?;
+        match db.load_session(id, tenant_id).await {
+            Ok(Some(session)) => {
+                let mut guard = self.inner.sessions.write().unwrap();
+                // Another caller may have loaded or created it meanwhile
+                let session = guard.entry(id.to_string()).or_insert(session).clone();
+                drop(guard);
+                // The stored timestamp predates eviction; don't reap it again
+                session.touch();
+                Some(session)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                tracing::warn!("Failed to load session {}: {:?}", id, e);
+                None
+            }
+        }
+    }
+
+    /// Get a session by ID, loading or creating it if it isn't in memory.
+    ///
+    /// New sessions are owned by `tenant_id`. Returns `None` if the ID is
+    /// taken by another tenant's session.
+    pub async fn get_or_create(&self, id: &str, tenant_id: Option<&str>) -> Option<Session> {
+        if let Some(session) = self.load(id, tenant_id).await {
+            return Some(session);
+        }
+        if self.get(id).is_some() {
+            return None;
+        }
+        Some(self.insert(Session::with_durability(
+            id.to_string(),
+            false,
+            tenant_id.map(str::to_string),
+        )))
     }
 
     /// Remove a session by ID.
@@ -366,12 +663,13 @@ impl SessionStore {
         self.len() == 0
     }
 
-    /// Remove all expired sessions.
+    /// Remove all expired sessions without persisting them.
     ///
-    /// Returns the number of sessions removed.
+    /// Returns the number of sessions removed. Prefer
+    /// [`SessionStore::evict_expired`], which saves unsaved changes first.
     #[allow(dead_code)]
     pub fn cleanup_expired(&self) -> usize {
-        self.cleanup_expired_with_timeout(DEFAULT_SESSION_TIMEOUT)
+        self.cleanup_expired_with_timeout(self.ttl)
     }
 
     /// Remove sessions that have been inactive longer than the timeout.
@@ -383,6 +681,65 @@ impl SessionStore {
         before - guard.len()
     }
 
+    /// Evict sessions inactive for longer than the store's TTL.
+    ///
+    /// Durable sessions with unsaved changes are persisted first; if that
+    /// fails they stay in memory and are retried on the next sweep.
+    /// Returns the number of sessions evicted.
+    pub async fn evict_expired(&self) -> usize {
+        let expired: Vec<Session> = self
+            .inner
+            .sessions
+            .read()
+            .unwrap()
+            .values()
+            .filter(|s| s.is_expired_with_timeout(self.ttl))
+            .cloned()
+            .collect();
+
+        let mut evicted = 0;
+        for session in expired {
+            if session.is_dirty() {
+                if let Err(e) = self.persist(&session).await {
+                    tracing::warn!(
+                        "Failed to persist session {} before eviction: {:?}",
+                        session.id(),
+                        e
+                    );
+                    continue;
+                }
+            }
+
+            let mut guard = self.inner.sessions.write().unwrap();
+            // Skip sessions that became active while we were persisting
+            if guard
+                .get(session.id())
+                .is_some_and(|s| s.is_expired_with_timeout(self.ttl))
+            {
+                guard.remove(session.id());
+                evicted += 1;
+            }
+        }
+
+        if evicted > 0 {
+            tracing::debug!("Evicted {} expired sessions", evicted);
+        }
+        evicted
+    }
+
+    /// Spawn a background task that evicts expired sessions every `interval`.
+    pub fn spawn_sweeper(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                store.evict_expired().await;
+            }
+        })
+    }
+
     /// List all session IDs.
     #[must_use]
     pub fn list_ids(&self) -> Vec<String> {
@@ -434,6 +791,223 @@ mod tests {
         assert!(store.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_ephemeral_session_not_persisted() {
+        use crate::uar::persistence::testing::InMemoryPersistence;
+
+        let db = Arc::new(InMemoryPersistence::new());
+        let store = SessionStore::with_persistence(db.clone());
+
+        let durable = store.create();
+        durable.add_user_message("keep me");
+        let ephemeral = store.create_ephemeral();
+        ephemeral.add_user_message("forget me");
+
+        assert!(store.persist(&durable).await.unwrap());
+        assert!(!store.persist(&ephemeral).await.unwrap());
+
+        assert!(db.has_session(durable.id()));
+        assert!(!db.has_session(ephemeral.id()));
+        // Ephemeral sessions are still served from memory
+        assert_eq!(store.get(ephemeral.id()).unwrap().message_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_restarted_store_recovers_session() {
+        use crate::uar::persistence::testing::InMemoryPersistence;
+
+        let db = Arc::new(InMemoryPersistence::new());
+
+        let store = SessionStore::with_persistence(db.clone());
+        let session = store.get_or_create("restart-me", None).await.unwrap();
+        session.set_system_prompt("Be brief.");
+        session.add_user_message("Hello");
+        session.add_assistant_message("Hi!");
+        assert!(session.is_dirty());
+
+        assert_eq!(store.flush().await, 1);
+        assert!(!session.is_dirty());
+        // Nothing changed, nothing to write
+        assert_eq!(store.flush().await, 0);
+        drop(store);
+
+        // Simulate a server restart with a fresh in-memory store
+        let restarted = SessionStore::with_persistence(db);
+        assert!(restarted.get("restart-me").is_none());
+
+        let recovered = restarted.get_or_create("restart-me", None).await.unwrap();
+        assert_eq!(recovered.message_count(), 2);
+        assert_eq!(recovered.system_prompt().as_deref(), Some("Be brief."));
+        assert_eq!(recovered.messages()[1].content.to_string(), "Hi!");
+        assert!(restarted.get("restart-me").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_get_or_create_without_persistence() {
+        let store = SessionStore::new();
+        let session = store.get_or_create("local", None).await.unwrap();
+        session.add_user_message("Hello");
+
+        assert_eq!(store.flush().await, 0);
+        assert_eq!(store.get_or_create("local", None).await.unwrap().message_count(), 1);
+        assert!(store.spawn_flusher(DEFAULT_FLUSH_INTERVAL).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_evict_expired_persists_first() {
+        use crate::uar::persistence::testing::InMemoryPersistence;
+
+        let db = Arc::new(InMemoryPersistence::new());
+        let store =
+            SessionStore::with_persistence(db.clone()).with_ttl(Duration::from_millis(50));
+        let session = store.create_with_id("idle");
+        session.add_user_message("Hello");
+        let ephemeral = store.create_ephemeral();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(store.evict_expired().await, 2);
+        assert!(store.is_empty());
+        assert!(db.has_session("idle"));
+        assert!(!db.has_session(ephemeral.id()));
+
+        // Reloading restarts the TTL instead of reaping it again
+        let reloaded = store.load("idle", None).await.unwrap();
+        assert_eq!(reloaded.message_count(), 1);
+        assert_eq!(store.evict_expired().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_tenant_scoped() {
+        use crate::uar::persistence::testing::InMemoryPersistence;
+
+        let db = Arc::new(InMemoryPersistence::new());
+        let store = SessionStore::with_persistence(db.clone());
+        let session = store.get_or_create("chat", Some("acme")).await.unwrap();
+        assert_eq!(session.tenant_id(), Some("acme"));
+        session.add_user_message("secret");
+
+        assert!(store.load("chat", Some("globex")).await.is_none());
+        // Another tenant cannot take over the ID either
+        assert!(store.get_or_create("chat", Some("globex")).await.is_none());
+
+        assert_eq!(store.flush().await, 1);
+        let restarted = SessionStore::with_persistence(db);
+        assert!(restarted.load("chat", None).await.is_none());
+        let reloaded = restarted.load("chat", Some("acme")).await.unwrap();
+        assert_eq!(reloaded.tenant_id(), Some("acme"));
+    }
+
+    #[tokio::test]
+    async fn test_access_keeps_session_alive() {
+        let store = SessionStore::new().with_ttl(Duration::from_millis(50));
+        store.create_with_id("active").add_user_message("Hi");
+        store.create_with_id("idle").add_user_message("Hi");
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // Resolving the session for a new run (as `RunManager` does) touches it
+        store.get_or_create("active", None).await;
+
+        assert_eq!(store.evict_expired().await, 1);
+        assert!(store.get("active").is_some());
+        assert!(store.get("idle").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tool_state_survives_reload() {
+        use crate::uar::persistence::testing::InMemoryPersistence;
+
+        let db = Arc::new(InMemoryPersistence::new());
+        let store = SessionStore::with_persistence(db.clone());
+        let session = store.create_with_id("cart");
+        session
+            .tool_state_handle("cart")
+            .set(serde_json::json!({ "items": ["apple"] }));
+        assert!(session.is_dirty());
+        assert_eq!(store.flush().await, 1);
+
+        let restarted = SessionStore::with_persistence(db);
+        let reloaded = restarted.load("cart", None).await.unwrap();
+        assert_eq!(
+            reloaded.get_tool_state("cart"),
+            Some(serde_json::json!({ "items": ["apple"] }))
+        );
+        assert!(reloaded.get_tool_state("other").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_assistant_turn_survives_reload() {
+        use crate::llm::ToolCallFunction;
+        use crate::session::TurnToolCall;
+        use crate::uar::persistence::testing::InMemoryPersistence;
+
+        let db = Arc::new(InMemoryPersistence::new());
+        let store = SessionStore::with_persistence(db.clone());
+        let session = store.create_with_id("turns");
+        session.add_user_message("Weather in Paris?");
+
+        let call = ToolCall {
+            id: "call-1".to_string(),
+            call_type: "function".to_string(),
+            function: ToolCallFunction {
+                name: "weather".to_string(),
+                arguments: r#"{"city":"Paris"}"#.to_string(),
+            },
+        };
+        let mut turn = AssistantTurn::default();
+        turn.push_text("Let me check. ");
+        turn.record_tool_call(&call);
+        session.add_assistant_with_tool_calls(Some("Let me check. ".to_string()), vec![call]);
+        turn.record_tool_result("call-1", "Sunny", true);
+        session.add_tool_result("call-1", "Sunny");
+        turn.push_text("It is sunny.");
+        session.add_assistant_turn("It is sunny.", turn.clone());
+        assert_eq!(store.flush().await, 1);
+
+        let restarted = SessionStore::with_persistence(db);
+        let history = restarted.load("turns", None).await.unwrap().history();
+        assert_eq!(history.len(), 4);
+        assert!(history[..3].iter().all(|m| m.turn.is_none()));
+        let reloaded = history[3].turn.as_ref().unwrap();
+        assert_eq!(reloaded, &turn);
+        assert_eq!(reloaded.text, "Let me check. It is sunny.");
+        assert_eq!(
+            reloaded.tool_calls,
+            [TurnToolCall {
+                id: "call-1".to_string(),
+                name: "weather".to_string(),
+                arguments: r#"{"city":"Paris"}"#.to_string(),
+                result: Some("Sunny".to_string()),
+                success: true,
+            }]
+        );
+        // The model still sees only the closing text
+        assert_eq!(history[3].message.content.to_string(), "It is sunny.");
+    }
+
+    #[test]
+    fn test_flat_messages_still_deserialize() {
+        let session: Session = serde_json::from_value(serde_json::json!({
+            "id": "old",
+            "messages": [
+                { "role": "user", "content": "Hello" },
+                { "role": "assistant", "content": "Hi!" },
+            ],
+            "created_at": "2025-01-01T00:00:00Z",
+            "last_activity": "2025-01-01T00:00:00Z",
+            "system_prompt": null,
+        }))
+        .unwrap();
+
+        assert_eq!(session.messages()[1].content.to_string(), "Hi!");
+        assert!(session.history().iter().all(|m| m.turn.is_none()));
+        // Messages without a turn serialize as before
+        let state = serde_json::to_value(&session).unwrap();
+        assert_eq!(
+            state["messages"][1],
+            serde_json::json!({ "role": "assistant", "content": "Hi!" })
+        );
+    }
+
     #[test]
     fn test_system_prompt() {
         let session = Session::new("test".to_string());
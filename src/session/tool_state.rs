@@ -0,0 +1,97 @@
+//! Per-session state for stateful tools.
+//!
+//! Lets native tools (a code sandbox, a shopping cart) keep state across the
+//! turns of a conversation. State lives on the [`Session`], keyed by tool
+//! name, and is persisted along with the session's messages.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use serde_json::Value;
+
+use super::Session;
+
+/// Tool name -> state for a single session.
+#[derive(Debug, Default)]
+pub struct SessionToolState {
+    states: RwLock<HashMap<String, Value>>,
+}
+
+impl SessionToolState {
+    /// Restore tool state from its persisted form.
+    pub(crate) fn from_map(states: HashMap<String, Value>) -> Self {
+        Self {
+            states: RwLock::new(states),
+        }
+    }
+
+    /// Get the state stored by a tool.
+    #[must_use]
+    pub fn get(&self, tool: &str) -> Option<Value> {
+        self.states.read().unwrap().get(tool).cloned()
+    }
+
+    pub(crate) fn set(&self, tool: &str, value: Value) {
+        self.states.write().unwrap().insert(tool.to_string(), value);
+    }
+
+    pub(crate) fn remove(&self, tool: &str) -> Option<Value> {
+        self.states.write().unwrap().remove(tool)
+    }
+
+    /// Copy of every tool's state (for persistence).
+    #[must_use]
+    pub fn snapshot(&self) -> HashMap<String, Value> {
+        self.states.read().unwrap().clone()
+    }
+}
+
+/// A tool's view of its own state within one session.
+///
+/// Handed to [`NativeTool::call_with_state`](crate::mcp::registry::NativeTool::call_with_state)
+/// so a tool can read what it wrote in earlier turns. Writes mark the
+/// session modified, so they are persisted with it.
+#[derive(Debug, Clone)]
+pub struct ToolStateHandle {
+    session: Session,
+    tool: String,
+}
+
+impl ToolStateHandle {
+    /// Create a handle for `tool`'s state in `session`.
+    #[must_use]
+    pub fn new(session: Session, tool: impl Into<String>) -> Self {
+        Self {
+            session,
+            tool: tool.into(),
+        }
+    }
+
+    /// The session this state belongs to.
+    #[must_use]
+    pub fn session_id(&self) -> &str {
+        self.session.id()
+    }
+
+    /// The tool this state belongs to.
+    #[must_use]
+    pub fn tool(&self) -> &str {
+        &self.tool
+    }
+
+    /// Get the tool's current state, if it has stored any.
+    #[must_use]
+    pub fn get(&self) -> Option<Value> {
+        self.session.get_tool_state(&self.tool)
+    }
+
+    /// Replace the tool's state.
+    pub fn set(&self, value: Value) {
+        self.session.set_tool_state(&self.tool, value);
+    }
+
+    /// Remove the tool's state, returning the previous value.
+    pub fn clear(&self) -> Option<Value> {
+        self.session.clear_tool_state(&self.tool)
+    }
+}
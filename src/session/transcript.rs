@@ -0,0 +1,438 @@
+//! Portable conversation transcripts.
+//!
+//! A transcript is a session's system prompt and message history, in one of
+//! two formats:
+//!
+//! - JSON: `{"version": 1, "system_prompt": "...", "messages": [...]}`
+//! - JSON Lines: one message per line, the system prompt (if any) as a
+//!   leading `system` message
+//!
+//! Messages are stored exactly as sessions keep them, so assistant turns
+//! survive a round trip. Parsing validates roles and tool-call structure,
+//! so an imported session is always one the model can continue.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::thread::Session;
+use super::turn::SessionMessage;
+use crate::llm::MessageRole;
+
+/// Version of the JSON transcript format.
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+/// Serialization of a transcript.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptFormat {
+    #[default]
+    Json,
+    Jsonl,
+}
+
+impl TranscriptFormat {
+    /// Format of a request body with `content_type` (JSON unless it names
+    /// JSON Lines).
+    #[must_use]
+    pub fn from_content_type(content_type: &str) -> Self {
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        match mime {
+            "application/jsonl" | "application/x-ndjson" | "application/x-jsonlines" => {
+                Self::Jsonl
+            }
+            _ => Self::Json,
+        }
+    }
+
+    /// Content type of an exported transcript.
+    #[must_use]
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Jsonl => "application/jsonl",
+        }
+    }
+}
+
+/// Why a transcript can't be imported.
+#[derive(Debug, thiserror::Error)]
+pub enum TranscriptError {
+    #[error("Transcript is not valid JSON: {0}")]
+    InvalidJson(serde_json::Error),
+    #[error("Line {line} is not valid JSON: {error}")]
+    InvalidLine {
+        line: usize,
+        error: serde_json::Error,
+    },
+    #[error("Unsupported transcript version {0} (expected {TRANSCRIPT_VERSION})")]
+    UnsupportedVersion(u32),
+    #[error("Transcript has no messages")]
+    Empty,
+    #[error("Transcript has {count} messages, more than the {max} allowed")]
+    TooLong { count: usize, max: usize },
+    #[error("Message {index}: unknown role {role} (expected user, assistant or tool)")]
+    InvalidRole { index: usize, role: String },
+    #[error("Message {index}: {reason}")]
+    InvalidMessage { index: usize, reason: String },
+}
+
+impl TranscriptError {
+    /// HTTP status to reject the transcript with.
+    #[must_use]
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        match self {
+            Self::InvalidJson(_) | Self::InvalidLine { .. } => axum::http::StatusCode::BAD_REQUEST,
+            Self::TooLong { .. } => axum::http::StatusCode::PAYLOAD_TOO_LARGE,
+            _ => axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+/// A conversation as exported from, or imported into, a session.
+#[derive(Debug, Clone, Serialize)]
+pub struct Transcript {
+    pub version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    pub messages: Vec<SessionMessage>,
+}
+
+/// JSON transcript before its messages are checked one by one.
+#[derive(Deserialize)]
+struct RawTranscript {
+    #[serde(default = "default_version")]
+    version: u32,
+    #[serde(default)]
+    system_prompt: Option<String>,
+    messages: Vec<serde_json::Value>,
+}
+
+fn default_version() -> u32 {
+    TRANSCRIPT_VERSION
+}
+
+impl Transcript {
+    /// The system prompt and history of `session`.
+    #[must_use]
+    pub fn from_session(session: &Session) -> Self {
+        Self {
+            version: TRANSCRIPT_VERSION,
+            system_prompt: session.system_prompt(),
+            messages: session.history(),
+        }
+    }
+
+    /// Serialize as `format`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a message fails to serialize.
+    pub fn to_format(&self, format: TranscriptFormat) -> serde_json::Result<String> {
+        match format {
+            TranscriptFormat::Json => serde_json::to_string_pretty(self),
+            TranscriptFormat::Jsonl => {
+                let mut out = String::new();
+                if let Some(prompt) = &self.system_prompt {
+                    let system = serde_json::json!({ "role": "system", "content": prompt });
+                    out.push_str(&system.to_string());
+                    out.push('\n');
+                }
+                for message in &self.messages {
+                    out.push_str(&serde_json::to_string(message)?);
+                    out.push('\n');
+                }
+                Ok(out)
+            }
+        }
+    }
+
+    /// Parse and validate a transcript of at most `max_messages` messages.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first problem found: malformed JSON, an unsupported
+    /// version, too many or no messages, an unknown role, or tool calls and
+    /// results that don't pair up.
+    pub fn parse(
+        body: &str,
+        format: TranscriptFormat,
+        max_messages: usize,
+    ) -> Result<Self, TranscriptError> {
+        let raw = match format {
+            TranscriptFormat::Json => {
+                serde_json::from_str(body).map_err(TranscriptError::InvalidJson)?
+            }
+            TranscriptFormat::Jsonl => parse_lines(body)?,
+        };
+        if raw.version != TRANSCRIPT_VERSION {
+            return Err(TranscriptError::UnsupportedVersion(raw.version));
+        }
+        if raw.messages.is_empty() {
+            return Err(TranscriptError::Empty);
+        }
+        if raw.messages.len() > max_messages {
+            return Err(TranscriptError::TooLong {
+                count: raw.messages.len(),
+                max: max_messages,
+            });
+        }
+
+        let messages = raw
+            .messages
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| parse_message(i + 1, value))
+            .collect::<Result<Vec<_>, _>>()?;
+        validate_tool_calls(&messages)?;
+
+        Ok(Self {
+            version: raw.version,
+            system_prompt: raw.system_prompt,
+            messages,
+        })
+    }
+}
+
+/// Split JSON Lines into the system prompt and messages.
+fn parse_lines(body: &str) -> Result<RawTranscript, TranscriptError> {
+    let mut raw = RawTranscript {
+        version: TRANSCRIPT_VERSION,
+        system_prompt: None,
+        messages: Vec::new(),
+    };
+    for (i, line) in body.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: serde_json::Value = serde_json::from_str(line)
+            .map_err(|error| TranscriptError::InvalidLine { line: i + 1, error })?;
+        // A leading system message is the system prompt
+        if raw.messages.is_empty()
+            && raw.system_prompt.is_none()
+            && value["role"] == "system"
+            && let Some(prompt) = value["content"].as_str()
+        {
+            raw.system_prompt = Some(prompt.to_string());
+            continue;
+        }
+        raw.messages.push(value);
+    }
+    Ok(raw)
+}
+
+/// Check the role of message `index` (1-based) and deserialize it.
+fn parse_message(
+    index: usize,
+    value: serde_json::Value,
+) -> Result<SessionMessage, TranscriptError> {
+    let role = &value["role"];
+    if !matches!(role.as_str(), Some("user" | "assistant" | "tool")) {
+        return Err(TranscriptError::InvalidRole {
+            index,
+            role: role.to_string(),
+        });
+    }
+    serde_json::from_value(value).map_err(|e| TranscriptError::InvalidMessage {
+        index,
+        reason: e.to_string(),
+    })
+}
+
+/// Check that tool calls are well-formed and each is answered by exactly one
+/// tool message before the conversation moves on.
+fn validate_tool_calls(messages: &[SessionMessage]) -> Result<(), TranscriptError> {
+    let invalid = |index: usize, reason: String| TranscriptError::InvalidMessage { index, reason };
+    let mut seen = HashSet::new();
+    let mut pending: Vec<&str> = Vec::new();
+
+    for (i, SessionMessage { message, .. }) in messages.iter().enumerate() {
+        let index = i + 1;
+        if message.role != MessageRole::Tool && !pending.is_empty() {
+            return Err(invalid(
+                index,
+                format!("tool calls {} have no tool result", pending.join(", ")),
+            ));
+        }
+        if message.role != MessageRole::Tool && message.tool_call_id.is_some() {
+            return Err(invalid(index, "only tool messages have a tool_call_id".to_string()));
+        }
+        let calls = message.tool_calls.as_deref().unwrap_or_default();
+        if message.role != MessageRole::Assistant && !calls.is_empty() {
+            return Err(invalid(index, "only assistant messages make tool calls".to_string()));
+        }
+
+        for call in calls {
+            if call.id.is_empty() || call.function.name.is_empty() {
+                return Err(invalid(index, "tool calls need an id and a name".to_string()));
+            }
+            if call.call_type != "function" {
+                return Err(invalid(
+                    index,
+                    format!("tool call {} has unsupported type '{}'", call.id, call.call_type),
+                ));
+            }
+            if !call.function.arguments.is_empty()
+                && serde_json::from_str::<serde_json::Value>(&call.function.arguments).is_err()
+            {
+                return Err(invalid(
+                    index,
+                    format!("arguments of tool call {} are not valid JSON", call.id),
+                ));
+            }
+            if !seen.insert(call.id.as_str()) {
+                return Err(invalid(index, format!("duplicate tool call id {}", call.id)));
+            }
+            pending.push(&call.id);
+        }
+
+        if message.role == MessageRole::Tool {
+            let Some(id) = message.tool_call_id.as_deref() else {
+                return Err(invalid(index, "tool message has no tool_call_id".to_string()));
+            };
+            let Some(position) = pending.iter().position(|p| *p == id) else {
+                return Err(invalid(
+                    index,
+                    format!("tool result for {id} doesn't answer a pending tool call"),
+                ));
+            };
+            pending.remove(position);
+        }
+    }
+
+    if pending.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid(
+            messages.len(),
+            format!("tool calls {} have no tool result", pending.join(", ")),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{ToolCall, ToolCallFunction};
+    use crate::session::{AssistantTurn, SessionStore};
+
+    fn conversation(store: &SessionStore) -> Session {
+        let session = store.create();
+        session.set_system_prompt("You are terse.");
+        session.add_user_message("What time is it?");
+        session.add_assistant_with_tool_calls(
+            None,
+            vec![ToolCall {
+                id: "call_1".to_string(),
+                call_type: "function".to_string(),
+                function: ToolCallFunction {
+                    name: "time__now".to_string(),
+                    arguments: "{}".to_string(),
+                },
+            }],
+        );
+        session.add_tool_result("call_1", "12:00");
+        session.add_assistant_turn(
+            "Noon.",
+            AssistantTurn {
+                text: "Noon.".to_string(),
+                ..AssistantTurn::default()
+            },
+        );
+        session
+    }
+
+    fn history_json(session: &Session) -> serde_json::Value {
+        serde_json::to_value(session.history()).unwrap()
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let store = SessionStore::new();
+        let original = conversation(&store);
+
+        for format in [TranscriptFormat::Json, TranscriptFormat::Jsonl] {
+            let exported = Transcript::from_session(&original).to_format(format).unwrap();
+            let transcript = Transcript::parse(&exported, format, 100).unwrap();
+            let imported = store.import(transcript, Some("acme"));
+
+            assert_ne!(imported.id(), original.id());
+            assert_eq!(imported.tenant_id(), Some("acme"));
+            assert_eq!(imported.system_prompt().as_deref(), Some("You are terse."));
+            assert_eq!(history_json(&imported), history_json(&original), "{format:?}");
+            assert!(imported.is_dirty());
+        }
+    }
+
+    #[test]
+    fn test_content_types() {
+        assert_eq!(
+            TranscriptFormat::from_content_type("application/x-ndjson; charset=utf-8"),
+            TranscriptFormat::Jsonl
+        );
+        assert_eq!(
+            TranscriptFormat::from_content_type("application/json"),
+            TranscriptFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_malformed_transcripts_are_rejected() {
+        let parse = |body: &str| Transcript::parse(body, TranscriptFormat::Json, 3).unwrap_err();
+        let jsonl = |body: &str| Transcript::parse(body, TranscriptFormat::Jsonl, 3).unwrap_err();
+        let user = r#"{"role": "user", "content": "Hi"}"#;
+
+        assert!(matches!(parse("{"), TranscriptError::InvalidJson(_)));
+        let err = jsonl(&format!("{user}\nnot json"));
+        assert!(matches!(err, TranscriptError::InvalidLine { line: 2, .. }), "{err}");
+        assert!(matches!(
+            parse(r#"{"version": 2, "messages": []}"#),
+            TranscriptError::UnsupportedVersion(2)
+        ));
+        assert!(matches!(parse(r#"{"messages": []}"#), TranscriptError::Empty));
+        let err = jsonl(&[user; 4].join("\n"));
+        assert!(matches!(err, TranscriptError::TooLong { count: 4, max: 3 }), "{err}");
+
+        let err = jsonl(&format!("{user}\n{}", r#"{"role": "moderator", "content": "x"}"#));
+        assert_eq!(
+            err.to_string(),
+            r#"Message 2: unknown role "moderator" (expected user, assistant or tool)"#
+        );
+        let err = jsonl(r#"{"role": "user"}"#);
+        assert!(matches!(err, TranscriptError::InvalidMessage { index: 1, .. }), "{err}");
+
+        let call = |id: &str, args: &str| {
+            serde_json::json!({
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{
+                    "id": id,
+                    "type": "function",
+                    "function": { "name": "time__now", "arguments": args }
+                }]
+            })
+            .to_string()
+        };
+        let result = |id: &str| {
+            serde_json::json!({ "role": "tool", "content": "12:00", "tool_call_id": id })
+                .to_string()
+        };
+        let err = jsonl(&format!("{user}\n{}", result("call_1")));
+        assert_eq!(
+            err.to_string(),
+            "Message 2: tool result for call_1 doesn't answer a pending tool call"
+        );
+        let err = jsonl(&format!("{}\n{user}", call("call_1", "{}")));
+        assert_eq!(err.to_string(), "Message 2: tool calls call_1 have no tool result");
+        let err = jsonl(&call("call_1", "{"));
+        assert!(err.to_string().contains("not valid JSON"), "{err}");
+        let err = jsonl(&format!(
+            "{}\n{}\n{}",
+            call("call_1", "{}"),
+            result("call_1"),
+            call("call_1", "{}")
+        ));
+        assert_eq!(err.to_string(), "Message 3: duplicate tool call id call_1");
+        assert_eq!(err.status_code(), axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
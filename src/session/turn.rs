@@ -0,0 +1,103 @@
+//! Structured record of what the assistant produced in a turn.
+
+use serde::{Deserialize, Serialize};
+
+use crate::llm::{Message, ToolCall};
+use crate::uar::domain::events::ArtifactPayload;
+
+/// A message as kept in a session.
+///
+/// The turn is flattened next to the message's own fields, so messages
+/// stored before turns were recorded still deserialize.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMessage {
+    #[serde(flatten)]
+    pub message: Message,
+    /// Structure of the assistant turn this message closes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn: Option<AssistantTurn>,
+}
+
+impl From<Message> for SessionMessage {
+    fn from(message: Message) -> Self {
+        Self {
+            message,
+            turn: None,
+        }
+    }
+}
+
+/// Everything the assistant produced in answer to one user message.
+///
+/// A turn spans the whole tool loop, whereas the session's messages only
+/// keep what the model needs to continue the conversation.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssistantTurn {
+    /// Answer text over all model calls of the turn
+    #[serde(default)]
+    pub text: String,
+    /// Reasoning the model exposed while answering
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_summary: Option<String>,
+    /// Tools called, in order, with their results
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<TurnToolCall>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<ArtifactPayload>,
+}
+
+/// A tool call made during a turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnToolCall {
+    pub id: String,
+    pub name: String,
+    /// Arguments as JSON string
+    pub arguments: String,
+    /// Tool output; `None` if the turn ended before the tool returned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    #[serde(default)]
+    pub success: bool,
+}
+
+impl AssistantTurn {
+    /// Append answer text.
+    pub fn push_text(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    /// Append reasoning text.
+    pub fn push_reasoning(&mut self, text: &str) {
+        self.reasoning_summary
+            .get_or_insert_default()
+            .push_str(text);
+    }
+
+    /// Record a tool call the model made.
+    pub fn record_tool_call(&mut self, call: &ToolCall) {
+        self.tool_calls.push(TurnToolCall {
+            id: call.id.clone(),
+            name: call.function.name.clone(),
+            arguments: call.function.arguments.clone(),
+            result: None,
+            success: false,
+        });
+    }
+
+    /// Attach a tool's result to its call.
+    pub fn record_tool_result(&mut self, id: &str, content: &str, success: bool) {
+        if let Some(call) = self.tool_calls.iter_mut().rev().find(|c| c.id == id) {
+            call.result = Some(content.to_string());
+            call.success = success;
+        }
+    }
+
+    /// Whether nothing was produced.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+            && self.reasoning_summary.is_none()
+            && self.tool_calls.is_empty()
+            && self.artifacts.is_empty()
+    }
+}
@@ -31,6 +31,20 @@ pub fn to_ag_ui(event: &NormalizedEvent) -> Value {
                 "props": artifact
             }
         }),
+        NormalizedEvent::Usage {
+            run_id,
+            prompt_tokens,
+            completion_tokens,
+            total_tokens,
+        } => json!({
+            "type": "usage",
+            "id": run_id,
+            "payload": {
+                "prompt_tokens": prompt_tokens,
+                "completion_tokens": completion_tokens,
+                "total_tokens": total_tokens
+            }
+        }),
         // Default fallthrough to raw event
         other => serde_json::to_value(other).unwrap_or(json!({"error": "serialization_failed"})),
     }
@@ -0,0 +1,455 @@
+//! Administrative endpoints.
+//!
+//! All endpoints are restricted to users with the admin role.
+//!
+//! `GET /audit?resource=kb` returns the audit trail of knowledge base and
+//! document mutations, newest first; an admin acting in a tenant only sees
+//! the entries recorded in that tenant. `GET /ingestion` is a human-readable
+//! snapshot of the ingestion pipeline's backlog and throughput.
+//! `GET /rate-limits` lists the requests each client made in the current
+//! rate limit window.
+
+use axum::{
+    Json, Router,
+    Extension,
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::uar::{
+    domain::audit::{AuditEntry, AuditResource},
+    rag::ingestion_worker::{IngestionStats, IngestionWorkerPool},
+    security::{
+        audit::AuditSink,
+        claims::{TenantContext, tenant_scope},
+        middleware::require_admin,
+        rate_limit::RateLimiter,
+    },
+};
+
+/// Most entries returned by one audit query.
+const MAX_AUDIT_LIMIT: usize = 1000;
+
+// =============================================================================
+// State & DTOs
+// =============================================================================
+
+/// Shared state for admin API handlers.
+#[derive(Clone, Debug)]
+pub struct AdminApiState {
+    /// `None` when auditing is disabled
+    pub audit: Option<Arc<dyn AuditSink>>,
+    /// `None` when documents aren't ingested in the background
+    pub ingestion_pool: Option<Arc<IngestionWorkerPool>>,
+    /// `None` when rate limiting is disabled
+    pub rate_limiter: Option<Arc<dyn RateLimiter>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    /// Only entries for this resource (e.g. `kb`)
+    pub resource: Option<AuditResource>,
+    #[serde(default = "default_audit_limit")]
+    pub limit: usize,
+}
+
+fn default_audit_limit() -> usize {
+    100
+}
+
+#[derive(Debug, Serialize)]
+pub struct RateLimitCounts {
+    /// `memory` or `redis`
+    pub backend: &'static str,
+    /// Requests in the current window, by client
+    pub clients: BTreeMap<String, u64>,
+}
+
+// =============================================================================
+// Router
+// =============================================================================
+
+pub fn build_router() -> Router<Arc<AdminApiState>> {
+    Router::new()
+        .route(
+            "/audit",
+            get(list_audit_entries).route_layer(axum::middleware::from_fn(require_admin)),
+        )
+        .route(
+            "/ingestion",
+            get(ingestion_stats).route_layer(axum::middleware::from_fn(require_admin)),
+        )
+        .route(
+            "/rate-limits",
+            get(rate_limit_counts).route_layer(axum::middleware::from_fn(require_admin)),
+        )
+}
+
+/// GET /audit - Recorded mutations, newest first (admin only)
+///
+/// Admins without a tenant see the whole trail, tenant admins only their
+/// tenant's entries.
+async fn list_audit_entries(
+    State(state): State<Arc<AdminApiState>>,
+    tenant: Option<Extension<TenantContext>>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, (StatusCode, String)> {
+    let audit = state.audit.as_ref().ok_or((
+        StatusCode::NOT_FOUND,
+        "Audit trail is disabled".to_string(),
+    ))?;
+
+    let entries = audit
+        .list(
+            query.resource,
+            tenant_scope(tenant.as_deref()),
+            query.limit.min(MAX_AUDIT_LIMIT),
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(entries))
+}
+
+/// GET /ingestion - Queue depth, workers and job timings of the ingestion pool (admin only)
+async fn ingestion_stats(
+    State(state): State<Arc<AdminApiState>>,
+) -> Result<Json<IngestionStats>, (StatusCode, String)> {
+    let pool = state.ingestion_pool.as_ref().ok_or((
+        StatusCode::NOT_FOUND,
+        "Ingestion worker pool is not running".to_string(),
+    ))?;
+    Ok(Json(pool.stats()))
+}
+
+/// GET /rate-limits - Requests per client in the current window (admin only)
+async fn rate_limit_counts(
+    State(state): State<Arc<AdminApiState>>,
+) -> Result<Json<RateLimitCounts>, (StatusCode, String)> {
+    let limiter = state.rate_limiter.as_ref().ok_or((
+        StatusCode::NOT_FOUND,
+        "Rate limiting is disabled".to_string(),
+    ))?;
+    let clients = limiter
+        .counts()
+        .await
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e.to_string()))?;
+    Ok(Json(RateLimitCounts {
+        backend: limiter.backend(),
+        clients,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::domain::audit::{AuditAction, AuditTarget};
+    use crate::uar::domain::knowledge::{DocumentStatus, KnowledgeDocument};
+    use crate::uar::persistence::testing::InMemoryPersistence;
+    use crate::uar::security::audit::PersistentAuditSink;
+    use crate::uar::security::claims::{ADMIN_ROLE, UserClaims, UserContext};
+    use crate::uar::rag::{chunking::ChunkingStrategy, embedding::EmbeddingProvider};
+    use crate::uar::rag::ingest::IngestService;
+    use crate::uar::runtime::matching::VectorMatcher;
+    use async_trait::async_trait;
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+    use tower::ServiceExt;
+
+    /// Embeds only once the test hands out a permit.
+    #[derive(Debug)]
+    struct GatedEmbedder(Arc<Semaphore>);
+
+    #[async_trait]
+    impl EmbeddingProvider for GatedEmbedder {
+        async fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+            self.0.acquire().await?.forget();
+            Ok(texts.iter().map(|_| vec![0.0; 4]).collect())
+        }
+
+        fn dimensions(&self) -> usize {
+            4
+        }
+    }
+
+    fn user(roles: Vec<String>) -> UserContext {
+        UserContext {
+            user_id: "ops".to_string(),
+            claims: UserClaims {
+                sub: "ops".to_string(),
+                name: None,
+                roles: Some(roles),
+                exp: 0,
+                tenant_id: None,
+            },
+        }
+    }
+
+    fn document(id: &str) -> KnowledgeDocument {
+        KnowledgeDocument {
+            id: id.to_string(),
+            kb_id: "kb-1".to_string(),
+            filename: format!("{id}.txt"),
+            file_path: None,
+            mime_type: Some("text/plain".to_string()),
+            chunk_count: 0,
+            status: DocumentStatus::Pending,
+            tenant_id: None,
+            metadata: None,
+            idempotency_key: None,
+            content_hash: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    async fn get_stats(state: &Arc<AdminApiState>) -> serde_json::Value {
+        let response = build_router()
+            .with_state(Arc::clone(state))
+            .oneshot(
+                axum::http::Request::get("/ingestion")
+                    .extension(user(vec![ADMIN_ROLE.to_string()]))
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// Poll the stats until `done` holds for them.
+    async fn wait_for(
+        state: &Arc<AdminApiState>,
+        done: impl Fn(&serde_json::Value) -> bool,
+    ) -> serde_json::Value {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let stats = get_stats(state).await;
+                if done(&stats) {
+                    return stats;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("ingestion stats did not settle")
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_ingestion_stats_follow_submitted_jobs() {
+        let db = Arc::new(InMemoryPersistence::new());
+        let permits = Arc::new(Semaphore::new(0));
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(GatedEmbedder(Arc::clone(&permits))),
+        ));
+        let ingest = Arc::new(IngestService::new(
+            Arc::clone(&db),
+            matcher,
+            ChunkingStrategy::FixedSize { size: 64 },
+        ));
+        let pool = Arc::new(IngestionWorkerPool::new(1, 10, ingest, db).unwrap());
+        let state = Arc::new(AdminApiState {
+            audit: None,
+            ingestion_pool: Some(Arc::clone(&pool)),
+            rate_limiter: None,
+        });
+
+        let stats = get_stats(&state).await;
+        assert_eq!(stats["queue_depth"], 0);
+        assert_eq!(stats["jobs_processed"], 0);
+        assert_eq!(stats["worker_count"], 1);
+
+        // The only worker blocks on the first job, so the second one queues
+        pool.submit(document("doc-1"), b"first document".to_vec())
+            .await
+            .unwrap();
+        wait_for(&state, |stats| stats["active_workers"] == 1).await;
+        pool.submit(document("doc-2"), b"second document".to_vec())
+            .await
+            .unwrap();
+        let stats = get_stats(&state).await;
+        assert_eq!(stats["queue_depth"], 1);
+        assert_eq!(stats["active_workers"], 1);
+
+        permits.add_permits(2);
+        let stats = wait_for(&state, |stats| stats["jobs_processed"] == 2).await;
+        assert_eq!(stats["queue_depth"], 0);
+        assert_eq!(stats["jobs_failed"], 0);
+        for stage in crate::uar::rag::ingestion_worker::STAGES {
+            assert!(stats["stage_avg_ms"][stage].is_number(), "{stats}");
+        }
+        assert!(stats["avg_processing_ms"].as_f64().unwrap() > 0.0, "{stats}");
+    }
+
+    #[tokio::test]
+    async fn test_ingestion_stats_without_pool() {
+        let state = Arc::new(AdminApiState {
+            audit: None,
+            ingestion_pool: None,
+            rate_limiter: None,
+        });
+        let response = build_router()
+            .with_state(state)
+            .oneshot(
+                axum::http::Request::get("/ingestion")
+                    .extension(user(vec![ADMIN_ROLE.to_string()]))
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_ingestion_stats_need_admin() {
+        let state = Arc::new(AdminApiState {
+            audit: None,
+            ingestion_pool: None,
+            rate_limiter: None,
+        });
+        let response = build_router()
+            .with_state(state)
+            .oneshot(
+                axum::http::Request::get("/ingestion")
+                    .extension(user(vec![]))
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_audit_trail_needs_admin() {
+        let sink = PersistentAuditSink::new(Arc::new(InMemoryPersistence::new()));
+        sink.record(AuditEntry::new(
+            "alice",
+            AuditAction::Delete,
+            AuditTarget::KnowledgeBase,
+            "kb-1",
+        ))
+        .await
+        .unwrap();
+        let state = Arc::new(AdminApiState {
+            audit: Some(Arc::new(sink)),
+            ingestion_pool: None,
+            rate_limiter: None,
+        });
+
+        let get = |roles: Vec<String>| {
+            build_router().with_state(Arc::clone(&state)).oneshot(
+                axum::http::Request::get("/audit?resource=kb")
+                    .extension(user(roles))
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = get(vec![]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = get(vec![ADMIN_ROLE.to_string()]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let entries: Vec<AuditEntry> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].actor, "alice");
+    }
+
+    #[tokio::test]
+    async fn test_tenant_admin_sees_only_own_audit_entries() {
+        let sink = PersistentAuditSink::new(Arc::new(InMemoryPersistence::new()));
+        for (actor, tenant) in [("alice", Some("acme")), ("bob", Some("globex")), ("ops", None)] {
+            let entry =
+                AuditEntry::new(actor, AuditAction::Delete, AuditTarget::KnowledgeBase, "kb-1")
+                    .with_tenant(tenant);
+            sink.record(entry).await.unwrap();
+        }
+        let state = Arc::new(AdminApiState {
+            audit: Some(Arc::new(sink)),
+            ingestion_pool: None,
+            rate_limiter: None,
+        });
+
+        let actors = |tenant: Option<&str>| {
+            let mut request = axum::http::Request::get("/audit")
+                .extension(user(vec![ADMIN_ROLE.to_string()]));
+            if let Some(tenant_id) = tenant {
+                request = request.extension(TenantContext {
+                    tenant_id: tenant_id.to_string(),
+                });
+            }
+            let request = request.body(axum::body::Body::empty()).unwrap();
+            let router = build_router().with_state(Arc::clone(&state));
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let entries: Vec<AuditEntry> = serde_json::from_slice(&body).unwrap();
+                let mut actors: Vec<String> = entries.into_iter().map(|e| e.actor).collect();
+                actors.sort();
+                actors
+            }
+        };
+
+        assert_eq!(actors(Some("acme")).await, ["alice"]);
+        assert_eq!(actors(Some("globex")).await, ["bob"]);
+        assert_eq!(actors(None).await, ["alice", "bob", "ops"]);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_counts_need_admin() {
+        use crate::uar::security::rate_limit::AppRateLimiter;
+
+        let limiter = AppRateLimiter::new(1.0, 3);
+        for _ in 0..2 {
+            assert!(limiter.check("10.0.0.1"));
+        }
+        assert!(limiter.check("10.0.0.2"));
+        let state = Arc::new(AdminApiState {
+            audit: None,
+            ingestion_pool: None,
+            rate_limiter: Some(Arc::new(limiter)),
+        });
+
+        let get = |roles: Vec<String>| {
+            build_router().with_state(Arc::clone(&state)).oneshot(
+                axum::http::Request::get("/rate-limits")
+                    .extension(user(roles))
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let response = get(vec![]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = get(vec![ADMIN_ROLE.to_string()]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let counts: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            counts,
+            serde_json::json!({
+                "backend": "memory",
+                "clients": { "10.0.0.1": 2, "10.0.0.2": 1 },
+            })
+        );
+    }
+}
@@ -0,0 +1,397 @@
+//! Liveness and readiness probes.
+//!
+//! `GET /healthz` checks the persistence layer, the embedding model and
+//! every MCP server, and reports the circuit of each LLM provider;
+//! `GET /readyz` additionally requires an idle ingestion worker, room in the
+//! ingestion queue and a successful embedding provider warmup (when
+//! enabled). Both are served without authentication.
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::llm::{CircuitBreakerRegistry, CircuitSnapshot};
+use crate::mcp::registry::McpRegistry;
+use crate::uar::{
+    persistence::PersistenceLayer, rag::ingestion_worker::IngestionWorkerPool,
+    runtime::matching::VectorMatcher,
+};
+
+/// Time allowed for the persistence round trip.
+const DB_TIMEOUT: Duration = Duration::from_secs(2);
+/// Time allowed for each MCP server to answer.
+const MCP_TIMEOUT: Duration = Duration::from_secs(2);
+/// Gauge set to 1 for healthy components and 0 otherwise.
+const HEALTH_METRIC: &str = "uar_health";
+
+// =============================================================================
+// State & DTOs
+// =============================================================================
+
+/// Dependencies probed by the health endpoints.
+#[derive(Clone, Debug)]
+pub struct HealthState {
+    pub persistence: Option<Arc<dyn PersistenceLayer>>,
+    pub vector_matcher: Arc<VectorMatcher>,
+    pub mcp: Arc<McpRegistry>,
+    pub ingestion_pool: Option<Arc<IngestionWorkerPool>>,
+    pub circuit_breakers: Option<Arc<CircuitBreakerRegistry>>,
+}
+
+/// Overall health of the service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    /// Serving, but with reduced functionality
+    Degraded,
+    /// Not able to serve requests
+    Unhealthy,
+}
+
+/// Outcome of a single dependency check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum Check {
+    Ok,
+    /// Not configured, so not checked
+    Disabled,
+    Failed { error: String },
+}
+
+impl Check {
+    fn from_result(result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => Self::Ok,
+            Err(error) => Self::Failed { error },
+        }
+    }
+
+    fn is_failed(&self) -> bool {
+        matches!(self, Self::Failed { .. })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthChecks {
+    pub db: Check,
+    pub embedder: Check,
+    pub mcp: BTreeMap<String, Check>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workers: Option<Check>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedder_warmup: Option<Check>,
+    /// Circuit of each LLM provider called so far
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub llm: BTreeMap<String, CircuitSnapshot>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    pub status: HealthStatus,
+    pub checks: HealthChecks,
+}
+
+// =============================================================================
+// Router
+// =============================================================================
+
+pub fn build_router() -> Router<Arc<HealthState>> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+}
+
+/// GET /healthz - Dependency status; 503 only when unhealthy
+async fn healthz(State(state): State<Arc<HealthState>>) -> (StatusCode, Json<HealthResponse>) {
+    respond(check_dependencies(&state).await)
+}
+
+/// GET /readyz - Like /healthz, but also requires an idle ingestion worker
+/// and valid embedding credentials
+async fn readyz(State(state): State<Arc<HealthState>>) -> (StatusCode, Json<HealthResponse>) {
+    let mut health = check_dependencies(&state).await;
+
+    let workers = match &state.ingestion_pool {
+        Some(pool) if pool.is_queue_full() => Check::Failed {
+            error: format!("ingestion queue is full ({} jobs waiting)", pool.queue_depth()),
+        },
+        Some(pool) if pool.available_workers() == 0 => Check::Failed {
+            error: "all ingestion workers are busy".to_string(),
+        },
+        Some(_) => Check::Ok,
+        None => Check::Disabled,
+    };
+    if workers.is_failed() {
+        health.status = HealthStatus::Unhealthy;
+    }
+    record("workers", &workers);
+    health.checks.workers = Some(workers);
+
+    let warmup = match state.vector_matcher.warmup_result() {
+        Some(result) => Check::from_result(result),
+        None => Check::Disabled,
+    };
+    if warmup.is_failed() {
+        health.status = HealthStatus::Unhealthy;
+    }
+    record("embedder_warmup", &warmup);
+    health.checks.embedder_warmup = Some(warmup);
+
+    respond(health)
+}
+
+fn respond(health: HealthResponse) -> (StatusCode, Json<HealthResponse>) {
+    let code = match health.status {
+        HealthStatus::Ok | HealthStatus::Degraded => StatusCode::OK,
+        HealthStatus::Unhealthy => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (code, Json(health))
+}
+
+// =============================================================================
+// Checks
+// =============================================================================
+
+/// Probe every dependency.
+///
+/// An unreachable database makes the service unhealthy; a missing embedding
+/// model, a failing MCP server or an open LLM circuit only degrades it.
+pub async fn check_dependencies(state: &HealthState) -> HealthResponse {
+    let db = match &state.persistence {
+        Some(db) => match tokio::time::timeout(DB_TIMEOUT, db.ping()).await {
+            Ok(result) => Check::from_result(result.map_err(|e| format!("{e:#}"))),
+            Err(_) => Check::Failed {
+                error: format!("no response within {}s", DB_TIMEOUT.as_secs()),
+            },
+        },
+        None => Check::Disabled,
+    };
+
+    let embedder = if state.vector_matcher.is_ready() {
+        Check::Ok
+    } else {
+        Check::Failed {
+            error: "embedding model not initialized".to_string(),
+        }
+    };
+
+    let mcp: BTreeMap<String, Check> = state
+        .mcp
+        .health_check(MCP_TIMEOUT)
+        .await
+        .into_iter()
+        .map(|(server, result)| (server, Check::from_result(result)))
+        .collect();
+
+    let llm = state
+        .circuit_breakers
+        .as_ref()
+        .map(|breakers| breakers.snapshots())
+        .unwrap_or_default();
+
+    let mut status = HealthStatus::Ok;
+    if embedder.is_failed()
+        || mcp.values().any(Check::is_failed)
+        || llm.values().any(|circuit| circuit.state == "open")
+    {
+        status = HealthStatus::Degraded;
+    }
+    if db.is_failed() {
+        status = HealthStatus::Unhealthy;
+    }
+
+    record("db", &db);
+    record("embedder", &embedder);
+    for (server, check) in &mcp {
+        record(&format!("mcp:{server}"), check);
+    }
+
+    HealthResponse {
+        status,
+        checks: HealthChecks {
+            db,
+            embedder,
+            mcp,
+            workers: None,
+            embedder_warmup: None,
+            llm,
+        },
+    }
+}
+
+fn record(component: &str, check: &Check) {
+    let value = if check.is_failed() { 0.0 } else { 1.0 };
+    metrics::gauge!(HEALTH_METRIC, "component" => component.to_string()).set(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CircuitBreakerConfig;
+    use crate::uar::domain::knowledge::KbConfig;
+    use crate::uar::persistence::testing::InMemoryPersistence;
+    use crate::uar::rag::embedding::EmbeddingProvider;
+    use crate::uar::testing::StubEmbedder;
+    use async_trait::async_trait;
+    use tower::ServiceExt;
+
+    async fn state(db: Arc<InMemoryPersistence>) -> Arc<HealthState> {
+        let vector_matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::new(vec![0.0; 4])),
+        ));
+        vector_matcher.initialize().await.unwrap();
+        Arc::new(HealthState {
+            persistence: Some(db),
+            vector_matcher,
+            mcp: Arc::new(McpRegistry::new_empty()),
+            ingestion_pool: None,
+            circuit_breakers: None,
+        })
+    }
+
+    async fn get_json(state: Arc<HealthState>, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = build_router()
+            .with_state(state)
+            .oneshot(
+                axum::http::Request::get(uri)
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_healthy() {
+        let state = state(Arc::new(InMemoryPersistence::new())).await;
+        let (status, body) = get_json(state, "/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["checks"]["db"]["status"], "ok");
+        assert_eq!(body["checks"]["embedder"]["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn test_failing_database_is_unhealthy() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.set_unreachable(true);
+        let state = state(db).await;
+
+        for uri in ["/healthz", "/readyz"] {
+            let (status, body) = get_json(Arc::clone(&state), uri).await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{uri}");
+            assert_eq!(body["status"], "unhealthy");
+            assert_eq!(body["checks"]["db"]["status"], "failed");
+            assert_eq!(
+                body["checks"]["db"]["error"],
+                "database connection failed: connection refused"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_circuit_degrades() {
+        let breakers = Arc::new(CircuitBreakerRegistry::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            ..CircuitBreakerConfig::default()
+        }));
+        let state = Arc::new(HealthState {
+            circuit_breakers: Some(Arc::clone(&breakers)),
+            ..(*state(Arc::new(InMemoryPersistence::new())).await).clone()
+        });
+
+        breakers.for_provider("api.openai.com");
+        let (_, body) = get_json(Arc::clone(&state), "/healthz").await;
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["checks"]["llm"]["api.openai.com"]["state"], "closed");
+
+        breakers.for_provider("api.openai.com").record_failure(std::time::Instant::now());
+        let (status, body) = get_json(state, "/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["checks"]["llm"]["api.openai.com"]["state"], "open");
+        let retry_in = body["checks"]["llm"]["api.openai.com"]["retry_in_secs"].as_u64();
+        assert!(retry_in.is_some_and(|secs| secs <= 30), "{retry_in:?}");
+    }
+
+    #[tokio::test]
+    async fn test_uninitialized_embedder_degrades() {
+        let state = Arc::new(HealthState {
+            persistence: None,
+            vector_matcher: Arc::new(VectorMatcher::with_provider(
+                0.5,
+                Arc::new(StubEmbedder::new(vec![0.0; 4])),
+            )),
+            mcp: Arc::new(McpRegistry::new_empty()),
+            ingestion_pool: None,
+            circuit_breakers: None,
+        });
+        let (status, body) = get_json(state, "/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["checks"]["db"]["status"], "disabled");
+        assert_eq!(body["checks"]["embedder"]["status"], "failed");
+    }
+
+    /// Embedder whose endpoint rejects its credentials.
+    #[derive(Debug)]
+    struct RejectedEmbedder;
+
+    #[async_trait]
+    impl EmbeddingProvider for RejectedEmbedder {
+        async fn embed(&self, _texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+            anyhow::bail!("Embedding API rejected the credentials")
+        }
+
+        fn dimensions(&self) -> usize {
+            4
+        }
+
+        async fn initialize(&self) -> anyhow::Result<()> {
+            anyhow::bail!("Embedding API rejected the credentials")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_warmup_fails_readiness() {
+        let state = state(Arc::new(InMemoryPersistence::new())).await;
+        let (status, body) = get_json(Arc::clone(&state), "/readyz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["checks"]["embedder_warmup"]["status"], "disabled");
+
+        state.vector_matcher.warm_up([&KbConfig::default()]).await;
+        let (status, body) = get_json(Arc::clone(&state), "/readyz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["checks"]["embedder_warmup"]["status"], "ok");
+
+        let state = Arc::new(HealthState {
+            persistence: None,
+            vector_matcher: Arc::new(VectorMatcher::with_provider(
+                0.5,
+                Arc::new(RejectedEmbedder),
+            )),
+            mcp: Arc::new(McpRegistry::new_empty()),
+            ingestion_pool: None,
+            circuit_breakers: None,
+        });
+        state.vector_matcher.warm_up([&KbConfig::default()]).await;
+        let (status, body) = get_json(Arc::clone(&state), "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["checks"]["embedder_warmup"]["status"], "failed");
+        let error = body["checks"]["embedder_warmup"]["error"].as_str().unwrap();
+        assert!(error.contains("rejected the credentials"), "{error}");
+
+        // Liveness is unaffected
+        let (status, _) = get_json(state, "/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
@@ -3,19 +3,55 @@
 //! Provides CRUD operations for knowledge bases and document ingestion.
 
 use axum::{
-    Json, Router,
-    extract::{Multipart, Path, Query, State},
-    http::StatusCode,
+    Extension, Json, Router,
+    body::Body,
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    handler::Handler,
+    http::{HeaderMap, StatusCode, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
     routing::{get, post},
 };
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::path::PathBuf;
 use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::io::ReaderStream;
 
 use crate::uar::{
-    domain::knowledge::{DocumentStatus, KbConfig, KnowledgeBase, KnowledgeDocument},
-    persistence::PersistenceLayer,
-    rag::{chunking::ChunkingStrategy, ingestion_worker::IngestionWorkerPool},
-    runtime::matching::VectorMatcher,
+    api::search_cache::{SearchCache, SearchKey},
+    domain::{
+        audit::{AuditAction, AuditEntry, AuditTarget},
+        graph::{GraphEdge, GraphNode},
+        knowledge::{
+            DocumentStatus, IngestionProgress, KbConfig, KnowledgeBase, KnowledgeDocument,
+            RerankerConfig,
+        },
+        tenant::owned_by,
+    },
+    file_processing::{MIME_SNIFF_LEN, detect_mime_type},
+    persistence::{PersistenceError, PersistenceLayer},
+    rag::{
+        archive::ARCHIVE_MIME_TYPE,
+        chunking::{Chunker, ChunkingStrategy},
+        embedding::validate_kb_dimensions,
+        extraction::ExtractionStrategy,
+        graph::MAX_GRAPH_DEPTH,
+        ingest::{IngestService, extract_document},
+        ingestion_worker::{IngestionWorkerPool, SubmitError},
+        reindex::ReindexJob,
+        rerank::{self, RerankerRegistry},
+    },
+    runtime::{context::token_service::TokenService, matching::VectorMatcher},
+    security::{
+        audit::{AuditSink, actor},
+        claims::{TenantContext, UserContext, tenant_scope},
+    },
 };
 
 // =============================================================================
@@ -28,6 +64,33 @@ pub struct KnowledgeApiState {
     pub persistence: Arc<dyn PersistenceLayer>,
     pub vector_matcher: Arc<VectorMatcher>,
     pub ingestion_pool: Option<Arc<IngestionWorkerPool>>,
+    /// Exports and imports knowledge bases; `None` disables both
+    pub ingest_service: Option<Arc<IngestService>>,
+    pub rerankers: Arc<RerankerRegistry>,
+    /// Records KB and document mutations; `None` when auditing is disabled
+    pub audit: Option<Arc<dyn AuditSink>>,
+    /// Directory uploaded documents are streamed to
+    pub upload_dir: PathBuf,
+    /// Largest document upload accepted, in bytes
+    pub max_upload_size: usize,
+    /// MIME types uploads may have (exact, or `type/*`); empty allows all
+    pub allowed_mime_types: Vec<String>,
+    /// Responses of `GET /{id}/search`; `None` disables caching
+    pub search_cache: Option<Arc<SearchCache>>,
+}
+
+impl KnowledgeApiState {
+    /// Record a mutation in the audit trail.
+    ///
+    /// The mutation has already happened, so a failed write is logged
+    /// rather than failing the request.
+    async fn audit(&self, entry: AuditEntry) {
+        if let Some(audit) = &self.audit
+            && let Err(e) = audit.record(entry).await
+        {
+            tracing::error!(error = %e, "Failed to write audit entry");
+        }
+    }
 }
 
 // =============================================================================
@@ -57,6 +120,9 @@ pub struct KbConfigRequest {
     pub file_processor: Option<String>,
     pub chunk_strategy: Option<String>,
     pub chunk_size: Option<usize>,
+    pub rerank: Option<RerankerConfig>,
+    /// "llm", "spacy", "leiden" or "none"
+    pub extraction_strategy: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -76,6 +142,8 @@ pub struct KbConfigResponse {
     pub vector_dimensions: Option<usize>,
     pub file_processor: String,
     pub chunk_strategy: String,
+    pub rerank: Option<RerankerConfig>,
+    pub extraction_strategy: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -88,10 +156,40 @@ pub struct DocumentResponse {
     pub chunk_count: usize,
     pub status: String,
     pub error_message: Option<String>,
+    /// Extracted title, author, creation date, word count and language
+    pub metadata: Option<serde_json::Value>,
+    /// SHA-256 of the file it was last indexed from
+    pub content_hash: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Chunk settings to try instead of the KB's own.
+#[derive(Debug, Deserialize)]
+pub struct PreviewQuery {
+    pub chunk_strategy: Option<String>,
+    pub chunk_size: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewResponse {
+    pub filename: String,
+    pub chunk_strategy: String,
+    pub chunk_count: usize,
+    pub total_characters: usize,
+    /// Tokens the chunks would cost to embed (cl100k_base estimate)
+    pub estimated_tokens: usize,
+    pub chunks: Vec<ChunkPreview>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkPreview {
+    pub index: usize,
+    pub content: String,
+    pub characters: usize,
+    pub estimated_tokens: usize,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SearchRequest {
     pub query: String,
@@ -99,6 +197,23 @@ pub struct SearchRequest {
     pub limit: usize,
     #[serde(default = "default_min_score")]
     pub min_score: f32,
+    /// Only search chunks whose metadata contains this object,
+    /// e.g. `{"source": "faq", "language": "en"}`
+    #[serde(default)]
+    pub metadata_filter: Option<serde_json::Value>,
+    /// Metadata keys to return with each result; empty returns all of them
+    #[serde(default)]
+    pub retrieve_metadata_fields: Vec<String>,
+}
+
+/// Search parameters of `GET /{id}/search`, so searches can be linked to.
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(default = "default_min_score")]
+    pub min_score: f32,
 }
 
 fn default_limit() -> usize {
@@ -108,12 +223,12 @@ fn default_min_score() -> f32 {
     0.7
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SearchResponse {
     pub results: Vec<SearchResult>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SearchResult {
     pub content: String,
     pub score: f32,
@@ -133,6 +248,49 @@ fn default_page_limit() -> usize {
     50
 }
 
+/// Part of the graph `GET /{id}/graph` returns: the nodes within `depth`
+/// edges of `node_id`, or the whole graph without one.
+#[derive(Debug, Deserialize)]
+pub struct GraphQuery {
+    #[serde(default)]
+    pub node_id: Option<String>,
+    #[serde(default = "default_graph_depth")]
+    pub depth: usize,
+}
+
+fn default_graph_depth() -> usize {
+    1
+}
+
+/// A knowledge graph in the shape of D3's force layout: `links` refer to
+/// `nodes` by ID.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphResponse {
+    pub nodes: Vec<GraphNode>,
+    pub links: Vec<GraphLink>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphLink {
+    pub id: String,
+    pub source: String,
+    pub target: String,
+    pub relationship_type: String,
+    pub weight: f32,
+}
+
+impl From<GraphEdge> for GraphLink {
+    fn from(edge: GraphEdge) -> Self {
+        Self {
+            id: edge.id,
+            source: edge.source_id,
+            target: edge.target_id,
+            relationship_type: edge.relationship_type,
+            weight: edge.weight,
+        }
+    }
+}
+
 // =============================================================================
 // Router Builder
 // =============================================================================
@@ -148,14 +306,33 @@ pub fn build_router() -> Router<Arc<KnowledgeApiState>> {
                 .put(update_knowledge_base)
                 .delete(delete_knowledge_base),
         )
+        // Archives
+        .route("/{id}/export", get(export_knowledge_base))
+        .route(
+            "/import",
+            post(import_knowledge_base.layer(DefaultBodyLimit::disable())),
+        )
         // Documents
-        .route("/{id}/documents", get(list_documents).post(upload_document))
+        // Uploads stream to disk, so the handler enforces its own size limit
+        .route(
+            "/{id}/documents",
+            get(list_documents).post(upload_document.layer(DefaultBodyLimit::disable())),
+        )
+        .route("/{id}/documents/preview", post(preview_document))
         .route(
             "/{id}/documents/{doc_id}",
             get(get_document).delete(delete_document),
         )
+        .route("/{id}/documents/{doc_id}/events", get(document_events))
+        .route("/{id}/reindex", post(reindex_knowledge_base))
+        .route("/{id}/reindex/{job_id}", get(get_reindex_job))
+        // Knowledge graph
+        .route("/{id}/graph", get(get_knowledge_graph))
         // Search
-        .route("/{id}/search", post(search_knowledge_base))
+        .route(
+            "/{id}/search",
+            get(search_knowledge_base_link).post(search_knowledge_base),
+        )
 }
 
 // =============================================================================
@@ -165,12 +342,13 @@ pub fn build_router() -> Router<Arc<KnowledgeApiState>> {
 /// GET / - List all knowledge bases
 async fn list_knowledge_bases(
     State(state): State<Arc<KnowledgeApiState>>,
+    tenant: Option<Extension<TenantContext>>,
 ) -> Result<Json<Vec<KnowledgeBaseResponse>>, (StatusCode, String)> {
     let kbs = state
         .persistence
-        .list_knowledge_bases()
+        .list_knowledge_bases(tenant_scope(tenant.as_deref()))
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(persistence_error)?;
 
     let responses: Vec<KnowledgeBaseResponse> = kbs.into_iter().map(kb_to_response).collect();
     Ok(Json(responses))
@@ -179,28 +357,22 @@ async fn list_knowledge_bases(
 /// POST / - Create a new knowledge base
 async fn create_knowledge_base(
     State(state): State<Arc<KnowledgeApiState>>,
+    user: Option<Extension<UserContext>>,
+    tenant: Option<Extension<TenantContext>>,
     Json(req): Json<CreateKnowledgeBaseRequest>,
 ) -> Result<(StatusCode, Json<KnowledgeBaseResponse>), (StatusCode, String)> {
-    // Check if name already exists
-    if let Ok(Some(_)) = state
-        .persistence
-        .get_knowledge_base_by_name(&req.name)
-        .await
-    {
-        return Err((
-            StatusCode::CONFLICT,
-            format!("Knowledge base with name '{}' already exists", req.name),
-        ));
-    }
-
     let now = chrono::Utc::now().to_rfc3339();
     let config = build_kb_config(req.config);
+    validate_kb_dimensions(&config).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    validate_extraction_strategy(&config)?;
+    validate_reranker(&config)?;
 
     let kb = KnowledgeBase {
         id: uuid::Uuid::new_v4().to_string(),
         name: req.name,
         description: req.description,
         config,
+        tenant_id: tenant_scope(tenant.as_deref()).map(str::to_string),
         created_at: now.clone(),
         updated_at: now,
     };
@@ -209,22 +381,34 @@ async fn create_knowledge_base(
         .persistence
         .save_knowledge_base(&kb)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(persistence_error)?;
 
     tracing::info!("Created knowledge base: {} ({})", kb.name, kb.id);
+    state
+        .audit(
+            AuditEntry::new(
+                actor(user.as_deref()),
+                AuditAction::Create,
+                AuditTarget::KnowledgeBase,
+                &kb.id,
+            )
+            .with_tenant(tenant_scope(tenant.as_deref())),
+        )
+        .await;
     Ok((StatusCode::CREATED, Json(kb_to_response(kb))))
 }
 
 /// GET /{id} - Get a knowledge base by ID
 async fn get_knowledge_base(
     State(state): State<Arc<KnowledgeApiState>>,
+    tenant: Option<Extension<TenantContext>>,
     Path(id): Path<String>,
 ) -> Result<Json<KnowledgeBaseResponse>, (StatusCode, String)> {
     let kb = state
         .persistence
-        .get_knowledge_base(&id)
+        .get_knowledge_base(&id, tenant_scope(tenant.as_deref()))
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(persistence_error)?
         .ok_or((
             StatusCode::NOT_FOUND,
             format!("Knowledge base '{}' not found", id),
@@ -237,29 +421,26 @@ async fn get_knowledge_base(
 async fn update_knowledge_base(
     State(state): State<Arc<KnowledgeApiState>>,
     Path(id): Path<String>,
+    user: Option<Extension<UserContext>>,
+    tenant: Option<Extension<TenantContext>>,
     Json(req): Json<UpdateKnowledgeBaseRequest>,
 ) -> Result<Json<KnowledgeBaseResponse>, (StatusCode, String)> {
+    // Shared knowledge bases are visible to tenants, but not theirs to edit
+    let tenant_id = tenant_scope(tenant.as_deref());
     let mut kb = state
         .persistence
-        .get_knowledge_base(&id)
+        .get_knowledge_base(&id, tenant_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(persistence_error)?
+        .filter(|kb| owned_by(kb.tenant_id.as_deref(), tenant_id))
         .ok_or((
             StatusCode::NOT_FOUND,
             format!("Knowledge base '{}' not found", id),
         ))?;
 
     // Apply updates
+    // Name clashes surface as DuplicateName from the save
     if let Some(name) = req.name {
-        // Check uniqueness
-        if let Ok(Some(existing)) = state.persistence.get_knowledge_base_by_name(&name).await {
-            if existing.id != kb.id {
-                return Err((
-                    StatusCode::CONFLICT,
-                    format!("Knowledge base with name '{}' already exists", name),
-                ));
-            }
-        }
         kb.name = name;
     }
     if let Some(desc) = req.description {
@@ -267,6 +448,10 @@ async fn update_knowledge_base(
     }
     if let Some(cfg_req) = req.config {
         kb.config = merge_kb_config(kb.config, cfg_req);
+        validate_kb_dimensions(&kb.config)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        validate_extraction_strategy(&kb.config)?;
+        validate_reranker(&kb.config)?;
     }
     kb.updated_at = chrono::Utc::now().to_rfc3339();
 
@@ -274,8 +459,19 @@ async fn update_knowledge_base(
         .persistence
         .save_knowledge_base(&kb)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(persistence_error)?;
 
+    state
+        .audit(
+            AuditEntry::new(
+                actor(user.as_deref()),
+                AuditAction::Update,
+                AuditTarget::KnowledgeBase,
+                &kb.id,
+            )
+            .with_tenant(tenant_scope(tenant.as_deref())),
+        )
+        .await;
     Ok(Json(kb_to_response(kb)))
 }
 
@@ -283,26 +479,197 @@ async fn update_knowledge_base(
 async fn delete_knowledge_base(
     State(state): State<Arc<KnowledgeApiState>>,
     Path(id): Path<String>,
+    user: Option<Extension<UserContext>>,
+    tenant: Option<Extension<TenantContext>>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    // Check exists
-    let _ = state
+    state
+        .persistence
+        .delete_knowledge_base(&id, tenant_scope(tenant.as_deref()))
+        .await
+        .map_err(persistence_error)?;
+    state.vector_matcher.invalidate_chunk_index(Some(&id));
+
+    tracing::info!("Deleted knowledge base: {}", id);
+    state
+        .audit(
+            AuditEntry::new(
+                actor(user.as_deref()),
+                AuditAction::Delete,
+                AuditTarget::KnowledgeBase,
+                &id,
+            )
+            .with_tenant(tenant_scope(tenant.as_deref())),
+        )
+        .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /{id}/export - Download a knowledge base as a ZIP archive
+///
+/// The archive (see [`IngestService::export_kb`]) is built in `upload_dir`
+/// and streamed from there; it is removed once the response is done.
+async fn export_knowledge_base(
+    State(state): State<Arc<KnowledgeApiState>>,
+    tenant: Option<Extension<TenantContext>>,
+    Path(id): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let ingest = archive_service(&state)?;
+    let tenant_id = tenant_scope(tenant.as_deref());
+    let kb = state
         .persistence
-        .get_knowledge_base(&id)
+        .get_knowledge_base(&id, tenant_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(persistence_error)?
         .ok_or((
             StatusCode::NOT_FOUND,
             format!("Knowledge base '{}' not found", id),
         ))?;
 
+    let dir = ExportDir(state.upload_dir.join(format!("export-{}", uuid::Uuid::new_v4())));
+    let export_error = |e: anyhow::Error| {
+        tracing::error!(kb_id = %id, error = %e, "Failed to export knowledge base");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to export knowledge base".to_string(),
+        )
+    };
+    let path = ingest
+        .export_kb(&kb.id, &dir.0, tenant_id)
+        .await
+        .map_err(export_error)?;
+    let file = tokio::fs::File::open(&path)
+        .await
+        .map_err(|e| export_error(e.into()))?;
+    // The closure owns the directory, so it goes when the body is dropped
+    let body = ReaderStream::new(file).map(move |bytes| {
+        let _dir = &dir;
+        bytes
+    });
+
+    let disposition = format!("attachment; filename=\"{}\"", archive_filename(&kb.name));
+    Ok((
+        [
+            (header::CONTENT_TYPE, ARCHIVE_MIME_TYPE.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// POST /import - Create a knowledge base from an exported ZIP archive
+///
+/// The archive (multipart field `file`) is streamed to `upload_dir` under
+/// the upload size limit, then imported with
+/// [`IngestService::import_kb`] into a new knowledge base of the caller's
+/// tenant.
+async fn import_knowledge_base(
+    State(state): State<Arc<KnowledgeApiState>>,
+    user: Option<Extension<UserContext>>,
+    tenant: Option<Extension<TenantContext>>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<KnowledgeBaseResponse>), (StatusCode, String)> {
+    let ingest = archive_service(&state)?;
+    let upload_id = uuid::Uuid::new_v4().to_string();
+    let allowed = [ARCHIVE_MIME_TYPE.to_string()];
+    let SavedFile { path, .. } =
+        save_file_field(&mut multipart, &state, &upload_id, &allowed).await?;
+
+    let imported = ingest
+        .import_kb(&path, None, tenant_scope(tenant.as_deref()))
+        .await;
+    remove_upload(&path).await;
+    let kb = imported.map_err(|e| match e.downcast::<PersistenceError>() {
+        Ok(e) => persistence_error(e),
+        Err(e) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Failed to import knowledge base: {e:#}"),
+        ),
+    })?;
+
+    tracing::info!("Imported knowledge base: {} ({})", kb.name, kb.id);
     state
+        .audit(
+            AuditEntry::new(
+                actor(user.as_deref()),
+                AuditAction::Create,
+                AuditTarget::KnowledgeBase,
+                &kb.id,
+            )
+            .with_tenant(tenant_scope(tenant.as_deref())),
+        )
+        .await;
+    Ok((StatusCode::CREATED, Json(kb_to_response(kb))))
+}
+
+/// POST /{id}/reindex - Index changed and failed documents again
+///
+/// Runs in the background; poll the returned job at
+/// `GET /{id}/reindex/{job_id}` for its report. While a reindex of the
+/// knowledge base runs, that job is returned instead of starting another.
+async fn reindex_knowledge_base(
+    State(state): State<Arc<KnowledgeApiState>>,
+    Path(id): Path<String>,
+    user: Option<Extension<UserContext>>,
+    tenant: Option<Extension<TenantContext>>,
+) -> Result<(StatusCode, Json<ReindexJob>), (StatusCode, String)> {
+    let ingest = state.ingest_service.as_ref().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Reindexing is not available".to_string(),
+    ))?;
+    let tenant_id = tenant_scope(tenant.as_deref());
+    let kb = state
         .persistence
-        .delete_knowledge_base(&id)
+        .get_knowledge_base(&id, tenant_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(persistence_error)?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!("Knowledge base '{}' not found", id),
+        ))?;
 
-    tracing::info!("Deleted knowledge base: {}", id);
-    Ok(StatusCode::NO_CONTENT)
+    let job = ingest.start_reindex(&kb.id, tenant_id);
+    tracing::info!(kb_id = %kb.id, job_id = %job.id, "Reindex started");
+    state
+        .audit(
+            AuditEntry::new(
+                actor(user.as_deref()),
+                AuditAction::Reindex,
+                AuditTarget::KnowledgeBase,
+                &kb.id,
+            )
+            .with_tenant(tenant_scope(tenant.as_deref())),
+        )
+        .await;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// GET /{id}/reindex/{job_id} - Status of a reindex, with its report once
+/// it completed
+async fn get_reindex_job(
+    State(state): State<Arc<KnowledgeApiState>>,
+    Path((id, job_id)): Path<(String, String)>,
+    tenant: Option<Extension<TenantContext>>,
+) -> Result<Json<ReindexJob>, (StatusCode, String)> {
+    let not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Reindex job '{}' not found", job_id),
+        )
+    };
+    let ingest = state.ingest_service.as_ref().ok_or_else(not_found)?;
+    // Jobs of knowledge bases the caller can't see don't exist for them
+    state
+        .persistence
+        .get_knowledge_base(&id, tenant_scope(tenant.as_deref()))
+        .await
+        .map_err(persistence_error)?
+        .ok_or_else(not_found)?;
+    ingest
+        .reindex_job(&job_id)
+        .filter(|job| job.kb_id == id)
+        .map(Json)
+        .ok_or_else(not_found)
 }
 
 // =============================================================================
@@ -312,15 +679,17 @@ async fn delete_knowledge_base(
 /// GET /{id}/documents - List documents in a knowledge base
 async fn list_documents(
     State(state): State<Arc<KnowledgeApiState>>,
+    tenant: Option<Extension<TenantContext>>,
     Path(kb_id): Path<String>,
     Query(_query): Query<ListQuery>,
 ) -> Result<Json<Vec<DocumentResponse>>, (StatusCode, String)> {
+    let tenant_id = tenant_scope(tenant.as_deref());
     // Verify KB exists
     let _ = state
         .persistence
-        .get_knowledge_base(&kb_id)
+        .get_knowledge_base(&kb_id, tenant_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(persistence_error)?
         .ok_or((
             StatusCode::NOT_FOUND,
             format!("Knowledge base '{}' not found", kb_id),
@@ -328,82 +697,136 @@ async fn list_documents(
 
     let docs = state
         .persistence
-        .list_documents(&kb_id)
+        .list_documents(&kb_id, tenant_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(persistence_error)?;
 
     let responses: Vec<DocumentResponse> = docs.into_iter().map(doc_to_response).collect();
     Ok(Json(responses))
 }
 
+/// Seconds clients are asked to wait before retrying an upload the full
+/// ingestion queue rejected
+const QUEUE_FULL_RETRY_AFTER_SECS: u64 = 10;
+
+/// Longest accepted idempotency key, in bytes
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
 /// POST /{id}/documents - Upload a document (multipart form)
+///
+/// The file is streamed to `upload_dir` as it arrives and the worker reads
+/// it from there. Fails with 413 above `max_upload_size`, with 415 when the
+/// type sniffed from its content isn't allowed, and with 503 and
+/// `Retry-After` while the ingestion queue is full.
+///
+/// An `Idempotency-Key` header (or an `idempotency_key` field before the
+/// file) makes the upload safe to retry: when the knowledge base already has
+/// a document uploaded with the key, that document is returned with 200 and
+/// nothing new is stored. Keys are honored for as long as their document
+/// exists; deleting it frees the key.
 async fn upload_document(
     State(state): State<Arc<KnowledgeApiState>>,
     Path(kb_id): Path<String>,
+    user: Option<Extension<UserContext>>,
+    tenant: Option<Extension<TenantContext>>,
+    headers: HeaderMap,
     mut multipart: Multipart,
-) -> Result<(StatusCode, Json<DocumentResponse>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<DocumentResponse>), Response> {
+    let header_key = headers
+        .get("idempotency-key")
+        .map(|value| {
+            let value = value.to_str().map_err(|_| {
+                let message = "Idempotency-Key must be visible ASCII".to_string();
+                (StatusCode::BAD_REQUEST, message).into_response()
+            })?;
+            check_idempotency_key(value).map_err(IntoResponse::into_response)
+        })
+        .transpose()?;
+
     // Verify KB exists
-    let _ = state
+    let tenant_id = tenant_scope(tenant.as_deref());
+    let kb = state
         .persistence
-        .get_knowledge_base(&kb_id)
+        .get_knowledge_base(&kb_id, tenant_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .ok_or((
-            StatusCode::NOT_FOUND,
-            format!("Knowledge base '{}' not found", kb_id),
-        ))?;
-
-    // Read multipart file
-    let mut filename = String::new();
-    let mut mime_type = None;
-    let mut file_data = Vec::new();
+        .map_err(|e| persistence_error(e).into_response())?
+        .ok_or_else(|| {
+            let message = format!("Knowledge base '{}' not found", kb_id);
+            (StatusCode::NOT_FOUND, message).into_response()
+        })?;
 
-    while let Some(field) = multipart
-        .next_field()
-        .await
-        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+    // Answer retries without reading the file again
+    if let Some(key) = &header_key
+        && let Some(existing) = find_upload(&state, &kb_id, key, tenant_id).await?
     {
-        let field_name = field.name().unwrap_or_default().to_string();
-        if field_name == "file" {
-            filename = field.file_name().unwrap_or("uploaded_file").to_string();
-            mime_type = field.content_type().map(|s| s.to_string());
-            file_data = field
-                .bytes()
-                .await
-                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
-                .to_vec();
-        }
+        return Ok((StatusCode::OK, Json(doc_to_response(existing))));
     }
 
-    if filename.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "No file field in multipart form".to_string(),
-        ));
+    let doc_id = uuid::Uuid::new_v4().to_string();
+    let SavedFile {
+        filename,
+        mime_type,
+        path,
+        idempotency_key: field_key,
+    } = save_file_field(&mut multipart, &state, &doc_id, &state.allowed_mime_types)
+        .await
+        .map_err(IntoResponse::into_response)?;
+    let idempotency_key = match header_key {
+        Some(key) => Some(key),
+        None => match field_key.as_deref().map(check_idempotency_key).transpose() {
+            Ok(key) => key,
+            Err(e) => {
+                remove_upload(&path).await;
+                return Err(e.into_response());
+            }
+        },
+    };
+    if let Some(key) = &idempotency_key {
+        match find_upload(&state, &kb_id, key, tenant_id).await {
+            Ok(None) => {}
+            Ok(Some(existing)) => {
+                remove_upload(&path).await;
+                return Ok((StatusCode::OK, Json(doc_to_response(existing))));
+            }
+            Err(e) => {
+                remove_upload(&path).await;
+                return Err(e);
+            }
+        }
     }
 
     let now = chrono::Utc::now().to_rfc3339();
     let doc = KnowledgeDocument {
-        id: uuid::Uuid::new_v4().to_string(),
+        id: doc_id,
         kb_id: kb_id.clone(),
         filename: filename.clone(),
-        file_path: None, // Would be set after saving to storage
-        mime_type,
+        file_path: Some(path.to_string_lossy().into_owned()),
+        mime_type: Some(mime_type),
         chunk_count: 0,
         status: DocumentStatus::Pending,
+        // Documents and their chunks live in the knowledge base's namespace
+        tenant_id: kb.tenant_id,
+        metadata: None,
+        idempotency_key,
+        content_hash: None,
         created_at: now.clone(),
         updated_at: now,
     };
 
-    state
-        .persistence
-        .save_document(&doc)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if let Err(e) = state.persistence.save_document(&doc).await {
+        remove_upload(&path).await;
+        // A concurrent upload with the same key got there first
+        if let (PersistenceError::DuplicateName(_), Some(key)) = (&e, &doc.idempotency_key)
+            && let Some(existing) = find_upload(&state, &kb_id, key, tenant_id).await?
+        {
+            return Ok((StatusCode::OK, Json(doc_to_response(existing))));
+        }
+        return Err(persistence_error(e).into_response());
+    }
 
     // Submit to worker pool for async processing
     if let Some(pool) = &state.ingestion_pool {
-        match pool.submit(doc.clone(), file_data).await {
+        match pool.submit(doc.clone(), path.clone()).await {
             Ok(job_key) => {
                 tracing::info!(
                     document_id = %doc.id,
@@ -411,6 +834,24 @@ async fn upload_document(
                     "Document submitted to ingestion queue"
                 );
             }
+            Err(e @ SubmitError::QueueFull { .. }) => {
+                // The client uploads again later; don't leave a pending copy
+                if let Err(e) = state
+                    .persistence
+                    .delete_document(&doc.id, doc.tenant_id.as_deref())
+                    .await
+                {
+                    tracing::warn!(
+                        document_id = %doc.id,
+                        error = %e,
+                        "Failed to drop rejected document"
+                    );
+                }
+                remove_upload(&path).await;
+                let retry_after = [(header::RETRY_AFTER, QUEUE_FULL_RETRY_AFTER_SECS.to_string())];
+                let message = format!("{e}; retry later");
+                return Err((StatusCode::SERVICE_UNAVAILABLE, retry_after, message).into_response());
+            }
             Err(e) => {
                 tracing::error!(
                     document_id = %doc.id,
@@ -428,19 +869,128 @@ async fn upload_document(
     }
 
     tracing::info!("Document uploaded: {} -> KB {}", doc.id, kb_id);
+    state
+        .audit(
+            AuditEntry::new(
+                actor(user.as_deref()),
+                AuditAction::Upload,
+                AuditTarget::Document,
+                &doc.id,
+            )
+            .with_kb_id(&kb_id)
+            .with_tenant(tenant_id),
+        )
+        .await;
     Ok((StatusCode::ACCEPTED, Json(doc_to_response(doc))))
 }
 
+/// `key` if it is usable as an idempotency key.
+fn check_idempotency_key(key: &str) -> Result<String, (StatusCode, String)> {
+    let key = key.trim();
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Idempotency key must be 1 to {MAX_IDEMPOTENCY_KEY_LEN} bytes"),
+        ));
+    }
+    Ok(key.to_string())
+}
+
+/// The document of `kb_id` uploaded with idempotency key `key`, if any.
+async fn find_upload(
+    state: &KnowledgeApiState,
+    kb_id: &str,
+    key: &str,
+    tenant_id: Option<&str>,
+) -> Result<Option<KnowledgeDocument>, Response> {
+    state
+        .persistence
+        .find_document_by_idempotency_key(kb_id, key, tenant_id)
+        .await
+        .map_err(|e| persistence_error(e).into_response())
+}
+
+/// POST /{id}/documents/preview - Extract and chunk a file without storing it
+///
+/// Nothing is persisted or embedded; the response shows how the document
+/// would be split, so chunk settings can be tuned before uploading.
+async fn preview_document(
+    State(state): State<Arc<KnowledgeApiState>>,
+    tenant: Option<Extension<TenantContext>>,
+    Path(kb_id): Path<String>,
+    Query(query): Query<PreviewQuery>,
+    mut multipart: Multipart,
+) -> Result<Json<PreviewResponse>, (StatusCode, String)> {
+    let kb = state
+        .persistence
+        .get_knowledge_base(&kb_id, tenant_scope(tenant.as_deref()))
+        .await
+        .map_err(persistence_error)?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!("Knowledge base '{}' not found", kb_id),
+        ))?;
+
+    let strategy = if query.chunk_strategy.is_some() || query.chunk_size.is_some() {
+        parse_chunk_strategy(query.chunk_strategy.as_deref(), query.chunk_size)
+    } else {
+        kb.config.chunk_strategy
+    };
+    // Semantic chunking embeds every sentence, which a preview must not do
+    if matches!(strategy, ChunkingStrategy::Semantic { .. }) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Semantic chunking needs embeddings and cannot be previewed".to_string(),
+        ));
+    }
+
+    let UploadedFile {
+        filename,
+        mime_type,
+        data,
+    } = read_file_field(&mut multipart).await?;
+    let (text, _) =
+        tokio::task::spawn_blocking(move || extract_document(&data, mime_type.as_deref()))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+    let chunks = Chunker::new(strategy.clone(), None)
+        .chunk(&text)
+        .await
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    let chunks: Vec<ChunkPreview> = chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, content)| ChunkPreview {
+            index,
+            characters: content.chars().count(),
+            estimated_tokens: TokenService::estimate_string(&content),
+            content,
+        })
+        .collect();
+
+    Ok(Json(PreviewResponse {
+        filename,
+        chunk_strategy: format!("{:?}", strategy),
+        chunk_count: chunks.len(),
+        total_characters: chunks.iter().map(|c| c.characters).sum(),
+        estimated_tokens: chunks.iter().map(|c| c.estimated_tokens).sum(),
+        chunks,
+    }))
+}
+
 /// GET /{id}/documents/{doc_id} - Get document status
 async fn get_document(
     State(state): State<Arc<KnowledgeApiState>>,
+    tenant: Option<Extension<TenantContext>>,
     Path((kb_id, doc_id)): Path<(String, String)>,
 ) -> Result<Json<DocumentResponse>, (StatusCode, String)> {
     let doc = state
         .persistence
-        .get_document(&doc_id)
+        .get_document(&doc_id, tenant_scope(tenant.as_deref()))
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(persistence_error)?
         .ok_or((
             StatusCode::NOT_FOUND,
             format!("Document '{}' not found", doc_id),
@@ -457,17 +1007,99 @@ async fn get_document(
     Ok(Json(doc_to_response(doc)))
 }
 
+/// GET /{id}/documents/{doc_id}/events - Stream ingestion progress (SSE)
+///
+/// Each event is named after its stage (`extracting`, `chunked`,
+/// `embedded`, `indexed`, `failed`); the stream ends after `indexed` or
+/// `failed`. A document that is already done gets just its final event.
+async fn document_events(
+    State(state): State<Arc<KnowledgeApiState>>,
+    tenant: Option<Extension<TenantContext>>,
+    Path((kb_id, doc_id)): Path<(String, String)>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    // Subscribed before reading the status, so a job finishing in between
+    // shows up in one or the other
+    let receiver = state
+        .ingestion_pool
+        .as_ref()
+        .map(|pool| pool.subscribe(&doc_id));
+
+    let doc = state
+        .persistence
+        .get_document(&doc_id, tenant_scope(tenant.as_deref()))
+        .await
+        .map_err(persistence_error)?
+        .filter(|doc| doc.kb_id == kb_id);
+    let finished = doc
+        .as_ref()
+        .map(|doc| IngestionProgress::finished(&doc.status, doc.chunk_count));
+    if !matches!(finished, Some(None))
+        && let Some(pool) = &state.ingestion_pool
+    {
+        // Nothing will be published for the subscription
+        pool.release(&doc_id);
+    }
+
+    let progress = match (finished, receiver) {
+        (None, _) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("Document '{}' not found in KB '{}'", doc_id, kb_id),
+            ));
+        }
+        (Some(Some(done)), _) => futures::stream::once(async { done }).boxed(),
+        (Some(None), Some(receiver)) => progress_until_done(receiver).boxed(),
+        (Some(None), None) => {
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "No ingestion pool is running to process the document".to_string(),
+            ));
+        }
+    };
+
+    let events = progress.map(|progress| {
+        let event = Event::default().event(progress.stage());
+        Ok::<_, Infallible>(event.json_data(&progress).unwrap_or_default())
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Progress from `receiver` up to and including the final event.
+fn progress_until_done(
+    mut receiver: broadcast::Receiver<IngestionProgress>,
+) -> impl Stream<Item = IngestionProgress> + Send {
+    async_stream::stream! {
+        loop {
+            match receiver.recv().await {
+                Ok(progress) => {
+                    let done = progress.is_final();
+                    yield progress;
+                    if done {
+                        break;
+                    }
+                }
+                // Missed events are superseded by later ones
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
 /// DELETE /{id}/documents/{doc_id} - Delete a document
 async fn delete_document(
     State(state): State<Arc<KnowledgeApiState>>,
     Path((kb_id, doc_id)): Path<(String, String)>,
+    user: Option<Extension<UserContext>>,
+    tenant: Option<Extension<TenantContext>>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    let tenant_id = tenant_scope(tenant.as_deref());
     // Verify document exists and belongs to KB
     let doc = state
         .persistence
-        .get_document(&doc_id)
+        .get_document(&doc_id, tenant_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(persistence_error)?
         .ok_or((
             StatusCode::NOT_FOUND,
             format!("Document '{}' not found", doc_id),
@@ -483,14 +1115,70 @@ async fn delete_document(
     // Delete document and its chunks
     state
         .persistence
-        .delete_document(&doc_id)
+        .delete_document(&doc_id, tenant_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(persistence_error)?;
+    state.vector_matcher.invalidate_chunk_index(Some(&kb_id));
+    if let Some(path) = &doc.file_path {
+        remove_upload(std::path::Path::new(path)).await;
+    }
 
     tracing::info!("Deleted document: {} from KB {}", doc_id, kb_id);
+    state
+        .audit(
+            AuditEntry::new(
+                actor(user.as_deref()),
+                AuditAction::Delete,
+                AuditTarget::Document,
+                &doc_id,
+            )
+            .with_kb_id(&kb_id)
+            .with_tenant(tenant_id),
+        )
+        .await;
     Ok(StatusCode::NO_CONTENT)
 }
 
+// =============================================================================
+// Graph Handler
+// =============================================================================
+
+/// GET /{id}/graph?node_id=...&depth=... - The knowledge graph built from
+/// the entities of a knowledge base's documents, or a node's neighbourhood
+async fn get_knowledge_graph(
+    State(state): State<Arc<KnowledgeApiState>>,
+    Path(id): Path<String>,
+    tenant: Option<Extension<TenantContext>>,
+    Query(query): Query<GraphQuery>,
+) -> Result<Json<GraphResponse>, (StatusCode, String)> {
+    if query.depth > MAX_GRAPH_DEPTH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("depth must be at most {MAX_GRAPH_DEPTH}"),
+        ));
+    }
+    let kb = state
+        .persistence
+        .get_knowledge_base(&id, tenant_scope(tenant.as_deref()))
+        .await
+        .map_err(persistence_error)?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!("Knowledge base '{}' not found", id),
+        ))?;
+
+    let node_ids: Vec<&str> = query.node_id.as_deref().into_iter().collect();
+    let graph = state
+        .persistence
+        .get_subgraph(&kb.id, &node_ids, query.depth)
+        .await
+        .map_err(persistence_error)?;
+    Ok(Json(GraphResponse {
+        nodes: graph.nodes,
+        links: graph.edges.into_iter().map(GraphLink::from).collect(),
+    }))
+}
+
 // =============================================================================
 // Search Handler
 // =============================================================================
@@ -498,20 +1186,76 @@ async fn delete_document(
 /// POST /{id}/search - Vector search within a knowledge base
 async fn search_knowledge_base(
     State(state): State<Arc<KnowledgeApiState>>,
+    tenant: Option<Extension<TenantContext>>,
     Path(kb_id): Path<String>,
     Json(req): Json<SearchRequest>,
 ) -> Result<Json<SearchResponse>, (StatusCode, String)> {
+    let tenant_id = tenant_scope(tenant.as_deref());
+    search_knowledge_base_core(&state, &kb_id, &req, tenant_id)
+        .await
+        .map(Json)
+}
+
+/// GET /{id}/search?q=...&limit=...&min_score=... - Search by URL, for
+/// bookmarked and shared searches
+///
+/// Responses are cached per tenant and parameters in `search_cache`, so they
+/// can be up to its TTL behind the knowledge base.
+async fn search_knowledge_base_link(
+    State(state): State<Arc<KnowledgeApiState>>,
+    tenant: Option<Extension<TenantContext>>,
+    Path(kb_id): Path<String>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, (StatusCode, String)> {
+    let tenant_id = tenant_scope(tenant.as_deref());
+    let key = SearchKey::new(tenant_id, &kb_id, &query.q, query.limit, query.min_score);
+    if let Some(cached) = state.search_cache.as_ref().and_then(|cache| cache.get(&key)) {
+        return Ok(Json(cached));
+    }
+
+    let req = SearchRequest {
+        query: query.q,
+        limit: query.limit,
+        min_score: query.min_score,
+        metadata_filter: None,
+        retrieve_metadata_fields: Vec::new(),
+    };
+    let response = search_knowledge_base_core(&state, &kb_id, &req, tenant_id).await?;
+    if let Some(cache) = &state.search_cache {
+        cache.insert(key, response.clone());
+    }
+    Ok(Json(response))
+}
+
+/// Search shared by the POST and GET handlers.
+async fn search_knowledge_base_core(
+    state: &KnowledgeApiState,
+    kb_id: &str,
+    req: &SearchRequest,
+    tenant_id: Option<&str>,
+) -> Result<SearchResponse, (StatusCode, String)> {
     // Verify KB exists
     let kb = state
         .persistence
-        .get_knowledge_base(&kb_id)
+        .get_knowledge_base(kb_id, tenant_id)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(persistence_error)?
         .ok_or((
             StatusCode::NOT_FOUND,
             format!("Knowledge base '{}' not found", kb_id),
         ))?;
 
+    if req
+        .metadata_filter
+        .as_ref()
+        .is_some_and(|filter| !filter.is_object())
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "metadata_filter must be a JSON object".to_string(),
+        ));
+    }
+
     tracing::debug!(
         "Search in KB '{}': query='{}', limit={}",
         kb.name,
@@ -519,10 +1263,10 @@ async fn search_knowledge_base(
         req.limit
     );
 
-    // Embed the query using VectorMatcher
+    // Embed the query with the KB's embedding provider
     let embeddings = state
         .vector_matcher
-        .embed_batch(vec![req.query.clone()])
+        .embed_for_kb(&kb.config, vec![req.query.clone()])
         .await
         .map_err(|e| {
             tracing::error!("Failed to embed query: {}", e);
@@ -537,12 +1281,21 @@ async fn search_knowledge_base(
         "No embedding generated".to_string(),
     ))?;
 
-    // Search knowledge scoped to this KB
-    let matches = state
-        .persistence
-        .search_knowledge_scoped(&[kb_id.as_str()], &query_vec, req.limit, req.min_score)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    // Search knowledge scoped to this KB, reranking when configured
+    let matches = rerank::search_knowledge_base(
+        state.persistence.as_ref(),
+        &state.vector_matcher,
+        &state.rerankers,
+        &kb,
+        &req.query,
+        &query_vec,
+        req.limit,
+        req.min_score,
+        req.metadata_filter.as_ref(),
+        tenant_id,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Transform to response
     let results = matches
@@ -550,18 +1303,305 @@ async fn search_knowledge_base(
         .map(|m| SearchResult {
             content: m.chunk.content,
             score: m.score,
-            metadata: m.chunk.metadata.unwrap_or_else(|| serde_json::json!({})),
+            metadata: select_metadata_fields(m.chunk.metadata, &req.retrieve_metadata_fields),
             document_id: m.chunk.document_id,
         })
         .collect();
 
-    Ok(Json(SearchResponse { results }))
+    Ok(SearchResponse { results })
 }
 
 // =============================================================================
 // Helper Functions
 // =============================================================================
 
+/// The `file` field of a multipart upload.
+struct UploadedFile {
+    filename: String,
+    mime_type: Option<String>,
+    data: Vec<u8>,
+}
+
+async fn read_file_field(multipart: &mut Multipart) -> Result<UploadedFile, (StatusCode, String)> {
+    let mut file = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+    {
+        if field.name() == Some("file") {
+            let filename = field.file_name().unwrap_or("uploaded_file").to_string();
+            let mime_type = field.content_type().map(|s| s.to_string());
+            let data = field
+                .bytes()
+                .await
+                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+                .to_vec();
+            file = Some(UploadedFile {
+                filename,
+                mime_type,
+                data,
+            });
+        }
+    }
+
+    file.ok_or((
+        StatusCode::BAD_REQUEST,
+        "No file field in multipart form".to_string(),
+    ))
+}
+
+/// The `file` field of a multipart upload, saved to disk.
+struct SavedFile {
+    filename: String,
+    /// Sniffed from the content, not taken from the client
+    mime_type: String,
+    path: PathBuf,
+    /// `idempotency_key` field sent before the file
+    idempotency_key: Option<String>,
+}
+
+/// Stream the `file` field into the upload directory, named after the
+/// document, without holding more than one chunk in memory.
+///
+/// Fails with 413 once more than `max_upload_size` bytes arrive, and with
+/// 415 as soon as the first bytes show a type not in `allowed_mime_types`.
+async fn save_file_field(
+    multipart: &mut Multipart,
+    state: &KnowledgeApiState,
+    doc_id: &str,
+    allowed_mime_types: &[String],
+) -> Result<SavedFile, (StatusCode, String)> {
+    let dir = state.upload_dir.as_path();
+    let max_size = state.max_upload_size;
+    let io_error = |e: std::io::Error| {
+        tracing::error!(error = %e, "Failed to save upload");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to save upload".to_string(),
+        )
+    };
+
+    let mut idempotency_key = None;
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+    {
+        if field.name() == Some("idempotency_key") {
+            let key = field
+                .text()
+                .await
+                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+            idempotency_key = Some(key);
+            continue;
+        }
+        if field.name() != Some("file") {
+            continue;
+        }
+        let filename = field.file_name().unwrap_or("uploaded_file").to_string();
+        let path = dir.join(upload_file_name(doc_id, &filename));
+        let check_type = |head: &[u8]| {
+            let mime_type = sniff_mime_type(head, &filename);
+            if mime_type_allowed(&mime_type, allowed_mime_types) {
+                Ok(mime_type)
+            } else {
+                Err((
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    format!(
+                        "File type '{mime_type}' is not allowed (allowed: {})",
+                        allowed_mime_types.join(", ")
+                    ),
+                ))
+            }
+        };
+
+        tokio::fs::create_dir_all(dir).await.map_err(io_error)?;
+        let mut file = tokio::fs::File::create(&path).await.map_err(io_error)?;
+        let mut size = 0;
+        let mut head = Vec::with_capacity(MIME_SNIFF_LEN);
+        let mut mime_type = None;
+        let written: Result<String, (StatusCode, String)> = async {
+            while let Some(chunk) = field
+                .chunk()
+                .await
+                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+            {
+                size += chunk.len();
+                if size > max_size {
+                    return Err((
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        format!("File exceeds the {max_size} byte upload limit"),
+                    ));
+                }
+                if mime_type.is_none() {
+                    let wanted = MIME_SNIFF_LEN - head.len();
+                    head.extend_from_slice(&chunk[..chunk.len().min(wanted)]);
+                    if head.len() == MIME_SNIFF_LEN {
+                        mime_type = Some(check_type(&head)?);
+                    }
+                }
+                file.write_all(&chunk).await.map_err(io_error)?;
+            }
+            file.flush().await.map_err(io_error)?;
+            match mime_type.take() {
+                Some(mime_type) => Ok(mime_type),
+                // Smaller than the sniffed prefix
+                None => check_type(&head),
+            }
+        }
+        .await;
+        let mime_type = match written {
+            Ok(mime_type) => mime_type,
+            Err(e) => {
+                drop(file);
+                remove_upload(&path).await;
+                return Err(e);
+            }
+        };
+
+        return Ok(SavedFile {
+            filename,
+            mime_type,
+            path,
+            idempotency_key,
+        });
+    }
+
+    Err((
+        StatusCode::BAD_REQUEST,
+        "No file field in multipart form".to_string(),
+    ))
+}
+
+/// MIME type of an upload from its first bytes; the client's `Content-Type`
+/// isn't trusted.
+///
+/// Formats without a signature are typed by the filename's extension when
+/// the content is text and the extension names a text format, as plain text
+/// otherwise; unrecognized binary content is `application/octet-stream`.
+fn sniff_mime_type(head: &[u8], filename: &str) -> String {
+    if let Some(mime_type) = detect_mime_type(head) {
+        return mime_type;
+    }
+    // The prefix may end inside a multi-byte character
+    let is_text = match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    };
+    if !is_text {
+        return "application/octet-stream".to_string();
+    }
+    mime_guess::from_path(filename)
+        .first()
+        .map(|mime| mime.essence_str().to_string())
+        .filter(|mime| {
+            mime.starts_with("text/")
+                || ["json", "xml", "yaml", "toml"]
+                    .iter()
+                    .any(|format| mime.ends_with(format))
+        })
+        .unwrap_or_else(|| "text/plain".to_string())
+}
+
+/// Whether `mime_type` matches an entry of `allowed`, exactly or by a
+/// `type/*` wildcard. An empty list allows every type.
+fn mime_type_allowed(mime_type: &str, allowed: &[String]) -> bool {
+    allowed.is_empty()
+        || allowed.iter().any(|entry| {
+            entry.eq_ignore_ascii_case(mime_type)
+                || entry.strip_suffix("/*").is_some_and(|top| {
+                    mime_type
+                        .split('/')
+                        .next()
+                        .is_some_and(|t| t.eq_ignore_ascii_case(top))
+                })
+        })
+}
+
+/// Name of a document's file in the upload directory: its ID, plus the
+/// uploaded file's extension when that is plain alphanumerics.
+fn upload_file_name(doc_id: &str, filename: &str) -> String {
+    let ext = std::path::Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| ext.len() <= 16 && ext.chars().all(|c| c.is_ascii_alphanumeric()));
+    match ext {
+        Some(ext) => format!("{doc_id}.{}", ext.to_ascii_lowercase()),
+        None => doc_id.to_string(),
+    }
+}
+
+/// Directory an export is built in, removed when dropped.
+struct ExportDir(PathBuf);
+
+impl Drop for ExportDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::warn!(path = %self.0.display(), error = %e, "Failed to remove export");
+        }
+    }
+}
+
+/// The ingest service archives are made with, or 503 without one.
+fn archive_service(state: &KnowledgeApiState) -> Result<&IngestService, (StatusCode, String)> {
+    state.ingest_service.as_deref().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Knowledge base export and import are not available".to_string(),
+    ))
+}
+
+/// Download name of a knowledge base's archive, safe in a header.
+fn archive_filename(kb_name: &str) -> String {
+    let stem: String = kb_name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect();
+    format!("{stem}.zip")
+}
+
+/// Best-effort removal of an uploaded file.
+async fn remove_upload(path: &std::path::Path) {
+    if let Err(e) = tokio::fs::remove_file(path).await
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        tracing::warn!(path = %path.display(), error = %e, "Failed to remove upload");
+    }
+}
+
+/// `metadata` narrowed to `fields` (all of it when `fields` is empty), as an
+/// object.
+fn select_metadata_fields(
+    metadata: Option<serde_json::Value>,
+    fields: &[String],
+) -> serde_json::Value {
+    match metadata {
+        Some(serde_json::Value::Object(mut object)) if !fields.is_empty() => {
+            object.retain(|key, _| fields.contains(key));
+            serde_json::Value::Object(object)
+        }
+        Some(metadata) if fields.is_empty() => metadata,
+        _ => serde_json::json!({}),
+    }
+}
+
+/// Map a persistence failure to its HTTP status.
+fn persistence_error(e: PersistenceError) -> (StatusCode, String) {
+    let status = match e {
+        PersistenceError::NotFound { .. } => StatusCode::NOT_FOUND,
+        PersistenceError::DuplicateName(_) | PersistenceError::AlreadyExists { .. } => {
+            StatusCode::CONFLICT
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
 fn kb_to_response(kb: KnowledgeBase) -> KnowledgeBaseResponse {
     KnowledgeBaseResponse {
         id: kb.id,
@@ -573,6 +1613,8 @@ fn kb_to_response(kb: KnowledgeBase) -> KnowledgeBaseResponse {
             vector_dimensions: kb.config.vector_dimensions,
             file_processor: kb.config.file_processor,
             chunk_strategy: format!("{:?}", kb.config.chunk_strategy),
+            rerank: kb.config.rerank,
+            extraction_strategy: kb.config.extraction_strategy,
         },
         created_at: kb.created_at,
         updated_at: kb.updated_at,
@@ -596,6 +1638,8 @@ fn doc_to_response(doc: KnowledgeDocument) -> DocumentResponse {
         chunk_count: doc.chunk_count,
         status: status_str,
         error_message: error_msg,
+        metadata: doc.metadata,
+        content_hash: doc.content_hash,
         created_at: doc.created_at,
         updated_at: doc.updated_at,
     }
@@ -615,6 +1659,8 @@ fn build_kb_config(req: Option<KbConfigRequest>) -> KbConfig {
                 .file_processor
                 .unwrap_or_else(KbConfig::default_file_processor),
             chunk_strategy: parse_chunk_strategy(cfg.chunk_strategy.as_deref(), cfg.chunk_size),
+            rerank: cfg.rerank,
+            extraction_strategy: cfg.extraction_strategy,
         },
         None => KbConfig::default(),
     }
@@ -637,9 +1683,35 @@ fn merge_kb_config(mut existing: KbConfig, req: KbConfigRequest) -> KbConfig {
         existing.chunk_strategy =
             parse_chunk_strategy(req.chunk_strategy.as_deref(), req.chunk_size);
     }
+    if req.rerank.is_some() {
+        existing.rerank = req.rerank;
+    }
+    if req.extraction_strategy.is_some() {
+        existing.extraction_strategy = req.extraction_strategy;
+    }
     existing
 }
 
+/// Reject extraction strategies ingestion doesn't know.
+fn validate_reranker(config: &KbConfig) -> Result<(), (StatusCode, String)> {
+    match &config.rerank {
+        Some(rerank) => {
+            rerank::check_config(rerank).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+        }
+        None => Ok(()),
+    }
+}
+
+fn validate_extraction_strategy(config: &KbConfig) -> Result<(), (StatusCode, String)> {
+    match &config.extraction_strategy {
+        Some(name) => name
+            .parse::<ExtractionStrategy>()
+            .map(|_| ())
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string())),
+        None => Ok(()),
+    }
+}
+
 fn parse_chunk_strategy(strategy: Option<&str>, size: Option<usize>) -> ChunkingStrategy {
     let size = size.unwrap_or(512);
     match strategy {
@@ -652,3 +1724,1089 @@ fn parse_chunk_strategy(strategy: Option<&str>, size: Option<usize>) -> Chunking
         _ => ChunkingStrategy::Recursive { size },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::domain::{
+        audit::AuditResource,
+        graph::{Entity, EntityType, ExtractionResult, Relationship},
+        knowledge::KnowledgeChunk,
+    };
+    use crate::uar::file_processing::sample_pdf;
+    use crate::uar::persistence::testing::InMemoryPersistence;
+    use crate::uar::rag::extraction::RelationshipExtractor;
+    use crate::uar::security::{audit::PersistentAuditSink, claims::UserClaims};
+    use crate::uar::testing::StubEmbedder;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn state(db: Arc<InMemoryPersistence>) -> Arc<KnowledgeApiState> {
+        Arc::new(KnowledgeApiState {
+            audit: Some(Arc::new(PersistentAuditSink::new(Arc::clone(&db)))),
+            persistence: db,
+            vector_matcher: Arc::new(VectorMatcher::new(0.5)),
+            ingestion_pool: None,
+            ingest_service: None,
+            rerankers: Arc::new(RerankerRegistry::new()),
+            upload_dir: std::env::temp_dir().join("uar-kb-api-tests"),
+            max_upload_size: 1024 * 1024,
+            allowed_mime_types: Vec::new(),
+            search_cache: None,
+        })
+    }
+
+    async fn send(
+        state: Arc<KnowledgeApiState>,
+        request: axum::http::Request<axum::body::Body>,
+    ) -> StatusCode {
+        build_router()
+            .with_state(state)
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    fn kb(id: &str, name: &str) -> KnowledgeBase {
+        KnowledgeBase {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: None,
+            config: KbConfig::default(),
+            tenant_id: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_persistence_errors_map_to_status_codes() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "docs")).await.unwrap();
+        db.save_knowledge_base(&kb("kb-2", "notes")).await.unwrap();
+        let state = state(db);
+
+        let delete = |id: &str| {
+            axum::http::Request::delete(format!("/{id}"))
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        assert_eq!(send(Arc::clone(&state), delete("kb-1")).await, StatusCode::NO_CONTENT);
+        assert_eq!(send(Arc::clone(&state), delete("kb-1")).await, StatusCode::NOT_FOUND);
+
+        // Taking another KB's name conflicts
+        let json = |request: axum::http::request::Builder, body: &'static str| {
+            request
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let rename = json(axum::http::Request::put("/kb-2"), r#"{"name":"docs"}"#);
+        assert_eq!(send(Arc::clone(&state), rename).await, StatusCode::OK);
+        let create = json(axum::http::Request::post("/"), r#"{"name":"docs"}"#);
+        assert_eq!(send(state, create).await, StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_delete_is_audited_with_actor() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "docs")).await.unwrap();
+        let state = state(Arc::clone(&db));
+
+        // As injected by the auth middleware
+        let user = UserContext {
+            user_id: "alice".to_string(),
+            claims: UserClaims {
+                sub: "alice".to_string(),
+                name: None,
+                roles: None,
+                exp: 0,
+                tenant_id: None,
+            },
+        };
+        let request = axum::http::Request::delete("/kb-1")
+            .extension(user)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        assert_eq!(send(Arc::clone(&state), request).await, StatusCode::NO_CONTENT);
+
+        let entries = db
+            .list_audit_entries(Some(AuditResource::Kb), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].actor, "alice");
+        assert_eq!(entries[0].action, AuditAction::Delete);
+        assert_eq!(entries[0].target_type, AuditTarget::KnowledgeBase);
+        assert_eq!(entries[0].target_id, "kb-1");
+    }
+
+    #[test]
+    fn test_persistence_error_status() {
+        let (status, _) = persistence_error(PersistenceError::not_found("document", "d1"));
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, body) = persistence_error(PersistenceError::DuplicateName("docs".into()));
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body, "name 'docs' is already in use");
+        let (status, _) = persistence_error(PersistenceError::ConnectionError("down".into()));
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_preview_chunks_without_persisting() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "docs")).await.unwrap();
+        let state = state(Arc::clone(&db));
+
+        let body = "--XX\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            First sentence. Second sentence! Third?\r\n\
+            --XX--\r\n";
+        let request = axum::http::Request::post("/kb-1/documents/preview?chunk_strategy=sentence")
+            .header("content-type", "multipart/form-data; boundary=XX")
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        let response = build_router().with_state(state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let preview: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(preview["filename"], "notes.txt");
+        assert_eq!(preview["chunk_count"], 3);
+        assert_eq!(preview["chunks"][1]["content"], "Second sentence!");
+        assert!(preview["estimated_tokens"].as_u64().unwrap() > 0);
+
+        assert!(db.list_documents("kb-1", None).await.unwrap().is_empty());
+        assert_eq!(db.chunk_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_knowledge_bases_are_tenant_isolated() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-shared", "handbook")).await.unwrap();
+        let state = state(Arc::clone(&db));
+
+        // As injected by the tenant middleware
+        let request = |builder: axum::http::request::Builder, tenant: &str, body: &'static str| {
+            builder
+                .extension(TenantContext {
+                    tenant_id: tenant.to_string(),
+                })
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let json = |response: axum::response::Response| async move {
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+        };
+        let router = build_router().with_state(state);
+
+        let create = request(axum::http::Request::post("/"), "acme", r#"{"name":"secrets"}"#);
+        let response = router.clone().oneshot(create).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let id = json(response).await["id"].as_str().unwrap().to_string();
+
+        // Invisible to another tenant, which may reuse the name
+        let get = |tenant| request(axum::http::Request::get(format!("/{id}")), tenant, "");
+        let response = router.clone().oneshot(get("globex")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = router.clone().oneshot(get("acme")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let list = request(axum::http::Request::get("/"), "globex", "");
+        let listed = json(router.clone().oneshot(list).await.unwrap()).await;
+        let names: Vec<&str> = listed
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|kb| kb["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["handbook"]);
+
+        let create = request(axum::http::Request::post("/"), "globex", r#"{"name":"secrets"}"#);
+        let response = router.clone().oneshot(create).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        // Requests without a tenant see shared knowledge bases only
+        assert!(db.get_knowledge_base(&id, None).await.unwrap().is_none());
+        assert_eq!(db.list_knowledge_bases(None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_shared_knowledge_bases_are_not_deleted_by_tenants() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-shared", "handbook")).await.unwrap();
+        let state = state(Arc::clone(&db));
+
+        // Visible to the tenant, but not its to delete
+        let delete = axum::http::Request::delete("/kb-shared")
+            .extension(TenantContext {
+                tenant_id: "acme".to_string(),
+            })
+            .body(axum::body::Body::empty())
+            .unwrap();
+        assert_eq!(send(Arc::clone(&state), delete).await, StatusCode::NOT_FOUND);
+        assert!(db.get_knowledge_base("kb-shared", Some("acme")).await.unwrap().is_some());
+
+        let delete = axum::http::Request::delete("/kb-shared")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        assert_eq!(send(state, delete).await, StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_shared_knowledge_bases_are_not_edited_by_tenants() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-shared", "handbook")).await.unwrap();
+        let state = state(Arc::clone(&db));
+
+        let rename = axum::http::Request::put("/kb-shared")
+            .header(axum::http::header::CONTENT_TYPE, "application/json")
+            .extension(TenantContext {
+                tenant_id: "acme".to_string(),
+            })
+            .body(axum::body::Body::from(r#"{"name":"acme handbook"}"#))
+            .unwrap();
+        assert_eq!(send(state, rename).await, StatusCode::NOT_FOUND);
+        let stored = db.get_knowledge_base("kb-shared", None).await.unwrap().unwrap();
+        assert_eq!(stored.name, "handbook");
+
+        // Nor taken over by saving under the same ID
+        let mut takeover = kb("kb-shared", "mine");
+        takeover.tenant_id = Some("acme".to_string());
+        let err = db.save_knowledge_base(&takeover).await.unwrap_err();
+        assert!(matches!(err, PersistenceError::AlreadyExists { .. }), "{err}");
+        let mut owned = kb("kb-acme", "acme docs");
+        owned.tenant_id = Some("acme".to_string());
+        db.save_knowledge_base(&owned).await.unwrap();
+        owned.tenant_id = Some("globex".to_string());
+        let err = db.save_knowledge_base(&owned).await.unwrap_err();
+        assert!(matches!(err, PersistenceError::AlreadyExists { .. }), "{err}");
+        let stored = db.get_knowledge_base("kb-acme", Some("acme")).await.unwrap();
+        assert_eq!(stored.unwrap().tenant_id.as_deref(), Some("acme"));
+    }
+
+    #[tokio::test]
+    async fn test_finished_document_events() {
+        let db = Arc::new(InMemoryPersistence::new());
+        let doc = KnowledgeDocument {
+            id: "doc-1".to_string(),
+            kb_id: "kb-1".to_string(),
+            filename: "notes.txt".to_string(),
+            file_path: None,
+            mime_type: None,
+            chunk_count: 3,
+            status: DocumentStatus::Indexed,
+            tenant_id: None,
+            metadata: None,
+            idempotency_key: None,
+            content_hash: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        db.save_document(&doc).await.unwrap();
+        let pending = KnowledgeDocument {
+            id: "doc-2".to_string(),
+            status: DocumentStatus::Pending,
+            ..doc
+        };
+        db.save_document(&pending).await.unwrap();
+        let router = build_router().with_state(state(db));
+        let get = |uri: &str| {
+            axum::http::Request::get(uri)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        // Only the final event, then the stream ends
+        let response = router
+            .clone()
+            .oneshot(get("/kb-1/documents/doc-1/events"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(bytes.to_vec()).unwrap();
+        assert_eq!(body.matches("event: ").count(), 1, "{body}");
+        assert!(body.contains("event: indexed"), "{body}");
+        assert!(body.contains(r#"{"stage":"indexed","chunk_count":3}"#), "{body}");
+
+        let response = router
+            .clone()
+            .oneshot(get("/kb-2/documents/doc-1/events"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        // Nothing would ever process the pending document
+        let response = router
+            .oneshot(get("/kb-1/documents/doc-2/events"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_uploaded_pdf_metadata_is_returned() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "docs")).await.unwrap();
+        let store: Arc<dyn PersistenceLayer> = db;
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let ingest = IngestService::new(Arc::clone(&store), matcher, ChunkingStrategy::Sentence);
+        let pool = IngestionWorkerPool::new(1, 10, Arc::new(ingest), Arc::clone(&store)).unwrap();
+        let uploads = tempfile::tempdir().unwrap();
+        let router = build_router().with_state(Arc::new(KnowledgeApiState {
+            persistence: Arc::clone(&store),
+            vector_matcher: Arc::new(VectorMatcher::new(0.5)),
+            ingestion_pool: Some(Arc::new(pool)),
+            ingest_service: None,
+            rerankers: Arc::new(RerankerRegistry::new()),
+            audit: None,
+            upload_dir: uploads.path().to_path_buf(),
+            max_upload_size: 1024 * 1024,
+            allowed_mime_types: Vec::new(),
+            search_cache: None,
+        }));
+
+        let pdf = sample_pdf("Ownership frees memory.", "The Rust Book", "Ferris Crab");
+        let mut body = b"--XX\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"book.pdf\"\r\n\
+            Content-Type: application/pdf\r\n\r\n"
+            .to_vec();
+        body.extend_from_slice(&pdf);
+        body.extend_from_slice(b"\r\n--XX--\r\n");
+        let upload = axum::http::Request::post("/kb-1/documents")
+            .header("content-type", "multipart/form-data; boundary=XX")
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        let response = router.clone().oneshot(upload).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let uploaded: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let id = uploaded["id"].as_str().unwrap();
+        let uri = format!("/kb-1/documents/{id}");
+
+        // The worker reads the upload from disk
+        let saved = store.get_document(id, None).await.unwrap().unwrap();
+        let path = uploads.path().join(format!("{id}.pdf"));
+        assert_eq!(saved.file_path.as_deref(), path.to_str());
+        assert_eq!(std::fs::read(&path).unwrap(), pdf);
+
+        let document = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let get = axum::http::Request::get(&uri)
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+                let response = router.clone().oneshot(get).await.unwrap();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let document: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+                if document["status"] != "pending" && document["status"] != "processing" {
+                    return document;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("document was not processed within 10 seconds");
+
+        assert_eq!(document["status"], "indexed", "{document}");
+        assert_eq!(document["metadata"]["title"], "The Rust Book");
+        assert_eq!(document["metadata"]["author"], "Ferris Crab");
+        assert_eq!(document["metadata"]["word_count"], 3);
+
+        let delete = axum::http::Request::delete(&uri)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router.oneshot(delete).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_search_filters_on_chunk_metadata() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "docs")).await.unwrap();
+        let chunks = [("Refunds take 5 days.", "faq"), ("Refund policy v2.", "policy")];
+        for (content, source) in chunks {
+            db.save_chunk(&KnowledgeChunk {
+                id: uuid::Uuid::new_v4(),
+                kb_id: "kb-1".to_string(),
+                document_id: None,
+                content: content.to_string(),
+                metadata: Some(serde_json::json!({"source": source, "language": "en"})),
+                embedding: vec![1.0, 0.0],
+                tenant_id: None,
+                created_at: String::new(),
+            })
+            .await
+            .unwrap();
+        }
+        let state = Arc::new(KnowledgeApiState {
+            vector_matcher: Arc::new(VectorMatcher::with_provider(
+                0.5,
+                Arc::new(StubEmbedder::default()),
+            )),
+            ..(*state(db)).clone()
+        });
+        let router = build_router().with_state(state);
+        let search = |body: serde_json::Value| {
+            let request = axum::http::Request::post("/kb-1/search")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let body = serde_json::from_slice::<serde_json::Value>(&bytes);
+                (status, body.unwrap_or_default())
+            }
+        };
+
+        let (status, all) = search(serde_json::json!({"query": "refunds"})).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(all["results"].as_array().unwrap().len(), 2);
+
+        let (_, faq) = search(serde_json::json!({
+            "query": "refunds",
+            "metadata_filter": {"source": "faq", "language": "en"},
+            "retrieve_metadata_fields": ["source"],
+        }))
+        .await;
+        let results = faq["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["content"], "Refunds take 5 days.");
+        assert_eq!(results[0]["metadata"], serde_json::json!({"source": "faq"}));
+
+        let (_, none) = search(serde_json::json!({
+            "query": "refunds",
+            "metadata_filter": {"source": "blog"},
+        }))
+        .await;
+        assert!(none["results"].as_array().unwrap().is_empty());
+
+        let (status, _) = search(serde_json::json!({
+            "query": "refunds",
+            "metadata_filter": ["faq"],
+        }))
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_linked_search_is_cached() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "docs")).await.unwrap();
+        db.save_chunk(&KnowledgeChunk {
+            id: uuid::Uuid::new_v4(),
+            kb_id: "kb-1".to_string(),
+            document_id: None,
+            content: "Refunds take 5 days.".to_string(),
+            metadata: None,
+            embedding: vec![1.0, 0.0],
+            tenant_id: None,
+            created_at: String::new(),
+        })
+        .await
+        .unwrap();
+        let cache = Arc::new(SearchCache::default());
+        let state = Arc::new(KnowledgeApiState {
+            vector_matcher: Arc::new(VectorMatcher::with_provider(
+                0.5,
+                Arc::new(StubEmbedder::default()),
+            )),
+            search_cache: Some(Arc::clone(&cache)),
+            ..(*state(Arc::clone(&db))).clone()
+        });
+        let router = build_router().with_state(state);
+        let search = |uri: &str| {
+            let request = axum::http::Request::get(uri)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let body = serde_json::from_slice::<serde_json::Value>(&bytes);
+                (status, body.unwrap_or_default())
+            }
+        };
+
+        let (status, first) = search("/kb-1/search?q=refunds&limit=3").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(first["results"][0]["content"], "Refunds take 5 days.");
+        assert_eq!(cache.len(), 1);
+
+        // With the KB gone, only a cached response can still answer
+        db.delete_knowledge_base("kb-1", None).await.unwrap();
+        let (status, second) = search("/kb-1/search?q=refunds&limit=3").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(second, first);
+
+        let (status, _) = search("/kb-1/search?q=refunds&limit=4").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = search("/kb-1/search?limit=3").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_upload_type_is_sniffed_from_content() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "docs")).await.unwrap();
+        let uploads = tempfile::tempdir().unwrap();
+        let state = Arc::new(KnowledgeApiState {
+            upload_dir: uploads.path().to_path_buf(),
+            allowed_mime_types: vec!["text/*".to_string(), "application/pdf".to_string()],
+            ..(*state(Arc::clone(&db))).clone()
+        });
+        let upload = |filename: &str, content_type: &str, content: &[u8]| {
+            let mut body = format!(
+                "--XX\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+                Content-Type: {content_type}\r\n\r\n"
+            )
+            .into_bytes();
+            body.extend_from_slice(content);
+            body.extend_from_slice(b"\r\n--XX--\r\n");
+            axum::http::Request::post("/kb-1/documents")
+                .header("content-type", "multipart/form-data; boundary=XX")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let router = build_router().with_state(state);
+
+        // A PNG claiming to be text
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01";
+        let response = router
+            .clone()
+            .oneshot(upload("notes.txt", "text/plain", png))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("'image/png' is not allowed"));
+        assert!(db.list_documents("kb-1", None).await.unwrap().is_empty());
+        assert_eq!(std::fs::read_dir(uploads.path()).unwrap().count(), 0);
+
+        // Text claiming to be a PDF is stored as what it is
+        let response = router
+            .oneshot(upload("notes.txt", "application/pdf", b"Plain text notes."))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let documents = db.list_documents("kb-1", None).await.unwrap();
+        assert_eq!(documents[0].mime_type.as_deref(), Some("text/plain"));
+
+        assert_eq!(
+            sniff_mime_type(b"\xff\xfe\x00binary", "data.txt"),
+            "application/octet-stream"
+        );
+        assert_eq!(sniff_mime_type(b"plain", "notes.pdf"), "text/plain");
+        assert!(mime_type_allowed("image/png", &[]));
+        assert!(!mime_type_allowed("image/png", &["text/*".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_upload_is_rejected() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "docs")).await.unwrap();
+        let uploads = tempfile::tempdir().unwrap();
+        let state = Arc::new(KnowledgeApiState {
+            upload_dir: uploads.path().to_path_buf(),
+            max_upload_size: 16,
+            ..(*state(Arc::clone(&db))).clone()
+        });
+
+        let upload = |text: &str| {
+            let body = format!(
+                "--XX\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"\r\n\
+                Content-Type: text/plain\r\n\r\n\
+                {text}\r\n\
+                --XX--\r\n"
+            );
+            axum::http::Request::post("/kb-1/documents")
+                .header("content-type", "multipart/form-data; boundary=XX")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let router = build_router().with_state(state);
+
+        let response = router.clone().oneshot(upload("Too long for the limit")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(db.list_documents("kb-1", None).await.unwrap().is_empty());
+        assert_eq!(std::fs::read_dir(uploads.path()).unwrap().count(), 0);
+
+        let response = router.oneshot(upload("Fits")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(std::fs::read_dir(uploads.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retried_upload_returns_existing_document() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "docs")).await.unwrap();
+        let uploads = tempfile::tempdir().unwrap();
+        let state = Arc::new(KnowledgeApiState {
+            upload_dir: uploads.path().to_path_buf(),
+            ..(*state(Arc::clone(&db))).clone()
+        });
+        let upload = |header_key: Option<&str>, field_key: Option<&str>| {
+            let mut body = String::new();
+            if let Some(key) = field_key {
+                body.push_str(&format!(
+                    "--XX\r\n\
+                    Content-Disposition: form-data; name=\"idempotency_key\"\r\n\r\n\
+                    {key}\r\n"
+                ));
+            }
+            body.push_str(
+                "--XX\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"\r\n\
+                Content-Type: text/plain\r\n\r\n\
+                Plain text notes.\r\n\
+                --XX--\r\n",
+            );
+            let mut request = axum::http::Request::post("/kb-1/documents")
+                .header("content-type", "multipart/form-data; boundary=XX");
+            if let Some(key) = header_key {
+                request = request.header("idempotency-key", key);
+            }
+            request.body(axum::body::Body::from(body)).unwrap()
+        };
+        let router = build_router().with_state(state);
+        let send = |request| {
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let doc: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+                (status, doc["id"].as_str().unwrap_or_default().to_string())
+            }
+        };
+
+        let (status, first) = send(upload(Some("upload-1"), None)).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let (status, retried) = send(upload(Some("upload-1"), None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(retried, first);
+
+        // The form field works too, and the retried file isn't kept
+        let (status, second) = send(upload(None, Some("upload-2"))).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let (status, retried) = send(upload(None, Some("upload-2"))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(retried, second);
+        assert_ne!(first, second);
+
+        assert_eq!(db.list_documents("kb-1", None).await.unwrap().len(), 2);
+        assert_eq!(std::fs::read_dir(uploads.path()).unwrap().count(), 2);
+
+        let (status, _) = send(upload(Some(&"k".repeat(256)), None)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_exported_kb_can_be_imported() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "docs")).await.unwrap();
+        let store: Arc<dyn PersistenceLayer> = Arc::clone(&db);
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let ingest = IngestService::new(store, matcher, ChunkingStrategy::Sentence);
+        let text = "Ownership frees memory. Borrowing lends it.";
+        ingest
+            .ingest_text(text, "kb-1", "doc-1".to_string(), None)
+            .await
+            .unwrap();
+        let chunks = db.list_chunks("kb-1", None).await.unwrap().len();
+        let uploads = tempfile::tempdir().unwrap();
+        let router = build_router().with_state(Arc::new(KnowledgeApiState {
+            ingest_service: Some(Arc::new(ingest)),
+            upload_dir: uploads.path().to_path_buf(),
+            ..(*state(Arc::clone(&db))).clone()
+        }));
+
+        let export = axum::http::Request::get("/kb-1/export")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(export).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/zip");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"docs.zip\""
+        );
+        let archive = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        // The archive is only kept while it is sent
+        assert_eq!(std::fs::read_dir(uploads.path()).unwrap().count(), 0);
+
+        let import = |filename: &str, content: &[u8]| {
+            let mut body = format!(
+                "--XX\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+                Content-Type: application/zip\r\n\r\n"
+            )
+            .into_bytes();
+            body.extend_from_slice(content);
+            body.extend_from_slice(b"\r\n--XX--\r\n");
+            axum::http::Request::post("/import")
+                .header("content-type", "multipart/form-data; boundary=XX")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let response = router
+            .clone()
+            .oneshot(import("docs.zip", &archive))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let imported: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        let id = imported["id"].as_str().unwrap();
+        assert_ne!(id, "kb-1");
+        assert_eq!(db.list_chunks(id, None).await.unwrap().len(), chunks);
+        assert_eq!(std::fs::read_dir(uploads.path()).unwrap().count(), 0);
+
+        // Only ZIP archives are read
+        let response = router
+            .oneshot(import("docs.zip", text.as_bytes()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(db.list_knowledge_bases(None).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reindex_job_reports_reprocessed_documents() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "docs")).await.unwrap();
+        let files = tempfile::tempdir().unwrap();
+        let path = files.path().join("notes.txt");
+        std::fs::write(&path, "Ownership frees memory.").unwrap();
+        // Indexed before content hashes were recorded
+        db.save_document(&KnowledgeDocument {
+            id: "doc-1".to_string(),
+            kb_id: "kb-1".to_string(),
+            filename: "notes.txt".to_string(),
+            file_path: Some(path.to_string_lossy().into_owned()),
+            mime_type: Some("text/plain".to_string()),
+            chunk_count: 0,
+            status: DocumentStatus::Indexed,
+            tenant_id: None,
+            metadata: None,
+            idempotency_key: None,
+            content_hash: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        })
+        .await
+        .unwrap();
+        let store: Arc<dyn PersistenceLayer> = Arc::clone(&db);
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let ingest = IngestService::new(store, matcher, ChunkingStrategy::Sentence);
+        let router = build_router().with_state(Arc::new(KnowledgeApiState {
+            ingest_service: Some(Arc::new(ingest)),
+            ..(*state(Arc::clone(&db))).clone()
+        }));
+        let call = |request: axum::http::Request<axum::body::Body>| {
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+                (status, body)
+            }
+        };
+        let reindex = || async {
+            let start = axum::http::Request::post("/kb-1/reindex")
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let (status, job) = call(start).await;
+            assert_eq!(status, StatusCode::ACCEPTED);
+            let uri = format!("/kb-1/reindex/{}", job["id"].as_str().unwrap());
+            loop {
+                let poll = axum::http::Request::get(&uri)
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+                let (status, job) = call(poll).await;
+                assert_eq!(status, StatusCode::OK);
+                if job["status"] != "running" {
+                    return job;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        };
+
+        let job = reindex().await;
+        assert_eq!(job["status"], "completed", "{job}");
+        assert_eq!(
+            job["report"],
+            serde_json::json!({ "skipped": 0, "reprocessed": 1, "failed": 0 })
+        );
+        let doc = db.get_document("doc-1", None).await.unwrap().unwrap();
+        assert_eq!(doc.chunk_count, 1);
+        assert!(doc.content_hash.is_some());
+
+        let job = reindex().await;
+        assert_eq!(job["report"]["skipped"], 1);
+        assert_eq!(job["report"]["reprocessed"], 0);
+
+        let missing = axum::http::Request::get("/kb-1/reindex/nope")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        assert_eq!(call(missing).await.0, StatusCode::NOT_FOUND);
+    }
+
+    /// Finds a few known names, and who designed or wrote about what.
+    #[derive(Debug)]
+    struct KnownEntities;
+
+    #[async_trait::async_trait]
+    impl RelationshipExtractor for KnownEntities {
+        async fn extract(&self, chunk: &KnowledgeChunk) -> anyhow::Result<ExtractionResult> {
+            self.extract_from_text(&chunk.content).await
+        }
+
+        async fn extract_from_text(&self, text: &str) -> anyhow::Result<ExtractionResult> {
+            let known = [
+                ("Charles Babbage", EntityType::Person),
+                ("Ada Lovelace", EntityType::Person),
+                ("Analytical Engine", EntityType::Product),
+            ];
+            let entities = known
+                .into_iter()
+                .filter(|(name, _)| text.contains(name))
+                .map(|(name, entity_type)| Entity {
+                    id: name.to_string(),
+                    canonical_name: name.to_string(),
+                    entity_type,
+                    description: None,
+                    embedding: Vec::new(),
+                    source_chunk_ids: Vec::new(),
+                    created_at: String::new(),
+                })
+                .collect();
+            let relationships = [
+                ("Charles Babbage", "designed", "Analytical Engine"),
+                ("Ada Lovelace", "wrote about", "Analytical Engine"),
+            ]
+            .into_iter()
+            .filter(|(source, verb, target)| {
+                text.contains(&format!("{source} {verb} the {target}"))
+            })
+            .map(|(source, verb, target)| Relationship {
+                id: format!("{source}-{target}"),
+                source_id: source.to_string(),
+                target_id: target.to_string(),
+                relation_type: verb.replace(' ', "_"),
+                weight: 1.0,
+                description: None,
+                source_chunk_id: String::new(),
+                created_at: String::new(),
+            })
+            .collect();
+            Ok(ExtractionResult {
+                entities,
+                relationships,
+            })
+        }
+
+        fn name(&self) -> &'static str {
+            "known_entities"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ingested_entities_form_the_knowledge_graph() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "history")).await.unwrap();
+        let store: Arc<dyn PersistenceLayer> = Arc::clone(&db);
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let ingest = IngestService::new(store, matcher, ChunkingStrategy::Sentence)
+            .with_extractor(ExtractionStrategy::DEFAULT, Arc::new(KnownEntities));
+        let document = KnowledgeDocument {
+            id: "doc-1".to_string(),
+            kb_id: "kb-1".to_string(),
+            filename: "engine.txt".to_string(),
+            file_path: None,
+            mime_type: Some("text/plain".to_string()),
+            chunk_count: 0,
+            status: DocumentStatus::Pending,
+            tenant_id: None,
+            metadata: None,
+            idempotency_key: None,
+            content_hash: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        let text = "Charles Babbage designed the Analytical Engine. \
+                    Ada Lovelace wrote about the Analytical Engine.";
+        ingest
+            .ingest_document(&document, text.as_bytes().to_vec())
+            .await
+            .unwrap();
+
+        let router = build_router().with_state(state(db));
+        let graph = |uri: String| {
+            let router = router.clone();
+            async move {
+                let request = axum::http::Request::get(uri)
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+                (status, body)
+            }
+        };
+        let labels = |body: &serde_json::Value| {
+            let mut labels: Vec<String> = body["nodes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|node| node["label"].as_str().unwrap().to_string())
+                .collect();
+            labels.sort();
+            labels
+        };
+
+        // Extraction runs in the background once the chunks are saved
+        let (status, body) = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let (status, body) = graph("/kb-1/graph".to_string()).await;
+                let linked = body["links"].as_array().is_some_and(|links| links.len() == 2);
+                if status != StatusCode::OK || linked {
+                    return (status, body);
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("knowledge graph was not extracted");
+
+        // The engine is mentioned twice but is one node
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(
+            labels(&body),
+            ["Ada Lovelace", "Analytical Engine", "Charles Babbage"]
+        );
+        let ada = GraphNode::id_for("kb-1", "Ada Lovelace");
+        let babbage = GraphNode::id_for("kb-1", "Charles Babbage");
+        let engine = GraphNode::id_for("kb-1", "Analytical Engine");
+        let mut links: Vec<(String, String, String)> = body["links"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|link| {
+                let field = |name: &str| link[name].as_str().unwrap().to_string();
+                (field("source"), field("relationship_type"), field("target"))
+            })
+            .collect();
+        links.sort();
+        let mut expected = vec![
+            (babbage, "designed".to_string(), engine.clone()),
+            (ada.clone(), "wrote_about".to_string(), engine),
+        ];
+        expected.sort();
+        assert_eq!(links, expected);
+
+        // Ada's neighbourhood reaches Babbage through the engine
+        let (_, body) = graph(format!("/kb-1/graph?node_id={ada}&depth=1")).await;
+        assert_eq!(labels(&body), ["Ada Lovelace", "Analytical Engine"]);
+        assert_eq!(body["links"].as_array().unwrap().len(), 1);
+        let (_, body) = graph(format!("/kb-1/graph?node_id={ada}&depth=2")).await;
+        assert_eq!(labels(&body).len(), 3);
+
+        let (status, _) = graph(format!("/kb-1/graph?depth={}", MAX_GRAPH_DEPTH + 1)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = graph("/kb-2/graph".to_string()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    /// Never finishes, like an extractor waiting for its token budget.
+    #[derive(Debug)]
+    struct StalledExtractor;
+
+    #[async_trait::async_trait]
+    impl RelationshipExtractor for StalledExtractor {
+        async fn extract(&self, _chunk: &KnowledgeChunk) -> anyhow::Result<ExtractionResult> {
+            std::future::pending().await
+        }
+
+        async fn extract_from_text(&self, _text: &str) -> anyhow::Result<ExtractionResult> {
+            std::future::pending().await
+        }
+
+        fn name(&self) -> &'static str {
+            "stalled"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ingestion_does_not_wait_for_graph_extraction() {
+        let db = Arc::new(InMemoryPersistence::new());
+        db.save_knowledge_base(&kb("kb-1", "history")).await.unwrap();
+        let store: Arc<dyn PersistenceLayer> = Arc::clone(&db);
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let ingest = IngestService::new(store, matcher, ChunkingStrategy::Sentence)
+            .with_extractor(ExtractionStrategy::DEFAULT, Arc::new(StalledExtractor));
+
+        let created = tokio::time::timeout(
+            Duration::from_secs(10),
+            ingest.ingest_text("Babbage designed an engine.", "kb-1", "doc-1".to_string(), None),
+        )
+        .await
+        .expect("ingestion waited for extraction")
+        .unwrap();
+        assert_eq!(created, 1);
+        assert_eq!(db.list_chunks("kb-1", None).await.unwrap().len(), 1);
+    }
+}
@@ -1,7 +1,8 @@
 use crate::AppState;
 use crate::uar::domain::memory::Memory;
+use crate::uar::security::claims::{TenantContext, tenant_scope};
 use axum::{
-    Json,
+    Extension, Json,
     extract::{Query, State},
     http::StatusCode,
     response::IntoResponse,
@@ -27,6 +28,7 @@ pub struct SearchMemoryQuery {
 
 pub async fn save_memory_handler(
     State(state): State<AppState>,
+    tenant: Option<Extension<TenantContext>>,
     Json(payload): Json<SaveMemoryRequest>,
 ) -> impl IntoResponse {
     let persistence = match &state.persistence {
@@ -69,6 +71,7 @@ pub async fn save_memory_handler(
         content: payload.content,
         tags: payload.tags.unwrap_or_default(),
         embedding,
+        tenant_id: tenant_scope(tenant.as_deref()).map(str::to_string),
         created_at: chrono::Utc::now().to_rfc3339(),
     };
 
@@ -89,6 +92,7 @@ pub async fn save_memory_handler(
 
 pub async fn search_memory_handler(
     State(state): State<AppState>,
+    tenant: Option<Extension<TenantContext>>,
     Query(query): Query<SearchMemoryQuery>,
 ) -> impl IntoResponse {
     let persistence = match &state.persistence {
@@ -135,7 +139,13 @@ pub async fn search_memory_handler(
     // So passing query.agent_id.as_deref() works (matches PostgresProvider logic).
 
     let matches = match persistence
-        .search_memory(query.agent_id.as_deref(), &embedding, limit, min_score)
+        .search_memory(
+            query.agent_id.as_deref(),
+            &embedding,
+            limit,
+            min_score,
+            tenant_scope(tenant.as_deref()),
+        )
         .await
     {
         Ok(m) => m,
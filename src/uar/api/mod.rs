@@ -1,11 +1,15 @@
 pub mod adapters;
+pub mod admin;
+pub mod health;
 pub mod ingest;
 pub mod knowledge;
 pub mod memory;
 pub mod openai;
 pub mod routes;
+pub mod search_cache;
 pub mod sse;
 pub mod upload;
+pub mod ws;
 
 use axum::Router;
 
@@ -1,7 +1,16 @@
 use super::types::*;
 use crate::AppState;
-use crate::uar::security::claims::UserContext;
-use crate::uar::{defaults, domain::events::NormalizedEvent};
+use crate::uar::domain::knowledge::KbConfig;
+use crate::uar::runtime::context::token_service::TokenService;
+use crate::uar::runtime::matching::VectorMatcher;
+use crate::uar::security::claims::{TenantContext, UserContext, tenant_scope};
+use crate::uar::security::rate_limit::ClientKey;
+use crate::uar::telemetry::RequestId;
+use crate::uar::{
+    defaults,
+    domain::events::NormalizedEvent,
+    domain::runs::{RunOptions, TokenUsage},
+};
 use axum::{
     extract::{Extension, Json, State},
     http::StatusCode,
@@ -10,31 +19,59 @@ use axum::{
         sse::{Event, Sse},
     },
 };
+use futures::Stream;
+use std::collections::{BTreeSet, HashSet};
+use std::convert::Infallible;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
-pub async fn list_models(State(_state): State<AppState>) -> impl IntoResponse {
-    // In a real implementation, we would list all active agents from persistence
-    // For now, we return the default/orchestrator agents
+/// Model IDs of OpenAI fine-tunes start with this.
+const FINE_TUNED_PREFIX: &str = "ft:";
+
+/// Models callable through `/v1/chat/completions`.
+///
+/// The built-in agents, the configured LLM model and any fine-tuned model
+/// (`ft:` prefix) an agent of the tenant is set up to use.
+pub async fn list_models(
+    State(state): State<AppState>,
+    tenant: Option<Extension<TenantContext>>,
+) -> impl IntoResponse {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs();
 
-    let models = vec![
-        ModelCard {
-            id: "default".to_string(),
-            object: "model".to_string(),
-            created: now,
-            owned_by: "uar".to_string(),
-        },
-        ModelCard {
-            id: "orchestrator".to_string(),
+    let mut ids = vec![
+        "default".to_string(),
+        "orchestrator".to_string(),
+        state.orchestrator.settings().model.clone(),
+    ];
+    if let Some(persistence) = &state.persistence {
+        match persistence.list_agents(tenant_scope(tenant.as_deref())).await {
+            Ok(agents) => {
+                let fine_tuned: BTreeSet<String> = agents
+                    .into_iter()
+                    .map(|agent| agent.policy.provider.default.model)
+                    .filter(|model| model.starts_with(FINE_TUNED_PREFIX))
+                    .collect();
+                ids.extend(fine_tuned);
+            }
+            Err(e) => tracing::warn!("Failed to list agents for /v1/models: {:#}", e),
+        }
+    }
+    let mut seen = HashSet::new();
+    ids.retain(|id| seen.insert(id.clone()));
+
+    let models = ids
+        .into_iter()
+        .map(|id| ModelCard {
+            id,
             object: "model".to_string(),
             created: now,
             owned_by: "uar".to_string(),
-        },
-    ];
+        })
+        .collect();
 
     Json(ModelList {
         object: "list".to_string(),
@@ -42,11 +79,75 @@ pub async fn list_models(State(_state): State<AppState>) -> impl IntoResponse {
     })
 }
 
+/// OpenAI-compatible embeddings with the default embedding model.
+///
+/// Rate-limited on its own budget, so bulk embedding can't starve chat.
+pub async fn embeddings(
+    State(state): State<AppState>,
+    client: Option<Extension<ClientKey>>,
+    Json(req): Json<EmbeddingsRequest>,
+) -> Result<Json<EmbeddingsResponse>, (StatusCode, String)> {
+    let client = client.map_or_else(String::new, |Extension(ClientKey(key))| key);
+    if state.config.resilience.rate_limit_enabled
+        && !state.embeddings_rate_limiter.check(&client)
+    {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            "Embeddings rate limit exceeded".to_string(),
+        ));
+    }
+    create_embeddings(&state.vector_matcher, req).await.map(Json)
+}
+
+async fn create_embeddings(
+    matcher: &VectorMatcher,
+    req: EmbeddingsRequest,
+) -> Result<EmbeddingsResponse, (StatusCode, String)> {
+    let model = KbConfig::default_embedding_model();
+    if req.model != model {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("Model '{}' not found, embeddings use '{}'", req.model, model),
+        ));
+    }
+    let texts = req.input.into_texts();
+    if texts.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "input must not be empty".to_string()));
+    }
+
+    let tokens = texts.iter().map(|t| TokenService::estimate_string(t)).sum();
+    let vectors = matcher
+        .embed_batch(texts)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))?;
+
+    Ok(EmbeddingsResponse {
+        object: "list".to_string(),
+        data: vectors
+            .into_iter()
+            .enumerate()
+            .map(|(index, embedding)| EmbeddingData {
+                object: "embedding".to_string(),
+                embedding,
+                index,
+            })
+            .collect(),
+        model,
+        usage: EmbeddingsUsage {
+            prompt_tokens: tokens,
+            total_tokens: tokens,
+        },
+    })
+}
+
 pub async fn chat_completions(
     State(state): State<AppState>,
     Extension(user_context): axum::Extension<UserContext>,
+    tenant: Option<Extension<TenantContext>>,
+    request_id: Option<Extension<RequestId>>,
     Json(req): Json<ChatCompletionRequest>,
 ) -> impl IntoResponse {
+    let tenant_id = tenant_scope(tenant.as_deref());
     let run_manager = &state.run_manager;
     let conversation_id = Uuid::new_v4().to_string();
     let created = SystemTime::now()
@@ -69,7 +170,7 @@ pub async fn chat_completions(
         match run_manager.persistence.as_ref() {
             Some(p) => {
                 // Try to load dynamic agent
-                match futures::executor::block_on(p.load_agent_by_name(&req.model)) {
+                match futures::executor::block_on(p.load_agent_by_name(&req.model, tenant_id)) {
                     Ok(Some(a)) => a,
                     _ => defaults::default_agent(),
                 }
@@ -83,17 +184,26 @@ pub async fn chat_completions(
     // because OpenAI API is stateless (except for message history passed in request).
     // Ideally we would map thread_id if UAR supported it in context, but UAR sessions are ID-based.
     // We'll create an ephemeral session ID here.
-    let run_id = run_manager
+    let run_id = match run_manager
         .start_run(
             agent,
             last_message.clone(),
             Some(conversation_id.clone()),
             Some(user_context.user_id),
+            tenant_id.map(str::to_string),
+            RunOptions {
+                request_id: request_id.map(|Extension(id)| id.0),
+                ..Default::default()
+            },
         )
-        .await;
+        .await
+    {
+        Ok(run_id) => run_id,
+        Err(e) => return (e.status_code(), e.to_string()).into_response(),
+    };
 
     // Subscribe to events
-    let mut rx = match run_manager.subscribe(&run_id).await {
+    let rx = match run_manager.subscribe(&run_id).await {
         Some(rx) => rx,
         None => {
             return (
@@ -105,71 +215,241 @@ pub async fn chat_completions(
     };
 
     // Convert to SSE stream
-    let stream = async_stream::stream! {
-        let id = Uuid::new_v4().to_string();
+    let chunks = ChunkStream {
+        id: Uuid::new_v4().to_string(),
+        created,
+        model: req.model,
+        include_usage: req.stream_options.is_some_and(|options| options.include_usage),
+    };
+    let stream = chunks.events(rx);
+
+    Sse::new(stream)
+        .keep_alive(axum::response::sse::KeepAlive::default())
+        .into_response()
+}
 
-        // Yield initial role chunk
-         let initial_chunk = ChatCompletionChunk {
-            id: id.clone(),
+/// Turns a run's events into `chat.completion.chunk`s.
+struct ChunkStream {
+    id: String,
+    created: u64,
+    model: String,
+    /// Send a last chunk with the run's usage (`stream_options.include_usage`)
+    include_usage: bool,
+}
+
+impl ChunkStream {
+    fn chunk(
+        &self,
+        choices: Vec<ChatCompletionChunkChoice>,
+        usage: Option<TokenUsage>,
+    ) -> ChatCompletionChunk {
+        ChatCompletionChunk {
+            id: self.id.clone(),
             object: "chat.completion.chunk".to_string(),
-            created,
-            model: req.model.clone(),
-            choices: vec![ChatCompletionChunkChoice {
-                index: 0,
-                delta: ChatCompletionChunkDelta {
-                    role: Some("assistant".to_string()),
-                    content: Some("".to_string()),
-                },
-                finish_reason: None,
-            }],
+            created: self.created,
+            model: self.model.clone(),
+            choices,
+            usage,
+        }
+    }
+
+    fn delta(
+        &self,
+        delta: ChatCompletionChunkDelta,
+        finish_reason: Option<String>,
+    ) -> ChatCompletionChunk {
+        let choice = ChatCompletionChunkChoice {
+            index: 0,
+            delta,
+            finish_reason,
         };
-        yield Ok::<_, std::convert::Infallible>(Event::default().json_data(initial_chunk).unwrap());
-
-        while let Ok(event) = rx.recv().await {
-            match event {
-                NormalizedEvent::ChatDelta { text_delta, .. } => {
-                    let chunk = ChatCompletionChunk {
-                        id: id.clone(),
-                        object: "chat.completion.chunk".to_string(),
-                        created,
-                        model: req.model.clone(),
-                        choices: vec![ChatCompletionChunkChoice {
-                            index: 0,
-                            delta: ChatCompletionChunkDelta {
-                                role: None,
-                                content: Some(text_delta),
-                            },
-                            finish_reason: None,
-                        }],
-                    };
-                    yield Ok(Event::default().json_data(chunk).unwrap());
-                }
-                NormalizedEvent::RunDone { .. } => {
-                     let chunk = ChatCompletionChunk {
-                        id: id.clone(),
-                        object: "chat.completion.chunk".to_string(),
-                        created,
-                        model: req.model.clone(),
-                        choices: vec![ChatCompletionChunkChoice {
-                            index: 0,
-                            delta: ChatCompletionChunkDelta::default(),
-                            finish_reason: Some("stop".to_string()),
-                        }],
-                    };
-                    yield Ok(Event::default().json_data(chunk).unwrap());
-                    yield Ok(Event::default().data("[DONE]"));
-                    break;
-                }
-                NormalizedEvent::Error { message, .. } => {
-                    tracing::error!("Error in chat completion: {}", message);
-                    break;
+        self.chunk(vec![choice], None)
+    }
+
+    /// SSE events for the run's events on `rx`, ending with `[DONE]` once
+    /// the run is done.
+    ///
+    /// With `include_usage`, the chunk before `[DONE]` has no choices and
+    /// the run's total usage, as OpenAI sends it.
+    fn events(
+        self,
+        mut rx: broadcast::Receiver<NormalizedEvent>,
+    ) -> impl Stream<Item = Result<Event, Infallible>> {
+        async_stream::stream! {
+            // Yield initial role chunk
+            let role = ChatCompletionChunkDelta {
+                role: Some("assistant".to_string()),
+                content: Some(String::new()),
+            };
+            yield Ok(Event::default().json_data(self.delta(role, None)).unwrap());
+
+            // Usage events carry the run's totals so far
+            let mut usage = None;
+            while let Ok(event) = rx.recv().await {
+                match event {
+                    NormalizedEvent::ChatDelta { text_delta, .. } => {
+                        let delta = ChatCompletionChunkDelta {
+                            role: None,
+                            content: Some(text_delta),
+                        };
+                        yield Ok(Event::default().json_data(self.delta(delta, None)).unwrap());
+                    }
+                    NormalizedEvent::Usage {
+                        prompt_tokens,
+                        completion_tokens,
+                        total_tokens,
+                        ..
+                    } => {
+                        usage = Some(TokenUsage {
+                            prompt_tokens,
+                            completion_tokens,
+                            total_tokens,
+                        });
+                    }
+                    NormalizedEvent::RunDone { usage: run_usage, .. } => {
+                        let stop = Some("stop".to_string());
+                        let chunk = self.delta(ChatCompletionChunkDelta::default(), stop);
+                        yield Ok(Event::default().json_data(chunk).unwrap());
+                        if self.include_usage {
+                            // Prefer the run's final totals
+                            let usage = run_usage.map(|run| run.tokens).or(usage);
+                            let chunk = self.chunk(Vec::new(), Some(usage.unwrap_or_default()));
+                            yield Ok(Event::default().json_data(chunk).unwrap());
+                        }
+                        yield Ok(Event::default().data("[DONE]"));
+                        break;
+                    }
+                    NormalizedEvent::Error { message, .. } => {
+                        tracing::error!("Error in chat completion: {}", message);
+                        break;
+                    }
+                    _ => {}
                 }
-                _ => {}
             }
         }
-    };
+    }
+}
 
-    Sse::new(stream)
-        .keep_alive(axum::response::sse::KeepAlive::default())
-        .into_response()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::testing::StubEmbedder;
+    use std::sync::Arc;
+
+    fn request(body: serde_json::Value) -> EmbeddingsRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_response_is_openai_shaped() {
+        let matcher = VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::new(vec![1.0, 0.5])),
+        );
+        let body = serde_json::json!({
+            "model": KbConfig::default_embedding_model(),
+            "input": ["hello", "hi"],
+        });
+
+        let response = create_embeddings(&matcher, request(body)).await.unwrap();
+        let json = serde_json::to_value(response).unwrap();
+
+        assert_eq!(json["object"], "list");
+        assert_eq!(json["model"], KbConfig::default_embedding_model());
+        assert_eq!(json["data"][0]["object"], "embedding");
+        assert_eq!(json["data"][0]["index"], 0);
+        assert_eq!(json["data"][0]["embedding"], serde_json::json!([1.0, 0.5]));
+        assert_eq!(json["data"][1]["index"], 1);
+        let usage = &json["usage"];
+        assert!(usage["prompt_tokens"].as_u64().unwrap() > 0);
+        assert_eq!(usage["prompt_tokens"], usage["total_tokens"]);
+    }
+
+    #[tokio::test]
+    async fn test_single_input_and_unknown_model() {
+        let matcher = VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::new(vec![1.0, 0.5])),
+        );
+
+        let body = serde_json::json!({
+            "model": KbConfig::default_embedding_model(),
+            "input": "hello",
+        });
+        let response = create_embeddings(&matcher, request(body)).await.unwrap();
+        assert_eq!(response.data.len(), 1);
+
+        let body = serde_json::json!({ "model": "text-embedding-3-small", "input": "hello" });
+        let (status, _) = create_embeddings(&matcher, request(body)).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    /// `data` fields of the SSE events `chunks` sends for `events`.
+    async fn sse_data(chunks: ChunkStream, events: Vec<NormalizedEvent>) -> Vec<String> {
+        let (tx, rx) = broadcast::channel(16);
+        for event in events {
+            tx.send(event).unwrap();
+        }
+        let response = Sse::new(chunks.events(rx)).into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|data| data.trim_start().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_usage_chunk_precedes_done() {
+        let chunks = |include_usage| ChunkStream {
+            id: "chatcmpl-1".to_string(),
+            created: 0,
+            model: "default".to_string(),
+            include_usage,
+        };
+        let run_id = "run-1".to_string();
+        let events = || {
+            vec![
+                NormalizedEvent::ChatDelta {
+                    run_id: run_id.clone(),
+                    text_delta: "Hi".to_string(),
+                },
+                NormalizedEvent::Usage {
+                    run_id: run_id.clone(),
+                    prompt_tokens: 12,
+                    completion_tokens: 3,
+                    total_tokens: 15,
+                },
+                NormalizedEvent::RunDone {
+                    run_id: run_id.clone(),
+                    usage: None,
+                },
+            ]
+        };
+        let parse = |data: &str| serde_json::from_str::<serde_json::Value>(data).unwrap();
+
+        let data = sse_data(chunks(true), events()).await;
+        assert_eq!(data.len(), 5);
+        assert_eq!(data[4], "[DONE]");
+        let usage = parse(&data[3]);
+        assert_eq!(usage["object"], "chat.completion.chunk");
+        assert_eq!(usage["id"], "chatcmpl-1");
+        assert_eq!(usage["choices"], serde_json::json!([]));
+        assert_eq!(
+            usage["usage"],
+            serde_json::json!({ "prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15 })
+        );
+        let stop = parse(&data[2]);
+        assert_eq!(stop["choices"][0]["finish_reason"], "stop");
+        assert!(stop.get("usage").is_none());
+
+        // Only sent when asked for
+        let data = sse_data(chunks(false), events()).await;
+        assert_eq!(data.len(), 4);
+        assert_eq!(parse(&data[2])["choices"][0]["finish_reason"], "stop");
+        assert_eq!(data[3], "[DONE]");
+    }
 }
@@ -1,3 +1,4 @@
+use crate::uar::domain::runs::TokenUsage;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Debug)]
@@ -20,9 +21,18 @@ pub struct ChatCompletionRequest {
     pub messages: Vec<OpenAIMessage>,
     #[serde(default)]
     pub stream: bool,
+    #[serde(default)]
+    pub stream_options: Option<StreamOptions>,
     // Add other fields as needed (temperature, etc.) but make them optional
 }
 
+#[derive(Deserialize, Debug, Default)]
+pub struct StreamOptions {
+    /// Send a last chunk with the run's token usage and no choices
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct OpenAIMessage {
     pub role: String,
@@ -36,6 +46,9 @@ pub struct ChatCompletionChunk {
     pub created: u64,
     pub model: String,
     pub choices: Vec<ChatCompletionChunkChoice>,
+    /// Only on the usage chunk
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
 }
 
 #[derive(Serialize, Debug)]
@@ -53,3 +66,47 @@ pub struct ChatCompletionChunkDelta {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
 }
+
+#[derive(Deserialize, Debug)]
+pub struct EmbeddingsRequest {
+    pub model: String,
+    pub input: EmbeddingsInput,
+}
+
+/// A single text or a batch of texts.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum EmbeddingsInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+impl EmbeddingsInput {
+    pub fn into_texts(self) -> Vec<String> {
+        match self {
+            Self::Single(text) => vec![text],
+            Self::Batch(texts) => texts,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct EmbeddingsResponse {
+    pub object: String, // "list"
+    pub data: Vec<EmbeddingData>,
+    pub model: String,
+    pub usage: EmbeddingsUsage,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EmbeddingData {
+    pub object: String, // "embedding"
+    pub embedding: Vec<f32>,
+    pub index: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EmbeddingsUsage {
+    pub prompt_tokens: usize,
+    pub total_tokens: usize,
+}
@@ -1,23 +1,72 @@
+use crate::mcp::{
+    config::DEFAULT_MCP_CONFIG_PATH,
+    connection::ServerDownError,
+    registry::{NamespacedItem, ReloadSummary, UnknownMcpItemError},
+};
 use crate::uar::{
-    api::sse::build_sse_response,
-    domain::{artifact::AgentArtifact, events::NormalizedEvent},
-    runtime::manager::RunManager,
+    api::{
+        sse::{SseFrame, build_sse_response, with_heartbeats},
+        ws::chat_socket,
+    },
+    domain::{
+        artifact::{AgentArtifact, AgentArtifactValidator, ChainStep},
+        runs::{RunLog, RunOptions, RunRecord, RunUsage, RunView},
+        skills::{Skill, parse_skill_version},
+    },
+    persistence::PersistenceError,
+    runtime::{
+        eval::{
+            AgentTestCase, AgentTestResult, DEFAULT_SUITE_TIMEOUT, DEFAULT_TEST_CONCURRENCY,
+            MAX_SUITE_TIMEOUT, MAX_TEST_CASES, MAX_TEST_CONCURRENCY, run_agent_tests,
+        },
+        manager::{RunManager, StartRunError},
+        skill_metrics::SkillStats,
+    },
+    security::{
+        claims::{TenantContext, tenant_scope},
+        middleware::require_admin,
+    },
+    telemetry::RequestId,
 };
 use axum::{
-    Json, Router,
-    extract::{Path, State},
-    response::IntoResponse,
+    Extension, Json, Router,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
     routing::{get, post},
 };
+use rmcp::model::{GetPromptResult, JsonObject, Prompt, ReadResourceResult, Resource};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio_stream::StreamExt;
-use tokio_stream::wrappers::BroadcastStream;
 
 pub fn build_router() -> Router<Arc<RunManager>> {
     Router::new()
-        .route("/runs", post(create_run))
+        .route("/runs", post(create_run).get(list_runs))
+        .route("/runs/{id}", get(get_run))
         .route("/runs/{id}/stream", get(stream_run))
+        .route("/runs/{id}/usage", get(run_usage))
+        .route("/runs/{id}/resume", post(resume_run))
+        .route("/runs/{id}/tool-approvals", post(decide_tool_approval))
+        .route(
+            "/runs/{id}/log",
+            get(run_log).route_layer(axum::middleware::from_fn(require_admin)),
+        )
+        .route("/ws", get(chat_socket))
+        .route("/chains/run", post(run_chain))
+        .route("/agents/import", post(import_agent))
+        .route("/agents/{id}/export", get(export_agent))
+        .route("/agents/{id}/test", post(test_agent))
+        .route("/mcp/reload", post(reload_mcp))
+        .route("/mcp/resources", get(list_mcp_resources))
+        .route("/mcp/resources/read", get(read_mcp_resource))
+        .route("/mcp/prompts", get(list_mcp_prompts))
+        .route("/mcp/prompts/get", post(get_mcp_prompt))
+        .route("/skills/metrics", get(skill_metrics))
+        .route("/skills/{id}/versions", get(list_skill_versions))
+        .route("/skills/{id}/rollback", post(rollback_skill))
 }
 
 #[derive(Deserialize)]
@@ -25,6 +74,9 @@ struct CreateRunRequest {
     artifact: AgentArtifact,
     input: String,
     session_id: Option<String>,
+    /// e.g. `interactive_tools`
+    #[serde(flatten)]
+    options: RunOptions,
 }
 
 #[derive(serde::Serialize)]
@@ -33,30 +85,1050 @@ struct CreateRunResponse {
     stream_url: String,
 }
 
+impl CreateRunResponse {
+    fn new(run_id: String) -> Self {
+        Self {
+            stream_url: format!("/api/uar/runs/{}/stream", run_id),
+            run_id,
+        }
+    }
+}
+
 async fn create_run(
     State(manager): State<Arc<RunManager>>,
-    Json(req): Json<CreateRunRequest>,
-) -> Json<CreateRunResponse> {
+    tenant: Option<Extension<TenantContext>>,
+    request_id: Option<Extension<RequestId>>,
+    Json(mut req): Json<CreateRunRequest>,
+) -> Result<Json<CreateRunResponse>, (StatusCode, String)> {
+    let tenant_id = tenant_scope(tenant.as_deref()).map(str::to_string);
+    req.options.request_id = request_id.map(|Extension(id)| id.0);
+    manager
+        .check_request_artifact(&req.artifact, tenant_id.as_deref())
+        .await
+        .map_err(|e| (e.status_code(), e.to_string()))?;
+
+    // Artifacts with a chain run as a pipeline of their steps
+    if let Some(chain) = req.artifact.chain.filter(|c| !c.is_empty()) {
+        let run_id = manager
+            .start_chained_run(chain, req.input, req.session_id, tenant_id)
+            .await
+            .map_err(chain_error)?;
+        return Ok(Json(CreateRunResponse::new(run_id)));
+    }
+
     let run_id = manager
-        .start_run(req.artifact, req.input, req.session_id, None)
-        .await;
-    Json(CreateRunResponse {
-        run_id: run_id.clone(),
-        stream_url: format!("/api/uar/runs/{}/stream", run_id),
-    })
+        .start_run(
+            req.artifact,
+            req.input,
+            req.session_id,
+            None,
+            tenant_id,
+            req.options,
+        )
+        .await
+        .map_err(|e| (e.status_code(), e.to_string()))?;
+    Ok(Json(CreateRunResponse::new(run_id)))
+}
+
+#[derive(Deserialize)]
+struct ChainRunRequest {
+    chain: Vec<ChainStep>,
+    input: String,
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+/// POST /chains/run - Run agents in sequence; stream via the chain's ID
+async fn run_chain(
+    State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
+    Json(req): Json<ChainRunRequest>,
+) -> Result<Json<CreateRunResponse>, (StatusCode, String)> {
+    let tenant_id = tenant_scope(tenant.as_deref()).map(str::to_string);
+    let run_id = manager
+        .start_chained_run(req.chain, req.input, req.session_id, tenant_id)
+        .await
+        .map_err(chain_error)?;
+    Ok(Json(CreateRunResponse::new(run_id)))
+}
+
+/// Rejected runs keep their own status; anything else is a bad chain definition.
+fn chain_error(e: anyhow::Error) -> (StatusCode, String) {
+    let status = e
+        .downcast_ref::<StartRunError>()
+        .map_or(StatusCode::BAD_REQUEST, StartRunError::status_code);
+    (status, format!("{:#}", e))
+}
+
+/// Fail with 404 unless the run exists and is visible to `tenant`, as for
+/// [`get_run`].
+async fn require_run(
+    manager: &RunManager,
+    run_id: &str,
+    tenant: Option<&TenantContext>,
+) -> Result<(), (StatusCode, String)> {
+    manager
+        .find_run(run_id, tenant_scope(tenant))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(drop)
+        .ok_or((StatusCode::NOT_FOUND, format!("Run '{}' not found", run_id)))
 }
 
 async fn stream_run(
     State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
     Path(run_id): Path<String>,
-) -> impl IntoResponse {
-    let rx = match manager.subscribe(&run_id).await {
-        Some(rx) => rx,
-        None => return axum::http::StatusCode::NOT_FOUND.into_response(),
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    require_run(&manager, &run_id, tenant.as_deref()).await?;
+    // A reconnecting EventSource sends the ID of the last event it received
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+
+    let Some(events) = manager.resume_stream(&run_id, last_event_id).await else {
+        return Err((StatusCode::NOT_FOUND, format!("Run '{}' not found", run_id)));
+    };
+
+    let stream = with_heartbeats(run_id, events.map(SseFrame::from), manager.sse_heartbeat());
+    Ok(build_sse_response(stream, manager.sse_keepalive()).into_response())
+}
+
+/// Page size of `GET /runs` when no limit is given
+const DEFAULT_RUN_PAGE_SIZE: usize = 50;
+
+#[derive(Deserialize)]
+struct ListRunsQuery {
+    session_id: Option<String>,
+    agent_id: Option<String>,
+    /// ID of the last run of the previous page
+    after: Option<String>,
+    limit: Option<usize>,
+}
+
+/// GET /runs?session_id=&agent_id=&after=&limit= - Finished runs, most recent first
+async fn list_runs(
+    State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
+    Query(query): Query<ListRunsQuery>,
+) -> Result<Json<Vec<RunRecord>>, (StatusCode, String)> {
+    manager
+        .list_runs(
+            query.session_id.as_deref(),
+            query.agent_id.as_deref(),
+            query.after.as_deref(),
+            query.limit.unwrap_or(DEFAULT_RUN_PAGE_SIZE),
+            tenant_scope(tenant.as_deref()),
+        )
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// GET /runs/{id} - A run in progress, or the archived record of a finished one
+async fn get_run(
+    State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
+    Path(run_id): Path<String>,
+) -> Result<Json<RunView>, (StatusCode, String)> {
+    manager
+        .find_run(&run_id, tenant_scope(tenant.as_deref()))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, format!("Run '{}' not found", run_id)))
+}
+
+/// GET /runs/{id}/usage - Token usage and cost of a finished run
+async fn run_usage(
+    State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
+    Path(run_id): Path<String>,
+) -> Result<Json<RunUsage>, (StatusCode, String)> {
+    require_run(&manager, &run_id, tenant.as_deref()).await?;
+    manager
+        .run_usage(&run_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!("No usage recorded for run '{}'", run_id),
+        ))
+}
+
+/// GET /runs/{id}/log - Messages sent to the LLM and its answer (admin only)
+async fn run_log(
+    State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
+    Path(run_id): Path<String>,
+) -> Result<Json<RunLog>, (StatusCode, String)> {
+    manager
+        .run_log(&run_id, tenant_scope(tenant.as_deref()))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!("No log recorded for run '{}'", run_id),
+        ))
+}
+
+/// POST /runs/{id}/resume - Continue an interactive run paused after its tool results
+async fn resume_run(
+    State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
+    Path(run_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_run(&manager, &run_id, tenant.as_deref()).await?;
+    manager
+        .resume_run(&run_id)
+        .await
+        .map_err(|e| (e.status_code(), e.to_string()))?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[derive(Deserialize)]
+struct ToolApprovalRequest {
+    tool_call_id: String,
+    approved: bool,
+}
+
+/// POST /runs/{id}/tool-approvals - Approve or deny a tool call waiting for approval
+async fn decide_tool_approval(
+    State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
+    Path(run_id): Path<String>,
+    Json(req): Json<ToolApprovalRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_run(&manager, &run_id, tenant.as_deref()).await?;
+    manager
+        .decide_tool_approval(&run_id, &req.tool_call_id, req.approved)
+        .await
+        .map_err(|e| (e.status_code(), e.to_string()))?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// POST /mcp/reload - Re-read mcp.json and apply it to the running servers
+async fn reload_mcp(
+    State(manager): State<Arc<RunManager>>,
+) -> Result<Json<ReloadSummary>, (StatusCode, String)> {
+    manager
+        .tools()
+        .reload_from_file(DEFAULT_MCP_CONFIG_PATH)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))
+}
+
+// =============================================================================
+// Skills
+// =============================================================================
+
+#[derive(serde::Serialize)]
+struct SkillVersionResponse {
+    version: String,
+    /// Whether runs currently use this version
+    active: bool,
+    skill: Skill,
+}
+
+#[derive(Deserialize)]
+struct RollbackQuery {
+    version: String,
+}
+
+/// GET /skills/metrics - Invocations and prompt overhead of each registered skill
+async fn skill_metrics(
+    State(manager): State<Arc<RunManager>>,
+) -> Json<HashMap<String, SkillStats>> {
+    Json(manager.skills().read().await.stats())
+}
+
+/// GET /skills/{id}/versions - Every known version of a skill, newest first
+async fn list_skill_versions(
+    State(manager): State<Arc<RunManager>>,
+    Path(skill_id): Path<String>,
+) -> Result<Json<Vec<SkillVersionResponse>>, (StatusCode, String)> {
+    let registry = manager.skills().read().await;
+    let history = registry.get_version_history(&skill_id).await;
+    if history.is_empty() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("Skill '{}' not found", skill_id),
+        ));
+    }
+    let active = registry
+        .get(&skill_id)
+        .and_then(|s| parse_skill_version(&s.version).ok());
+    Ok(Json(
+        history
+            .into_iter()
+            .map(|(version, skill)| SkillVersionResponse {
+                active: active.as_ref() == Some(&version),
+                version: version.to_string(),
+                skill,
+            })
+            .collect(),
+    ))
+}
+
+/// POST /skills/{id}/rollback?version= - Make an earlier version of a skill active
+async fn rollback_skill(
+    State(manager): State<Arc<RunManager>>,
+    Path(skill_id): Path<String>,
+    Query(query): Query<RollbackQuery>,
+) -> Result<Json<Skill>, (StatusCode, String)> {
+    manager
+        .skills()
+        .write()
+        .await
+        .rollback(&skill_id, &query.version)
+        .await
+        .map(Json)
+        .map_err(|e| (e.status_code(), e.to_string()))
+}
+
+// =============================================================================
+// MCP Resources & Prompts
+// =============================================================================
+
+#[derive(Deserialize)]
+struct ReadResourceQuery {
+    /// Namespaced resource ID (`server::uri`)
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct GetPromptRequest {
+    /// Namespaced prompt ID (`server::name`)
+    id: String,
+    #[serde(default)]
+    arguments: Option<JsonObject>,
+}
+
+/// GET /mcp/resources - Resources of every connected MCP server
+async fn list_mcp_resources(
+    State(manager): State<Arc<RunManager>>,
+) -> Json<Vec<NamespacedItem<Resource>>> {
+    Json(manager.tools().resources().await)
+}
+
+/// GET /mcp/resources/read?id=server::uri - Contents of one resource
+async fn read_mcp_resource(
+    State(manager): State<Arc<RunManager>>,
+    Query(query): Query<ReadResourceQuery>,
+) -> Result<Json<ReadResourceResult>, (StatusCode, String)> {
+    manager
+        .tools()
+        .read_resource(&query.id)
+        .await
+        .map(Json)
+        .map_err(mcp_item_error)
+}
+
+/// GET /mcp/prompts - Prompts of every connected MCP server
+async fn list_mcp_prompts(
+    State(manager): State<Arc<RunManager>>,
+) -> Json<Vec<NamespacedItem<Prompt>>> {
+    Json(manager.tools().prompts().await)
+}
+
+/// POST /mcp/prompts/get - Render a prompt with arguments
+async fn get_mcp_prompt(
+    State(manager): State<Arc<RunManager>>,
+    Json(req): Json<GetPromptRequest>,
+) -> Result<Json<GetPromptResult>, (StatusCode, String)> {
+    manager
+        .tools()
+        .get_prompt(&req.id, req.arguments)
+        .await
+        .map(Json)
+        .map_err(mcp_item_error)
+}
+
+/// Unknown IDs are 404s, unreachable servers 503s; anything else the server
+/// rejected.
+fn mcp_item_error(e: anyhow::Error) -> (StatusCode, String) {
+    let status = if e.is::<UnknownMcpItemError>() {
+        StatusCode::NOT_FOUND
+    } else if e.is::<ServerDownError>() {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::BAD_GATEWAY
     };
+    (status, format!("{:#}", e))
+}
+
+// =============================================================================
+// Agent Import / Export
+// =============================================================================
+
+const YAML_CONTENT_TYPE: &str = "application/x-yaml";
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    /// "json" (default) or "yaml"
+    #[serde(default)]
+    format: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct ImportAgentResponse {
+    id: String,
+    version: String,
+    title: String,
+}
+
+fn is_yaml_content_type(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    matches!(mime, "application/x-yaml" | "application/yaml" | "text/yaml")
+}
+
+/// GET /agents/{id}/export?format=yaml|json - Download an agent artifact,
+/// without its webhook secret
+async fn export_agent(
+    State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
+    Path(id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    // The built-in agent is always exportable as a starting point
+    let artifact = manager
+        .resolve_agent(&id, tenant_scope(tenant.as_deref()))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, format!("Agent '{}' not found", id)))?
+        .redacted();
+
+    let (body, content_type) = match query.format.as_deref().unwrap_or("json") {
+        "yaml" | "yml" => (artifact.to_yaml(), YAML_CONTENT_TYPE),
+        "json" => (artifact.to_json(), "application/json"),
+        other => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported format '{}': expected 'json' or 'yaml'", other),
+            ));
+        }
+    };
+    let body = body.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+}
+
+/// POST /agents/import - Save an agent artifact sent as YAML or JSON
+async fn import_agent(
+    State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<(StatusCode, Json<ImportAgentResponse>), (StatusCode, String)> {
+    let db = manager.persistence.as_ref().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Agent import requires a persistence backend".to_string(),
+    ))?;
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/json");
+    let mut artifact = if is_yaml_content_type(content_type) {
+        AgentArtifact::from_yaml(&body)
+    } else {
+        AgentArtifact::from_json(&body)
+    }
+    .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    // Imported agents belong to the caller's tenant, whatever the file says
+    let tenant_id = tenant_scope(tenant.as_deref());
+    artifact.tenant_id = tenant_id.map(str::to_string);
+
+    let errors = AgentArtifactValidator::new()
+        .with_tools(manager.tools())
+        .with_persistence(db.as_ref())
+        .with_tenant(tenant_id)
+        .for_request(manager.mcp_allowed_hosts())
+        .validate(&artifact)
+        .await;
+    if !errors.is_empty() {
+        let details: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, details.join("\n")));
+    }
+
+    db.save_agent(&artifact).await.map_err(|e| match e {
+        PersistenceError::AlreadyExists { .. } => (StatusCode::CONFLICT, e.to_string()),
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    })?;
+
+    tracing::info!("Imported agent: {} ({})", artifact.metadata.title, artifact.id);
+    Ok((
+        StatusCode::CREATED,
+        Json(ImportAgentResponse {
+            id: artifact.id,
+            version: artifact.version,
+            title: artifact.metadata.title,
+        }),
+    ))
+}
+
+#[derive(Deserialize)]
+struct AgentTestRequest {
+    cases: Vec<AgentTestCase>,
+    /// Cases run at the same time; capped at `MAX_TEST_CONCURRENCY`
+    #[serde(default)]
+    concurrency: Option<usize>,
+    /// Time budget of all cases; capped at `MAX_SUITE_TIMEOUT`
+    #[serde(default)]
+    timeout_secs: Option<u64>,
+}
+
+#[derive(serde::Serialize)]
+struct AgentTestResponse {
+    agent_id: String,
+    passed: usize,
+    failed: usize,
+    results: Vec<AgentTestResult>,
+}
+
+/// POST /agents/{id}/test - Run sample inputs against an agent and check
+/// its answers and tool calls
+async fn test_agent(
+    State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
+    Path(id): Path<String>,
+    Json(req): Json<AgentTestRequest>,
+) -> Result<Json<AgentTestResponse>, (StatusCode, String)> {
+    if req.cases.is_empty() || req.cases.len() > MAX_TEST_CASES {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Expected 1 to {MAX_TEST_CASES} test cases"),
+        ));
+    }
+    let tenant_id = tenant_scope(tenant.as_deref());
+    let artifact = manager
+        .resolve_agent(&id, tenant_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, format!("Agent '{}' not found", id)))?;
+
+    let concurrency = req
+        .concurrency
+        .unwrap_or(DEFAULT_TEST_CONCURRENCY)
+        .min(MAX_TEST_CONCURRENCY);
+    let timeout = req
+        .timeout_secs
+        .map_or(DEFAULT_SUITE_TIMEOUT, Duration::from_secs)
+        .min(MAX_SUITE_TIMEOUT);
+    let results =
+        run_agent_tests(&manager, &artifact, req.cases, tenant_id, concurrency, timeout).await;
+
+    let passed = results.iter().filter(|result| result.passed).count();
+    Ok(Json(AgentTestResponse {
+        agent_id: artifact.id,
+        passed,
+        failed: results.len() - passed,
+        results,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::registry::McpRegistry;
+    use crate::session::SessionStore;
+    use crate::uar::{
+        defaults::default_agent,
+        domain::runs::RunStatus,
+        persistence::{PersistenceLayer, testing::InMemoryPersistence},
+        runtime::{matching::VectorMatcher, skills::SkillRegistry},
+        security::claims::{ADMIN_ROLE, UserClaims, UserContext},
+        testing::{StubEmbedder, llm_settings},
+    };
+    use axum::body::Body;
+    use tokio::sync::RwLock;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_yaml_content_types() {
+        assert!(is_yaml_content_type("application/x-yaml"));
+        assert!(is_yaml_content_type("application/yaml; charset=utf-8"));
+        assert!(!is_yaml_content_type("application/json"));
+    }
+
+    /// Streams a fixed answer in Chat Completions SSE format.
+    async fn mock_completion() -> impl IntoResponse {
+        let chunk = |delta: serde_json::Value, finish: Option<&str>| {
+            let choice = serde_json::json!({ "index": 0, "delta": delta, "finish_reason": finish });
+            format!("data: {}\n\n", serde_json::json!({ "choices": [choice] }))
+        };
+        let body = [
+            chunk(serde_json::json!({ "content": "Hello from the mock" }), None),
+            chunk(serde_json::json!({}), Some("stop")),
+            "data: [DONE]\n\n".to_string(),
+        ]
+        .concat();
+        ([(header::CONTENT_TYPE, "text/event-stream")], body)
+    }
+
+    fn user(roles: &[&str]) -> UserContext {
+        UserContext {
+            user_id: "auditor".to_string(),
+            claims: UserClaims {
+                sub: "auditor".to_string(),
+                name: None,
+                roles: Some(roles.iter().map(|r| r.to_string()).collect()),
+                exp: 0,
+                tenant_id: None,
+            },
+        }
+    }
+
+    /// A manager with in-memory persistence, calling the mock LLM.
+    async fn mock_manager() -> RunManager {
+        let app = Router::new().route("/v1/chat/completions", post(mock_completion));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let settings = llm_settings(format!("http://{addr}"), "mock-model");
+        let db: Arc<dyn PersistenceLayer> = Arc::new(InMemoryPersistence::new());
+        RunManager::new(
+            settings,
+            Arc::new(McpRegistry::new_empty()),
+            SessionStore::new(),
+            Arc::new(RwLock::new(SkillRegistry::new(None, None))),
+            Arc::new(VectorMatcher::with_provider(0.75, Arc::new(StubEmbedder::new(vec![0.0; 4])))),
+            Some(db),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_run_log_is_recorded_for_admins() {
+        let manager = Arc::new(mock_manager().await.with_run_logging(true));
+
+        let run_id = manager
+            .start_run(
+                default_agent(),
+                "Say hello".to_string(),
+                None,
+                None,
+                Some("acme".to_string()),
+                RunOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        let log = tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                if let Some(log) = manager.run_log(&run_id, Some("acme")).await.unwrap() {
+                    return log;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("no run log within 30 seconds");
+        assert_eq!(log.agent_id, "default-agent");
+        assert_eq!(log.tenant_id.as_deref(), Some("acme"));
+        assert_eq!(log.response_text, "Hello from the mock");
+        assert_eq!(log.message_history[0]["role"], "system");
+        assert_eq!(log.message_history.last().unwrap()["content"], "Say hello");
+        assert!(log.tool_calls.is_empty());
+
+        let router = build_router().with_state(Arc::clone(&manager));
+        let uri = format!("/runs/{run_id}/log");
+        let get = |user: Option<UserContext>, tenant: &str| {
+            let tenant = TenantContext {
+                tenant_id: tenant.to_string(),
+            };
+            let mut request = axum::http::Request::get(&uri).extension(tenant);
+            if let Some(user) = user {
+                request = request.extension(user);
+            }
+            router.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        assert_eq!(get(None, "acme").await.unwrap().status(), StatusCode::FORBIDDEN);
+        let viewer = get(Some(user(&["viewer"])), "acme").await.unwrap();
+        assert_eq!(viewer.status(), StatusCode::FORBIDDEN);
+        // Admins of other tenants don't see it
+        let other = get(Some(user(&[ADMIN_ROLE])), "globex").await.unwrap();
+        assert_eq!(other.status(), StatusCode::NOT_FOUND);
+
+        let response = get(Some(user(&[ADMIN_ROLE])), "acme").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let served: RunLog = serde_json::from_slice(&body).unwrap();
+        assert_eq!(served.run_id, run_id);
+        assert_eq!(served.response_text, log.response_text);
+    }
+
+    #[tokio::test]
+    async fn test_finished_runs_are_archived() {
+        let manager = mock_manager().await.with_replay_grace(Duration::ZERO);
+        let manager = Arc::new(manager);
+        let run_id = manager
+            .start_run(
+                default_agent(),
+                "Say hello".to_string(),
+                None,
+                None,
+                Some("acme".to_string()),
+                RunOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        // Finished runs leave the active runs once the replay grace is over
+        tokio::time::timeout(Duration::from_secs(30), async {
+            while manager.get_run(&run_id).await.is_some() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("run still active after 30 seconds");
+
+        let router = build_router().with_state(Arc::clone(&manager));
+        let get = |uri: String, tenant: &str| {
+            let tenant = TenantContext {
+                tenant_id: tenant.to_string(),
+            };
+            let request = axum::http::Request::get(uri).extension(tenant);
+            router.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let response = get(format!("/runs/{run_id}"), "acme").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let record: RunRecord = serde_json::from_slice(&body).unwrap();
+        assert_eq!(record.run.run_id, run_id);
+        assert_eq!(record.run.status, RunStatus::Done);
+        assert_eq!(record.accumulated_content, "Hello from the mock");
+        assert!(record.tool_calls_log.is_empty());
+
+        let session_id = record.run.conversation_id.unwrap();
+        let response = get(format!("/runs?session_id={session_id}"), "acme").await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let listed: Vec<RunRecord> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].run.run_id, run_id);
+
+        // Other tenants see neither
+        let response = get(format!("/runs/{run_id}"), "globex").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_runs_are_not_controlled_by_other_tenants() {
+        let manager = Arc::new(mock_manager().await);
+        let run_id = manager
+            .start_run(
+                default_agent(),
+                "Say hello".to_string(),
+                None,
+                None,
+                Some("acme".to_string()),
+                RunOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        let router = build_router().with_state(Arc::clone(&manager));
+        let send = |method: &str, path: &str, tenant: &str| {
+            let tenant = TenantContext {
+                tenant_id: tenant.to_string(),
+            };
+            let request = axum::http::Request::builder()
+                .method(method)
+                .uri(format!("/runs/{run_id}{path}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .extension(tenant)
+                .body(Body::from(r#"{"tool_call_id":"call_1","approved":true}"#))
+                .unwrap();
+            router.clone().oneshot(request)
+        };
+
+        for (method, path) in [
+            ("GET", "/stream"),
+            ("GET", "/usage"),
+            ("POST", "/resume"),
+            ("POST", "/tool-approvals"),
+        ] {
+            let response = send(method, path, "globex").await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{method} {path}");
+        }
+        let response = send("GET", "/stream", "acme").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_import_keeps_agents_of_other_tenants() {
+        let manager = Arc::new(mock_manager().await);
+        let router = build_router().with_state(Arc::clone(&manager));
+        let import = |tenant: &str, title: &str| {
+            let mut agent = default_agent();
+            agent.id = "imported-agent".to_string();
+            agent.metadata.title = title.to_string();
+            let tenant = TenantContext {
+                tenant_id: tenant.to_string(),
+            };
+            let request = axum::http::Request::post("/agents/import")
+                .header(header::CONTENT_TYPE, "application/json")
+                .extension(tenant)
+                .body(Body::from(agent.to_json().unwrap()))
+                .unwrap();
+            router.clone().oneshot(request)
+        };
+
+        let response = import("acme", "Acme agent").await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        // Another tenant can't take over the ID, but the owner can update it
+        let response = import("globex", "Globex agent").await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = import("acme", "Acme agent v2").await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let db = manager.persistence.as_ref().unwrap();
+        let agent = db.load_agent("imported-agent", Some("acme")).await.unwrap();
+        assert_eq!(agent.unwrap().metadata.title, "Acme agent v2");
+        let agent = db.load_agent("imported-agent", Some("globex")).await.unwrap();
+        assert!(agent.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_export_leaves_out_the_webhook_secret() {
+        let manager = Arc::new(mock_manager().await);
+        let mut agent = default_agent();
+        agent.id = "hooked-agent".to_string();
+        agent.runtime.webhook_url = Some("https://hooks.example.com/runs".to_string());
+        agent.runtime.webhook_secret = Some("hunter2".to_string());
+        let db = manager.persistence.as_ref().unwrap();
+        db.save_agent(&agent).await.unwrap();
+
+        let router = build_router().with_state(Arc::clone(&manager));
+        for format in ["json", "yaml"] {
+            let uri = format!("/agents/hooked-agent/export?format={format}");
+            let request = axum::http::Request::get(uri).body(Body::empty()).unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body = String::from_utf8(body.to_vec()).unwrap();
+            assert!(body.contains("hooks.example.com"), "{body}");
+            assert!(!body.contains("hunter2"), "{body}");
+        }
+        // The stored agent keeps signing its webhooks
+        let stored = db.load_agent("hooked-agent", None).await.unwrap().unwrap();
+        assert_eq!(stored.runtime.webhook_secret.as_deref(), Some("hunter2"));
+    }
+
+    #[tokio::test]
+    async fn test_requests_cannot_start_stdio_mcp_servers() {
+        use crate::mcp::config::{McpConfig, McpServerEntry};
+
+        let manager = Arc::new(mock_manager().await);
+        let router = build_router().with_state(Arc::clone(&manager));
+        let mut agent = default_agent();
+        agent.id = "shell-agent".to_string();
+        let shell = McpServerEntry::Stdio {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), "touch /tmp/pwned".to_string()],
+            env: HashMap::new(),
+            require_approval: vec![],
+            tool_limits: HashMap::new(),
+        };
+        agent.tools.mcp = Some(McpConfig {
+            mcp_servers: [("shell".to_string(), shell)].into(),
+        });
+        let post = |uri: &str, body: String| {
+            let tenant = TenantContext {
+                tenant_id: "acme".to_string(),
+            };
+            let request = axum::http::Request::post(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .extension(tenant)
+                .body(Body::from(body))
+                .unwrap();
+            router.clone().oneshot(request)
+        };
+
+        let run = serde_json::json!({ "artifact": agent, "input": "hi" }).to_string();
+        let response = post("/runs", run).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let response = post("/agents/import", agent.to_json().unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // The same servers are fine when the operator stored the agent
+        let db = manager.persistence.as_ref().unwrap();
+        db.save_agent(&agent).await.unwrap();
+        manager.check_request_artifact(&agent, Some("acme")).await.unwrap();
+        // ...but not once the request changes them
+        agent.tools.mcp.as_mut().unwrap().mcp_servers.clear();
+        agent.tools.mcp.as_mut().unwrap().mcp_servers.insert(
+            "other".to_string(),
+            McpServerEntry::Stdio {
+                command: "sh".to_string(),
+                args: vec![],
+                env: HashMap::new(),
+                require_approval: vec![],
+                tool_limits: HashMap::new(),
+            },
+        );
+        let err = manager.check_request_artifact(&agent, Some("acme")).await.unwrap_err();
+        assert!(matches!(err, StartRunError::InvalidArtifact(_)), "{err}");
+    }
+
+    /// A manager whose LLM endpoint refuses connections.
+    async fn offline_manager() -> Arc<RunManager> {
+        let settings = llm_settings("http://127.0.0.1:9", "mock-model");
+        Arc::new(
+            RunManager::new(
+                settings,
+                Arc::new(McpRegistry::new_empty()),
+                SessionStore::new(),
+                Arc::new(RwLock::new(SkillRegistry::new(None, None))),
+                Arc::new(VectorMatcher::with_provider(
+                    0.75,
+                    Arc::new(StubEmbedder::new(vec![0.0; 4])),
+                )),
+                None,
+            )
+            .await,
+        )
+    }
+
+    fn skill(skill_id: &str, version: &str, keywords: &[&str], prompt_overlay: &str) -> Skill {
+        use crate::uar::domain::skills::{SkillConstraints, SkillTriggers};
+        Skill {
+            skill_id: skill_id.to_string(),
+            version: version.to_string(),
+            title: "Triage".to_string(),
+            description: format!("Triage {version}"),
+            triggers: SkillTriggers {
+                keywords: keywords.iter().map(ToString::to_string).collect(),
+                semantic: None,
+                regex_patterns: None,
+            },
+            prompt_overlay: prompt_overlay.to_string(),
+            preferred_tools: vec![],
+            tool_choice: None,
+            mcp_config: None,
+            constraints: SkillConstraints::default(),
+            upgrade_path: None,
+            dependencies: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skill_versions_and_rollback() {
+        let manager = offline_manager().await;
+        for version in ["1.0", "1.1", "2.0"] {
+            let skill = skill("triage", version, &[], "");
+            manager.skills().write().await.register(skill).await.unwrap();
+        }
+
+        let router = build_router().with_state(Arc::clone(&manager));
+        let send = |request: axum::http::request::Builder| {
+            router.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+        let json = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let response = send(axum::http::Request::get("/skills/triage/versions")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let versions = json(response).await;
+        let listed: Vec<_> = versions
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| (v["version"].as_str().unwrap(), v["active"].as_bool().unwrap()))
+            .collect();
+        assert_eq!(listed, [("2.0.0", true), ("1.1.0", false), ("1.0.0", false)]);
+
+        let response = send(axum::http::Request::post("/skills/triage/rollback?version=1.1"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["description"], "Triage 1.1");
+        assert_eq!(manager.skills().read().await.get("triage").unwrap().version, "1.1");
+
+        let response = send(axum::http::Request::post("/skills/triage/rollback?version=9.0"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = send(axum::http::Request::post("/skills/triage/rollback?version=x"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = send(axum::http::Request::get("/skills/missing/versions")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_skill_metrics_count_invocations() {
+        let manager = offline_manager().await;
+        {
+            let mut skills = manager.skills().write().await;
+            skills
+                .register(skill("triage", "1.0", &["escalate"], "Sort by severity."))
+                .await
+                .unwrap();
+            skills
+                .register(skill("translate", "1.0", &["in french"], "Answer in French."))
+                .await
+                .unwrap();
+        }
+
+        for _ in 0..2 {
+            manager
+                .start_run(
+                    default_agent(),
+                    "Please escalate this ticket".to_string(),
+                    None,
+                    None,
+                    None,
+                    RunOptions::default(),
+                )
+                .await
+                .unwrap();
+        }
 
-    // Convert Broadcast Receiver to Stream
-    let stream = BroadcastStream::new(rx).filter_map(|res: Result<NormalizedEvent, _>| res.ok());
+        let response = build_router()
+            .with_state(Arc::clone(&manager))
+            .oneshot(
+                axum::http::Request::get("/skills/metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
 
-    build_sse_response(stream).into_response()
+        let triage = &stats["triage"];
+        assert_eq!(triage["invocations_total"], 2);
+        assert_eq!(triage["version"], "1.0");
+        // "\n\n[SKILL: Triage]\n" plus the overlay
+        assert_eq!(triage["avg_prompt_overhead_bytes"], 18 + "Sort by severity.".len());
+        assert!(triage["last_triggered_at"].is_string(), "{stats}");
+        assert_eq!(stats["translate"]["invocations_total"], 0);
+        assert!(stats["translate"]["last_triggered_at"].is_null());
+    }
 }
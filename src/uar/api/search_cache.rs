@@ -0,0 +1,91 @@
+//! Response cache for `GET /{id}/search`.
+//!
+//! Search links get opened over and over (shared, bookmarked, previewed in
+//! chat), so identical searches within the TTL are answered from memory
+//! without embedding the query or touching the store. Bounded in size with
+//! least-recently-used eviction; results can be up to one TTL stale.
+
+use std::time::Duration;
+
+use super::knowledge::SearchResponse;
+use crate::cache::TtlLruCache;
+
+/// Prometheus counter for cache hits.
+const CACHE_HITS_METRIC: &str = "uar_kb_search_cache_hits_total";
+/// Prometheus counter for cache misses.
+const CACHE_MISSES_METRIC: &str = "uar_kb_search_cache_misses_total";
+
+/// What a cached search was run with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SearchKey {
+    /// Tenants never see each other's cached results
+    tenant_id: Option<String>,
+    kb_id: String,
+    query: String,
+    limit: usize,
+    /// Bits of the `f32` threshold, which isn't `Hash` itself
+    min_score: u32,
+}
+
+impl SearchKey {
+    pub fn new(
+        tenant_id: Option<&str>,
+        kb_id: &str,
+        query: &str,
+        limit: usize,
+        min_score: f32,
+    ) -> Self {
+        Self {
+            tenant_id: tenant_id.map(str::to_string),
+            kb_id: kb_id.to_string(),
+            query: query.to_string(),
+            limit,
+            min_score: min_score.to_bits(),
+        }
+    }
+}
+
+/// Bounded cache of search responses that expire after a TTL.
+pub type SearchCache = TtlLruCache<SearchKey, SearchResponse>;
+
+impl SearchCache {
+    /// Default maximum number of cached responses.
+    pub const DEFAULT_MAX_ENTRIES: usize = 1_000;
+    /// Default time a response is served from the cache.
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(60);
+}
+
+impl Default for SearchCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_ENTRIES, Self::DEFAULT_TTL)
+            .with_metrics(CACHE_HITS_METRIC, CACHE_MISSES_METRIC)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::api::knowledge::SearchResult;
+
+    fn response(content: &str) -> SearchResponse {
+        SearchResponse {
+            results: vec![SearchResult {
+                content: content.to_string(),
+                score: 1.0,
+                metadata: serde_json::Value::Null,
+                document_id: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_other_tenants_and_thresholds_are_other_searches() {
+        let cache = SearchCache::default();
+        let key = SearchKey::new(None, "kb-1", "a", 5, 0.7);
+
+        cache.insert(key.clone(), response("a"));
+        assert!(cache.get(&key).is_some());
+        assert!(cache.get(&SearchKey::new(Some("acme"), "kb-1", "a", 5, 0.7)).is_none());
+        assert!(cache.get(&SearchKey::new(None, "kb-1", "a", 5, 0.5)).is_none());
+    }
+}
@@ -1,17 +1,63 @@
+use crate::normalized::sequenced_payload;
 use crate::uar::domain::events::NormalizedEvent;
+use crate::uar::runtime::replay::SequencedEvent;
 use axum::response::sse::{Event, Sse};
 use futures::{Stream, StreamExt};
+use std::borrow::Borrow;
 use std::convert::Infallible;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 
-pub fn build_sse_response<S>(stream: S) -> Sse<impl Stream<Item = Result<Event, Infallible>> + Send>
+/// An event to send, with the SSE `id:` a client can resume after.
+///
+/// The ID is also sent as `seq` in the payload. Heartbeats and other
+/// per-connection events have no ID.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseFrame {
+    pub id: Option<u64>,
+    pub event: NormalizedEvent,
+}
+
+impl From<NormalizedEvent> for SseFrame {
+    fn from(event: NormalizedEvent) -> Self {
+        Self { id: None, event }
+    }
+}
+
+impl From<SequencedEvent> for SseFrame {
+    fn from(sequenced: SequencedEvent) -> Self {
+        Self {
+            id: Some(sequenced.id),
+            event: sequenced.event,
+        }
+    }
+}
+
+impl Borrow<NormalizedEvent> for SseFrame {
+    fn borrow(&self) -> &NormalizedEvent {
+        &self.event
+    }
+}
+
+/// Serve `stream` as SSE, with a keepalive comment after every `keepalive`
+/// without an event.
+pub fn build_sse_response<S, T>(
+    stream: S,
+    keepalive: Duration,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>> + Send>
 where
-    S: Stream<Item = NormalizedEvent> + Send + 'static,
+    S: Stream<Item = T> + Send + 'static,
+    T: Into<SseFrame>,
 {
-    let stream = stream.map(|event| {
-        let json = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
+    let stream = stream.map(|frame| {
+        let SseFrame { id, event } = frame.into();
+        let json = sequenced_payload(&event, id).unwrap_or_else(|_| "{}".to_string());
 
         let mut sse_event = Event::default().data(json);
+        if let Some(id) = id {
+            sse_event = sse_event.id(id.to_string());
+        }
 
         // Add event type if needed for client routing (e.g. HTMX sse-swap)
         // For general usage, we might just use 'message' or inspect payload
@@ -19,13 +65,274 @@ where
             sse_event = sse_event.event("error");
         } else if let NormalizedEvent::RunDone { .. } = event {
             sse_event = sse_event.event("done");
+        } else if let NormalizedEvent::Usage { .. } = event {
+            sse_event = sse_event.event("usage");
+        } else if let NormalizedEvent::Heartbeat { .. } = event {
+            sse_event = sse_event.event("heartbeat");
+        } else if let NormalizedEvent::RunPhase { .. } = event {
+            sse_event = sse_event.event("phase");
         } else {
             sse_event = sse_event.event("message");
         }
 
-        Ok(sse_event)
+        sse_event
+    });
+
+    Sse::new(with_keepalive(stream, keepalive))
+}
+
+/// Send a `: keepalive` comment whenever `events` is idle for `interval`.
+///
+/// Load balancers close connections that carry no bytes for a while, which
+/// happens during slow tool calls. Comments have no `id:`, so they don't
+/// move a client's `Last-Event-ID`.
+fn with_keepalive<S>(
+    events: S,
+    interval: Duration,
+) -> impl Stream<Item = Result<Event, Infallible>> + Send
+where
+    S: Stream<Item = Event> + Send + 'static,
+{
+    async_stream::stream! {
+        let mut events = Box::pin(events);
+        let start = tokio::time::Instant::now() + interval;
+        let mut ticker = tokio::time::interval_at(start, interval);
+
+        loop {
+            let event = tokio::select! {
+                event = events.next() => {
+                    let Some(event) = event else { break };
+                    ticker.reset();
+                    event
+                }
+                _ = ticker.tick() => Event::default().comment("keepalive"),
+            };
+            yield Ok(event);
+        }
+    }
+}
+
+/// Interleave `Heartbeat` events into a run's event stream.
+///
+/// A task spawned per connection forwards `events` and emits a heartbeat
+/// every `interval`, so proxies don't drop the connection during long LLM
+/// calls. It stops after the `RunDone` of `run_id` (a chain's stream also
+/// carries events of its steps' runs) or once the client disconnects.
+/// Heartbeats only exist on this connection: they never reach the run's
+/// broadcast channel or the persisted event log.
+pub fn with_heartbeats<S, T>(
+    run_id: String,
+    events: S,
+    interval: Duration,
+) -> impl Stream<Item = T> + Send + 'static
+where
+    S: Stream<Item = T> + Send + 'static,
+    T: From<NormalizedEvent> + Borrow<NormalizedEvent> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(32);
+
+    tokio::spawn(async move {
+        let mut events = Box::pin(events);
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+
+        loop {
+            tokio::select! {
+                event = events.next() => {
+                    let Some(event) = event else { break };
+                    let done = matches!(
+                        event.borrow(),
+                        NormalizedEvent::RunDone { run_id: done, .. } if *done == run_id
+                    );
+                    if tx.send(event).await.is_err() || done {
+                        break;
+                    }
+                }
+                _ = ticker.tick() => {
+                    let heartbeat = NormalizedEvent::Heartbeat {
+                        run_id: run_id.clone(),
+                        timestamp_ms: now_ms(),
+                    };
+                    if tx.send(heartbeat.into()).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
     });
 
-    Sse::new(stream)
-        .keep_alive(axum::response::sse::KeepAlive::new().interval(Duration::from_secs(15)))
+    ReceiverStream::new(rx)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, response::IntoResponse, routing::get};
+
+    #[tokio::test]
+    async fn test_heartbeat_on_slow_run() {
+        // A run that never produces an event
+        let app = Router::new().route(
+            "/stream",
+            get(|| async {
+                let events = with_heartbeats(
+                    "run-1".to_string(),
+                    futures::stream::pending(),
+                    Duration::from_secs(1),
+                );
+                build_sse_response(events, Duration::from_secs(15)).into_response()
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let response = reqwest::get(format!("http://{addr}/stream")).await.unwrap();
+        let mut body = response.bytes_stream();
+        let mut received = String::new();
+        let frame = tokio::time::timeout(Duration::from_secs(20), async {
+            loop {
+                let chunk = body.next().await.unwrap().unwrap();
+                received.push_str(&String::from_utf8_lossy(&chunk));
+                if let Some(end) = received.find("\n\n") {
+                    return received[..end].to_string();
+                }
+            }
+        })
+        .await
+        .expect("no heartbeat within 20 seconds");
+
+        assert!(frame.contains("event: heartbeat"), "{frame}");
+        assert!(frame.contains(r#""type":"Heartbeat""#), "{frame}");
+        assert!(frame.contains(r#""run_id":"run-1""#), "{frame}");
+    }
+
+    #[tokio::test]
+    async fn test_sequenced_frames_carry_ids() {
+        let frames = [
+            SseFrame::from(SequencedEvent {
+                id: 7,
+                event: NormalizedEvent::ChatDelta {
+                    run_id: "run-1".to_string(),
+                    text_delta: "Hi".to_string(),
+                },
+            }),
+            SseFrame::from(NormalizedEvent::Heartbeat {
+                run_id: "run-1".to_string(),
+                timestamp_ms: 0,
+            }),
+        ];
+        let stream = futures::stream::iter(frames);
+        let response = build_sse_response(stream, Duration::from_secs(15)).into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8_lossy(&body);
+        let frames: Vec<&str> = body.split("\n\n").collect();
+
+        assert!(frames[0].lines().any(|line| line == "id: 7"), "{body}");
+        assert!(!frames[1].contains("id:"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn test_run_event_ids_strictly_increase() {
+        let sender = crate::uar::runtime::replay::RunEventSender::new(100);
+        for text in ["a", "b", "c", "d"] {
+            let _ = sender.send(NormalizedEvent::ChatDelta {
+                run_id: "run-1".to_string(),
+                text_delta: text.to_string(),
+            });
+        }
+        let _ = sender.send(NormalizedEvent::RunDone {
+            run_id: "run-1".to_string(),
+            usage: None,
+        });
+
+        let events = sender.resume(None).take(5).map(SseFrame::from);
+        let response = build_sse_response(events, Duration::from_secs(15)).into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8_lossy(&body);
+
+        let mut last = 0;
+        for frame in body.split("\n\n").filter(|f| !f.is_empty()) {
+            let id: u64 = frame
+                .lines()
+                .find_map(|line| line.strip_prefix("id: "))
+                .expect("every run event has an id")
+                .parse()
+                .unwrap();
+            assert!(id > last, "{body}");
+            last = id;
+
+            let data = frame.lines().find_map(|line| line.strip_prefix("data: ")).unwrap();
+            let payload: serde_json::Value = serde_json::from_str(data).unwrap();
+            assert_eq!(payload["seq"], id);
+        }
+        assert_eq!(last, 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_keepalive_comment_while_idle() {
+        let first = SseFrame::from(SequencedEvent {
+            id: 1,
+            event: NormalizedEvent::ChatDelta {
+                run_id: "run-1".to_string(),
+                text_delta: "Hi".to_string(),
+            },
+        });
+        // One event, then a long silence
+        let stream = futures::stream::iter([first]).chain(futures::stream::pending());
+        let response = build_sse_response(stream, Duration::from_secs(5)).into_response();
+        let mut body = response.into_body().into_data_stream();
+
+        let mut frames = Vec::new();
+        for _ in 0..2 {
+            let chunk = body.next().await.unwrap().unwrap();
+            frames.push(String::from_utf8_lossy(&chunk).into_owned());
+        }
+
+        assert!(frames[0].contains("id: 1\n"), "{frames:?}");
+        assert_eq!(frames[1], ": keepalive\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_heartbeats_stop_after_run_done() {
+        let events = futures::stream::iter(vec![NormalizedEvent::RunDone {
+            run_id: "run-1".to_string(),
+            usage: None,
+        }])
+        .chain(futures::stream::pending());
+        let stream = with_heartbeats("run-1".to_string(), events, Duration::from_secs(3600));
+
+        let received: Vec<NormalizedEvent> =
+            tokio::time::timeout(Duration::from_secs(5), stream.collect())
+                .await
+                .expect("stream should end after RunDone");
+        assert_eq!(received.len(), 1);
+        assert!(matches!(received[0], NormalizedEvent::RunDone { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_heartbeats_continue_past_other_runs_done() {
+        let done = |run_id: &str| NormalizedEvent::RunDone {
+            run_id: run_id.to_string(),
+            usage: None,
+        };
+        let events = futures::stream::iter(vec![done("step-1"), done("chain-1")])
+            .chain(futures::stream::pending());
+        let stream = with_heartbeats("chain-1".to_string(), events, Duration::from_secs(3600));
+
+        let received: Vec<NormalizedEvent> =
+            tokio::time::timeout(Duration::from_secs(5), stream.collect())
+                .await
+                .expect("stream should end after the chain's RunDone");
+        assert_eq!(received, [done("step-1"), done("chain-1")]);
+    }
 }
@@ -0,0 +1,212 @@
+//! WebSocket alternative to the SSE run stream.
+//!
+//! `GET /ws` upgrades to a socket carrying JSON text frames. The client
+//! sends:
+//! - `{"type": "start", "artifact": {...}, "input": "...", "session_id": "..."}`
+//!   to start a run, with the same fields as `POST /runs`. Follow-up turns
+//!   may omit `artifact` and `session_id` to continue with the previous
+//!   run's agent and conversation.
+//! - `{"type": "cancel"}` to stop the run in progress.
+//!
+//! The server answers with the run's [`NormalizedEvent`]s, serialized as on
+//! the SSE stream, up to and including `RunDone`. One run streams at a time
+//! per socket; protocol errors are sent as `Error` events. A run still
+//! streaming when the socket closes is cancelled.
+
+use axum::{
+    Extension,
+    extract::{
+        State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::Response,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::uar::{
+    domain::{artifact::AgentArtifact, events::NormalizedEvent, runs::RunOptions},
+    runtime::manager::RunManager,
+    security::claims::{TenantContext, tenant_scope},
+};
+
+/// Message from the client.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Start(StartRun),
+    Cancel,
+}
+
+#[derive(Debug, Deserialize)]
+struct StartRun {
+    /// Defaults to the agent of the socket's previous run
+    #[serde(default)]
+    artifact: Option<Box<AgentArtifact>>,
+    input: String,
+    /// Defaults to the conversation of the socket's previous run
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(flatten)]
+    options: RunOptions,
+}
+
+/// Run events streaming to the socket.
+type ActiveRun = (String, broadcast::Receiver<NormalizedEvent>);
+
+/// Agent and conversation of the socket's last run, reused by follow-ups.
+#[derive(Debug, Default)]
+struct Conversation {
+    artifact: Option<AgentArtifact>,
+    session_id: Option<String>,
+}
+
+impl Conversation {
+    async fn start(
+        &mut self,
+        manager: &RunManager,
+        request: StartRun,
+        tenant_id: Option<String>,
+    ) -> Result<ActiveRun, NormalizedEvent> {
+        let artifact = match request.artifact {
+            Some(artifact) => *artifact,
+            None => self.artifact.clone().ok_or_else(|| {
+                error_event("", "missing_artifact", "The first run needs an artifact")
+            })?,
+        };
+        let session_id = request.session_id.or_else(|| self.session_id.clone());
+        manager
+            .check_request_artifact(&artifact, tenant_id.as_deref())
+            .await
+            .map_err(|e| error_event("", e.code(), e.to_string()))?;
+
+        let (run_id, events) = manager
+            .start_run_streaming(
+                artifact.clone(),
+                request.input,
+                session_id,
+                None,
+                tenant_id,
+                request.options,
+            )
+            .await
+            .map_err(|e| error_event("", "run_rejected", e.to_string()))?;
+
+        self.session_id = manager
+            .get_run(&run_id)
+            .await
+            .and_then(|run| run.conversation_id);
+        self.artifact = Some(artifact);
+        Ok((run_id, events))
+    }
+}
+
+/// GET /ws - Start runs and stream their events over a WebSocket
+pub async fn chat_socket(
+    ws: WebSocketUpgrade,
+    State(manager): State<Arc<RunManager>>,
+    tenant: Option<Extension<TenantContext>>,
+) -> Response {
+    let tenant_id = tenant_scope(tenant.as_deref()).map(str::to_string);
+    ws.on_upgrade(move |socket| serve_socket(socket, manager, tenant_id))
+}
+
+async fn serve_socket(mut socket: WebSocket, manager: Arc<RunManager>, tenant_id: Option<String>) {
+    let mut conversation = Conversation::default();
+    let mut current: Option<ActiveRun> = None;
+
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                    // Pings are answered by axum
+                    Some(Ok(_)) => continue,
+                };
+
+                let reply = match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(ClientMessage::Start(_)) if current.is_some() => Some(error_event(
+                        current_run_id(current.as_ref()),
+                        "run_in_progress",
+                        "A run is already streaming on this socket",
+                    )),
+                    Ok(ClientMessage::Start(request)) => {
+                        match conversation.start(&manager, request, tenant_id.clone()).await {
+                            Ok(run) => {
+                                current = Some(run);
+                                None
+                            }
+                            Err(event) => Some(event),
+                        }
+                    }
+                    Ok(ClientMessage::Cancel) => match &current {
+                        Some((run_id, _)) => {
+                            manager.cancel_run(run_id).await;
+                            None
+                        }
+                        None => Some(error_event("", "no_run", "No run is in progress")),
+                    },
+                    Err(e) => Some(error_event(
+                        current_run_id(current.as_ref()),
+                        "invalid_message",
+                        e.to_string(),
+                    )),
+                };
+                if let Some(event) = reply
+                    && !send_event(&mut socket, &event).await
+                {
+                    break;
+                }
+            }
+            event = next_event(&mut current) => match event {
+                Ok(event) => {
+                    let done = matches!(event, NormalizedEvent::RunDone { .. });
+                    if !send_event(&mut socket, &event).await {
+                        break;
+                    }
+                    if done {
+                        current = None;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("WebSocket client missed {} run events", skipped);
+                }
+                Err(RecvError::Closed) => current = None,
+            },
+        }
+    }
+
+    // Nobody is left to stream the run to
+    if let Some((run_id, _)) = current {
+        manager.cancel_run(&run_id).await;
+    }
+}
+
+/// Next event of the active run; never resolves while no run is active.
+async fn next_event(current: &mut Option<ActiveRun>) -> Result<NormalizedEvent, RecvError> {
+    match current {
+        Some((_, events)) => events.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn current_run_id(current: Option<&ActiveRun>) -> &str {
+    current.map_or("", |(run_id, _)| run_id.as_str())
+}
+
+fn error_event(run_id: &str, code: &str, message: impl Into<String>) -> NormalizedEvent {
+    NormalizedEvent::Error {
+        run_id: run_id.to_string(),
+        code: code.to_string(),
+        message: message.into(),
+        request_id: None,
+    }
+}
+
+/// Send `event` as a JSON text frame; `false` once the client is gone.
+async fn send_event(socket: &mut WebSocket, event: &NormalizedEvent) -> bool {
+    let json = serde_json::to_string(event).unwrap_or_else(|_| "{}".to_string());
+    socket.send(Message::Text(json.into())).await.is_ok()
+}
@@ -1,3 +1,4 @@
+use crate::llm::GenerationParams;
 use crate::uar::domain::artifact::*;
 use std::collections::HashMap;
 
@@ -17,24 +18,33 @@ pub fn default_agent() -> AgentArtifact {
         runtime: AgentRuntimeConfig {
             entry: "default".to_string(),
             protocols: HashMap::new(),
+            webhook_url: None,
+            webhook_secret: None,
         },
         policy: AgentPolicy {
             provider: ProviderPolicy {
+                // Runs on the server's configured model
                 default: ProviderSelection {
                     provider: "openai".to_string(),
-                    model: "gpt-4o".to_string(),
+                    model: String::new(),
+                    base_url: None,
+                    api_key_env: None,
                 },
                 fallbacks: vec![],
+                generation: GenerationParams::default(),
             },
             tools: ToolPolicy {
                 allow: vec!["*".to_string()],
                 deny: vec![],
                 max_concurrent: 1,
+                max_per_turn: 10,
+                tool_choice: None,
             },
             skills: SkillPolicy {
                 prefer: vec![],
                 max_active: 3,
             },
+            rate_limit: None,
         },
         schemas: AgentSchemas {
             inputs: None,
@@ -47,17 +57,23 @@ pub fn default_agent() -> AgentArtifact {
             Always provide clear, concise, and accurate information."
                 .to_string(),
             instructions: vec![],
+            variables: HashMap::new(),
         },
         memory: AgentMemoryConfig {
             conversation: ConversationMemory { enabled: true },
             kb: KbMemory::default(),
         },
-        tools: AgentToolConfig { bundles: vec![] },
+        tools: AgentToolConfig {
+            bundles: vec![],
+            mcp: None,
+        },
         ui: AgentUiConfig {
             forms: FeatureFlag::default(),
             artifacts: ArtifactsConfig::default(),
         },
+        chain: None,
         extensions: HashMap::new(),
+        tenant_id: None,
     }
 }
 
@@ -82,7 +98,7 @@ pub async fn ensure_default_knowledge_base(
 
     // Check if default KB already exists
     if let Some(existing) = persistence
-        .get_knowledge_base_by_name(DEFAULT_KB_NAME)
+        .get_knowledge_base_by_name(DEFAULT_KB_NAME, None)
         .await?
     {
         tracing::debug!("Default knowledge base already exists: {}", existing.id);
@@ -116,10 +132,13 @@ pub async fn ensure_default_knowledge_base(
             vector_dimensions: cfg.vector_dimensions,
             file_processor: cfg.file_processor.clone(),
             chunk_strategy,
+            rerank: cfg.rerank.clone(),
+            extraction_strategy: cfg.extraction_strategy.clone(),
         }
     } else {
         KbConfig::default()
     };
+    crate::uar::rag::embedding::validate_kb_dimensions(&kb_config)?;
 
     let now = chrono::Utc::now().to_rfc3339();
     let kb = KnowledgeBase {
@@ -127,6 +146,7 @@ pub async fn ensure_default_knowledge_base(
         name: DEFAULT_KB_NAME.to_string(),
         description: Some("Default knowledge base for general documents".to_string()),
         config: kb_config,
+        tenant_id: None,
         created_at: now.clone(),
         updated_at: now,
     };
@@ -1,7 +1,13 @@
+use crate::llm::{GenerationParams, LlmSettings, Provider, ToolChoice};
+use crate::mcp::config::{McpConfig, McpServerEntry};
+use crate::mcp::registry::McpRegistry;
+use crate::uar::persistence::PersistenceLayer;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentArtifact {
     pub version: String,
     pub kind: String, // must be "agent"
@@ -14,11 +20,68 @@ pub struct AgentArtifact {
     pub memory: AgentMemoryConfig,
     pub tools: AgentToolConfig,
     pub ui: AgentUiConfig,
+    /// Agents to run in sequence, each fed the previous one's output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain: Option<Vec<ChainStep>>,
     #[serde(default)]
     pub extensions: HashMap<String, serde_json::Value>,
+    /// Owning tenant; `None` is shared with every tenant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// File suffix of agent artifacts auto-imported from disk.
+pub const AGENT_FILE_SUFFIX: &str = ".agent.yaml";
+
+impl AgentArtifact {
+    /// Parse an artifact from YAML.
+    pub fn from_yaml(s: &str) -> Result<Self> {
+        serde_yaml::from_str(s).context("Invalid agent artifact YAML")
+    }
+
+    /// Serialize the artifact as YAML.
+    pub fn to_yaml(&self) -> Result<String> {
+        serde_yaml::to_string(self).context("Failed to serialize agent artifact as YAML")
+    }
+
+    /// Parse an artifact from JSON.
+    pub fn from_json(s: &str) -> Result<Self> {
+        serde_json::from_str(s).context("Invalid agent artifact JSON")
+    }
+
+    /// Serialize the artifact as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize agent artifact as JSON")
+    }
+
+    /// The artifact without its secrets (`runtime.webhook_secret`), as sent
+    /// to clients.
+    #[must_use]
+    pub fn redacted(mut self) -> Self {
+        self.runtime.webhook_secret = None;
+        self
+    }
+
+    /// Prefix of the servers in `tools.mcp`, so their tools can't collide
+    /// with global or skill tools.
+    pub fn mcp_server_prefix(&self) -> String {
+        format!("agent-{}__", self.id)
+    }
+
+    /// The agent's own MCP servers, renamed under [`Self::mcp_server_prefix`].
+    pub fn mcp_config(&self) -> Option<McpConfig> {
+        let config = self.tools.mcp.as_ref()?;
+        let prefix = self.mcp_server_prefix();
+        let mcp_servers = config
+            .mcp_servers
+            .iter()
+            .map(|(name, entry)| (format!("{prefix}{name}"), entry.clone()))
+            .collect();
+        Some(McpConfig { mcp_servers })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentMetadata {
     pub title: String,
     pub description: String,
@@ -30,53 +93,173 @@ pub struct AgentMetadata {
     pub icon: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentRuntimeConfig {
     pub entry: String,
     #[serde(default)]
     pub protocols: HashMap<String, ProtocolConfig>,
+    /// Called with a `POST` when a run of this agent finishes or fails
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// Key for the `X-UAR-Signature-256` HMAC of webhook bodies; stored, but
+    /// never exported (see [`AgentArtifact::redacted`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_secret: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProtocolConfig {
     pub enabled: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentPolicy {
     pub provider: ProviderPolicy,
     pub tools: ToolPolicy,
     pub skills: SkillPolicy,
+    /// Limit on how often this agent may be run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<AgentRateLimit>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Run budget of one agent, independent of the server-wide rate limit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentRateLimit {
+    /// Sustained runs allowed per minute
+    pub requests_per_minute: u32,
+    /// Runs allowed in a burst (defaults to `requests_per_minute`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+    /// Give each user their own budget instead of sharing one per agent
+    #[serde(default)]
+    pub per_user: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProviderPolicy {
     pub default: ProviderSelection,
     #[serde(default)]
     pub fallbacks: Vec<ProviderSelection>,
+    /// Generation parameters for this agent (`temperature`,
+    /// `reasoning_effort`, ...), overriding the server's `LLM_*` settings
+    #[serde(flatten)]
+    pub generation: GenerationParams,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// LLM an agent runs on.
+///
+/// Unset fields fall back to the server's LLM settings, so an agent can
+/// change only the model, or point at an entirely different endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProviderSelection {
+    /// One of [`KNOWN_PROVIDERS`]
     pub provider: String,
+    /// Model to run; empty uses the server's model
+    #[serde(default)]
     pub model: String,
+    /// Endpoint to call instead of the server's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Environment variable holding the endpoint's API key
+    ///
+    /// Read when a run starts; the key itself never lives in the artifact.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Provider names an agent may select.
+pub const KNOWN_PROVIDERS: &[&str] = &[
+    "openai",
+    "azure",
+    "openrouter",
+    "together",
+    "groq",
+    "bedrock",
+    "openai_compatible",
+    "generic",
+];
+
+impl ProviderSelection {
+    /// `base` with this selection applied.
+    ///
+    /// A different endpoint never gets the server's API key: it needs its
+    /// own `api_key_env`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `api_key_env` names an unset variable.
+    pub fn resolve_settings(&self, base: &LlmSettings) -> Result<LlmSettings, ValidationError> {
+        let mut settings = base.clone();
+        if let Some(base_url) = &self.base_url {
+            settings.provider = Provider::detect_from_url(base_url);
+            settings.base_url.clone_from(base_url);
+            settings.api_key = None;
+        }
+        if let Some(var) = &self.api_key_env {
+            let key = std::env::var(var)
+                .ok()
+                .filter(|key| !key.trim().is_empty())
+                .ok_or_else(|| {
+                    ValidationError::new(
+                        "policy.provider.default.api_key_env",
+                        format!("environment variable '{var}' is not set"),
+                    )
+                })?;
+            settings.api_key = Some(key);
+        }
+        if !self.model.trim().is_empty() {
+            settings.model.clone_from(&self.model);
+        }
+        if let Provider::Bedrock { model_id, .. } = &mut settings.provider {
+            model_id.clone_from(&settings.model);
+        }
+        Ok(settings)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolPolicy {
+    /// Tools the agent may use; empty allows none, `*` allows all
     #[serde(default)]
     pub allow: Vec<String>,
+    /// Tools the agent may not use, even if allowed
     #[serde(default)]
     pub deny: Vec<String>,
     #[serde(default = "default_max_concurrent")]
     pub max_concurrent: u32,
+    /// Tool calls executed per model turn; the model's further calls are
+    /// answered with an error asking it to repeat them later
+    #[serde(default = "default_max_per_turn")]
+    pub max_per_turn: u32,
+    /// Whether the model must call a tool; unset leaves it to the provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+}
+
+impl ToolPolicy {
+    /// Whether the agent may see and call `tool` (a namespaced name).
+    ///
+    /// Patterns match either the namespaced name (`tavily__search`) or the
+    /// server's own tool name (`search`); a deny match always wins.
+    pub fn permits(&self, tool: &str) -> bool {
+        let bare = tool.split_once("__").map(|(_, name)| name);
+        let matches = |pattern: &String| {
+            tool_pattern_matches(pattern, tool)
+                || bare.is_some_and(|name| tool_pattern_matches(pattern, name))
+        };
+        !self.deny.iter().any(matches) && self.allow.iter().any(matches)
+    }
 }
 
 fn default_max_concurrent() -> u32 {
     1
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+fn default_max_per_turn() -> u32 {
+    10
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SkillPolicy {
     #[serde(default)]
     pub prefer: Vec<String>,
@@ -88,7 +271,7 @@ fn default_max_active() -> u32 {
     3
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentSchemas {
     #[serde(default)]
     pub inputs: Option<serde_json::Value>,
@@ -98,14 +281,18 @@ pub struct AgentSchemas {
     pub state: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentPrompt {
     pub system: String,
     #[serde(default)]
     pub instructions: Vec<String>,
+    /// Values for custom `{{variables}}` in `system`; a run request's own
+    /// `variables` override these.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentMemoryConfig {
     #[serde(default)]
     pub conversation: ConversationMemory,
@@ -113,7 +300,7 @@ pub struct AgentMemoryConfig {
     pub kb: KbMemory,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConversationMemory {
     #[serde(default)]
     pub enabled: bool,
@@ -125,7 +312,7 @@ impl Default for ConversationMemory {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct KbMemory {
     #[serde(default)]
     pub enabled: bool,
@@ -134,15 +321,42 @@ pub struct KbMemory {
     pub knowledge_bases: Vec<String>,
     #[serde(default)]
     pub citation_required: bool,
+    /// Only mark injected chunks as cited when the answer actually used them
+    #[serde(default)]
+    pub cite_only_used: bool,
+    /// Minimum answer/chunk similarity for a chunk to count as used
+    #[serde(default = "default_citation_threshold")]
+    pub citation_threshold: f32,
+}
+
+fn default_citation_threshold() -> f32 {
+    0.5
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Default for KbMemory {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            knowledge_bases: Vec::new(),
+            citation_required: false,
+            cite_only_used: false,
+            citation_threshold: default_citation_threshold(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentToolConfig {
     #[serde(default)]
     pub bundles: Vec<ToolBundle>,
+    /// MCP servers started for each run of this agent and stopped when it
+    /// ends, in the `mcpServers` format of the global configuration. Their
+    /// tools are namespaced `agent-{id}__{server}__{tool}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mcp: Option<McpConfig>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolBundle {
     pub id: String,
     #[serde(default)]
@@ -151,7 +365,22 @@ pub struct ToolBundle {
     pub required: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// One step of an agent chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainStep {
+    pub agent_id: String,
+    /// Handlebars template for the step's input. Receives
+    /// `previous_output` (the initial input for the first step), `run_id`
+    /// and `session_id`.
+    #[serde(default = "default_input_template")]
+    pub input_template: String,
+}
+
+fn default_input_template() -> String {
+    "{{ previous_output }}".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentUiConfig {
     #[serde(default)]
     pub forms: FeatureFlag,
@@ -159,14 +388,671 @@ pub struct AgentUiConfig {
     pub artifacts: ArtifactsConfig,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct FeatureFlag {
     pub enabled: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct ArtifactsConfig {
     pub enabled: bool,
     #[serde(default)]
     pub preferred_types: Vec<String>,
 }
+
+/// Highest `policy.tools.max_concurrent` an artifact may request.
+pub const MAX_CONCURRENT_TOOLS: u32 = 32;
+/// Highest `policy.tools.max_per_turn` an artifact may request.
+pub const MAX_TOOL_CALLS_PER_TURN: u32 = 128;
+
+/// A problem with one field of an [`AgentArtifact`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+    /// Dotted path of the offending field, e.g. `policy.tools.allow[1]`.
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub(crate) fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Checks an [`AgentArtifact`] before it is saved or run.
+///
+/// Structural rules always apply. Tool names are only checked when a
+/// registry is supplied, knowledge base names only when a persistence
+/// layer is, and MCP servers only for artifacts sent with a request.
+#[derive(Default)]
+pub struct AgentArtifactValidator<'a> {
+    tools: Option<&'a McpRegistry>,
+    persistence: Option<&'a dyn PersistenceLayer>,
+    tenant_id: Option<&'a str>,
+    /// Hosts remote MCP servers may use, set for artifacts sent with a request
+    mcp_allowed_hosts: Option<&'a [String]>,
+}
+
+impl fmt::Debug for AgentArtifactValidator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentArtifactValidator")
+            .field("tools", &self.tools.map(|r| r.tools().len()))
+            .field("persistence", &self.persistence)
+            .field("tenant_id", &self.tenant_id)
+            .field("mcp_allowed_hosts", &self.mcp_allowed_hosts)
+            .finish()
+    }
+}
+
+impl<'a> AgentArtifactValidator<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check `policy.tools.allow` against the tools in `registry`.
+    #[must_use]
+    pub fn with_tools(mut self, registry: &'a McpRegistry) -> Self {
+        self.tools = Some(registry);
+        self
+    }
+
+    /// Check `memory.kb.knowledge_bases` against the stored knowledge bases.
+    #[must_use]
+    pub fn with_persistence(mut self, persistence: &'a dyn PersistenceLayer) -> Self {
+        self.persistence = Some(persistence);
+        self
+    }
+
+    /// Resolve knowledge bases as seen by `tenant_id`.
+    #[must_use]
+    pub fn with_tenant(mut self, tenant_id: Option<&'a str>) -> Self {
+        self.tenant_id = tenant_id;
+        self
+    }
+
+    /// Treat the artifact as sent with a request: reject its stdio MCP
+    /// servers, and remote ones on hosts not in `mcp_allowed_hosts`.
+    #[must_use]
+    pub fn for_request(mut self, mcp_allowed_hosts: &'a [String]) -> Self {
+        self.mcp_allowed_hosts = Some(mcp_allowed_hosts);
+        self
+    }
+
+    /// Validate `artifact`, returning every problem found (empty when valid).
+    pub async fn validate(&self, artifact: &AgentArtifact) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+
+        if artifact.kind != "agent" {
+            errors.push(ValidationError::new(
+                "kind",
+                format!("expected 'agent', got '{}'", artifact.kind),
+            ));
+        }
+        for (field, value) in [
+            ("id", &artifact.id),
+            ("version", &artifact.version),
+            ("metadata.title", &artifact.metadata.title),
+            ("runtime.entry", &artifact.runtime.entry),
+            ("prompt.system", &artifact.prompt.system),
+        ] {
+            if value.trim().is_empty() {
+                errors.push(ValidationError::new(field, "must not be empty"));
+            }
+        }
+
+        if let Some(webhook_url) = &artifact.runtime.webhook_url {
+            let valid = url::Url::parse(webhook_url)
+                .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+            if !valid {
+                errors.push(ValidationError::new(
+                    "runtime.webhook_url",
+                    format!("'{webhook_url}' is not an http(s) URL"),
+                ));
+            }
+        }
+
+        let selection = &artifact.policy.provider.default;
+        if !KNOWN_PROVIDERS.contains(&selection.provider.as_str()) {
+            errors.push(ValidationError::new(
+                "policy.provider.default.provider",
+                format!(
+                    "unknown provider '{}', expected one of: {}",
+                    selection.provider,
+                    KNOWN_PROVIDERS.join(", ")
+                ),
+            ));
+        }
+        if let Some(base_url) = &selection.base_url {
+            let valid = url::Url::parse(base_url)
+                .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+            if !valid {
+                errors.push(ValidationError::new(
+                    "policy.provider.default.base_url",
+                    format!("'{base_url}' is not an http(s) URL"),
+                ));
+            }
+        }
+        if let Some(var) = &selection.api_key_env {
+            let valid = !var.is_empty()
+                && var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !var.starts_with(|c: char| c.is_ascii_digit());
+            if !valid {
+                errors.push(ValidationError::new(
+                    "policy.provider.default.api_key_env",
+                    format!("'{var}' is not an environment variable name"),
+                ));
+            }
+        }
+
+        let max_concurrent = artifact.policy.tools.max_concurrent;
+        if !(1..=MAX_CONCURRENT_TOOLS).contains(&max_concurrent) {
+            errors.push(ValidationError::new(
+                "policy.tools.max_concurrent",
+                format!("must be between 1 and {MAX_CONCURRENT_TOOLS}, got {max_concurrent}"),
+            ));
+        }
+
+        let max_per_turn = artifact.policy.tools.max_per_turn;
+        if !(1..=MAX_TOOL_CALLS_PER_TURN).contains(&max_per_turn) {
+            errors.push(ValidationError::new(
+                "policy.tools.max_per_turn",
+                format!("must be between 1 and {MAX_TOOL_CALLS_PER_TURN}, got {max_per_turn}"),
+            ));
+        }
+
+        if let Some(limit) = &artifact.policy.rate_limit {
+            if limit.requests_per_minute == 0 {
+                errors.push(ValidationError::new(
+                    "policy.rate_limit.requests_per_minute",
+                    "must be at least 1",
+                ));
+            }
+            if limit.burst == Some(0) {
+                errors.push(ValidationError::new(
+                    "policy.rate_limit.burst",
+                    "must be at least 1",
+                ));
+            }
+        }
+
+        if let Some(allowed_hosts) = self.mcp_allowed_hosts {
+            errors.extend(request_mcp_errors(artifact, allowed_hosts));
+        }
+
+        if let Some(registry) = self.tools {
+            let own_tools = artifact.mcp_server_prefix();
+            for (i, pattern) in artifact.policy.tools.allow.iter().enumerate() {
+                // "Everything" is valid even when no tools are loaded, and the
+                // agent's own servers only start with its runs
+                if pattern == "*"
+                    || (artifact.tools.mcp.is_some() && pattern.starts_with(&own_tools))
+                {
+                    continue;
+                }
+                let known = registry.tools().iter().any(|(ns_name, tool)| {
+                    tool_pattern_matches(pattern, ns_name)
+                        || tool_pattern_matches(pattern, &tool.name)
+                });
+                if !known {
+                    errors.push(ValidationError::new(
+                        format!("policy.tools.allow[{i}]"),
+                        format!("no loaded tool matches '{pattern}'"),
+                    ));
+                }
+            }
+        }
+
+        if let Some(db) = self.persistence {
+            for (i, name) in artifact.memory.kb.knowledge_bases.iter().enumerate() {
+                match db.get_knowledge_base_by_name(name, self.tenant_id).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => errors.push(ValidationError::new(
+                        format!("memory.kb.knowledge_bases[{i}]"),
+                        format!("unknown knowledge base '{name}'"),
+                    )),
+                    // Don't reject agents because the database is unreachable
+                    Err(e) => tracing::warn!("Failed to look up knowledge base '{}': {:?}", name, e),
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+/// Problems with the `tools.mcp` servers of an artifact sent with a
+/// request: it may not start processes on the host, and only reach remote
+/// servers on `allowed_hosts`.
+pub(crate) fn request_mcp_errors(
+    artifact: &AgentArtifact,
+    allowed_hosts: &[String],
+) -> Vec<ValidationError> {
+    let Some(config) = &artifact.tools.mcp else {
+        return Vec::new();
+    };
+    let mut servers: Vec<_> = config.mcp_servers.iter().collect();
+    servers.sort_by_key(|(name, _)| name.as_str());
+
+    let mut errors = Vec::new();
+    for (name, entry) in servers {
+        match entry {
+            McpServerEntry::Stdio { .. } => errors.push(ValidationError::new(
+                format!("tools.mcp.mcpServers.{name}.command"),
+                "stdio MCP servers can only be configured by the operator",
+            )),
+            McpServerEntry::RemoteHttp { url, .. } => {
+                let host = url::Url::parse(url)
+                    .ok()
+                    .filter(|url| matches!(url.scheme(), "http" | "https"))
+                    .and_then(|url| url.host_str().map(str::to_string));
+                let message = match host {
+                    None => format!("'{url}' is not an http(s) URL"),
+                    Some(host) if !allowed_hosts.iter().any(|h| h.eq_ignore_ascii_case(&host)) => {
+                        format!("host '{host}' is not in security.mcp_allowed_hosts")
+                    }
+                    Some(_) => continue,
+                };
+                errors.push(ValidationError::new(
+                    format!("tools.mcp.mcpServers.{name}.url"),
+                    message,
+                ));
+            }
+        }
+    }
+    errors
+}
+
+/// Whether an allow-list entry matches a tool name.
+///
+/// `*` matches everything and a trailing `*` matches by prefix
+/// (`tavily__*`); anything else must match exactly.
+fn tool_pattern_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::domain::knowledge::{KbConfig, KnowledgeBase};
+    use crate::uar::persistence::testing::InMemoryPersistence;
+    use crate::uar::testing::llm_settings;
+
+    /// An artifact with every optional section populated.
+    fn full_artifact() -> AgentArtifact {
+        let mut artifact = crate::uar::defaults::default_agent();
+        artifact.id = "research-agent".to_string();
+        artifact.metadata.icon = Some("book".to_string());
+        artifact
+            .runtime
+            .protocols
+            .insert("a2a".to_string(), ProtocolConfig { enabled: true });
+        artifact.policy.provider.fallbacks.push(ProviderSelection {
+            provider: "anthropic".to_string(),
+            model: "claude-sonnet".to_string(),
+            base_url: Some("https://api.anthropic.com".to_string()),
+            api_key_env: Some("ANTHROPIC_API_KEY".to_string()),
+        });
+        artifact.policy.tools.deny = vec!["shell__exec".to_string()];
+        artifact.policy.rate_limit = Some(AgentRateLimit {
+            requests_per_minute: 10,
+            burst: Some(2),
+            per_user: true,
+        });
+        artifact.policy.skills.prefer = vec!["research".to_string()];
+        artifact.schemas = AgentSchemas {
+            inputs: Some(serde_json::json!({ "type": "object" })),
+            outputs: Some(serde_json::json!({ "type": "string" })),
+            state: None,
+        };
+        artifact.prompt.instructions = vec!["Cite sources.".to_string()];
+        artifact.memory.kb = KbMemory {
+            enabled: true,
+            knowledge_bases: vec!["papers".to_string()],
+            citation_required: true,
+            cite_only_used: true,
+            citation_threshold: 0.6,
+        };
+        artifact.tools.bundles.push(ToolBundle {
+            id: "search".to_string(),
+            tools: vec!["tavily__search".to_string()],
+            required: true,
+        });
+        artifact.tools.mcp = Some(McpConfig {
+            mcp_servers: [("papers".to_string(), papers_server())].into(),
+        });
+        artifact.ui.artifacts = ArtifactsConfig {
+            enabled: true,
+            preferred_types: vec!["markdown".to_string()],
+        };
+        artifact.chain = Some(vec![ChainStep {
+            agent_id: "summarizer".to_string(),
+            input_template: "Summarize:\n{{ previous_output }}".to_string(),
+        }]);
+        artifact.extensions.insert(
+            "x-team".to_string(),
+            serde_json::json!({ "owner": "research", "nested": [1, 2.5, null] }),
+        );
+        artifact
+    }
+
+    fn papers_server() -> McpServerEntry {
+        McpServerEntry::Stdio {
+            command: "papers-mcp".to_string(),
+            args: vec!["--stdio".to_string()],
+            env: HashMap::new(),
+            require_approval: vec![],
+            tool_limits: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let artifact = full_artifact();
+        let yaml = artifact.to_yaml().unwrap();
+        assert!(yaml.contains("id: research-agent"));
+
+        let parsed = AgentArtifact::from_yaml(&yaml).unwrap();
+        assert_eq!(parsed, artifact);
+    }
+
+    #[test]
+    fn test_yaml_and_json_agree() {
+        let artifact = full_artifact();
+        let from_json = AgentArtifact::from_json(&artifact.to_json().unwrap()).unwrap();
+        let from_yaml = AgentArtifact::from_yaml(&artifact.to_yaml().unwrap()).unwrap();
+        assert_eq!(from_json, from_yaml);
+    }
+
+    #[test]
+    fn test_invalid_yaml_reports_error() {
+        let err = AgentArtifact::from_yaml("kind: agent\nid: [").unwrap_err();
+        assert!(err.to_string().contains("Invalid agent artifact YAML"));
+    }
+
+    // ---- AgentArtifactValidator ----
+
+    /// A registry exposing `test__search` and a persistence layer holding
+    /// a `papers` knowledge base.
+    async fn environment() -> (McpRegistry, InMemoryPersistence) {
+        let registry = McpRegistry::new_with_test_tool("search", "Search the web");
+        let db = InMemoryPersistence::new();
+        db.save_knowledge_base(&KnowledgeBase {
+            id: "kb-1".to_string(),
+            name: "papers".to_string(),
+            description: None,
+            config: KbConfig::default(),
+            tenant_id: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        })
+        .await
+        .unwrap();
+        (registry, db)
+    }
+
+    fn valid_artifact() -> AgentArtifact {
+        let mut artifact = crate::uar::defaults::default_agent();
+        artifact.policy.tools.allow = vec!["test__search".to_string()];
+        artifact.memory.kb.knowledge_bases = vec!["papers".to_string()];
+        artifact
+    }
+
+    /// Validate `artifact` and return the fields that were flagged.
+    async fn flagged_fields(artifact: &AgentArtifact) -> Vec<String> {
+        let (registry, db) = environment().await;
+        AgentArtifactValidator::new()
+            .with_tools(&registry)
+            .with_persistence(&db)
+            .validate(artifact)
+            .await
+            .into_iter()
+            .map(|e| e.field)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_valid_artifact_passes() {
+        assert!(flagged_fields(&valid_artifact()).await.is_empty());
+        assert!(flagged_fields(&crate::uar::defaults::default_agent()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_each_rule_fires_independently() {
+        type Mutation = fn(&mut AgentArtifact);
+        let cases: [(Mutation, &str); 17] = [
+            (|a: &mut AgentArtifact| a.kind = "tool".to_string(), "kind"),
+            (|a: &mut AgentArtifact| a.id = String::new(), "id"),
+            (|a: &mut AgentArtifact| a.id = "   ".to_string(), "id"),
+            (|a: &mut AgentArtifact| a.version = String::new(), "version"),
+            (|a: &mut AgentArtifact| a.metadata.title = String::new(), "metadata.title"),
+            (|a: &mut AgentArtifact| a.runtime.entry = String::new(), "runtime.entry"),
+            (|a: &mut AgentArtifact| a.prompt.system = "\n".to_string(), "prompt.system"),
+            (|a: &mut AgentArtifact| a.policy.tools.max_concurrent = 0, "policy.tools.max_concurrent"),
+            (|a: &mut AgentArtifact| a.policy.tools.max_concurrent = 33, "policy.tools.max_concurrent"),
+            (|a: &mut AgentArtifact| a.policy.tools.max_per_turn = 0, "policy.tools.max_per_turn"),
+            (|a: &mut AgentArtifact| a.policy.tools.max_per_turn = 129, "policy.tools.max_per_turn"),
+            (
+                |a: &mut AgentArtifact| a.policy.tools.allow.push("time__now".to_string()),
+                "policy.tools.allow[1]",
+            ),
+            (
+                |a: &mut AgentArtifact| a.policy.tools.allow = vec!["tavily__*".to_string()],
+                "policy.tools.allow[0]",
+            ),
+            (
+                |a: &mut AgentArtifact| a.memory.kb.knowledge_bases.push("missing".to_string()),
+                "memory.kb.knowledge_bases[1]",
+            ),
+            (
+                |a: &mut AgentArtifact| a.policy.provider.default.provider = "acme".to_string(),
+                "policy.provider.default.provider",
+            ),
+            (
+                |a: &mut AgentArtifact| {
+                    a.policy.provider.default.base_url = Some("ftp://llm.local".to_string());
+                },
+                "policy.provider.default.base_url",
+            ),
+            (
+                |a: &mut AgentArtifact| {
+                    a.policy.provider.default.api_key_env = Some("MY-KEY".to_string());
+                },
+                "policy.provider.default.api_key_env",
+            ),
+        ];
+
+        for (mutate, field) in cases {
+            let mut artifact = valid_artifact();
+            mutate(&mut artifact);
+            assert_eq!(flagged_fields(&artifact).await, vec![field.to_string()]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reports_all_errors_with_messages() {
+        let mut artifact = valid_artifact();
+        artifact.id = String::new();
+        artifact.policy.tools.max_concurrent = 64;
+        artifact.memory.kb.knowledge_bases = vec!["missing".to_string()];
+
+        let (registry, db) = environment().await;
+        let errors = AgentArtifactValidator::new()
+            .with_tools(&registry)
+            .with_persistence(&db)
+            .validate(&artifact)
+            .await;
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "id: must not be empty",
+                "policy.tools.max_concurrent: must be between 1 and 32, got 64",
+                "memory.kb.knowledge_bases[0]: unknown knowledge base 'missing'",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_environment_checks_are_optional() {
+        let mut artifact = valid_artifact();
+        artifact.policy.tools.allow = vec!["unknown__tool".to_string()];
+        artifact.memory.kb.knowledge_bases = vec!["missing".to_string()];
+        assert!(AgentArtifactValidator::new().validate(&artifact).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_must_allow_runs() {
+        let mut artifact = valid_artifact();
+        artifact.policy.rate_limit = Some(AgentRateLimit {
+            requests_per_minute: 0,
+            burst: Some(0),
+            per_user: false,
+        });
+        assert_eq!(
+            flagged_fields(&artifact).await,
+            vec![
+                "policy.rate_limit.requests_per_minute",
+                "policy.rate_limit.burst"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_allow_all_with_no_tools() {
+        let registry = McpRegistry::new_empty();
+        let errors = AgentArtifactValidator::new()
+            .with_tools(&registry)
+            .validate(&crate::uar::defaults::default_agent())
+            .await;
+        assert!(errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_agent_mcp_servers_are_namespaced_by_agent() {
+        let artifact = full_artifact();
+        let config = artifact.mcp_config().unwrap();
+        assert_eq!(
+            config.mcp_servers.keys().collect::<Vec<_>>(),
+            ["agent-research-agent__papers"]
+        );
+        assert_eq!(crate::uar::defaults::default_agent().mcp_config(), None);
+
+        // Its own tools are only known once a run starts them
+        let mut artifact = valid_artifact();
+        artifact.tools.mcp = full_artifact().tools.mcp;
+        artifact.id = "research-agent".to_string();
+        artifact.policy.tools.allow = vec![
+            "agent-research-agent__papers__*".to_string(),
+            "agent-other__papers__*".to_string(),
+        ];
+        assert_eq!(flagged_fields(&artifact).await, ["policy.tools.allow[1]"]);
+    }
+
+    #[tokio::test]
+    async fn test_request_artifacts_only_reach_allowed_mcp_hosts() {
+        let remote = |url: &str| McpServerEntry::RemoteHttp {
+            url: url.to_string(),
+            env: HashMap::new(),
+            require_approval: vec![],
+            tool_limits: HashMap::new(),
+        };
+        let mut artifact = crate::uar::defaults::default_agent();
+        artifact.tools.mcp = Some(McpConfig {
+            mcp_servers: [
+                ("papers".to_string(), papers_server()),
+                ("search".to_string(), remote("https://MCP.example.com/sse")),
+                ("metadata".to_string(), remote("http://169.254.169.254/")),
+                ("files".to_string(), remote("file:///etc/passwd")),
+            ]
+            .into(),
+        });
+        // Stored and operator artifacts may run anything
+        assert!(AgentArtifactValidator::new().validate(&artifact).await.is_empty());
+
+        let allowed = vec!["mcp.example.com".to_string()];
+        let errors = AgentArtifactValidator::new()
+            .for_request(&allowed)
+            .validate(&artifact)
+            .await;
+        let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "tools.mcp.mcpServers.files.url: 'file:///etc/passwd' is not an http(s) URL",
+                "tools.mcp.mcpServers.metadata.url: host '169.254.169.254' is not in \
+                 security.mcp_allowed_hosts",
+                "tools.mcp.mcpServers.papers.command: stdio MCP servers can only be \
+                 configured by the operator",
+            ]
+        );
+    }
+
+    fn server_settings() -> LlmSettings {
+        LlmSettings {
+            api_key: Some("sk-server".to_string()),
+            protocol: crate::llm::LlmProtocol::Auto,
+            provider: Provider::OpenAI,
+            ..llm_settings("https://api.openai.com", "gpt-4o-mini")
+        }
+    }
+
+    #[test]
+    fn test_selection_overrides_server_settings() {
+        let mut selection = crate::uar::defaults::default_agent().policy.provider.default;
+        let settings = selection.resolve_settings(&server_settings()).unwrap();
+        assert_eq!(settings.model, "gpt-4o-mini");
+        assert_eq!(settings.api_key.as_deref(), Some("sk-server"));
+
+        selection.model = "llama3".to_string();
+        selection.base_url = Some("http://localhost:11434".to_string());
+        let settings = selection.resolve_settings(&server_settings()).unwrap();
+        assert_eq!(settings.model, "llama3");
+        assert_eq!(settings.base_url, "http://localhost:11434");
+        assert_eq!(settings.provider, Provider::Generic);
+        // The server's key stays with the server's endpoint
+        assert_eq!(settings.api_key, None);
+
+        selection.api_key_env = Some("UAR_TEST_UNSET_AGENT_KEY".to_string());
+        let err = selection.resolve_settings(&server_settings()).unwrap_err();
+        assert_eq!(err.field, "policy.provider.default.api_key_env");
+    }
+
+    #[test]
+    fn test_tool_patterns() {
+        assert!(tool_pattern_matches("*", "time__now"));
+        assert!(tool_pattern_matches("time__*", "time__now"));
+        assert!(tool_pattern_matches("time__now", "time__now"));
+        assert!(!tool_pattern_matches("time__now", "time__nowish"));
+        assert!(!tool_pattern_matches("tavily__*", "time__now"));
+    }
+
+    #[test]
+    fn test_tool_policy_permits() {
+        let mut policy = crate::uar::defaults::default_agent().policy.tools;
+        assert!(policy.permits("tavily__search"));
+
+        policy.deny = vec!["search".to_string()];
+        assert!(!policy.permits("tavily__search"));
+        assert!(policy.permits("time__now"));
+
+        policy.allow = vec!["mirror".to_string()];
+        policy.deny.clear();
+        assert!(policy.permits("mirror"));
+        assert!(!policy.permits("tavily__search"));
+
+        policy.allow.clear();
+        assert!(!policy.permits("mirror"));
+    }
+}
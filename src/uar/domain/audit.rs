@@ -0,0 +1,98 @@
+//! Audit trail of administrative mutations.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Area of the system an audit entry belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditResource {
+    /// Knowledge bases and their documents
+    Kb,
+}
+
+impl AuditResource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Kb => "kb",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Upload,
+    Reindex,
+}
+
+/// Kind of object that was changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditTarget {
+    KnowledgeBase,
+    Document,
+}
+
+impl AuditTarget {
+    pub fn resource(self) -> AuditResource {
+        match self {
+            Self::KnowledgeBase | Self::Document => AuditResource::Kb,
+        }
+    }
+}
+
+/// One recorded mutation: who did what to which object, and when.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: String,
+    pub resource: AuditResource,
+    pub action: AuditAction,
+    /// User ID from the request's token, or `anonymous`
+    pub actor: String,
+    pub target_type: AuditTarget,
+    pub target_id: String,
+    /// Knowledge base a document belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kb_id: Option<String>,
+    /// Tenant the actor acted in; `None` for requests without a tenant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub timestamp: String, // RFC3339
+}
+
+impl AuditEntry {
+    pub fn new(
+        actor: impl Into<String>,
+        action: AuditAction,
+        target_type: AuditTarget,
+        target_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            resource: target_type.resource(),
+            action,
+            actor: actor.into(),
+            target_type,
+            target_id: target_id.into(),
+            kb_id: None,
+            tenant_id: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_kb_id(mut self, kb_id: impl Into<String>) -> Self {
+        self.kb_id = Some(kb_id.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_tenant(mut self, tenant_id: Option<&str>) -> Self {
+        self.tenant_id = tenant_id.map(str::to_string);
+        self
+    }
+}
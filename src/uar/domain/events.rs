@@ -15,6 +15,18 @@ pub enum NormalizedEvent {
         run_id: String,
         text_delta: String,
     },
+    /// The model declined to answer; sent instead of `ChatDelta`s.
+    RefusalDelta {
+        run_id: String,
+        text_delta: String,
+    },
+    /// The provider's content filter flagged the prompt or the response.
+    ContentFilter {
+        run_id: String,
+        categories: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        severity: Option<crate::normalized::ContentFilterSeverity>,
+    },
 
     Citation {
         run_id: String,
@@ -48,22 +60,110 @@ pub enum NormalizedEvent {
         artifact: ArtifactPayload,
     },
 
+    /// Running count of completion tokens, estimated while the run streams.
+    PartialUsage {
+        run_id: String,
+        completion_tokens_so_far: u32,
+    },
+
+    /// Token usage of the run so far, summed over every LLM call it made.
+    Usage {
+        run_id: String,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+        total_tokens: u32,
+    },
+
     Error {
         run_id: String,
         code: String,
         message: String,
+        /// `X-Request-Id` of the request that started the run, to quote in
+        /// support tickets
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
     },
     RunDone {
         run_id: String,
+        /// Total token usage and cost of the run, when the provider reported usage
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<super::runs::RunUsage>,
+    },
+    /// A chained run moved on to its next step.
+    ChainProgress {
+        /// Run executing this step
+        run_id: String,
+        /// 1-based step number
+        step: usize,
+        total: usize,
+        agent_id: String,
+    },
+    /// An interactive run executed its tools and waits for
+    /// `POST /runs/{id}/resume` before sending the results to the model.
+    RunPaused {
+        run_id: String,
+    },
+    /// A tool flagged as requiring approval waits for a decision on
+    /// `POST /runs/{id}/tool-approvals` before it is called.
+    ToolApprovalRequest {
+        run_id: String,
+        tool_call_id: String,
+        tool: String,
+        input: serde_json::Value,
+    },
+    /// The run was cancelled by its client; `RunDone` follows.
+    RunCancelled {
+        run_id: String,
+    },
+    /// The run moved on to `phase`; only sent when run phases are enabled.
+    RunPhase {
+        run_id: String,
+        phase: RunPhase,
+    },
+    /// Keep-alive sent on idle SSE connections.
+    ///
+    /// Generated per connection by the SSE layer; never broadcast on the
+    /// run's channel or persisted.
+    Heartbeat {
+        run_id: String,
+        /// Milliseconds since the Unix epoch
+        timestamp_ms: u64,
     },
     ContextAction(super::context::ContextAction),
 }
 
+/// Stage of a run, for clients that show progress.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunPhase {
+    /// Searching the agent's knowledge bases
+    RagRetrieval,
+    /// Choosing the skills to inject
+    SkillMatching,
+    /// Waiting on the model's output
+    Generating,
+    /// Executing tools the model called
+    ToolExecution,
+    /// Finished; `RunDone` follows
+    Done,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CitationSource {
     pub title: String,
     pub url: String,
     pub snippet: Option<String>,
+    /// Retrieval score, or similarity to the answer when usage is checked
+    #[serde(default)]
+    pub relevance: Option<f32>,
+    /// Whether the answer used this source; unused sources were available
+    /// to the model but are not cited
+    #[serde(default = "default_used")]
+    pub used: bool,
+}
+
+fn default_used() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
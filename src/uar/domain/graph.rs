@@ -4,6 +4,8 @@
 //! knowledge graph-enhanced retrieval.
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 
 // =============================================================================
 // Entity Types
@@ -87,6 +89,115 @@ pub struct Relationship {
     pub created_at: String,
 }
 
+// =============================================================================
+// Knowledge Graph
+// =============================================================================
+
+/// An entity in the graph of a knowledge base.
+///
+/// Mentions of the same name across chunks and documents are one node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphNode {
+    /// See [`GraphNode::id_for`]
+    pub id: String,
+    /// Entity name
+    pub label: String,
+    pub entity_type: EntityType,
+    /// What the extractor said about the entity (e.g. `description`)
+    #[serde(default)]
+    pub properties: serde_json::Value,
+    pub kb_id: String,
+}
+
+impl GraphNode {
+    /// ID of the node named `name` in `kb_id`, ignoring case and spacing.
+    pub fn id_for(kb_id: &str, name: &str) -> String {
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        let key = format!("{kb_id}\n{}", name.to_lowercase());
+        hex::encode(&Sha256::digest(key.as_bytes())[..16])
+    }
+}
+
+/// A directed, typed edge between two [`GraphNode`]s.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    /// See [`GraphEdge::id_for`]
+    pub id: String,
+    pub source_id: String,
+    pub target_id: String,
+    /// e.g. "works_at"
+    pub relationship_type: String,
+    /// Confidence of the relationship (0.0 - 1.0)
+    pub weight: f32,
+    pub kb_id: String,
+}
+
+impl GraphEdge {
+    /// ID of the `relationship_type` edge from `source_id` to `target_id`.
+    pub fn id_for(source_id: &str, target_id: &str, relationship_type: &str) -> String {
+        let key = format!("{source_id}\n{target_id}\n{relationship_type}");
+        hex::encode(&Sha256::digest(key.as_bytes())[..16])
+    }
+}
+
+/// Nodes of a knowledge graph and the edges between them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Subgraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl Subgraph {
+    /// The part of this graph within `depth` edges of `node_ids`, following
+    /// edges either way; no `node_ids` keeps the whole graph.
+    pub fn around(self, node_ids: &[&str], depth: usize) -> Self {
+        if node_ids.is_empty() {
+            return self;
+        }
+        let mut reached: HashSet<&str> = node_ids
+            .iter()
+            .copied()
+            .filter(|id| self.nodes.iter().any(|node| node.id == *id))
+            .collect();
+        for _ in 0..depth {
+            let next: Vec<&str> = self
+                .edges
+                .iter()
+                .filter_map(|edge| {
+                    match (
+                        reached.contains(edge.source_id.as_str()),
+                        reached.contains(edge.target_id.as_str()),
+                    ) {
+                        (true, false) => Some(edge.target_id.as_str()),
+                        (false, true) => Some(edge.source_id.as_str()),
+                        _ => None,
+                    }
+                })
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            reached.extend(next);
+        }
+
+        let reached: HashSet<String> = reached.into_iter().map(str::to_string).collect();
+        Self {
+            nodes: self
+                .nodes
+                .into_iter()
+                .filter(|node| reached.contains(&node.id))
+                .collect(),
+            edges: self
+                .edges
+                .into_iter()
+                .filter(|edge| {
+                    reached.contains(&edge.source_id) && reached.contains(&edge.target_id)
+                })
+                .collect(),
+        }
+    }
+}
+
 // =============================================================================
 // Community
 // =============================================================================
@@ -181,3 +292,60 @@ impl Default for ExtractionResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            label: id.to_string(),
+            entity_type: EntityType::Concept,
+            properties: serde_json::Value::Null,
+            kb_id: "kb-1".to_string(),
+        }
+    }
+
+    fn edge(source: &str, target: &str) -> GraphEdge {
+        GraphEdge {
+            id: GraphEdge::id_for(source, target, "related_to"),
+            source_id: source.to_string(),
+            target_id: target.to_string(),
+            relationship_type: "related_to".to_string(),
+            weight: 1.0,
+            kb_id: "kb-1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_node_ids_ignore_case_and_spacing() {
+        assert_eq!(
+            GraphNode::id_for("kb-1", "Ada  Lovelace"),
+            GraphNode::id_for("kb-1", "ada lovelace")
+        );
+        assert_ne!(
+            GraphNode::id_for("kb-1", "Ada Lovelace"),
+            GraphNode::id_for("kb-2", "Ada Lovelace")
+        );
+    }
+
+    #[test]
+    fn test_around_follows_edges_both_ways_up_to_depth() {
+        // a -> b <- c -> d, and e on its own
+        let graph = Subgraph {
+            nodes: ["a", "b", "c", "d", "e"].map(node).to_vec(),
+            edges: vec![edge("a", "b"), edge("c", "b"), edge("c", "d")],
+        };
+        let ids = |graph: &Subgraph| graph.nodes.iter().map(|n| n.id.clone()).collect::<Vec<_>>();
+
+        let near = graph.clone().around(&["a"], 1);
+        assert_eq!(ids(&near), ["a", "b"]);
+        assert_eq!(near.edges, [edge("a", "b")]);
+        assert_eq!(ids(&graph.clone().around(&["a"], 2)), ["a", "b", "c"]);
+        assert_eq!(graph.clone().around(&["a"], 3).edges.len(), 3);
+        assert_eq!(ids(&graph.clone().around(&["e"], 3)), ["e"]);
+        assert!(graph.clone().around(&["missing"], 1).nodes.is_empty());
+        assert_eq!(graph.clone().around(&[], 0), graph);
+    }
+}
@@ -1,6 +1,7 @@
 // Use String for ISO8601/RFC3339 to avoid chrono serde feature dominance issues
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 /// A named knowledge base container for RAG document scoping.
@@ -14,6 +15,9 @@ pub struct KnowledgeBase {
     #[serde(default)]
     pub description: Option<String>,
     pub config: KbConfig,
+    /// Owning tenant; `None` is shared with every tenant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
     pub created_at: String, // RFC3339
     pub updated_at: String, // RFC3339
 }
@@ -30,11 +34,18 @@ pub struct KbConfig {
     /// Vector dimensions (None = use model default)
     #[serde(default)]
     pub vector_dimensions: Option<usize>,
-    /// File processor: "auto", "unstructured", "mistral", "kreuzberg"
+    /// File processor: "auto", "unstructured", "mistral", "kreuzberg", "tika"
     #[serde(default = "KbConfig::default_file_processor")]
     pub file_processor: String,
     /// Chunking strategy for document processing
     pub chunk_strategy: crate::uar::rag::chunking::ChunkingStrategy,
+    /// Optional cross-encoder reranking of search results
+    #[serde(default)]
+    pub rerank: Option<RerankerConfig>,
+    /// How entities are extracted into the knowledge graph: "llm", "spacy",
+    /// "leiden" or "none" (None = "spacy")
+    #[serde(default)]
+    pub extraction_strategy: Option<String>,
 }
 
 impl KbConfig {
@@ -62,6 +73,85 @@ impl Default for KbConfig {
             vector_dimensions: None,
             file_processor: Self::default_file_processor(),
             chunk_strategy: crate::uar::rag::chunking::ChunkingStrategy::Recursive { size: 512 },
+            rerank: None,
+            extraction_strategy: None,
+        }
+    }
+}
+
+/// Which reranker scores a knowledge base's search results.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RerankerKind {
+    /// No reranking, even if a global default is configured
+    None,
+    /// Local fastembed cross-encoder
+    CrossEncoder,
+    /// Cohere reranking API (`COHERE_API_KEY`)
+    Cohere,
+}
+
+impl RerankerKind {
+    /// Cohere model used when `model` names no Cohere model
+    pub const DEFAULT_COHERE_MODEL: &'static str = "rerank-v3.5";
+}
+
+/// Cross-encoder reranking settings for a knowledge base.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RerankerConfig {
+    /// Whether to rerank search results
+    #[serde(default = "RerankerConfig::default_enabled")]
+    pub enabled: bool,
+    /// Reranker to use (None = inferred from `model`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<RerankerKind>,
+    /// Reranker model: a local fastembed model ID, `jina-*` (Jina API), or `rerank-*` (Cohere API)
+    #[serde(default = "RerankerConfig::default_model")]
+    pub model: String,
+    /// Number of results returned after reranking
+    #[serde(default = "RerankerConfig::default_top_n")]
+    pub top_n: usize,
+}
+
+impl RerankerConfig {
+    /// Reranking is on once configured
+    pub fn default_enabled() -> bool {
+        true
+    }
+
+    /// Default reranker model (local cross-encoder)
+    pub fn default_model() -> String {
+        "BAAI/bge-reranker-base".to_string()
+    }
+
+    /// Default number of reranked results
+    pub fn default_top_n() -> usize {
+        5
+    }
+
+    /// Whether these settings rerank at all
+    pub fn is_enabled(&self) -> bool {
+        self.enabled && self.kind != Some(RerankerKind::None)
+    }
+
+    /// Model to rerank with; Cohere needs one of its `rerank-*` models
+    pub fn effective_model(&self) -> &str {
+        match self.kind {
+            Some(RerankerKind::Cohere) if !self.model.starts_with("rerank-") => {
+                RerankerKind::DEFAULT_COHERE_MODEL
+            }
+            _ => &self.model,
+        }
+    }
+}
+
+impl Default for RerankerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            kind: None,
+            model: Self::default_model(),
+            top_n: Self::default_top_n(),
         }
     }
 }
@@ -79,9 +169,59 @@ pub struct KnowledgeChunk {
     // Embedding is not typically serialized to frontend, but good to have
     #[serde(skip)]
     pub embedding: Vec<f32>,
+    /// Owning tenant; `None` is shared with every tenant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
     pub created_at: String, // RFC3339
 }
 
+impl KnowledgeChunk {
+    /// [`content_hash`] of the chunk's text.
+    pub fn content_hash(&self) -> String {
+        content_hash(&self.content)
+    }
+
+    /// Whether the chunk's metadata contains `filter` (see [`json_contains`]).
+    pub fn matches_metadata(&self, filter: &serde_json::Value) -> bool {
+        self.metadata
+            .as_ref()
+            .is_some_and(|metadata| json_contains(metadata, filter))
+    }
+}
+
+/// JSON containment as Postgres' JSONB `@>` defines it: every key of a
+/// `filter` object is in `value` with a contained value, every element of a
+/// `filter` array is contained in some element of `value`, and scalars are
+/// equal.
+pub fn json_contains(value: &serde_json::Value, filter: &serde_json::Value) -> bool {
+    use serde_json::Value;
+    match (value, filter) {
+        (Value::Object(value), Value::Object(filter)) => filter
+            .iter()
+            .all(|(key, f)| value.get(key).is_some_and(|v| json_contains(v, f))),
+        (Value::Array(value), Value::Array(filter)) => filter
+            .iter()
+            .all(|f| value.iter().any(|v| json_contains(v, f))),
+        (Value::Array(value), scalar) if !scalar.is_object() => value.contains(scalar),
+        _ => value == filter,
+    }
+}
+
+/// Hex SHA-256 of `text` with whitespace runs collapsed to single spaces and
+/// trimmed, so re-chunking the same document yields the same hashes.
+///
+/// A knowledge base holds at most one chunk per hash.
+pub fn content_hash(text: &str) -> String {
+    let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+/// Hex SHA-256 of a document's raw bytes, telling whether its file changed
+/// since it was last indexed.
+pub fn file_hash(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
 /// A search result matching a knowledge chunk.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KnowledgeMatch {
@@ -101,6 +241,19 @@ pub struct KnowledgeDocument {
     #[serde(default)]
     pub chunk_count: usize,
     pub status: DocumentStatus,
+    /// Owning tenant; `None` is shared with every tenant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    /// Extracted document metadata (title, author, ...), once indexed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    /// Key the client uploaded the document with, unique within the
+    /// knowledge base; retried uploads with it get this document back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// [`file_hash`] of the content it was last indexed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
     pub created_at: String, // RFC3339
     pub updated_at: String, // RFC3339
 }
@@ -120,3 +273,45 @@ impl Default for DocumentStatus {
         Self::Pending
     }
 }
+
+/// A step of a document's way through the ingestion pipeline.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum IngestionProgress {
+    Extracting,
+    /// The text was split into `count` chunks the knowledge base lacks
+    Chunked { count: usize },
+    /// `done` of `total` chunks have their embedding
+    Embedded { done: usize, total: usize },
+    Indexed { chunk_count: usize },
+    Failed { error: String },
+}
+
+impl IngestionProgress {
+    /// Name of the stage, as in the serialized `stage` field.
+    pub fn stage(&self) -> &'static str {
+        match self {
+            Self::Extracting => "extracting",
+            Self::Chunked { .. } => "chunked",
+            Self::Embedded { .. } => "embedded",
+            Self::Indexed { .. } => "indexed",
+            Self::Failed { .. } => "failed",
+        }
+    }
+
+    /// Whether ingestion of the document is over.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Indexed { .. } | Self::Failed { .. })
+    }
+
+    /// The final progress of a document in `status`, if it is done.
+    pub fn finished(status: &DocumentStatus, chunk_count: usize) -> Option<Self> {
+        match status {
+            DocumentStatus::Indexed => Some(Self::Indexed { chunk_count }),
+            DocumentStatus::Failed { error } => Some(Self::Failed {
+                error: error.clone(),
+            }),
+            DocumentStatus::Pending | DocumentStatus::Processing => None,
+        }
+    }
+}
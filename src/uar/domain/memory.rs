@@ -8,6 +8,9 @@ pub struct Memory {
     pub tags: Vec<String>,
     #[serde(skip)]
     pub embedding: Vec<f32>,
+    /// Owning tenant; `None` is shared with every tenant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
     pub created_at: String, // RFC3339
 }
 
@@ -1,4 +1,5 @@
 pub mod artifact;
+pub mod audit;
 pub mod context;
 pub mod events;
 pub mod graph;
@@ -7,4 +8,5 @@ pub mod matching;
 pub mod memory;
 pub mod runs;
 pub mod skills;
+pub mod tenant;
 pub mod tools;
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Run {
@@ -6,8 +7,101 @@ pub struct Run {
     pub agent_id: String,
     pub conversation_id: Option<String>,
     pub user_id: Option<String>,
+    /// Owning tenant; `None` is shared with every tenant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
     pub status: RunStatus,
     pub context: serde_json::Value,
+    /// Token usage and cost, once the run has finished
+    #[serde(default)]
+    pub usage: Option<RunUsage>,
+}
+
+/// How a run ended, as archived by `RunManager::finalize_run`.
+#[derive(Debug, Clone)]
+pub struct RunOutcome {
+    /// `Done`, `Error` or `Cancelled`
+    pub status: RunStatus,
+    /// All assistant text of the run
+    pub accumulated_content: String,
+    /// Every tool call the model made, in order
+    pub tool_calls_log: Vec<crate::llm::ToolCall>,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub duration_ms: u64,
+}
+
+/// A finished run and what it produced, kept after it leaves memory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    #[serde(flatten)]
+    pub run: Run,
+    pub accumulated_content: String,
+    pub tool_calls_log: Vec<crate::llm::ToolCall>,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub duration_ms: u64,
+    pub finished_at: String, // RFC3339
+}
+
+impl RunOutcome {
+    /// A run that failed before producing anything.
+    pub fn error(duration_ms: u64) -> Self {
+        Self {
+            status: RunStatus::Error,
+            accumulated_content: String::new(),
+            tool_calls_log: Vec::new(),
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            duration_ms,
+        }
+    }
+}
+
+impl RunRecord {
+    /// Record of `run` ending with `outcome`, finished now.
+    pub fn new(mut run: Run, outcome: RunOutcome) -> Self {
+        run.status = outcome.status;
+        Self {
+            run,
+            accumulated_content: outcome.accumulated_content,
+            tool_calls_log: outcome.tool_calls_log,
+            prompt_tokens: outcome.prompt_tokens,
+            completion_tokens: outcome.completion_tokens,
+            duration_ms: outcome.duration_ms,
+            finished_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// A run as `GET /api/uar/runs/{id}` shows it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum RunView {
+    /// Still running, or finished within the replay grace
+    Active(Run),
+    Archived(RunRecord),
+}
+
+/// Per-request settings of a run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunOptions {
+    /// Pause after each batch of tool results until the client resumes
+    #[serde(default)]
+    pub interactive_tools: bool,
+    /// Values for custom `{{variables}}` in the agent's system prompt
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, String>,
+    /// Model (or model alias) to run instead of the agent's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Seconds the run may take; can only shorten the server's run timeout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// `X-Request-Id` of the HTTP request starting the run; logged with the
+    /// run and added to its error events
+    #[serde(skip)]
+    pub request_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -20,3 +114,79 @@ pub enum RunStatus {
     Error,
     Cancelled,
 }
+
+/// Token counts of one or more LLM calls.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+impl TokenUsage {
+    /// Add another call's usage to this total.
+    pub fn add(&mut self, other: TokenUsage) {
+        self.prompt_tokens = self.prompt_tokens.saturating_add(other.prompt_tokens);
+        self.completion_tokens = self.completion_tokens.saturating_add(other.completion_tokens);
+        self.total_tokens = self.total_tokens.saturating_add(other.total_tokens);
+    }
+}
+
+/// Token usage and cost of a finished run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunUsage {
+    pub run_id: String,
+    pub agent_id: String,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    pub model: String,
+    #[serde(flatten)]
+    pub tokens: TokenUsage,
+    /// Cost in USD; `None` when the model has no configured price
+    pub cost_usd: Option<f64>,
+}
+
+/// Verbatim record of what a run sent to and received from the LLM.
+///
+/// Kept for auditability when `audit.enabled` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunLog {
+    pub run_id: String,
+    pub agent_id: String,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    pub timestamp: String, // RFC3339
+    /// Messages exactly as sent to the LLM
+    pub message_history: Vec<serde_json::Value>,
+    /// All assistant text of the run
+    pub response_text: String,
+    /// Every tool call the model made, in order
+    pub tool_calls: Vec<crate::llm::ToolCall>,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+impl RunLog {
+    /// Log of a run about to send `messages`, timestamped now.
+    pub fn new(
+        run_id: impl Into<String>,
+        agent_id: impl Into<String>,
+        tenant_id: Option<String>,
+        messages: &[crate::llm::Message],
+    ) -> Self {
+        Self {
+            run_id: run_id.into(),
+            agent_id: agent_id.into(),
+            tenant_id,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            message_history: messages
+                .iter()
+                .filter_map(|m| serde_json::to_value(m).ok())
+                .collect(),
+            response_text: String::new(),
+            tool_calls: Vec::new(),
+            prompt_tokens: 0,
+            completion_tokens: 0,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::llm::ToolChoice;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,10 +11,35 @@ pub struct Skill {
     pub prompt_overlay: String,
     #[serde(default)]
     pub preferred_tools: Vec<String>,
+    /// Tool choice while this skill is active (e.g. `required` to force
+    /// one of `preferred_tools`); overrides the agent's policy
+    #[serde(default)]
+    pub tool_choice: Option<ToolChoice>,
     #[serde(default, skip)]
     pub mcp_config: Option<crate::mcp::config::McpConfig>,
     #[serde(default)]
     pub constraints: SkillConstraints,
+    /// How to migrate from the previous version of the skill
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgrade_path: Option<String>,
+    /// IDs of skills whose overlays are injected before this skill's
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+/// Parse a skill version as semver, accepting a leading `v` and missing
+/// minor or patch numbers (`v1.2` is `1.2.0`).
+pub fn parse_skill_version(version: &str) -> Result<semver::Version, semver::Error> {
+    let version = version.trim();
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let core_end = version.find(['-', '+']).unwrap_or(version.len());
+    let (core, suffix) = version.split_at(core_end);
+    let padding = match core.matches('.').count() {
+        0 => ".0.0",
+        1 => ".0",
+        _ => "",
+    };
+    semver::Version::parse(&format!("{core}{padding}{suffix}"))
 }
 
 /// Represents the YAML frontmatter of a SKILL.md file
@@ -28,6 +54,13 @@ pub struct SkillManifest {
     pub triggers: SkillTriggers,
     #[serde(default)]
     pub tools: Vec<String>,
+    #[serde(default)]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(default)]
+    pub upgrade_path: Option<String>,
+    /// IDs of skills this one builds on
+    #[serde(default)]
+    pub dependencies: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -36,6 +69,10 @@ pub struct SkillTriggers {
     pub keywords: Vec<String>,
     #[serde(default)]
     pub semantic: Option<String>,
+    /// Regular expressions any of which matching the input triggers the
+    /// skill, e.g. `(?i)\bsql\b`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex_patterns: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -49,3 +86,19 @@ pub struct SkillMatch {
     pub skill: Skill,
     pub score: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_skill_version() {
+        let parse = |v: &str| parse_skill_version(v).unwrap().to_string();
+        assert_eq!(parse("1.2.3"), "1.2.3");
+        assert_eq!(parse("1.0"), "1.0.0");
+        assert_eq!(parse("v2"), "2.0.0");
+        assert_eq!(parse("1.1-beta.1"), "1.1.0-beta.1");
+        assert!(parse_skill_version("latest").is_err());
+        assert!(parse_skill_version("").is_err());
+    }
+}
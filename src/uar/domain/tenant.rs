@@ -0,0 +1,40 @@
+//! Tenant scoping of stored records.
+
+/// Whether a record owned by `owner` is visible to `tenant`.
+///
+/// Records without a tenant are shared with everyone; a tenant's records
+/// are only visible inside that tenant.
+pub fn visible_to(owner: Option<&str>, tenant: Option<&str>) -> bool {
+    owner.is_none() || owner == tenant
+}
+
+/// Whether a record owned by `owner` may be changed by `tenant`.
+///
+/// Only the owning tenant may change its records, and shared records only
+/// change outside any tenant.
+pub fn owned_by(owner: Option<&str>, tenant: Option<&str>) -> bool {
+    owner == tenant
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_to() {
+        assert!(visible_to(None, None));
+        assert!(visible_to(None, Some("acme")));
+        assert!(visible_to(Some("acme"), Some("acme")));
+        assert!(!visible_to(Some("acme"), Some("globex")));
+        assert!(!visible_to(Some("acme"), None));
+    }
+
+    #[test]
+    fn test_owned_by() {
+        assert!(owned_by(None, None));
+        assert!(!owned_by(None, Some("acme")));
+        assert!(owned_by(Some("acme"), Some("acme")));
+        assert!(!owned_by(Some("acme"), Some("globex")));
+        assert!(!owned_by(Some("acme"), None));
+    }
+}
@@ -0,0 +1,263 @@
+//! EPUB file processing provider.
+//!
+//! Extracts text from EPUB books locally using the `epub` crate. Chapters are
+//! read in spine (reading) order, stripped of HTML markup, and titled using
+//! the book's table of contents (`toc.ncx` / `nav.xhtml`).
+
+use super::provider::{DocumentMetadata, FileProcessor, ProcessingError, ProcessingResult};
+use async_trait::async_trait;
+use epub::doc::{EpubDoc, NavPoint};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// MIME type for EPUB documents.
+pub const EPUB_MIME_TYPE: &str = "application/epub+zip";
+
+/// Separator inserted between chapters in the extracted content.
+const SECTION_SEPARATOR: &str = "\n\n---\n\n";
+
+/// Local EPUB processor.
+///
+/// Always available (no API key or network access required), so EPUB files
+/// are never routed to an external provider.
+#[derive(Debug, Default)]
+pub struct EpubProcessor;
+
+impl EpubProcessor {
+    /// Create a new EPUB processor.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Check whether a path looks like an EPUB file based on its extension.
+    pub fn is_epub_path(path: &Path) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("epub"))
+    }
+
+    /// Extract chapters synchronously (the `epub` crate is blocking).
+    fn extract(path: &Path) -> Result<ProcessingResult, ProcessingError> {
+        let mut doc = EpubDoc::new(path).map_err(|e| {
+            ProcessingError::ProviderError(format!("Failed to open EPUB {}: {}", path.display(), e))
+        })?;
+
+        // Map chapter paths (without fragments) to their TOC labels
+        let mut titles = HashMap::new();
+        collect_toc_titles(&doc.toc, &mut titles);
+
+        let mut sections = Vec::new();
+        for page in 0..doc.get_num_pages() {
+            if !doc.set_current_page(page) {
+                continue;
+            }
+
+            let chapter_path = doc.get_current_path();
+            let Some((html, _mime)) = doc.get_current_str() else {
+                continue;
+            };
+
+            let text = strip_html(&html);
+            let title = chapter_path.and_then(|p| titles.get(&p).cloned());
+
+            let section = match title {
+                Some(title) if text.is_empty() => format!("## {title}"),
+                Some(title) => format!("## {title}\n\n{text}"),
+                None if text.is_empty() => continue,
+                None => text,
+            };
+            sections.push(section);
+        }
+
+        let metadata = serde_json::json!({
+            "title": doc.mdata("title"),
+            "authors": doc.mdata("creator"),
+            "language": doc.mdata("language"),
+            "chapters": sections.len(),
+        });
+
+        let content = sections.join(SECTION_SEPARATOR);
+        let document_metadata = DocumentMetadata {
+            title: doc.mdata("title"),
+            author: doc.mdata("creator"),
+            created_date: doc.mdata("date"),
+            language: doc.mdata("language"),
+            ..DocumentMetadata::default()
+        }
+        .with_word_count(&content);
+
+        Ok(ProcessingResult {
+            content,
+            mime_type: EPUB_MIME_TYPE.to_string(),
+            metadata: Some(metadata),
+            document_metadata,
+            images: vec![],
+        })
+    }
+}
+
+#[async_trait]
+impl FileProcessor for EpubProcessor {
+    async fn process(&self, path: &Path) -> Result<ProcessingResult, ProcessingError> {
+        let path_buf = path.to_path_buf();
+        tokio::task::spawn_blocking(move || Self::extract(&path_buf))
+            .await
+            .map_err(|e| ProcessingError::ProviderError(format!("Task join error: {}", e)))?
+    }
+
+    fn supports_mime_type(&self, mime_type: &str) -> bool {
+        mime_type == EPUB_MIME_TYPE
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "EPUB"
+    }
+}
+
+/// Flatten the navigation tree into a path -> label map.
+///
+/// The first label wins when several nav points target the same file
+/// (e.g. sub-sections addressed by fragment).
+fn collect_toc_titles(points: &[NavPoint], titles: &mut HashMap<PathBuf, String>) {
+    for point in points {
+        let content = point.content.to_string_lossy();
+        let without_fragment = content.split('#').next().unwrap_or_default();
+        let label = point.label.trim();
+        if !label.is_empty() {
+            titles
+                .entry(PathBuf::from(without_fragment))
+                .or_insert_with(|| label.to_string());
+        }
+        collect_toc_titles(&point.children, titles);
+    }
+}
+
+/// Strip HTML markup from an XHTML chapter, keeping paragraph breaks.
+fn strip_html(html: &str) -> String {
+    // Only the body carries chapter text; the head holds title/style noise
+    let lower = html.to_ascii_lowercase();
+    let body = match lower.find("<body") {
+        Some(start) => {
+            let end = lower.rfind("</body>").unwrap_or(html.len());
+            // Malformed chapters may close the body before opening it
+            if start <= end {
+                &html[start..end]
+            } else {
+                html
+            }
+        }
+        None => html,
+    };
+
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.char_indices().peekable();
+    let mut skip_until: Option<&str> = None;
+
+    while let Some((i, c)) = chars.next() {
+        if c != '<' {
+            if skip_until.is_none() {
+                out.push(c);
+            }
+            continue;
+        }
+
+        // Consume the whole tag
+        let tag_end = body[i..].find('>').map_or(body.len(), |end| i + end);
+        let tag = body[i + 1..tag_end].trim().to_ascii_lowercase();
+        while chars.peek().is_some_and(|(j, _)| *j <= tag_end) {
+            chars.next();
+        }
+
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|ch| ch.is_ascii_alphanumeric())
+            .collect();
+
+        if let Some(closing) = skip_until {
+            if tag.starts_with('/') && name == closing {
+                skip_until = None;
+            }
+            continue;
+        }
+
+        match name.as_str() {
+            "script" => skip_until = Some("script"),
+            "style" => skip_until = Some("style"),
+            "p" | "div" | "br" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+            | "blockquote" | "section" | "pre" => out.push('\n'),
+            _ => {}
+        }
+    }
+
+    let decoded = decode_entities(&out);
+
+    // Collapse whitespace within lines and blank runs between paragraphs
+    decoded
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Decode the handful of HTML entities commonly found in EPUB chapters.
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sample.epub")
+    }
+
+    #[test]
+    fn test_supports_epub() {
+        let processor = EpubProcessor::new();
+        assert!(processor.supports_mime_type("application/epub+zip"));
+        assert!(!processor.supports_mime_type("application/pdf"));
+    }
+
+    #[test]
+    fn test_is_epub_path() {
+        assert!(EpubProcessor::is_epub_path(Path::new("book.epub")));
+        assert!(EpubProcessor::is_epub_path(Path::new("BOOK.EPUB")));
+        assert!(!EpubProcessor::is_epub_path(Path::new("book.pdf")));
+    }
+
+    #[test]
+    fn test_strip_html() {
+        let html = "<html><head><title>x</title></head><body><p>A &amp; B</p><script>bad()</script><p>C</p></body></html>";
+        assert_eq!(strip_html(html), "A & B\n\nC");
+    }
+
+    #[test]
+    fn test_strip_html_with_body_closed_before_opened() {
+        let html = "<html></body><p>Stray</p><body><p>Text</p></html>";
+        assert_eq!(strip_html(html), "Stray\n\nText");
+    }
+
+    #[tokio::test]
+    async fn test_chapter_order_and_headings() {
+        let processor = EpubProcessor::new();
+        let result = processor.process(&fixture()).await.unwrap();
+
+        let sections: Vec<&str> = result.content.split(SECTION_SEPARATOR).collect();
+        assert_eq!(sections.len(), 2);
+        assert!(sections[0].starts_with("## The Beginning"));
+        assert!(sections[0].contains("It was a bright & cold day."));
+        assert!(sections[1].starts_with("## The Ending"));
+        assert!(sections[1].contains("And that was the end."));
+        assert!(!result.content.contains("var x"));
+        assert_eq!(result.mime_type, EPUB_MIME_TYPE);
+    }
+}
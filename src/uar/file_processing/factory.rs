@@ -1,17 +1,70 @@
 //! Factory for creating file processors based on configuration.
 
+use super::epub::{EPUB_MIME_TYPE, EpubProcessor};
 use super::kreuzberg::KreuzbergProvider;
 use super::local::LocalProvider;
 use super::mistral::MistralProvider;
 use super::provider::{FileProcessor, ProcessingError};
+use super::tika::TikaProvider;
 use super::unstructured::UnstructuredProvider;
-use crate::config::{FileProcessingConfig, KreuzbergConfig, MistralConfig, UnstructuredConfig};
+use super::vision::{VisionLlm, VisionProvider};
+use crate::config::{
+    FileProcessingConfig, KreuzbergConfig, MistralConfig, TikaConfig, UnstructuredConfig,
+};
+use std::io::Read;
+use std::path::Path;
 use std::sync::Arc;
 
 /// Factory for creating file processors based on configuration.
 #[derive(Debug)]
 pub struct FileProcessorFactory;
 
+/// Bytes of a file [`detect_mime_type`] looks at.
+pub const MIME_SNIFF_LEN: usize = 512;
+
+/// MIME type of a file from its magic bytes (only the first
+/// [`MIME_SNIFF_LEN`] are needed); `None` for formats without a signature,
+/// such as plain text.
+pub fn detect_mime_type(data: &[u8]) -> Option<String> {
+    infer::get(&data[..data.len().min(MIME_SNIFF_LEN)]).map(|kind| kind.mime_type().to_string())
+}
+
+/// MIME type to route the file at `path` by, given its first bytes `head`.
+///
+/// Uploads often have a wrong or missing extension, so the type the magic
+/// bytes show wins, with a warning when the extension claims another. Files
+/// without a signature (text) get their extension's type, or
+/// `application/octet-stream` unless `fallback_to_extension`.
+pub fn routing_mime_type(path: &Path, head: &[u8], fallback_to_extension: bool) -> String {
+    let by_extension = mime_guess::from_path(path).first().map(|m| m.to_string());
+    match detect_mime_type(head) {
+        Some(detected) => {
+            if let Some(claimed) = by_extension.filter(|claimed| *claimed != detected) {
+                tracing::warn!(
+                    path = %path.display(),
+                    claimed = %claimed,
+                    detected = %detected,
+                    "File content doesn't match its extension; routing by content"
+                );
+            }
+            detected
+        }
+        None if fallback_to_extension => {
+            by_extension.unwrap_or_else(|| "application/octet-stream".to_string())
+        }
+        None => "application/octet-stream".to_string(),
+    }
+}
+
+/// The first [`MIME_SNIFF_LEN`] bytes of the file at `path`.
+pub fn read_head(path: &Path) -> std::io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(MIME_SNIFF_LEN);
+    std::fs::File::open(path)?
+        .take(MIME_SNIFF_LEN as u64)
+        .read_to_end(&mut head)?;
+    Ok(head)
+}
+
 impl FileProcessorFactory {
     /// Create a file processor based on configuration.
     ///
@@ -19,9 +72,13 @@ impl FileProcessorFactory {
     ///
     /// For "auto" mode, returns the first available provider in order:
     /// 1. Kreuzberg (if configured, high-performance local processing)
-    /// 2. Unstructured.io (if API key configured or self-hosted URL)
-    /// 3. Mistral OCR (if API key configured)
-    /// 4. Local (always available, text files only)
+    /// 2. Apache Tika (if configured)
+    /// 3. Unstructured.io (if API key configured or self-hosted URL)
+    /// 4. Mistral OCR (if API key configured)
+    /// 5. Local (always available, text files, EPUB and PDF)
+    ///
+    /// The vision LLM provider is only used when asked for by name here;
+    /// [`Self::create_for_file`] also falls back to it for images.
     ///
     /// # Arguments
     ///
@@ -29,6 +86,8 @@ impl FileProcessorFactory {
     /// * `unstructured` - Optional Unstructured.io configuration
     /// * `mistral` - Optional Mistral OCR configuration
     /// * `kreuzberg` - Optional Kreuzberg configuration
+    /// * `tika` - Optional Apache Tika configuration
+    /// * `vision` - Optional LLM to transcribe images with
     ///
     /// # Returns
     ///
@@ -38,6 +97,8 @@ impl FileProcessorFactory {
         unstructured: Option<&UnstructuredConfig>,
         mistral: Option<&MistralConfig>,
         kreuzberg: Option<&KreuzbergConfig>,
+        tika: Option<&TikaConfig>,
+        vision: Option<VisionLlm<'_>>,
     ) -> Result<Arc<dyn FileProcessor>, ProcessingError> {
         match config.provider.as_str() {
             "kreuzberg" => {
@@ -48,6 +109,11 @@ impl FileProcessorFactory {
                 );
                 Ok(Arc::new(KreuzbergProvider::new(cfg)))
             }
+            "tika" => {
+                let cfg = tika.cloned().unwrap_or_default();
+                tracing::info!("Using Apache Tika at {} for file processing", cfg.server_url);
+                Ok(Arc::new(TikaProvider::new(cfg)))
+            }
             "unstructured" => {
                 let cfg = unstructured.ok_or_else(|| {
                     ProcessingError::ProviderNotConfigured(
@@ -69,8 +135,18 @@ impl FileProcessorFactory {
                 }
                 Ok(Arc::new(MistralProvider::new(cfg.clone())))
             }
+            "vision" => {
+                let llm = vision.ok_or_else(|| {
+                    ProcessingError::ProviderNotConfigured(
+                        "LLM settings required for vision OCR".to_string(),
+                    )
+                })?;
+                let provider = VisionProvider::new(llm, config.max_file_size)?;
+                tracing::info!("Using vision LLM for file processing (images only)");
+                Ok(Arc::new(provider))
+            }
             "local" => {
-                tracing::info!("Using local file processing (text files only)");
+                tracing::info!("Using local file processing (text files, EPUB and PDF)");
                 Ok(Arc::new(LocalProvider::new()))
             }
             "auto" | _ => {
@@ -85,7 +161,13 @@ impl FileProcessorFactory {
                     return Ok(Arc::new(KreuzbergProvider::new(cfg.clone())));
                 }
 
-                // 2. Unstructured.io
+                // 2. Apache Tika
+                if let Some(cfg) = tika {
+                    tracing::info!("Using Apache Tika at {} for file processing", cfg.server_url);
+                    return Ok(Arc::new(TikaProvider::new(cfg.clone())));
+                }
+
+                // 3. Unstructured.io
                 if let Some(cfg) = unstructured {
                     let provider = UnstructuredProvider::new(cfg.clone());
                     if provider.is_configured() {
@@ -94,7 +176,7 @@ impl FileProcessorFactory {
                     }
                 }
 
-                // 3. Mistral OCR
+                // 4. Mistral OCR
                 if let Some(cfg) = mistral {
                     let provider = MistralProvider::new(cfg.clone());
                     if provider.is_configured() {
@@ -103,8 +185,8 @@ impl FileProcessorFactory {
                     }
                 }
 
-                // 4. Fall back to local processing
-                tracing::info!("Using local file processing (text files only)");
+                // 5. Fall back to local processing
+                tracing::info!("Using local file processing (text files, EPUB and PDF)");
                 Ok(Arc::new(LocalProvider::new()))
             }
         }
@@ -112,35 +194,57 @@ impl FileProcessorFactory {
 
     /// Create a processor for a specific file, choosing the best provider.
     ///
-    /// This method selects a provider based on the file's MIME type,
-    /// preferring providers that explicitly support the type.
+    /// This method selects a provider based on the file's MIME type (see
+    /// [`routing_mime_type`]), preferring providers that explicitly support
+    /// the type. EPUB files are always handled locally by [`EpubProcessor`].
+    /// Images go to Kreuzberg if its OCR is enabled, else Mistral OCR, else
+    /// the vision LLM (when its model accepts images).
     pub fn create_for_file(
-        path: &std::path::Path,
+        path: &Path,
         config: &FileProcessingConfig,
         unstructured: Option<&UnstructuredConfig>,
         mistral: Option<&MistralConfig>,
         kreuzberg: Option<&KreuzbergConfig>,
+        tika: Option<&TikaConfig>,
+        vision: Option<VisionLlm<'_>>,
     ) -> Result<Arc<dyn FileProcessor>, ProcessingError> {
-        let mime_type = mime_guess::from_path(path)
-            .first_or_octet_stream()
-            .to_string();
+        // A file that can't be read yet is routed by its extension
+        let head = read_head(path).unwrap_or_default();
+        let mime_type = routing_mime_type(path, &head, config.mime_fallback_to_extension);
+
+        // EPUB never needs an external API
+        if mime_type == EPUB_MIME_TYPE {
+            return Ok(Arc::new(EpubProcessor::new()));
+        }
+
+        let is_image = mime_type.starts_with("image/");
 
-        // For complex documents, prefer Kreuzberg (if available)
+        // For complex documents, prefer Kreuzberg (if available, and for
+        // images only with OCR on)
         if let Some(cfg) = kreuzberg {
             let provider = KreuzbergProvider::new(cfg.clone());
-            if provider.supports_mime_type(&mime_type) {
+            if provider.supports_mime_type(&mime_type) && (cfg.ocr_enabled || !is_image) {
                 return Ok(Arc::new(provider));
             }
         }
 
-        // For images, prefer Mistral OCR (if API key available)
-        if mime_type.starts_with("image/") {
+        if is_image {
+            // For images, prefer Mistral OCR (if API key available)
             if let Some(cfg) = mistral {
                 let provider = MistralProvider::new(cfg.clone());
                 if provider.is_configured() && provider.supports_mime_type(&mime_type) {
                     return Ok(Arc::new(provider));
                 }
             }
+
+            // Then a vision LLM, rather than extracting nothing
+            if let Some(llm) = vision
+                && let Ok(provider) = VisionProvider::new(llm, config.max_file_size)
+                && provider.supports_mime_type(&mime_type)
+            {
+                tracing::info!("Using vision LLM to extract text from {}", path.display());
+                return Ok(Arc::new(provider));
+            }
         }
 
         // For PDFs and complex documents, prefer Unstructured
@@ -161,13 +265,24 @@ impl FileProcessorFactory {
         }
 
         // Fall back to default provider selection
-        Self::create(config, unstructured, mistral, kreuzberg)
+        Self::create(config, unstructured, mistral, kreuzberg, tika, vision)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::VisionConfig;
+    use crate::uar::testing::llm_settings;
+
+    #[test]
+    fn test_detect_mime_type() {
+        let pdf = crate::uar::file_processing::sample_pdf("Hello", "Title", "Author");
+        assert_eq!(detect_mime_type(&pdf).as_deref(), Some("application/pdf"));
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert_eq!(detect_mime_type(png).as_deref(), Some("image/png"));
+        assert_eq!(detect_mime_type(b"plain text"), None);
+    }
 
     #[test]
     fn test_create_local_provider() {
@@ -175,7 +290,7 @@ mod tests {
             provider: "local".to_string(),
             ..Default::default()
         };
-        let result = FileProcessorFactory::create(&config, None, None, None);
+        let result = FileProcessorFactory::create(&config, None, None, None, None, None);
         assert!(result.is_ok());
         assert_eq!(result.unwrap().provider_name(), "Local");
     }
@@ -187,7 +302,8 @@ mod tests {
             ..Default::default()
         };
         let kreuzberg_config = KreuzbergConfig::default();
-        let result = FileProcessorFactory::create(&config, None, None, Some(&kreuzberg_config));
+        let result =
+            FileProcessorFactory::create(&config, None, None, Some(&kreuzberg_config), None, None);
         assert!(result.is_ok());
         assert_eq!(result.unwrap().provider_name(), "Kreuzberg");
     }
@@ -198,7 +314,7 @@ mod tests {
             provider: "auto".to_string(),
             ..Default::default()
         };
-        let result = FileProcessorFactory::create(&config, None, None, None);
+        let result = FileProcessorFactory::create(&config, None, None, None, None, None);
         assert!(result.is_ok());
         assert_eq!(result.unwrap().provider_name(), "Local");
     }
@@ -210,19 +326,89 @@ mod tests {
             ..Default::default()
         };
         let kreuzberg_config = KreuzbergConfig::default();
-        let result = FileProcessorFactory::create(&config, None, None, Some(&kreuzberg_config));
+        let result =
+            FileProcessorFactory::create(&config, None, None, Some(&kreuzberg_config), None, None);
         assert!(result.is_ok());
         // When kreuzberg config is present, auto mode should prefer it
         assert_eq!(result.unwrap().provider_name(), "Kreuzberg");
     }
 
+    #[test]
+    fn test_create_for_file_epub_is_local() {
+        let config = FileProcessingConfig {
+            provider: "unstructured".to_string(),
+            ..Default::default()
+        };
+        let unstructured_config = UnstructuredConfig {
+            api_url: "http://localhost:8000".to_string(),
+            api_key: Some("test-key".to_string()),
+        };
+        let kreuzberg_config = KreuzbergConfig::default();
+        let result = FileProcessorFactory::create_for_file(
+            std::path::Path::new("book.epub"),
+            &config,
+            Some(&unstructured_config),
+            None,
+            Some(&kreuzberg_config),
+            None,
+            None,
+        );
+        assert_eq!(result.unwrap().provider_name(), "EPUB");
+    }
+
+    #[test]
+    fn test_pdf_with_text_extension_takes_the_pdf_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        let pdf = crate::uar::file_processing::sample_pdf("Hello", "Title", "Author");
+        std::fs::write(&path, pdf).unwrap();
+        assert_eq!(routing_mime_type(&path, &read_head(&path).unwrap(), true), "application/pdf");
+
+        // Unstructured takes PDFs, the local text path everything else
+        let config = FileProcessingConfig {
+            provider: "local".to_string(),
+            ..Default::default()
+        };
+        let unstructured_config = UnstructuredConfig {
+            api_url: "http://localhost:8000".to_string(),
+            api_key: Some("test-key".to_string()),
+        };
+        let create = |path: &Path, config: &FileProcessingConfig| {
+            FileProcessorFactory::create_for_file(
+                path,
+                config,
+                Some(&unstructured_config),
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap()
+            .provider_name()
+        };
+        assert_eq!(create(&path, &config), "Unstructured.io");
+
+        let text = dir.path().join("report.pdf");
+        std::fs::write(&text, "Plain text notes.").unwrap();
+        assert_eq!(create(&text, &config), "Unstructured.io");
+        let config = FileProcessingConfig {
+            mime_fallback_to_extension: false,
+            ..config
+        };
+        assert_eq!(create(&text, &config), "Local");
+        assert_eq!(
+            routing_mime_type(&text, b"Plain text notes.", false),
+            "application/octet-stream"
+        );
+    }
+
     #[test]
     fn test_create_unstructured_without_config() {
         let config = FileProcessingConfig {
             provider: "unstructured".to_string(),
             ..Default::default()
         };
-        let result = FileProcessorFactory::create(&config, None, None, None);
+        let result = FileProcessorFactory::create(&config, None, None, None, None, None);
         assert!(result.is_err());
     }
 
@@ -236,8 +422,101 @@ mod tests {
             api_url: "http://localhost:8000".to_string(),
             api_key: Some("test-key".to_string()),
         };
-        let result = FileProcessorFactory::create(&config, Some(&unstructured_config), None, None);
+        let result = FileProcessorFactory::create(
+            &config,
+            Some(&unstructured_config),
+            None,
+            None,
+            None,
+            None,
+        );
         assert!(result.is_ok());
         assert_eq!(result.unwrap().provider_name(), "Unstructured.io");
     }
+
+    #[test]
+    fn test_create_tika_provider() {
+        let config = FileProcessingConfig {
+            provider: "tika".to_string(),
+            ..Default::default()
+        };
+        let result = FileProcessorFactory::create(&config, None, None, None, None, None);
+        assert_eq!(result.unwrap().provider_name(), "Apache Tika");
+
+        // Auto mode picks Tika once it is configured
+        let config = FileProcessingConfig::default();
+        let tika_config = TikaConfig::default();
+        let result =
+            FileProcessorFactory::create(&config, None, None, None, Some(&tika_config), None);
+        assert_eq!(result.unwrap().provider_name(), "Apache Tika");
+    }
+
+    fn vision_llm(model: &str) -> crate::llm::LlmSettings {
+        llm_settings("http://localhost:9", model)
+    }
+
+    #[test]
+    fn test_create_vision_provider() {
+        let config = FileProcessingConfig {
+            provider: "vision".to_string(),
+            ..Default::default()
+        };
+        let settings = vision_llm("gpt-4o");
+        let vision_config = VisionConfig::default();
+        let vision = VisionLlm {
+            settings: &settings,
+            config: &vision_config,
+        };
+        let result = FileProcessorFactory::create(&config, None, None, None, None, Some(vision));
+        assert_eq!(result.unwrap().provider_name(), "Vision LLM");
+
+        let result = FileProcessorFactory::create(&config, None, None, None, None, None);
+        assert!(matches!(result, Err(ProcessingError::ProviderNotConfigured(_))));
+    }
+
+    #[test]
+    fn test_create_for_file_images_fall_back_to_vision() {
+        let config = FileProcessingConfig::default();
+        let scan = std::path::Path::new("scan.png");
+        let settings = vision_llm("gpt-4o");
+        let vision_config = VisionConfig::default();
+        let vision = VisionLlm {
+            settings: &settings,
+            config: &vision_config,
+        };
+        let create = |kreuzberg: Option<&KreuzbergConfig>, vision| {
+            let processor = FileProcessorFactory::create_for_file(
+                scan, &config, None, None, kreuzberg, None, vision,
+            );
+            processor.unwrap().provider_name()
+        };
+
+        // Kreuzberg OCR handles images while it is enabled
+        let ocr = KreuzbergConfig::default();
+        assert_eq!(create(Some(&ocr), Some(vision)), "Kreuzberg");
+        let no_ocr = KreuzbergConfig {
+            ocr_enabled: false,
+            ..KreuzbergConfig::default()
+        };
+        assert_eq!(create(Some(&no_ocr), Some(vision)), "Vision LLM");
+        assert_eq!(create(None, Some(vision)), "Vision LLM");
+
+        // Not for models that can't see images, nor for other documents
+        let text_only = vision_llm("gpt-3.5-turbo");
+        let vision = VisionLlm {
+            settings: &text_only,
+            config: &vision_config,
+        };
+        assert_eq!(create(None, Some(vision)), "Local");
+        let notes = FileProcessorFactory::create_for_file(
+            std::path::Path::new("notes.txt"),
+            &config,
+            None,
+            None,
+            None,
+            None,
+            Some(vision),
+        );
+        assert_eq!(notes.unwrap().provider_name(), "Local");
+    }
 }
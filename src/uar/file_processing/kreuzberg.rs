@@ -8,7 +8,9 @@ use std::path::Path;
 
 use crate::config::KreuzbergConfig;
 
-use super::provider::{ExtractedImage, FileProcessor, ProcessingError, ProcessingResult};
+use super::provider::{
+    DocumentMetadata, ExtractedImage, FileProcessor, ProcessingError, ProcessingResult,
+};
 
 /// Kreuzberg-based file processor using the native Rust core.
 ///
@@ -86,6 +88,9 @@ impl FileProcessor for KreuzbergProvider {
             }
         }
 
+        let document_metadata = document_metadata(&result, self.config.extract_metadata)
+            .with_word_count(&content);
+
         // Convert metadata to JSON
         let metadata = if self.config.extract_metadata {
             Some(serde_json::json!({
@@ -116,6 +121,7 @@ impl FileProcessor for KreuzbergProvider {
             content,
             mime_type,
             metadata,
+            document_metadata,
             images,
         })
     }
@@ -210,6 +216,7 @@ pub async fn process_bytes(
     .map_err(|e| ProcessingError::ProviderError(format!("Task join error: {}", e)))?
     .map_err(|e| ProcessingError::ProviderError(format!("Kreuzberg error: {}", e)))?;
 
+    let document_metadata = document_metadata(&result, config.extract_metadata);
     let mut content = result.content;
 
     // Append tables
@@ -245,6 +252,7 @@ pub async fn process_bytes(
         .collect();
 
     Ok(ProcessingResult {
+        document_metadata: document_metadata.with_word_count(&content),
         content,
         mime_type: mime_for_result,
         metadata,
@@ -252,6 +260,25 @@ pub async fn process_bytes(
     })
 }
 
+/// Title, first author, creation date and language from Kreuzberg's
+/// metadata; nothing when metadata extraction is off.
+fn document_metadata(result: &kreuzberg::ExtractionResult, extract: bool) -> DocumentMetadata {
+    if !extract {
+        return DocumentMetadata::default();
+    }
+    let metadata = &result.metadata;
+    DocumentMetadata {
+        title: metadata.title.clone(),
+        author: metadata
+            .authors
+            .as_ref()
+            .and_then(|authors| authors.first().cloned()),
+        created_date: metadata.created_at.clone(),
+        word_count: None,
+        language: metadata.language.clone(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4,9 +4,13 @@
 //! without requiring external API calls. For more complex document
 //! formats, consider using Kreuzberg or another external provider.
 
-use super::provider::{FileProcessor, ProcessingError, ProcessingResult};
+use super::epub::{EPUB_MIME_TYPE, EpubProcessor};
+use super::factory::{MIME_SNIFF_LEN, routing_mime_type};
+use super::pdf::{PDF_MIME_TYPE, PdfProcessor};
+use super::provider::{DocumentMetadata, FileProcessor, ProcessingError, ProcessingResult};
 use async_trait::async_trait;
 use std::path::Path;
+use tokio::io::AsyncReadExt;
 
 /// Local file processor for text-based files.
 ///
@@ -16,8 +20,10 @@ use std::path::Path;
 /// - JSON (.json)
 /// - CSV (.csv)
 /// - XML (.xml)
+/// - EPUB (.epub, delegated to [`EpubProcessor`])
+/// - PDF with a text layer (.pdf, delegated to [`PdfProcessor`])
 ///
-/// For binary formats like PDF, DOCX, etc., this provider
+/// For other binary formats like DOCX, images, etc., this provider
 /// falls back to returning an error suggesting to use an
 /// external provider.
 #[derive(Debug, Default)]
@@ -38,9 +44,22 @@ impl LocalProvider {
 #[async_trait]
 impl FileProcessor for LocalProvider {
     async fn process(&self, path: &Path) -> Result<ProcessingResult, ProcessingError> {
-        let mime_type = mime_guess::from_path(path)
-            .first_or_octet_stream()
-            .to_string();
+        // Route by content, so a PDF named .txt isn't read as text
+        let mut head = Vec::with_capacity(MIME_SNIFF_LEN);
+        tokio::fs::File::open(path)
+            .await?
+            .take(MIME_SNIFF_LEN as u64)
+            .read_to_end(&mut head)
+            .await?;
+        let mime_type = routing_mime_type(path, &head, true);
+
+        // EPUB is a zip container, not text: hand it to the dedicated processor
+        if mime_type == EPUB_MIME_TYPE {
+            return EpubProcessor::new().process(path).await;
+        }
+        if mime_type == PDF_MIME_TYPE {
+            return PdfProcessor::new().process(path).await;
+        }
 
         // Check if we support this type
         if !self.supports_mime_type(&mime_type) {
@@ -63,6 +82,7 @@ impl FileProcessor for LocalProvider {
         })?;
 
         Ok(ProcessingResult {
+            document_metadata: DocumentMetadata::from_content(&content),
             content,
             mime_type,
             metadata: None,
@@ -79,6 +99,8 @@ impl FileProcessor for LocalProvider {
                 | "text/xml"
                 | "application/json"
                 | "application/xml"
+                | "application/epub+zip"
+                | "application/pdf"
         )
     }
 
@@ -101,9 +123,16 @@ mod tests {
     }
 
     #[test]
-    fn test_does_not_support_pdf() {
+    fn test_supports_epub() {
         let provider = LocalProvider::new();
-        assert!(!provider.supports_mime_type("application/pdf"));
+        assert!(provider.supports_mime_type("application/epub+zip"));
+    }
+
+    #[test]
+    fn test_supports_pdf() {
+        let provider = LocalProvider::new();
+        assert!(provider.supports_mime_type("application/pdf"));
+        assert!(!provider.supports_mime_type("image/png"));
     }
 
     #[tokio::test]
@@ -132,6 +161,20 @@ mod tests {
         assert!(result.content.contains("# Heading"));
     }
 
+    #[tokio::test]
+    async fn test_pdf_with_text_extension_is_read_as_pdf() {
+        let provider = LocalProvider::new();
+
+        let mut temp_file = NamedTempFile::with_suffix(".txt").unwrap();
+        let pdf = crate::uar::file_processing::sample_pdf("Hello", "Title", "Author");
+        temp_file.write_all(&pdf).unwrap();
+
+        let result = provider.process(temp_file.path()).await.unwrap();
+        assert_eq!(result.mime_type, PDF_MIME_TYPE);
+        assert!(result.content.contains("Hello"));
+        assert_eq!(result.document_metadata.title.as_deref(), Some("Title"));
+    }
+
     #[test]
     fn test_always_configured() {
         let provider = LocalProvider::new();
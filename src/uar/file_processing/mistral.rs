@@ -3,7 +3,7 @@
 //! Uses Mistral's document AI API for OCR and document processing.
 //! Supports PDF documents and images.
 
-use super::provider::{FileProcessor, ProcessingError, ProcessingResult};
+use super::provider::{DocumentMetadata, FileProcessor, ProcessingError, ProcessingResult};
 use crate::config::MistralConfig;
 use async_trait::async_trait;
 use base64::{Engine, engine::general_purpose::STANDARD};
@@ -104,6 +104,7 @@ impl FileProcessor for MistralProvider {
             .unwrap_or_default();
 
         Ok(ProcessingResult {
+            document_metadata: DocumentMetadata::from_content(&content),
             content,
             mime_type,
             metadata: None,
@@ -8,7 +8,11 @@
 //! - [`UnstructuredProvider`] - Unstructured.io (hosted or self-hosted)
 //! - [`MistralProvider`] - Mistral OCR API
 //! - [`KreuzbergProvider`] - Kreuzberg Rust core (high-performance local processing)
-//! - [`LocalProvider`] - Simple local processing (fallback, text files only)
+//! - [`TikaProvider`] - Apache Tika server (self-hosted)
+//! - [`VisionProvider`] - Vision-capable LLM (OCR fallback for images)
+//! - [`LocalProvider`] - Simple local processing (fallback, text files, EPUB and PDF)
+//! - [`EpubProcessor`] - Local EPUB extraction (always used for `.epub` files)
+//! - [`PdfProcessor`] - Local PDF text and metadata extraction
 //!
 //! # Usage
 //!
@@ -20,16 +24,28 @@
 //! println!("Extracted: {}", result.content);
 //! ```
 
+mod epub;
 mod factory;
 mod kreuzberg;
 mod local;
 mod mistral;
+mod pdf;
 mod provider;
+mod tika;
 mod unstructured;
+mod vision;
 
-pub use factory::FileProcessorFactory;
+pub use epub::EpubProcessor;
+pub use factory::{FileProcessorFactory, MIME_SNIFF_LEN, detect_mime_type, routing_mime_type};
 pub use kreuzberg::KreuzbergProvider;
 pub use local::LocalProvider;
 pub use mistral::MistralProvider;
-pub use provider::{ExtractedImage, FileProcessor, ProcessingError, ProcessingResult};
+#[cfg(test)]
+pub(crate) use pdf::sample_pdf;
+pub use pdf::{PDF_MIME_TYPE, PdfProcessor, extract_pdf};
+pub use provider::{
+    DocumentMetadata, ExtractedImage, FileProcessor, ProcessingError, ProcessingResult,
+};
+pub use tika::TikaProvider;
 pub use unstructured::UnstructuredProvider;
+pub use vision::{VisionLlm, VisionProvider};
@@ -0,0 +1,262 @@
+//! PDF file processing provider.
+//!
+//! Extracts text locally with `pdf-extract` and reads the document
+//! information dictionary (title, author, creation date) and the catalog's
+//! language with `lopdf`. Scanned PDFs have no text layer; use an OCR
+//! provider for those.
+
+use super::provider::{DocumentMetadata, FileProcessor, ProcessingError, ProcessingResult};
+use async_trait::async_trait;
+use lopdf::{Dictionary, Document, Object};
+use std::path::Path;
+
+/// MIME type for PDF documents.
+pub const PDF_MIME_TYPE: &str = "application/pdf";
+
+/// Local PDF processor, used by [`super::LocalProvider`] for PDFs.
+#[derive(Debug, Default)]
+pub struct PdfProcessor;
+
+impl PdfProcessor {
+    /// Create a new PDF processor.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Check whether `content` starts like a PDF file.
+    pub fn is_pdf(content: &[u8]) -> bool {
+        content.starts_with(b"%PDF-")
+    }
+}
+
+#[async_trait]
+impl FileProcessor for PdfProcessor {
+    async fn process(&self, path: &Path) -> Result<ProcessingResult, ProcessingError> {
+        let data = tokio::fs::read(path).await?;
+        let (content, document_metadata) = tokio::task::spawn_blocking(move || extract_pdf(&data))
+            .await
+            .map_err(|e| ProcessingError::ProviderError(format!("Task join error: {}", e)))??;
+
+        Ok(ProcessingResult {
+            content,
+            mime_type: PDF_MIME_TYPE.to_string(),
+            metadata: None,
+            document_metadata,
+            images: vec![],
+        })
+    }
+
+    fn supports_mime_type(&self, mime_type: &str) -> bool {
+        mime_type == PDF_MIME_TYPE
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "PDF"
+    }
+}
+
+/// Text and metadata of an in-memory PDF (blocking).
+pub fn extract_pdf(data: &[u8]) -> Result<(String, DocumentMetadata), ProcessingError> {
+    let doc = Document::load_mem(data)
+        .map_err(|e| ProcessingError::ProviderError(format!("Failed to open PDF: {}", e)))?;
+    let content = pdf_extract::extract_text_from_mem(data)
+        .map_err(|e| ProcessingError::ProviderError(format!("Failed to extract PDF text: {}", e)))?;
+
+    let info = doc
+        .trailer
+        .get(b"Info")
+        .ok()
+        .and_then(|info| dictionary(&doc, info));
+    let field = |key: &[u8]| info.and_then(|info| text_string(info.get(key).ok()?));
+    let language = doc
+        .trailer
+        .get(b"Root")
+        .ok()
+        .and_then(|root| dictionary(&doc, root))
+        .and_then(|catalog| text_string(catalog.get(b"Lang").ok()?));
+
+    let metadata = DocumentMetadata {
+        title: field(b"Title"),
+        author: field(b"Author"),
+        created_date: field(b"CreationDate").map(|date| pdf_date(&date)),
+        word_count: None,
+        language,
+    }
+    .with_word_count(&content);
+    Ok((content, metadata))
+}
+
+/// The dictionary `object` is or refers to.
+fn dictionary<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Dictionary> {
+    match object {
+        Object::Reference(id) => doc.get_dictionary(*id).ok(),
+        Object::Dictionary(dict) => Some(dict),
+        _ => None,
+    }
+}
+
+/// Decode a PDF text string: UTF-16BE with a byte order mark, UTF-8 with
+/// one, or PDFDocEncoding (read as Latin-1). Blank strings are `None`.
+fn text_string(object: &Object) -> Option<String> {
+    let Object::String(bytes, _) = object else {
+        return None;
+    };
+    let text = if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        String::from_utf8_lossy(utf8).into_owned()
+    } else {
+        bytes.iter().map(|&b| char::from(b)).collect()
+    };
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Convert a PDF date (`D:YYYYMMDDHHmmSS+HH'mm'`) to RFC3339.
+///
+/// Omitted parts default to the start of the period, and a missing offset
+/// to UTC. Dates that don't parse are returned unchanged.
+fn pdf_date(raw: &str) -> String {
+    let date = raw.strip_prefix("D:").unwrap_or(raw);
+    let digits: String = date.chars().take_while(char::is_ascii_digit).collect();
+    if digits.len() < 4 {
+        return raw.to_string();
+    }
+    let part =
+        |start: usize, default: &'static str| digits.get(start..start + 2).unwrap_or(default);
+    let offset = match date[digits.len()..].chars().next() {
+        Some(sign @ ('+' | '-')) => {
+            let zone: String = date[digits.len() + 1..]
+                .chars()
+                .filter(char::is_ascii_digit)
+                .collect();
+            match (zone.get(0..2), zone.get(2..4)) {
+                (Some(hours), minutes) => format!("{sign}{hours}:{}", minutes.unwrap_or("00")),
+                (None, _) => "Z".to_string(),
+            }
+        }
+        _ => "Z".to_string(),
+    };
+    let rfc3339 = format!(
+        "{}-{}-{}T{}:{}:{}{}",
+        &digits[..4],
+        part(4, "01"),
+        part(6, "01"),
+        part(8, "00"),
+        part(10, "00"),
+        part(12, "00"),
+        offset
+    );
+    match chrono::DateTime::parse_from_rfc3339(&rfc3339) {
+        Ok(parsed) => parsed.to_rfc3339(),
+        Err(_) => raw.to_string(),
+    }
+}
+
+/// A one-page PDF showing `text`, with `title` and `author` in its
+/// information dictionary.
+#[cfg(test)]
+pub(crate) fn sample_pdf(text: &str, title: &str, author: &str) -> Vec<u8> {
+    use lopdf::content::{Content, Operation};
+    use lopdf::{Stream, dictionary};
+
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+    });
+    let resources_id = doc.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+    let content = Content {
+        operations: vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 12.into()]),
+            Operation::new("Td", vec![72.into(), 720.into()]),
+            Operation::new("Tj", vec![Object::string_literal(text)]),
+            Operation::new("ET", vec![]),
+        ],
+    };
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "Contents" => content_id,
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+        "Lang" => Object::string_literal("en-US"),
+    });
+    let info_id = doc.add_object(dictionary! {
+        "Title" => Object::string_literal(title),
+        "Author" => Object::string_literal(author),
+        "CreationDate" => Object::string_literal("D:20240115103000+01'00'"),
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.trailer.set("Info", info_id);
+
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes).unwrap();
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_and_metadata() {
+        let pdf = sample_pdf("Ownership frees memory", "The Rust Book", "Ferris Crab");
+        assert!(PdfProcessor::is_pdf(&pdf));
+
+        let (content, metadata) = extract_pdf(&pdf).unwrap();
+        assert!(content.contains("Ownership frees memory"), "{content}");
+        assert_eq!(metadata.title.as_deref(), Some("The Rust Book"));
+        assert_eq!(metadata.author.as_deref(), Some("Ferris Crab"));
+        assert_eq!(metadata.created_date.as_deref(), Some("2024-01-15T10:30:00+01:00"));
+        assert_eq!(metadata.language.as_deref(), Some("en-US"));
+        assert_eq!(metadata.word_count, Some(3));
+    }
+
+    #[test]
+    fn test_text_strings() {
+        let utf16 = Object::String(
+            vec![0xFE, 0xFF, 0x00, b'C', 0x00, 0xE9],
+            lopdf::StringFormat::Hexadecimal,
+        );
+        assert_eq!(text_string(&utf16).as_deref(), Some("Cé"));
+        assert_eq!(text_string(&Object::string_literal("  ")), None);
+        assert_eq!(text_string(&Object::Integer(1)), None);
+    }
+
+    #[test]
+    fn test_pdf_dates() {
+        assert_eq!(pdf_date("D:20240115103000Z"), "2024-01-15T10:30:00+00:00");
+        assert_eq!(pdf_date("D:20240115103000-05'30'"), "2024-01-15T10:30:00-05:30");
+        assert_eq!(pdf_date("D:2024"), "2024-01-01T00:00:00+00:00");
+        assert_eq!(pdf_date("yesterday"), "yesterday");
+    }
+
+    #[test]
+    fn test_not_a_pdf() {
+        assert!(!PdfProcessor::is_pdf(b"plain text"));
+        assert!(extract_pdf(b"plain text").is_err());
+    }
+}
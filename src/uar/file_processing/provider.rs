@@ -1,6 +1,7 @@
 //! Core trait and types for file processing providers.
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 /// Result of file processing.
@@ -10,12 +11,46 @@ pub struct ProcessingResult {
     pub content: String,
     /// Original MIME type of the processed file.
     pub mime_type: String,
-    /// Extracted metadata (title, author, page count, etc.).
+    /// Provider-specific metadata, as the provider reported it.
     pub metadata: Option<serde_json::Value>,
+    /// Descriptive metadata, the same for every provider.
+    pub document_metadata: DocumentMetadata,
     /// Extracted images (for documents with embedded images).
     pub images: Vec<ExtractedImage>,
 }
 
+/// Descriptive metadata of a document, kept with it after ingestion.
+///
+/// Fields are `None` when the format or provider doesn't report them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// When the document was created, RFC3339 where the source date parses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub word_count: Option<usize>,
+    /// Language code, e.g. `en`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+impl DocumentMetadata {
+    /// Metadata holding only the word count of `content`.
+    pub fn from_content(content: &str) -> Self {
+        Self::default().with_word_count(content)
+    }
+
+    /// Set the word count to that of `content`.
+    pub fn with_word_count(mut self, content: &str) -> Self {
+        self.word_count = Some(content.split_whitespace().count());
+        self
+    }
+}
+
 /// An image extracted from a document.
 #[derive(Debug, Clone)]
 pub struct ExtractedImage {
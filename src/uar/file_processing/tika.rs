@@ -0,0 +1,320 @@
+//! Apache Tika file processing provider.
+//!
+//! Sends documents to a self-hosted Tika server: `PUT /tika` returns the
+//! extracted text (or XHTML), and `PUT /unpack` the files embedded in the
+//! document as a tar archive, from which images are kept.
+
+use super::provider::{
+    DocumentMetadata, ExtractedImage, FileProcessor, ProcessingError, ProcessingResult,
+};
+use crate::config::TikaConfig;
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use std::path::Path;
+use std::time::Duration;
+
+/// Size of tar headers and the unit file contents are padded to.
+const TAR_BLOCK: usize = 512;
+
+/// File processor using an Apache Tika server.
+#[derive(Debug)]
+pub struct TikaProvider {
+    client: reqwest::Client,
+    config: TikaConfig,
+}
+
+impl TikaProvider {
+    /// Create a new Tika provider with the given configuration.
+    pub fn new(config: TikaConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .unwrap_or_default();
+        Self { client, config }
+    }
+
+    /// `Accept` header asking `/tika` for the configured output format.
+    fn accept(&self) -> Result<&'static str, ProcessingError> {
+        match self.config.output_format.as_str() {
+            "text" => Ok("text/plain; charset=UTF-8"),
+            "xhtml" => Ok("text/html; charset=UTF-8"),
+            other => Err(ProcessingError::ProviderNotConfigured(format!(
+                "Unknown Tika output format '{other}' (expected \"text\" or \"xhtml\")"
+            ))),
+        }
+    }
+
+    /// PUT `file_bytes` to `endpoint`, failing on non-success responses.
+    async fn put(
+        &self,
+        endpoint: &str,
+        file_bytes: Vec<u8>,
+        mime_type: &str,
+        accept: &str,
+    ) -> Result<reqwest::Response, ProcessingError> {
+        let url = format!("{}/{endpoint}", self.config.server_url.trim_end_matches('/'));
+        let response = self
+            .client
+            .put(url)
+            .header(reqwest::header::CONTENT_TYPE, mime_type)
+            .header(reqwest::header::ACCEPT, accept)
+            .body(file_bytes)
+            .send()
+            .await
+            .map_err(|e| ProcessingError::HttpError(e.to_string()))?;
+
+        let status = response.status();
+        if status == StatusCode::UNSUPPORTED_MEDIA_TYPE {
+            return Err(ProcessingError::UnsupportedType(mime_type.to_string()));
+        }
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(ProcessingError::ProviderError(format!(
+                "Tika error ({status}): {error_text}"
+            )));
+        }
+        Ok(response)
+    }
+
+    /// Images embedded in the document.
+    async fn unpack_images(
+        &self,
+        file_bytes: Vec<u8>,
+        mime_type: &str,
+    ) -> Result<Vec<ExtractedImage>, ProcessingError> {
+        let response = self
+            .put("unpack", file_bytes, mime_type, "application/x-tar")
+            .await?;
+        // No embedded files
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(vec![]);
+        }
+        let archive = response
+            .bytes()
+            .await
+            .map_err(|e| ProcessingError::HttpError(e.to_string()))?;
+
+        Ok(tar_entries(&archive)
+            .into_iter()
+            .filter_map(|(name, data)| {
+                let mime_type = mime_guess::from_path(&name).first()?.to_string();
+                mime_type.starts_with("image/").then(|| ExtractedImage {
+                    data: data.to_vec(),
+                    mime_type,
+                    description: Some(name),
+                })
+            })
+            .collect())
+    }
+}
+
+/// Regular files in a tar archive, as (name, contents).
+///
+/// Stops at the end-of-archive marker or the first truncated entry.
+fn tar_entries(archive: &[u8]) -> Vec<(String, &[u8])> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = archive.get(offset..offset + TAR_BLOCK) {
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let name_len = header[..100].iter().position(|&b| b == 0).unwrap_or(100);
+        let name = String::from_utf8_lossy(&header[..name_len]).into_owned();
+        let size = std::str::from_utf8(&header[124..136])
+            .ok()
+            .and_then(|s| usize::from_str_radix(s.trim_matches(['\0', ' ']), 8).ok())
+            .unwrap_or(0);
+
+        let start = offset + TAR_BLOCK;
+        let Some(data) = archive.get(start..start + size) else {
+            break;
+        };
+        // '0' (or NUL in old archives) marks a regular file
+        if matches!(header[156], b'0' | 0) {
+            entries.push((name, data));
+        }
+        offset = start + size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+    }
+    entries
+}
+
+#[async_trait]
+impl FileProcessor for TikaProvider {
+    async fn process(&self, path: &Path) -> Result<ProcessingResult, ProcessingError> {
+        let accept = self.accept()?;
+        let file_bytes = tokio::fs::read(path).await?;
+        let mime_type = mime_guess::from_path(path)
+            .first_or_octet_stream()
+            .to_string();
+
+        let images = if self.config.extract_images {
+            self.unpack_images(file_bytes.clone(), &mime_type).await?
+        } else {
+            vec![]
+        };
+
+        let content = self
+            .put("tika", file_bytes, &mime_type, accept)
+            .await?
+            .text()
+            .await
+            .map_err(|e| ProcessingError::HttpError(e.to_string()))?;
+
+        let content = content.trim().to_string();
+        Ok(ProcessingResult {
+            document_metadata: DocumentMetadata::from_content(&content),
+            content,
+            mime_type,
+            metadata: None,
+            images,
+        })
+    }
+
+    fn supports_mime_type(&self, mime_type: &str) -> bool {
+        // Tika parses most document formats; images need OCR on the server
+        !mime_type.starts_with("image/")
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "Apache Tika"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Bytes, http::HeaderMap, routing::put};
+
+    /// A tar archive holding `files`, as `tar` would write it.
+    fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        for (name, data) in files {
+            let mut header = [0u8; TAR_BLOCK];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            let size = format!("{:011o}\0", data.len());
+            header[124..136].copy_from_slice(size.as_bytes());
+            header[156] = b'0';
+            archive.extend_from_slice(&header);
+            archive.extend_from_slice(data);
+            archive.resize(archive.len().div_ceil(TAR_BLOCK) * TAR_BLOCK, 0);
+        }
+        archive.resize(archive.len() + 2 * TAR_BLOCK, 0);
+        archive
+    }
+
+    /// Serve a mock Tika server, returning its base URL.
+    async fn spawn_tika() -> String {
+        let app = Router::new()
+            .route(
+                "/tika",
+                put(|headers: HeaderMap, body: Bytes| async move {
+                    if body.is_empty() {
+                        return (StatusCode::UNPROCESSABLE_ENTITY, String::new());
+                    }
+                    let accept = headers["accept"].to_str().unwrap();
+                    if headers["content-type"] == "application/octet-stream" {
+                        return (StatusCode::UNSUPPORTED_MEDIA_TYPE, String::new());
+                    }
+                    let text = String::from_utf8_lossy(&body);
+                    let content = if accept.starts_with("text/html") {
+                        format!("<html><body><p>{text}</p></body></html>")
+                    } else {
+                        format!("\n{text}\n")
+                    };
+                    (StatusCode::OK, content)
+                }),
+            )
+            .route(
+                "/unpack",
+                put(|| async {
+                    tar(&[("image1.png", b"\x89PNG"), ("notes.txt", b"embedded text")])
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn provider(server_url: String, output_format: &str, extract_images: bool) -> TikaProvider {
+        TikaProvider::new(TikaConfig {
+            server_url,
+            output_format: output_format.to_string(),
+            extract_images,
+            ..TikaConfig::default()
+        })
+    }
+
+    fn document(dir: &tempfile::TempDir, name: &str, content: &str) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_text_and_xhtml_output() {
+        let url = spawn_tika().await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = document(&dir, "report.pdf", "Quarterly report");
+
+        let result = provider(url.clone(), "text", false).process(&path).await.unwrap();
+        assert_eq!(result.content, "Quarterly report");
+        assert_eq!(result.mime_type, "application/pdf");
+        assert!(result.images.is_empty());
+
+        let result = provider(url, "xhtml", false).process(&path).await.unwrap();
+        assert_eq!(result.content, "<html><body><p>Quarterly report</p></body></html>");
+    }
+
+    #[tokio::test]
+    async fn test_embedded_images() {
+        let url = spawn_tika().await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = document(&dir, "slides.pdf", "Slides");
+
+        let result = provider(url, "text", true).process(&path).await.unwrap();
+        assert_eq!(result.images.len(), 1);
+        assert_eq!(result.images[0].mime_type, "image/png");
+        assert_eq!(result.images[0].data, b"\x89PNG");
+        assert_eq!(result.images[0].description.as_deref(), Some("image1.png"));
+    }
+
+    #[tokio::test]
+    async fn test_failures_map_to_processing_errors() {
+        let url = spawn_tika().await;
+        let dir = tempfile::tempdir().unwrap();
+
+        let empty = document(&dir, "empty.pdf", "");
+        let err = provider(url.clone(), "text", false).process(&empty).await.unwrap_err();
+        assert!(matches!(err, ProcessingError::ProviderError(_)), "{err}");
+
+        let unknown = document(&dir, "blob.bin", "\u{0}\u{1}");
+        let err = provider(url.clone(), "text", false).process(&unknown).await.unwrap_err();
+        assert!(matches!(err, ProcessingError::UnsupportedType(_)), "{err}");
+
+        let path = document(&dir, "report.pdf", "Report");
+        let err = provider(url, "markdown", false).process(&path).await.unwrap_err();
+        assert!(matches!(err, ProcessingError::ProviderNotConfigured(_)), "{err}");
+
+        // Nothing listens on the port any more
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let err = provider(closed, "text", false).process(&path).await.unwrap_err();
+        assert!(matches!(err, ProcessingError::HttpError(_)), "{err}");
+    }
+
+    #[test]
+    fn test_tar_entries() {
+        let archive = tar(&[("a.png", &[1; 600]), ("dir/b.txt", b"hello")]);
+        let entries = tar_entries(&archive);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "a.png");
+        assert_eq!(entries[0].1.len(), 600);
+        assert_eq!(entries[1], ("dir/b.txt".to_string(), &b"hello"[..]));
+
+        // Truncated archives yield the complete entries only
+        assert_eq!(tar_entries(&archive[..1024]).len(), 0);
+        assert!(tar_entries(&[]).is_empty());
+    }
+}
@@ -4,7 +4,7 @@
 //! This provider can extract text from a wide variety of document formats
 //! including PDF, DOCX, XLSX, PPTX, HTML, and more.
 
-use super::provider::{FileProcessor, ProcessingError, ProcessingResult};
+use super::provider::{DocumentMetadata, FileProcessor, ProcessingError, ProcessingResult};
 use crate::config::UnstructuredConfig;
 use async_trait::async_trait;
 use std::path::Path;
@@ -90,13 +90,25 @@ impl FileProcessor for UnstructuredProvider {
             .collect::<Vec<_>>()
             .join("\n\n");
 
-        // Extract metadata if available
-        let metadata = elements.first().and_then(|e| e.get("metadata").cloned());
+        // Metadata of the first element (`filetype`, `languages`, ...), with
+        // the page count taken from the last page any element is on
+        let mut metadata = elements.first().and_then(|e| e.get("metadata").cloned());
+        let page_count = elements
+            .iter()
+            .filter_map(|e| e.pointer("/metadata/page_number")?.as_u64())
+            .max();
+        if let Some(serde_json::Value::Object(fields)) = &mut metadata
+            && let Some(pages) = page_count
+        {
+            fields.insert("page_count".to_string(), pages.into());
+        }
+        let document_metadata = element_metadata(&elements).with_word_count(&content);
 
         Ok(ProcessingResult {
             content,
             mime_type,
             metadata,
+            document_metadata,
             images: vec![],
         })
     }
@@ -134,6 +146,32 @@ impl FileProcessor for UnstructuredProvider {
     }
 }
 
+/// Descriptive metadata from Unstructured's elements.
+///
+/// The title is the first `Title` element; the language and date come from
+/// the first element's metadata (`languages`, `last_modified`).
+fn element_metadata(elements: &[serde_json::Value]) -> DocumentMetadata {
+    let first = |pointer: &str| {
+        elements
+            .first()
+            .and_then(|e| e.pointer(pointer))
+            .and_then(serde_json::Value::as_str)
+            .map(str::to_string)
+    };
+    let title = elements
+        .iter()
+        .find(|e| e.get("type").and_then(serde_json::Value::as_str) == Some("Title"))
+        .and_then(|e| e.get("text")?.as_str())
+        .map(|text| text.trim().to_string());
+    DocumentMetadata {
+        title,
+        author: None,
+        created_date: first("/metadata/last_modified"),
+        word_count: None,
+        language: first("/metadata/languages/0"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,6 +183,28 @@ mod tests {
         assert!(provider.supports_mime_type("application/pdf"));
     }
 
+    #[test]
+    fn test_element_metadata() {
+        let elements = serde_json::json!([
+            {
+                "type": "Title",
+                "text": "Quarterly Report ",
+                "metadata": {
+                    "filetype": "application/pdf",
+                    "languages": ["eng"],
+                    "last_modified": "2024-03-01T09:30:00",
+                    "page_number": 1
+                }
+            },
+            { "type": "NarrativeText", "text": "Revenue grew.", "metadata": { "page_number": 2 } }
+        ]);
+        let metadata = element_metadata(elements.as_array().unwrap());
+        assert_eq!(metadata.title.as_deref(), Some("Quarterly Report"));
+        assert_eq!(metadata.language.as_deref(), Some("eng"));
+        assert_eq!(metadata.created_date.as_deref(), Some("2024-03-01T09:30:00"));
+        assert_eq!(metadata.author, None);
+    }
+
     #[test]
     fn test_supports_docx() {
         let config = UnstructuredConfig::default();
@@ -0,0 +1,320 @@
+//! Vision LLM file processing provider.
+//!
+//! OCR fallback for images when no OCR service is available: the image is
+//! sent to a vision-capable chat model with a prompt to transcribe all of
+//! its text. Images larger than the upload limit, or than vision APIs
+//! accept, are downscaled before sending.
+
+use super::provider::{DocumentMetadata, FileProcessor, ProcessingError, ProcessingResult};
+use crate::config::VisionConfig;
+use crate::llm::{
+    ContentPart, LlmSettings, Message, MessageContent, MessageRole, Orchestrator, Provider,
+};
+use crate::mcp::registry::McpRegistry;
+use async_trait::async_trait;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use image::{DynamicImage, GenericImageView, ImageFormat, imageops::FilterType};
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Longest side, in pixels, of images sent to the model.
+const MAX_DIMENSION: u32 = 2048;
+/// Smallest side downscaling stops at when shrinking images to fit.
+const MIN_DIMENSION: u32 = 64;
+
+const EXTRACT_PROMPT: &str = "Extract all text from this image. Preserve the reading order, \
+    headings, lists and tables (as Markdown). Answer with the extracted text only, or with \
+    nothing if the image contains no text.";
+
+/// The LLM a [`VisionProvider`] calls, and which of its models sees images.
+#[derive(Debug, Clone, Copy)]
+pub struct VisionLlm<'a> {
+    pub settings: &'a LlmSettings,
+    pub config: &'a VisionConfig,
+}
+
+impl VisionLlm<'_> {
+    /// The configured vision model, else the default model if it is known
+    /// to accept images (and auto-detection is on).
+    pub fn model(&self) -> Option<&str> {
+        self.config.model.as_deref().or_else(|| {
+            (self.config.auto_detect && Provider::supports_vision(&self.settings.model))
+                .then_some(self.settings.model.as_str())
+        })
+    }
+}
+
+/// File processor transcribing images with a vision-capable LLM.
+#[derive(Debug)]
+pub struct VisionProvider {
+    orchestrator: Arc<Orchestrator>,
+    model: String,
+    /// Images are downscaled until their encoding fits
+    max_file_size: usize,
+}
+
+impl VisionProvider {
+    /// Create a provider calling `llm`'s vision model.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProcessingError::ProviderNotConfigured`] if no vision model
+    /// is configured and the default model isn't known to accept images.
+    pub fn new(llm: VisionLlm<'_>, max_file_size: usize) -> Result<Self, ProcessingError> {
+        let model = llm.model().ok_or_else(|| {
+            ProcessingError::ProviderNotConfigured(format!(
+                "Model '{}' doesn't accept images; set vision.model",
+                llm.settings.model
+            ))
+        })?;
+        let settings = LlmSettings {
+            model: model.to_string(),
+            ..llm.settings.clone()
+        };
+        let orchestrator = Orchestrator::new(settings, Arc::new(McpRegistry::new_empty()));
+        Ok(Self {
+            orchestrator: Arc::new(orchestrator),
+            model: model.to_string(),
+            max_file_size,
+        })
+    }
+
+    /// `bytes` as an image the model accepts: at most `MAX_DIMENSION`
+    /// pixels a side and `max_file_size` bytes, re-encoded if either is
+    /// exceeded.
+    fn fit_image(
+        &self,
+        bytes: Vec<u8>,
+        mime_type: &str,
+    ) -> Result<(Vec<u8>, String), ProcessingError> {
+        let image = image::load_from_memory(&bytes)
+            .map_err(|e| ProcessingError::ProviderError(format!("Unreadable image: {e}")))?;
+        let (width, height) = image.dimensions();
+        if bytes.len() <= self.max_file_size && width.max(height) <= MAX_DIMENSION {
+            return Ok((bytes, mime_type.to_string()));
+        }
+
+        let mut side = width.max(height).min(MAX_DIMENSION);
+        loop {
+            let resized = image.resize(side, side, FilterType::Lanczos3);
+            let encoded = encode_jpeg(&resized)?;
+            if encoded.len() <= self.max_file_size {
+                tracing::debug!(
+                    from = ?(width, height),
+                    to = ?resized.dimensions(),
+                    bytes = encoded.len(),
+                    "Downscaled image for vision OCR"
+                );
+                return Ok((encoded, "image/jpeg".to_string()));
+            }
+            if side / 2 < MIN_DIMENSION {
+                return Err(ProcessingError::ProviderError(format!(
+                    "Image doesn't fit in {} bytes even at {side}px",
+                    self.max_file_size
+                )));
+            }
+            side /= 2;
+        }
+    }
+}
+
+/// Encode as JPEG (which has no alpha channel).
+fn encode_jpeg(image: &DynamicImage) -> Result<Vec<u8>, ProcessingError> {
+    let mut out = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_to(&mut out, ImageFormat::Jpeg)
+        .map_err(|e| ProcessingError::ProviderError(format!("Failed to encode image: {e}")))?;
+    Ok(out.into_inner())
+}
+
+#[async_trait]
+impl FileProcessor for VisionProvider {
+    async fn process(&self, path: &Path) -> Result<ProcessingResult, ProcessingError> {
+        let mime_type = mime_guess::from_path(path)
+            .first_or_octet_stream()
+            .to_string();
+        if !self.supports_mime_type(&mime_type) {
+            return Err(ProcessingError::UnsupportedType(mime_type));
+        }
+
+        let file_bytes = tokio::fs::read(path).await?;
+        let (image, image_type) = self.fit_image(file_bytes, &mime_type)?;
+        let data_url = format!("data:{image_type};base64,{}", STANDARD.encode(&image));
+
+        let message = Message {
+            role: MessageRole::User,
+            content: MessageContent::parts(vec![
+                ContentPart::text(EXTRACT_PROMPT),
+                ContentPart::image_url(data_url),
+            ]),
+            tool_call_id: None,
+            tool_calls: None,
+        };
+        let content = self
+            .orchestrator
+            .chat_non_streaming(vec![message], None)
+            .await
+            .map_err(|e| ProcessingError::ProviderError(format!("{e:#}")))?;
+
+        let content = content.trim().to_string();
+        Ok(ProcessingResult {
+            document_metadata: DocumentMetadata::from_content(&content),
+            content,
+            mime_type,
+            metadata: Some(serde_json::json!({ "ocr_model": self.model })),
+            images: vec![],
+        })
+    }
+
+    fn supports_mime_type(&self, mime_type: &str) -> bool {
+        matches!(
+            mime_type,
+            "image/png" | "image/jpeg" | "image/gif" | "image/webp" | "image/bmp" | "image/tiff"
+        )
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "Vision LLM"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::testing::llm_settings;
+    use axum::{Json, Router, extract::State, http::header, response::IntoResponse, routing::post};
+    use tokio::sync::mpsc;
+
+    /// Answers every chat request with a fixed transcription.
+    async fn transcribe(
+        State(bodies): State<mpsc::UnboundedSender<serde_json::Value>>,
+        Json(body): Json<serde_json::Value>,
+    ) -> impl IntoResponse {
+        let _ = bodies.send(body);
+        let delta = serde_json::json!({
+            "choices": [{ "index": 0, "delta": { "content": "  INVOICE #42\n" } }]
+        });
+        (
+            [(header::CONTENT_TYPE, "text/event-stream")],
+            format!("data: {delta}\n\ndata: [DONE]\n\n"),
+        )
+    }
+
+    async fn spawn_llm(bodies: mpsc::UnboundedSender<serde_json::Value>) -> String {
+        let app = Router::new()
+            .route("/v1/chat/completions", post(transcribe))
+            .with_state(bodies);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = DynamicImage::new_rgb8(width, height);
+        let mut out = Cursor::new(Vec::new());
+        image.write_to(&mut out, ImageFormat::Png).unwrap();
+        out.into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_image_is_transcribed() {
+        let (tx, mut bodies) = mpsc::unbounded_channel();
+        let settings = llm_settings(spawn_llm(tx).await, "gpt-4o-mini");
+        let config = VisionConfig::default();
+        let llm = VisionLlm {
+            settings: &settings,
+            config: &config,
+        };
+        let provider = VisionProvider::new(llm, 1 << 20).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.png");
+        std::fs::write(&path, png(40, 20)).unwrap();
+
+        let result = provider.process(&path).await.unwrap();
+        assert_eq!(result.content, "INVOICE #42");
+        assert_eq!(result.mime_type, "image/png");
+        assert_eq!(provider.provider_name(), "Vision LLM");
+
+        let body = bodies.recv().await.unwrap();
+        assert_eq!(body["model"], "gpt-4o-mini");
+        let parts = &body["messages"][0]["content"];
+        assert_eq!(parts[0]["text"], EXTRACT_PROMPT);
+        let url = parts[1]["image_url"]["url"].as_str().unwrap();
+        assert!(url.starts_with("data:image/png;base64,"), "{url}");
+
+        let text = dir.path().join("notes.txt");
+        std::fs::write(&text, "text").unwrap();
+        let err = provider.process(&text).await.unwrap_err();
+        assert!(matches!(err, ProcessingError::UnsupportedType(_)), "{err}");
+    }
+
+    #[test]
+    fn test_vision_model_selection() {
+        let auto = VisionConfig::default();
+        let text_only = llm_settings(String::new(), "gpt-3.5-turbo");
+        let llm = |settings, config| VisionLlm { settings, config };
+        let err = VisionProvider::new(llm(&text_only, &auto), 1024).unwrap_err();
+        assert!(matches!(err, ProcessingError::ProviderNotConfigured(_)), "{err}");
+
+        let explicit = VisionConfig {
+            model: Some("pixtral-large".to_string()),
+            auto_detect: false,
+        };
+        assert_eq!(llm(&text_only, &explicit).model(), Some("pixtral-large"));
+
+        let vision = llm_settings(String::new(), "claude-3-5-sonnet");
+        let disabled = VisionConfig {
+            model: None,
+            auto_detect: false,
+        };
+        assert_eq!(llm(&vision, &disabled).model(), None);
+        assert_eq!(llm(&vision, &auto).model(), Some("claude-3-5-sonnet"));
+    }
+
+    #[test]
+    fn test_oversized_images_are_downscaled() {
+        let settings = llm_settings(String::new(), "gpt-4o");
+        let config = VisionConfig::default();
+        let llm = VisionLlm {
+            settings: &settings,
+            config: &config,
+        };
+
+        // Small enough: sent as is
+        let provider = VisionProvider::new(llm, 1 << 20).unwrap();
+        let small = png(100, 50);
+        let (bytes, mime) = provider.fit_image(small.clone(), "image/png").unwrap();
+        assert_eq!((bytes, mime.as_str()), (small, "image/png"));
+
+        // Too many pixels: fitted within MAX_DIMENSION
+        let (bytes, mime) = provider.fit_image(png(4096, 1024), "image/png").unwrap();
+        assert_eq!(mime, "image/jpeg");
+        let fitted = image::load_from_memory(&bytes).unwrap();
+        assert_eq!(fitted.dimensions(), (MAX_DIMENSION, MAX_DIMENSION / 4));
+
+        // Too many bytes: shrunk until the encoding fits
+        let mut seed = 0x2545_f491_u32;
+        let noise: Vec<u8> = (0..1000 * 1000 * 3)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed.to_le_bytes()[0]
+            })
+            .collect();
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_raw(1000, 1000, noise).unwrap());
+        let mut large = Cursor::new(Vec::new());
+        image.write_to(&mut large, ImageFormat::Png).unwrap();
+        let limit = 200 * 1024;
+        let provider = VisionProvider::new(llm, limit).unwrap();
+        let (bytes, _) = provider.fit_image(large.into_inner(), "image/png").unwrap();
+        assert!(bytes.len() <= limit, "{} bytes", bytes.len());
+
+        // Nothing fits in a handful of bytes
+        let provider = VisionProvider::new(llm, 16).unwrap();
+        assert!(provider.fit_image(png(600, 600), "image/png").is_err());
+    }
+}
@@ -7,4 +7,6 @@ pub mod rag;
 pub mod runtime;
 pub mod security;
 pub mod telemetry;
+#[cfg(any(test, feature = "test-support"))]
+pub mod testing;
 pub mod tools;
@@ -1,67 +1,189 @@
 use crate::session::Session;
+use crate::uar::domain::graph::{GraphEdge, GraphNode, Subgraph};
 use crate::uar::domain::knowledge::{
     DocumentStatus, KnowledgeBase, KnowledgeChunk, KnowledgeDocument, KnowledgeMatch,
 };
 use crate::uar::domain::skills::{Skill, SkillMatch};
-use anyhow::Result;
 use async_trait::async_trait;
 
 pub mod providers;
+#[cfg(test)]
+pub mod testing;
+
+/// Result of a [`PersistenceLayer`] operation.
+pub type Result<T, E = PersistenceError> = std::result::Result<T, E>;
+
+/// Errors returned by a [`PersistenceLayer`].
+///
+/// Providers translate their driver errors into these variants so callers
+/// can tell, say, a missing record from a lost connection.
+#[derive(Debug, thiserror::Error)]
+pub enum PersistenceError {
+    #[error("{resource_type} '{id}' not found")]
+    NotFound {
+        resource_type: &'static str,
+        id: String,
+    },
+
+    #[error("name '{0}' is already in use")]
+    DuplicateName(String),
+
+    #[error("{resource_type} '{id}' already exists")]
+    AlreadyExists {
+        resource_type: &'static str,
+        id: String,
+    },
+
+    #[error("vector has {actual} dimensions, expected {expected}")]
+    VectorDimensionMismatch { expected: usize, actual: usize },
+
+    #[error("database connection failed: {0}")]
+    ConnectionError(String),
+
+    #[error("record serialization failed: {0}")]
+    SerializationError(String),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl PersistenceError {
+    pub fn not_found(resource_type: &'static str, id: impl Into<String>) -> Self {
+        Self::NotFound {
+            resource_type,
+            id: id.into(),
+        }
+    }
+
+    pub fn already_exists(resource_type: &'static str, id: impl Into<String>) -> Self {
+        Self::AlreadyExists {
+            resource_type,
+            id: id.into(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for PersistenceError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::SerializationError(e.to_string())
+    }
+}
 
 #[derive(Debug)]
 pub struct PostgresProvider;
 
+/// Storage backend for sessions, agents, knowledge and memories.
+///
+/// Reads take the caller's `tenant_id` and only reach records owned by that
+/// tenant or shared ones (no tenant), so `None` sees shared records only.
+/// Deletes only reach records owned by exactly that tenant: shared records
+/// can only be deleted with `None`. Records carry their own tenant, so saves
+/// take none; a save never replaces a record of another tenant.
 #[async_trait]
 pub trait PersistenceLayer: Send + Sync + std::fmt::Debug {
+    /// Check that the backend is reachable.
+    async fn ping(&self) -> Result<()>;
+
     // Session Management
     async fn save_session(&self, session: &Session) -> Result<()>;
-    async fn load_session(&self, id: &str) -> Result<Option<Session>>;
+    async fn load_session(&self, id: &str, tenant_id: Option<&str>) -> Result<Option<Session>>;
 
     // Skill Management
     async fn save_skill(&self, skill: &Skill, embedding: &[f32]) -> Result<()>;
     async fn search_skills(&self, query_vec: &[f32], limit: usize) -> Result<Vec<SkillMatch>>;
 
+    /// Record a version of a skill, replacing an earlier record of the same
+    /// `skill_id` and `version`.
+    async fn save_skill_version(&self, skill: &Skill) -> Result<()>;
+
+    /// Every recorded version of a skill, in no particular order.
+    async fn list_skill_versions(&self, skill_id: &str) -> Result<Vec<Skill>>;
+
     // =========================================================================
     // Knowledge Base Management
     // =========================================================================
 
     /// Save or update a knowledge base definition.
+    ///
+    /// Fails with [`PersistenceError::DuplicateName`] if another knowledge
+    /// base already has its name, and with [`PersistenceError::AlreadyExists`]
+    /// if its ID is taken by a knowledge base of another tenant, or by a
+    /// shared one.
     async fn save_knowledge_base(&self, kb: &KnowledgeBase) -> Result<()>;
 
     /// Get a knowledge base by ID.
-    async fn get_knowledge_base(&self, id: &str) -> Result<Option<KnowledgeBase>>;
+    async fn get_knowledge_base(
+        &self,
+        id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeBase>>;
 
     /// Get a knowledge base by name.
-    async fn get_knowledge_base_by_name(&self, name: &str) -> Result<Option<KnowledgeBase>>;
+    async fn get_knowledge_base_by_name(
+        &self,
+        name: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeBase>>;
 
     /// List all knowledge bases.
-    async fn list_knowledge_bases(&self) -> Result<Vec<KnowledgeBase>>;
+    async fn list_knowledge_bases(&self, tenant_id: Option<&str>) -> Result<Vec<KnowledgeBase>>;
 
     /// Delete a knowledge base and all its chunks/documents.
-    async fn delete_knowledge_base(&self, id: &str) -> Result<()>;
+    ///
+    /// Fails with [`PersistenceError::NotFound`] if there is no such knowledge
+    /// base owned by `tenant_id`.
+    async fn delete_knowledge_base(&self, id: &str, tenant_id: Option<&str>) -> Result<()>;
 
     // =========================================================================
     // Knowledge Chunk Management
     // =========================================================================
 
     /// Save a knowledge chunk.
+    ///
+    /// Fails if the knowledge base already has another chunk with the same
+    /// [`KnowledgeChunk::content_hash`]; check with [`Self::chunk_exists`].
     async fn save_chunk(&self, chunk: &KnowledgeChunk) -> Result<()>;
 
+    /// Whether knowledge base `kb_id` has a chunk whose text hashes to
+    /// `content_hash` (see [`crate::uar::domain::knowledge::content_hash`]).
+    async fn chunk_exists(&self, kb_id: &str, content_hash: &str) -> Result<bool>;
+
+    /// List the chunks of a knowledge base, grouped by document, without
+    /// their embeddings.
+    async fn list_chunks(
+        &self,
+        kb_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<KnowledgeChunk>>;
+
+    /// All chunks of knowledge base `kb_id`, of every tenant, with their
+    /// embeddings, for building an in-memory vector index.
+    ///
+    /// `None` if the store searches chunks with a vector index of its own,
+    /// so an in-memory one isn't needed.
+    async fn list_chunk_embeddings(&self, kb_id: &str) -> Result<Option<Vec<KnowledgeChunk>>>;
+
     /// Search knowledge across ALL knowledge bases (original behavior).
     async fn search_knowledge(
         &self,
         query_vec: &[f32],
         limit: usize,
         min_score: f32,
+        tenant_id: Option<&str>,
     ) -> Result<Vec<KnowledgeMatch>>;
 
     /// Search knowledge scoped to specific knowledge base IDs.
+    ///
+    /// With a `metadata_filter` object, only chunks whose metadata contains
+    /// it are candidates (e.g. `{"source": "faq"}`).
     async fn search_knowledge_scoped(
         &self,
         kb_ids: &[&str],
         query_vec: &[f32],
         limit: usize,
         min_score: f32,
+        metadata_filter: Option<&serde_json::Value>,
+        tenant_id: Option<&str>,
     ) -> Result<Vec<KnowledgeMatch>>;
 
     // =========================================================================
@@ -69,39 +191,184 @@ pub trait PersistenceLayer: Send + Sync + std::fmt::Debug {
     // =========================================================================
 
     /// Save a document record.
+    ///
+    /// Fails with [`PersistenceError::DuplicateName`] if another document of
+    /// the knowledge base has its idempotency key.
     async fn save_document(&self, doc: &KnowledgeDocument) -> Result<()>;
 
+    /// Save `doc` (status and chunk count included) and all of its `chunks`
+    /// in one transaction.
+    ///
+    /// If any write fails none is kept, so ingestion never leaves a document
+    /// marked indexed with only some of its chunks, or chunks without their
+    /// document. Chunks follow the uniqueness rule of [`Self::save_chunk`].
+    async fn save_document_with_chunks(
+        &self,
+        doc: &KnowledgeDocument,
+        chunks: &[KnowledgeChunk],
+    ) -> Result<()>;
+
     /// Get a document by ID.
-    async fn get_document(&self, id: &str) -> Result<Option<KnowledgeDocument>>;
+    async fn get_document(
+        &self,
+        id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeDocument>>;
 
     /// List documents in a knowledge base.
-    async fn list_documents(&self, kb_id: &str) -> Result<Vec<KnowledgeDocument>>;
+    async fn list_documents(
+        &self,
+        kb_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<KnowledgeDocument>>;
+
+    /// The document of knowledge base `kb_id` uploaded with
+    /// `idempotency_key`, if there is one.
+    async fn find_document_by_idempotency_key(
+        &self,
+        kb_id: &str,
+        idempotency_key: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeDocument>>;
 
     /// Update document processing status.
+    ///
+    /// Only used by ingestion for documents it was handed, so it is not
+    /// tenant-scoped. Fails with [`PersistenceError::NotFound`] if there is
+    /// no such document.
     async fn update_document_status(&self, doc_id: &str, status: &DocumentStatus) -> Result<()>;
 
+    /// Delete the chunks of a document, keeping its record.
+    ///
+    /// Used by ingestion before indexing a changed document again, so not
+    /// tenant-scoped either.
+    async fn delete_document_chunks(&self, doc_id: &str) -> Result<()>;
+
     /// Delete a document and all its associated chunks.
-    async fn delete_document(&self, doc_id: &str) -> Result<()>;
+    ///
+    /// Fails with [`PersistenceError::NotFound`] if there is no such document
+    /// owned by `tenant_id`.
+    async fn delete_document(&self, doc_id: &str, tenant_id: Option<&str>) -> Result<()>;
+
+    // =========================================================================
+    // Knowledge Graph
+    // =========================================================================
+
+    /// Save a graph node, replacing the one with the same ID.
+    ///
+    /// Graphs belong to their knowledge base; callers check access to it.
+    async fn save_graph_node(&self, node: &GraphNode) -> Result<()>;
+
+    /// Save a graph edge, replacing the one with the same ID.
+    async fn save_graph_edge(&self, edge: &GraphEdge) -> Result<()>;
+
+    /// The nodes of `kb_id`'s graph within `depth` edges of `node_ids`
+    /// (following edges either way) and the edges between them; no
+    /// `node_ids` returns the whole graph.
+    async fn get_subgraph(
+        &self,
+        kb_id: &str,
+        node_ids: &[&str],
+        depth: usize,
+    ) -> Result<Subgraph>;
 
     // =========================================================================
     // Agent Persistence
     // =========================================================================
 
+    /// Save or update an agent.
+    ///
+    /// Fails with [`PersistenceError::AlreadyExists`] if its ID is taken by
+    /// an agent of another tenant, or by a shared one.
     async fn save_agent(&self, agent: &crate::uar::domain::artifact::AgentArtifact) -> Result<()>;
     async fn load_agent(
         &self,
         id: &str,
+        tenant_id: Option<&str>,
     ) -> Result<Option<crate::uar::domain::artifact::AgentArtifact>>;
     async fn load_agent_by_name(
         &self,
         name: &str,
+        tenant_id: Option<&str>,
     ) -> Result<Option<crate::uar::domain::artifact::AgentArtifact>>;
-    async fn list_agents(&self) -> Result<Vec<crate::uar::domain::artifact::AgentArtifact>>;
+    async fn list_agents(
+        &self,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<crate::uar::domain::artifact::AgentArtifact>>;
+
+    // =========================================================================
+    // Run Accounting
+    // =========================================================================
+
+    /// Save the token usage and cost of a finished run.
+    async fn save_run_usage(&self, usage: &crate::uar::domain::runs::RunUsage) -> Result<()>;
+
+    /// Load the recorded usage of a run.
+    async fn load_run_usage(
+        &self,
+        run_id: &str,
+    ) -> Result<Option<crate::uar::domain::runs::RunUsage>>;
+
+    /// Save the verbatim LLM exchange of a finished run.
+    async fn save_run_log(&self, log: &crate::uar::domain::runs::RunLog) -> Result<()>;
+
+    /// Load the recorded LLM exchange of a run, if it is visible to
+    /// `tenant_id`.
+    async fn get_run_log(
+        &self,
+        run_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<crate::uar::domain::runs::RunLog>>;
+
+    /// Archive a finished run with its output.
+    async fn save_run_record(&self, record: &crate::uar::domain::runs::RunRecord) -> Result<()>;
+
+    /// Load an archived run visible to `tenant_id`.
+    async fn get_run_record(
+        &self,
+        run_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<crate::uar::domain::runs::RunRecord>>;
+
+    /// Archived runs visible to `tenant_id`, most recently finished first.
+    ///
+    /// `session_id` and `agent_id` narrow the list; `after` is the run ID
+    /// the previous page ended with, continuing past it.
+    async fn list_runs(
+        &self,
+        session_id: Option<&str>,
+        agent_id: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<crate::uar::domain::runs::RunRecord>>;
+
+    // =========================================================================
+    // Audit Trail
+    // =========================================================================
+
+    /// Append an entry to the audit trail.
+    async fn save_audit_entry(&self, entry: &crate::uar::domain::audit::AuditEntry) -> Result<()>;
+
+    /// Most recent audit entries first, optionally only for one resource.
+    ///
+    /// With `tenant_id` only the entries recorded in that tenant are listed;
+    /// `None` lists the entries of every tenant.
+    async fn list_audit_entries(
+        &self,
+        resource: Option<crate::uar::domain::audit::AuditResource>,
+        tenant_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<crate::uar::domain::audit::AuditEntry>>;
 
     // =========================================================================
     // Memory System
     // =========================================================================
 
+    /// Save or update a memory.
+    ///
+    /// Fails with [`PersistenceError::AlreadyExists`] if its ID is taken by
+    /// a memory of another tenant, or by a shared one.
     async fn save_memory(&self, memory: &crate::uar::domain::memory::Memory) -> Result<()>;
     async fn search_memory(
         &self,
@@ -109,5 +376,6 @@ pub trait PersistenceLayer: Send + Sync + std::fmt::Debug {
         query_vec: &[f32],
         limit: usize,
         min_score: f32,
+        tenant_id: Option<&str>,
     ) -> Result<Vec<crate::uar::domain::memory::MemoryMatch>>;
 }
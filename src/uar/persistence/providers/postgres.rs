@@ -1,14 +1,17 @@
 use crate::session::Session;
+use crate::uar::domain::graph::{GraphEdge, GraphNode, Subgraph};
 use crate::uar::domain::knowledge::{
     DocumentStatus, KnowledgeBase, KnowledgeChunk, KnowledgeDocument, KnowledgeMatch,
 };
 use crate::uar::domain::skills::{Skill, SkillMatch};
-use crate::uar::persistence::PersistenceLayer;
-use anyhow::Result;
+use crate::uar::persistence::{PersistenceError, PersistenceLayer, Result};
+use crate::uar::rag::embedding::{
+    STORAGE_DIMENSIONS, dimension_reconciliation_enabled, reconcile_dimensions,
+};
 use async_trait::async_trait;
 use pgvector::Vector;
 use sqlx::postgres::PgPoolOptions;
-use sqlx::{PgPool, Row};
+use sqlx::{PgExecutor, PgPool, Row};
 
 #[derive(Debug)]
 pub struct PostgresProvider {
@@ -16,7 +19,7 @@ pub struct PostgresProvider {
 }
 
 impl PostgresProvider {
-    pub async fn new(connection_string: &str) -> Result<Self> {
+    pub async fn new(connection_string: &str) -> anyhow::Result<Self> {
         let pool = PgPoolOptions::new()
             .max_connections(5)
             .connect(connection_string)
@@ -33,35 +36,232 @@ impl PostgresProvider {
     }
 }
 
+impl From<sqlx::Error> for PersistenceError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                Self::DuplicateName(db.message().to_string())
+            }
+            sqlx::Error::Database(db) => match vector_dimensions(db.message()) {
+                Some((expected, actual)) => Self::VectorDimensionMismatch { expected, actual },
+                None => Self::Other(sqlx::Error::Database(db).into()),
+            },
+            sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::WorkerCrashed => Self::ConnectionError(e.to_string()),
+            sqlx::Error::ColumnDecode { .. } | sqlx::Error::Decode(_) => {
+                Self::SerializationError(e.to_string())
+            }
+            e => Self::Other(e.into()),
+        }
+    }
+}
+
+/// Read a BIGINT token counter.
+fn token_count(row: &sqlx::postgres::PgRow, column: &str) -> Result<u32> {
+    let count: i64 = row.try_get(column)?;
+    u32::try_from(count).map_err(|e| PersistenceError::SerializationError(format!("{column}: {e}")))
+}
+
+/// Parse pgvector's "expected 384 dimensions, not 768" error.
+fn vector_dimensions(message: &str) -> Option<(usize, usize)> {
+    let (expected, actual) = message
+        .strip_prefix("expected ")?
+        .split_once(" dimensions, not ")?;
+    Some((expected.parse().ok()?, actual.trim().parse().ok()?))
+}
+
+/// `embedding` as a value of the `VECTOR(384)` columns.
+///
+/// Vectors of another dimensionality are rejected, or padded and truncated
+/// while `embedding.reconcile_dimensions` is on.
+fn storage_vector(embedding: &[f32]) -> Result<Vector> {
+    let fitted = reconcile_dimensions(
+        embedding,
+        STORAGE_DIMENSIONS,
+        dimension_reconciliation_enabled(),
+    )
+    .map_err(|e| PersistenceError::VectorDimensionMismatch {
+        expected: e.expected,
+        actual: e.actual,
+    })?;
+    Ok(Vector::from(fitted.into_owned()))
+}
+
+/// Insert or update `doc`, on the pool or inside a transaction.
+async fn upsert_document<'e>(executor: impl PgExecutor<'e>, doc: &KnowledgeDocument) -> Result<()> {
+    let status_str = match &doc.status {
+        DocumentStatus::Pending => "pending",
+        DocumentStatus::Processing => "processing",
+        DocumentStatus::Indexed => "indexed",
+        DocumentStatus::Failed { .. } => "failed",
+    };
+    let error_msg = match &doc.status {
+        DocumentStatus::Failed { error } => Some(error.clone()),
+        _ => None,
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO knowledge_documents (id, kb_id, filename, file_path, mime_type, chunk_count, status, error_message, tenant_id, metadata, idempotency_key, content_hash, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, NOW(), NOW())
+        ON CONFLICT (id) DO UPDATE SET
+            filename = EXCLUDED.filename,
+            file_path = EXCLUDED.file_path,
+            mime_type = EXCLUDED.mime_type,
+            chunk_count = EXCLUDED.chunk_count,
+            status = EXCLUDED.status,
+            error_message = EXCLUDED.error_message,
+            metadata = EXCLUDED.metadata,
+            idempotency_key = EXCLUDED.idempotency_key,
+            content_hash = EXCLUDED.content_hash,
+            updated_at = NOW()
+        "#,
+    )
+    .bind(&doc.id)
+    .bind(&doc.kb_id)
+    .bind(&doc.filename)
+    .bind(&doc.file_path)
+    .bind(&doc.mime_type)
+    .bind(doc.chunk_count as i32)
+    .bind(status_str)
+    .bind(error_msg)
+    .bind(&doc.tenant_id)
+    .bind(&doc.metadata)
+    .bind(&doc.idempotency_key)
+    .bind(&doc.content_hash)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Read a `knowledge_documents` row.
+fn document_from_row(row: &sqlx::postgres::PgRow) -> Result<KnowledgeDocument> {
+    let mime_type: String = row.try_get("mime_type")?;
+    let chunk_count: i32 = row.try_get("chunk_count")?;
+    let status_str: String = row.try_get("status")?;
+    let error_message: Option<String> = row.try_get("error_message")?;
+    let created_at: Option<chrono::DateTime<chrono::Utc>> = row.try_get("created_at")?;
+    let updated_at: Option<chrono::DateTime<chrono::Utc>> = row.try_get("updated_at")?;
+
+    let status = match status_str.as_str() {
+        "processing" => DocumentStatus::Processing,
+        "indexed" => DocumentStatus::Indexed,
+        "failed" => DocumentStatus::Failed {
+            error: error_message.unwrap_or_default(),
+        },
+        _ => DocumentStatus::Pending,
+    };
+
+    Ok(KnowledgeDocument {
+        id: row.try_get("id")?,
+        kb_id: row.try_get("kb_id")?,
+        filename: row.try_get("filename")?,
+        file_path: row.try_get("file_path")?,
+        mime_type: Some(mime_type),
+        chunk_count: chunk_count as usize,
+        status,
+        tenant_id: row.try_get("tenant_id")?,
+        metadata: row.try_get("metadata")?,
+        idempotency_key: row.try_get("idempotency_key")?,
+        content_hash: row.try_get("content_hash")?,
+        created_at: created_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
+        updated_at: updated_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
+    })
+}
+
+/// Insert or update `chunk`, on the pool or inside a transaction.
+fn graph_node_from_row(row: &sqlx::postgres::PgRow) -> Result<GraphNode> {
+    Ok(GraphNode {
+        id: row.try_get("id")?,
+        label: row.try_get("label")?,
+        entity_type: serde_json::from_value(row.try_get("entity_type")?)?,
+        properties: row.try_get("properties")?,
+        kb_id: row.try_get("kb_id")?,
+    })
+}
+
+fn graph_edge_from_row(row: &sqlx::postgres::PgRow) -> Result<GraphEdge> {
+    Ok(GraphEdge {
+        id: row.try_get("id")?,
+        source_id: row.try_get("source_id")?,
+        target_id: row.try_get("target_id")?,
+        relationship_type: row.try_get("relationship_type")?,
+        weight: row.try_get("weight")?,
+        kb_id: row.try_get("kb_id")?,
+    })
+}
+
+async fn upsert_chunk<'e>(executor: impl PgExecutor<'e>, chunk: &KnowledgeChunk) -> Result<()> {
+    let embedding_vector = storage_vector(&chunk.embedding)?;
+    let metadata = serde_json::to_value(&chunk.metadata)?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO knowledge_chunks (id, kb_id, document_id, content, metadata, embedding, tenant_id, content_hash, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+        ON CONFLICT (id) DO UPDATE SET
+            document_id = EXCLUDED.document_id,
+            content = EXCLUDED.content,
+            metadata = EXCLUDED.metadata,
+            embedding = EXCLUDED.embedding,
+            content_hash = EXCLUDED.content_hash
+        "#,
+    )
+    .bind(chunk.id)
+    .bind(&chunk.kb_id)
+    .bind(&chunk.document_id)
+    .bind(&chunk.content)
+    .bind(metadata)
+    .bind(embedding_vector)
+    .bind(&chunk.tenant_id)
+    .bind(chunk.content_hash())
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
 #[async_trait]
 impl PersistenceLayer for PostgresProvider {
+    async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
     async fn save_session(&self, session: &Session) -> Result<()> {
         let id = session.id();
-        // Serialize session to JSON
+        // Serialize session to JSON; a session never moves between tenants
         let data = serde_json::to_value(session)?;
 
         sqlx::query(
             r#"
-            INSERT INTO sessions (id, data, created_at, updated_at)
-            VALUES ($1, $2, NOW(), NOW())
+            INSERT INTO sessions (id, data, tenant_id, created_at, updated_at)
+            VALUES ($1, $2, $3, NOW(), NOW())
             ON CONFLICT (id) DO UPDATE SET
                 data = EXCLUDED.data,
                 updated_at = NOW()
+            WHERE sessions.tenant_id IS NOT DISTINCT FROM EXCLUDED.tenant_id
             "#,
         )
         .bind(id)
         .bind(data)
+        .bind(session.tenant_id())
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    async fn load_session(&self, id: &str) -> Result<Option<Session>> {
-        let row = sqlx::query("SELECT data FROM sessions WHERE id = $1")
-            .bind(id)
-            .fetch_optional(&self.pool)
-            .await?;
+    async fn load_session(&self, id: &str, tenant_id: Option<&str>) -> Result<Option<Session>> {
+        let row = sqlx::query(
+            "SELECT data FROM sessions WHERE id = $1 AND (tenant_id = $2 OR tenant_id IS NULL)",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .fetch_optional(&self.pool)
+        .await?;
 
         if let Some(row) = row {
             let val: serde_json::Value = row.try_get("data")?;
@@ -73,7 +273,7 @@ impl PersistenceLayer for PostgresProvider {
     }
 
     async fn save_skill(&self, skill: &Skill, embedding: &[f32]) -> Result<()> {
-        let embedding_vector = Vector::from(embedding.to_vec());
+        let embedding_vector = storage_vector(embedding)?;
         let definition = serde_json::to_value(skill)?;
 
         sqlx::query(
@@ -98,8 +298,38 @@ impl PersistenceLayer for PostgresProvider {
         Ok(())
     }
 
+    async fn save_skill_version(&self, skill: &Skill) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO skill_versions (skill_id, version, definition)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (skill_id, version) DO UPDATE SET
+                definition = EXCLUDED.definition
+            "#,
+        )
+        .bind(&skill.skill_id)
+        .bind(&skill.version)
+        .bind(serde_json::to_value(skill)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_skill_versions(&self, skill_id: &str) -> Result<Vec<Skill>> {
+        let rows = sqlx::query("SELECT definition FROM skill_versions WHERE skill_id = $1")
+            .bind(skill_id)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .map(|row| {
+                let definition: serde_json::Value = row.try_get("definition")?;
+                Ok(serde_json::from_value(definition)?)
+            })
+            .collect()
+    }
+
     async fn search_skills(&self, query_vec: &[f32], limit: usize) -> Result<Vec<SkillMatch>> {
-        let embedding_vector = Vector::from(query_vec.to_vec());
+        let embedding_vector = storage_vector(query_vec)?;
         let limit_i64 = limit as i64;
 
         let rows = sqlx::query(
@@ -135,48 +365,84 @@ impl PersistenceLayer for PostgresProvider {
     async fn save_knowledge_base(&self, kb: &KnowledgeBase) -> Result<()> {
         let config = serde_json::to_value(&kb.config)?;
 
-        sqlx::query(
+        // A knowledge base never moves between tenants
+        let result = sqlx::query(
             r#"
-            INSERT INTO knowledge_bases (id, name, description, config, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, NOW(), NOW())
+            INSERT INTO knowledge_bases (id, name, description, config, tenant_id, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, NOW(), NOW())
             ON CONFLICT (id) DO UPDATE SET
                 name = EXCLUDED.name,
                 description = EXCLUDED.description,
                 config = EXCLUDED.config,
                 updated_at = NOW()
+            WHERE knowledge_bases.tenant_id IS NOT DISTINCT FROM EXCLUDED.tenant_id
             "#,
         )
         .bind(&kb.id)
         .bind(&kb.name)
         .bind(&kb.description)
         .bind(config)
+        .bind(&kb.tenant_id)
         .execute(&self.pool)
-        .await?;
+        .await
+        .map_err(|e| match PersistenceError::from(e) {
+            PersistenceError::DuplicateName(_) => PersistenceError::DuplicateName(kb.name.clone()),
+            other => other,
+        })?;
+        if result.rows_affected() == 0 {
+            return Err(PersistenceError::already_exists("knowledge base", &kb.id));
+        }
         Ok(())
     }
 
     async fn save_chunk(&self, chunk: &KnowledgeChunk) -> Result<()> {
-        let embedding_vector = Vector::from(chunk.embedding.clone());
-        let metadata = serde_json::to_value(&chunk.metadata)?;
+        upsert_chunk(&self.pool, chunk).await
+    }
 
-        sqlx::query(
-            r#"
-            INSERT INTO knowledge_chunks (id, kb_id, content, metadata, embedding, created_at)
-            VALUES ($1, $2, $3, $4, $5, NOW())
-            ON CONFLICT (id) DO UPDATE SET
-                content = EXCLUDED.content,
-                metadata = EXCLUDED.metadata,
-                embedding = EXCLUDED.embedding
-            "#,
+    async fn chunk_exists(&self, kb_id: &str, content_hash: &str) -> Result<bool> {
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM knowledge_chunks WHERE kb_id = $1 AND content_hash = $2)",
         )
-        .bind(chunk.id)
-        .bind(&chunk.kb_id)
-        .bind(&chunk.content)
-        .bind(metadata)
-        .bind(embedding_vector)
-        .execute(&self.pool)
+        .bind(kb_id)
+        .bind(content_hash)
+        .fetch_one(&self.pool)
         .await?;
-        Ok(())
+        Ok(exists)
+    }
+
+    async fn list_chunks(
+        &self,
+        kb_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<KnowledgeChunk>> {
+        let rows = sqlx::query(
+            "SELECT id, kb_id, document_id, content, metadata, tenant_id, created_at FROM knowledge_chunks WHERE kb_id = $1 AND (tenant_id = $2 OR tenant_id IS NULL) ORDER BY document_id, created_at",
+        )
+        .bind(kb_id)
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut chunks = Vec::with_capacity(rows.len());
+        for row in rows {
+            let created_at: Option<chrono::DateTime<chrono::Utc>> = row.try_get("created_at")?;
+            chunks.push(KnowledgeChunk {
+                id: row.try_get("id")?,
+                kb_id: row.try_get("kb_id")?,
+                document_id: row.try_get("document_id")?,
+                content: row.try_get("content")?,
+                metadata: row.try_get("metadata")?,
+                embedding: vec![],
+                tenant_id: row.try_get("tenant_id")?,
+                created_at: created_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
+            });
+        }
+        Ok(chunks)
+    }
+
+    async fn list_chunk_embeddings(&self, _kb_id: &str) -> Result<Option<Vec<KnowledgeChunk>>> {
+        // pgvector indexes the embeddings
+        Ok(None)
     }
 
     async fn search_knowledge(
@@ -184,16 +450,18 @@ impl PersistenceLayer for PostgresProvider {
         query_vec: &[f32],
         limit: usize,
         min_score: f32,
+        tenant_id: Option<&str>,
     ) -> Result<Vec<KnowledgeMatch>> {
-        let embedding_vector = Vector::from(query_vec.to_vec());
+        let embedding_vector = storage_vector(query_vec)?;
         let limit_i64 = limit as i64;
         let min_score_f64 = min_score as f64;
 
         let rows = sqlx::query(
             r#"
-            SELECT id, kb_id, content, metadata, created_at, 1 - (embedding <=> $1) as score
+            SELECT id, kb_id, content, metadata, tenant_id, created_at, 1 - (embedding <=> $1) as score
             FROM knowledge_chunks
             WHERE 1 - (embedding <=> $1) >= $3
+              AND (tenant_id = $4 OR tenant_id IS NULL)
             ORDER BY embedding <=> $1
             LIMIT $2
             "#,
@@ -201,6 +469,7 @@ impl PersistenceLayer for PostgresProvider {
         .bind(embedding_vector) // $1
         .bind(limit_i64) // $2
         .bind(min_score_f64) // $3
+        .bind(tenant_id) // $4
         .fetch_all(&self.pool)
         .await?;
 
@@ -233,6 +502,7 @@ impl PersistenceLayer for PostgresProvider {
                 content,
                 metadata: metadata_val,
                 embedding: vec![], // we don't return embedding in search results unless needed
+                tenant_id: row.try_get("tenant_id")?,
                 created_at: created_at_str,
             };
 
@@ -248,15 +518,17 @@ impl PersistenceLayer for PostgresProvider {
     async fn save_agent(&self, agent: &crate::uar::domain::artifact::AgentArtifact) -> Result<()> {
         let definition = serde_json::to_value(agent)?;
 
-        sqlx::query(
+        // An agent never moves between tenants
+        let result = sqlx::query(
             r#"
-            INSERT INTO agents (id, name, version, definition, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, NOW(), NOW())
+            INSERT INTO agents (id, name, version, definition, tenant_id, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, NOW(), NOW())
             ON CONFLICT (id) DO UPDATE SET
                 name = EXCLUDED.name,
                 version = EXCLUDED.version,
                 definition = EXCLUDED.definition,
                 updated_at = NOW()
+            WHERE agents.tenant_id IS NOT DISTINCT FROM EXCLUDED.tenant_id
             "#,
         )
         .bind(&agent.id)
@@ -267,19 +539,27 @@ impl PersistenceLayer for PostgresProvider {
         // Assuming metadata.title is the name for now.
         .bind(&agent.version)
         .bind(definition)
+        .bind(&agent.tenant_id)
         .execute(&self.pool)
         .await?;
+        if result.rows_affected() == 0 {
+            return Err(PersistenceError::already_exists("agent", &agent.id));
+        }
         Ok(())
     }
 
     async fn load_agent(
         &self,
         id: &str,
+        tenant_id: Option<&str>,
     ) -> Result<Option<crate::uar::domain::artifact::AgentArtifact>> {
-        let row = sqlx::query("SELECT definition FROM agents WHERE id = $1")
-            .bind(id)
-            .fetch_optional(&self.pool)
-            .await?;
+        let row = sqlx::query(
+            "SELECT definition FROM agents WHERE id = $1 AND (tenant_id = $2 OR tenant_id IS NULL)",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .fetch_optional(&self.pool)
+        .await?;
 
         if let Some(row) = row {
             let val: serde_json::Value = row.try_get("definition")?;
@@ -293,11 +573,21 @@ impl PersistenceLayer for PostgresProvider {
     async fn load_agent_by_name(
         &self,
         name: &str,
+        tenant_id: Option<&str>,
     ) -> Result<Option<crate::uar::domain::artifact::AgentArtifact>> {
-        let row = sqlx::query("SELECT definition FROM agents WHERE name = $1")
-            .bind(name)
-            .fetch_optional(&self.pool)
-            .await?;
+        // A tenant's own agent shadows a shared one of the same name
+        let row = sqlx::query(
+            r#"
+            SELECT definition FROM agents
+            WHERE name = $1 AND (tenant_id = $2 OR tenant_id IS NULL)
+            ORDER BY tenant_id NULLS LAST
+            LIMIT 1
+            "#,
+        )
+        .bind(name)
+        .bind(tenant_id)
+        .fetch_optional(&self.pool)
+        .await?;
 
         if let Some(row) = row {
             let val: serde_json::Value = row.try_get("definition")?;
@@ -308,10 +598,15 @@ impl PersistenceLayer for PostgresProvider {
         }
     }
 
-    async fn list_agents(&self) -> Result<Vec<crate::uar::domain::artifact::AgentArtifact>> {
-        let rows = sqlx::query("SELECT definition FROM agents")
-            .fetch_all(&self.pool)
-            .await?;
+    async fn list_agents(
+        &self,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<crate::uar::domain::artifact::AgentArtifact>> {
+        let rows =
+            sqlx::query("SELECT definition FROM agents WHERE tenant_id = $1 OR tenant_id IS NULL")
+                .bind(tenant_id)
+                .fetch_all(&self.pool)
+                .await?;
 
         let mut agents = Vec::new();
         for row in rows {
@@ -322,19 +617,250 @@ impl PersistenceLayer for PostgresProvider {
         Ok(agents)
     }
 
+    // Run Accounting
+    async fn save_run_usage(&self, usage: &crate::uar::domain::runs::RunUsage) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO run_costs
+                (run_id, agent_id, session_id, model, prompt_tokens, completion_tokens, total_tokens, cost_usd)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (run_id) DO UPDATE SET
+                prompt_tokens = EXCLUDED.prompt_tokens,
+                completion_tokens = EXCLUDED.completion_tokens,
+                total_tokens = EXCLUDED.total_tokens,
+                cost_usd = EXCLUDED.cost_usd
+            "#,
+        )
+        .bind(&usage.run_id)
+        .bind(&usage.agent_id)
+        .bind(&usage.session_id)
+        .bind(&usage.model)
+        .bind(i64::from(usage.tokens.prompt_tokens))
+        .bind(i64::from(usage.tokens.completion_tokens))
+        .bind(i64::from(usage.tokens.total_tokens))
+        .bind(usage.cost_usd)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn load_run_usage(
+        &self,
+        run_id: &str,
+    ) -> Result<Option<crate::uar::domain::runs::RunUsage>> {
+        let row = sqlx::query("SELECT * FROM run_costs WHERE run_id = $1")
+            .bind(run_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        Ok(Some(crate::uar::domain::runs::RunUsage {
+            run_id: row.try_get("run_id")?,
+            agent_id: row.try_get("agent_id")?,
+            session_id: row.try_get("session_id")?,
+            model: row.try_get("model")?,
+            tokens: crate::uar::domain::runs::TokenUsage {
+                prompt_tokens: token_count(&row, "prompt_tokens")?,
+                completion_tokens: token_count(&row, "completion_tokens")?,
+                total_tokens: token_count(&row, "total_tokens")?,
+            },
+            cost_usd: row.try_get("cost_usd")?,
+        }))
+    }
+
+    async fn save_run_log(&self, log: &crate::uar::domain::runs::RunLog) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO run_logs
+                (run_id, agent_id, tenant_id, timestamp, message_history, response_text, tool_calls, prompt_tokens, completion_tokens)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (run_id) DO UPDATE SET
+                message_history = EXCLUDED.message_history,
+                response_text = EXCLUDED.response_text,
+                tool_calls = EXCLUDED.tool_calls,
+                prompt_tokens = EXCLUDED.prompt_tokens,
+                completion_tokens = EXCLUDED.completion_tokens
+            "#,
+        )
+        .bind(&log.run_id)
+        .bind(&log.agent_id)
+        .bind(&log.tenant_id)
+        .bind(&log.timestamp)
+        .bind(serde_json::to_value(&log.message_history)?)
+        .bind(&log.response_text)
+        .bind(serde_json::to_value(&log.tool_calls)?)
+        .bind(i64::from(log.prompt_tokens))
+        .bind(i64::from(log.completion_tokens))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_run_log(
+        &self,
+        run_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<crate::uar::domain::runs::RunLog>> {
+        let row = sqlx::query(
+            "SELECT * FROM run_logs WHERE run_id = $1 AND (tenant_id = $2 OR tenant_id IS NULL)",
+        )
+        .bind(run_id)
+        .bind(tenant_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let message_history: serde_json::Value = row.try_get("message_history")?;
+        let tool_calls: serde_json::Value = row.try_get("tool_calls")?;
+        Ok(Some(crate::uar::domain::runs::RunLog {
+            run_id: row.try_get("run_id")?,
+            agent_id: row.try_get("agent_id")?,
+            tenant_id: row.try_get("tenant_id")?,
+            timestamp: row.try_get("timestamp")?,
+            message_history: serde_json::from_value(message_history)?,
+            response_text: row.try_get("response_text")?,
+            tool_calls: serde_json::from_value(tool_calls)?,
+            prompt_tokens: token_count(&row, "prompt_tokens")?,
+            completion_tokens: token_count(&row, "completion_tokens")?,
+        }))
+    }
+
+    async fn save_run_record(&self, record: &crate::uar::domain::runs::RunRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO run_records (run_id, agent_id, session_id, tenant_id, finished_at, data)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (run_id) DO UPDATE SET
+                finished_at = EXCLUDED.finished_at,
+                data = EXCLUDED.data
+            "#,
+        )
+        .bind(&record.run.run_id)
+        .bind(&record.run.agent_id)
+        .bind(&record.run.conversation_id)
+        .bind(&record.run.tenant_id)
+        .bind(&record.finished_at)
+        .bind(serde_json::to_value(record)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_run_record(
+        &self,
+        run_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<crate::uar::domain::runs::RunRecord>> {
+        let row = sqlx::query(
+            "SELECT data FROM run_records WHERE run_id = $1 AND (tenant_id = $2 OR tenant_id IS NULL)",
+        )
+        .bind(run_id)
+        .bind(tenant_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let data: serde_json::Value = row.try_get("data")?;
+        Ok(Some(serde_json::from_value(data)?))
+    }
+
+    async fn list_runs(
+        &self,
+        session_id: Option<&str>,
+        agent_id: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<crate::uar::domain::runs::RunRecord>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT data FROM run_records
+            WHERE ($1::TEXT IS NULL OR session_id = $1)
+              AND ($2::TEXT IS NULL OR agent_id = $2)
+              AND ($3::TEXT IS NULL OR (finished_at, run_id) <
+                  (SELECT finished_at, run_id FROM run_records WHERE run_id = $3))
+              AND (tenant_id = $4 OR tenant_id IS NULL)
+            ORDER BY finished_at DESC, run_id DESC
+            LIMIT $5
+            "#,
+        )
+        .bind(session_id)
+        .bind(agent_id)
+        .bind(after)
+        .bind(tenant_id)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut runs = Vec::with_capacity(rows.len());
+        for row in rows {
+            let data: serde_json::Value = row.try_get("data")?;
+            runs.push(serde_json::from_value(data)?);
+        }
+        Ok(runs)
+    }
+
+    // Audit Trail
+    async fn save_audit_entry(&self, entry: &crate::uar::domain::audit::AuditEntry) -> Result<()> {
+        sqlx::query("INSERT INTO audit_log (id, resource, data) VALUES ($1, $2, $3)")
+            .bind(&entry.id)
+            .bind(entry.resource.as_str())
+            .bind(serde_json::to_value(entry)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn list_audit_entries(
+        &self,
+        resource: Option<crate::uar::domain::audit::AuditResource>,
+        tenant_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<crate::uar::domain::audit::AuditEntry>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT data FROM audit_log
+            WHERE ($1::TEXT IS NULL OR resource = $1)
+              AND ($2::TEXT IS NULL OR data->>'tenant_id' = $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(resource.map(|r| r.as_str()))
+        .bind(tenant_id)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for row in rows {
+            let data: serde_json::Value = row.try_get("data")?;
+            entries.push(serde_json::from_value(data)?);
+        }
+        Ok(entries)
+    }
+
     // Memory System
     async fn save_memory(&self, memory: &crate::uar::domain::memory::Memory) -> Result<()> {
-        let embedding_vector = Vector::from(memory.embedding.clone());
+        let embedding_vector = storage_vector(&memory.embedding)?;
 
-        sqlx::query(
+        // A memory never moves between tenants
+        let result = sqlx::query(
             r#"
-            INSERT INTO memories (id, agent_id, content, tags, embedding, created_at)
-            VALUES ($1, $2, $3, $4, $5, NOW())
+            INSERT INTO memories (id, agent_id, content, tags, embedding, tenant_id, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, NOW())
             ON CONFLICT (id) DO UPDATE SET
                 agent_id = EXCLUDED.agent_id,
                 content = EXCLUDED.content,
                 tags = EXCLUDED.tags,
                 embedding = EXCLUDED.embedding
+            WHERE memories.tenant_id IS NOT DISTINCT FROM EXCLUDED.tenant_id
             "#,
         )
         .bind(&memory.id)
@@ -342,8 +868,12 @@ impl PersistenceLayer for PostgresProvider {
         .bind(&memory.content)
         .bind(&memory.tags)
         .bind(embedding_vector)
+        .bind(&memory.tenant_id)
         .execute(&self.pool)
         .await?;
+        if result.rows_affected() == 0 {
+            return Err(PersistenceError::already_exists("memory", &memory.id));
+        }
         Ok(())
     }
 
@@ -353,8 +883,9 @@ impl PersistenceLayer for PostgresProvider {
         query_vec: &[f32],
         limit: usize,
         min_score: f32,
+        tenant_id: Option<&str>,
     ) -> Result<Vec<crate::uar::domain::memory::MemoryMatch>> {
-        let embedding_vector = Vector::from(query_vec.to_vec());
+        let embedding_vector = storage_vector(query_vec)?;
         let limit_i64 = limit as i64;
         let min_score_f64 = min_score as f64;
 
@@ -363,9 +894,10 @@ impl PersistenceLayer for PostgresProvider {
         // If $1 is 'A', it matches 'A' and Global.
         let rows = sqlx::query(
             r#"
-            SELECT id, agent_id, content, tags, created_at, 1 - (embedding <=> $2) as score
+            SELECT id, agent_id, content, tags, tenant_id, created_at, 1 - (embedding <=> $2) as score
             FROM memories
             WHERE (agent_id = $1 OR agent_id IS NULL)
+              AND (tenant_id = $5 OR tenant_id IS NULL)
               AND 1 - (embedding <=> $2) >= $3
             ORDER BY embedding <=> $2
             LIMIT $4
@@ -375,6 +907,7 @@ impl PersistenceLayer for PostgresProvider {
         .bind(embedding_vector) // $2
         .bind(min_score_f64) // $3
         .bind(limit_i64) // $4
+        .bind(tenant_id) // $5
         .fetch_all(&self.pool)
         .await?;
 
@@ -396,6 +929,7 @@ impl PersistenceLayer for PostgresProvider {
                 content,
                 tags,
                 embedding: vec![],
+                tenant_id: row.try_get("tenant_id")?,
                 created_at: created_at_str,
             };
 
@@ -411,11 +945,16 @@ impl PersistenceLayer for PostgresProvider {
     // Knowledge Base Retrieval Methods
     // =========================================================================
 
-    async fn get_knowledge_base(&self, id: &str) -> Result<Option<KnowledgeBase>> {
+    async fn get_knowledge_base(
+        &self,
+        id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeBase>> {
         let row = sqlx::query(
-            "SELECT id, name, description, config, created_at, updated_at FROM knowledge_bases WHERE id = $1",
+            "SELECT id, name, description, config, tenant_id, created_at, updated_at FROM knowledge_bases WHERE id = $1 AND (tenant_id = $2 OR tenant_id IS NULL)",
         )
         .bind(id)
+        .bind(tenant_id)
         .fetch_optional(&self.pool)
         .await?;
 
@@ -433,6 +972,7 @@ impl PersistenceLayer for PostgresProvider {
                 name: name.unwrap_or_default(),
                 description,
                 config,
+                tenant_id: row.try_get("tenant_id")?,
                 created_at: created_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
                 updated_at: updated_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
             }))
@@ -441,11 +981,23 @@ impl PersistenceLayer for PostgresProvider {
         }
     }
 
-    async fn get_knowledge_base_by_name(&self, name: &str) -> Result<Option<KnowledgeBase>> {
+    async fn get_knowledge_base_by_name(
+        &self,
+        name: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeBase>> {
+        // A tenant's own knowledge base shadows a shared one of the same name
         let row = sqlx::query(
-            "SELECT id, name, description, config, created_at, updated_at FROM knowledge_bases WHERE name = $1",
+            r#"
+            SELECT id, name, description, config, tenant_id, created_at, updated_at
+            FROM knowledge_bases
+            WHERE name = $1 AND (tenant_id = $2 OR tenant_id IS NULL)
+            ORDER BY tenant_id NULLS LAST
+            LIMIT 1
+            "#,
         )
         .bind(name)
+        .bind(tenant_id)
         .fetch_optional(&self.pool)
         .await?;
 
@@ -463,6 +1015,7 @@ impl PersistenceLayer for PostgresProvider {
                 name: name.unwrap_or_default(),
                 description,
                 config,
+                tenant_id: row.try_get("tenant_id")?,
                 created_at: created_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
                 updated_at: updated_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
             }))
@@ -471,10 +1024,11 @@ impl PersistenceLayer for PostgresProvider {
         }
     }
 
-    async fn list_knowledge_bases(&self) -> Result<Vec<KnowledgeBase>> {
+    async fn list_knowledge_bases(&self, tenant_id: Option<&str>) -> Result<Vec<KnowledgeBase>> {
         let rows = sqlx::query(
-            "SELECT id, name, description, config, created_at, updated_at FROM knowledge_bases ORDER BY created_at",
+            "SELECT id, name, description, config, tenant_id, created_at, updated_at FROM knowledge_bases WHERE tenant_id = $1 OR tenant_id IS NULL ORDER BY created_at",
         )
+        .bind(tenant_id)
         .fetch_all(&self.pool)
         .await?;
 
@@ -493,6 +1047,7 @@ impl PersistenceLayer for PostgresProvider {
                 name: name.unwrap_or_default(),
                 description,
                 config,
+                tenant_id: row.try_get("tenant_id")?,
                 created_at: created_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
                 updated_at: updated_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
             });
@@ -500,12 +1055,18 @@ impl PersistenceLayer for PostgresProvider {
         Ok(kbs)
     }
 
-    async fn delete_knowledge_base(&self, id: &str) -> Result<()> {
+    async fn delete_knowledge_base(&self, id: &str, tenant_id: Option<&str>) -> Result<()> {
         // CASCADE will handle chunks and documents
-        sqlx::query("DELETE FROM knowledge_bases WHERE id = $1")
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
+        let result = sqlx::query(
+            "DELETE FROM knowledge_bases WHERE id = $1 AND tenant_id IS NOT DISTINCT FROM $2",
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(PersistenceError::not_found("knowledge base", id));
+        }
         Ok(())
     }
 
@@ -519,31 +1080,45 @@ impl PersistenceLayer for PostgresProvider {
         query_vec: &[f32],
         limit: usize,
         min_score: f32,
+        metadata_filter: Option<&serde_json::Value>,
+        tenant_id: Option<&str>,
     ) -> Result<Vec<KnowledgeMatch>> {
         if kb_ids.is_empty() {
             return Ok(vec![]);
         }
 
-        let embedding_vector = Vector::from(query_vec.to_vec());
+        let embedding_vector = storage_vector(query_vec)?;
         let limit_i64 = limit as i64;
         let min_score_f64 = min_score as f64;
         let kb_ids_vec: Vec<String> = kb_ids.iter().map(|s| s.to_string()).collect();
 
-        let rows = sqlx::query(
+        // Containment is answered by the GIN index on metadata
+        let metadata_clause = if metadata_filter.is_some() {
+            "AND metadata @> $6"
+        } else {
+            ""
+        };
+        let sql = format!(
             r#"
-            SELECT id, kb_id, document_id, content, metadata, created_at, 1 - (embedding <=> $1) as score
+            SELECT id, kb_id, document_id, content, metadata, tenant_id, created_at, 1 - (embedding <=> $1) as score
             FROM knowledge_chunks
-            WHERE kb_id = ANY($4) AND 1 - (embedding <=> $1) >= $3
+            WHERE kb_id = ANY($4) {metadata_clause}
+              AND (tenant_id = $5 OR tenant_id IS NULL)
+              AND 1 - (embedding <=> $1) >= $3
             ORDER BY embedding <=> $1
             LIMIT $2
-            "#,
-        )
-        .bind(embedding_vector)
-        .bind(limit_i64)
-        .bind(min_score_f64)
-        .bind(&kb_ids_vec)
-        .fetch_all(&self.pool)
-        .await?;
+            "#
+        );
+        let mut query = sqlx::query(&sql)
+            .bind(embedding_vector)
+            .bind(limit_i64)
+            .bind(min_score_f64)
+            .bind(&kb_ids_vec)
+            .bind(tenant_id);
+        if let Some(filter) = metadata_filter {
+            query = query.bind(filter);
+        }
+        let rows = query.fetch_all(&self.pool).await?;
 
         let mut matches = Vec::new();
         for row in rows {
@@ -563,6 +1138,7 @@ impl PersistenceLayer for PostgresProvider {
                 content,
                 metadata: metadata_val,
                 embedding: vec![],
+                tenant_id: row.try_get("tenant_id")?,
                 created_at: created_at_str,
             };
 
@@ -579,132 +1155,72 @@ impl PersistenceLayer for PostgresProvider {
     // =========================================================================
 
     async fn save_document(&self, doc: &KnowledgeDocument) -> Result<()> {
-        let status_str = match &doc.status {
-            DocumentStatus::Pending => "pending",
-            DocumentStatus::Processing => "processing",
-            DocumentStatus::Indexed => "indexed",
-            DocumentStatus::Failed { .. } => "failed",
-        };
-        let error_msg = match &doc.status {
-            DocumentStatus::Failed { error } => Some(error.clone()),
-            _ => None,
-        };
+        upsert_document(&self.pool, doc).await
+    }
 
-        sqlx::query(
-            r#"
-            INSERT INTO knowledge_documents (id, kb_id, filename, file_path, mime_type, chunk_count, status, error_message, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW(), NOW())
-            ON CONFLICT (id) DO UPDATE SET
-                filename = EXCLUDED.filename,
-                file_path = EXCLUDED.file_path,
-                mime_type = EXCLUDED.mime_type,
-                chunk_count = EXCLUDED.chunk_count,
-                status = EXCLUDED.status,
-                error_message = EXCLUDED.error_message,
-                updated_at = NOW()
-            "#,
-        )
-        .bind(&doc.id)
-        .bind(&doc.kb_id)
-        .bind(&doc.filename)
-        .bind(&doc.file_path)
-        .bind(&doc.mime_type)
-        .bind(doc.chunk_count as i32)
-        .bind(status_str)
-        .bind(error_msg)
-        .execute(&self.pool)
-        .await?;
+    async fn save_document_with_chunks(
+        &self,
+        doc: &KnowledgeDocument,
+        chunks: &[KnowledgeChunk],
+    ) -> Result<()> {
+        // Rolled back when dropped without a commit
+        let mut tx = self.pool.begin().await?;
+        upsert_document(&mut *tx, doc).await?;
+        for chunk in chunks {
+            upsert_chunk(&mut *tx, chunk).await?;
+        }
+        tx.commit().await?;
         Ok(())
     }
 
-    async fn get_document(&self, id: &str) -> Result<Option<KnowledgeDocument>> {
+    async fn get_document(
+        &self,
+        id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeDocument>> {
         let row = sqlx::query(
-            "SELECT id, kb_id, filename, file_path, mime_type, chunk_count, status, error_message, created_at, updated_at FROM knowledge_documents WHERE id = $1",
+            "SELECT id, kb_id, filename, file_path, mime_type, chunk_count, status, error_message, tenant_id, metadata, idempotency_key, content_hash, created_at, updated_at FROM knowledge_documents WHERE id = $1 AND (tenant_id = $2 OR tenant_id IS NULL)",
         )
         .bind(id)
+        .bind(tenant_id)
         .fetch_optional(&self.pool)
         .await?;
 
-        if let Some(row) = row {
-            let id: String = row.try_get("id")?;
-            let kb_id: String = row.try_get("kb_id")?;
-            let filename: String = row.try_get("filename")?;
-            let file_path: Option<String> = row.try_get("file_path")?;
-            let mime_type: String = row.try_get("mime_type")?;
-            let chunk_count: i32 = row.try_get("chunk_count")?;
-            let status_str: String = row.try_get("status")?;
-            let error_message: Option<String> = row.try_get("error_message")?;
-            let created_at: Option<chrono::DateTime<chrono::Utc>> = row.try_get("created_at")?;
-            let updated_at: Option<chrono::DateTime<chrono::Utc>> = row.try_get("updated_at")?;
-
-            let status = match status_str.as_str() {
-                "processing" => DocumentStatus::Processing,
-                "indexed" => DocumentStatus::Indexed,
-                "failed" => DocumentStatus::Failed {
-                    error: error_message.unwrap_or_default(),
-                },
-                _ => DocumentStatus::Pending,
-            };
-
-            Ok(Some(KnowledgeDocument {
-                id,
-                kb_id,
-                filename,
-                file_path,
-                mime_type: Some(mime_type),
-                chunk_count: chunk_count as usize,
-                status,
-                created_at: created_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
-                updated_at: updated_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
-            }))
-        } else {
-            Ok(None)
-        }
+        row.as_ref().map(document_from_row).transpose()
     }
 
-    async fn list_documents(&self, kb_id: &str) -> Result<Vec<KnowledgeDocument>> {
+    async fn list_documents(
+        &self,
+        kb_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<KnowledgeDocument>> {
         let rows = sqlx::query(
-            "SELECT id, kb_id, filename, file_path, mime_type, chunk_count, status, error_message, created_at, updated_at FROM knowledge_documents WHERE kb_id = $1 ORDER BY created_at",
+            "SELECT id, kb_id, filename, file_path, mime_type, chunk_count, status, error_message, tenant_id, metadata, idempotency_key, content_hash, created_at, updated_at FROM knowledge_documents WHERE kb_id = $1 AND (tenant_id = $2 OR tenant_id IS NULL) ORDER BY created_at",
         )
         .bind(kb_id)
+        .bind(tenant_id)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut docs = Vec::new();
-        for row in rows {
-            let id: String = row.try_get("id")?;
-            let kb_id: String = row.try_get("kb_id")?;
-            let filename: String = row.try_get("filename")?;
-            let file_path: Option<String> = row.try_get("file_path")?;
-            let mime_type: String = row.try_get("mime_type")?;
-            let chunk_count: i32 = row.try_get("chunk_count")?;
-            let status_str: String = row.try_get("status")?;
-            let error_message: Option<String> = row.try_get("error_message")?;
-            let created_at: Option<chrono::DateTime<chrono::Utc>> = row.try_get("created_at")?;
-            let updated_at: Option<chrono::DateTime<chrono::Utc>> = row.try_get("updated_at")?;
+        rows.iter().map(document_from_row).collect()
+    }
 
-            let status = match status_str.as_str() {
-                "processing" => DocumentStatus::Processing,
-                "indexed" => DocumentStatus::Indexed,
-                "failed" => DocumentStatus::Failed {
-                    error: error_message.unwrap_or_default(),
-                },
-                _ => DocumentStatus::Pending,
-            };
+    async fn find_document_by_idempotency_key(
+        &self,
+        kb_id: &str,
+        idempotency_key: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeDocument>> {
+        let row = sqlx::query(
+            "SELECT id, kb_id, filename, file_path, mime_type, chunk_count, status, error_message, tenant_id, metadata, idempotency_key, content_hash, created_at, updated_at FROM knowledge_documents WHERE kb_id = $1 AND idempotency_key = $2 AND (tenant_id = $3 OR tenant_id IS NULL)",
+        )
+        .bind(kb_id)
+        .bind(idempotency_key)
+        .bind(tenant_id)
+        .fetch_optional(&self.pool)
+        .await?;
 
-            docs.push(KnowledgeDocument {
-                id,
-                kb_id,
-                filename,
-                file_path,
-                mime_type: Some(mime_type),
-                chunk_count: chunk_count as usize,
-                status,
-                created_at: created_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
-                updated_at: updated_at.map(|d| d.to_rfc3339()).unwrap_or_default(),
-            });
-        }
-        Ok(docs)
+        row.as_ref().map(document_from_row).transpose()
     }
 
     async fn update_document_status(&self, doc_id: &str, status: &DocumentStatus) -> Result<()> {
@@ -719,7 +1235,7 @@ impl PersistenceLayer for PostgresProvider {
             _ => None,
         };
 
-        sqlx::query(
+        let result = sqlx::query(
             "UPDATE knowledge_documents SET status = $1, error_message = $2, updated_at = NOW() WHERE id = $3",
         )
         .bind(status_str)
@@ -727,22 +1243,186 @@ impl PersistenceLayer for PostgresProvider {
         .bind(doc_id)
         .execute(&self.pool)
         .await?;
+        if result.rows_affected() == 0 {
+            return Err(PersistenceError::not_found("document", doc_id));
+        }
         Ok(())
     }
 
-    async fn delete_document(&self, doc_id: &str) -> Result<()> {
-        // Delete associated chunks first
+    async fn delete_document_chunks(&self, doc_id: &str) -> Result<()> {
         sqlx::query("DELETE FROM knowledge_chunks WHERE document_id = $1")
             .bind(doc_id)
             .execute(&self.pool)
             .await?;
+        Ok(())
+    }
+
+    async fn delete_document(&self, doc_id: &str, tenant_id: Option<&str>) -> Result<()> {
+        // Delete associated chunks first
+        sqlx::query(
+            r#"
+            DELETE FROM knowledge_chunks WHERE document_id IN (
+                SELECT id FROM knowledge_documents
+                WHERE id = $1 AND tenant_id IS NOT DISTINCT FROM $2
+            )
+            "#,
+        )
+        .bind(doc_id)
+        .bind(tenant_id)
+        .execute(&self.pool)
+        .await?;
 
         // Delete the document
-        sqlx::query("DELETE FROM knowledge_documents WHERE id = $1")
-            .bind(doc_id)
-            .execute(&self.pool)
-            .await?;
+        let result = sqlx::query(
+            "DELETE FROM knowledge_documents WHERE id = $1 AND tenant_id IS NOT DISTINCT FROM $2",
+        )
+        .bind(doc_id)
+        .bind(tenant_id)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(PersistenceError::not_found("document", doc_id));
+        }
 
         Ok(())
     }
+
+    async fn save_graph_node(&self, node: &GraphNode) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO knowledge_entities (id, kb_id, label, entity_type, properties)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (id) DO UPDATE SET
+                label = EXCLUDED.label,
+                entity_type = EXCLUDED.entity_type,
+                properties = EXCLUDED.properties
+            "#,
+        )
+        .bind(&node.id)
+        .bind(&node.kb_id)
+        .bind(&node.label)
+        .bind(serde_json::to_value(&node.entity_type)?)
+        .bind(&node.properties)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn save_graph_edge(&self, edge: &GraphEdge) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO knowledge_relationships (id, kb_id, source_id, target_id, relationship_type, weight)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (id) DO UPDATE SET weight = EXCLUDED.weight
+            "#,
+        )
+        .bind(&edge.id)
+        .bind(&edge.kb_id)
+        .bind(&edge.source_id)
+        .bind(&edge.target_id)
+        .bind(&edge.relationship_type)
+        .bind(edge.weight)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_subgraph(
+        &self,
+        kb_id: &str,
+        node_ids: &[&str],
+        depth: usize,
+    ) -> Result<Subgraph> {
+        // Nodes reached from `node_ids`, one level of edges at a time; `None`
+        // for the whole graph
+        let reached = if node_ids.is_empty() {
+            None
+        } else {
+            let ids: Vec<String> = node_ids.iter().map(|id| id.to_string()).collect();
+            let mut reached: Vec<String> = sqlx::query_scalar(
+                "SELECT id FROM knowledge_entities WHERE kb_id = $1 AND id = ANY($2)",
+            )
+            .bind(kb_id)
+            .bind(&ids)
+            .fetch_all(&self.pool)
+            .await?;
+            let mut frontier = reached.clone();
+            for _ in 0..depth {
+                let rows = sqlx::query(
+                    r#"
+                    SELECT source_id, target_id FROM knowledge_relationships
+                    WHERE kb_id = $1 AND (source_id = ANY($2) OR target_id = ANY($2))
+                    "#,
+                )
+                .bind(kb_id)
+                .bind(&frontier)
+                .fetch_all(&self.pool)
+                .await?;
+                frontier.clear();
+                for row in rows {
+                    for column in ["source_id", "target_id"] {
+                        let id: String = row.try_get(column)?;
+                        if !reached.contains(&id) {
+                            reached.push(id.clone());
+                            frontier.push(id);
+                        }
+                    }
+                }
+                if frontier.is_empty() {
+                    break;
+                }
+            }
+            Some(reached)
+        };
+
+        let nodes = sqlx::query(
+            r#"
+            SELECT id, kb_id, label, entity_type, properties FROM knowledge_entities
+            WHERE kb_id = $1 AND ($2::text[] IS NULL OR id = ANY($2))
+            ORDER BY id
+            "#,
+        )
+        .bind(kb_id)
+        .bind(&reached)
+        .fetch_all(&self.pool)
+        .await?;
+        let edges = sqlx::query(
+            r#"
+            SELECT id, kb_id, source_id, target_id, relationship_type, weight
+            FROM knowledge_relationships
+            WHERE kb_id = $1
+              AND ($2::text[] IS NULL OR (source_id = ANY($2) AND target_id = ANY($2)))
+            ORDER BY id
+            "#,
+        )
+        .bind(kb_id)
+        .bind(&reached)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(Subgraph {
+            nodes: nodes
+                .iter()
+                .map(graph_node_from_row)
+                .collect::<Result<_>>()?,
+            edges: edges
+                .iter()
+                .map(graph_edge_from_row)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_dimensions() {
+        assert_eq!(
+            vector_dimensions("expected 384 dimensions, not 768"),
+            Some((384, 768))
+        );
+        assert_eq!(vector_dimensions("relation \"chunks\" does not exist"), None);
+    }
 }
@@ -1,13 +1,14 @@
 use crate::session::Session;
+use crate::uar::domain::graph::{GraphEdge, GraphNode, Subgraph};
 use crate::uar::domain::knowledge::{
     DocumentStatus, KnowledgeBase, KnowledgeChunk, KnowledgeDocument, KnowledgeMatch,
 };
 use crate::uar::domain::skills::{Skill, SkillMatch};
-use crate::uar::persistence::PersistenceLayer;
-use anyhow::Result;
+use crate::uar::domain::tenant::{owned_by, visible_to};
+use crate::uar::persistence::{PersistenceError, PersistenceLayer, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use surrealdb::Surreal;
+use surrealdb::{RecordId, Surreal};
 use surrealdb::engine::any::{Any, connect};
 
 #[derive(Debug)]
@@ -16,7 +17,7 @@ pub struct SurrealDbProvider {
 }
 
 impl SurrealDbProvider {
-    pub async fn new(connection_string: &str) -> Result<Self> {
+    pub async fn new(connection_string: &str) -> anyhow::Result<Self> {
         let db = connect(connection_string).await?;
 
         // Use default namespace and database for now
@@ -26,11 +27,47 @@ impl SurrealDbProvider {
     }
 }
 
+impl From<surrealdb::Error> for PersistenceError {
+    fn from(e: surrealdb::Error) -> Self {
+        use surrealdb::error::{Api, Db};
+        match e {
+            surrealdb::Error::Db(Db::IndexExists { value, .. }) => Self::DuplicateName(value),
+            surrealdb::Error::Api(Api::ConnectionUninitialised | Api::Ws(_) | Api::Http(_)) => {
+                Self::ConnectionError(e.to_string())
+            }
+            e => Self::Other(e.into()),
+        }
+    }
+}
+
 // Helper structs for table records if needed, or use serde_json::Value
 // Using generic structs or the domain objects directly if they serialize well.
 
+/// A chunk as stored, with the hash `idx_chunk_kb_hash` is built on.
+#[derive(Serialize, Deserialize)]
+struct ChunkRecord {
+    #[serde(flatten)]
+    chunk: KnowledgeChunk,
+    // chunk already has embedding field
+    content_hash: String,
+}
+
+impl ChunkRecord {
+    fn new(chunk: &KnowledgeChunk) -> Self {
+        Self {
+            chunk: chunk.clone(),
+            content_hash: chunk.content_hash(),
+        }
+    }
+}
+
 #[async_trait]
 impl PersistenceLayer for SurrealDbProvider {
+    async fn ping(&self) -> Result<()> {
+        self.db.health().await?;
+        Ok(())
+    }
+
     // Session Management
     async fn save_session(&self, session: &Session) -> Result<()> {
         let id = session.id().to_string();
@@ -43,9 +80,9 @@ impl PersistenceLayer for SurrealDbProvider {
         Ok(())
     }
 
-    async fn load_session(&self, id: &str) -> Result<Option<Session>> {
+    async fn load_session(&self, id: &str, tenant_id: Option<&str>) -> Result<Option<Session>> {
         let session: Option<Session> = self.db.select(("sessions", id)).await?;
-        Ok(session)
+        Ok(session.filter(|s| visible_to(s.tenant_id(), tenant_id)))
     }
 
     // Skill Management
@@ -72,6 +109,29 @@ impl PersistenceLayer for SurrealDbProvider {
         Ok(())
     }
 
+    async fn save_skill_version(&self, skill: &Skill) -> Result<()> {
+        let _: Option<Skill> = self
+            .db
+            .upsert((
+                "skill_versions",
+                format!("{}@{}", skill.skill_id, skill.version),
+            ))
+            .content(skill.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn list_skill_versions(&self, skill_id: &str) -> Result<Vec<Skill>> {
+        let sql = "SELECT * FROM skill_versions WHERE skill_id = $skill_id";
+        let mut res = self
+            .db
+            .query(sql)
+            .bind(("skill_id", skill_id.to_string()))
+            .await?;
+        let versions: Vec<Skill> = res.take(0)?;
+        Ok(versions)
+    }
+
     async fn search_skills(&self, query_vec: &[f32], limit: usize) -> Result<Vec<SkillMatch>> {
         // Fallback: Fetch all, compute cosine similarity in memory
         // Ideally use vector search plugin/feature if available.
@@ -108,40 +168,85 @@ impl PersistenceLayer for SurrealDbProvider {
 
     // Knowledge Base Management
     async fn save_knowledge_base(&self, kb: &KnowledgeBase) -> Result<()> {
+        // A knowledge base never moves between tenants
+        let existing: Option<KnowledgeBase> =
+            self.db.select(("knowledge_bases", kb.id.as_str())).await?;
+        if existing.is_some_and(|e| !owned_by(e.tenant_id.as_deref(), kb.tenant_id.as_deref())) {
+            return Err(PersistenceError::already_exists("knowledge base", &kb.id));
+        }
         let _: Option<KnowledgeBase> = self
             .db
             .upsert(("knowledge_bases", kb.id.clone()))
             .content(kb.clone())
-            .await?;
+            .await
+            .map_err(|e| match PersistenceError::from(e) {
+                PersistenceError::DuplicateName(_) => {
+                    PersistenceError::DuplicateName(kb.name.clone())
+                }
+                other => other,
+            })?;
         Ok(())
     }
 
     async fn save_chunk(&self, chunk: &KnowledgeChunk) -> Result<()> {
-        #[derive(Serialize, Deserialize)]
-        struct ChunkRecord {
-            #[serde(flatten)]
-            chunk: KnowledgeChunk,
-            // chunk already has embedding field
-        }
-
         let _: Option<ChunkRecord> = self
             .db
             .upsert(("knowledge_chunks", chunk.id))
-            .content(chunk.clone())
+            .content(ChunkRecord::new(chunk))
             .await?;
         Ok(())
     }
 
+    async fn chunk_exists(&self, kb_id: &str, content_hash: &str) -> Result<bool> {
+        let sql = "SELECT * FROM knowledge_chunks WHERE kb_id = $kb_id AND content_hash = $hash LIMIT 1";
+        let mut res = self
+            .db
+            .query(sql)
+            .bind(("kb_id", kb_id.to_string()))
+            .bind(("hash", content_hash.to_string()))
+            .await?;
+        let chunks: Vec<KnowledgeChunk> = res.take(0)?;
+        Ok(!chunks.is_empty())
+    }
+
+    async fn list_chunks(
+        &self,
+        kb_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<KnowledgeChunk>> {
+        let sql = "SELECT * OMIT embedding FROM knowledge_chunks WHERE kb_id = $kb_id ORDER BY document_id, created_at";
+        let mut res = self
+            .db
+            .query(sql)
+            .bind(("kb_id", kb_id.to_string()))
+            .await?;
+        let mut chunks: Vec<KnowledgeChunk> = res.take(0)?;
+        chunks.retain(|c| visible_to(c.tenant_id.as_deref(), tenant_id));
+        Ok(chunks)
+    }
+
+    async fn list_chunk_embeddings(&self, kb_id: &str) -> Result<Option<Vec<KnowledgeChunk>>> {
+        let sql = "SELECT * FROM knowledge_chunks WHERE kb_id = $kb_id";
+        let mut res = self
+            .db
+            .query(sql)
+            .bind(("kb_id", kb_id.to_string()))
+            .await?;
+        Ok(Some(res.take(0)?))
+    }
+
     async fn search_knowledge(
         &self,
         query_vec: &[f32],
         limit: usize,
         min_score: f32,
+        tenant_id: Option<&str>,
     ) -> Result<Vec<KnowledgeMatch>> {
         let chunks: Vec<KnowledgeChunk> = self.db.select("knowledge_chunks").await?;
 
         let mut matches: Vec<KnowledgeMatch> = chunks
             .into_iter()
+            .filter(|c| visible_to(c.tenant_id.as_deref(), tenant_id))
             .map(|c| {
                 let score = cosine_similarity(&c.embedding, query_vec);
                 KnowledgeMatch { chunk: c, score }
@@ -161,6 +266,13 @@ impl PersistenceLayer for SurrealDbProvider {
 
     // Agent Persistence
     async fn save_agent(&self, agent: &crate::uar::domain::artifact::AgentArtifact) -> Result<()> {
+        // An agent never moves between tenants
+        let existing: Option<crate::uar::domain::artifact::AgentArtifact> =
+            self.db.select(("agents", agent.id.as_str())).await?;
+        let tenant_id = agent.tenant_id.as_deref();
+        if existing.is_some_and(|a| !owned_by(a.tenant_id.as_deref(), tenant_id)) {
+            return Err(PersistenceError::already_exists("agent", &agent.id));
+        }
         let _: Option<crate::uar::domain::artifact::AgentArtifact> = self
             .db
             .upsert(("agents", agent.id.clone()))
@@ -172,33 +284,181 @@ impl PersistenceLayer for SurrealDbProvider {
     async fn load_agent(
         &self,
         id: &str,
+        tenant_id: Option<&str>,
     ) -> Result<Option<crate::uar::domain::artifact::AgentArtifact>> {
         let agent: Option<crate::uar::domain::artifact::AgentArtifact> =
             self.db.select(("agents", id)).await?;
-        Ok(agent)
+        Ok(agent.filter(|a| visible_to(a.tenant_id.as_deref(), tenant_id)))
     }
 
     async fn load_agent_by_name(
         &self,
         name: &str,
+        tenant_id: Option<&str>,
     ) -> Result<Option<crate::uar::domain::artifact::AgentArtifact>> {
         // Select where name = $name
         // Assume metadata.title contains name.
         // This is inefficient without index but fine for now.
-        let sql = "SELECT * FROM agents WHERE metadata.title = $name LIMIT 1";
+        let sql = "SELECT * FROM agents WHERE metadata.title = $name";
         let mut response = self.db.query(sql).bind(("name", name.to_string())).await?;
-        let agent: Option<crate::uar::domain::artifact::AgentArtifact> = response.take(0)?;
-        Ok(agent)
+        let agents: Vec<crate::uar::domain::artifact::AgentArtifact> = response.take(0)?;
+        // A tenant's own agent shadows a shared one of the same name
+        Ok(agents
+            .into_iter()
+            .filter(|a| visible_to(a.tenant_id.as_deref(), tenant_id))
+            .max_by_key(|a| a.tenant_id.is_some()))
     }
 
-    async fn list_agents(&self) -> Result<Vec<crate::uar::domain::artifact::AgentArtifact>> {
-        let agents: Vec<crate::uar::domain::artifact::AgentArtifact> =
+    async fn list_agents(
+        &self,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<crate::uar::domain::artifact::AgentArtifact>> {
+        let mut agents: Vec<crate::uar::domain::artifact::AgentArtifact> =
             self.db.select("agents").await?;
+        agents.retain(|a| visible_to(a.tenant_id.as_deref(), tenant_id));
         Ok(agents)
     }
 
+    // Run Accounting
+    async fn save_run_usage(&self, usage: &crate::uar::domain::runs::RunUsage) -> Result<()> {
+        let _: Option<crate::uar::domain::runs::RunUsage> = self
+            .db
+            .upsert(("run_costs", usage.run_id.clone()))
+            .content(usage.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn load_run_usage(
+        &self,
+        run_id: &str,
+    ) -> Result<Option<crate::uar::domain::runs::RunUsage>> {
+        let usage: Option<crate::uar::domain::runs::RunUsage> =
+            self.db.select(("run_costs", run_id)).await?;
+        Ok(usage)
+    }
+
+    async fn save_run_log(&self, log: &crate::uar::domain::runs::RunLog) -> Result<()> {
+        let _: Option<crate::uar::domain::runs::RunLog> = self
+            .db
+            .upsert(("run_logs", log.run_id.clone()))
+            .content(log.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn get_run_log(
+        &self,
+        run_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<crate::uar::domain::runs::RunLog>> {
+        let log: Option<crate::uar::domain::runs::RunLog> =
+            self.db.select(("run_logs", run_id)).await?;
+        Ok(log.filter(|log| visible_to(log.tenant_id.as_deref(), tenant_id)))
+    }
+
+    async fn save_run_record(&self, record: &crate::uar::domain::runs::RunRecord) -> Result<()> {
+        let _: Option<crate::uar::domain::runs::RunRecord> = self
+            .db
+            .upsert(("run_records", record.run.run_id.clone()))
+            .content(record.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn get_run_record(
+        &self,
+        run_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<crate::uar::domain::runs::RunRecord>> {
+        let record: Option<crate::uar::domain::runs::RunRecord> =
+            self.db.select(("run_records", run_id)).await?;
+        Ok(record.filter(|r| visible_to(r.run.tenant_id.as_deref(), tenant_id)))
+    }
+
+    async fn list_runs(
+        &self,
+        session_id: Option<&str>,
+        agent_id: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<crate::uar::domain::runs::RunRecord>> {
+        // Continue past the cursor run; an unknown cursor lists nothing
+        let cursor = match after {
+            Some(after) => {
+                let cursor: Option<crate::uar::domain::runs::RunRecord> =
+                    self.db.select(("run_records", after)).await?;
+                match cursor {
+                    Some(cursor) => Some((cursor.finished_at, cursor.run.run_id)),
+                    None => return Ok(Vec::new()),
+                }
+            }
+            None => None,
+        };
+        let (cursor_at, cursor_id) = cursor.unzip();
+
+        let sql = "SELECT * FROM run_records \
+                   WHERE ($session_id = NONE OR conversation_id = $session_id) \
+                   AND ($agent_id = NONE OR agent_id = $agent_id) \
+                   AND ($cursor_at = NONE OR finished_at < $cursor_at \
+                        OR (finished_at = $cursor_at AND run_id < $cursor_id)) \
+                   AND (tenant_id = NONE OR tenant_id = $tenant_id) \
+                   ORDER BY finished_at DESC, run_id DESC LIMIT $limit";
+        let mut res = self
+            .db
+            .query(sql)
+            .bind(("session_id", session_id.map(str::to_string)))
+            .bind(("agent_id", agent_id.map(str::to_string)))
+            .bind(("cursor_at", cursor_at))
+            .bind(("cursor_id", cursor_id))
+            .bind(("tenant_id", tenant_id.map(str::to_string)))
+            .bind(("limit", limit))
+            .await?;
+        let runs: Vec<crate::uar::domain::runs::RunRecord> = res.take(0)?;
+        Ok(runs)
+    }
+
+    // Audit Trail
+    async fn save_audit_entry(&self, entry: &crate::uar::domain::audit::AuditEntry) -> Result<()> {
+        let _: Option<crate::uar::domain::audit::AuditEntry> = self
+            .db
+            .create(("audit_log", entry.id.clone()))
+            .content(entry.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn list_audit_entries(
+        &self,
+        resource: Option<crate::uar::domain::audit::AuditResource>,
+        tenant_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<crate::uar::domain::audit::AuditEntry>> {
+        let sql = "SELECT * FROM audit_log \
+                   WHERE ($resource = NONE OR resource = $resource) \
+                   AND ($tenant_id = NONE OR tenant_id = $tenant_id) \
+                   ORDER BY timestamp DESC LIMIT $limit";
+        let mut res = self
+            .db
+            .query(sql)
+            .bind(("resource", resource.map(|r| r.as_str())))
+            .bind(("tenant_id", tenant_id.map(str::to_string)))
+            .bind(("limit", limit))
+            .await?;
+        let entries: Vec<crate::uar::domain::audit::AuditEntry> = res.take(0)?;
+        Ok(entries)
+    }
+
     // Memory System
     async fn save_memory(&self, memory: &crate::uar::domain::memory::Memory) -> Result<()> {
+        // A memory never moves between tenants
+        let existing: Option<crate::uar::domain::memory::Memory> =
+            self.db.select(("memories", memory.id.as_str())).await?;
+        let tenant_id = memory.tenant_id.as_deref();
+        if existing.is_some_and(|m| !owned_by(m.tenant_id.as_deref(), tenant_id)) {
+            return Err(PersistenceError::already_exists("memory", &memory.id));
+        }
         // memory has embedding field
         let _: Option<crate::uar::domain::memory::Memory> = self
             .db
@@ -214,6 +474,7 @@ impl PersistenceLayer for SurrealDbProvider {
         query_vec: &[f32],
         limit: usize,
         min_score: f32,
+        tenant_id: Option<&str>,
     ) -> Result<Vec<crate::uar::domain::memory::MemoryMatch>> {
         // Fetch all (or filter by agent_id first if indexed)
         // Then cosine similarity
@@ -235,6 +496,7 @@ impl PersistenceLayer for SurrealDbProvider {
 
         let mut matches: Vec<crate::uar::domain::memory::MemoryMatch> = memories
             .into_iter()
+            .filter(|m| visible_to(m.tenant_id.as_deref(), tenant_id))
             .map(|m| {
                 let score = cosine_similarity(&m.embedding, query_vec);
                 crate::uar::domain::memory::MemoryMatch { memory: m, score }
@@ -256,26 +518,46 @@ impl PersistenceLayer for SurrealDbProvider {
     // Knowledge Base Retrieval Methods
     // =========================================================================
 
-    async fn get_knowledge_base(&self, id: &str) -> Result<Option<KnowledgeBase>> {
+    async fn get_knowledge_base(
+        &self,
+        id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeBase>> {
         let kb: Option<KnowledgeBase> = self.db.select(("knowledge_bases", id)).await?;
-        Ok(kb)
+        Ok(kb.filter(|kb| visible_to(kb.tenant_id.as_deref(), tenant_id)))
     }
 
-    async fn get_knowledge_base_by_name(&self, name: &str) -> Result<Option<KnowledgeBase>> {
-        let sql = "SELECT * FROM knowledge_bases WHERE name = $name LIMIT 1";
+    async fn get_knowledge_base_by_name(
+        &self,
+        name: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeBase>> {
+        let sql = "SELECT * FROM knowledge_bases WHERE name = $name";
         let mut response = self.db.query(sql).bind(("name", name.to_string())).await?;
-        let kb: Option<KnowledgeBase> = response.take(0)?;
-        Ok(kb)
+        let kbs: Vec<KnowledgeBase> = response.take(0)?;
+        // A tenant's own knowledge base shadows a shared one of the same name
+        Ok(kbs
+            .into_iter()
+            .filter(|kb| visible_to(kb.tenant_id.as_deref(), tenant_id))
+            .max_by_key(|kb| kb.tenant_id.is_some()))
     }
 
-    async fn list_knowledge_bases(&self) -> Result<Vec<KnowledgeBase>> {
-        let kbs: Vec<KnowledgeBase> = self.db.select("knowledge_bases").await?;
+    async fn list_knowledge_bases(&self, tenant_id: Option<&str>) -> Result<Vec<KnowledgeBase>> {
+        let mut kbs: Vec<KnowledgeBase> = self.db.select("knowledge_bases").await?;
+        kbs.retain(|kb| visible_to(kb.tenant_id.as_deref(), tenant_id));
         Ok(kbs)
     }
 
-    async fn delete_knowledge_base(&self, id: &str) -> Result<()> {
+    async fn delete_knowledge_base(&self, id: &str, tenant_id: Option<&str>) -> Result<()> {
+        let kb = self.get_knowledge_base(id, tenant_id).await?;
+        if !kb.is_some_and(|kb| owned_by(kb.tenant_id.as_deref(), tenant_id)) {
+            return Err(PersistenceError::not_found("knowledge base", id));
+        }
         // Delete the KB - SurrealDB doesn't have FK CASCADE, so we delete related records first
-        let _: Option<KnowledgeBase> = self.db.delete(("knowledge_bases", id)).await?;
+        let deleted: Option<KnowledgeBase> = self.db.delete(("knowledge_bases", id)).await?;
+        if deleted.is_none() {
+            return Err(PersistenceError::not_found("knowledge base", id));
+        }
         // Also delete related chunks and documents
         let sql = "DELETE FROM knowledge_chunks WHERE kb_id = $id";
         self.db.query(sql).bind(("id", id.to_string())).await?;
@@ -294,20 +576,31 @@ impl PersistenceLayer for SurrealDbProvider {
         query_vec: &[f32],
         limit: usize,
         min_score: f32,
+        metadata_filter: Option<&serde_json::Value>,
+        tenant_id: Option<&str>,
     ) -> Result<Vec<KnowledgeMatch>> {
         if kb_ids.is_empty() {
             return Ok(vec![]);
         }
 
-        // Query with kb_id filter
+        // Query with kb_id (and metadata) filter
         let kb_ids_vec: Vec<String> = kb_ids.iter().map(|s| s.to_string()).collect();
-        let sql = "SELECT * FROM knowledge_chunks WHERE kb_id IN $kb_ids";
-        let mut res = self.db.query(sql).bind(("kb_ids", kb_ids_vec)).await?;
+        let mut sql = String::from("SELECT * FROM knowledge_chunks WHERE kb_id IN $kb_ids");
+        if metadata_filter.is_some() {
+            sql.push_str(" AND metadata CONTAINS $filter");
+        }
+        let mut res = self
+            .db
+            .query(sql)
+            .bind(("kb_ids", kb_ids_vec))
+            .bind(("filter", metadata_filter.cloned()))
+            .await?;
         let chunks: Vec<KnowledgeChunk> = res.take(0)?;
 
         // In-memory cosine similarity
         let mut matches: Vec<KnowledgeMatch> = chunks
             .into_iter()
+            .filter(|c| visible_to(c.tenant_id.as_deref(), tenant_id))
             .map(|c| {
                 let score = cosine_similarity(&c.embedding, query_vec);
                 KnowledgeMatch { chunk: c, score }
@@ -338,23 +631,81 @@ impl PersistenceLayer for SurrealDbProvider {
         Ok(())
     }
 
-    async fn get_document(&self, id: &str) -> Result<Option<KnowledgeDocument>> {
+    async fn save_document_with_chunks(
+        &self,
+        doc: &KnowledgeDocument,
+        chunks: &[KnowledgeChunk],
+    ) -> Result<()> {
+        let mut sql = String::from("BEGIN TRANSACTION;\nUPSERT $doc_id CONTENT $doc;\n");
+        for i in 0..chunks.len() {
+            sql.push_str(&format!("UPSERT $chunk_id_{i} CONTENT $chunk_{i};\n"));
+        }
+        sql.push_str("COMMIT TRANSACTION;");
+
+        let mut query = self
+            .db
+            .query(sql)
+            .bind(("doc_id", RecordId::from(("knowledge_documents", doc.id.as_str()))))
+            .bind(("doc", doc.clone()));
+        for (i, chunk) in chunks.iter().enumerate() {
+            query = query
+                .bind((format!("chunk_id_{i}"), RecordId::from(("knowledge_chunks", chunk.id))))
+                .bind((format!("chunk_{i}"), ChunkRecord::new(chunk)));
+        }
+        // A failed statement cancels the whole transaction
+        query.await?.check()?;
+        Ok(())
+    }
+
+    async fn get_document(
+        &self,
+        id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeDocument>> {
         let doc: Option<KnowledgeDocument> = self.db.select(("knowledge_documents", id)).await?;
-        Ok(doc)
+        Ok(doc.filter(|d| visible_to(d.tenant_id.as_deref(), tenant_id)))
     }
 
-    async fn list_documents(&self, kb_id: &str) -> Result<Vec<KnowledgeDocument>> {
+    async fn list_documents(
+        &self,
+        kb_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<KnowledgeDocument>> {
         let sql = "SELECT * FROM knowledge_documents WHERE kb_id = $kb_id ORDER BY created_at";
         let mut res = self
             .db
             .query(sql)
             .bind(("kb_id", kb_id.to_string()))
             .await?;
-        let docs: Vec<KnowledgeDocument> = res.take(0)?;
+        let mut docs: Vec<KnowledgeDocument> = res.take(0)?;
+        docs.retain(|d| visible_to(d.tenant_id.as_deref(), tenant_id));
         Ok(docs)
     }
 
+    async fn find_document_by_idempotency_key(
+        &self,
+        kb_id: &str,
+        idempotency_key: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeDocument>> {
+        let sql = "SELECT * FROM knowledge_documents WHERE kb_id = $kb_id AND idempotency_key = $key";
+        let mut res = self
+            .db
+            .query(sql)
+            .bind(("kb_id", kb_id.to_string()))
+            .bind(("key", idempotency_key.to_string()))
+            .await?;
+        let docs: Vec<KnowledgeDocument> = res.take(0)?;
+        Ok(docs
+            .into_iter()
+            .find(|d| visible_to(d.tenant_id.as_deref(), tenant_id)))
+    }
+
     async fn update_document_status(&self, doc_id: &str, status: &DocumentStatus) -> Result<()> {
+        let doc: Option<KnowledgeDocument> = self.db.select(("knowledge_documents", doc_id)).await?;
+        if doc.is_none() {
+            return Err(PersistenceError::not_found("document", doc_id));
+        }
         let sql = "UPDATE knowledge_documents SET status = $status, updated_at = time::now() WHERE id = $id";
         self.db
             .query(sql)
@@ -364,7 +715,21 @@ impl PersistenceLayer for SurrealDbProvider {
         Ok(())
     }
 
-    async fn delete_document(&self, doc_id: &str) -> Result<()> {
+    async fn delete_document_chunks(&self, doc_id: &str) -> Result<()> {
+        let sql = "DELETE FROM knowledge_chunks WHERE document_id = $doc_id";
+        self.db
+            .query(sql)
+            .bind(("doc_id", doc_id.to_string()))
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    async fn delete_document(&self, doc_id: &str, tenant_id: Option<&str>) -> Result<()> {
+        let doc = self.get_document(doc_id, tenant_id).await?;
+        if !doc.is_some_and(|d| owned_by(d.tenant_id.as_deref(), tenant_id)) {
+            return Err(PersistenceError::not_found("document", doc_id));
+        }
         // Delete associated chunks first
         let sql = "DELETE FROM knowledge_chunks WHERE document_id = $doc_id";
         self.db
@@ -373,10 +738,53 @@ impl PersistenceLayer for SurrealDbProvider {
             .await?;
 
         // Delete the document
-        let _: Option<KnowledgeDocument> = self.db.delete(("knowledge_documents", doc_id)).await?;
+        let deleted: Option<KnowledgeDocument> =
+            self.db.delete(("knowledge_documents", doc_id)).await?;
+        if deleted.is_none() {
+            return Err(PersistenceError::not_found("document", doc_id));
+        }
 
         Ok(())
     }
+
+    async fn save_graph_node(&self, node: &GraphNode) -> Result<()> {
+        let _: Option<GraphNode> = self
+            .db
+            .upsert(("knowledge_entities", node.id.clone()))
+            .content(node.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn save_graph_edge(&self, edge: &GraphEdge) -> Result<()> {
+        let _: Option<GraphEdge> = self
+            .db
+            .upsert(("knowledge_relationships", edge.id.clone()))
+            .content(edge.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn get_subgraph(
+        &self,
+        kb_id: &str,
+        node_ids: &[&str],
+        depth: usize,
+    ) -> Result<Subgraph> {
+        // Graphs are small next to their chunks; walk the KB's in memory
+        let sql = "SELECT * FROM knowledge_entities WHERE kb_id = $kb_id ORDER BY id;
+                   SELECT * FROM knowledge_relationships WHERE kb_id = $kb_id ORDER BY id;";
+        let mut res = self
+            .db
+            .query(sql)
+            .bind(("kb_id", kb_id.to_string()))
+            .await?;
+        let graph = Subgraph {
+            nodes: res.take(0)?,
+            edges: res.take(1)?,
+        };
+        Ok(graph.around(node_ids, depth))
+    }
 }
 
 fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
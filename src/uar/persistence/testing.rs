@@ -0,0 +1,662 @@
+//! In-memory `PersistenceLayer` for unit tests.
+//!
+//! Only sessions, agents, skill versions, knowledge bases, documents, chunks, graphs, run usage,
+//! run logs, archived runs and audit entries are stored, and chunks are searched by brute-force
+//! cosine similarity; every other operation is a no-op returning empty results. Sessions are
+//! round-tripped through JSON like the real providers, so loaded sessions are independent
+//! copies. Tenant scoping follows the real providers.
+
+use super::{PersistenceError, PersistenceLayer, Result};
+use crate::session::Session;
+use crate::uar::domain::artifact::AgentArtifact;
+use crate::uar::domain::audit::{AuditEntry, AuditResource};
+use crate::uar::domain::graph::{GraphEdge, GraphNode, Subgraph};
+use crate::uar::domain::knowledge::{
+    DocumentStatus, KnowledgeBase, KnowledgeChunk, KnowledgeDocument, KnowledgeMatch,
+};
+use crate::uar::domain::memory::{Memory, MemoryMatch};
+use crate::uar::domain::runs::{RunLog, RunRecord, RunUsage};
+use crate::uar::domain::skills::{Skill, SkillMatch};
+use crate::uar::domain::tenant::{owned_by, visible_to};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Default)]
+pub struct InMemoryPersistence {
+    sessions: Mutex<HashMap<String, serde_json::Value>>,
+    agents: Mutex<HashMap<String, AgentArtifact>>,
+    /// Keyed by skill ID and version
+    skill_versions: Mutex<HashMap<(String, String), Skill>>,
+    knowledge_bases: Mutex<HashMap<String, KnowledgeBase>>,
+    documents: Mutex<HashMap<String, KnowledgeDocument>>,
+    chunks: Mutex<Vec<KnowledgeChunk>>,
+    graph_nodes: Mutex<HashMap<String, GraphNode>>,
+    graph_edges: Mutex<HashMap<String, GraphEdge>>,
+    run_usage: Mutex<HashMap<String, RunUsage>>,
+    run_logs: Mutex<HashMap<String, RunLog>>,
+    run_records: Mutex<HashMap<String, RunRecord>>,
+    audit: Mutex<Vec<AuditEntry>>,
+    unreachable: AtomicBool,
+    /// Chunk writes that succeed before every further one fails
+    chunk_writes_left: Mutex<Option<usize>>,
+}
+
+impl InMemoryPersistence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `ping` fail, as if the database connection were lost.
+    pub fn set_unreachable(&self, unreachable: bool) {
+        self.unreachable.store(unreachable, Ordering::SeqCst);
+    }
+
+    /// Whether a session with this ID has been saved.
+    pub fn has_session(&self, id: &str) -> bool {
+        self.sessions.lock().unwrap().contains_key(id)
+    }
+
+    /// Let `n` more chunk writes succeed, then fail the rest, as if the
+    /// database went away mid-ingestion.
+    pub fn fail_chunk_writes_after(&self, n: usize) {
+        *self.chunk_writes_left.lock().unwrap() = Some(n);
+    }
+
+    /// Take one chunk write from the budget set by
+    /// [`Self::fail_chunk_writes_after`].
+    fn take_chunk_write(&self) -> Result<()> {
+        match self.chunk_writes_left.lock().unwrap().as_mut() {
+            Some(0) => Err(PersistenceError::ConnectionError(
+                "chunk writes are failing".to_string(),
+            )),
+            Some(left) => {
+                *left -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Number of chunks saved so far.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.lock().unwrap().len()
+    }
+
+    /// Visible chunks matching `filter`, best cosine similarity first.
+    fn rank_chunks(
+        &self,
+        filter: impl Fn(&KnowledgeChunk) -> bool,
+        query_vec: &[f32],
+        limit: usize,
+        min_score: f32,
+        tenant_id: Option<&str>,
+    ) -> Vec<KnowledgeMatch> {
+        let mut matches: Vec<KnowledgeMatch> = self
+            .chunks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| filter(c) && visible_to(c.tenant_id.as_deref(), tenant_id))
+            .map(|c| KnowledgeMatch {
+                chunk: c.clone(),
+                score: cosine_similarity(&c.embedding, query_vec),
+            })
+            .filter(|m| m.score >= min_score)
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(limit);
+        matches
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Like the unique (kb_id, idempotency_key) index of the real providers.
+fn check_unique_idempotency_key(
+    documents: &HashMap<String, KnowledgeDocument>,
+    doc: &KnowledgeDocument,
+) -> Result<()> {
+    let Some(key) = &doc.idempotency_key else {
+        return Ok(());
+    };
+    if documents.values().any(|d| {
+        d.id != doc.id && d.kb_id == doc.kb_id && d.idempotency_key.as_ref() == Some(key)
+    }) {
+        return Err(PersistenceError::DuplicateName(key.clone()));
+    }
+    Ok(())
+}
+
+/// Like the unique (kb_id, content_hash) index of the real providers.
+fn check_unique_content(chunks: &[KnowledgeChunk], chunk: &KnowledgeChunk) -> Result<()> {
+    let hash = chunk.content_hash();
+    if chunks
+        .iter()
+        .any(|c| c.id != chunk.id && c.kb_id == chunk.kb_id && c.content_hash() == hash)
+    {
+        return Err(PersistenceError::Other(anyhow::anyhow!(
+            "knowledge base '{}' already has a chunk with this content",
+            chunk.kb_id
+        )));
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl PersistenceLayer for InMemoryPersistence {
+    async fn ping(&self) -> Result<()> {
+        if self.unreachable.load(Ordering::SeqCst) {
+            return Err(PersistenceError::ConnectionError("connection refused".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn save_session(&self, session: &Session) -> Result<()> {
+        let data = serde_json::to_value(session)?;
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session.id().to_string(), data);
+        Ok(())
+    }
+
+    async fn load_session(&self, id: &str, tenant_id: Option<&str>) -> Result<Option<Session>> {
+        let data = self.sessions.lock().unwrap().get(id).cloned();
+        let session: Option<Session> = data.map(serde_json::from_value).transpose()?;
+        Ok(session.filter(|s| visible_to(s.tenant_id(), tenant_id)))
+    }
+
+    async fn save_skill(&self, _skill: &Skill, _embedding: &[f32]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn search_skills(&self, _query_vec: &[f32], _limit: usize) -> Result<Vec<SkillMatch>> {
+        Ok(vec![])
+    }
+
+    async fn save_skill_version(&self, skill: &Skill) -> Result<()> {
+        self.skill_versions.lock().unwrap().insert(
+            (skill.skill_id.clone(), skill.version.clone()),
+            skill.clone(),
+        );
+        Ok(())
+    }
+
+    async fn list_skill_versions(&self, skill_id: &str) -> Result<Vec<Skill>> {
+        Ok(self
+            .skill_versions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|skill| skill.skill_id == skill_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn save_knowledge_base(&self, kb: &KnowledgeBase) -> Result<()> {
+        let mut kbs = self.knowledge_bases.lock().unwrap();
+        if let Some(existing) = kbs.get(&kb.id)
+            && !owned_by(existing.tenant_id.as_deref(), kb.tenant_id.as_deref())
+        {
+            return Err(PersistenceError::already_exists("knowledge base", &kb.id));
+        }
+        if kbs.values().any(|other| {
+            other.name == kb.name && other.id != kb.id && other.tenant_id == kb.tenant_id
+        }) {
+            return Err(PersistenceError::DuplicateName(kb.name.clone()));
+        }
+        kbs.insert(kb.id.clone(), kb.clone());
+        Ok(())
+    }
+
+    async fn get_knowledge_base(
+        &self,
+        id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeBase>> {
+        Ok(self
+            .knowledge_bases
+            .lock()
+            .unwrap()
+            .get(id)
+            .filter(|kb| visible_to(kb.tenant_id.as_deref(), tenant_id))
+            .cloned())
+    }
+
+    async fn get_knowledge_base_by_name(
+        &self,
+        name: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeBase>> {
+        Ok(self
+            .knowledge_bases
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|kb| kb.name == name && visible_to(kb.tenant_id.as_deref(), tenant_id))
+            .max_by_key(|kb| kb.tenant_id.is_some())
+            .cloned())
+    }
+
+    async fn list_knowledge_bases(&self, tenant_id: Option<&str>) -> Result<Vec<KnowledgeBase>> {
+        Ok(self
+            .knowledge_bases
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|kb| visible_to(kb.tenant_id.as_deref(), tenant_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_knowledge_base(&self, id: &str, tenant_id: Option<&str>) -> Result<()> {
+        let mut kbs = self.knowledge_bases.lock().unwrap();
+        match kbs.get(id) {
+            Some(kb) if owned_by(kb.tenant_id.as_deref(), tenant_id) => {
+                kbs.remove(id);
+                Ok(())
+            }
+            _ => Err(PersistenceError::not_found("knowledge base", id)),
+        }
+    }
+
+    async fn save_chunk(&self, chunk: &KnowledgeChunk) -> Result<()> {
+        self.take_chunk_write()?;
+        let mut chunks = self.chunks.lock().unwrap();
+        check_unique_content(&chunks, chunk)?;
+        chunks.retain(|c| c.id != chunk.id);
+        chunks.push(chunk.clone());
+        Ok(())
+    }
+
+    async fn chunk_exists(&self, kb_id: &str, content_hash: &str) -> Result<bool> {
+        Ok(self
+            .chunks
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|c| c.kb_id == kb_id && c.content_hash() == content_hash))
+    }
+
+    async fn list_chunks(
+        &self,
+        kb_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<KnowledgeChunk>> {
+        let mut chunks: Vec<KnowledgeChunk> = self
+            .chunks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| c.kb_id == kb_id && visible_to(c.tenant_id.as_deref(), tenant_id))
+            .map(|c| KnowledgeChunk {
+                embedding: Vec::new(),
+                ..c.clone()
+            })
+            .collect();
+        chunks.sort_by(|a, b| a.document_id.cmp(&b.document_id));
+        Ok(chunks)
+    }
+
+    async fn list_chunk_embeddings(&self, kb_id: &str) -> Result<Option<Vec<KnowledgeChunk>>> {
+        let chunks = self.chunks.lock().unwrap();
+        Ok(Some(chunks.iter().filter(|c| c.kb_id == kb_id).cloned().collect()))
+    }
+
+    async fn search_knowledge(
+        &self,
+        query_vec: &[f32],
+        limit: usize,
+        min_score: f32,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<KnowledgeMatch>> {
+        Ok(self.rank_chunks(|_| true, query_vec, limit, min_score, tenant_id))
+    }
+
+    async fn search_knowledge_scoped(
+        &self,
+        kb_ids: &[&str],
+        query_vec: &[f32],
+        limit: usize,
+        min_score: f32,
+        metadata_filter: Option<&serde_json::Value>,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<KnowledgeMatch>> {
+        let in_scope = |chunk: &KnowledgeChunk| {
+            kb_ids.contains(&chunk.kb_id.as_str())
+                && metadata_filter.is_none_or(|filter| chunk.matches_metadata(filter))
+        };
+        Ok(self.rank_chunks(in_scope, query_vec, limit, min_score, tenant_id))
+    }
+
+    async fn save_document(&self, doc: &KnowledgeDocument) -> Result<()> {
+        let mut documents = self.documents.lock().unwrap();
+        check_unique_idempotency_key(&documents, doc)?;
+        documents.insert(doc.id.clone(), doc.clone());
+        Ok(())
+    }
+
+    async fn save_document_with_chunks(
+        &self,
+        doc: &KnowledgeDocument,
+        new_chunks: &[KnowledgeChunk],
+    ) -> Result<()> {
+        let mut documents = self.documents.lock().unwrap();
+        let mut chunks = self.chunks.lock().unwrap();
+        // Validate everything before writing anything
+        check_unique_idempotency_key(&documents, doc)?;
+        let mut staged = chunks.clone();
+        for chunk in new_chunks {
+            self.take_chunk_write()?;
+            check_unique_content(&staged, chunk)?;
+            staged.retain(|c| c.id != chunk.id);
+            staged.push(chunk.clone());
+        }
+        *chunks = staged;
+        documents.insert(doc.id.clone(), doc.clone());
+        Ok(())
+    }
+
+    async fn get_document(
+        &self,
+        id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeDocument>> {
+        Ok(self
+            .documents
+            .lock()
+            .unwrap()
+            .get(id)
+            .filter(|d| visible_to(d.tenant_id.as_deref(), tenant_id))
+            .cloned())
+    }
+
+    async fn list_documents(
+        &self,
+        kb_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<KnowledgeDocument>> {
+        Ok(self
+            .documents
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|d| d.kb_id == kb_id && visible_to(d.tenant_id.as_deref(), tenant_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn find_document_by_idempotency_key(
+        &self,
+        kb_id: &str,
+        idempotency_key: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<KnowledgeDocument>> {
+        Ok(self
+            .documents
+            .lock()
+            .unwrap()
+            .values()
+            .find(|d| {
+                d.kb_id == kb_id
+                    && d.idempotency_key.as_deref() == Some(idempotency_key)
+                    && visible_to(d.tenant_id.as_deref(), tenant_id)
+            })
+            .cloned())
+    }
+
+    async fn update_document_status(&self, doc_id: &str, status: &DocumentStatus) -> Result<()> {
+        match self.documents.lock().unwrap().get_mut(doc_id) {
+            Some(doc) => {
+                doc.status = status.clone();
+                Ok(())
+            }
+            None => Err(PersistenceError::not_found("document", doc_id)),
+        }
+    }
+
+    async fn delete_document_chunks(&self, doc_id: &str) -> Result<()> {
+        self.chunks
+            .lock()
+            .unwrap()
+            .retain(|c| c.document_id.as_deref() != Some(doc_id));
+        Ok(())
+    }
+
+    async fn save_graph_node(&self, node: &GraphNode) -> Result<()> {
+        self.graph_nodes
+            .lock()
+            .unwrap()
+            .insert(node.id.clone(), node.clone());
+        Ok(())
+    }
+
+    async fn save_graph_edge(&self, edge: &GraphEdge) -> Result<()> {
+        self.graph_edges
+            .lock()
+            .unwrap()
+            .insert(edge.id.clone(), edge.clone());
+        Ok(())
+    }
+
+    async fn get_subgraph(
+        &self,
+        kb_id: &str,
+        node_ids: &[&str],
+        depth: usize,
+    ) -> Result<Subgraph> {
+        let mut graph = Subgraph {
+            nodes: self
+                .graph_nodes
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|node| node.kb_id == kb_id)
+                .cloned()
+                .collect(),
+            edges: self
+                .graph_edges
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|edge| edge.kb_id == kb_id)
+                .cloned()
+                .collect(),
+        };
+        graph.nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        graph.edges.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(graph.around(node_ids, depth))
+    }
+
+    async fn delete_document(&self, doc_id: &str, tenant_id: Option<&str>) -> Result<()> {
+        let mut docs = self.documents.lock().unwrap();
+        if !docs
+            .get(doc_id)
+            .is_some_and(|d| owned_by(d.tenant_id.as_deref(), tenant_id))
+        {
+            return Err(PersistenceError::not_found("document", doc_id));
+        }
+        docs.remove(doc_id);
+        self.chunks
+            .lock()
+            .unwrap()
+            .retain(|c| c.document_id.as_deref() != Some(doc_id));
+        Ok(())
+    }
+
+    async fn save_agent(&self, agent: &AgentArtifact) -> Result<()> {
+        let mut agents = self.agents.lock().unwrap();
+        if let Some(existing) = agents.get(&agent.id)
+            && !owned_by(existing.tenant_id.as_deref(), agent.tenant_id.as_deref())
+        {
+            return Err(PersistenceError::already_exists("agent", &agent.id));
+        }
+        agents.insert(agent.id.clone(), agent.clone());
+        Ok(())
+    }
+
+    async fn load_agent(&self, id: &str, tenant_id: Option<&str>) -> Result<Option<AgentArtifact>> {
+        Ok(self
+            .agents
+            .lock()
+            .unwrap()
+            .get(id)
+            .filter(|a| visible_to(a.tenant_id.as_deref(), tenant_id))
+            .cloned())
+    }
+
+    async fn load_agent_by_name(
+        &self,
+        name: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<AgentArtifact>> {
+        Ok(self
+            .agents
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|a| a.metadata.title == name && visible_to(a.tenant_id.as_deref(), tenant_id))
+            .max_by_key(|a| a.tenant_id.is_some())
+            .cloned())
+    }
+
+    async fn list_agents(&self, tenant_id: Option<&str>) -> Result<Vec<AgentArtifact>> {
+        Ok(self
+            .agents
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|a| visible_to(a.tenant_id.as_deref(), tenant_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn save_run_usage(&self, usage: &RunUsage) -> Result<()> {
+        self.run_usage
+            .lock()
+            .unwrap()
+            .insert(usage.run_id.clone(), usage.clone());
+        Ok(())
+    }
+
+    async fn load_run_usage(&self, run_id: &str) -> Result<Option<RunUsage>> {
+        Ok(self.run_usage.lock().unwrap().get(run_id).cloned())
+    }
+
+    async fn save_run_log(&self, log: &RunLog) -> Result<()> {
+        self.run_logs
+            .lock()
+            .unwrap()
+            .insert(log.run_id.clone(), log.clone());
+        Ok(())
+    }
+
+    async fn get_run_log(&self, run_id: &str, tenant_id: Option<&str>) -> Result<Option<RunLog>> {
+        Ok(self
+            .run_logs
+            .lock()
+            .unwrap()
+            .get(run_id)
+            .filter(|log| visible_to(log.tenant_id.as_deref(), tenant_id))
+            .cloned())
+    }
+
+    async fn save_run_record(&self, record: &RunRecord) -> Result<()> {
+        self.run_records
+            .lock()
+            .unwrap()
+            .insert(record.run.run_id.clone(), record.clone());
+        Ok(())
+    }
+
+    async fn get_run_record(
+        &self,
+        run_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<RunRecord>> {
+        Ok(self
+            .run_records
+            .lock()
+            .unwrap()
+            .get(run_id)
+            .filter(|record| visible_to(record.run.tenant_id.as_deref(), tenant_id))
+            .cloned())
+    }
+
+    async fn list_runs(
+        &self,
+        session_id: Option<&str>,
+        agent_id: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<RunRecord>> {
+        let records = self.run_records.lock().unwrap();
+        let mut runs: Vec<&RunRecord> = records
+            .values()
+            .filter(|r| visible_to(r.run.tenant_id.as_deref(), tenant_id))
+            .filter(|r| session_id.is_none_or(|id| r.run.conversation_id.as_deref() == Some(id)))
+            .filter(|r| agent_id.is_none_or(|id| r.run.agent_id == id))
+            .collect();
+        // Newest first, like the real providers
+        let key = |r: &RunRecord| (r.finished_at.clone(), r.run.run_id.clone());
+        runs.sort_by_key(|r| std::cmp::Reverse(key(r)));
+        let start = match after {
+            Some(after) => match records.get(after) {
+                Some(cursor) => runs.iter().take_while(|r| key(r) >= key(cursor)).count(),
+                None => runs.len(),
+            },
+            None => 0,
+        };
+        Ok(runs.into_iter().skip(start).take(limit).cloned().collect())
+    }
+
+    async fn save_audit_entry(&self, entry: &AuditEntry) -> Result<()> {
+        self.audit.lock().unwrap().push(entry.clone());
+        Ok(())
+    }
+
+    async fn list_audit_entries(
+        &self,
+        resource: Option<AuditResource>,
+        tenant_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<AuditEntry>> {
+        Ok(self
+            .audit
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|e| resource.is_none_or(|r| e.resource == r))
+            .filter(|e| tenant_id.is_none_or(|t| e.tenant_id.as_deref() == Some(t)))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn save_memory(&self, _memory: &Memory) -> Result<()> {
+        Ok(())
+    }
+
+    async fn search_memory(
+        &self,
+        _agent_id: Option<&str>,
+        _query_vec: &[f32],
+        _limit: usize,
+        _min_score: f32,
+        _tenant_id: Option<&str>,
+    ) -> Result<Vec<MemoryMatch>> {
+        Ok(vec![])
+    }
+}
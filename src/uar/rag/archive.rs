@@ -0,0 +1,662 @@
+//! Knowledge base export and import as ZIP archives.
+//!
+//! An archive holds `manifest.json` (the knowledge base and its document
+//! records), the documents' original files under `documents/`, and
+//! `chunks.jsonl` with one [`KnowledgeChunk`] per line. Embeddings are left
+//! out: importing embeds the chunks again with the knowledge base's model,
+//! so archives move between environments and vector stores.
+//!
+//! Imports read every member through a limit from [`ArchiveImportConfig`]
+//! and fail once it's exceeded, whatever sizes the archive claims.
+
+use crate::config::ArchiveImportConfig;
+use crate::uar::domain::knowledge::{
+    DocumentStatus, KnowledgeBase, KnowledgeChunk, KnowledgeDocument,
+};
+use crate::uar::rag::ingest::{EMBED_BATCH_SIZE, IngestService};
+use anyhow::{Context, Result, anyhow, bail};
+use async_zip::base::write::ZipFileWriter;
+use async_zip::tokio::read::fs::ZipFileReader;
+use async_zip::{Compression, ZipEntryBuilder};
+use futures::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+use uuid::Uuid;
+
+/// Archive member with the knowledge base and document records.
+pub const MANIFEST_PATH: &str = "manifest.json";
+
+/// Archive member with the chunks, one JSON object per line.
+pub const CHUNKS_PATH: &str = "chunks.jsonl";
+
+/// Content type of archives.
+pub const ARCHIVE_MIME_TYPE: &str = "application/zip";
+
+/// Format version written to manifests; newer archives are refused.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Contents of `manifest.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KbManifest {
+    pub version: u32,
+    pub exported_at: String, // RFC3339
+    pub knowledge_base: KnowledgeBase,
+    #[serde(default)]
+    pub documents: Vec<ArchivedDocument>,
+}
+
+/// A document record in a manifest. `file_path` is cleared; paths on the
+/// exporting host mean nothing elsewhere.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedDocument {
+    #[serde(flatten)]
+    pub document: KnowledgeDocument,
+    /// Archive member with the original file, if it was kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_path: Option<String>,
+}
+
+impl IngestService {
+    /// Write knowledge base `kb_id` to `dest_path/{kb_id}.zip`, returning
+    /// the archive's path.
+    ///
+    /// Members are written one at a time and original files are copied
+    /// through in pieces, so the archive is never held in memory. Documents
+    /// whose file is gone are exported without it.
+    pub async fn export_kb(
+        &self,
+        kb_id: &str,
+        dest_path: &Path,
+        tenant_id: Option<&str>,
+    ) -> Result<PathBuf> {
+        let kb = self
+            .persistence
+            .get_knowledge_base(kb_id, tenant_id)
+            .await?
+            .ok_or_else(|| anyhow!("Knowledge base '{}' not found", kb_id))?;
+
+        let mut documents = Vec::new();
+        let mut files = Vec::new();
+        for document in self.persistence.list_documents(kb_id, tenant_id).await? {
+            let archive_path = match &document.file_path {
+                Some(path) if tokio::fs::try_exists(path).await.unwrap_or(false) => {
+                    let member = document_member(&document);
+                    files.push((member.clone(), PathBuf::from(path)));
+                    Some(member)
+                }
+                Some(path) => {
+                    tracing::warn!(
+                        document_id = %document.id,
+                        path = %path,
+                        "Exporting document without its missing file"
+                    );
+                    None
+                }
+                None => None,
+            };
+            documents.push(ArchivedDocument {
+                document: KnowledgeDocument {
+                    file_path: None,
+                    ..document
+                },
+                archive_path,
+            });
+        }
+        let manifest = KbManifest {
+            version: ARCHIVE_VERSION,
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            knowledge_base: kb,
+            documents,
+        };
+
+        tokio::fs::create_dir_all(dest_path).await?;
+        let path = dest_path.join(format!("{kb_id}.zip"));
+        let mut zip = ZipFileWriter::with_tokio(tokio::fs::File::create(&path).await?);
+        zip.write_entry_whole(member(MANIFEST_PATH), &serde_json::to_vec_pretty(&manifest)?)
+            .await?;
+
+        for (name, source) in files {
+            let mut file = tokio::fs::File::open(&source).await?.compat();
+            let mut entry = zip.write_entry_stream(member(&name)).await?;
+            futures::io::copy(&mut file, &mut entry).await?;
+            entry.close().await?;
+        }
+
+        let mut entry = zip.write_entry_stream(member(CHUNKS_PATH)).await?;
+        for chunk in self.persistence.list_chunks(kb_id, tenant_id).await? {
+            let mut line = serde_json::to_vec(&chunk)?;
+            line.push(b'\n');
+            entry.write_all(&line).await?;
+        }
+        entry.close().await?;
+        zip.close().await?;
+
+        Ok(path)
+    }
+
+    /// Create a knowledge base owned by `tenant_id` from an archive written
+    /// by [`Self::export_kb`], with id `target_kb_id` (a new one if `None`).
+    ///
+    /// Documents get new ids and their chunks are embedded again with the
+    /// knowledge base's model. Original files are restored to the upload
+    /// directory when one is set. The archived name is kept unless it's
+    /// taken, in which case the new id is appended to it. If anything fails,
+    /// nothing imported is kept.
+    pub async fn import_kb(
+        &self,
+        zip_path: &Path,
+        target_kb_id: Option<String>,
+        tenant_id: Option<&str>,
+    ) -> Result<KnowledgeBase> {
+        let zip = ZipFileReader::new(zip_path)
+            .await
+            .context("Not a ZIP archive")?;
+        let limit = self.archive_limits.max_manifest_bytes;
+        let entry = zip
+            .reader_with_entry(member_index(&zip, MANIFEST_PATH)?)
+            .await?;
+        let mut manifest = String::new();
+        std::pin::pin!(entry)
+            .take(limit as u64 + 1)
+            .read_to_string(&mut manifest)
+            .await?;
+        if manifest.len() > limit {
+            bail!("{} is larger than {} bytes", MANIFEST_PATH, limit);
+        }
+        let manifest: KbManifest =
+            serde_json::from_str(&manifest).context("Invalid archive manifest")?;
+        if manifest.version > ARCHIVE_VERSION {
+            bail!("Unsupported archive version {}", manifest.version);
+        }
+
+        let id = target_kb_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+        if self
+            .persistence
+            .get_knowledge_base(&id, tenant_id)
+            .await?
+            .is_some()
+        {
+            bail!("Knowledge base '{}' already exists", id);
+        }
+        let archived = manifest.knowledge_base;
+        let name = match self
+            .persistence
+            .get_knowledge_base_by_name(&archived.name, tenant_id)
+            .await?
+        {
+            Some(_) => format!("{} ({})", archived.name, id),
+            None => archived.name,
+        };
+        let now = chrono::Utc::now().to_rfc3339();
+        let kb = KnowledgeBase {
+            id,
+            name,
+            description: archived.description,
+            config: archived.config,
+            tenant_id: tenant_id.map(str::to_string),
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        self.persistence.save_knowledge_base(&kb).await?;
+
+        let mut imported = Vec::new();
+        match self
+            .import_contents(&zip, &kb, manifest.documents, &mut imported)
+            .await
+        {
+            Ok(()) => Ok(kb),
+            Err(e) => {
+                self.discard_import(&kb, imported).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Restore the documents and chunks of an archive into `kb`, adding
+    /// each document to `imported` before anything of it is written.
+    ///
+    /// Fails on a file, chunk line or chunk count beyond the archive limits.
+    async fn import_contents(
+        &self,
+        zip: &ZipFileReader,
+        kb: &KnowledgeBase,
+        archived: Vec<ArchivedDocument>,
+        imported: &mut Vec<KnowledgeDocument>,
+    ) -> Result<()> {
+        // Archived document ID -> index in `imported`
+        let mut documents = HashMap::new();
+        for ArchivedDocument {
+            document,
+            archive_path,
+        } in archived
+        {
+            let id = Uuid::new_v4().to_string();
+            let file = match (&self.upload_dir, archive_path) {
+                (Some(dir), Some(member)) => {
+                    Some((member, dir.join(restored_file_name(&id, &document.filename))))
+                }
+                _ => None,
+            };
+            documents.insert(document.id.clone(), imported.len());
+            let document = KnowledgeDocument {
+                id,
+                kb_id: kb.id.clone(),
+                file_path: file
+                    .as_ref()
+                    .map(|(_, path)| path.to_string_lossy().into_owned()),
+                chunk_count: 0,
+                status: DocumentStatus::Processing,
+                tenant_id: kb.tenant_id.clone(),
+                idempotency_key: None,
+                created_at: kb.created_at.clone(),
+                updated_at: kb.created_at.clone(),
+                ..document
+            };
+            imported.push(document.clone());
+
+            if let Some((member, path)) = &file {
+                restore_file(zip, member, path, self.archive_limits.max_file_bytes).await?;
+            }
+            self.persistence.save_document(&document).await?;
+        }
+
+        let ArchiveImportConfig {
+            max_chunk_line_bytes,
+            max_chunks,
+            ..
+        } = self.archive_limits;
+        let chunks = zip.reader_with_entry(member_index(zip, CHUNKS_PATH)?).await?;
+        let mut reader = std::pin::pin!(futures::io::BufReader::new(chunks));
+        let mut line = Vec::new();
+        let mut count = 0;
+        let mut batch = Vec::with_capacity(EMBED_BATCH_SIZE);
+        loop {
+            line.clear();
+            // One byte past the limit tells a line that's too long
+            let read = reader
+                .as_mut()
+                .take(max_chunk_line_bytes as u64 + 1)
+                .read_until(b'\n', &mut line)
+                .await?;
+            if read == 0 {
+                break;
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            if line.len() > max_chunk_line_bytes {
+                bail!("Chunk in archive is longer than {max_chunk_line_bytes} bytes");
+            }
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            count += 1;
+            if count > max_chunks {
+                bail!("Archive has more than {max_chunks} chunks");
+            }
+            let chunk: KnowledgeChunk =
+                serde_json::from_slice(&line).context("Invalid chunk in archive")?;
+            batch.push(chunk);
+            if batch.len() == EMBED_BATCH_SIZE {
+                self.import_chunks(kb, std::mem::take(&mut batch), &documents, imported)
+                    .await?;
+            }
+        }
+        if !batch.is_empty() {
+            self.import_chunks(kb, batch, &documents, imported).await?;
+        }
+
+        for document in imported.iter_mut() {
+            document.status = DocumentStatus::Indexed;
+            document.updated_at = chrono::Utc::now().to_rfc3339();
+            self.persistence.save_document(document).await?;
+        }
+        Ok(())
+    }
+
+    /// Embed archived `chunks` and save them to `kb`, moving them to the
+    /// imported copies of their documents.
+    async fn import_chunks(
+        &self,
+        kb: &KnowledgeBase,
+        chunks: Vec<KnowledgeChunk>,
+        documents: &HashMap<String, usize>,
+        imported: &mut [KnowledgeDocument],
+    ) -> Result<()> {
+        let texts = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+        let embeddings = self.vector_matcher.embed_for_kb(&kb.config, texts).await?;
+        if embeddings.len() != chunks.len() {
+            bail!(
+                "Expected {} embeddings, got {}",
+                chunks.len(),
+                embeddings.len()
+            );
+        }
+
+        for (chunk, embedding) in chunks.into_iter().zip(embeddings) {
+            let document_id = chunk
+                .document_id
+                .as_ref()
+                .and_then(|id| documents.get(id))
+                .map(|&i| {
+                    imported[i].chunk_count += 1;
+                    imported[i].id.clone()
+                });
+            let mut metadata = chunk.metadata;
+            if let (Some(serde_json::Value::Object(fields)), Some(id)) =
+                (&mut metadata, &document_id)
+                && fields.contains_key("document_id")
+            {
+                fields.insert("document_id".to_string(), id.clone().into());
+            }
+
+            self.persistence
+                .save_chunk(&KnowledgeChunk {
+                    id: Uuid::new_v4(),
+                    kb_id: kb.id.clone(),
+                    document_id,
+                    content: chunk.content,
+                    metadata,
+                    embedding,
+                    tenant_id: kb.tenant_id.clone(),
+                    created_at: chrono::Utc::now().to_rfc3339(),
+                })
+                .await?;
+        }
+        self.notify_chunks_saved(&kb.id);
+        Ok(())
+    }
+
+    /// Best-effort removal of a failed import.
+    async fn discard_import(&self, kb: &KnowledgeBase, imported: Vec<KnowledgeDocument>) {
+        let tenant_id = kb.tenant_id.as_deref();
+        for document in imported {
+            // Not every document got as far as being saved
+            let _ = self.persistence.delete_document(&document.id, tenant_id).await;
+            if let Some(path) = &document.file_path {
+                let _ = tokio::fs::remove_file(path).await;
+            }
+        }
+        if let Err(e) = self.persistence.delete_knowledge_base(&kb.id, tenant_id).await {
+            tracing::warn!(kb_id = %kb.id, error = %e, "Failed to remove partial import");
+        }
+    }
+}
+
+/// Entry for archive member `name`.
+fn member(name: &str) -> ZipEntryBuilder {
+    ZipEntryBuilder::new(name.to_string().into(), Compression::Deflate)
+}
+
+/// Index of archive member `name`.
+fn member_index(zip: &ZipFileReader, name: &str) -> Result<usize> {
+    zip.file()
+        .entries()
+        .iter()
+        .position(|entry| entry.filename().as_str().is_ok_and(|n| n == name))
+        .ok_or_else(|| anyhow!("Archive has no {}", name))
+}
+
+/// Archive member for `document`'s original file.
+fn document_member(document: &KnowledgeDocument) -> String {
+    let filename = Path::new(&document.filename)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("document");
+    format!("documents/{}.{}", document.id, filename)
+}
+
+/// Name of a restored file: the document ID with the original extension,
+/// as uploads are named.
+fn restored_file_name(doc_id: &str, filename: &str) -> String {
+    let ext = Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| ext.len() <= 16 && ext.chars().all(|c| c.is_ascii_alphanumeric()));
+    match ext {
+        Some(ext) => format!("{doc_id}.{}", ext.to_ascii_lowercase()),
+        None => doc_id.to_string(),
+    }
+}
+
+/// Copy archive member `name` to `path`, removing what was written if the
+/// copy fails or the member holds more than `limit` bytes.
+async fn restore_file(zip: &ZipFileReader, name: &str, path: &Path, limit: u64) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let copied: Result<()> = async {
+        let entry = zip.reader_with_entry(member_index(zip, name)?).await?;
+        let mut file = tokio::fs::File::create(path).await?.compat_write();
+        let copied = futures::io::copy(entry.take(limit + 1), &mut file).await?;
+        if copied > limit {
+            bail!("{} is larger than {} bytes", name, limit);
+        }
+        file.flush().await?;
+        Ok(())
+    }
+    .await;
+    if copied.is_err() {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+    copied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::domain::knowledge::KbConfig;
+    use crate::uar::persistence::{PersistenceLayer, testing::InMemoryPersistence};
+    use crate::uar::rag::chunking::ChunkingStrategy;
+    use crate::uar::runtime::matching::VectorMatcher;
+    use crate::uar::testing::StubEmbedder;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_exported_kb_imports_with_all_chunks() {
+        let store: Arc<dyn PersistenceLayer> = Arc::new(InMemoryPersistence::new());
+        let files = tempfile::tempdir().unwrap();
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let ingest = IngestService::new(Arc::clone(&store), matcher, ChunkingStrategy::Sentence)
+            .with_upload_dir(files.path().join("uploads"));
+
+        let now = chrono::Utc::now().to_rfc3339();
+        store
+            .save_knowledge_base(&KnowledgeBase {
+                id: "kb-1".to_string(),
+                name: "docs".to_string(),
+                description: Some("Rust notes".to_string()),
+                config: KbConfig {
+                    chunk_strategy: ChunkingStrategy::Sentence,
+                    ..KbConfig::default()
+                },
+                tenant_id: None,
+                created_at: now.clone(),
+                updated_at: now.clone(),
+            })
+            .await
+            .unwrap();
+        let texts = [
+            "Ownership frees memory. Borrowing lends it.",
+            "Traits share behavior. Generics reuse code. Macros write code.",
+            "Cargo builds crates.",
+        ];
+        for (i, text) in texts.iter().enumerate() {
+            let id = format!("doc-{i}");
+            let path = files.path().join(format!("{id}.txt"));
+            std::fs::write(&path, text).unwrap();
+            let created = ingest.ingest_text(text, "kb-1", id.clone(), None).await.unwrap();
+            store
+                .save_document(&KnowledgeDocument {
+                    id,
+                    kb_id: "kb-1".to_string(),
+                    filename: format!("notes-{i}.txt"),
+                    file_path: Some(path.to_string_lossy().into_owned()),
+                    mime_type: Some("text/plain".to_string()),
+                    chunk_count: created,
+                    status: DocumentStatus::Indexed,
+                    tenant_id: None,
+                    metadata: None,
+                    idempotency_key: None,
+                    content_hash: None,
+                    created_at: now.clone(),
+                    updated_at: now.clone(),
+                })
+                .await
+                .unwrap();
+        }
+        let original = store.list_chunks("kb-1", None).await.unwrap();
+        assert_eq!(original.len(), 6);
+
+        let archive = ingest
+            .export_kb("kb-1", &files.path().join("exports"), None)
+            .await
+            .unwrap();
+        let kb = ingest
+            .import_kb(&archive, Some("kb-2".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(kb.id, "kb-2");
+        // The original still has the name
+        assert_eq!(kb.name, "docs (kb-2)");
+        assert_eq!(kb.description.as_deref(), Some("Rust notes"));
+
+        let chunks = store.list_chunks("kb-2", None).await.unwrap();
+        assert_eq!(chunks.len(), original.len());
+        let documents = store.list_documents("kb-2", None).await.unwrap();
+        assert_eq!(documents.len(), 3);
+        for document in &documents {
+            assert_eq!(document.status, DocumentStatus::Indexed);
+            let own: Vec<_> = chunks
+                .iter()
+                .filter(|c| c.document_id.as_deref() == Some(document.id.as_str()))
+                .collect();
+            assert_eq!(document.chunk_count, own.len());
+            for chunk in own {
+                let metadata = chunk.metadata.as_ref().unwrap();
+                assert_eq!(metadata["document_id"], document.id.as_str());
+            }
+            let restored = std::fs::read_to_string(document.file_path.as_ref().unwrap()).unwrap();
+            assert!(texts.contains(&restored.as_str()), "{restored}");
+        }
+
+        // Chunks were embedded again
+        let matches = store
+            .search_knowledge_scoped(&["kb-2"], &[1.0, 0.0], 10, 0.5, None, None)
+            .await
+            .unwrap();
+        assert_eq!(matches.len(), 6);
+
+        // Existing knowledge bases aren't overwritten
+        assert!(ingest.import_kb(&archive, Some("kb-1".to_string()), None).await.is_err());
+        assert_eq!(store.list_chunks("kb-1", None).await.unwrap().len(), 6);
+    }
+
+    /// Write an archive of `kb-1` with one document, its file and `chunks`.
+    async fn write_archive(path: &Path, file: &[u8], chunks: &str) {
+        let now = chrono::Utc::now().to_rfc3339();
+        let manifest = KbManifest {
+            version: ARCHIVE_VERSION,
+            exported_at: now.clone(),
+            knowledge_base: KnowledgeBase {
+                id: "kb-1".to_string(),
+                name: "docs".to_string(),
+                description: None,
+                config: KbConfig::default(),
+                tenant_id: None,
+                created_at: now.clone(),
+                updated_at: now.clone(),
+            },
+            documents: vec![ArchivedDocument {
+                document: KnowledgeDocument {
+                    id: "doc-1".to_string(),
+                    kb_id: "kb-1".to_string(),
+                    filename: "notes.txt".to_string(),
+                    file_path: None,
+                    mime_type: Some("text/plain".to_string()),
+                    chunk_count: 0,
+                    status: DocumentStatus::Indexed,
+                    tenant_id: None,
+                    metadata: None,
+                    idempotency_key: None,
+                    content_hash: None,
+                    created_at: now.clone(),
+                    updated_at: now,
+                },
+                archive_path: Some("documents/doc-1.notes.txt".to_string()),
+            }],
+        };
+        let mut zip = ZipFileWriter::with_tokio(tokio::fs::File::create(path).await.unwrap());
+        let members = [
+            (MANIFEST_PATH, serde_json::to_vec(&manifest).unwrap()),
+            ("documents/doc-1.notes.txt", file.to_vec()),
+            (CHUNKS_PATH, chunks.as_bytes().to_vec()),
+        ];
+        for (name, data) in members {
+            zip.write_entry_whole(member(name), &data).await.unwrap();
+        }
+        zip.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_stops_at_archive_limits() {
+        let store: Arc<dyn PersistenceLayer> = Arc::new(InMemoryPersistence::new());
+        let files = tempfile::tempdir().unwrap();
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let limits = ArchiveImportConfig {
+            max_manifest_bytes: 4096,
+            max_file_bytes: 64,
+            max_chunk_line_bytes: 256,
+            max_chunks: 2,
+        };
+        let ingest = IngestService::new(Arc::clone(&store), matcher, ChunkingStrategy::Sentence)
+            .with_upload_dir(files.path().join("uploads"))
+            .with_archive_limits(limits.clone());
+        let chunk = |content: &str| {
+            serde_json::to_string(&KnowledgeChunk {
+                id: Uuid::new_v4(),
+                kb_id: "kb-1".to_string(),
+                document_id: Some("doc-1".to_string()),
+                content: content.to_string(),
+                metadata: None,
+                embedding: Vec::new(),
+                tenant_id: None,
+                created_at: String::new(),
+            })
+            .unwrap()
+        };
+        let archive = files.path().join("kb.zip");
+
+        write_archive(&archive, b"notes", &format!("{}\n{}\n", chunk("a"), chunk("b"))).await;
+        let kb = ingest.import_kb(&archive, None, None).await.unwrap();
+        assert_eq!(store.list_chunks(&kb.id, None).await.unwrap().len(), 2);
+
+        // Each of these compresses to a few bytes but unpacks past a limit
+        let cases = [
+            (vec![b'x'; 1 << 20], format!("{}\n", chunk("a"))),
+            (b"notes".to_vec(), format!("{}\n", chunk(&"a".repeat(1 << 20)))),
+            (b"notes".to_vec(), format!("{}\n", chunk("a")).repeat(3)),
+        ];
+        for (file, chunks) in cases {
+            write_archive(&archive, &file, &chunks).await;
+            assert!(ingest.import_kb(&archive, Some("kb-2".to_string()), None).await.is_err());
+            assert!(store.get_knowledge_base("kb-2", None).await.unwrap().is_none());
+        }
+
+        let ingest = ingest.with_archive_limits(ArchiveImportConfig {
+            max_manifest_bytes: 64,
+            ..limits
+        });
+        write_archive(&archive, b"notes", &chunk("a")).await;
+        let error = ingest.import_kb(&archive, None, None).await.unwrap_err();
+        assert!(error.to_string().contains(MANIFEST_PATH), "{error}");
+    }
+}
@@ -0,0 +1,187 @@
+//! Citations for knowledge injected into a run.
+//!
+//! Every chunk added to the prompt is a citation candidate. With
+//! `memory.kb.cite_only_used`, candidates are compared against the final
+//! answer by embedding similarity, and only those above the agent's
+//! threshold are marked as used; the rest are reported as available but
+//! unused.
+
+use crate::uar::domain::artifact::KbMemory;
+use crate::uar::domain::events::CitationSource;
+use crate::uar::domain::knowledge::KnowledgeMatch;
+use crate::uar::runtime::matching::VectorMatcher;
+use tracing::warn;
+
+/// Maximum snippet length (in characters) attached to a citation.
+const SNIPPET_CHARS: usize = 200;
+
+/// Citation for an injected chunk, marked as used with its retrieval score.
+pub fn citation_source(m: &KnowledgeMatch) -> CitationSource {
+    let metadata = m.chunk.metadata.as_ref();
+    let field = |key: &str| {
+        metadata
+            .and_then(|md| md.get(key))
+            .and_then(serde_json::Value::as_str)
+            .map(str::to_string)
+    };
+
+    CitationSource {
+        title: field("filename")
+            .or_else(|| m.chunk.document_id.clone())
+            .unwrap_or_else(|| "Knowledge base".to_string()),
+        url: field("path").unwrap_or_else(|| format!("kb://{}/{}", m.chunk.kb_id, m.chunk.id)),
+        snippet: Some(m.chunk.content.chars().take(SNIPPET_CHARS).collect()),
+        relevance: Some(m.score),
+        used: true,
+    }
+}
+
+/// Build the citations for a finished answer.
+///
+/// Without `cite_only_used` every injected chunk is cited. Otherwise each
+/// chunk is scored against the answer; if embedding fails, all chunks are
+/// cited rather than none.
+pub async fn build_citations(
+    matcher: &VectorMatcher,
+    matches: &[KnowledgeMatch],
+    answer: &str,
+    config: &KbMemory,
+) -> Vec<CitationSource> {
+    let mut sources: Vec<CitationSource> = matches.iter().map(citation_source).collect();
+    if !config.cite_only_used || sources.is_empty() {
+        return sources;
+    }
+
+    if answer.trim().is_empty() {
+        for source in &mut sources {
+            source.used = false;
+        }
+        return sources;
+    }
+
+    let mut texts = vec![answer.to_string()];
+    texts.extend(matches.iter().map(|m| m.chunk.content.clone()));
+    match matcher.embed_batch(texts).await {
+        Ok(embeddings) => match embeddings.split_first() {
+            Some((answer_vec, chunk_vecs)) => {
+                mark_used(&mut sources, answer_vec, chunk_vecs, config.citation_threshold);
+            }
+            None => warn!("Embedding returned no vectors; citing all chunks"),
+        },
+        Err(e) => warn!("Failed to embed answer for citation filtering: {:?}", e),
+    }
+    sources
+}
+
+/// Score each source against the answer and mark those below `threshold`
+/// as unused.
+pub fn mark_used(
+    sources: &mut [CitationSource],
+    answer: &[f32],
+    chunks: &[Vec<f32>],
+    threshold: f32,
+) {
+    for (source, chunk) in sources.iter_mut().zip(chunks) {
+        let similarity = cosine_similarity(answer, chunk);
+        source.relevance = Some(similarity);
+        source.used = similarity >= threshold;
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot_product: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot_product / (norm_a * norm_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::domain::knowledge::KnowledgeChunk;
+    use crate::uar::rag::embedding::EmbeddingProvider;
+    use async_trait::async_trait;
+    use std::sync::Arc;
+
+    /// Embeds text as counts of a few topic words.
+    #[derive(Debug)]
+    struct TopicEmbedder;
+
+    const TOPICS: [&str; 3] = ["rust", "borrow", "recipe"];
+
+    #[async_trait]
+    impl EmbeddingProvider for TopicEmbedder {
+        #[allow(clippy::cast_precision_loss)]
+        async fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    let t = t.to_lowercase();
+                    TOPICS.iter().map(|w| t.matches(w).count() as f32).collect()
+                })
+                .collect())
+        }
+
+        fn dimensions(&self) -> usize {
+            TOPICS.len()
+        }
+    }
+
+    fn chunk(content: &str, score: f32) -> KnowledgeMatch {
+        KnowledgeMatch {
+            chunk: KnowledgeChunk {
+                id: uuid::Uuid::new_v4(),
+                kb_id: "kb-1".to_string(),
+                document_id: None,
+                content: content.to_string(),
+                metadata: Some(serde_json::json!({ "filename": "notes.md" })),
+                embedding: Vec::new(),
+                tenant_id: None,
+                created_at: String::new(),
+            },
+            score,
+        }
+    }
+
+    fn config(cite_only_used: bool) -> KbMemory {
+        KbMemory {
+            enabled: true,
+            cite_only_used,
+            ..KbMemory::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unused_chunk_not_cited_when_filtering() {
+        let matcher = VectorMatcher::with_provider(0.5, Arc::new(TopicEmbedder));
+        let matches = vec![
+            chunk("Rust's borrow checker enforces ownership.", 0.82),
+            chunk("A recipe for sourdough bread.", 0.71),
+        ];
+        let answer = "The borrow checker in Rust rejects aliasing mutable borrows.";
+
+        let sources = build_citations(&matcher, &matches, answer, &config(true)).await;
+        assert_eq!(sources.len(), 2);
+        assert!(sources[0].used);
+        assert!(!sources[1].used, "unrelated chunk must not be cited");
+        assert!(sources[1].relevance.unwrap() < 0.5);
+
+        // Without the filter every injected chunk is cited
+        let sources = build_citations(&matcher, &matches, answer, &config(false)).await;
+        assert!(sources.iter().all(|s| s.used));
+        assert_eq!(sources[1].relevance, Some(0.71));
+    }
+
+    #[test]
+    fn test_citation_source_fields() {
+        let source = citation_source(&chunk("content", 0.9));
+        assert_eq!(source.title, "notes.md");
+        assert!(source.url.starts_with("kb://kb-1/"));
+        assert_eq!(source.snippet.as_deref(), Some("content"));
+    }
+}
@@ -0,0 +1,825 @@
+//! Pluggable embedding providers.
+//!
+//! Knowledge bases select an embedding backend through
+//! `KbConfig::embedding_provider`:
+//! - `fastembed`: local ONNX inference (default, no API key)
+//! - `openai`: OpenAI-compatible `/v1/embeddings` (`OPENAI_API_KEY`, falling
+//!   back to `LLM_API_KEY`; `OPENAI_BASE_URL` overrides the endpoint)
+//! - `mistral`: Mistral's embeddings API (`MISTRAL_API_KEY`)
+//!
+//! Remote providers share one pooled HTTP client (see [`http_client`]), so
+//! connections and TLS sessions are reused across knowledge bases.
+//!
+//! Knowledge chunks are stored in `VECTOR(384)` columns, so a KB whose model
+//! produces a different dimensionality is rejected at creation time by
+//! [`validate_kb_dimensions`]. Vectors that still arrive with the wrong
+//! dimensionality (e.g. after a model change) are rejected, or padded and
+//! truncated by [`reconcile_dimensions`] while `embedding.reconcile_dimensions`
+//! is on.
+
+use crate::uar::domain::knowledge::KbConfig;
+use crate::uar::runtime::matching::{EmbeddingCache, EmbeddingLimiter};
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::info;
+
+/// Dimensionality of the pgvector columns holding chunk embeddings.
+pub const STORAGE_DIMENSIONS: usize = 384;
+
+/// Idle connections per host kept by the shared client unless configured.
+pub const DEFAULT_MAX_IDLE_CONNECTIONS: usize = 16;
+/// How long an unused pooled connection stays open.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+static HTTP_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+static RECONCILE_DIMENSIONS: AtomicBool = AtomicBool::new(false);
+
+fn build_http_client(max_idle_connections: usize) -> reqwest::Client {
+    reqwest::Client::builder()
+        .pool_max_idle_per_host(max_idle_connections)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(POOL_IDLE_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Configure the HTTP client shared by remote embedding providers.
+///
+/// Must be called before the first provider is created; returns `false` if
+/// the shared client was already initialized.
+pub fn configure_http_client(max_idle_connections: usize) -> bool {
+    HTTP_CLIENT.set(build_http_client(max_idle_connections)).is_ok()
+}
+
+/// The HTTP client shared by remote embedding providers.
+///
+/// Clones share one connection pool.
+pub fn http_client() -> reqwest::Client {
+    HTTP_CLIENT
+        .get_or_init(|| build_http_client(DEFAULT_MAX_IDLE_CONNECTIONS))
+        .clone()
+}
+
+/// Pad or truncate stored and searched vectors whose dimensionality does
+/// not match the storage columns, instead of rejecting them.
+pub fn configure_dimension_reconciliation(enabled: bool) {
+    if enabled {
+        tracing::warn!(
+            "embedding.reconcile_dimensions is on: mismatched vectors are padded or truncated \
+             to {STORAGE_DIMENSIONS} dimensions, which degrades search; re-embed the affected \
+             knowledge and turn it off"
+        );
+    }
+    RECONCILE_DIMENSIONS.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether [`configure_dimension_reconciliation`] turned reconciliation on.
+pub fn dimension_reconciliation_enabled() -> bool {
+    RECONCILE_DIMENSIONS.load(Ordering::Relaxed)
+}
+
+/// A vector has the wrong dimensionality and reconciliation is off.
+#[derive(Debug, thiserror::Error)]
+#[error("vector has {actual} dimensions, expected {expected}")]
+pub struct DimensionMismatch {
+    pub expected: usize,
+    pub actual: usize,
+}
+
+/// Fit `vector` to `target` dimensions.
+///
+/// Vectors of the right size pass through. Otherwise, with `reconcile` on,
+/// shorter vectors are padded with zeros and longer ones truncated, with a
+/// warning that the data needs re-embedding; with it off they are rejected.
+pub fn reconcile_dimensions(
+    vector: &[f32],
+    target: usize,
+    reconcile: bool,
+) -> Result<Cow<'_, [f32]>, DimensionMismatch> {
+    let actual = vector.len();
+    if actual == target {
+        return Ok(Cow::Borrowed(vector));
+    }
+    if !reconcile {
+        return Err(DimensionMismatch {
+            expected: target,
+            actual,
+        });
+    }
+
+    tracing::warn!(
+        actual,
+        target,
+        "Reconciling a {actual}-dimensional embedding to {target} dimensions; \
+         re-embed this data with a {target}-dimensional model"
+    );
+    let mut fitted = vector[..actual.min(target)].to_vec();
+    fitted.resize(target, 0.0);
+    Ok(Cow::Owned(fitted))
+}
+
+// =============================================================================
+// Provider Trait
+// =============================================================================
+
+/// Produces vector embeddings for text.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync + std::fmt::Debug {
+    /// Embed texts, returning one vector per input in order.
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>>;
+
+    /// Dimensionality of the produced vectors.
+    fn dimensions(&self) -> usize;
+
+    /// Eagerly load models or check connectivity (optional).
+    async fn initialize(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// =============================================================================
+// FastEmbed (local)
+// =============================================================================
+
+/// Local embedding provider using fastembed.
+///
+/// The model is loaded on [`EmbeddingProvider::initialize`] or lazily on the
+/// first embed call.
+pub struct FastEmbedProvider {
+    model_id: EmbeddingModel,
+    model_name: String,
+    dimensions: usize,
+    model: Arc<Mutex<Option<TextEmbedding>>>,
+}
+
+impl std::fmt::Debug for FastEmbedProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FastEmbedProvider")
+            .field("model", &self.model_name)
+            .field("dimensions", &self.dimensions)
+            .finish()
+    }
+}
+
+impl Default for FastEmbedProvider {
+    fn default() -> Self {
+        Self::new(&KbConfig::default_embedding_model())
+            .expect("default embedding model is supported")
+    }
+}
+
+impl FastEmbedProvider {
+    /// Create a provider for a supported fastembed model ID.
+    pub fn new(model: &str) -> Result<Self> {
+        let (model_id, dimensions) = fastembed_model(model)
+            .ok_or_else(|| anyhow!("Unsupported fastembed model: {}", model))?;
+        Ok(Self {
+            model_id,
+            model_name: model.to_string(),
+            dimensions,
+            model: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    fn load(model_id: EmbeddingModel, model_name: &str) -> Result<TextEmbedding> {
+        info!("Initializing fastembed model ({})...", model_name);
+        let mut options = InitOptions::new(model_id);
+        options.show_download_progress = true;
+        Ok(TextEmbedding::try_new(options)?)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for FastEmbedProvider {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let mut model_guard = self.model.lock().await;
+        let permit = EmbeddingLimiter::global().acquire().await?;
+        let model = model_guard.take();
+        let model_id = self.model_id.clone();
+        let model_name = self.model_name.clone();
+
+        // Inference (and first-use loading) is CPU-bound
+        let (result, model) = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let mut model = match model {
+                Some(model) => model,
+                None => match Self::load(model_id, &model_name) {
+                    Ok(model) => model,
+                    Err(e) => return (Err(e), None),
+                },
+            };
+            let result = model.embed(texts, None).map_err(|e| anyhow!(e));
+            (result, Some(model))
+        })
+        .await?;
+
+        *model_guard = model;
+        result
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    async fn initialize(&self) -> Result<()> {
+        let mut model_guard = self.model.lock().await;
+        if model_guard.is_none() {
+            let model_id = self.model_id.clone();
+            let model_name = self.model_name.clone();
+            let model =
+                tokio::task::spawn_blocking(move || Self::load(model_id, &model_name)).await??;
+            *model_guard = Some(model);
+        }
+        Ok(())
+    }
+}
+
+/// Map a model ID to its fastembed variant and dimensionality.
+fn fastembed_model(model: &str) -> Option<(EmbeddingModel, usize)> {
+    match model {
+        "BAAI/bge-small-en-v1.5" => Some((EmbeddingModel::BGESmallENV15, 384)),
+        "BAAI/bge-base-en-v1.5" => Some((EmbeddingModel::BGEBaseENV15, 768)),
+        "BAAI/bge-large-en-v1.5" => Some((EmbeddingModel::BGELargeENV15, 1024)),
+        "sentence-transformers/all-MiniLM-L6-v2" => Some((EmbeddingModel::AllMiniLML6V2, 384)),
+        "intfloat/multilingual-e5-small" => Some((EmbeddingModel::MultilingualE5Small, 384)),
+        "nomic-ai/nomic-embed-text-v1.5" => Some((EmbeddingModel::NomicEmbedTextV15, 768)),
+        _ => None,
+    }
+}
+
+// =============================================================================
+// OpenAI-compatible HTTP provider
+// =============================================================================
+
+/// Embedding provider for OpenAI's `/v1/embeddings` API.
+///
+/// Inputs are sent in batches of at most [`Self::MAX_BATCH_SIZE`]; rate
+/// limited (429) requests are retried with exponential backoff. Results are
+/// cached per model and dimensions when a shared [`EmbeddingCache`] is
+/// attached. Requests don't take [`EmbeddingLimiter`] permits: they are
+/// I/O-bound, and a slow API would otherwise stall local embedding.
+/// [`EmbeddingProvider::initialize`] sends a one-input request to check the
+/// endpoint and credentials.
+#[derive(Debug)]
+pub struct OpenAIEmbeddingProvider {
+    client: reqwest::Client,
+    endpoint: String,
+    api_key: String,
+    model: String,
+    dimensions: usize,
+    /// Send `dimensions` in requests (for models supporting shortened vectors)
+    request_dimensions: bool,
+    batch_size: usize,
+    cache: Option<Arc<EmbeddingCache>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl OpenAIEmbeddingProvider {
+    /// OpenAI's per-request input limit.
+    pub const MAX_BATCH_SIZE: usize = 2048;
+    /// Maximum retries for rate-limited requests.
+    const MAX_RETRIES: u32 = 5;
+    /// Base delay between retries (doubled on each attempt).
+    const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+    /// Default API base URL.
+    const DEFAULT_BASE_URL: &'static str = "https://api.openai.com";
+
+    /// Create a provider for an OpenAI embedding model.
+    ///
+    /// `dimensions` overrides the model's native size (supported by the
+    /// `text-embedding-3` family); it is required for unknown models.
+    pub fn new(
+        base_url: &str,
+        api_key: impl Into<String>,
+        model: impl Into<String>,
+        dimensions: Option<usize>,
+    ) -> Result<Self> {
+        let model = model.into();
+        let native = openai_dimensions(&model);
+        let resolved = dimensions.or(native).ok_or_else(|| {
+            anyhow!(
+                "Unknown dimensions for embedding model '{}'; set vector_dimensions",
+                model
+            )
+        })?;
+
+        Ok(Self {
+            client: http_client(),
+            endpoint: format!("{}/v1/embeddings", base_url.trim_end_matches('/')),
+            api_key: api_key.into(),
+            request_dimensions: dimensions.is_some() && native != dimensions,
+            model,
+            dimensions: resolved,
+            batch_size: Self::MAX_BATCH_SIZE,
+            cache: None,
+        })
+    }
+
+    /// Create a provider from `OPENAI_API_KEY` (or `LLM_API_KEY`) and
+    /// `OPENAI_BASE_URL`.
+    pub fn from_env(model: impl Into<String>, dimensions: Option<usize>) -> Result<Self> {
+        let api_key = std::env::var("OPENAI_API_KEY")
+            .or_else(|_| std::env::var("LLM_API_KEY"))
+            .context("OPENAI_API_KEY must be set to use OpenAI embeddings")?;
+        let base_url = std::env::var("OPENAI_BASE_URL")
+            .unwrap_or_else(|_| Self::DEFAULT_BASE_URL.to_string());
+        Self::new(&base_url, api_key, model, dimensions)
+    }
+
+    /// Cache results in a shared embedding cache, keyed by model and
+    /// dimensions.
+    pub fn with_cache(mut self, cache: Arc<EmbeddingCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Limit the number of inputs per request.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, Self::MAX_BATCH_SIZE);
+        self
+    }
+
+    async fn embed_uncached(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            embeddings.extend(self.embed_batch(batch).await?);
+        }
+        Ok(embeddings)
+    }
+
+    async fn embed_batch(&self, batch: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut body = serde_json::json!({
+            "model": self.model,
+            "input": batch,
+        });
+        if self.request_dimensions {
+            body["dimensions"] = serde_json::json!(self.dimensions);
+        }
+
+        let mut attempt = 0;
+        let response = loop {
+            let response = self
+                .client
+                .post(&self.endpoint)
+                .bearer_auth(&self.api_key)
+                .json(&body)
+                .send()
+                .await
+                .context("Embedding request failed")?;
+
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS
+                || attempt >= Self::MAX_RETRIES
+            {
+                break response;
+            }
+
+            let delay = retry_after(&response)
+                .unwrap_or_else(|| Self::RETRY_BASE_DELAY * 2u32.pow(attempt));
+            tracing::warn!(
+                model = %self.model,
+                attempt = attempt + 1,
+                "Embedding API rate limited, retrying in {:?}",
+                delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
+
+        let status = response.status();
+        if matches!(status, reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN) {
+            return Err(anyhow!(
+                "Embedding API rejected the credentials for model '{}' ({})",
+                self.model,
+                status
+            ));
+        }
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Embedding API error ({}): {}", status, error_text));
+        }
+
+        let mut parsed: EmbeddingResponse = response.json().await?;
+        if parsed.data.len() != batch.len() {
+            return Err(anyhow!(
+                "Embedding API returned {} vectors for {} inputs",
+                parsed.data.len(),
+                batch.len()
+            ));
+        }
+        parsed.data.sort_by_key(|d| d.index);
+        Ok(parsed.data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAIEmbeddingProvider {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        match &self.cache {
+            Some(cache) => {
+                // Shortened vectors of a model differ from its native ones
+                let namespace = format!("{}:{}", self.model, self.dimensions);
+                cache
+                    .get_or_embed_in(&namespace, texts, |misses| self.embed_uncached(misses))
+                    .await
+            }
+            None => self.embed_uncached(texts).await,
+        }
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// Warm up the pooled connection and validate the API key.
+    async fn initialize(&self) -> Result<()> {
+        self.embed_batch(&["ping".to_string()])
+            .await
+            .with_context(|| format!("Embedding warmup failed for model '{}'", self.model))?;
+        Ok(())
+    }
+}
+
+/// Parse a `Retry-After` header given in seconds.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Native dimensionality of known OpenAI embedding models.
+fn openai_dimensions(model: &str) -> Option<usize> {
+    match model {
+        "text-embedding-3-small" | "text-embedding-ada-002" => Some(1536),
+        "text-embedding-3-large" => Some(3072),
+        _ => None,
+    }
+}
+
+// =============================================================================
+// Mistral
+// =============================================================================
+
+/// Embedding provider for Mistral's embeddings API.
+///
+/// The API is OpenAI-compatible, so this wraps [`OpenAIEmbeddingProvider`]
+/// with Mistral's endpoint, smaller batches, and model dimensions.
+#[derive(Debug)]
+pub struct MistralEmbeddingProvider {
+    inner: OpenAIEmbeddingProvider,
+}
+
+impl MistralEmbeddingProvider {
+    /// Mistral API base URL.
+    const BASE_URL: &'static str = "https://api.mistral.ai";
+    /// Inputs per request (Mistral limits total tokens per request).
+    const BATCH_SIZE: usize = 128;
+
+    /// Create a provider for a Mistral embedding model.
+    pub fn new(api_key: impl Into<String>, model: impl Into<String>) -> Result<Self> {
+        let model = model.into();
+        let dimensions = match model.as_str() {
+            "mistral-embed" => 1024,
+            "codestral-embed" => 1536,
+            other => return Err(anyhow!("Unknown Mistral embedding model: {}", other)),
+        };
+        let inner = OpenAIEmbeddingProvider::new(Self::BASE_URL, api_key, model, Some(dimensions))?
+            .with_batch_size(Self::BATCH_SIZE);
+        Ok(Self { inner })
+    }
+
+    /// Create a provider from `MISTRAL_API_KEY`.
+    pub fn from_env(model: impl Into<String>) -> Result<Self> {
+        let api_key = std::env::var("MISTRAL_API_KEY")
+            .context("MISTRAL_API_KEY must be set to use Mistral embeddings")?;
+        Self::new(api_key, model)
+    }
+
+    /// Cache results in a shared embedding cache, keyed by model and
+    /// dimensions.
+    pub fn with_cache(mut self, cache: Arc<EmbeddingCache>) -> Self {
+        self.inner = self.inner.with_cache(cache);
+        self
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for MistralEmbeddingProvider {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        self.inner.embed(texts).await
+    }
+
+    fn dimensions(&self) -> usize {
+        self.inner.dimensions()
+    }
+
+    async fn initialize(&self) -> Result<()> {
+        self.inner.initialize().await
+    }
+}
+
+// =============================================================================
+// Factory & Validation
+// =============================================================================
+
+/// Factory for creating embedding providers from knowledge base configuration.
+#[derive(Debug)]
+pub struct EmbeddingProviderFactory;
+
+impl EmbeddingProviderFactory {
+    /// Create the embedding provider a knowledge base is configured for.
+    ///
+    /// HTTP providers share `cache` (keyed by model and dimensions) when
+    /// given.
+    pub fn create(
+        config: &KbConfig,
+        cache: Option<Arc<EmbeddingCache>>,
+    ) -> Result<Arc<dyn EmbeddingProvider>> {
+        match config.embedding_provider.as_str() {
+            "fastembed" => Ok(Arc::new(FastEmbedProvider::new(&config.embedding_model)?)),
+            "openai" => {
+                let mut provider = OpenAIEmbeddingProvider::from_env(
+                    config.embedding_model.clone(),
+                    config.vector_dimensions,
+                )?;
+                if let Some(cache) = cache {
+                    provider = provider.with_cache(cache);
+                }
+                Ok(Arc::new(provider))
+            }
+            "mistral" => {
+                let mut provider =
+                    MistralEmbeddingProvider::from_env(config.embedding_model.clone())?;
+                if let Some(cache) = cache {
+                    provider = provider.with_cache(cache);
+                }
+                Ok(Arc::new(provider))
+            }
+            other => Err(anyhow!("Unknown embedding provider: {}", other)),
+        }
+    }
+
+    /// Cache key identifying a provider configuration.
+    pub fn cache_key(config: &KbConfig) -> String {
+        format!(
+            "{}:{}:{}",
+            config.embedding_provider,
+            config.embedding_model,
+            config.vector_dimensions.unwrap_or_default()
+        )
+    }
+}
+
+/// Resolve the dimensionality a KB's embedding model will produce.
+pub fn resolve_dimensions(config: &KbConfig) -> Result<usize> {
+    let native = match config.embedding_provider.as_str() {
+        "fastembed" => fastembed_model(&config.embedding_model).map(|(_, dims)| dims),
+        "openai" => openai_dimensions(&config.embedding_model),
+        "mistral" => match config.embedding_model.as_str() {
+            "mistral-embed" => Some(1024),
+            "codestral-embed" => Some(1536),
+            _ => None,
+        },
+        other => return Err(anyhow!("Unknown embedding provider: {}", other)),
+    };
+
+    match (native, config.vector_dimensions) {
+        // Only OpenAI's text-embedding-3 models can shorten their output
+        (Some(native), Some(requested))
+            if requested != native && !config.embedding_model.starts_with("text-embedding-3") =>
+        {
+            Err(anyhow!(
+                "Embedding model '{}' produces {}-dimensional vectors, not the configured {}",
+                config.embedding_model,
+                native,
+                requested
+            ))
+        }
+        (_, Some(requested)) => Ok(requested),
+        (Some(native), None) => Ok(native),
+        (None, None) => Err(anyhow!(
+            "Unknown embedding model '{}' for provider '{}'; set vector_dimensions",
+            config.embedding_model,
+            config.embedding_provider
+        )),
+    }
+}
+
+/// Check that a KB's embeddings fit the vector storage columns.
+///
+/// Catches mismatches when the KB is created instead of as a pgvector cast
+/// failure on the first ingested chunk.
+pub fn validate_kb_dimensions(config: &KbConfig) -> Result<()> {
+    let dimensions = resolve_dimensions(config)?;
+    if dimensions != STORAGE_DIMENSIONS {
+        let hint = if config.embedding_model.starts_with("text-embedding-3") {
+            format!("set vector_dimensions: {STORAGE_DIMENSIONS}")
+        } else {
+            format!("choose a {STORAGE_DIMENSIONS}-dimensional model")
+        };
+        return Err(anyhow!(
+            "Embedding model '{}' produces {}-dimensional vectors but knowledge chunks are stored as {}-dimensional vectors; {}",
+            config.embedding_model,
+            dimensions,
+            STORAGE_DIMENSIONS,
+            hint
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Json, Router,
+        extract::ConnectInfo,
+        http::{HeaderMap, StatusCode, header},
+        routing::post,
+    };
+    use std::net::SocketAddr;
+
+    fn kb_config(provider: &str, model: &str, dims: Option<usize>) -> KbConfig {
+        KbConfig {
+            embedding_provider: provider.to_string(),
+            embedding_model: model.to_string(),
+            vector_dimensions: dims,
+            ..KbConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_reconcile_dimensions() {
+        let vector = [0.5, -0.5, 1.0];
+        assert!(matches!(reconcile_dimensions(&vector, 3, false), Ok(Cow::Borrowed(_))));
+
+        let err = reconcile_dimensions(&vector, 5, false).unwrap_err();
+        assert_eq!((err.expected, err.actual), (5, 3));
+        assert!(reconcile_dimensions(&vector, 2, false).is_err());
+
+        let padded = reconcile_dimensions(&vector, 5, true).unwrap();
+        assert_eq!(*padded, [0.5, -0.5, 1.0, 0.0, 0.0]);
+        let truncated = reconcile_dimensions(&vector, 2, true).unwrap();
+        assert_eq!(*truncated, [0.5, -0.5]);
+    }
+
+    #[test]
+    fn test_default_kb_dimensions_valid() {
+        assert!(validate_kb_dimensions(&KbConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_dimension_mismatch_rejected() {
+        let err = validate_kb_dimensions(&kb_config("openai", "text-embedding-3-small", None))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("1536"));
+        assert!(err.contains("vector_dimensions: 384"));
+
+        assert!(validate_kb_dimensions(&kb_config("mistral", "mistral-embed", None)).is_err());
+        assert!(
+            validate_kb_dimensions(&kb_config("fastembed", "BAAI/bge-small-en-v1.5", Some(768)))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_openai_shortened_dimensions_accepted() {
+        let config = kb_config("openai", "text-embedding-3-small", Some(384));
+        assert!(validate_kb_dimensions(&config).is_ok());
+
+        let provider = OpenAIEmbeddingProvider::new(
+            "http://localhost",
+            "key",
+            "text-embedding-3-small",
+            Some(384),
+        )
+        .unwrap();
+        assert_eq!(provider.dimensions(), 384);
+        assert!(provider.request_dimensions);
+    }
+
+    #[test]
+    fn test_unknown_provider_rejected() {
+        assert!(resolve_dimensions(&kb_config("nope", "model", None)).is_err());
+        assert!(EmbeddingProviderFactory::create(&kb_config("nope", "model", None), None).is_err());
+    }
+
+    type Peers = Arc<std::sync::Mutex<Vec<SocketAddr>>>;
+
+    /// Serve `/v1/embeddings`, recording each request's client address and
+    /// accepting only `good-key`.
+    async fn mock_embeddings_api() -> (String, Peers) {
+        let peers = Peers::default();
+        let recorded = Arc::clone(&peers);
+        let app = Router::new().route(
+            "/v1/embeddings",
+            post(
+                move |ConnectInfo(peer): ConnectInfo<SocketAddr>,
+                      headers: HeaderMap,
+                      Json(body): Json<serde_json::Value>| {
+                    let recorded = Arc::clone(&recorded);
+                    async move {
+                        recorded.lock().unwrap().push(peer);
+                        let authorized = headers
+                            .get(header::AUTHORIZATION)
+                            .is_some_and(|v| v == "Bearer good-key");
+                        if !authorized {
+                            let error = serde_json::json!({ "error": "invalid api key" });
+                            return (StatusCode::UNAUTHORIZED, Json(error));
+                        }
+                        let inputs = body["input"].as_array().map_or(0, Vec::len);
+                        let dims = body["dimensions"].as_u64().unwrap_or(4);
+                        let embedding = vec![0.5; usize::try_from(dims).unwrap()];
+                        let data: Vec<_> = (0..inputs)
+                            .map(|i| serde_json::json!({ "index": i, "embedding": embedding }))
+                            .collect();
+                        (StatusCode::OK, Json(serde_json::json!({ "data": data })))
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .await
+                .unwrap()
+        });
+        (format!("http://{addr}"), peers)
+    }
+
+    #[tokio::test]
+    async fn test_remote_embedders_reuse_pooled_connection() {
+        let (base_url, peers) = mock_embeddings_api().await;
+        let first =
+            OpenAIEmbeddingProvider::new(&base_url, "good-key", "model-a", Some(4)).unwrap();
+        let second =
+            OpenAIEmbeddingProvider::new(&base_url, "good-key", "model-b", Some(4)).unwrap();
+
+        for provider in [&first, &second, &first, &second] {
+            let vectors = provider.embed(vec!["hello".to_string()]).await.unwrap();
+            assert_eq!(vectors, vec![vec![0.5; 4]]);
+        }
+
+        // Every request arrived over the same connection
+        let peers = peers.lock().unwrap();
+        assert_eq!(peers.len(), 4);
+        assert!(peers.iter().all(|p| *p == peers[0]), "{peers:?}");
+    }
+
+    #[tokio::test]
+    async fn test_cache_separates_dimensions_of_a_model() {
+        let (base_url, peers) = mock_embeddings_api().await;
+        let cache = Arc::new(EmbeddingCache::new(EmbeddingCache::DEFAULT_MAX_ENTRIES));
+        let provider = |dims| {
+            OpenAIEmbeddingProvider::new(&base_url, "good-key", "model", Some(dims))
+                .unwrap()
+                .with_cache(Arc::clone(&cache))
+        };
+        let (small, large) = (provider(4), provider(8));
+
+        let text = || vec!["hello".to_string()];
+        assert_eq!(small.embed(text()).await.unwrap(), vec![vec![0.5; 4]]);
+        assert_eq!(large.embed(text()).await.unwrap(), vec![vec![0.5; 8]]);
+        assert_eq!(small.embed(text()).await.unwrap(), vec![vec![0.5; 4]]);
+        assert_eq!(peers.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_key_detected_at_warmup() {
+        let (base_url, _) = mock_embeddings_api().await;
+
+        let rejected =
+            OpenAIEmbeddingProvider::new(&base_url, "bad-key", "model", Some(4)).unwrap();
+        let err = format!("{:#}", rejected.initialize().await.unwrap_err());
+        assert!(err.contains("Embedding warmup failed"), "{err}");
+        assert!(err.contains("rejected the credentials"), "{err}");
+
+        let accepted =
+            OpenAIEmbeddingProvider::new(&base_url, "good-key", "model", Some(4)).unwrap();
+        assert!(accepted.initialize().await.is_ok());
+    }
+}
@@ -0,0 +1,474 @@
+//! LLM Entity Extraction
+//!
+//! Sends chunks to the LLM, a batch at a time, with a prompt asking for their
+//! entities and relationships as JSON. Extraction runs in the background
+//! during ingestion, so it draws on its own hourly token budget instead of
+//! the quota chat needs, waiting for it to refill rather than failing, and
+//! its token use and cost are counted apart from runs' (see
+//! [`LlmExtractor::usage`]). An answer that isn't the JSON asked for
+//! extracts nothing.
+
+use super::RelationshipExtractor;
+use crate::config::LlmExtractionConfig;
+use crate::llm::{Message, MessageContent, MessageRole, Orchestrator};
+use crate::uar::domain::{
+    graph::{Entity, EntityType, ExtractionResult, Relationship},
+    knowledge::KnowledgeChunk,
+    runs::TokenUsage,
+};
+use crate::uar::runtime::{context::token_service::TokenService, pricing::PricingTable};
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use governor::{
+    Quota, RateLimiter,
+    clock::DefaultClock,
+    state::{InMemoryState, NotKeyed},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+
+/// Prometheus counter of tokens extraction used, by `kind` (prompt or
+/// completion).
+const EXTRACTION_TOKENS_METRIC: &str = "rag_extraction_tokens_total";
+
+const EXTRACTION_PROMPT: &str = "Extract the named entities and the relationships \
+    between them from the numbered passages you are sent. Answer with JSON only, \
+    in this shape:\n\
+    {\"entities\": [{\"name\": \"Marie Curie\", \"type\": \"person\", \
+    \"description\": \"Physicist and chemist\"}], \
+    \"relationships\": [{\"source\": \"Marie Curie\", \"target\": \"University of Paris\", \
+    \"type\": \"worked_at\"}]}\n\
+    Entity types are person, organization, location, event, concept, product, \
+    temporal and quantity. Name each entity once, in its fullest form, and refer \
+    to entities by those names in relationships. Relationship types are short \
+    snake_case verbs.";
+
+type DirectLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
+
+/// LLM calls extraction made since startup and what they cost.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ExtractionUsage {
+    pub calls: u64,
+    /// Estimated with the default tokenizer; non-streaming answers carry no
+    /// provider usage
+    pub tokens: TokenUsage,
+    /// Estimated cost in USD, `None` for unpriced models
+    pub cost_usd: Option<f64>,
+}
+
+/// Extractor asking the LLM for the entities and relationships of chunks.
+pub struct LlmExtractor {
+    orchestrator: Arc<Orchestrator>,
+    batch_size: usize,
+    /// Prompt tokens left for extraction this hour, `None` for no limit
+    quota: Option<DirectLimiter>,
+    pricing: PricingTable,
+    usage: Mutex<ExtractionUsage>,
+}
+
+impl std::fmt::Debug for LlmExtractor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LlmExtractor")
+            .field("model", &self.orchestrator.settings().model)
+            .field("batch_size", &self.batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LlmExtractor {
+    pub fn new(orchestrator: Arc<Orchestrator>, config: &LlmExtractionConfig) -> Self {
+        let quota = NonZeroU32::new(config.tokens_per_hour)
+            .map(|tokens| RateLimiter::direct(Quota::per_hour(tokens)));
+        Self {
+            orchestrator,
+            batch_size: config.batch_size.max(1),
+            quota,
+            pricing: PricingTable::default(),
+            usage: Mutex::new(ExtractionUsage::default()),
+        }
+    }
+
+    /// Price extraction calls with `pricing`.
+    pub fn with_pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Calls and tokens extraction used so far, with their cost.
+    pub fn usage(&self) -> ExtractionUsage {
+        let mut usage = *self.usage.lock().unwrap();
+        usage.cost_usd = self
+            .pricing
+            .estimate_cost(&self.orchestrator.settings().model, &usage.tokens);
+        usage
+    }
+
+    /// Entities and relationships of `passages`, in one LLM call.
+    async fn extract_passages(&self, passages: &[&str]) -> Result<ExtractionResult> {
+        let mut numbered = String::new();
+        for (i, passage) in passages.iter().enumerate() {
+            let _ = writeln!(numbered, "[{}]\n{}\n", i + 1, passage.trim());
+        }
+        let request = vec![
+            text_message(MessageRole::System, EXTRACTION_PROMPT.to_string()),
+            text_message(MessageRole::User, numbered),
+        ];
+        let prompt_tokens = TokenService::estimate_messages(&request);
+        self.take_budget(prompt_tokens).await?;
+
+        let answer = self.orchestrator.chat_non_streaming(request, None).await?;
+        self.record(prompt_tokens, TokenService::estimate_string(&answer));
+        Ok(parse_extraction(&answer).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "LLM extraction answer is not the JSON asked for");
+            ExtractionResult::default()
+        }))
+    }
+
+    /// Take `tokens` from the hourly budget, waiting until it has them.
+    /// Fails only for more tokens than a whole hour's budget.
+    async fn take_budget(&self, tokens: usize) -> Result<()> {
+        let Some(quota) = &self.quota else {
+            return Ok(());
+        };
+        let Some(n) = NonZeroU32::new(u32::try_from(tokens).unwrap_or(u32::MAX)) else {
+            return Ok(());
+        };
+        quota
+            .until_n_ready(n)
+            .await
+            .map_err(|_| anyhow!("Extraction needs {tokens} tokens, more than its hourly budget"))
+    }
+
+    fn record(&self, prompt_tokens: usize, completion_tokens: usize) {
+        let prompt_tokens = u32::try_from(prompt_tokens).unwrap_or(u32::MAX);
+        let completion_tokens = u32::try_from(completion_tokens).unwrap_or(u32::MAX);
+        metrics::counter!(EXTRACTION_TOKENS_METRIC, "kind" => "prompt")
+            .increment(u64::from(prompt_tokens));
+        metrics::counter!(EXTRACTION_TOKENS_METRIC, "kind" => "completion")
+            .increment(u64::from(completion_tokens));
+
+        let mut usage = self.usage.lock().unwrap();
+        usage.calls += 1;
+        usage.tokens.add(TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens.saturating_add(completion_tokens),
+        });
+    }
+}
+
+#[async_trait]
+impl RelationshipExtractor for LlmExtractor {
+    async fn extract(&self, chunk: &KnowledgeChunk) -> Result<ExtractionResult> {
+        self.extract_passages(&[chunk.content.as_str()]).await
+    }
+
+    async fn extract_from_text(&self, text: &str) -> Result<ExtractionResult> {
+        self.extract_passages(&[text]).await
+    }
+
+    async fn extract_batch(&self, chunks: &[KnowledgeChunk]) -> Result<ExtractionResult> {
+        let passages: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        self.extract_passages(&passages).await
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    fn name(&self) -> &'static str {
+        "llm"
+    }
+}
+
+// =============================================================================
+// Answer Parsing
+// =============================================================================
+
+/// The JSON the prompt asks for.
+#[derive(Debug, Deserialize)]
+struct ExtractedGraph {
+    #[serde(default)]
+    entities: Vec<ExtractedEntity>,
+    #[serde(default)]
+    relationships: Vec<ExtractedRelationship>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtractedEntity {
+    name: String,
+    #[serde(rename = "type", default)]
+    entity_type: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtractedRelationship {
+    source: String,
+    target: String,
+    #[serde(rename = "type")]
+    relation_type: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// Entities and relationships of an answer to [`EXTRACTION_PROMPT`].
+///
+/// Entities named twice are kept once, and relationships between entities
+/// the answer doesn't list are dropped.
+fn parse_extraction(answer: &str) -> Result<ExtractionResult> {
+    // Models like to fence JSON in markdown
+    let json = match (answer.find('{'), answer.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &answer[start..=end],
+        _ => answer,
+    };
+    let extracted: ExtractedGraph = serde_json::from_str(json)?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    let mut ids = HashMap::new();
+    let mut entities = Vec::new();
+    for entity in extracted.entities {
+        let name = entity.name.trim();
+        if name.is_empty() || ids.contains_key(&name.to_lowercase()) {
+            continue;
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        ids.insert(name.to_lowercase(), id.clone());
+        entities.push(Entity {
+            id,
+            canonical_name: name.to_string(),
+            entity_type: parse_entity_type(&entity.entity_type),
+            description: entity.description.filter(|d| !d.trim().is_empty()),
+            embedding: Vec::new(),
+            source_chunk_ids: Vec::new(),
+            created_at: now.clone(),
+        });
+    }
+
+    let relationships = extracted
+        .relationships
+        .into_iter()
+        .filter_map(|r| {
+            let source_id = ids.get(&r.source.trim().to_lowercase())?;
+            let target_id = ids.get(&r.target.trim().to_lowercase())?;
+            Some(Relationship {
+                id: uuid::Uuid::new_v4().to_string(),
+                source_id: source_id.clone(),
+                target_id: target_id.clone(),
+                relation_type: r.relation_type.trim().to_lowercase().replace(' ', "_"),
+                weight: 1.0,
+                description: r.description,
+                source_chunk_id: String::new(),
+                created_at: now.clone(),
+            })
+        })
+        .collect();
+
+    Ok(ExtractionResult {
+        entities,
+        relationships,
+    })
+}
+
+/// Entity type named in an answer; unknown names become custom types.
+fn parse_entity_type(name: &str) -> EntityType {
+    match name.trim().to_lowercase().as_str() {
+        "person" => EntityType::Person,
+        "organization" | "organisation" | "org" | "company" => EntityType::Organization,
+        "location" | "place" => EntityType::Location,
+        "event" => EntityType::Event,
+        "concept" | "" => EntityType::Concept,
+        "product" => EntityType::Product,
+        "temporal" | "date" | "time" => EntityType::Temporal,
+        "quantity" => EntityType::Quantity,
+        other => EntityType::Custom(other.to_string()),
+    }
+}
+
+fn text_message(role: MessageRole, text: String) -> Message {
+    Message {
+        role,
+        content: MessageContent::text(text),
+        tool_call_id: None,
+        tool_calls: None,
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{LlmDriver, LlmRequest};
+    use crate::mcp::registry::McpRegistry;
+    use crate::normalized::NormalizedEvent;
+    use crate::uar::testing::llm_settings;
+    use futures::Stream;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    const BIOGRAPHY: [&str; 2] = [
+        "Marie Curie was a physicist who studied radioactivity.",
+        "She taught at the University of Paris and won the Nobel Prize in 1903.",
+    ];
+
+    /// Answers every request with `answer`, recording the passages it was
+    /// sent.
+    struct StubLlm {
+        answer: &'static str,
+        calls: AtomicUsize,
+        prompts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl LlmDriver for StubLlm {
+        async fn stream(
+            &self,
+            req: LlmRequest,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<NormalizedEvent>> + Send>>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let prompt = req.messages.last().unwrap()["content"].to_string();
+            self.prompts.lock().unwrap().push(prompt);
+            let events = vec![
+                NormalizedEvent::MessageDelta {
+                    text: self.answer.to_string(),
+                },
+                NormalizedEvent::Done,
+            ];
+            Ok(Box::pin(futures::stream::iter(events.into_iter().map(Ok))))
+        }
+    }
+
+    fn extractor(answer: &'static str, tokens_per_hour: u32) -> (Arc<StubLlm>, LlmExtractor) {
+        let llm = Arc::new(StubLlm {
+            answer,
+            calls: AtomicUsize::new(0),
+            prompts: Mutex::new(Vec::new()),
+        });
+        let settings = llm_settings("http://localhost", "test-model");
+        let orchestrator = Orchestrator::with_driver(
+            settings,
+            Arc::new(McpRegistry::new_empty()),
+            Arc::clone(&llm) as Arc<dyn LlmDriver>,
+        );
+        let config = LlmExtractionConfig {
+            tokens_per_hour,
+            ..LlmExtractionConfig::default()
+        };
+        (llm, LlmExtractor::new(Arc::new(orchestrator), &config))
+    }
+
+    fn chunk(content: &str) -> KnowledgeChunk {
+        KnowledgeChunk {
+            id: uuid::Uuid::new_v4(),
+            kb_id: "kb-1".to_string(),
+            document_id: None,
+            content: content.to_string(),
+            metadata: None,
+            embedding: Vec::new(),
+            tenant_id: None,
+            created_at: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_biography_yields_people_and_organizations() {
+        let answer = r#"```json
+{
+  "entities": [
+    {"name": "Marie Curie", "type": "person", "description": "Physicist"},
+    {"name": "University of Paris", "type": "organization"},
+    {"name": "Nobel Prize", "type": "award"},
+    {"name": "marie curie", "type": "person"}
+  ],
+  "relationships": [
+    {"source": "Marie Curie", "target": "University of Paris", "type": "taught at"},
+    {"source": "Marie Curie", "target": "Sorbonne", "type": "studied_at"}
+  ]
+}
+```"#;
+        let (llm, extractor) = extractor(answer, 10_000);
+        let chunks: Vec<_> = BIOGRAPHY.into_iter().map(chunk).collect();
+
+        let result = extractor.extract_batch(&chunks).await.unwrap();
+        // Both chunks went in one call
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 1);
+        let prompt = &llm.prompts.lock().unwrap()[0];
+        assert!(prompt.contains("[1]") && prompt.contains("[2]"));
+        assert!(prompt.contains("University of Paris"));
+
+        let types: Vec<_> = result
+            .entities
+            .iter()
+            .map(|e| (e.canonical_name.as_str(), e.entity_type.clone()))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("Marie Curie", EntityType::Person),
+                ("University of Paris", EntityType::Organization),
+                ("Nobel Prize", EntityType::Custom("award".to_string())),
+            ]
+        );
+        assert_eq!(result.entities[0].description.as_deref(), Some("Physicist"));
+
+        // The Sorbonne isn't among the entities
+        assert_eq!(result.relationships.len(), 1);
+        let taught = &result.relationships[0];
+        assert_eq!(taught.relation_type, "taught_at");
+        assert_eq!(taught.source_id, result.entities[0].id);
+        assert_eq!(taught.target_id, result.entities[1].id);
+
+        let usage = extractor.usage();
+        assert_eq!(usage.calls, 1);
+        assert!(usage.tokens.prompt_tokens > 0 && usage.tokens.completion_tokens > 0);
+        assert_eq!(usage.cost_usd, None);
+        assert_eq!(extractor.batch_size(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_unparseable_answers_extract_nothing() {
+        let (_, extractor) = extractor("Marie Curie is a person.", 10_000);
+        let result = extractor.extract(&chunk(BIOGRAPHY[0])).await.unwrap();
+        assert!(result.entities.is_empty());
+        assert!(result.relationships.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_budget_skips_the_llm() {
+        // The prompt alone is more than the hourly budget
+        let (llm, extractor) = extractor("{}", 10);
+        assert!(extractor.extract(&chunk(BIOGRAPHY[0])).await.is_err());
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 0);
+        assert_eq!(extractor.usage().calls, 0);
+    }
+
+    #[tokio::test]
+    async fn test_spent_budget_waits_for_refill() {
+        let (llm, extractor) = extractor("{}", 1_000);
+        // Over half the budget per call
+        let passage = chunk(&BIOGRAPHY[0].repeat(40));
+        extractor.extract(&passage).await.unwrap();
+
+        // What's left of the hour's budget can't pay for a second call yet
+        let second = tokio::time::timeout(Duration::from_millis(100), extractor.extract(&passage));
+        assert!(second.await.is_err());
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_zero_budget_is_unlimited() {
+        let (llm, extractor) = extractor("{}", 0);
+        for _ in 0..3 {
+            extractor.extract(&chunk(BIOGRAPHY[0])).await.unwrap();
+        }
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -5,10 +5,12 @@
 
 pub mod external_nlp;
 pub mod leiden;
+pub mod llm;
 
 use crate::uar::domain::{graph::ExtractionResult, knowledge::KnowledgeChunk};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use async_trait::async_trait;
+use std::str::FromStr;
 
 // =============================================================================
 // Extraction Strategy Trait
@@ -23,10 +25,76 @@ pub trait RelationshipExtractor: Send + Sync {
     /// Extract from raw text (convenience method).
     async fn extract_from_text(&self, text: &str) -> Result<ExtractionResult>;
 
+    /// Extract from up to [`Self::batch_size`] chunks at once, merging what
+    /// is found in each.
+    async fn extract_batch(&self, chunks: &[KnowledgeChunk]) -> Result<ExtractionResult> {
+        let mut merged = ExtractionResult::default();
+        for chunk in chunks {
+            let result = self.extract(chunk).await?;
+            merged.entities.extend(result.entities);
+            merged.relationships.extend(result.relationships);
+        }
+        Ok(merged)
+    }
+
+    /// Most chunks worth passing to [`Self::extract_batch`] at once.
+    fn batch_size(&self) -> usize {
+        1
+    }
+
     /// Get the name of this extraction strategy.
     fn name(&self) -> &'static str;
 }
 
+// =============================================================================
+// Extraction Strategies
+// =============================================================================
+
+/// How a knowledge base builds its graph, as named by
+/// `KbConfig::extraction_strategy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtractionStrategy {
+    /// Ask the LLM ([`llm::LlmExtractor`])
+    Llm,
+    /// Call the spaCy-compatible NLP service
+    /// ([`external_nlp::ExternalNlpExtractor`])
+    Spacy,
+    /// As `Spacy`, for graphs meant for Leiden community detection
+    Leiden,
+    /// Build no graph
+    None,
+}
+
+impl ExtractionStrategy {
+    /// Strategy of knowledge bases that don't name one.
+    pub const DEFAULT: Self = Self::Spacy;
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Llm => "llm",
+            Self::Spacy => "spacy",
+            Self::Leiden => "leiden",
+            Self::None => "none",
+        }
+    }
+}
+
+impl FromStr for ExtractionStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "llm" => Ok(Self::Llm),
+            "spacy" => Ok(Self::Spacy),
+            "leiden" => Ok(Self::Leiden),
+            "none" => Ok(Self::None),
+            other => Err(anyhow!(
+                "Unknown extraction strategy '{other}' (expected llm, spacy, leiden or none)"
+            )),
+        }
+    }
+}
+
 // =============================================================================
 // Extraction Configuration
 // =============================================================================
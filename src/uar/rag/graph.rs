@@ -0,0 +1,197 @@
+//! Knowledge graphs built from the entities and relationships extracted
+//! from a knowledge base's chunks.
+//!
+//! Chunks are run through the extractor of their knowledge base's
+//! `extraction_strategy` (see [`IngestService::with_extractor`]) in the
+//! background once they are saved and indexed, in batches of the
+//! extractor's size, so a slow extractor or an exhausted token budget never
+//! holds up ingestion. Entities become
+//! [`GraphNode`]s, one per name in the knowledge base however many chunks
+//! mention it, and relationships between them [`GraphEdge`]s. The graph is
+//! an extra: chunks the extractor fails on are logged, and chunks ingested
+//! while [`MAX_PENDING_EXTRACTIONS`] extractions are waiting get no graph.
+
+use crate::uar::domain::graph::{ExtractionResult, GraphEdge, GraphNode, Subgraph};
+use crate::uar::domain::knowledge::KnowledgeChunk;
+use crate::uar::persistence::PersistenceLayer;
+use crate::uar::rag::extraction::{ExtractionStrategy, RelationshipExtractor};
+use crate::uar::rag::ingest::IngestService;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+/// Deepest neighbourhood `GET /knowledge-bases/{id}/graph` returns.
+pub const MAX_GRAPH_DEPTH: usize = 3;
+
+/// Most graph extractions waiting or running at once; the chunks of
+/// ingestions beyond it are indexed without a graph.
+pub const MAX_PENDING_EXTRACTIONS: usize = 64;
+
+/// Chunks whose graph extraction was skipped because too many were pending.
+const EXTRACTION_SKIPPED_METRIC: &str = "rag_extraction_skipped_chunks_total";
+
+impl IngestService {
+    /// Extract the entities and relationships of saved `chunks` into the
+    /// graph of `kb_id` in the background; does nothing without an extractor
+    /// for its strategy.
+    pub(super) async fn start_graph_extraction(&self, kb_id: &str, chunks: Vec<KnowledgeChunk>) {
+        let Some(first) = chunks.first() else {
+            return;
+        };
+        let Some(extractor) = self.extractor_for(kb_id, first.tenant_id.as_deref()).await else {
+            return;
+        };
+        let reserved = self
+            .pending_extractions
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pending| {
+                (pending < MAX_PENDING_EXTRACTIONS).then_some(pending + 1)
+            });
+        if reserved.is_err() {
+            tracing::warn!(
+                kb_id,
+                chunks = chunks.len(),
+                "Too many graph extractions pending; chunks are indexed without a graph"
+            );
+            metrics::counter!(EXTRACTION_SKIPPED_METRIC).increment(chunks.len() as u64);
+            return;
+        }
+
+        let persistence = Arc::clone(&self.persistence);
+        let pending = Arc::clone(&self.pending_extractions);
+        let kb_id = kb_id.to_string();
+        tokio::spawn(async move {
+            extract_graph(persistence.as_ref(), extractor.as_ref(), &kb_id, &chunks).await;
+            pending.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+
+    /// Extractor of the strategy `kb_id` is configured with, or of
+    /// [`ExtractionStrategy::DEFAULT`] when it names none.
+    async fn extractor_for(
+        &self,
+        kb_id: &str,
+        tenant_id: Option<&str>,
+    ) -> Option<Arc<dyn RelationshipExtractor>> {
+        if self.extractors.is_empty() {
+            return None;
+        }
+        let kb = match self.persistence.get_knowledge_base(kb_id, tenant_id).await {
+            Ok(kb) => kb,
+            Err(e) => {
+                tracing::warn!(kb_id, error = %e, "Failed to load knowledge base for extraction");
+                return None;
+            }
+        };
+        let strategy = match kb.and_then(|kb| kb.config.extraction_strategy) {
+            Some(name) => match name.parse() {
+                Ok(strategy) => strategy,
+                Err(e) => {
+                    tracing::warn!(kb_id, error = %e, "Not extracting entities");
+                    return None;
+                }
+            },
+            None => ExtractionStrategy::DEFAULT,
+        };
+        self.extractors.get(&strategy).cloned()
+    }
+}
+
+/// Extract the entities and relationships of `chunks` into the graph of
+/// `kb_id` with `extractor`.
+async fn extract_graph(
+    persistence: &dyn PersistenceLayer,
+    extractor: &dyn RelationshipExtractor,
+    kb_id: &str,
+    chunks: &[KnowledgeChunk],
+) {
+    // Chunks of a document mention the same entities; save each once
+    let mut nodes = BTreeMap::new();
+    let mut edges: BTreeMap<String, GraphEdge> = BTreeMap::new();
+    for batch in chunks.chunks(extractor.batch_size().max(1)) {
+        let extracted = match extractor.extract_batch(batch).await {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                tracing::warn!(
+                    chunk_id = %batch[0].id,
+                    chunks = batch.len(),
+                    extractor = extractor.name(),
+                    error = %e,
+                    "Entity extraction failed"
+                );
+                continue;
+            }
+        };
+        let graph = graph_from_extraction(kb_id, &extracted);
+        for node in graph.nodes {
+            nodes.entry(node.id.clone()).or_insert(node);
+        }
+        for edge in graph.edges {
+            edges
+                .entry(edge.id.clone())
+                .and_modify(|seen| seen.weight = seen.weight.max(edge.weight))
+                .or_insert(edge);
+        }
+    }
+
+    // Edges after the nodes they join
+    for node in nodes.values() {
+        if let Err(e) = persistence.save_graph_node(node).await {
+            tracing::warn!(kb_id, node_id = %node.id, error = %e, "Failed to save graph node");
+        }
+    }
+    for edge in edges.values() {
+        if let Err(e) = persistence.save_graph_edge(edge).await {
+            tracing::warn!(kb_id, edge_id = %edge.id, error = %e, "Failed to save graph edge");
+        }
+    }
+    tracing::debug!(
+        kb_id,
+        nodes = nodes.len(),
+        edges = edges.len(),
+        "Knowledge graph updated"
+    );
+}
+
+/// The nodes and edges of `kb_id`'s graph that `extracted` describes.
+///
+/// Relationships to entities `extracted` doesn't have are dropped.
+pub fn graph_from_extraction(kb_id: &str, extracted: &ExtractionResult) -> Subgraph {
+    // Relationships refer to entities by the extractor's IDs
+    let mut node_ids = HashMap::new();
+    let nodes = extracted
+        .entities
+        .iter()
+        .map(|entity| {
+            let id = GraphNode::id_for(kb_id, &entity.canonical_name);
+            node_ids.insert(entity.id.as_str(), id.clone());
+            let mut properties = serde_json::Map::new();
+            if let Some(description) = &entity.description {
+                properties.insert("description".to_string(), description.clone().into());
+            }
+            GraphNode {
+                id,
+                label: entity.canonical_name.clone(),
+                entity_type: entity.entity_type.clone(),
+                properties: properties.into(),
+                kb_id: kb_id.to_string(),
+            }
+        })
+        .collect();
+    let edges = extracted
+        .relationships
+        .iter()
+        .filter_map(|relationship| {
+            let source_id = node_ids.get(relationship.source_id.as_str())?;
+            let target_id = node_ids.get(relationship.target_id.as_str())?;
+            Some(GraphEdge {
+                id: GraphEdge::id_for(source_id, target_id, &relationship.relation_type),
+                source_id: source_id.clone(),
+                target_id: target_id.clone(),
+                relationship_type: relationship.relation_type.clone(),
+                weight: relationship.weight,
+                kb_id: kb_id.to_string(),
+            })
+        })
+        .collect();
+    Subgraph { nodes, edges }
+}
@@ -1,18 +1,92 @@
-use crate::uar::domain::knowledge::KnowledgeChunk;
+use crate::config::ArchiveImportConfig;
+use crate::uar::domain::knowledge::{
+    DocumentStatus, IngestionProgress, KnowledgeChunk, KnowledgeDocument, content_hash, file_hash,
+};
+use crate::uar::file_processing::{DocumentMetadata, PDF_MIME_TYPE, PdfProcessor, extract_pdf};
 use crate::uar::persistence::PersistenceLayer;
 use crate::uar::rag::chunking::{Chunker, ChunkingStrategy};
+use crate::uar::rag::extraction::{ExtractionStrategy, RelationshipExtractor};
+use crate::uar::rag::reindex::ReindexJob;
 use crate::uar::runtime::matching::VectorMatcher;
 use anyhow::{Result, anyhow};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
 use uuid::Uuid;
 use walkdir::WalkDir;
 
+/// Text of an uploaded file read as UTF-8; invalid sequences are replaced.
+pub fn extract_text(content: &[u8]) -> Cow<'_, str> {
+    String::from_utf8_lossy(content)
+}
+
+/// Text and metadata of an uploaded file, as the ingestion pipeline sees it.
+///
+/// PDFs (by MIME type or signature) are parsed for their text layer and
+/// information dictionary; anything else goes through [`extract_text`].
+/// Parsing PDFs blocks, so call this from a blocking task.
+pub fn extract_document(
+    content: &[u8],
+    mime_type: Option<&str>,
+) -> Result<(String, DocumentMetadata)> {
+    if mime_type == Some(PDF_MIME_TYPE) || PdfProcessor::is_pdf(content) {
+        return Ok(extract_pdf(content)?);
+    }
+    let text = extract_text(content).into_owned();
+    let metadata = DocumentMetadata::from_content(&text);
+    Ok((text, metadata))
+}
+
+/// Chunks embedded per provider call when progress is reported.
+pub(super) const EMBED_BATCH_SIZE: usize = 32;
+
+/// Knowledge base IDs [`IngestService::chunks_saved`] receivers may fall
+/// behind by before missing some.
+const CHUNKS_SAVED_CAPACITY: usize = 256;
+
+/// Time spent in each step of [`IngestService::ingest_text_timed`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngestTimings {
+    /// Reading the text out of a file; zero when given text
+    pub extract: Duration,
+    pub chunk: Duration,
+    pub embed: Duration,
+    pub store: Duration,
+}
+
+/// A document after [`IngestService::ingest_document`].
+#[derive(Debug, Clone)]
+pub struct IngestedDocument {
+    /// The document as stored
+    pub document: KnowledgeDocument,
+    /// Chunks added to the knowledge base
+    pub chunks_created: usize,
+    /// `false` when the content was unchanged and nothing was done
+    pub processed: bool,
+    pub timings: IngestTimings,
+}
+
 pub struct IngestService {
-    persistence: Arc<dyn PersistenceLayer>,
-    vector_matcher: Arc<VectorMatcher>,
+    pub(super) persistence: Arc<dyn PersistenceLayer>,
+    pub(super) vector_matcher: Arc<VectorMatcher>,
     chunker: Chunker,
+    /// Where imported documents' original files are restored to
+    pub(super) upload_dir: Option<PathBuf>,
+    /// What imported archives may unpack to
+    pub(super) archive_limits: ArchiveImportConfig,
+    /// Sent a knowledge base's ID after its chunks are saved, so its
+    /// in-memory vector index rebuilds
+    chunks_saved: broadcast::Sender<String>,
+    /// Reindexes started by [`Self::start_reindex`], by job ID
+    pub(super) reindex_jobs: Mutex<HashMap<String, ReindexJob>>,
+    /// Build knowledge graphs from ingested chunks, by strategy
+    pub(super) extractors: HashMap<ExtractionStrategy, Arc<dyn RelationshipExtractor>>,
+    /// Graph extractions started and not yet finished
+    pub(super) pending_extractions: Arc<AtomicUsize>,
     // Track processed files to avoid re-ingesting identical content (naive check by path/mtime)
     // For MVP, we just ingest everything on startup or change.
     // Ideally store tracking info in DB.
@@ -24,6 +98,8 @@ impl std::fmt::Debug for IngestService {
             .field("persistence", &"<dyn PersistenceLayer>")
             .field("vector_matcher", &self.vector_matcher)
             .field("chunker", &self.chunker)
+            .field("upload_dir", &self.upload_dir)
+            .field("extractors", &self.extractors.keys().collect::<Vec<_>>())
             .finish()
     }
 }
@@ -39,7 +115,64 @@ impl IngestService {
             persistence,
             vector_matcher,
             chunker,
+            upload_dir: None,
+            archive_limits: ArchiveImportConfig::default(),
+            chunks_saved: broadcast::channel(CHUNKS_SAVED_CAPACITY).0,
+            reindex_jobs: Mutex::new(HashMap::new()),
+            extractors: HashMap::new(),
+            pending_extractions: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Restore the original files of imported knowledge bases to `dir`;
+    /// without it only their chunks are imported.
+    pub fn with_upload_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.upload_dir = Some(dir.into());
+        self
+    }
+
+    /// Refuse archives whose contents unpack beyond `limits`.
+    pub fn with_archive_limits(mut self, limits: ArchiveImportConfig) -> Self {
+        self.archive_limits = limits;
+        self
+    }
+
+    /// Build the knowledge graphs of knowledge bases using `strategy` from
+    /// the entities and relationships `extractor` finds in their chunks.
+    pub fn with_extractor(
+        mut self,
+        strategy: ExtractionStrategy,
+        extractor: Arc<dyn RelationshipExtractor>,
+    ) -> Self {
+        self.extractors.insert(strategy, extractor);
+        self
+    }
+
+    /// Receives the ID of every knowledge base this service (or ingestion
+    /// through it) saves chunks to from now on.
+    pub fn chunks_saved(&self) -> broadcast::Receiver<String> {
+        self.chunks_saved.subscribe()
+    }
+
+    /// Tell [`Self::chunks_saved`] receivers that chunks of `kb_id` were
+    /// saved.
+    pub(crate) fn notify_chunks_saved(&self, kb_id: &str) {
+        // Without receivers there is no index to invalidate
+        let _ = self.chunks_saved.send(kb_id.to_string());
+    }
+
+    /// `chunks` the knowledge base doesn't have yet, with their index in
+    /// `chunks`; repeats within `chunks` are kept once.
+    async fn new_chunks(&self, kb_id: &str, chunks: Vec<String>) -> Result<Vec<(usize, String)>> {
+        let mut seen = HashSet::new();
+        let mut fresh = Vec::new();
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let hash = content_hash(&chunk);
+            if seen.insert(hash.clone()) && !self.persistence.chunk_exists(kb_id, &hash).await? {
+                fresh.push((i, chunk));
+            }
         }
+        Ok(fresh)
     }
 
     /// Process a single file
@@ -59,20 +192,22 @@ impl IngestService {
 
         tracing::info!("Ingesting processed file: {}", filename);
 
-        // 1. Chunking
+        // 1. Chunking, skipping chunks already ingested
         let chunks = self.chunker.chunk(&content).await?;
+        let chunks = self.new_chunks(kb_id, chunks).await?;
 
         if chunks.is_empty() {
             return Ok(());
         }
 
         // 2. Embedding
-        let embeddings = self.vector_matcher.embed_batch(chunks.clone()).await?;
+        let texts = chunks.iter().map(|(_, chunk)| chunk.clone()).collect();
+        let embeddings = self.vector_matcher.embed_batch(texts).await?;
 
         // 3. Storage
-        for (i, segment) in chunks.into_iter().enumerate() {
+        for (n, (i, segment)) in chunks.into_iter().enumerate() {
             let embedding = embeddings
-                .get(i)
+                .get(n)
                 .ok_or_else(|| anyhow!("Missing embedding for chunk {}", i))?;
 
             let mut metadata = HashMap::new();
@@ -95,37 +230,131 @@ impl IngestService {
                 content: segment,
                 metadata: Some(serde_json::to_value(metadata)?),
                 embedding: embedding.clone(),
+                tenant_id: None,
                 created_at: chrono::Utc::now().to_rfc3339(),
             };
 
             self.persistence.save_chunk(&k_chunk).await?;
         }
+        self.notify_chunks_saved(kb_id);
 
         Ok(())
     }
 
     /// Ingest text content directly (for worker pool use).
+    /// Chunks are owned by `tenant_id`, the document's tenant.
+    /// Chunks the KB already has are skipped.
     /// Returns the number of chunks created.
     pub async fn ingest_text(
         &self,
         content: &str,
         kb_id: &str,
         document_id: String,
+        tenant_id: Option<&str>,
     ) -> Result<usize> {
-        // 1. Chunking
-        let chunks = self.chunker.chunk(content).await?;
+        self.ingest_text_timed(content, kb_id, document_id, tenant_id)
+            .await
+            .map(|(chunks, _)| chunks)
+    }
+
+    /// [`Self::ingest_text`], also returning the time each step took.
+    pub async fn ingest_text_timed(
+        &self,
+        content: &str,
+        kb_id: &str,
+        document_id: String,
+        tenant_id: Option<&str>,
+    ) -> Result<(usize, IngestTimings)> {
+        let (chunks, mut timings) = self
+            .prepare_chunks(content, kb_id, document_id, tenant_id)
+            .await?;
+
+        // 3. Storage
+        let started = Instant::now();
+        for chunk in &chunks {
+            self.persistence.save_chunk(chunk).await?;
+        }
+        timings.store = started.elapsed();
+        self.notify_chunks_saved(kb_id);
+
+        let created = chunks.len();
+        self.start_graph_extraction(kb_id, chunks).await;
+        Ok((created, timings))
+    }
+
+    /// Chunk and embed `content` without storing anything, returning the
+    /// chunks [`Self::ingest_text`] would save and the time each step took
+    /// (`store` is left zero).
+    pub async fn prepare_chunks(
+        &self,
+        content: &str,
+        kb_id: &str,
+        document_id: String,
+        tenant_id: Option<&str>,
+    ) -> Result<(Vec<KnowledgeChunk>, IngestTimings)> {
+        self.prepare_chunks_with_progress(content, kb_id, document_id, tenant_id, |_| {})
+            .await
+    }
+
+    /// [`Self::prepare_chunks`], reporting `chunked` once the text is split
+    /// and `embedded` after each batch of [`EMBED_BATCH_SIZE`] embeddings.
+    pub async fn prepare_chunks_with_progress(
+        &self,
+        content: &str,
+        kb_id: &str,
+        document_id: String,
+        tenant_id: Option<&str>,
+        progress: impl Fn(IngestionProgress) + Send + Sync,
+    ) -> Result<(Vec<KnowledgeChunk>, IngestTimings)> {
+        let mut timings = IngestTimings::default();
+        let kb = self.persistence.get_knowledge_base(kb_id, tenant_id).await?;
+
+        let started = Instant::now();
+
+        // 1. Chunking, with the KB's strategy when it has one
+        let chunks = match &kb {
+            Some(kb) => {
+                Chunker::new(
+                    kb.config.chunk_strategy.clone(),
+                    Some(Arc::clone(&self.vector_matcher)),
+                )
+                .chunk(content)
+                .await?
+            }
+            None => self.chunker.chunk(content).await?,
+        };
+        // Re-uploads and overlapping documents repeat chunks the KB has
+        let chunks = self.new_chunks(kb_id, chunks).await?;
+
+        timings.chunk = started.elapsed();
+        progress(IngestionProgress::Chunked {
+            count: chunks.len(),
+        });
 
         if chunks.is_empty() {
-            return Ok(0);
+            return Ok((Vec::new(), timings));
         }
 
-        // 2. Embedding
-        let embeddings = self.vector_matcher.embed_batch(chunks.clone()).await?;
+        // 2. Embedding, with the provider the KB is configured for
+        let started = Instant::now();
+        let mut embeddings = Vec::with_capacity(chunks.len());
+        for batch in chunks.chunks(EMBED_BATCH_SIZE) {
+            let texts: Vec<String> = batch.iter().map(|(_, chunk)| chunk.clone()).collect();
+            embeddings.extend(match &kb {
+                Some(kb) => self.vector_matcher.embed_for_kb(&kb.config, texts).await?,
+                None => self.vector_matcher.embed_batch(texts).await?,
+            });
+            progress(IngestionProgress::Embedded {
+                done: embeddings.len(),
+                total: chunks.len(),
+            });
+        }
+        timings.embed = started.elapsed();
 
-        // 3. Storage
-        for (i, segment) in chunks.iter().enumerate() {
+        let mut prepared = Vec::with_capacity(chunks.len());
+        for (n, (i, segment)) in chunks.into_iter().enumerate() {
             let embedding = embeddings
-                .get(i)
+                .get(n)
                 .ok_or_else(|| anyhow!("Missing embedding for chunk {}", i))?;
 
             let mut metadata = HashMap::new();
@@ -137,20 +366,100 @@ impl IngestService {
 
             let chunk_id = Uuid::new_v4();
 
-            let k_chunk = KnowledgeChunk {
+            prepared.push(KnowledgeChunk {
                 id: chunk_id,
                 kb_id: kb_id.to_string(),
                 document_id: Some(document_id.clone()),
-                content: segment.clone(),
+                content: segment,
                 metadata: Some(serde_json::to_value(&metadata)?),
                 embedding: embedding.clone(),
+                tenant_id: tenant_id.map(str::to_string),
                 created_at: chrono::Utc::now().to_rfc3339(),
-            };
+            });
+        }
 
-            self.persistence.save_chunk(&k_chunk).await?;
+        Ok((prepared, timings))
+    }
+
+    /// Index `document` from its file's `content`, storing its chunks and
+    /// the document marked indexed.
+    ///
+    /// A document already indexed from the same content is returned as it
+    /// is. One indexed from other content loses its chunks first, so they
+    /// are gone if indexing it again fails.
+    pub async fn ingest_document(
+        &self,
+        document: &KnowledgeDocument,
+        content: Vec<u8>,
+    ) -> Result<IngestedDocument> {
+        self.ingest_document_with_progress(document, content, |_| {}).await
+    }
+
+    /// [`Self::ingest_document`], reporting progress like
+    /// [`Self::prepare_chunks_with_progress`].
+    pub async fn ingest_document_with_progress(
+        &self,
+        document: &KnowledgeDocument,
+        content: Vec<u8>,
+        progress: impl Fn(IngestionProgress) + Send + Sync,
+    ) -> Result<IngestedDocument> {
+        let hash = file_hash(&content);
+        if document.status == DocumentStatus::Indexed
+            && document.content_hash.as_deref() == Some(hash.as_str())
+        {
+            return Ok(IngestedDocument {
+                document: document.clone(),
+                chunks_created: 0,
+                processed: false,
+                timings: IngestTimings::default(),
+            });
         }
 
-        Ok(chunks.len())
+        let started = Instant::now();
+        let mime_type = document.mime_type.clone();
+        let (text, metadata) = tokio::task::spawn_blocking(move || {
+            extract_document(&content, mime_type.as_deref())
+        })
+        .await??;
+        let extract = started.elapsed();
+
+        // Its old chunks would count as already ingested
+        if document.chunk_count > 0 {
+            self.persistence.delete_document_chunks(&document.id).await?;
+        }
+        let (chunks, mut timings) = self
+            .prepare_chunks_with_progress(
+                &text,
+                &document.kb_id,
+                document.id.clone(),
+                document.tenant_id.as_deref(),
+                progress,
+            )
+            .await?;
+        timings.extract = extract;
+
+        let started = Instant::now();
+        let indexed = KnowledgeDocument {
+            chunk_count: chunks.len(),
+            status: DocumentStatus::Indexed,
+            metadata: Some(serde_json::to_value(&metadata)?),
+            content_hash: Some(hash),
+            ..document.clone()
+        };
+        self.persistence
+            .save_document_with_chunks(&indexed, &chunks)
+            .await?;
+        timings.store = started.elapsed();
+        self.notify_chunks_saved(&document.kb_id);
+
+        let chunks_created = chunks.len();
+        self.start_graph_extraction(&document.kb_id, chunks).await;
+        Ok(IngestedDocument {
+            document: indexed,
+            chunks_created,
+            processed: true,
+            timings,
+        })
     }
 
     /// Recursively scan and ingest a directory
@@ -2,22 +2,42 @@
 //!
 //! Uses `prometheus_parking_lot` WorkerPool for scalable document ingestion.
 //! This ensures CPU-bound document processing doesn't block the async HTTP server.
+//! Each job's [`IngestionProgress`] is broadcast to the document's
+//! subscribers (see [`IngestionWorkerPool::subscribe`]).
 
 use crate::uar::{
-    domain::knowledge::{DocumentStatus, KnowledgeDocument},
+    domain::knowledge::{DocumentStatus, IngestionProgress, KnowledgeDocument},
     persistence::PersistenceLayer,
     rag::ingest::IngestService,
 };
 use anyhow::Result;
 use async_trait::async_trait;
+use dashmap::DashMap;
 use prometheus_parking_lot::{
     config::WorkerPoolConfig,
     core::{PoolError, TaskMetadata, WorkerExecutor, WorkerPool},
     util::serde::{MailboxKey, Priority, ResourceCost, ResourceKind},
 };
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
 use tracing::{error, info, warn};
 
+/// Steps of a job, timed separately in [`IngestionStats`].
+pub const STAGES: [&str; 4] = ["extract", "chunk", "embed", "store"];
+
+/// Progress events a slow subscriber may fall behind by before missing some.
+const PROGRESS_CAPACITY: usize = 64;
+/// Gauge of submitted jobs waiting for a worker.
+const QUEUE_DEPTH_METRIC: &str = "uar_ingestion_queue_depth";
+
+/// Jobs waiting for a worker unless configured.
+pub const DEFAULT_MAX_QUEUE_DEPTH: usize = 100;
+
 // =============================================================================
 // Job and Result Types
 // =============================================================================
@@ -27,12 +47,70 @@ use tracing::{error, info, warn};
 pub struct DocumentIngestionJob {
     /// The document to process
     pub document: KnowledgeDocument,
-    /// Raw file content (bytes)
-    pub file_content: Vec<u8>,
+    /// Where the file's content is read from
+    pub source: DocumentSource,
     /// Knowledge base ID for the document
     pub kb_id: String,
 }
 
+/// Content of a document to ingest.
+#[derive(Debug, Clone)]
+pub enum DocumentSource {
+    /// Content held in memory
+    Bytes(Vec<u8>),
+    /// An upload saved to disk, read once a worker picks the job up
+    File(PathBuf),
+}
+
+impl DocumentSource {
+    /// Read the content.
+    pub async fn load(&self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Bytes(bytes) => Ok(bytes.clone()),
+            Self::File(path) => tokio::fs::read(path).await,
+        }
+    }
+}
+
+impl From<Vec<u8>> for DocumentSource {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Bytes(bytes)
+    }
+}
+
+impl From<PathBuf> for DocumentSource {
+    fn from(path: PathBuf) -> Self {
+        Self::File(path)
+    }
+}
+
+/// Backlog and throughput of the ingestion worker pool.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IngestionStats {
+    /// Submitted jobs waiting for a worker
+    pub queue_depth: usize,
+    /// Workers processing a document
+    pub active_workers: usize,
+    pub worker_count: usize,
+    /// Jobs that finished indexing their document
+    pub jobs_processed: u64,
+    pub jobs_failed: u64,
+    /// Mean time of finished jobs
+    pub avg_processing_ms: f64,
+    /// Mean time of each of [`STAGES`] over processed jobs
+    pub stage_avg_ms: BTreeMap<&'static str, f64>,
+}
+
+/// A job the pool would not take.
+#[derive(Debug, thiserror::Error)]
+pub enum SubmitError {
+    /// The queue is at its configured depth; retry once workers catch up
+    #[error("ingestion queue is full ({max_queue_depth} jobs waiting)")]
+    QueueFull { max_queue_depth: usize },
+    #[error("ingestion pool rejected the job: {0:?}")]
+    Pool(PoolError),
+}
+
 /// Result from processing a document.
 #[derive(Debug, Clone)]
 pub struct IngestionResult {
@@ -55,6 +133,131 @@ pub struct DocumentIngestionExecutor {
     ingest_service: Arc<IngestService>,
     /// Persistence layer for status updates
     persistence: Arc<dyn PersistenceLayer>,
+    /// Throughput counters (shared with the pool)
+    counters: Arc<IngestionCounters>,
+    /// Progress subscribers (shared with the pool)
+    progress: Arc<ProgressChannels>,
+}
+
+/// Broadcast channels of [`IngestionProgress`], by document ID.
+///
+/// A channel exists from the first subscription until the document's final
+/// event is published; progress of documents nobody watches goes nowhere.
+#[derive(Debug, Default)]
+struct ProgressChannels(DashMap<String, broadcast::Sender<IngestionProgress>>);
+
+impl ProgressChannels {
+    fn subscribe(&self, document_id: &str) -> broadcast::Receiver<IngestionProgress> {
+        self.0
+            .entry(document_id.to_string())
+            .or_insert_with(|| broadcast::channel(PROGRESS_CAPACITY).0)
+            .subscribe()
+    }
+
+    fn publish(&self, document_id: &str, progress: IngestionProgress) {
+        let done = progress.is_final();
+        if let Some(sender) = self.0.get(document_id) {
+            // Subscribers may all have left
+            let _ = sender.send(progress);
+        }
+        if done {
+            self.0.remove(document_id);
+        }
+    }
+
+    /// Drop the channel of a document once nobody is subscribed.
+    fn release(&self, document_id: &str) {
+        self.0
+            .remove_if(document_id, |_, sender| sender.receiver_count() == 0);
+    }
+}
+
+/// Counters behind [`IngestionWorkerPool::stats`].
+#[derive(Debug, Default)]
+struct IngestionCounters {
+    /// Submitted jobs no worker has started yet
+    queued: AtomicUsize,
+    /// Jobs currently executing
+    active: AtomicUsize,
+    processed: AtomicU64,
+    failed: AtomicU64,
+    /// Time spent on finished jobs, in microseconds
+    busy_micros: AtomicU64,
+    /// Time spent in each of [`STAGES`] by processed jobs, in microseconds
+    stage_micros: [AtomicU64; STAGES.len()],
+}
+
+impl IngestionCounters {
+    /// Count a submitted job, unless `max` jobs are already waiting.
+    fn enqueue(&self, max: usize) -> bool {
+        let reserved = self
+            .queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                (queued < max).then_some(queued + 1)
+            });
+        self.report_queue_depth();
+        reserved.is_ok()
+    }
+
+    /// Count a job leaving the queue.
+    fn dequeue(&self) {
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        self.report_queue_depth();
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn report_queue_depth(&self) {
+        metrics::gauge!(QUEUE_DEPTH_METRIC).set(self.queued.load(Ordering::SeqCst) as f64);
+    }
+
+    /// Count a finished job; `stages` holds its stage timings if it succeeded.
+    fn record(&self, elapsed: Duration, stages: Option<[Duration; STAGES.len()]>) {
+        add_micros(&self.busy_micros, elapsed);
+        match stages {
+            Some(stages) => {
+                for (total, time) in self.stage_micros.iter().zip(stages) {
+                    add_micros(total, time);
+                }
+                self.processed.fetch_add(1, Ordering::SeqCst);
+            }
+            None => {
+                self.failed.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+fn add_micros(total: &AtomicU64, time: Duration) {
+    total.fetch_add(
+        u64::try_from(time.as_micros()).unwrap_or(u64::MAX),
+        Ordering::SeqCst,
+    );
+}
+
+/// Mean of `count` items taking `total_micros`, in milliseconds.
+fn average_ms(total_micros: u64, count: u64) -> f64 {
+    if count == 0 {
+        return 0.0;
+    }
+    Duration::from_micros(total_micros / count).as_secs_f64() * 1000.0
+}
+
+/// Moves a job from the queue to the active count, and out of it when the
+/// job finishes, however it ends.
+struct ActiveJob(Arc<IngestionCounters>);
+
+impl ActiveJob {
+    fn start(counters: &Arc<IngestionCounters>) -> Self {
+        counters.active.fetch_add(1, Ordering::SeqCst);
+        counters.dequeue();
+        Self(Arc::clone(counters))
+    }
+}
+
+impl Drop for ActiveJob {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl DocumentIngestionExecutor {
@@ -63,6 +266,8 @@ impl DocumentIngestionExecutor {
         Self {
             ingest_service,
             persistence,
+            counters: Arc::new(IngestionCounters::default()),
+            progress: Arc::new(ProgressChannels::default()),
         }
     }
 }
@@ -70,6 +275,8 @@ impl DocumentIngestionExecutor {
 #[async_trait]
 impl WorkerExecutor<DocumentIngestionJob, IngestionResult> for DocumentIngestionExecutor {
     async fn execute(&self, job: DocumentIngestionJob, _meta: TaskMetadata) -> IngestionResult {
+        let _active = ActiveJob::start(&self.counters);
+        let started = Instant::now();
         let doc_id = job.document.id.clone();
         info!(document_id = %doc_id, "Starting document ingestion");
 
@@ -84,18 +291,13 @@ impl WorkerExecutor<DocumentIngestionJob, IngestionResult> for DocumentIngestion
 
         // Attempt to ingest the document
         match self.process_document(&job).await {
-            Ok(chunk_count) => {
-                // Update status to Indexed
+            Ok((chunk_count, stages)) => {
+                // Marked indexed together with its chunks
                 let status = DocumentStatus::Indexed;
-                if let Err(e) = self
-                    .persistence
-                    .update_document_status(&doc_id, &status)
-                    .await
-                {
-                    error!(document_id = %doc_id, error = %e, "Failed to update status to indexed");
-                }
-
                 info!(document_id = %doc_id, chunk_count, "Document ingestion completed");
+                self.progress
+                    .publish(&doc_id, IngestionProgress::Indexed { chunk_count });
+                self.counters.record(started.elapsed(), Some(stages));
                 IngestionResult {
                     document_id: doc_id,
                     chunk_count,
@@ -116,6 +318,13 @@ impl WorkerExecutor<DocumentIngestionJob, IngestionResult> for DocumentIngestion
                 }
 
                 error!(document_id = %doc_id, error = %e, "Document ingestion failed");
+                self.progress.publish(
+                    &doc_id,
+                    IngestionProgress::Failed {
+                        error: e.to_string(),
+                    },
+                );
+                self.counters.record(started.elapsed(), None);
                 IngestionResult {
                     document_id: doc_id,
                     chunk_count: 0,
@@ -127,19 +336,40 @@ impl WorkerExecutor<DocumentIngestionJob, IngestionResult> for DocumentIngestion
 }
 
 impl DocumentIngestionExecutor {
-    /// Process a document and return chunk count.
-    async fn process_document(&self, job: &DocumentIngestionJob) -> Result<usize> {
-        // Convert file content to text (for now, assume text files)
-        // In production, this would use file processors (Kreuzberg, etc.)
-        let text = String::from_utf8_lossy(&job.file_content);
-
-        // Use the ingest service to chunk, embed, and store
-        let chunks = self
+    /// Process a document and return chunk count and the time of each of
+    /// [`STAGES`].
+    ///
+    /// The document is saved as indexed in the same transaction as its
+    /// chunks, so nothing is stored if any step fails.
+    async fn process_document(
+        &self,
+        job: &DocumentIngestionJob,
+    ) -> Result<(usize, [Duration; STAGES.len()])> {
+        let document = &job.document;
+        self.progress
+            .publish(&document.id, IngestionProgress::Extracting);
+
+        let started = Instant::now();
+        let content = job.source.load().await?;
+        let load = started.elapsed();
+        let ingested = self
             .ingest_service
-            .ingest_text(&text, &job.kb_id, job.document.id.clone())
+            .ingest_document_with_progress(document, content, |progress| {
+                self.progress.publish(&document.id, progress);
+            })
             .await?;
+        if !ingested.processed {
+            // Unchanged, so it keeps the status it had before this job
+            self.persistence
+                .update_document_status(&document.id, &document.status)
+                .await?;
+        }
 
-        Ok(chunks)
+        let timings = ingested.timings;
+        Ok((
+            ingested.chunks_created,
+            [load + timings.extract, timings.chunk, timings.embed, timings.store],
+        ))
     }
 }
 
@@ -152,11 +382,21 @@ impl DocumentIngestionExecutor {
 pub struct IngestionWorkerPool {
     /// The underlying worker pool
     pool: WorkerPool<DocumentIngestionJob, IngestionResult, DocumentIngestionExecutor>,
+    worker_count: usize,
+    max_queue_depth: usize,
+    /// Throughput counters (shared with the executor)
+    counters: Arc<IngestionCounters>,
+    /// Progress subscribers (shared with the executor)
+    progress: Arc<ProgressChannels>,
 }
 
 impl std::fmt::Debug for IngestionWorkerPool {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("IngestionWorkerPool").finish()
+        f.debug_struct("IngestionWorkerPool")
+            .field("worker_count", &self.worker_count)
+            .field("max_queue_depth", &self.max_queue_depth)
+            .field("counters", &self.counters)
+            .finish()
     }
 }
 
@@ -165,7 +405,8 @@ impl IngestionWorkerPool {
     ///
     /// # Arguments
     /// * `worker_count` - Number of worker threads (0 = auto-detect based on CPU)
-    /// * `max_queue_depth` - Maximum pending jobs before backpressure
+    /// * `max_queue_depth` - Maximum pending jobs; further submissions fail
+    ///   with [`SubmitError::QueueFull`]
     /// * `ingest_service` - Shared ingest service
     /// * `persistence` - Persistence layer for status updates
     pub fn new(
@@ -186,6 +427,8 @@ impl IngestionWorkerPool {
             .with_max_queue_depth(max_queue_depth);
 
         let executor = DocumentIngestionExecutor::new(ingest_service, persistence);
+        let counters = Arc::clone(&executor.counters);
+        let progress = Arc::clone(&executor.progress);
         let pool = WorkerPool::new(config, executor)?;
 
         info!(
@@ -193,21 +436,83 @@ impl IngestionWorkerPool {
             max_queue_depth, "Ingestion worker pool initialized"
         );
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            worker_count,
+            max_queue_depth,
+            counters,
+            progress,
+        })
+    }
+
+    /// Receive the progress a worker reports for a document from now on.
+    ///
+    /// The channel closes after the final `indexed` or `failed` event.
+    /// Subscribe before checking the document's status, so a job finishing
+    /// in between is not missed, and [`Self::release`] if it already had.
+    pub fn subscribe(&self, document_id: &str) -> broadcast::Receiver<IngestionProgress> {
+        self.progress.subscribe(document_id)
+    }
+
+    /// Drop a document's progress channel if nobody is subscribed.
+    pub fn release(&self, document_id: &str) {
+        self.progress.release(document_id);
+    }
+
+    /// Workers not currently processing a document.
+    pub fn available_workers(&self) -> usize {
+        self.worker_count
+            .saturating_sub(self.counters.active.load(Ordering::SeqCst))
     }
 
-    /// Submit a document for ingestion.
+    /// Submitted jobs waiting for a worker.
+    pub fn queue_depth(&self) -> usize {
+        self.counters.queued.load(Ordering::SeqCst)
+    }
+
+    /// Whether further submissions would be rejected.
+    pub fn is_queue_full(&self) -> bool {
+        self.queue_depth() >= self.max_queue_depth
+    }
+
+    /// Snapshot of the pool's backlog and throughput.
+    pub fn stats(&self) -> IngestionStats {
+        let counters = &self.counters;
+        let processed = counters.processed.load(Ordering::SeqCst);
+        let failed = counters.failed.load(Ordering::SeqCst);
+        let stage_avg_ms = STAGES
+            .iter()
+            .zip(&counters.stage_micros)
+            .map(|(stage, total)| (*stage, average_ms(total.load(Ordering::SeqCst), processed)))
+            .collect();
+        IngestionStats {
+            queue_depth: self.queue_depth(),
+            active_workers: counters.active.load(Ordering::SeqCst),
+            worker_count: self.worker_count,
+            jobs_processed: processed,
+            jobs_failed: failed,
+            avg_processing_ms: average_ms(
+                counters.busy_micros.load(Ordering::SeqCst),
+                processed + failed,
+            ),
+            stage_avg_ms,
+        }
+    }
+
+    /// Submit a document for ingestion, with its content in memory or in a
+    /// file.
     ///
-    /// Returns a job key that can be used to retrieve the result.
+    /// Returns a job key that can be used to retrieve the result. Fails with
+    /// [`SubmitError::QueueFull`] while the queue is at its maximum depth.
     pub async fn submit(
         &self,
         document: KnowledgeDocument,
-        file_content: Vec<u8>,
-    ) -> Result<String, PoolError> {
+        source: impl Into<DocumentSource>,
+    ) -> Result<String, SubmitError> {
         let job = DocumentIngestionJob {
             kb_id: document.kb_id.clone(),
             document,
-            file_content,
+            source: source.into(),
         };
 
         let meta = TaskMetadata {
@@ -222,8 +527,24 @@ impl IngestionWorkerPool {
             mailbox: None,
         };
 
-        let key = self.pool.submit_async(job, meta).await?;
-        Ok(format!("{key:?}"))
+        // Counted before submitting, so a worker never starts an uncounted job
+        if !self.counters.enqueue(self.max_queue_depth) {
+            warn!(
+                document_id = %job.document.id,
+                max_queue_depth = self.max_queue_depth,
+                "Ingestion queue is full"
+            );
+            return Err(SubmitError::QueueFull {
+                max_queue_depth: self.max_queue_depth,
+            });
+        }
+        match self.pool.submit_async(job, meta).await {
+            Ok(key) => Ok(format!("{key:?}")),
+            Err(e) => {
+                self.counters.dequeue();
+                Err(SubmitError::Pool(e))
+            }
+        }
     }
 
     /// Retrieve the result of an ingestion job.
@@ -242,3 +563,187 @@ impl IngestionWorkerPool {
         drop(self.pool);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::persistence::testing::InMemoryPersistence;
+    use crate::uar::rag::chunking::ChunkingStrategy;
+    use crate::uar::runtime::matching::VectorMatcher;
+    use crate::uar::testing::StubEmbedder;
+
+    fn job(kb_id: &str, content: &str) -> DocumentIngestionJob {
+        let id = uuid::Uuid::new_v4().to_string();
+        DocumentIngestionJob {
+            document: KnowledgeDocument {
+                id,
+                kb_id: kb_id.to_string(),
+                filename: "notes.txt".to_string(),
+                file_path: None,
+                mime_type: Some("text/plain".to_string()),
+                chunk_count: 0,
+                status: DocumentStatus::Pending,
+                tenant_id: None,
+                metadata: None,
+                idempotency_key: None,
+                content_hash: None,
+                created_at: String::new(),
+                updated_at: String::new(),
+            },
+            source: content.as_bytes().to_vec().into(),
+            kb_id: kb_id.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reingesting_a_file_adds_no_chunks() {
+        let db = Arc::new(InMemoryPersistence::new());
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let store: Arc<dyn PersistenceLayer> = Arc::clone(&db);
+        let ingest = IngestService::new(Arc::clone(&store), matcher, ChunkingStrategy::Sentence);
+        let executor = DocumentIngestionExecutor::new(Arc::new(ingest), store);
+
+        let text = "Rust has no garbage collector. Ownership frees memory.";
+        let (created, _) = executor.process_document(&job("kb-1", text)).await.unwrap();
+        assert_eq!(created, 2);
+        assert_eq!(db.chunk_count(), 2);
+
+        // Same file again, and a repeated sentence with different spacing
+        let (created, _) = executor.process_document(&job("kb-1", text)).await.unwrap();
+        assert_eq!(created, 0);
+        let overlapping = "Ownership  frees memory. Borrowing lends it.";
+        let (created, _) = executor.process_document(&job("kb-1", overlapping)).await.unwrap();
+        assert_eq!(created, 1);
+        assert_eq!(db.chunk_count(), 3);
+
+        // Hashes are per knowledge base
+        let (created, _) = executor.process_document(&job("kb-2", text)).await.unwrap();
+        assert_eq!(created, 2);
+        assert_eq!(db.chunk_count(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_file_source_is_read_from_disk() {
+        let db = Arc::new(InMemoryPersistence::new());
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let store: Arc<dyn PersistenceLayer> = Arc::clone(&db);
+        let ingest = IngestService::new(Arc::clone(&store), matcher, ChunkingStrategy::Sentence);
+        let executor = DocumentIngestionExecutor::new(Arc::new(ingest), store);
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"Read from disk. Not from memory.").unwrap();
+        let job = DocumentIngestionJob {
+            source: file.path().to_path_buf().into(),
+            ..job("kb-1", "")
+        };
+        let (created, _) = executor.process_document(&job).await.unwrap();
+        assert_eq!(created, 2);
+
+        let missing = DocumentIngestionJob {
+            source: DocumentSource::File(file.path().with_extension("missing")),
+            ..job
+        };
+        assert!(executor.process_document(&missing).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_stores_no_chunks() {
+        let db = Arc::new(InMemoryPersistence::new());
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let store: Arc<dyn PersistenceLayer> = Arc::clone(&db);
+        let ingest = IngestService::new(Arc::clone(&store), matcher, ChunkingStrategy::Sentence);
+        let executor = DocumentIngestionExecutor::new(Arc::new(ingest), Arc::clone(&store));
+
+        // The second of three chunks fails to save
+        let text = "First sentence here. Second sentence here. Third sentence here.";
+        let failing = job("kb-1", text);
+        store.save_document(&failing.document).await.unwrap();
+        db.fail_chunk_writes_after(1);
+        assert!(executor.process_document(&failing).await.is_err());
+
+        assert_eq!(db.chunk_count(), 0);
+        let doc = store.get_document(&failing.document.id, None).await.unwrap().unwrap();
+        assert_eq!(doc.status, DocumentStatus::Pending);
+        assert_eq!(doc.chunk_count, 0);
+
+        db.fail_chunk_writes_after(usize::MAX);
+        let (created, _) = executor.process_document(&failing).await.unwrap();
+        assert_eq!(created, 3);
+        let doc = store.get_document(&failing.document.id, None).await.unwrap().unwrap();
+        assert_eq!(doc.status, DocumentStatus::Indexed);
+        assert_eq!(doc.chunk_count, 3);
+        assert_eq!(db.chunk_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_submissions_beyond_the_queue_depth_are_rejected() {
+        let db: Arc<dyn PersistenceLayer> = Arc::new(InMemoryPersistence::new());
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let ingest = IngestService::new(Arc::clone(&db), matcher, ChunkingStrategy::Sentence);
+        let pool = IngestionWorkerPool::new(1, 2, Arc::new(ingest), db).unwrap();
+
+        // Two jobs no worker has picked up yet
+        assert!(pool.counters.enqueue(2));
+        assert!(pool.counters.enqueue(2));
+        assert_eq!(pool.queue_depth(), 2);
+        assert!(pool.is_queue_full());
+
+        let job = job("kb-1", "Queued behind the others.");
+        let err = pool.submit(job.document, job.source).await.unwrap_err();
+        assert!(matches!(err, SubmitError::QueueFull { max_queue_depth: 2 }), "{err}");
+        assert_eq!(pool.queue_depth(), 2);
+
+        pool.counters.dequeue();
+        assert!(!pool.is_queue_full());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_subscribers_see_each_stage() {
+        let db = Arc::new(InMemoryPersistence::new());
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::new(StubEmbedder::default()),
+        ));
+        let store: Arc<dyn PersistenceLayer> = Arc::clone(&db);
+        let ingest = IngestService::new(Arc::clone(&store), matcher, ChunkingStrategy::Sentence);
+        let pool = IngestionWorkerPool::new(1, 10, Arc::new(ingest), Arc::clone(&store)).unwrap();
+
+        let text = "Rust has no garbage collector. Ownership frees memory.";
+        let job = job("kb-1", text);
+        store.save_document(&job.document).await.unwrap();
+        let mut progress = pool.subscribe(&job.document.id);
+        pool.submit(job.document, job.source).await.unwrap();
+
+        let events = tokio::time::timeout(Duration::from_secs(10), async {
+            let mut events = Vec::new();
+            // The channel closes after the final event
+            while let Ok(event) = progress.recv().await {
+                events.push(event);
+            }
+            events
+        })
+        .await
+        .expect("ingestion did not finish");
+        assert_eq!(
+            events,
+            [
+                IngestionProgress::Extracting,
+                IngestionProgress::Chunked { count: 2 },
+                IngestionProgress::Embedded { done: 2, total: 2 },
+                IngestionProgress::Indexed { chunk_count: 2 },
+            ]
+        );
+    }
+}
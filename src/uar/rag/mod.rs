@@ -1,5 +1,11 @@
+pub mod archive;
 pub mod chunking;
+pub mod citations;
+pub mod embedding;
 pub mod extraction;
+pub mod graph;
 pub mod ingest;
 pub mod ingestion_worker;
+pub mod reindex;
+pub mod rerank;
 pub mod retrieval;
@@ -0,0 +1,280 @@
+//! Re-indexing a knowledge base from its documents' stored files.
+//!
+//! Each file is hashed and compared with [`KnowledgeDocument::content_hash`],
+//! so only documents whose file changed since they were indexed, and ones
+//! that failed, are extracted, chunked and embedded again. Reindexes run in
+//! the background as jobs polled by ID (see [`IngestService::start_reindex`]).
+
+use crate::uar::domain::knowledge::{DocumentStatus, KnowledgeDocument};
+use crate::uar::rag::ingest::IngestService;
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long finished jobs can still be polled.
+pub const REINDEX_JOB_TTL: Duration = Duration::from_secs(3600);
+
+/// What a reindex did with the documents of a knowledge base.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ReindexReport {
+    /// Indexed documents whose file is unchanged, and ones still queued
+    pub skipped: usize,
+    pub reprocessed: usize,
+    /// Documents that could not be indexed, now marked failed
+    pub failed: usize,
+}
+
+/// A reindex started by [`IngestService::start_reindex`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ReindexJob {
+    pub id: String,
+    pub kb_id: String,
+    #[serde(flatten)]
+    pub status: ReindexStatus,
+    pub started_at: String, // RFC3339
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>, // RFC3339
+    /// When the job finished, for expiring it
+    #[serde(skip)]
+    finished: Option<Instant>,
+}
+
+/// Progress of a [`ReindexJob`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ReindexStatus {
+    Running,
+    Completed { report: ReindexReport },
+    Failed { error: String },
+}
+
+impl IngestService {
+    /// Index the documents of `kb_id` again where their file changed since
+    /// they were indexed, or their last attempt failed.
+    ///
+    /// Documents still waiting for (or held by) an ingestion worker are left
+    /// to it. A document that fails is marked failed and the rest go on.
+    pub async fn reindex_kb(&self, kb_id: &str, tenant_id: Option<&str>) -> Result<ReindexReport> {
+        let mut report = ReindexReport::default();
+        for document in self.persistence.list_documents(kb_id, tenant_id).await? {
+            if matches!(document.status, DocumentStatus::Pending | DocumentStatus::Processing) {
+                report.skipped += 1;
+                continue;
+            }
+            match self.reindex_document(&document).await {
+                Ok(true) => report.reprocessed += 1,
+                Ok(false) => report.skipped += 1,
+                Err(e) => {
+                    tracing::warn!(
+                        document_id = %document.id,
+                        error = %e,
+                        "Failed to reindex document"
+                    );
+                    let status = DocumentStatus::Failed {
+                        error: e.to_string(),
+                    };
+                    if let Err(e) = self
+                        .persistence
+                        .update_document_status(&document.id, &status)
+                        .await
+                    {
+                        tracing::error!(
+                            document_id = %document.id,
+                            error = %e,
+                            "Failed to update status to failed"
+                        );
+                    }
+                    report.failed += 1;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Index `document` again from its stored file; `false` if unchanged.
+    async fn reindex_document(&self, document: &KnowledgeDocument) -> Result<bool> {
+        let path = document
+            .file_path
+            .as_ref()
+            .ok_or_else(|| anyhow!("Document has no stored file"))?;
+        let content = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read {path}"))?;
+        Ok(self.ingest_document(document, content).await?.processed)
+    }
+
+    /// Run [`Self::reindex_kb`] in the background, returning the job to poll
+    /// with [`Self::reindex_job`].
+    ///
+    /// While a reindex of the knowledge base is running, that job is
+    /// returned instead of starting another.
+    pub fn start_reindex(self: &Arc<Self>, kb_id: &str, tenant_id: Option<&str>) -> ReindexJob {
+        let mut jobs = self.reindex_jobs.lock().unwrap();
+        jobs.retain(|_, job| job.finished.is_none_or(|at| at.elapsed() < REINDEX_JOB_TTL));
+        if let Some(running) = jobs
+            .values()
+            .find(|job| job.kb_id == kb_id && job.status == ReindexStatus::Running)
+        {
+            return running.clone();
+        }
+
+        let job = ReindexJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            kb_id: kb_id.to_string(),
+            status: ReindexStatus::Running,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            finished_at: None,
+            finished: None,
+        };
+        jobs.insert(job.id.clone(), job.clone());
+
+        let service = Arc::clone(self);
+        let (job_id, kb_id) = (job.id.clone(), job.kb_id.clone());
+        let tenant_id = tenant_id.map(str::to_string);
+        tokio::spawn(async move {
+            let status = match service.reindex_kb(&kb_id, tenant_id.as_deref()).await {
+                Ok(report) => {
+                    tracing::info!(kb_id = %kb_id, ?report, "Knowledge base reindexed");
+                    ReindexStatus::Completed { report }
+                }
+                Err(e) => {
+                    tracing::error!(kb_id = %kb_id, error = %e, "Knowledge base reindex failed");
+                    ReindexStatus::Failed {
+                        error: e.to_string(),
+                    }
+                }
+            };
+            if let Some(job) = service.reindex_jobs.lock().unwrap().get_mut(&job_id) {
+                job.status = status;
+                job.finished_at = Some(chrono::Utc::now().to_rfc3339());
+                job.finished = Some(Instant::now());
+            }
+        });
+        job
+    }
+
+    /// A reindex job started in the last [`REINDEX_JOB_TTL`] or still running.
+    pub fn reindex_job(&self, job_id: &str) -> Option<ReindexJob> {
+        self.reindex_jobs
+            .lock()
+            .unwrap()
+            .get(job_id)
+            .filter(|job| job.finished.is_none_or(|at| at.elapsed() < REINDEX_JOB_TTL))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::domain::knowledge::file_hash;
+    use crate::uar::persistence::{PersistenceLayer, testing::InMemoryPersistence};
+    use crate::uar::rag::chunking::ChunkingStrategy;
+    use crate::uar::rag::embedding::EmbeddingProvider;
+    use crate::uar::runtime::matching::VectorMatcher;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts the texts it embeds.
+    #[derive(Debug, Default)]
+    struct CountingEmbedder(AtomicUsize);
+
+    #[async_trait]
+    impl EmbeddingProvider for CountingEmbedder {
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+            self.0.fetch_add(texts.len(), Ordering::SeqCst);
+            Ok(texts.iter().map(|_| vec![1.0, 0.0]).collect())
+        }
+
+        fn dimensions(&self) -> usize {
+            2
+        }
+    }
+
+    fn document(id: &str, path: &std::path::Path) -> KnowledgeDocument {
+        KnowledgeDocument {
+            id: id.to_string(),
+            kb_id: "kb-1".to_string(),
+            filename: format!("{id}.txt"),
+            file_path: Some(path.to_string_lossy().into_owned()),
+            mime_type: Some("text/plain".to_string()),
+            chunk_count: 0,
+            status: DocumentStatus::Pending,
+            tenant_id: None,
+            metadata: None,
+            idempotency_key: None,
+            content_hash: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_changed_documents_are_reprocessed() {
+        let db = Arc::new(InMemoryPersistence::new());
+        let store: Arc<dyn PersistenceLayer> = Arc::clone(&db);
+        let embedder = Arc::new(CountingEmbedder::default());
+        let matcher = Arc::new(VectorMatcher::with_provider(
+            0.5,
+            Arc::clone(&embedder) as Arc<dyn EmbeddingProvider>,
+        ));
+        let ingest = Arc::new(IngestService::new(
+            Arc::clone(&store),
+            matcher,
+            ChunkingStrategy::Sentence,
+        ));
+
+        let files = tempfile::tempdir().unwrap();
+        let (stable, edited) = (files.path().join("a.txt"), files.path().join("b.txt"));
+        std::fs::write(&stable, "Ownership frees memory. Borrowing lends it.").unwrap();
+        std::fs::write(&edited, "Traits describe behaviour.").unwrap();
+        for doc in [document("doc-a", &stable), document("doc-b", &edited)] {
+            let content = std::fs::read(doc.file_path.as_ref().unwrap()).unwrap();
+            let ingested = ingest.ingest_document(&doc, content).await.unwrap();
+            assert!(ingested.processed);
+        }
+        assert_eq!(embedder.0.load(Ordering::SeqCst), 3);
+
+        // Nothing changed
+        let report = ingest.reindex_kb("kb-1", None).await.unwrap();
+        assert_eq!(
+            report,
+            ReindexReport {
+                skipped: 2,
+                ..Default::default()
+            }
+        );
+        assert_eq!(embedder.0.load(Ordering::SeqCst), 3);
+
+        std::fs::write(&edited, "Traits describe shared behaviour.").unwrap();
+        let report = ingest.reindex_kb("kb-1", None).await.unwrap();
+        assert_eq!(
+            report,
+            ReindexReport {
+                skipped: 1,
+                reprocessed: 1,
+                failed: 0
+            }
+        );
+        assert_eq!(embedder.0.load(Ordering::SeqCst), 4);
+        assert_eq!(db.chunk_count(), 3);
+        let doc = store.get_document("doc-b", None).await.unwrap().unwrap();
+        assert_eq!(doc.status, DocumentStatus::Indexed);
+        assert_eq!(
+            doc.content_hash.as_deref(),
+            Some(file_hash(b"Traits describe shared behaviour.").as_str())
+        );
+
+        // A missing file fails the document, which the next reindex retries
+        std::fs::remove_file(&edited).unwrap();
+        let report = ingest.reindex_kb("kb-1", None).await.unwrap();
+        assert_eq!(report.failed, 1);
+        let doc = store.get_document("doc-b", None).await.unwrap().unwrap();
+        assert!(matches!(doc.status, DocumentStatus::Failed { .. }));
+        std::fs::write(&edited, "Traits describe shared behaviour.").unwrap();
+        let report = ingest.reindex_kb("kb-1", None).await.unwrap();
+        assert_eq!(report.reprocessed, 1);
+        assert_eq!(report.skipped, 1);
+    }
+}
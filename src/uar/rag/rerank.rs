@@ -0,0 +1,676 @@
+//! Cross-encoder reranking of vector search results.
+//!
+//! Embedding similarity is a cheap but noisy first-stage filter. When a
+//! knowledge base enables reranking, search over-fetches candidates and a
+//! cross-encoder scores each `(query, chunk)` pair jointly, which is far
+//! better at rejecting near-miss chunks that merely share vocabulary.
+//!
+//! A KB picks its reranker with `kind` (`none`, `cross_encoder`, `cohere`);
+//! KBs without reranking settings use the global default, if any. Without a
+//! `kind`, rerankers are selected by model name:
+//! - `jina-*` models call the Jina reranking API (`JINA_API_KEY`)
+//! - `rerank-*` models call the Cohere reranking API (`COHERE_API_KEY`)
+//! - anything else is run locally with fastembed (e.g. `BAAI/bge-reranker-base`)
+//!
+//! Local models fastembed has no build of, such as
+//! `cross-encoder/ms-marco-MiniLM-L-6-v2`, are refused by [`check_config`]
+//! rather than swapped for another model.
+
+use crate::uar::domain::knowledge::{KnowledgeBase, KnowledgeMatch, RerankerConfig, RerankerKind};
+use crate::uar::persistence::PersistenceLayer;
+use crate::uar::runtime::matching::VectorMatcher;
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use fastembed::{RerankInitOptions, RerankerModel, TextRerank};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Number of candidates fetched per requested result before reranking.
+pub const OVERFETCH_FACTOR: usize = 3;
+
+// =============================================================================
+// Reranker Trait
+// =============================================================================
+
+/// Scores query/document pairs for relevance.
+#[async_trait]
+pub trait Reranker: Send + Sync + std::fmt::Debug {
+    /// Score each document against the query (higher is more relevant).
+    ///
+    /// Scores are returned in the same order as `documents`.
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>>;
+}
+
+/// Rerank matches with a cross-encoder, keeping the best `top_n`.
+///
+/// Each match's `score` is replaced by the reranker score.
+pub async fn rerank_matches(
+    reranker: &dyn Reranker,
+    query: &str,
+    matches: Vec<KnowledgeMatch>,
+    top_n: usize,
+) -> Result<Vec<KnowledgeMatch>> {
+    if matches.is_empty() {
+        return Ok(matches);
+    }
+
+    let documents: Vec<String> = matches.iter().map(|m| m.chunk.content.clone()).collect();
+    let scores = reranker.score(query, &documents).await?;
+    if scores.len() != matches.len() {
+        return Err(anyhow!(
+            "Reranker returned {} scores for {} documents",
+            scores.len(),
+            matches.len()
+        ));
+    }
+
+    let mut reranked: Vec<KnowledgeMatch> = matches
+        .into_iter()
+        .zip(scores)
+        .map(|(m, score)| KnowledgeMatch { score, ..m })
+        .collect();
+    reranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    reranked.truncate(top_n);
+    Ok(reranked)
+}
+
+/// Scoped knowledge search with cross-encoder reranking.
+///
+/// Over-fetches `top_n * OVERFETCH_FACTOR` candidates by embedding similarity
+/// and returns the `top_n` best by reranker score.
+#[allow(clippy::too_many_arguments)]
+pub async fn search_knowledge_reranked(
+    persistence: &dyn PersistenceLayer,
+    reranker: &dyn Reranker,
+    kb_ids: &[&str],
+    query: &str,
+    query_vec: &[f32],
+    top_n: usize,
+    min_score: f32,
+    metadata_filter: Option<&serde_json::Value>,
+    tenant_id: Option<&str>,
+) -> Result<Vec<KnowledgeMatch>> {
+    let fetch = top_n * OVERFETCH_FACTOR;
+    let candidates = persistence
+        .search_knowledge_scoped(
+            kb_ids,
+            query_vec,
+            fetch,
+            min_score,
+            metadata_filter,
+            tenant_id,
+        )
+        .await?;
+    rerank_matches(reranker, query, candidates, top_n).await
+}
+
+/// Search one knowledge base, reranking when the KB (or the registry's
+/// default) asks for it.
+///
+/// Candidates are found through `matcher`'s chunk index when it has one.
+/// Reranked searches return at most the reranker's `top_n` results.
+#[allow(clippy::too_many_arguments)]
+pub async fn search_knowledge_base(
+    persistence: &dyn PersistenceLayer,
+    matcher: &VectorMatcher,
+    rerankers: &RerankerRegistry,
+    kb: &KnowledgeBase,
+    query: &str,
+    query_vec: &[f32],
+    limit: usize,
+    min_score: f32,
+    metadata_filter: Option<&serde_json::Value>,
+    tenant_id: Option<&str>,
+) -> Result<Vec<KnowledgeMatch>> {
+    let kb_ids = [kb.id.as_str()];
+    match rerankers.resolve(kb.config.rerank.as_ref()) {
+        Some(cfg) => {
+            let reranker = rerankers.create(cfg)?;
+            let top_n = cfg.top_n.min(limit);
+            let candidates = matcher
+                .search_chunks(
+                    persistence,
+                    &kb_ids,
+                    query_vec,
+                    top_n.saturating_mul(OVERFETCH_FACTOR),
+                    min_score,
+                    metadata_filter,
+                    tenant_id,
+                )
+                .await?;
+            rerank_matches(reranker.as_ref(), query, candidates, top_n).await
+        }
+        None => {
+            matcher
+                .search_chunks(
+                    persistence,
+                    &kb_ids,
+                    query_vec,
+                    limit,
+                    min_score,
+                    metadata_filter,
+                    tenant_id,
+                )
+                .await
+        }
+    }
+}
+
+// =============================================================================
+// Local Cross-Encoder
+// =============================================================================
+
+/// Cross-encoder reranker running locally via fastembed (ONNX).
+///
+/// The model is downloaded and loaded on first use.
+pub struct CrossEncoderReranker {
+    model_name: RerankerModel,
+    model: Arc<Mutex<Option<TextRerank>>>,
+}
+
+impl std::fmt::Debug for CrossEncoderReranker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CrossEncoderReranker")
+            .field("model", &self.model_name)
+            .finish()
+    }
+}
+
+impl CrossEncoderReranker {
+    /// Create a reranker for a supported local model ID.
+    pub fn new(model: &str) -> Result<Self> {
+        Ok(Self {
+            model_name: local_model(model)?,
+            model: Arc::new(Mutex::new(None)),
+        })
+    }
+}
+
+/// The fastembed build of local model `model`.
+fn local_model(model: &str) -> Result<RerankerModel> {
+    match model {
+        "BAAI/bge-reranker-base" => Ok(RerankerModel::BGERerankerBase),
+        "rozgo/bge-reranker-v2-m3" | "BAAI/bge-reranker-v2-m3" => {
+            Ok(RerankerModel::BGERerankerV2M3)
+        }
+        "jinaai/jina-reranker-v1-turbo-en" => Ok(RerankerModel::JINARerankerV1TurboEn),
+        "jinaai/jina-reranker-v2-base-multilingual" => {
+            Ok(RerankerModel::JINARerankerV2BaseMultiligual)
+        }
+        other => Err(anyhow!(
+            "Unsupported local reranker model: {} (supported: {})",
+            other,
+            LOCAL_MODELS.join(", ")
+        )),
+    }
+}
+
+/// Local models [`CrossEncoderReranker`] runs.
+const LOCAL_MODELS: [&str; 4] = [
+    "BAAI/bge-reranker-base",
+    "BAAI/bge-reranker-v2-m3",
+    "jinaai/jina-reranker-v1-turbo-en",
+    "jinaai/jina-reranker-v2-base-multilingual",
+];
+
+/// Check that reranking settings name a model there is a reranker for.
+///
+/// Only local models are known up front; hosted models are checked by
+/// their API on first use.
+pub fn check_config(config: &RerankerConfig) -> Result<()> {
+    if !config.is_enabled() {
+        return Ok(());
+    }
+    let model = config.effective_model();
+    let local = match config.kind {
+        Some(RerankerKind::CrossEncoder) => true,
+        None => !model.starts_with("jina-") && !model.starts_with("rerank-"),
+        Some(RerankerKind::Cohere | RerankerKind::None) => false,
+    };
+    if local {
+        local_model(model)?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl Reranker for CrossEncoderReranker {
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        let mut model_guard = self.model.lock().await;
+        let model = model_guard.take();
+        let model_name = self.model_name.clone();
+        let query = query.to_string();
+        let documents = documents.to_vec();
+
+        // Model loading and inference are CPU-bound
+        let (result, model) = tokio::task::spawn_blocking(move || {
+            let mut model = match model {
+                Some(model) => model,
+                None => {
+                    tracing::info!("Initializing reranker model {:?}...", model_name);
+                    match TextRerank::try_new(RerankInitOptions::new(model_name)) {
+                        Ok(model) => model,
+                        Err(e) => return (Err(anyhow!(e)), None),
+                    }
+                }
+            };
+
+            let docs: Vec<&str> = documents.iter().map(String::as_str).collect();
+            let result = model
+                .rerank(query.as_str(), docs, false, None)
+                .map_err(|e| anyhow!(e))
+                .map(|results| {
+                    let mut scores = vec![0.0; documents.len()];
+                    for r in results {
+                        scores[r.index] = r.score;
+                    }
+                    scores
+                });
+            (result, Some(model))
+        })
+        .await?;
+
+        *model_guard = model;
+        result
+    }
+}
+
+// =============================================================================
+// HTTP Reranker (Jina / Cohere)
+// =============================================================================
+
+/// Reranker backed by a hosted reranking API.
+///
+/// Jina and Cohere share the same request/response shape:
+/// `{model, query, documents}` -> `{results: [{index, relevance_score}]}`.
+#[derive(Debug)]
+pub struct HttpReranker {
+    client: reqwest::Client,
+    endpoint: String,
+    api_key: String,
+    model: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RerankResponse {
+    results: Vec<RerankResultItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RerankResultItem {
+    index: usize,
+    relevance_score: f32,
+}
+
+impl HttpReranker {
+    /// Jina reranking API endpoint.
+    const JINA_URL: &'static str = "https://api.jina.ai/v1/rerank";
+    /// Cohere reranking API endpoint.
+    const COHERE_URL: &'static str = "https://api.cohere.com/v2/rerank";
+
+    /// Create a reranker for an arbitrary compatible endpoint.
+    pub fn new(
+        endpoint: impl Into<String>,
+        api_key: impl Into<String>,
+        model: impl Into<String>,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.into(),
+            api_key: api_key.into(),
+            model: model.into(),
+        }
+    }
+
+    /// Create a Jina reranker.
+    pub fn jina(api_key: impl Into<String>, model: impl Into<String>) -> Self {
+        Self::new(Self::JINA_URL, api_key, model)
+    }
+
+    /// Create a Cohere reranker.
+    pub fn cohere(api_key: impl Into<String>, model: impl Into<String>) -> Self {
+        Self::new(Self::COHERE_URL, api_key, model)
+    }
+}
+
+#[async_trait]
+impl Reranker for HttpReranker {
+    async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        let body = serde_json::json!({
+            "model": self.model,
+            "query": query,
+            "documents": documents,
+            "top_n": documents.len(),
+        });
+
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .context("Reranking request failed")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Reranking API error ({}): {}", status, error_text));
+        }
+
+        let parsed: RerankResponse = response.json().await?;
+        let mut scores = vec![f32::MIN; documents.len()];
+        for item in parsed.results {
+            if let Some(score) = scores.get_mut(item.index) {
+                *score = item.relevance_score;
+            }
+        }
+        Ok(scores)
+    }
+}
+
+// =============================================================================
+// Registry
+// =============================================================================
+
+/// Lazily constructed rerankers, keyed by model name.
+///
+/// Shared across requests so local models are only loaded once.
+#[derive(Debug, Default)]
+pub struct RerankerRegistry {
+    rerankers: DashMap<String, Arc<dyn Reranker>>,
+    /// Reranking for KBs without settings of their own
+    default: Option<RerankerConfig>,
+}
+
+impl RerankerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rerank KBs that don't configure reranking with `config`.
+    pub fn with_default(mut self, config: Option<RerankerConfig>) -> Self {
+        self.default = config;
+        self
+    }
+
+    /// Register a reranker for a model name, replacing any existing one.
+    pub fn register(&self, model: impl Into<String>, reranker: Arc<dyn Reranker>) {
+        self.rerankers.insert(model.into(), reranker);
+    }
+
+    /// Get the reranker for a model, constructing it on first use.
+    pub fn get_or_create(&self, model: &str) -> Result<Arc<dyn Reranker>> {
+        self.cached(model, || {
+            let reranker: Arc<dyn Reranker> = if model.starts_with("jina-") {
+                let api_key = std::env::var("JINA_API_KEY")
+                    .context("JINA_API_KEY must be set to use Jina rerankers")?;
+                Arc::new(HttpReranker::jina(api_key, model))
+            } else if model.starts_with("rerank-") {
+                Arc::new(HttpReranker::cohere(cohere_api_key()?, model))
+            } else {
+                Arc::new(CrossEncoderReranker::new(model)?)
+            };
+            Ok(reranker)
+        })
+    }
+
+    /// Get the reranker for reranking settings, constructing it on first use.
+    pub fn create(&self, config: &RerankerConfig) -> Result<Arc<dyn Reranker>> {
+        let model = config.effective_model();
+        match config.kind {
+            None => self.get_or_create(model),
+            Some(RerankerKind::CrossEncoder) => {
+                self.cached(model, || Ok(Arc::new(CrossEncoderReranker::new(model)?)))
+            }
+            Some(RerankerKind::Cohere) => self.cached(model, || {
+                Ok(Arc::new(HttpReranker::cohere(cohere_api_key()?, model)))
+            }),
+            Some(RerankerKind::None) => Err(anyhow!("Reranking is disabled")),
+        }
+    }
+
+    /// Reranking settings in effect for a KB: its own, else the default.
+    ///
+    /// `None` if the KB's results are not reranked.
+    pub fn resolve<'a>(&'a self, config: Option<&'a RerankerConfig>) -> Option<&'a RerankerConfig> {
+        config
+            .or(self.default.as_ref())
+            .filter(|cfg| cfg.is_enabled())
+    }
+
+    /// Get the reranker for a KB's config, if reranking is enabled.
+    pub fn for_config(&self, config: Option<&RerankerConfig>) -> Result<Option<Arc<dyn Reranker>>> {
+        self.resolve(config).map(|cfg| self.create(cfg)).transpose()
+    }
+
+    fn cached(
+        &self,
+        model: &str,
+        build: impl FnOnce() -> Result<Arc<dyn Reranker>>,
+    ) -> Result<Arc<dyn Reranker>> {
+        if let Some(reranker) = self.rerankers.get(model) {
+            return Ok(Arc::clone(&reranker));
+        }
+        let reranker = build()?;
+        self.rerankers
+            .insert(model.to_string(), Arc::clone(&reranker));
+        Ok(reranker)
+    }
+}
+
+fn cohere_api_key() -> Result<String> {
+    std::env::var("COHERE_API_KEY").context("COHERE_API_KEY must be set to use Cohere rerankers")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VectorIndexConfig;
+    use crate::uar::domain::knowledge::{KbConfig, KnowledgeChunk};
+    use crate::uar::persistence::testing::InMemoryPersistence;
+
+    /// Scores by the fraction of query words present in the document.
+    #[derive(Debug)]
+    struct OverlapReranker;
+
+    #[async_trait]
+    impl Reranker for OverlapReranker {
+        async fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+            let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+            Ok(documents
+                .iter()
+                .map(|doc| {
+                    let doc = doc.to_lowercase();
+                    let hits = words.iter().filter(|w| doc.contains(w.as_str())).count();
+                    hits as f32 / words.len() as f32
+                })
+                .collect())
+        }
+    }
+
+    fn knowledge_match(content: &str, score: f32) -> KnowledgeMatch {
+        KnowledgeMatch {
+            chunk: KnowledgeChunk {
+                id: uuid::Uuid::new_v4(),
+                kb_id: "kb".to_string(),
+                document_id: None,
+                content: content.to_string(),
+                metadata: None,
+                embedding: vec![],
+                tenant_id: None,
+                created_at: chrono::Utc::now().to_rfc3339(),
+            },
+            score,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rerank_reorders_and_truncates() {
+        let matches = vec![
+            knowledge_match("unrelated text", 0.95),
+            knowledge_match("paris is the capital of france", 0.80),
+            knowledge_match("capital letters", 0.90),
+        ];
+
+        let reranked = rerank_matches(&OverlapReranker, "capital of france", matches, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(reranked.len(), 2);
+        assert_eq!(reranked[0].chunk.content, "paris is the capital of france");
+        assert_eq!(reranked[0].score, 1.0);
+    }
+
+    #[test]
+    fn test_models_without_local_build_are_refused() {
+        for model in LOCAL_MODELS {
+            assert!(CrossEncoderReranker::new(model).is_ok(), "{model}");
+        }
+        let ms_marco = RerankerConfig {
+            model: "cross-encoder/ms-marco-MiniLM-L-6-v2".to_string(),
+            ..RerankerConfig::default()
+        };
+        let error = check_config(&ms_marco).unwrap_err();
+        assert!(error.to_string().contains("ms-marco-MiniLM-L-6-v2"), "{error}");
+        assert!(CrossEncoderReranker::new(&ms_marco.model).is_err());
+
+        // Hosted and disabled rerankers aren't checked against local models
+        for config in [
+            RerankerConfig {
+                model: "rerank-v3.5".to_string(),
+                ..RerankerConfig::default()
+            },
+            RerankerConfig {
+                enabled: false,
+                ..ms_marco
+            },
+        ] {
+            assert!(check_config(&config).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_registry_uses_registered_reranker() {
+        let registry = RerankerRegistry::new();
+        registry.register("custom", Arc::new(OverlapReranker));
+
+        assert!(registry.get_or_create("custom").is_ok());
+        assert!(registry.get_or_create("not/a-real-model").is_err());
+
+        let disabled = RerankerConfig {
+            enabled: false,
+            ..RerankerConfig::default()
+        };
+        assert!(registry.for_config(Some(&disabled)).unwrap().is_none());
+        assert!(registry.for_config(None).unwrap().is_none());
+    }
+
+    fn knowledge_base(id: &str, rerank: Option<RerankerConfig>) -> KnowledgeBase {
+        KnowledgeBase {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: None,
+            config: KbConfig {
+                rerank,
+                ..KbConfig::default()
+            },
+            tenant_id: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_kb_with_reranker_is_reordered() {
+        let db = InMemoryPersistence::new();
+        let registry = RerankerRegistry::new();
+        registry.register("overlap", Arc::new(OverlapReranker));
+
+        let overlap = RerankerConfig {
+            model: "overlap".to_string(),
+            ..RerankerConfig::default()
+        };
+        let reranked_kb = knowledge_base("kb-reranked", Some(overlap));
+        let plain_kb = knowledge_base("kb-plain", None);
+        for kb in [&reranked_kb, &plain_kb] {
+            db.save_knowledge_base(kb).await.unwrap();
+            // The distractor sits closest to the query vector
+            for (content, embedding) in [
+                ("capital letters", vec![1.0, 0.0]),
+                ("paris is the capital of france", vec![0.8, 0.6]),
+            ] {
+                let mut chunk = knowledge_match(content, 0.0).chunk;
+                chunk.kb_id = kb.id.clone();
+                chunk.embedding = embedding;
+                db.save_chunk(&chunk).await.unwrap();
+            }
+        }
+
+        let query = "capital of france";
+        let query_vec = [1.0, 0.0];
+        // Candidates come from the chunk index just as from the store
+        let matcher = VectorMatcher::new(0.5).with_chunk_index(VectorIndexConfig {
+            hnsw_enabled: true,
+            ..VectorIndexConfig::default()
+        });
+        let reranked = search_knowledge_base(
+            &db,
+            &matcher,
+            &registry,
+            &reranked_kb,
+            query,
+            &query_vec,
+            10,
+            0.0,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let plain = search_knowledge_base(
+            &db,
+            &matcher,
+            &registry,
+            &plain_kb,
+            query,
+            &query_vec,
+            10,
+            0.0,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(reranked[0].chunk.content, "paris is the capital of france");
+        assert_eq!(reranked[0].chunk.kb_id, "kb-reranked");
+        assert_eq!(plain[0].chunk.content, "capital letters");
+        assert_eq!(plain.len(), 2);
+    }
+
+    #[test]
+    fn test_kb_settings_fall_back_to_default() {
+        let default = RerankerConfig {
+            model: "custom".to_string(),
+            ..RerankerConfig::default()
+        };
+        let registry = RerankerRegistry::new().with_default(Some(default.clone()));
+        assert_eq!(registry.resolve(None), Some(&default));
+
+        // A KB can opt out of the default
+        let opt_out = RerankerConfig {
+            kind: Some(RerankerKind::None),
+            ..RerankerConfig::default()
+        };
+        assert!(registry.resolve(Some(&opt_out)).is_none());
+
+        let cohere = RerankerConfig {
+            kind: Some(RerankerKind::Cohere),
+            ..RerankerConfig::default()
+        };
+        assert_eq!(cohere.effective_model(), RerankerKind::DEFAULT_COHERE_MODEL);
+    }
+}
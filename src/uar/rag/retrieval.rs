@@ -225,6 +225,7 @@ mod tests {
             content: content.to_string(),
             metadata: None,
             embedding: Vec::new(),
+            tenant_id: None,
             created_at: "2024-01-01".to_string(),
         }
     }
@@ -0,0 +1,199 @@
+//! Agent artifacts loaded from disk.
+//!
+//! `*.agent.yaml` files under the watched directories are imported into the
+//! persistence layer at startup and re-imported whenever they change. Files
+//! that don't parse or fail validation are skipped with a warning.
+
+use crate::uar::domain::artifact::{AGENT_FILE_SUFFIX, AgentArtifact, AgentArtifactValidator};
+use crate::uar::persistence::PersistenceLayer;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::fs;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+/// Directories scanned for agent artifacts by default.
+pub const DEFAULT_AGENT_DIRS: &[&str] = &["agents", "skills"];
+
+/// Interval between scans for new or modified agent files.
+pub const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Whether a path names an agent artifact file.
+pub fn is_agent_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.ends_with(AGENT_FILE_SUFFIX))
+}
+
+/// Parse an agent artifact file, validate it and save it.
+pub async fn import_agent_file(
+    persistence: &dyn PersistenceLayer,
+    path: &Path,
+) -> anyhow::Result<AgentArtifact> {
+    let content = fs::read_to_string(path).await?;
+    let artifact = AgentArtifact::from_yaml(&content)?;
+    let errors = AgentArtifactValidator::new()
+        .with_persistence(persistence)
+        .with_tenant(artifact.tenant_id.as_deref())
+        .validate(&artifact)
+        .await;
+    if !errors.is_empty() {
+        let details: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        anyhow::bail!("Invalid agent artifact: {}", details.join("; "));
+    }
+    persistence.save_agent(&artifact).await?;
+    Ok(artifact)
+}
+
+/// Polling watcher that imports agent files when they appear or change.
+pub struct AgentFileWatcher {
+    persistence: Arc<dyn PersistenceLayer>,
+    dirs: Vec<PathBuf>,
+    // Last imported modification time per file
+    file_state: HashMap<PathBuf, SystemTime>,
+}
+
+impl std::fmt::Debug for AgentFileWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AgentFileWatcher")
+            .field("dirs", &self.dirs)
+            .field("tracked_files", &self.file_state.len())
+            .finish()
+    }
+}
+
+impl AgentFileWatcher {
+    pub fn new(persistence: Arc<dyn PersistenceLayer>, dirs: Vec<PathBuf>) -> Self {
+        Self {
+            persistence,
+            dirs,
+            file_state: HashMap::new(),
+        }
+    }
+
+    /// Import every agent file that is new or modified since the last scan.
+    ///
+    /// Returns the number of artifacts imported. Files that fail to parse or
+    /// validate are skipped with a warning and retried once they change
+    /// again.
+    pub async fn scan(&mut self) -> usize {
+        let mut imported = 0;
+
+        for dir in &self.dirs {
+            if !dir.exists() {
+                continue;
+            }
+
+            for entry in WalkDir::new(dir)
+                .follow_links(true)
+                .into_iter()
+                .filter_map(|e| e.ok())
+            {
+                let path = entry.path();
+                if !entry.file_type().is_file() || !is_agent_file(path) {
+                    continue;
+                }
+                let Some(modified) = entry.metadata().ok().and_then(|m| m.modified().ok()) else {
+                    continue;
+                };
+                if self
+                    .file_state
+                    .get(path)
+                    .is_some_and(|last_mod| modified <= *last_mod)
+                {
+                    continue;
+                }
+
+                match import_agent_file(self.persistence.as_ref(), path).await {
+                    Ok(artifact) => {
+                        info!("Imported agent '{}' from {:?}", artifact.id, path);
+                        imported += 1;
+                    }
+                    Err(e) => warn!("Skipping agent file {:?}: {:#}", path, e),
+                }
+                // Record failures too, so a broken file isn't re-parsed every tick
+                self.file_state.insert(path.to_path_buf(), modified);
+            }
+        }
+
+        imported
+    }
+
+    /// Keep rescanning every `interval` in the background.
+    pub fn spawn(mut self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                self.scan().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::persistence::testing::InMemoryPersistence;
+
+    #[test]
+    fn test_is_agent_file() {
+        assert!(is_agent_file(Path::new("agents/research.agent.yaml")));
+        assert!(!is_agent_file(Path::new("agents/research.yaml")));
+        assert!(!is_agent_file(Path::new("skills/SKILL.md")));
+    }
+
+    #[tokio::test]
+    async fn test_scan_imports_new_and_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("helper.agent.yaml");
+
+        let mut artifact = crate::uar::defaults::default_agent();
+        artifact.id = "helper".to_string();
+        std::fs::write(&path, artifact.to_yaml().unwrap()).unwrap();
+        std::fs::write(dir.path().join("notes.yaml"), "not: an agent").unwrap();
+
+        let db = Arc::new(InMemoryPersistence::new());
+        let mut watcher = AgentFileWatcher::new(db.clone(), vec![dir.path().to_path_buf()]);
+        assert_eq!(watcher.scan().await, 1);
+        // Unchanged files are not re-imported
+        assert_eq!(watcher.scan().await, 0);
+
+        // Bump the mtime explicitly; coarse filesystem clocks may not tick
+        artifact.metadata.title = "Helper v2".to_string();
+        std::fs::write(&path, artifact.to_yaml().unwrap()).unwrap();
+        let later = SystemTime::now() + Duration::from_secs(5);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert_eq!(watcher.scan().await, 1);
+
+        let saved = db.load_agent("helper", None).await.unwrap().unwrap();
+        assert_eq!(saved.metadata.title, "Helper v2");
+    }
+
+    #[tokio::test]
+    async fn test_scan_skips_invalid_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut artifact = crate::uar::defaults::default_agent();
+        artifact.id = "broken".to_string();
+        artifact.prompt.system = String::new();
+        std::fs::write(dir.path().join("broken.agent.yaml"), artifact.to_yaml().unwrap()).unwrap();
+
+        let db = Arc::new(InMemoryPersistence::new());
+        let mut watcher = AgentFileWatcher::new(db.clone(), vec![dir.path().to_path_buf()]);
+        assert_eq!(watcher.scan().await, 0);
+        assert!(db.load_agent("broken", None).await.unwrap().is_none());
+
+        let err = import_agent_file(db.as_ref(), &dir.path().join("broken.agent.yaml"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("prompt.system"), "{err}");
+    }
+}
@@ -0,0 +1,59 @@
+//! Agent chains: a sequence of agents where each step's output becomes the
+//! next step's input.
+//!
+//! Execution lives in [`RunManager::start_chained_run`](super::manager::RunManager::start_chained_run);
+//! this module renders the per-step input templates.
+
+use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use std::sync::OnceLock;
+
+/// Render a step's `input_template`.
+///
+/// Output is not HTML-escaped: the result is a prompt, not markup.
+pub fn render_step_input(
+    template: &str,
+    previous_output: &str,
+    run_id: &str,
+    session_id: &str,
+) -> Result<String> {
+    static ENGINE: OnceLock<Handlebars<'static>> = OnceLock::new();
+    let engine = ENGINE.get_or_init(|| {
+        let mut hb = Handlebars::new();
+        hb.register_escape_fn(handlebars::no_escape);
+        hb
+    });
+
+    engine
+        .render_template(
+            template,
+            &serde_json::json!({
+                "previous_output": previous_output,
+                "run_id": run_id,
+                "session_id": session_id,
+            }),
+        )
+        .context("Failed to render chain step input template")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_variables() {
+        let input = render_step_input(
+            "Summarize for {{ session_id }} ({{run_id}}):\n{{ previous_output }}",
+            "a <b> & c",
+            "run-1",
+            "sess-1",
+        )
+        .unwrap();
+        assert_eq!(input, "Summarize for sess-1 (run-1):\na <b> & c");
+    }
+
+    #[test]
+    fn test_invalid_template() {
+        assert!(render_step_input("{{#if}}", "", "run-1", "sess-1").is_err());
+    }
+}
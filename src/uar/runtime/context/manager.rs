@@ -1,8 +1,15 @@
+use super::model_limits;
 use super::token_service::TokenService;
 use crate::llm::{Message, MessageRole};
 use crate::uar::domain::context::{ContextAction, ContextConfig, ContextStrategy};
+use std::collections::HashSet;
+use tiktoken_rs::CoreBPE;
 use tracing::{info, warn};
 
+/// Tokens reserved for the model's reply when deriving the budget from the
+/// context window.
+const OUTPUT_RESERVE: usize = 1000;
+
 #[derive(Debug)]
 pub struct ContextManager {
     config: ContextConfig,
@@ -13,6 +20,21 @@ impl ContextManager {
         Self { config }
     }
 
+    /// Apply context management for `model`, using its context window and
+    /// tokenizer.
+    pub async fn apply_for_model(
+        &self,
+        messages: Vec<Message>,
+        model: &str,
+    ) -> (Vec<Message>, Option<ContextAction>) {
+        self.apply_with(
+            messages,
+            model_limits::context_window(model),
+            TokenService::tokenizer(model),
+        )
+        .await
+    }
+
     /// Check if context management is needed and apply the configured strategy.
     /// Returns the (potentially modified) messages and an action report if changes were made.
     pub async fn apply(
@@ -20,12 +42,22 @@ impl ContextManager {
         messages: Vec<Message>,
         model_token_limit: usize,
     ) -> (Vec<Message>, Option<ContextAction>) {
-        let current_tokens = TokenService::estimate_messages(&messages);
+        self.apply_with(messages, model_token_limit, TokenService::default_tokenizer())
+            .await
+    }
+
+    async fn apply_with(
+        &self,
+        messages: Vec<Message>,
+        model_token_limit: usize,
+        bpe: &CoreBPE,
+    ) -> (Vec<Message>, Option<ContextAction>) {
+        let current_tokens = TokenService::count_messages(bpe, &messages);
         // Use configured max or model limit - buffer (e.g. 1000 tokens for output)
         let effective_max = self
             .config
             .max_tokens
-            .unwrap_or(model_token_limit.saturating_sub(1000));
+            .unwrap_or(model_token_limit.saturating_sub(OUTPUT_RESERVE));
         let threshold = (effective_max as f32 * self.config.trigger_threshold) as usize;
 
         if current_tokens <= threshold {
@@ -39,11 +71,11 @@ impl ContextManager {
 
         match self.config.strategy {
             ContextStrategy::SlidingWindow => {
-                self.apply_sliding_window(messages, effective_max, current_tokens)
+                self.apply_sliding_window(messages, effective_max, current_tokens, bpe)
                     .await
             }
             ContextStrategy::KeepFirstLast => {
-                self.apply_keep_first_last(messages, effective_max, current_tokens)
+                self.apply_keep_first_last(messages, effective_max, current_tokens, bpe)
                     .await
             }
             ContextStrategy::ProgressiveSummarization => {
@@ -51,57 +83,84 @@ impl ContextManager {
                 warn!(
                     "ProgressiveSummarization not yet fully wired, falling back to KeepFirstLast"
                 );
-                self.apply_keep_first_last(messages, effective_max, current_tokens)
+                self.apply_keep_first_last(messages, effective_max, current_tokens, bpe)
                     .await
             }
             _ => (messages, None),
         }
     }
 
+    /// Drop the oldest non-system messages until the rest fit `token_budget`.
+    ///
+    /// System messages and the latest user turn are always kept, even if
+    /// they alone exceed the budget.
     async fn apply_sliding_window(
         &self,
         messages: Vec<Message>,
         token_budget: usize,
         original_tokens: usize,
+        bpe: &CoreBPE,
     ) -> (Vec<Message>, Option<ContextAction>) {
-        let mut budget = token_budget;
-        let mut final_list = Vec::new();
-
-        // 1. Preserve System Prompt
-        let system_msg = messages
-            .iter()
-            .find(|m| m.role == MessageRole::System)
-            .cloned();
-
-        if let Some(sys) = &system_msg {
-            let t = TokenService::estimate_string(sys.content.as_text().unwrap_or("")) + 3;
-            budget = budget.saturating_sub(t);
-            final_list.push(sys.clone());
+        let last_user = messages.iter().rposition(|m| m.role == MessageRole::User);
+        let mut keep = vec![false; messages.len()];
+        let mut used = 3; // reply priming
+
+        // 1. Pinned: system prompt(s) and the latest user turn
+        for (i, msg) in messages.iter().enumerate() {
+            if msg.role == MessageRole::System || Some(i) == last_user {
+                keep[i] = true;
+                used += TokenService::count_message(bpe, msg);
+            }
         }
+        let mut budget = token_budget.saturating_sub(used);
 
-        // 2. Keep recent messages within remaining budget
-        let mut tail = Vec::new();
-        // Skip system message in reverse iteration if we already handled it
-        for msg in messages.iter().rev() {
-            if msg.role == MessageRole::System {
+        // 2. Keep recent messages within remaining budget, newest first
+        for (i, msg) in messages.iter().enumerate().rev() {
+            if keep[i] {
                 continue;
             }
-
-            let t = TokenService::estimate_string(msg.content.as_text().unwrap_or("")) + 3;
+            let t = TokenService::count_message(bpe, msg);
             if t <= budget {
-                tail.push(msg.clone());
+                keep[i] = true;
                 budget -= t;
             } else {
-                break; // Stop once we hit limit
+                break; // Everything older is dropped
             }
         }
-        tail.reverse();
-        final_list.extend(tail);
 
-        let new_len = final_list.len();
-        let removed_count = messages.len() - new_len;
+        // Honour max_messages (pinned messages always stay)
+        if let Some(max_messages) = self.config.max_messages {
+            let mut excess = keep.iter().filter(|k| **k).count().saturating_sub(max_messages);
+            for (i, msg) in messages.iter().enumerate() {
+                if excess == 0 {
+                    break;
+                }
+                if keep[i] && msg.role != MessageRole::System && Some(i) != last_user {
+                    keep[i] = false;
+                    excess -= 1;
+                }
+            }
+        }
+
+        drop_orphaned_tool_results(&messages, &mut keep);
+
+        let final_list: Vec<Message> = messages
+            .iter()
+            .zip(&keep)
+            .filter(|(_, k)| **k)
+            .map(|(m, _)| m.clone())
+            .collect();
+
+        let removed_count = messages.len() - final_list.len();
         let tokens_saved =
-            original_tokens.saturating_sub(TokenService::estimate_messages(&final_list));
+            original_tokens.saturating_sub(TokenService::count_messages(bpe, &final_list));
+
+        info!(
+            "Sliding window dropped {} of {} messages ({} tokens)",
+            removed_count,
+            messages.len(),
+            tokens_saved
+        );
 
         (
             final_list,
@@ -109,86 +168,82 @@ impl ContextManager {
                 strategy: ContextStrategy::SlidingWindow,
                 messages_removed: removed_count,
                 tokens_saved,
-                was_applied: true,
+                was_applied: removed_count > 0,
                 summary_generated: false,
             }),
         )
     }
 
+    /// Keep the opening of the conversation and as many of its latest
+    /// messages as fit `token_budget`, dropping the middle.
+    ///
+    /// System messages and the latest user turn are always kept, even if
+    /// they alone exceed the budget; the first other message is kept when
+    /// it fits.
     async fn apply_keep_first_last(
         &self,
         messages: Vec<Message>,
         token_budget: usize,
         original_tokens: usize,
+        bpe: &CoreBPE,
     ) -> (Vec<Message>, Option<ContextAction>) {
-        // Simple implementation: Keep System + First User + Last N fit
-
-        // 1. Identify critical head messages
-        let mut head = Vec::new();
-        let mut budget = token_budget;
-
-        // Keep all system messages
-        let mut msg_iter = messages.iter().enumerate();
-
-        while let Some((_idx, msg)) = msg_iter.next() {
-            if msg.role == MessageRole::System {
-                let t = TokenService::estimate_string(msg.content.as_text().unwrap_or("")) + 3;
-                if t < budget {
-                    head.push(msg.clone());
-                    budget -= t;
-                }
-            } else {
-                // First non-system (User)
-                // Keep it if budget allows and strategy implies keeping "First"
-                // Assumption: "KeepFirstLast" usually implies keeping the very first prompt.
-                let t = TokenService::estimate_string(msg.content.as_text().unwrap_or("")) + 3;
-                if t < budget {
-                    head.push(msg.clone());
-                    budget -= t;
-                }
-                break;
+        let last_user = messages.iter().rposition(|m| m.role == MessageRole::User);
+        let mut keep = vec![false; messages.len()];
+        let mut used = 3; // reply priming
+
+        // 1. Pinned: system prompt(s) and the latest user turn
+        for (i, msg) in messages.iter().enumerate() {
+            if msg.role == MessageRole::System || Some(i) == last_user {
+                keep[i] = true;
+                used += TokenService::count_message(bpe, msg);
             }
         }
+        let mut budget = token_budget.saturating_sub(used);
 
-        // Keep last messages
-        let mut tail = Vec::new();
-        for msg in messages.iter().rev() {
-            // How to avoid re-adding head messages?
-            // Simple check: if message content/id matches head?
-            // Or just ensure we don't overlap indices.
-            // But we don't have indices in the loop easily.
-            // Simplified: Head is usually small (1-2 msgs). Tail is greedy.
-            // We just ensure we don't duplicate logic.
-            // If message is System, likely in head.
-            if msg.role == MessageRole::System {
-                continue;
+        // 2. The first message of the conversation proper
+        if let Some(first) = messages.iter().position(|m| m.role != MessageRole::System)
+            && !keep[first]
+        {
+            let t = TokenService::count_message(bpe, &messages[first]);
+            if t <= budget {
+                keep[first] = true;
+                budget -= t;
             }
+        }
 
-            // Check if this message is already in head (by content - imperfect but works for now)
-            if head
-                .iter()
-                .any(|h| h.content == msg.content && h.role == msg.role)
-            {
+        // 3. Recent messages within remaining budget, newest first
+        for (i, msg) in messages.iter().enumerate().rev() {
+            if keep[i] {
                 continue;
             }
-
-            let t = TokenService::estimate_string(msg.content.as_text().unwrap_or("")) + 3;
+            let t = TokenService::count_message(bpe, msg);
             if t <= budget {
-                tail.push(msg.clone());
+                keep[i] = true;
                 budget -= t;
             } else {
-                break;
+                break; // Everything older is dropped, but the first message
             }
         }
-        tail.reverse();
 
-        let mut final_list = head;
-        final_list.extend(tail);
+        drop_orphaned_tool_results(&messages, &mut keep);
+
+        let final_list: Vec<Message> = messages
+            .iter()
+            .zip(&keep)
+            .filter(|(_, k)| **k)
+            .map(|(m, _)| m.clone())
+            .collect();
 
-        let new_len = final_list.len();
-        let removed_count = messages.len() - new_len;
+        let removed_count = messages.len() - final_list.len();
         let tokens_saved =
-            original_tokens.saturating_sub(TokenService::estimate_messages(&final_list));
+            original_tokens.saturating_sub(TokenService::count_messages(bpe, &final_list));
+
+        info!(
+            "Keep first/last dropped {} of {} messages ({} tokens)",
+            removed_count,
+            messages.len(),
+            tokens_saved
+        );
 
         (
             final_list,
@@ -196,13 +251,40 @@ impl ContextManager {
                 strategy: ContextStrategy::KeepFirstLast,
                 messages_removed: removed_count,
                 tokens_saved,
-                was_applied: true,
+                was_applied: removed_count > 0,
                 summary_generated: false,
             }),
         )
     }
 }
 
+/// Unkeep tool results whose assistant tool-call message was dropped.
+///
+/// Providers reject a `tool` message that doesn't follow the assistant
+/// message carrying its `tool_calls`.
+fn drop_orphaned_tool_results(messages: &[Message], keep: &mut [bool]) {
+    let kept_call_ids: HashSet<&str> = messages
+        .iter()
+        .zip(keep.iter())
+        .filter(|(_, k)| **k)
+        .filter_map(|(m, _)| m.tool_calls.as_ref())
+        .flatten()
+        .map(|call| call.id.as_str())
+        .collect();
+
+    for (msg, k) in messages.iter().zip(keep.iter_mut()) {
+        if *k && msg.role == MessageRole::Tool {
+            let answered = msg
+                .tool_call_id
+                .as_deref()
+                .is_some_and(|id| kept_call_ids.contains(id));
+            if !answered {
+                *k = false;
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -296,4 +378,177 @@ mod tests {
         // Ensure some middle messages are gone
         assert!(outcome.len() < 23);
     }
+
+    #[tokio::test]
+    async fn test_keep_first_last_pins_system_and_latest_user() {
+        let huge = "word ".repeat(200);
+        let messages = vec![
+            make_msg("System", MessageRole::System),
+            make_msg("Old question", MessageRole::User),
+            make_msg("Old answer", MessageRole::Assistant),
+            make_msg(&huge, MessageRole::User),
+        ];
+
+        // Summarization falls back to the same strategy
+        for strategy in [
+            ContextStrategy::KeepFirstLast,
+            ContextStrategy::ProgressiveSummarization,
+        ] {
+            let manager = ContextManager::new(ContextConfig {
+                strategy: strategy.clone(),
+                max_tokens: Some(50),
+                trigger_threshold: 0.5,
+                ..Default::default()
+            });
+            let (optimized, action) = manager.apply(messages.clone(), 1000).await;
+
+            // The latest user turn alone exceeds the budget but is kept
+            assert_eq!(optimized.len(), 2, "{strategy:?}");
+            assert_eq!(optimized[0].role, MessageRole::System);
+            assert_eq!(optimized[1].content.as_text().unwrap(), huge);
+            assert_eq!(action.unwrap().messages_removed, 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keep_first_last_drops_orphaned_tool_results() {
+        use crate::llm::{ToolCall, ToolCallFunction};
+
+        let mut call = make_msg(&"plan ".repeat(40), MessageRole::Assistant);
+        call.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: ToolCallFunction {
+                name: "search".to_string(),
+                arguments: "{}".to_string(),
+            },
+        }]);
+        let mut result = make_msg("result", MessageRole::Tool);
+        result.tool_call_id = Some("call_1".to_string());
+        let messages = vec![
+            make_msg("System", MessageRole::System),
+            make_msg("Find it", MessageRole::User),
+            call,
+            result,
+            make_msg("Found it", MessageRole::Assistant),
+            make_msg("Thanks", MessageRole::User),
+        ];
+
+        for strategy in [
+            ContextStrategy::KeepFirstLast,
+            ContextStrategy::ProgressiveSummarization,
+        ] {
+            let manager = ContextManager::new(ContextConfig {
+                strategy: strategy.clone(),
+                max_tokens: Some(60),
+                trigger_threshold: 0.1,
+                ..Default::default()
+            });
+            let (optimized, _) = manager.apply(messages.clone(), 1000).await;
+
+            // The tool call didn't fit, so its result must not dangle
+            assert!(optimized.iter().all(|m| m.role != MessageRole::Tool), "{strategy:?}");
+            let texts: Vec<&str> = optimized
+                .iter()
+                .map(|m| m.content.as_text().unwrap())
+                .collect();
+            assert_eq!(texts, ["System", "Find it", "Found it", "Thanks"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_latest_user_turn_never_dropped() {
+        let config = ContextConfig {
+            strategy: ContextStrategy::SlidingWindow,
+            max_tokens: Some(50),
+            trigger_threshold: 0.5,
+            ..Default::default()
+        };
+        let manager = ContextManager::new(config);
+
+        let huge = "word ".repeat(200);
+        let messages = vec![
+            make_msg("System", MessageRole::System),
+            make_msg("Old question", MessageRole::User),
+            make_msg("Old answer", MessageRole::Assistant),
+            make_msg(&huge, MessageRole::User),
+        ];
+
+        let (optimized, action) = manager.apply(messages, 1000).await;
+
+        // The latest user turn alone exceeds the budget but is kept
+        assert_eq!(optimized.len(), 2);
+        assert_eq!(optimized[0].role, MessageRole::System);
+        assert_eq!(optimized[1].content.as_text().unwrap(), huge);
+        assert_eq!(action.unwrap().messages_removed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_tool_results_dropped() {
+        use crate::llm::{ToolCall, ToolCallFunction};
+
+        let config = ContextConfig {
+            strategy: ContextStrategy::SlidingWindow,
+            max_tokens: Some(60),
+            trigger_threshold: 0.1,
+            ..Default::default()
+        };
+        let manager = ContextManager::new(config);
+
+        let mut call = make_msg(&"plan ".repeat(40), MessageRole::Assistant);
+        call.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: ToolCallFunction {
+                name: "search".to_string(),
+                arguments: "{}".to_string(),
+            },
+        }]);
+        let mut result = make_msg("result", MessageRole::Tool);
+        result.tool_call_id = Some("call_1".to_string());
+
+        let messages = vec![
+            make_msg("System", MessageRole::System),
+            make_msg("Find it", MessageRole::User),
+            call,
+            result,
+            make_msg("Found it", MessageRole::Assistant),
+            make_msg("Thanks", MessageRole::User),
+        ];
+
+        let (optimized, _) = manager.apply(messages, 1000).await;
+
+        // The tool call didn't fit, so its result must not dangle
+        assert!(optimized.iter().all(|m| m.role != MessageRole::Tool));
+        assert_eq!(
+            optimized.last().unwrap().content.as_text().unwrap(),
+            "Thanks"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_budget_derived_from_model() {
+        let manager = ContextManager::new(ContextConfig::default());
+
+        let filler = "token ".repeat(100);
+        let mut messages = vec![make_msg("System", MessageRole::System)];
+        for _ in 0..100 {
+            messages.push(make_msg(&filler, MessageRole::User));
+            messages.push(make_msg(&filler, MessageRole::Assistant));
+        }
+        messages.push(make_msg("Latest", MessageRole::User));
+
+        // ~20k tokens: fits gpt-4o's window, not gpt-4's
+        let (_, action) = manager.apply_for_model(messages.clone(), "gpt-4o").await;
+        assert!(action.is_none());
+
+        let (optimized, action) = manager.apply_for_model(messages, "gpt-4").await;
+        assert!(action.unwrap().messages_removed > 0);
+        let bpe = TokenService::tokenizer("gpt-4");
+        assert!(TokenService::count_messages(bpe, &optimized) <= 8_192 - OUTPUT_RESERVE);
+        assert_eq!(
+            optimized.last().unwrap().content.as_text().unwrap(),
+            "Latest"
+        );
+    }
 }
@@ -1,2 +1,4 @@
 pub mod manager;
+pub mod model_limits;
+pub mod summarizer;
 pub mod token_service;
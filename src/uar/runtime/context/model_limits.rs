@@ -0,0 +1,96 @@
+//! Context window sizes of known models.
+
+/// Context window assumed for models missing from the table.
+///
+/// Deliberately small: trimming an unknown large-context model early is
+/// cheaper than a provider rejecting an oversized request.
+pub const DEFAULT_CONTEXT_WINDOW: usize = 8_192;
+
+/// Model name prefix -> context window in tokens.
+///
+/// Checked in order, so more specific prefixes come before shorter ones
+/// (`gpt-4o` before `gpt-4`).
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    // OpenAI
+    ("gpt-5", 400_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1-mini", 128_000),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+    // Anthropic
+    ("claude", 200_000),
+    // Google
+    ("gemini-1.5-pro", 2_097_152),
+    ("gemini", 1_048_576),
+    // Mistral
+    ("codestral", 256_000),
+    ("mistral-large", 128_000),
+    ("mistral-medium", 128_000),
+    ("mistral-small", 32_000),
+    // Open-weight models (as commonly served)
+    ("llama-3.1", 128_000),
+    ("llama-3.2", 128_000),
+    ("llama-3.3", 128_000),
+    ("llama3.1", 128_000),
+    ("llama3.2", 128_000),
+    ("llama3.3", 128_000),
+    ("llama-3", 8_192),
+    ("llama3", 8_192),
+    ("qwen2.5", 32_768),
+    ("deepseek", 64_000),
+];
+
+/// Context window of `model` in tokens.
+///
+/// Matching is case-insensitive and ignores a leading provider namespace
+/// (`openai/gpt-4o`, `anthropic.claude-3-5-sonnet`).
+pub fn context_window(model: &str) -> usize {
+    let model = model.to_ascii_lowercase();
+    // Drop "provider/" namespaces and ":tag" suffixes ("llama3.1:8b")
+    let name = model.rsplit('/').next().unwrap_or(&model);
+    let name = name.split(':').next().unwrap_or(name);
+    // Bedrock-style ids: "anthropic.claude-3-5-sonnet-20240620-v1:0"
+    let name = name.split_once('.').map_or(name, |(vendor, rest)| {
+        if vendor.chars().all(|c| c.is_ascii_alphabetic()) {
+            rest
+        } else {
+            name
+        }
+    });
+
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map_or(DEFAULT_CONTEXT_WINDOW, |(_, window)| *window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_models() {
+        assert_eq!(context_window("gpt-4o-mini"), 128_000);
+        assert_eq!(context_window("gpt-4"), 8_192);
+        assert_eq!(context_window("GPT-4.1-nano"), 1_047_576);
+        assert_eq!(context_window("claude-sonnet-4-20250514"), 200_000);
+    }
+
+    #[test]
+    fn test_provider_prefixes() {
+        assert_eq!(context_window("openai/gpt-4o"), 128_000);
+        assert_eq!(context_window("llama3.1:8b"), 128_000);
+        assert_eq!(context_window("anthropic.claude-3-5-sonnet-20240620-v1:0"), 200_000);
+    }
+
+    #[test]
+    fn test_unknown_model_uses_default() {
+        assert_eq!(context_window("my-finetune"), DEFAULT_CONTEXT_WINDOW);
+    }
+}
@@ -0,0 +1,305 @@
+//! LLM summarization of old conversation messages.
+//!
+//! Once a conversation grows past a number of messages, everything but the
+//! most recent ones is replaced by a short summary written by the run's own
+//! model. Summaries draw on a separate hourly token budget, so long sessions
+//! can't turn compression into a second bill; when it runs out, old messages
+//! are left to the context manager's truncation instead.
+
+use super::token_service::TokenService;
+use crate::llm::{Message, MessageContent, MessageRole, Orchestrator};
+use crate::uar::domain::context::{ContextAction, ContextStrategy};
+use async_trait::async_trait;
+use governor::{
+    Quota, RateLimiter,
+    clock::DefaultClock,
+    state::{InMemoryState, NotKeyed},
+};
+use std::fmt::Write as _;
+use std::num::NonZeroU32;
+use tracing::{info, warn};
+
+/// Marks the synthetic message that stands in for summarized messages.
+pub const SUMMARY_PREFIX: &str = "[SUMMARY]";
+
+const SUMMARY_PROMPT: &str = "Summarize the conversation so far in 2-3 sentences. \
+    Keep names, decisions and open questions; answer with the summary only.";
+
+/// When and how much to summarize.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SummarizerConfig {
+    /// Summarize once a conversation has more messages than this (0 = never)
+    pub threshold_messages: usize,
+    /// Most recent messages always kept verbatim
+    pub keep_recent: usize,
+    /// Input tokens all summaries may use per hour
+    pub tokens_per_hour: u32,
+}
+
+impl Default for SummarizerConfig {
+    fn default() -> Self {
+        Self {
+            threshold_messages: 20,
+            keep_recent: 10,
+            tokens_per_hour: 200_000,
+        }
+    }
+}
+
+/// Writes a summary of a transcript.
+#[async_trait]
+pub trait Summarize: Send + Sync {
+    async fn summarize(&self, messages: Vec<Message>) -> anyhow::Result<String>;
+}
+
+#[async_trait]
+impl Summarize for Orchestrator {
+    async fn summarize(&self, messages: Vec<Message>) -> anyhow::Result<String> {
+        self.chat_non_streaming(messages, None).await
+    }
+}
+
+type DirectLimiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
+
+/// Replaces the oldest messages of long conversations with a summary.
+#[derive(Debug)]
+pub struct ContextSummarizer {
+    config: SummarizerConfig,
+    /// Input tokens left for summaries this hour
+    quota: Option<DirectLimiter>,
+}
+
+impl ContextSummarizer {
+    pub fn new(config: SummarizerConfig) -> Self {
+        let quota = NonZeroU32::new(config.tokens_per_hour)
+            .map(|tokens| RateLimiter::direct(Quota::per_hour(tokens)));
+        Self { config, quota }
+    }
+
+    /// Summarize the oldest messages if the conversation is long enough.
+    ///
+    /// Leading system messages (the agent's prompt) are kept and don't count
+    /// towards the threshold. The result is those messages, one system
+    /// message holding the summary, and the `keep_recent` latest messages.
+    /// On failure or an exhausted budget, `messages` is returned unchanged.
+    pub async fn apply(
+        &self,
+        messages: Vec<Message>,
+        llm: &dyn Summarize,
+    ) -> (Vec<Message>, Option<ContextAction>) {
+        let start = messages
+            .iter()
+            .position(|m| m.role != MessageRole::System)
+            .unwrap_or(messages.len());
+        let conversation = messages.len() - start;
+        if self.config.threshold_messages == 0 || conversation <= self.config.threshold_messages {
+            return (messages, None);
+        }
+
+        // Tool results stay with the call they answer
+        let mut split = messages.len().saturating_sub(self.config.keep_recent).max(start);
+        while split > start && messages[split].role == MessageRole::Tool {
+            split -= 1;
+        }
+        if split - start < 2 {
+            return (messages, None);
+        }
+
+        let old = &messages[start..split];
+        let old_tokens = TokenService::estimate_messages(old);
+        if !self.take_budget(old_tokens) {
+            warn!(
+                "Summary budget exhausted, not summarizing {} messages ({} tokens)",
+                old.len(),
+                old_tokens
+            );
+            return (messages, None);
+        }
+
+        let request = vec![
+            text_message(MessageRole::System, SUMMARY_PROMPT.to_string()),
+            text_message(MessageRole::User, transcript(old)),
+        ];
+        let summary = match llm.summarize(request).await {
+            Ok(summary) if !summary.trim().is_empty() => summary,
+            Ok(_) => {
+                warn!("Summarization returned no text, keeping messages");
+                return (messages, None);
+            }
+            Err(e) => {
+                warn!("Summarization failed, keeping messages: {:#}", e);
+                return (messages, None);
+            }
+        };
+
+        let summary = text_message(
+            MessageRole::System,
+            format!("{SUMMARY_PREFIX} {}", summary.trim()),
+        );
+        let tokens_saved = old_tokens.saturating_sub(TokenService::estimate_messages(
+            std::slice::from_ref(&summary),
+        ));
+        let messages_removed = old.len();
+
+        let mut messages = messages;
+        messages.drain(start..split);
+        messages.insert(start, summary);
+        info!(
+            "Summarized {} old messages ({} tokens saved)",
+            messages_removed, tokens_saved
+        );
+
+        (
+            messages,
+            Some(ContextAction {
+                strategy: ContextStrategy::ProgressiveSummarization,
+                messages_removed,
+                tokens_saved,
+                was_applied: true,
+                summary_generated: true,
+            }),
+        )
+    }
+
+    /// Take `tokens` from this hour's budget.
+    fn take_budget(&self, tokens: usize) -> bool {
+        let Some(quota) = &self.quota else {
+            return false;
+        };
+        let tokens = u32::try_from(tokens).unwrap_or(u32::MAX);
+        NonZeroU32::new(tokens).is_none_or(|n| matches!(quota.check_n(n), Ok(Ok(()))))
+    }
+}
+
+impl Default for ContextSummarizer {
+    fn default() -> Self {
+        Self::new(SummarizerConfig::default())
+    }
+}
+
+fn text_message(role: MessageRole, text: String) -> Message {
+    Message {
+        role,
+        content: MessageContent::text(text),
+        tool_call_id: None,
+        tool_calls: None,
+    }
+}
+
+/// Messages as plain text, one speaker per paragraph.
+fn transcript(messages: &[Message]) -> String {
+    let mut out = String::new();
+    for message in messages {
+        let speaker = match message.role {
+            MessageRole::System => "System",
+            MessageRole::User => "User",
+            MessageRole::Assistant => "Assistant",
+            MessageRole::Tool => "Tool result",
+        };
+        let text = message.content.as_text().unwrap_or_default();
+        if !text.is_empty() {
+            let _ = writeln!(out, "{speaker}: {text}\n");
+        }
+        for call in message.tool_calls.iter().flatten() {
+            let _ = writeln!(
+                out,
+                "{speaker} called {}({})\n",
+                call.function.name, call.function.arguments
+            );
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Answers with a fixed summary and remembers what it was asked.
+    #[derive(Default)]
+    struct StubSummarizer {
+        requests: Mutex<Vec<Vec<Message>>>,
+    }
+
+    #[async_trait]
+    impl Summarize for StubSummarizer {
+        async fn summarize(&self, messages: Vec<Message>) -> anyhow::Result<String> {
+            self.requests.lock().unwrap().push(messages);
+            Ok("The user counted to 14.".to_string())
+        }
+    }
+
+    fn conversation(len: usize) -> Vec<Message> {
+        (0..len)
+            .map(|i| {
+                let role = if i % 2 == 0 {
+                    MessageRole::User
+                } else {
+                    MessageRole::Assistant
+                };
+                text_message(role, format!("Message {i}"))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_oldest_messages_replaced_by_summary() {
+        let summarizer = ContextSummarizer::default();
+        let llm = StubSummarizer::default();
+
+        let (messages, action) = summarizer.apply(conversation(25), &llm).await;
+
+        assert_eq!(messages.len(), summarizer.config.keep_recent + 1);
+        assert_eq!(messages[0].role, MessageRole::System);
+        assert_eq!(
+            messages[0].content.as_text().unwrap(),
+            "[SUMMARY] The user counted to 14."
+        );
+        assert_eq!(messages[1].content.as_text().unwrap(), "Message 15");
+        assert_eq!(messages.last().unwrap().content.as_text().unwrap(), "Message 24");
+
+        let action = action.unwrap();
+        assert_eq!(action.strategy, ContextStrategy::ProgressiveSummarization);
+        assert_eq!(action.messages_removed, 15);
+        assert!(action.summary_generated);
+
+        // Only the old messages were sent for summarization
+        let requests = llm.requests.lock().unwrap();
+        let transcript = requests[0][1].content.as_text().unwrap();
+        assert!(transcript.contains("User: Message 0"));
+        assert!(!transcript.contains("Message 15"));
+    }
+
+    #[tokio::test]
+    async fn test_short_conversation_and_agent_prompt_untouched() {
+        let summarizer = ContextSummarizer::default();
+        let llm = StubSummarizer::default();
+
+        let (messages, action) = summarizer.apply(conversation(20), &llm).await;
+        assert_eq!(messages.len(), 20);
+        assert!(action.is_none());
+
+        let mut long = vec![text_message(MessageRole::System, "You are terse.".to_string())];
+        long.extend(conversation(25));
+        let (messages, _) = summarizer.apply(long, &llm).await;
+        assert_eq!(messages.len(), summarizer.config.keep_recent + 2);
+        assert_eq!(messages[0].content.as_text().unwrap(), "You are terse.");
+        assert!(messages[1].content.as_text().unwrap().starts_with(SUMMARY_PREFIX));
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_budget_skips_summary() {
+        let summarizer = ContextSummarizer::new(SummarizerConfig {
+            tokens_per_hour: 10,
+            ..SummarizerConfig::default()
+        });
+        let llm = StubSummarizer::default();
+
+        let (messages, action) = summarizer.apply(conversation(25), &llm).await;
+
+        assert_eq!(messages.len(), 25);
+        assert!(action.is_none());
+        assert!(llm.requests.lock().unwrap().is_empty());
+    }
+}
@@ -1,45 +1,94 @@
 use crate::llm::Message;
-use tiktoken_rs::cl100k_base;
+use std::sync::OnceLock;
+use tiktoken_rs::{CoreBPE, cl100k_base, o200k_base};
+
+/// Per-message framing overhead (<|start|>{role}\n ... <|end|>\n).
+const MESSAGE_OVERHEAD: usize = 3;
+/// Every reply is primed with <|start|>assistant<|message|>.
+const REPLY_PRIMING: usize = 3;
 
 #[derive(Debug)]
 pub struct TokenService;
 
 impl TokenService {
+    /// Tokenizer for `model`.
+    ///
+    /// Models using OpenAI's newer `o200k_base` encoding (GPT-4o, GPT-4.1,
+    /// GPT-5, o-series) get it; everything else is approximated with
+    /// `cl100k_base`. Encoders are built once and shared.
+    pub fn tokenizer(model: &str) -> &'static CoreBPE {
+        static O200K: OnceLock<CoreBPE> = OnceLock::new();
+
+        let model = model.to_ascii_lowercase();
+        let name = model.rsplit('/').next().unwrap_or(&model);
+        let uses_o200k = ["gpt-4o", "gpt-4.1", "gpt-5", "o1", "o3", "o4"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix));
+
+        if uses_o200k {
+            O200K.get_or_init(|| o200k_base().expect("o200k_base encoding is bundled"))
+        } else {
+            Self::default_tokenizer()
+        }
+    }
+
+    /// The `cl100k_base` tokenizer (GPT-4/3.5 standard).
+    pub fn default_tokenizer() -> &'static CoreBPE {
+        static CL100K: OnceLock<CoreBPE> = OnceLock::new();
+        CL100K.get_or_init(|| cl100k_base().expect("cl100k_base encoding is bundled"))
+    }
+
     /// Estimate tokens for a string using cl100k_base (GPT-4/3.5 standard).
     pub fn estimate_string(content: &str) -> usize {
-        // Fallback to cl100k_base if model generic
-        let bpe = cl100k_base().unwrap();
-        bpe.encode_with_special_tokens(content).len()
+        Self::default_tokenizer()
+            .encode_with_special_tokens(content)
+            .len()
+    }
+
+    /// Count tokens of a single message, including its tool calls and
+    /// framing overhead.
+    pub fn count_message(bpe: &CoreBPE, message: &Message) -> usize {
+        let mut num_tokens = MESSAGE_OVERHEAD;
+        let content_str = message.content.as_text().unwrap_or("");
+        num_tokens += bpe.encode_with_special_tokens(content_str).len();
+
+        if let Some(calls) = &message.tool_calls {
+            for call in calls {
+                num_tokens += bpe.encode_with_special_tokens(&call.function.name).len();
+                num_tokens += bpe
+                    .encode_with_special_tokens(&call.function.arguments)
+                    .len();
+            }
+        }
+        num_tokens
+    }
+
+    /// Count tokens of a conversation with the given tokenizer.
+    pub fn count_messages(bpe: &CoreBPE, messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .map(|m| Self::count_message(bpe, m))
+            .sum::<usize>()
+            + REPLY_PRIMING
     }
 
     /// Estimate tokens for a list of messages.
     /// This follows OpenAI's chat format rules roughly (overhead per message).
     pub fn estimate_messages(messages: &[Message]) -> usize {
-        let bpe = cl100k_base().unwrap();
-        let mut num_tokens = 0;
-
-        // Every message follows <|start|>{role/name}\n{content}<|end|>\n
-        // Approximate overhead is 3 tokens per message logic + content
-        // We'll use a safe approximation.
-
-        for message in messages {
-            num_tokens += 3; // overhead
-            let content_str = message.content.as_text().unwrap_or("");
-            num_tokens += bpe.encode_with_special_tokens(content_str).len();
-
-            // If we had name field, +1 token.
-            // If tool calls, we need to count them too.
-            if let Some(calls) = &message.tool_calls {
-                for call in calls {
-                    num_tokens += bpe.encode_with_special_tokens(&call.function.name).len();
-                    num_tokens += bpe
-                        .encode_with_special_tokens(&call.function.arguments)
-                        .len();
-                }
-            }
-        }
+        Self::count_messages(Self::default_tokenizer(), messages)
+    }
+}
 
-        num_tokens += 3; // Every reply is primed with <|start|>assistant<|message|>
-        num_tokens
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenizer_selection() {
+        let o200k = TokenService::tokenizer("gpt-4o-mini");
+        let cl100k = TokenService::tokenizer("gpt-4");
+        assert!(!std::ptr::eq(o200k, cl100k));
+        assert!(std::ptr::eq(cl100k, TokenService::tokenizer("llama3")));
+        assert!(std::ptr::eq(o200k, TokenService::tokenizer("openai/gpt-4.1")));
     }
 }
@@ -0,0 +1,164 @@
+//! Sample-input tests of an agent.
+//!
+//! `POST /api/uar/agents/{id}/test` runs each case to completion and checks
+//! the answer and the tools the agent called, so an agent can be developed
+//! against a small suite of expected behaviours. Cases run a few at a time,
+//! each in its own ephemeral session, within one time budget for the suite.
+
+use std::time::Duration;
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::Instant;
+
+use super::manager::RunManager;
+use crate::uar::domain::{artifact::AgentArtifact, events::NormalizedEvent, runs::RunOptions};
+
+/// Most cases one request may run.
+pub const MAX_TEST_CASES: usize = 50;
+/// Cases run at the same time unless the request asks for fewer.
+pub const DEFAULT_TEST_CONCURRENCY: usize = 4;
+/// Most cases a request may run at the same time.
+pub const MAX_TEST_CONCURRENCY: usize = 8;
+/// Time all cases of a request may take together, unless it asks for less.
+pub const DEFAULT_SUITE_TIMEOUT: Duration = Duration::from_secs(120);
+/// Longest time budget a request may ask for.
+pub const MAX_SUITE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// An input and what the agent should do with it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AgentTestCase {
+    pub input: String,
+    /// Tool the agent must call, by its namespaced name (e.g. `search__web`)
+    #[serde(default)]
+    pub expect_tool: Option<String>,
+    /// Text the answer must contain, ignoring case
+    #[serde(default)]
+    pub expect_contains: Option<String>,
+}
+
+/// Outcome of one case.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct AgentTestResult {
+    pub input: String,
+    pub passed: bool,
+    /// The agent's answer
+    pub output: String,
+    /// Tools the agent called, in order
+    pub tool_calls: Vec<String>,
+    /// Why the case failed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<String>,
+}
+
+/// Run `cases` against `artifact`, at most `concurrency` at a time.
+///
+/// Cases still running when `timeout` is up are cancelled and fail, like
+/// the ones that didn't start by then. Results are in the order of `cases`.
+pub async fn run_agent_tests(
+    manager: &RunManager,
+    artifact: &AgentArtifact,
+    cases: Vec<AgentTestCase>,
+    tenant_id: Option<&str>,
+    concurrency: usize,
+    timeout: Duration,
+) -> Vec<AgentTestResult> {
+    let deadline = Instant::now() + timeout;
+    futures::stream::iter(cases)
+        .map(|case| run_case(manager, artifact, case, tenant_id, deadline))
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+async fn run_case(
+    manager: &RunManager,
+    artifact: &AgentArtifact,
+    case: AgentTestCase,
+    tenant_id: Option<&str>,
+    deadline: Instant,
+) -> AgentTestResult {
+    let mut run = CaseRun::default();
+    if Instant::now() >= deadline {
+        run.errors.push("Not run: the suite timed out".to_string());
+        return run.check(case);
+    }
+
+    // Test conversations aren't worth keeping
+    let session = manager.sessions().create_for_tenant(tenant_id, true);
+    let started = manager
+        .start_run_streaming(
+            artifact.clone(),
+            case.input.clone(),
+            Some(session.id().to_string()),
+            None,
+            tenant_id.map(str::to_string),
+            RunOptions::default(),
+        )
+        .await;
+    match started {
+        Ok((run_id, mut events)) => {
+            if tokio::time::timeout_at(deadline, run.collect(&mut events))
+                .await
+                .is_err()
+            {
+                manager.cancel_run(&run_id).await;
+                run.errors.push("Timed out".to_string());
+            }
+        }
+        Err(e) => run.errors.push(e.to_string()),
+    }
+    run.check(case)
+}
+
+/// What a case's run did.
+#[derive(Debug, Default)]
+struct CaseRun {
+    output: String,
+    tool_calls: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl CaseRun {
+    /// Record the run's events until it is done.
+    async fn collect(&mut self, events: &mut broadcast::Receiver<NormalizedEvent>) {
+        loop {
+            match events.recv().await {
+                Ok(NormalizedEvent::ChatDelta { text_delta, .. }) => {
+                    self.output.push_str(&text_delta);
+                }
+                Ok(NormalizedEvent::ToolStart { tool, .. }) => self.tool_calls.push(tool),
+                Ok(NormalizedEvent::Error { message, .. }) => self.errors.push(message),
+                Ok(NormalizedEvent::RunDone { .. }) | Err(RecvError::Closed) => return,
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Agent test missed {} events", skipped);
+                }
+            }
+        }
+    }
+
+    /// Compare the run with what `case` expects.
+    fn check(self, case: AgentTestCase) -> AgentTestResult {
+        let mut failures = self.errors;
+        if let Some(tool) = &case.expect_tool
+            && !self.tool_calls.contains(tool)
+        {
+            failures.push(format!("Expected a call to '{tool}'"));
+        }
+        if let Some(text) = &case.expect_contains
+            && !self.output.to_lowercase().contains(&text.to_lowercase())
+        {
+            failures.push(format!("Expected the output to contain '{text}'"));
+        }
+
+        AgentTestResult {
+            input: case.input,
+            passed: failures.is_empty(),
+            output: self.output,
+            tool_calls: self.tool_calls,
+            failures,
+        }
+    }
+}
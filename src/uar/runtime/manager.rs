@@ -1,31 +1,187 @@
-use crate::llm::{LlmSettings, Message, MessageRole, Orchestrator};
+use crate::llm::{
+    ApprovalGate, CircuitBreakerRegistry, DEFAULT_TOOL_APPROVAL_TIMEOUT, Deadline, LlmSettings,
+    Message, MessageRole, ModelAliases, Orchestrator, ResponseCache, ResumeGate,
+};
+use crate::mcp::cache::{CachedRegistry, McpRegistryCache};
 use crate::mcp::registry::McpRegistry;
-use crate::session::SessionStore;
+use crate::session::{AssistantTurn, SessionStore};
 use crate::uar::domain::{
-    artifact::AgentArtifact,
+    artifact::{
+        AgentArtifact, AgentArtifactValidator, ChainStep, ValidationError, request_mcp_errors,
+    },
     context::ContextConfig,
-    events::NormalizedEvent,
-    runs::{Run, RunStatus},
+    events::{NormalizedEvent, RunPhase},
+    knowledge::{KnowledgeBase, KnowledgeMatch},
+    runs::{
+        Run, RunLog, RunOptions, RunOutcome, RunRecord, RunStatus, RunUsage, RunView, TokenUsage,
+    },
 };
+use crate::uar::domain::tenant::visible_to;
+use crate::uar::persistence::PersistenceLayer;
+use crate::uar::rag::rerank::{self, RerankerRegistry};
+use crate::uar::runtime::agents::{AgentFileWatcher, DEFAULT_AGENT_DIRS, DEFAULT_WATCH_INTERVAL};
+use crate::uar::runtime::chain::render_step_input;
 use crate::uar::runtime::context::manager::ContextManager;
-use crate::uar::runtime::skills::SkillRegistry;
+use crate::uar::runtime::context::summarizer::{ContextSummarizer, SummarizerConfig};
+use crate::uar::runtime::partial_usage::PartialUsageCounter;
+use crate::uar::runtime::phases::PhaseTracker;
+use crate::uar::runtime::pricing::PricingTable;
+use crate::uar::runtime::prompt::{BuiltinVariables, TEMPLATE_ERROR_CODE, render_system_prompt};
+use crate::uar::runtime::replay::{DEFAULT_REPLAY_GRACE, RunEventSender, SequencedEvent};
+use crate::uar::runtime::skill_metrics::SkillMetricsMap;
+use crate::uar::runtime::skills::{
+    DEFAULT_MAX_SKILL_DEPTH, SKILL_DEPENDENCY_ERROR_CODE, SkillDisambiguator, SkillRegistry,
+};
+use crate::uar::runtime::webhook::{WebhookPayload, WebhookSender};
+use crate::uar::security::rate_limit::AgentRateLimiter;
+use anyhow::{anyhow, bail};
 use futures::StreamExt;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio::sync::{RwLock, broadcast};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
-use tracing::instrument;
+use tracing::{Instrument, instrument};
+
+/// Why a run could not be started.
+#[derive(Debug, thiserror::Error)]
+pub enum StartRunError {
+    /// The agent's `policy.rate_limit` is exhausted
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(String),
+    /// The session belongs to another tenant
+    #[error("Session '{0}' not found")]
+    SessionNotFound(String),
+    /// The artifact brings MCP servers its sender may not start
+    #[error("Invalid agent artifact: {0}")]
+    InvalidArtifact(String),
+}
+
+impl StartRunError {
+    /// HTTP status to answer a rejected run request with.
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        match self {
+            Self::RateLimited(_) => axum::http::StatusCode::TOO_MANY_REQUESTS,
+            Self::SessionNotFound(_) => axum::http::StatusCode::NOT_FOUND,
+            Self::InvalidArtifact(_) => axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    /// Code of the error event streamed for a rejected chain step.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::RateLimited(_) => "rate_limited",
+            Self::SessionNotFound(_) => "session_not_found",
+            Self::InvalidArtifact(_) => "invalid_artifact",
+        }
+    }
+}
+
+/// Why a run could not be resumed.
+#[derive(Debug, thiserror::Error)]
+pub enum ResumeRunError {
+    /// No active interactive run has this ID
+    #[error("Interactive run '{0}' not found")]
+    NotFound(String),
+    #[error("Run '{0}' is not paused")]
+    NotPaused(String),
+}
+
+impl ResumeRunError {
+    /// HTTP status to answer a rejected resume request with.
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        match self {
+            Self::NotFound(_) => axum::http::StatusCode::NOT_FOUND,
+            Self::NotPaused(_) => axum::http::StatusCode::CONFLICT,
+        }
+    }
+}
+
+/// Why a tool approval decision was rejected.
+#[derive(Debug, thiserror::Error)]
+pub enum ToolApprovalError {
+    /// No active run has this ID
+    #[error("Run '{0}' not found")]
+    RunNotFound(String),
+    #[error("Tool call '{0}' is not waiting for approval")]
+    NotPending(String),
+}
+
+impl ToolApprovalError {
+    /// HTTP status to answer a rejected decision with.
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        match self {
+            Self::RunNotFound(_) => axum::http::StatusCode::NOT_FOUND,
+            Self::NotPending(_) => axum::http::StatusCode::CONFLICT,
+        }
+    }
+}
+
+/// Heartbeat interval used unless configured otherwise.
+pub const DEFAULT_SSE_HEARTBEAT: Duration = Duration::from_secs(15);
+/// Keepalive interval used unless configured otherwise.
+pub const DEFAULT_SSE_KEEPALIVE: Duration = Duration::from_secs(15);
+/// Chunks retrieved for the prompt from KBs that aren't reranked.
+const RAG_TOP_K: usize = 3;
+/// Minimum similarity of retrieved chunks.
+const RAG_MIN_SCORE: f32 = 0.7;
 
 #[derive(Clone, Debug)]
 pub struct RunManager {
     // Map run_id -> (Run metadata, broadcast sender)
-    active_runs: Arc<RwLock<HashMap<String, (Run, broadcast::Sender<NormalizedEvent>)>>>,
+    active_runs: Arc<ActiveRuns>,
+    /// Gates of interactive runs, removed when the run finishes
+    resume_gates: Arc<RwLock<HashMap<String, Arc<ResumeGate>>>>,
+    /// Tool calls of each run waiting for approval, removed when the run finishes
+    approval_gates: Arc<RwLock<HashMap<String, Arc<ApprovalGate>>>>,
+    /// How long a tool call waits for approval before it is denied
+    tool_approval_timeout: Duration,
+    /// Longest a run may take, unless its request asks for less
+    run_timeout: Option<Duration>,
+    /// Cancellation of in-progress runs, removed when the run finishes
+    cancel_tokens: Arc<RwLock<HashMap<String, CancellationToken>>>,
     settings: LlmSettings,
+    /// Resolved to the provider's model when a run starts
+    model_aliases: Arc<ModelAliases>,
     global_mcp: Arc<McpRegistry>,
+    /// MCP servers of skills, shared by the runs matching them
+    skill_mcp: Arc<McpRegistryCache>,
     sessions: SessionStore,
     skills: Arc<RwLock<SkillRegistry>>,
+    /// Levels of skill dependencies resolved before a run fails
+    max_skill_depth: usize,
+    /// Picks one of the skills matching an input about equally well
+    skill_disambiguator: Option<Arc<SkillDisambiguator>>,
+    /// Usage of each skill, shared with the registry
+    skill_metrics: Arc<SkillMetricsMap>,
     vector_matcher: Arc<crate::uar::runtime::matching::VectorMatcher>,
     tag_matcher: Arc<crate::uar::runtime::matching::TagMatcher>,
     context_manager: Arc<ContextManager>,
+    summarizer: Arc<ContextSummarizer>,
+    pricing: Arc<PricingTable>,
+    agent_limiter: Arc<AgentRateLimiter>,
+    sse_heartbeat: Duration,
+    sse_keepalive: Duration,
+    /// How long a finished run's events stay available to reconnecting clients
+    replay_grace: Duration,
+    partial_usage_interval: Option<Duration>,
+    /// Stream `RunPhase` events as runs move between phases
+    run_phases: bool,
+    /// Shared by every run, so a failing LLM endpoint trips its circuit once
+    circuit_breakers: Option<Arc<CircuitBreakerRegistry>>,
+    /// Answers repeated deterministic LLM requests of every run
+    response_cache: Option<Arc<ResponseCache>>,
+    webhooks: WebhookSender,
+    /// Store each run's verbatim LLM exchange (needs persistence)
+    run_logging: bool,
+    /// Rerankers for knowledge bases retrieved from
+    rerankers: Arc<RerankerRegistry>,
+    /// Hosts the MCP servers of artifacts sent with a request may use
+    mcp_allowed_hosts: Arc<[String]>,
     // Persistence layer (optional)
     pub persistence: Option<Arc<dyn crate::uar::persistence::PersistenceLayer>>,
 }
@@ -44,48 +200,441 @@ impl RunManager {
             tracing::error!("Failed to initialize VectorMatcher: {:?}", e);
         }
 
+        // Import *.agent.yaml files and keep them in sync with the database
+        if let Some(db) = &persistence {
+            let dirs = DEFAULT_AGENT_DIRS.iter().map(PathBuf::from).collect();
+            let mut watcher = AgentFileWatcher::new(Arc::clone(db), dirs);
+            watcher.scan().await;
+            watcher.spawn(DEFAULT_WATCH_INTERVAL);
+        }
+
         let tag_matcher = Arc::new(crate::uar::runtime::matching::TagMatcher::new());
         let context_manager = Arc::new(ContextManager::new(ContextConfig::default()));
+        let skill_metrics = skills.read().await.metrics();
 
         Self {
             active_runs: Arc::new(RwLock::new(HashMap::new())),
+            resume_gates: Arc::new(RwLock::new(HashMap::new())),
+            approval_gates: Arc::new(RwLock::new(HashMap::new())),
+            tool_approval_timeout: DEFAULT_TOOL_APPROVAL_TIMEOUT,
+            run_timeout: None,
+            cancel_tokens: Arc::new(RwLock::new(HashMap::new())),
             settings,
+            model_aliases: Arc::new(ModelAliases::default()),
             global_mcp,
+            skill_mcp: Arc::new(McpRegistryCache::default()),
             sessions,
             skills,
+            max_skill_depth: DEFAULT_MAX_SKILL_DEPTH,
+            skill_disambiguator: None,
+            skill_metrics,
             vector_matcher,
             tag_matcher,
             context_manager,
+            summarizer: Arc::new(ContextSummarizer::default()),
+            pricing: Arc::new(PricingTable::default()),
+            agent_limiter: Arc::new(AgentRateLimiter::new()),
+            sse_heartbeat: DEFAULT_SSE_HEARTBEAT,
+            sse_keepalive: DEFAULT_SSE_KEEPALIVE,
+            replay_grace: DEFAULT_REPLAY_GRACE,
+            partial_usage_interval: None,
+            run_phases: false,
+            circuit_breakers: None,
+            response_cache: None,
+            webhooks: WebhookSender::default(),
+            run_logging: false,
+            rerankers: Arc::new(RerankerRegistry::new()),
+            mcp_allowed_hosts: Arc::from([]),
             persistence,
         }
     }
 
+    /// Price runs with `pricing` (runs are unpriced by default).
+    pub fn with_pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = Arc::new(pricing);
+        self
+    }
+
+    /// Let agents and run requests name models by the aliases in `aliases`.
+    pub fn with_model_aliases(mut self, aliases: ModelAliases) -> Self {
+        self.model_aliases = Arc::new(aliases);
+        self
+    }
+
+    /// Resolve skill dependencies at most `depth` levels deep.
+    pub fn with_max_skill_depth(mut self, depth: usize) -> Self {
+        self.max_skill_depth = depth;
+        self
+    }
+
+    /// Send a heartbeat event on run streams every `interval`.
+    pub fn with_sse_heartbeat(mut self, interval: Duration) -> Self {
+        self.sse_heartbeat = interval;
+        self
+    }
+
+    /// Send a keepalive comment on idle run streams every `interval`.
+    pub fn with_sse_keepalive(mut self, interval: Duration) -> Self {
+        self.sse_keepalive = interval;
+        self
+    }
+
+    /// Keep a finished run's events for `grace`, so SSE clients reconnecting
+    /// with `Last-Event-ID` can still catch up.
+    pub fn with_replay_grace(mut self, grace: Duration) -> Self {
+        self.replay_grace = grace;
+        self
+    }
+
+    /// Emit `PartialUsage` events at most once per `interval` while runs
+    /// generate (off by default).
+    pub fn with_partial_usage(mut self, interval: Duration) -> Self {
+        self.partial_usage_interval = Some(interval);
+        self
+    }
+
+    /// Emit `RunPhase` events as runs retrieve knowledge, match skills,
+    /// generate and execute tools (off by default).
+    pub fn with_run_phases(mut self, enabled: bool) -> Self {
+        self.run_phases = enabled;
+        self
+    }
+
+    /// Fail runs fast while their provider's circuit is open instead of
+    /// calling the LLM.
+    pub fn with_circuit_breakers(mut self, breakers: Arc<CircuitBreakerRegistry>) -> Self {
+        self.circuit_breakers = Some(breakers);
+        self
+    }
+
+    /// Replay the answer to an LLM request a run already made when its
+    /// agent's generation settings are deterministic.
+    pub fn with_response_cache(mut self, cache: Arc<ResponseCache>) -> Self {
+        self.response_cache = Some(cache);
+        self
+    }
+
+    /// Give each run webhook delivery attempt `timeout`.
+    pub fn with_webhook_timeout(mut self, timeout: Duration) -> Self {
+        self.webhooks = self.webhooks.with_timeout(timeout);
+        self
+    }
+
+    /// Deliver run webhooks to `hosts` even when they resolve to loopback or
+    /// private addresses (refused by default).
+    pub fn with_webhook_allowed_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.webhooks = self.webhooks.with_allowed_hosts(hosts);
+        self
+    }
+
+    /// Deny calls of tools requiring approval that nobody decided on
+    /// within `timeout`.
+    pub fn with_tool_approval_timeout(mut self, timeout: Duration) -> Self {
+        self.tool_approval_timeout = timeout;
+        self
+    }
+
+    /// Stop runs that take longer than `timeout` (no limit by default).
+    ///
+    /// Run requests can ask for a shorter one; LLM and tool calls only get
+    /// the time left.
+    pub fn with_run_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.run_timeout = timeout;
+        self
+    }
+
+    /// Store the messages sent to the LLM and its answer for every run
+    /// (off by default; needs persistence).
+    pub fn with_run_logging(mut self, enabled: bool) -> Self {
+        self.run_logging = enabled;
+        self
+    }
+
+    /// Summarize long conversations as `config` says.
+    pub fn with_summarizer(mut self, config: SummarizerConfig) -> Self {
+        self.summarizer = Arc::new(ContextSummarizer::new(config));
+        self
+    }
+
+    /// Inject only the skill `disambiguator` picks when several match a run's
+    /// input about equally well (all are injected by default).
+    pub fn with_skill_disambiguator(mut self, disambiguator: Arc<SkillDisambiguator>) -> Self {
+        self.skill_disambiguator = Some(disambiguator);
+        self
+    }
+
+    /// Start the MCP servers of skills through `cache`, so runs matching the
+    /// same skills share them.
+    pub fn with_skill_mcp_cache(mut self, cache: Arc<McpRegistryCache>) -> Self {
+        self.skill_mcp = cache;
+        self
+    }
+
+    /// Rerank retrieved knowledge with `rerankers` (shared with the search API).
+    pub fn with_rerankers(mut self, rerankers: Arc<RerankerRegistry>) -> Self {
+        self.rerankers = rerankers;
+        self
+    }
+
+    /// Let artifacts sent with a request use remote MCP servers on `hosts`
+    /// (none by default).
+    pub fn with_mcp_allowed_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.mcp_allowed_hosts = hosts.into();
+        self
+    }
+
+    /// Hosts the MCP servers of artifacts sent with a request may use.
+    pub fn mcp_allowed_hosts(&self) -> &[String] {
+        &self.mcp_allowed_hosts
+    }
+
+    /// Check `artifact`, sent with a request by `tenant_id`, before running
+    /// it: its MCP servers must be those of the stored agent it names, or
+    /// remote ones on [`Self::mcp_allowed_hosts`].
+    pub async fn check_request_artifact(
+        &self,
+        artifact: &AgentArtifact,
+        tenant_id: Option<&str>,
+    ) -> Result<(), StartRunError> {
+        let Some(mcp) = &artifact.tools.mcp else {
+            return Ok(());
+        };
+        if let Some(db) = &self.persistence
+            && let Ok(Some(stored)) = db.load_agent(&artifact.id, tenant_id).await
+            && stored.tools.mcp.as_ref() == Some(mcp)
+        {
+            return Ok(());
+        }
+        let errors = request_mcp_errors(artifact, &self.mcp_allowed_hosts);
+        if errors.is_empty() {
+            return Ok(());
+        }
+        let details: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        Err(StartRunError::InvalidArtifact(details.join("; ")))
+    }
+
+    /// Interval between heartbeats on run streams.
+    pub fn sse_heartbeat(&self) -> Duration {
+        self.sse_heartbeat
+    }
+
+    /// Idle time after which run streams send a keepalive comment.
+    pub fn sse_keepalive(&self) -> Duration {
+        self.sse_keepalive
+    }
+
+    /// Start a run of `artifact` and return its ID.
+    ///
+    /// The run sees the sessions and knowledge of `tenant_id` (plus shared
+    /// ones). Fails without starting anything if the agent's rate limit is
+    /// exhausted or the session belongs to another tenant.
+    pub async fn start_run(
+        &self,
+        artifact: AgentArtifact,
+        input: String,
+        session_id: Option<String>,
+        user_id: Option<String>,
+        tenant_id: Option<String>,
+        options: RunOptions,
+    ) -> Result<String, StartRunError> {
+        let (run_id, _events) = self
+            .start_run_streaming(artifact, input, session_id, user_id, tenant_id, options)
+            .await?;
+        Ok(run_id)
+    }
+
+    /// Like [`RunManager::start_run`], but also return a receiver subscribed
+    /// before the run starts executing, so none of its events are missed.
+    pub async fn start_run_streaming(
+        &self,
+        artifact: AgentArtifact,
+        input: String,
+        session_id: Option<String>,
+        user_id: Option<String>,
+        tenant_id: Option<String>,
+        options: RunOptions,
+    ) -> Result<(String, broadcast::Receiver<NormalizedEvent>), StartRunError> {
+        let run_id = Uuid::new_v4().to_string();
+        let events = self
+            .launch_run(run_id.clone(), artifact, input, session_id, user_id, tenant_id, options)
+            .await?;
+        Ok((run_id, events))
+    }
+
+    /// Fail a run that is registered but has not called the LLM yet: mark it
+    /// errored, notify its webhook and stream an error with `code`.
+    #[allow(clippy::too_many_arguments)]
+    async fn fail_prepared_run(
+        &self,
+        artifact: &AgentArtifact,
+        run_id: &str,
+        session_id: &str,
+        tx: RunEventSender,
+        code: &'static str,
+        message: String,
+        started: Instant,
+    ) {
+        tracing::warn!("{}", message);
+        self.finalize_run(run_id, RunOutcome::error(elapsed_ms(started))).await;
+        self.webhooks.spawn(
+            &artifact.runtime,
+            WebhookPayload::new(
+                run_id.to_string(),
+                RunStatus::Error,
+                artifact.id.clone(),
+                Some(session_id.to_string()),
+                "",
+            ),
+        );
+
+        let failed_run_id = run_id.to_string();
+        let replay_grace = self.replay_grace;
+        tokio::spawn(async move {
+            let _ = tx.send(NormalizedEvent::Error {
+                run_id: failed_run_id.clone(),
+                code: code.to_string(),
+                message,
+                request_id: None,
+            });
+            let _ = tx.send(NormalizedEvent::RunDone {
+                run_id: failed_run_id,
+                usage: None,
+            });
+            tx.expire_after(replay_grace);
+        });
+    }
+
+    /// Start a run with a caller-chosen ID.
+    ///
+    /// Returns a receiver subscribed before the run starts executing, so
+    /// none of its events are missed.
     #[instrument(
         skip(self, artifact, input),
         fields(
+            run_id = %run_id,
             agent_id = %artifact.id, 
             session_id = ?session_id, 
             user_id = ?user_id,
-            run_id = tracing::field::Empty
+            tenant_id = ?tenant_id,
+            request_id = ?options.request_id,
+            interactive_tools = options.interactive_tools
         )
     )]
-    pub async fn start_run(
+    #[allow(clippy::too_many_arguments)]
+    async fn launch_run(
         &self,
+        run_id: String,
         artifact: AgentArtifact,
         input: String,
         session_id: Option<String>,
         user_id: Option<String>,
-    ) -> String {
-        let run_id = Uuid::new_v4().to_string();
-        tracing::Span::current().record("run_id", &run_id);
+        tenant_id: Option<String>,
+        options: RunOptions,
+    ) -> Result<broadcast::Receiver<NormalizedEvent>, StartRunError> {
+        if let Some(limit) = &artifact.policy.rate_limit {
+            self.agent_limiter
+                .check(&artifact.id, user_id.as_deref(), limit)
+                .map_err(|reason| {
+                    tracing::warn!("Run rejected: {}", reason);
+                    StartRunError::RateLimited(reason)
+                })?;
+        }
+
         tracing::info!("Starting new run");
-        let (tx, _) = broadcast::channel(100); // Buffer size 100
+        let started = Instant::now();
+        // One budget for the whole run, from the shortest limit that applies
+        let deadline = Deadline::shortest([
+            self.run_timeout,
+            options.timeout_secs.map(Duration::from_secs),
+        ]);
+        let tx = RunEventSender::default().with_request_id(options.request_id.clone());
+        let rx = tx.subscribe();
+
+        // 0. Reject invalid artifacts before touching the session
+        let tenant = tenant_id.as_deref();
+        let mut validator = AgentArtifactValidator::new()
+            .with_tools(&self.global_mcp)
+            .with_tenant(tenant);
+        if let Some(db) = &self.persistence {
+            validator = validator.with_persistence(db.as_ref());
+        }
+        let mut errors = validator.validate(&artifact).await;
+        // The agent's own model and endpoint, if it names any
+        let mut settings = self.settings.clone();
+        match artifact.policy.provider.default.resolve_settings(&self.settings) {
+            Ok(resolved) => settings = resolved,
+            Err(e) => errors.push(e),
+        }
+        if let Some(model) = &options.model {
+            settings.model.clone_from(model);
+        }
+        if let Err(e) = self.model_aliases.apply(&mut settings) {
+            let field = if options.model.is_some() {
+                "model"
+            } else {
+                "policy.provider.default.model"
+            };
+            errors.push(ValidationError::new(field, e.to_string()));
+        }
+        if !errors.is_empty() {
+            let details: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            let message = format!("Invalid agent artifact: {}", details.join("; "));
+            tracing::warn!("{}", message);
+
+            self.webhooks.spawn(
+                &artifact.runtime,
+                WebhookPayload::new(
+                    run_id.clone(),
+                    RunStatus::Error,
+                    artifact.id.clone(),
+                    session_id.clone(),
+                    "",
+                ),
+            );
+            let run = Run {
+                run_id: run_id.clone(),
+                agent_id: artifact.id.clone(),
+                conversation_id: session_id,
+                user_id,
+                tenant_id,
+                status: RunStatus::Error,
+                context: serde_json::json!({ "input": input }),
+                usage: None,
+            };
+            self.active_runs
+                .write()
+                .await
+                .insert(run_id.clone(), (run, tx.clone()));
+
+            let failed_run_id = run_id.clone();
+            let replay_grace = self.replay_grace;
+            let manager = self.clone();
+            tokio::spawn(async move {
+                let _ = tx.send(NormalizedEvent::Error {
+                    run_id: failed_run_id.clone(),
+                    code: "invalid_agent".to_string(),
+                    message,
+                    request_id: None,
+                });
+                let outcome = RunOutcome::error(elapsed_ms(started));
+                manager.finalize_run(&failed_run_id, outcome).await;
+                let _ = tx.send(NormalizedEvent::RunDone {
+                    run_id: failed_run_id,
+                    usage: None,
+                });
+                tx.expire_after(replay_grace);
+            });
+            return Ok(rx);
+        }
 
         // 1. Resolve Session
         let session = if let Some(id) = session_id {
-            self.sessions.get_or_create(&id)
+            self.sessions
+                .get_or_create(&id, tenant)
+                .await
+                .ok_or(StartRunError::SessionNotFound(id))?
         } else {
-            self.sessions.create()
+            self.sessions.create_for_tenant(tenant, false)
         };
 
         // 2. Add User Message
@@ -95,9 +644,11 @@ impl RunManager {
             run_id: run_id.clone(),
             agent_id: artifact.id.clone(),
             conversation_id: Some(session.id().to_string()),
-            user_id,
+            user_id: user_id.clone(),
+            tenant_id: tenant_id.clone(),
             status: RunStatus::Running,
             context: serde_json::json!({ "input": input }),
+            usage: None,
         };
 
         {
@@ -106,50 +657,50 @@ impl RunManager {
         }
 
         // 3. Prepare Messages
-        // We prioritize the Artifact's system prompt.
+        // The artifact's system prompt comes first; knowledge and skills are appended.
         let mut messages = Vec::new();
-        let mut system_prompt = artifact.prompt.system.clone();
+        let mut prompt_additions = String::new();
+        // Chunks added to the prompt; cited once the answer is complete
+        let mut injected_chunks = Vec::new();
+
+        let mut phases = self.run_phases.then(PhaseTracker::new);
+        let mut send_phase = |phase| {
+            if let Some(phase) = phases.as_mut().and_then(|tracker| tracker.enter(phase)) {
+                let _ = tx.send(NormalizedEvent::RunPhase {
+                    run_id: run_id.clone(),
+                    phase,
+                });
+            }
+        };
 
         // RAG Retrieval - scoped to agent's configured knowledge bases
         if artifact.memory.kb.enabled {
+            send_phase(RunPhase::RagRetrieval);
             if let Some(db) = &self.persistence {
                 match self.vector_matcher.embed_batch(vec![input.clone()]).await {
                     Ok(embeddings) => {
                         if let Some(query_vec) = embeddings.first() {
                             // Get agent's configured KBs (or use all if empty)
                             let kb_names = &artifact.memory.kb.knowledge_bases;
-                            
-                            let search_result = if kb_names.is_empty() {
-                                // No specific KBs configured - search all
-                                db.search_knowledge(query_vec, 3, 0.7).await
-                            } else {
-                                // Resolve KB names to IDs and search scoped
-                                let mut kb_ids = Vec::new();
-                                for name in kb_names {
-                                    if let Ok(Some(kb)) = db.get_knowledge_base_by_name(name).await {
-                                        kb_ids.push(kb.id);
-                                    } else {
-                                        tracing::warn!("Knowledge base not found: {}", name);
-                                    }
-                                }
-                                
-                                if kb_ids.is_empty() {
-                                    // All configured KBs were not found - fallback to all
-                                    tracing::warn!("No configured knowledge bases found, searching all");
-                                    db.search_knowledge(query_vec, 3, 0.7).await
-                                } else {
-                                    let kb_id_refs: Vec<&str> = kb_ids.iter().map(|s| s.as_str()).collect();
-                                    db.search_knowledge_scoped(&kb_id_refs, query_vec, 3, 0.7).await
-                                }
-                            };
-                            
+                            let search_result = self
+                                .retrieve_knowledge(
+                                    db.as_ref(),
+                                    kb_names,
+                                    &input,
+                                    query_vec,
+                                    tenant,
+                                )
+                                .await;
+
                             match search_result {
                                 Ok(matches) => {
                                     if !matches.is_empty() {
-                                        system_prompt.push_str("\n\n[RELEVANT KNOWLEDGE]\n");
-                                        for m in matches {
-                                            system_prompt.push_str(&format!("- {}\n", m.chunk.content));
+                                        prompt_additions.push_str("\n\n[RELEVANT KNOWLEDGE]\n");
+                                        for m in &matches {
+                                            prompt_additions
+                                                .push_str(&format!("- {}\n", m.chunk.content));
                                         }
+                                        injected_chunks = matches;
                                     }
                                 }
                                 Err(e) => tracing::error!("RAG search failed: {:?}", e),
@@ -161,7 +712,8 @@ impl RunManager {
             }
         }
 
-        // SKILL INJECTION: Composite Matcher (Tag -> Vector -> LLM Selection in future)
+        // SKILL INJECTION: Composite Matcher (Tag -> Vector -> LLM Selection)
+        send_phase(RunPhase::SkillMatching);
         let skills_registry = self.skills.read().await;
         // Ensure skills are indexed for vector matching
         // Ideal optimization: Index on separate background task or when skills loaded.
@@ -180,7 +732,7 @@ impl RunManager {
         .await
         {
             for m in matches {
-                matched_skills.insert(m.skill_id.clone(), m.skill);
+                matched_skills.insert(m.skill_id.clone(), m);
             }
         }
 
@@ -194,25 +746,59 @@ impl RunManager {
         {
             for m in matches {
                 // Don't overwrite explicit tag matches if they exist, but here we just dedup by ID
-                matched_skills.entry(m.skill_id.clone()).or_insert(m.skill);
+                matched_skills.entry(m.skill_id.clone()).or_insert(m);
             }
         }
 
-        let sorted_skills: Vec<_> = matched_skills.values().collect();
-        // Collect registries to merge (starting with global)
-        let mut registries_to_merge = Vec::new();
+        // 3. LLM Selection among skills matching about equally well
+        let mut matches: Vec<_> = matched_skills.into_values().collect();
+        if let Some(disambiguator) = &self.skill_disambiguator {
+            matches = disambiguator.disambiguate(&input, matches).await;
+        }
+
+        // Skills a matched skill builds on are injected before it
+        let mut matched: Vec<_> = matches.into_iter().map(|m| m.skill).collect();
+        matched.sort_by(|a, b| a.skill_id.cmp(&b.skill_id));
+        let resolved = skills_registry.with_dependencies(matched, self.max_skill_depth);
+        let sorted_skills = match resolved {
+            Ok(skills) => skills,
+            Err(e) => {
+                drop(skills_registry);
+                let message = format!("Failed to resolve skill dependencies: {e}");
+                self.fail_prepared_run(
+                    &artifact,
+                    &run_id,
+                    session.id(),
+                    tx,
+                    SKILL_DEPENDENCY_ERROR_CODE,
+                    message,
+                    started,
+                )
+                .await;
+                return Ok(rx);
+            }
+        };
+        // Skill registries to merge into the global one, leased for the run
+        let mut skill_registries = Vec::new();
+        let mut tool_choice = None;
+
+        for skill in &sorted_skills {
+            if tool_choice.is_none() {
+                tool_choice.clone_from(&skill.tool_choice);
+            }
 
-        for skill in sorted_skills {
             // Append skill prompt overlay
-            system_prompt.push_str("\n\n[SKILL: ");
-            system_prompt.push_str(&skill.title);
-            system_prompt.push_str("]\n");
-            system_prompt.push_str(&skill.prompt_overlay);
+            let before = prompt_additions.len();
+            prompt_additions.push_str("\n\n[SKILL: ");
+            prompt_additions.push_str(&skill.title);
+            prompt_additions.push_str("]\n");
+            prompt_additions.push_str(&skill.prompt_overlay);
+            self.skill_metrics.record(&skill.skill_id, prompt_additions.len() - before);
 
             // Init Skill Tools
             if let Some(config) = &skill.mcp_config {
-                match McpRegistry::from_config(config).await {
-                    Ok(reg) => registries_to_merge.push(reg),
+                match self.skill_mcp.acquire(config).await {
+                    Ok(reg) => skill_registries.push(reg),
                     Err(e) => {
                         tracing::error!("Failed to init tools for skill {}: {:?}", skill.title, e)
                     }
@@ -220,6 +806,63 @@ impl RunManager {
             }
         }
 
+        // The agent's own servers live as long as this run
+        let agent_mcp = match artifact.mcp_config() {
+            Some(config) => match McpRegistry::from_config(&config).await {
+                Ok(reg) => Some(reg),
+                Err(e) => {
+                    tracing::error!("Failed to init tools for agent {}: {:?}", artifact.id, e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        // Merge registries
+        let mut final_mcp = (*self.global_mcp).clone();
+        let skill_mcp = skill_registries.iter().map(CachedRegistry::registry);
+        for reg in skill_mcp.chain(&agent_mcp) {
+            final_mcp = final_mcp.merge(reg);
+        }
+        // The agent only sees, and may only call, the tools its policy allows
+        let tool_policy = artifact.policy.tools.clone();
+        let mcp = Arc::new(final_mcp.with_tool_filter(move |name| tool_policy.permits(name)));
+
+        let tool_names: Vec<String> = mcp.tools().into_iter().map(|(name, _)| name).collect();
+        let builtins = BuiltinVariables::new(
+            user_id.as_deref(),
+            session.id(),
+            &run_id,
+            &artifact.memory.kb.knowledge_bases,
+            &tool_names,
+        );
+        let system_prompt = match render_system_prompt(
+            &artifact.prompt.system,
+            &builtins,
+            &artifact.prompt.variables,
+            &options.variables,
+        ) {
+            Ok(prompt) => prompt,
+            Err(e) => {
+                if let Some(reg) = &agent_mcp {
+                    reg.shutdown();
+                }
+                let message = format!("Failed to render the system prompt: {e}");
+                self.fail_prepared_run(
+                    &artifact,
+                    &run_id,
+                    session.id(),
+                    tx,
+                    TEMPLATE_ERROR_CODE,
+                    message,
+                    started,
+                )
+                .await;
+                return Ok(rx);
+            }
+        };
+
+        let system_prompt = format!("{system_prompt}{prompt_additions}");
         messages.push(Message {
             role: MessageRole::System,
             content: crate::llm::MessageContent::text(system_prompt),
@@ -228,9 +871,23 @@ impl RunManager {
         });
         messages.extend(session.messages());
 
+        settings.generation = settings
+            .generation
+            .overridden_by(&artifact.policy.provider.generation);
+        tracing::info!(model = %settings.model, base_url = %settings.base_url, "Resolved model");
+
+        // Summarize old messages with the run's model, on the summary budget
+        let summary_llm = Orchestrator::new(settings.clone(), Arc::new(McpRegistry::new_empty()));
+        let (messages, summary_action) = self.summarizer.apply(messages, &summary_llm).await;
+        if let Some(act) = summary_action {
+            let _ = tx.send(NormalizedEvent::ContextAction(act));
+        }
+
         // Context Management
-        let (optimized_messages, context_action) =
-            self.context_manager.apply(messages, 128000).await;
+        let (optimized_messages, context_action) = self
+            .context_manager
+            .apply_for_model(messages, &settings.model)
+            .await;
         let messages = optimized_messages;
         if let Some(act) = context_action {
             let _ = tx.send(NormalizedEvent::ContextAction(act));
@@ -239,53 +896,139 @@ impl RunManager {
         // Spawn async execution task
         // Create per-run Orchestrator.
 
-        // Merge registries
-        let mut final_mcp = (*self.global_mcp).clone();
-        for reg in registries_to_merge {
-            final_mcp = final_mcp.merge(&reg);
+        let model = settings.model.clone();
+        let breaker = self.circuit_breakers.as_ref().map(|b| b.for_settings(&settings));
+        let max_tool_calls =
+            usize::try_from(artifact.policy.tools.max_per_turn).unwrap_or(usize::MAX);
+        let mut orchestrator = Orchestrator::new(settings, mcp)
+            .with_session(session.clone())
+            .with_max_tool_calls_per_turn(max_tool_calls);
+        let tool_choice = tool_choice.or_else(|| artifact.policy.tools.tool_choice.clone());
+        if let Some(tool_choice) = tool_choice {
+            orchestrator = orchestrator.with_tool_choice(tool_choice);
         }
-        let mcp = Arc::new(final_mcp);
-
-        let settings = self.settings.clone();
+        if let Some(breaker) = breaker {
+            orchestrator = orchestrator.with_circuit_breaker(breaker);
+        }
+        if let Some(cache) = &self.response_cache {
+            orchestrator = orchestrator.with_response_cache(Arc::clone(cache));
+        }
+        if let Some(deadline) = deadline {
+            orchestrator = orchestrator.with_deadline(deadline);
+        }
+        if options.interactive_tools {
+            let gate = Arc::new(ResumeGate::new());
+            self.resume_gates
+                .write()
+                .await
+                .insert(run_id.clone(), Arc::clone(&gate));
+            orchestrator = orchestrator.with_resume_gate(gate);
+        }
+        let approval_gate = Arc::new(ApprovalGate::new(self.tool_approval_timeout));
+        self.approval_gates
+            .write()
+            .await
+            .insert(run_id.clone(), Arc::clone(&approval_gate));
+        orchestrator = orchestrator.with_approval_gate(approval_gate);
+        let orchestrator = Arc::new(orchestrator);
+        let cancel = CancellationToken::new();
+        self.cancel_tokens
+            .write()
+            .await
+            .insert(run_id.clone(), cancel.clone());
 
-        let orchestrator = Arc::new(Orchestrator::new(settings, mcp));
+        let mut run_log = (self.run_logging && self.persistence.is_some()).then(|| {
+            RunLog::new(run_id.clone(), artifact.id.clone(), tenant_id.clone(), &messages)
+        });
 
         let execute_run_id = run_id.clone();
         let execute_agent_id = artifact.id.clone();
         let tx_clone = tx.clone();
         let execution_session = session.clone();
+        let sessions = self.sessions.clone();
+        let vector_matcher = Arc::clone(&self.vector_matcher);
+        let kb_memory = artifact.memory.kb.clone();
+        let active_runs = Arc::clone(&self.active_runs);
+        let resume_gates = Arc::clone(&self.resume_gates);
+        let approval_gates = Arc::clone(&self.approval_gates);
+        let cancel_tokens = Arc::clone(&self.cancel_tokens);
+        let webhooks = self.webhooks.clone();
+        let runtime = artifact.runtime.clone();
+        let persistence = self.persistence.clone();
+        let replay_grace = self.replay_grace;
+        let pricing = self.pricing.clone();
+        let mut partial_usage = self
+            .partial_usage_interval
+            .map(|interval| PartialUsageCounter::new(&model, interval));
+        let manager = self.clone();
 
         tokio::spawn(async move {
             // 1. Run Start
             let _ = tx_clone.send(NormalizedEvent::RunStart {
                 run_id: execute_run_id.clone(),
-                agent_id: execute_agent_id,
+                agent_id: execute_agent_id.clone(),
             });
 
             let mut accumulated_content = String::new();
             let mut accumulated_tool_calls: Vec<crate::llm::ToolCall> = Vec::new();
+            // Every tool call of the run, for its archived record
+            let mut tool_calls_log = Vec::new();
+            // Everything the assistant produced, across tool-loop iterations
+            let mut turn = AssistantTurn::default();
+            // Usage summed over every LLM call in the tool loop
+            let mut run_tokens: Option<TokenUsage> = None;
+            // Running completion count due to be sent after the current delta
+            let mut tokens_so_far: Option<u32> = None;
+            let mut failed = false;
+            let mut cancelled = false;
+            let phase_event = |phase| NormalizedEvent::RunPhase {
+                run_id: execute_run_id.clone(),
+                phase,
+            };
+            if let Some(phase) = phases.as_mut().and_then(|p| p.enter(RunPhase::Generating)) {
+                let _ = tx_clone.send(phase_event(phase));
+            }
 
             // 2. Execute Orchestrator
             match orchestrator.chat_with_history(messages).await {
                 Ok(stream) => {
                     futures::pin_mut!(stream);
-                    while let Some(base_event) = stream.next().await {
+                    loop {
+                        let base_event = tokio::select! {
+                            () = cancel.cancelled() => {
+                                cancelled = true;
+                                break;
+                            }
+                            event = stream.next() => match event {
+                                Some(event) => event,
+                                None => break,
+                            },
+                        };
+                        if let Some(phase) = phases.as_mut().and_then(|p| p.observe(&base_event)) {
+                            let _ = tx_clone.send(phase_event(phase));
+                        }
                         // Map base NormalizedEvent to domain NormalizedEvent with run_id
                         let uar_event = match base_event {
                             crate::normalized::NormalizedEvent::MessageDelta { text } => {
                                 accumulated_content.push_str(&text);
+                                turn.push_text(&text);
+                                tokens_so_far = partial_usage
+                                    .as_mut()
+                                    .and_then(|counter| counter.push(&text));
                                 Some(NormalizedEvent::ChatDelta {
                                     run_id: execute_run_id.clone(),
                                     text_delta: text,
                                 })
                             }
                             crate::normalized::NormalizedEvent::ThinkingDelta { text } => {
+                                turn.push_reasoning(&text);
                                 Some(NormalizedEvent::ReasoningDelta {
                                     run_id: execute_run_id.clone(),
                                     text_delta: text,
                                 })
                             }
                             crate::normalized::NormalizedEvent::ReasoningDelta { text } => {
+                                turn.push_reasoning(&text);
                                 Some(NormalizedEvent::ReasoningDelta {
                                     run_id: execute_run_id.clone(),
                                     text_delta: text,
@@ -313,14 +1056,20 @@ impl RunManager {
                                 name,
                                 arguments_json,
                             } => {
-                                accumulated_tool_calls.push(crate::llm::ToolCall {
+                                let call = crate::llm::ToolCall {
                                     id: id.clone(),
                                     call_type: "function".to_string(),
                                     function: crate::llm::ToolCallFunction {
                                         name: name.clone(),
                                         arguments: arguments_json.clone(),
                                     },
-                                });
+                                };
+                                if let Some(log) = run_log.as_mut() {
+                                    log.tool_calls.push(call.clone());
+                                }
+                                turn.record_tool_call(&call);
+                                tool_calls_log.push(call.clone());
+                                accumulated_tool_calls.push(call);
 
                                 Some(NormalizedEvent::ToolStart {
                                     run_id: execute_run_id.clone(),
@@ -330,6 +1079,17 @@ impl RunManager {
                                         .unwrap_or(serde_json::Value::String(arguments_json)),
                                 })
                             }
+                            crate::normalized::NormalizedEvent::ToolApprovalRequest {
+                                id,
+                                name,
+                                arguments,
+                            } => Some(NormalizedEvent::ToolApprovalRequest {
+                                run_id: execute_run_id.clone(),
+                                tool_call_id: id,
+                                tool: name,
+                                input: serde_json::from_str(&arguments)
+                                    .unwrap_or(serde_json::Value::String(arguments)),
+                            }),
                             crate::normalized::NormalizedEvent::ToolResult {
                                 id,
                                 name: _,
@@ -351,6 +1111,7 @@ impl RunManager {
                                     accumulated_tool_calls.clear();
                                 }
 
+                                turn.record_tool_result(&id, &content, success);
                                 execution_session.add_tool_result(id.clone(), content.clone());
 
                                 Some(NormalizedEvent::ToolEnd {
@@ -361,40 +1122,527 @@ impl RunManager {
                                     ok: success,
                                 })
                             }
+                            crate::normalized::NormalizedEvent::RefusalDelta { text } => {
+                                Some(NormalizedEvent::RefusalDelta {
+                                    run_id: execute_run_id.clone(),
+                                    text_delta: text,
+                                })
+                            }
+                            crate::normalized::NormalizedEvent::ContentFilter {
+                                categories,
+                                severity,
+                            } => Some(NormalizedEvent::ContentFilter {
+                                run_id: execute_run_id.clone(),
+                                categories,
+                                severity,
+                            }),
+                            crate::normalized::NormalizedEvent::ToolLoopPaused => {
+                                set_run_status(&active_runs, &execute_run_id, RunStatus::Paused)
+                                    .await;
+                                Some(NormalizedEvent::RunPaused {
+                                    run_id: execute_run_id.clone(),
+                                })
+                            }
+                            crate::normalized::NormalizedEvent::Usage {
+                                prompt_tokens,
+                                completion_tokens,
+                                total_tokens,
+                            } => {
+                                let totals = run_tokens.get_or_insert_default();
+                                totals.add(TokenUsage {
+                                    prompt_tokens,
+                                    completion_tokens,
+                                    total_tokens,
+                                });
+                                Some(NormalizedEvent::Usage {
+                                    run_id: execute_run_id.clone(),
+                                    prompt_tokens: totals.prompt_tokens,
+                                    completion_tokens: totals.completion_tokens,
+                                    total_tokens: totals.total_tokens,
+                                })
+                            }
                             crate::normalized::NormalizedEvent::Error { message, code } => {
                                 Some(NormalizedEvent::Error {
                                     run_id: execute_run_id.clone(),
                                     message,
                                     code: code.unwrap_or_default(),
+                                    request_id: None,
                                 })
                             }
                             _ => None, // Ignore other events for now
                         };
 
                         if let Some(evt) = uar_event {
+                            failed |= matches!(evt, NormalizedEvent::Error { .. });
                             let _ = tx_clone.send(evt);
                         }
+                        if let Some(completion_tokens_so_far) = tokens_so_far.take() {
+                            let _ = tx_clone.send(NormalizedEvent::PartialUsage {
+                                run_id: execute_run_id.clone(),
+                                completion_tokens_so_far,
+                            });
+                        }
                     }
                 }
                 Err(e) => {
+                    failed = true;
                     let _ = tx_clone.send(NormalizedEvent::Error {
                         run_id: execute_run_id.clone(),
                         message: e.to_string(),
                         code: String::new(),
+                        request_id: None,
                     });
                 }
             }
 
+            if cancelled {
+                tracing::info!(run_id = %execute_run_id, "Run cancelled");
+                set_run_status(&active_runs, &execute_run_id, RunStatus::Cancelled).await;
+                let _ = tx_clone.send(NormalizedEvent::RunCancelled {
+                    run_id: execute_run_id.clone(),
+                });
+            }
+
+            if !injected_chunks.is_empty() {
+                let sources = crate::uar::rag::citations::build_citations(
+                    &vector_matcher,
+                    &injected_chunks,
+                    &turn.text,
+                    &kb_memory,
+                )
+                .await;
+                let _ = tx_clone.send(NormalizedEvent::Citation {
+                    run_id: execute_run_id.clone(),
+                    sources,
+                });
+            }
+
             if !accumulated_content.is_empty() {
-                execution_session.add_assistant_message(accumulated_content);
+                execution_session.add_assistant_turn(accumulated_content, turn.clone());
+            }
+
+            // Flush the completed turn immediately rather than waiting for
+            // the background flusher
+            if let Err(e) = sessions.persist(&execution_session).await {
+                tracing::warn!("Failed to persist session {}: {:?}", execution_session.id(), e);
+            }
+
+            let status = if cancelled {
+                RunStatus::Cancelled
+            } else if failed {
+                RunStatus::Error
+            } else {
+                RunStatus::Done
+            };
+            let outcome = RunOutcome {
+                status: status.clone(),
+                accumulated_content: turn.text.clone(),
+                tool_calls_log,
+                prompt_tokens: run_tokens.map_or(0, |tokens| tokens.prompt_tokens),
+                completion_tokens: run_tokens.map_or(0, |tokens| tokens.completion_tokens),
+                duration_ms: elapsed_ms(started),
+            };
+            let webhook = WebhookPayload::new(
+                execute_run_id.clone(),
+                status,
+                execute_agent_id.clone(),
+                Some(execution_session.id().to_string()),
+                &turn.text,
+            );
+
+            // Cost accounting (only when the provider reported usage)
+            let usage = run_tokens.map(|tokens| RunUsage {
+                run_id: execute_run_id.clone(),
+                agent_id: execute_agent_id,
+                session_id: Some(execution_session.id().to_string()),
+                cost_usd: pricing.estimate_cost(&model, &tokens),
+                model,
+                tokens,
+            });
+            if let Some(usage) = &usage {
+                if let Some((run, _)) = active_runs.write().await.get_mut(&execute_run_id) {
+                    run.usage = Some(usage.clone());
+                }
+                if let Some(db) = &persistence
+                    && let Err(e) = db.save_run_usage(usage).await
+                {
+                    tracing::warn!("Failed to persist usage of run {}: {:?}", execute_run_id, e);
+                }
             }
 
+            // Verbatim exchange for the audit trail
+            if let Some(mut log) = run_log {
+                log.response_text = turn.text;
+                if let Some(tokens) = run_tokens {
+                    log.prompt_tokens = tokens.prompt_tokens;
+                    log.completion_tokens = tokens.completion_tokens;
+                }
+                if let Some(db) = &persistence
+                    && let Err(e) = db.save_run_log(&log).await
+                {
+                    tracing::warn!("Failed to persist log of run {}: {:?}", execute_run_id, e);
+                }
+            }
+
+            resume_gates.write().await.remove(&execute_run_id);
+            approval_gates.write().await.remove(&execute_run_id);
+            cancel_tokens.write().await.remove(&execute_run_id);
+            if let Some(reg) = agent_mcp {
+                reg.shutdown();
+            }
+            // Idle skill servers are shut down by the cache
+            drop(skill_registries);
+            manager.finalize_run(&execute_run_id, outcome).await;
+            if let Some(phase) = phases.as_mut().and_then(|p| p.enter(RunPhase::Done)) {
+                let _ = tx_clone.send(phase_event(phase));
+            }
             let _ = tx_clone.send(NormalizedEvent::RunDone {
                 run_id: execute_run_id,
+                usage,
+            });
+            tx_clone.expire_after(replay_grace);
+            webhooks.spawn(&runtime, webhook);
+        }
+        // Logs of the run keep its `run_id` and `request_id`
+        .in_current_span());
+
+        Ok(rx)
+    }
+
+    /// Run `chain` step by step, feeding each step's answer into the next.
+    ///
+    /// Every step is a run of its own, in the same session. Returns the
+    /// chain's ID, registered like a run until the chain ends. Its stream
+    /// carries a `ChainProgress` event before each step, then that step's
+    /// events except its `RunDone`, and ends with the chain's own `RunDone`.
+    /// The chain stops at the first step that fails.
+    pub async fn start_chained_run(
+        &self,
+        chain: Vec<ChainStep>,
+        initial_input: String,
+        session_id: Option<String>,
+        tenant_id: Option<String>,
+    ) -> anyhow::Result<String> {
+        if chain.is_empty() {
+            bail!("Chain must have at least one step");
+        }
+
+        // Resolve every agent up front so a typo fails the request, not step 3
+        let mut agents = Vec::with_capacity(chain.len());
+        for step in &chain {
+            let agent = self
+                .resolve_agent(&step.agent_id, tenant_id.as_deref())
+                .await?
+                .ok_or_else(|| anyhow!("Agent '{}' not found", step.agent_id))?;
+            agents.push(agent);
+        }
+
+        let session_id = session_id
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| {
+                let session = self.sessions.create_for_tenant(tenant_id.as_deref(), false);
+                session.id().to_string()
+            });
+        let started = Instant::now();
+        let total = chain.len();
+        let chain_id = Uuid::new_v4().to_string();
+        let chain_tx = RunEventSender::default();
+        let agent_ids: Vec<&str> = chain.iter().map(|step| step.agent_id.as_str()).collect();
+        let chain_run = Run {
+            run_id: chain_id.clone(),
+            agent_id: agent_ids[0].to_string(),
+            conversation_id: Some(session_id.clone()),
+            user_id: None,
+            tenant_id: tenant_id.clone(),
+            status: RunStatus::Running,
+            context: serde_json::json!({ "input": initial_input, "chain": agent_ids }),
+            usage: None,
+        };
+        let mut steps = chain.into_iter().zip(agents);
+        let (first_step, first_agent) = steps.next().expect("chain is not empty");
+
+        let first_run_id = Uuid::new_v4().to_string();
+        let input = render_step_input(
+            &first_step.input_template,
+            &initial_input,
+            &first_run_id,
+            &session_id,
+        )?;
+        // Sent before the step starts, so it precedes the step's events
+        let _ = chain_tx.send(NormalizedEvent::ChainProgress {
+            run_id: first_run_id.clone(),
+            step: 1,
+            total,
+            agent_id: first_step.agent_id,
+        });
+        let mut rx = self
+            .launch_run(
+                first_run_id,
+                first_agent,
+                input,
+                Some(session_id.clone()),
+                None,
+                tenant_id.clone(),
+                RunOptions::default(),
+            )
+            .await?;
+        self.active_runs
+            .write()
+            .await
+            .insert(chain_id.clone(), (chain_run, chain_tx.clone()));
+
+        let manager = self.clone();
+        let task_chain_id = chain_id.clone();
+        tokio::spawn(async move {
+            let chain_id = task_chain_id;
+            let mut output = collect_run_output(&mut rx, &chain_tx).await;
+            for (index, (step, agent)) in steps.enumerate() {
+                // Stop after a failed step
+                let Some(previous) = output.take() else { break };
+
+                let run_id = Uuid::new_v4().to_string();
+                let input =
+                    match render_step_input(&step.input_template, &previous, &run_id, &session_id) {
+                        Ok(input) => input,
+                        Err(e) => {
+                            let _ = chain_tx.send(NormalizedEvent::Error {
+                                run_id,
+                                code: "chain_template".to_string(),
+                                message: format!("{e:#}"),
+                                request_id: None,
+                            });
+                            break;
+                        }
+                    };
+
+                let _ = chain_tx.send(NormalizedEvent::ChainProgress {
+                    run_id: run_id.clone(),
+                    step: index + 2,
+                    total,
+                    agent_id: step.agent_id,
+                });
+                let session = Some(session_id.clone());
+                let tenant = tenant_id.clone();
+                let options = RunOptions::default();
+                match manager
+                    .launch_run(run_id.clone(), agent, input, session, None, tenant, options)
+                    .await
+                {
+                    Ok(mut rx) => output = collect_run_output(&mut rx, &chain_tx).await,
+                    Err(e) => {
+                        let _ = chain_tx.send(NormalizedEvent::Error {
+                            run_id,
+                            code: e.code().to_string(),
+                            message: e.to_string(),
+                            request_id: None,
+                        });
+                    }
+                }
+            }
+
+            let mut outcome = RunOutcome::error(elapsed_ms(started));
+            match output {
+                Some(answer) => {
+                    outcome.status = RunStatus::Done;
+                    outcome.accumulated_content = answer;
+                }
+                None => tracing::warn!("Chain {} stopped after a failed step", chain_id),
+            }
+            manager.finalize_run(&chain_id, outcome).await;
+            let _ = chain_tx.send(NormalizedEvent::RunDone {
+                run_id: chain_id,
+                usage: None,
             });
+            chain_tx.expire_after(manager.replay_grace);
         });
 
-        run_id
+        Ok(chain_id)
+    }
+
+    /// Let a paused interactive run send its tool results to the model.
+    pub async fn resume_run(&self, run_id: &str) -> Result<(), ResumeRunError> {
+        let gate = self
+            .resume_gates
+            .read()
+            .await
+            .get(run_id)
+            .cloned()
+            .ok_or_else(|| ResumeRunError::NotFound(run_id.to_string()))?;
+        // Mark the run running first: the resumed loop may pause again at once
+        set_run_status(&self.active_runs, run_id, RunStatus::Running).await;
+        if !gate.resume() {
+            return Err(ResumeRunError::NotPaused(run_id.to_string()));
+        }
+        tracing::info!(run_id = %run_id, "Interactive run resumed");
+        Ok(())
+    }
+
+    /// Approve or deny a run's tool call that is waiting for approval.
+    pub async fn decide_tool_approval(
+        &self,
+        run_id: &str,
+        tool_call_id: &str,
+        approved: bool,
+    ) -> Result<(), ToolApprovalError> {
+        let gate = self
+            .approval_gates
+            .read()
+            .await
+            .get(run_id)
+            .cloned()
+            .ok_or_else(|| ToolApprovalError::RunNotFound(run_id.to_string()))?;
+        if !gate.decide(tool_call_id, approved) {
+            return Err(ToolApprovalError::NotPending(tool_call_id.to_string()));
+        }
+        tracing::info!(
+            run_id = %run_id,
+            tool_call_id = %tool_call_id,
+            approved,
+            "Tool call decided"
+        );
+        Ok(())
+    }
+
+    /// Stop an in-progress run before its next event.
+    ///
+    /// The run ends with `RunCancelled` followed by `RunDone`. Returns
+    /// `false` if no run with this ID is in progress.
+    pub async fn cancel_run(&self, run_id: &str) -> bool {
+        let Some(token) = self.cancel_tokens.read().await.get(run_id).cloned() else {
+            return false;
+        };
+        token.cancel();
+        true
+    }
+
+    /// Look up a stored agent, falling back to the built-in default agent.
+    pub async fn resolve_agent(
+        &self,
+        id: &str,
+        tenant_id: Option<&str>,
+    ) -> anyhow::Result<Option<AgentArtifact>> {
+        if let Some(db) = &self.persistence
+            && let Some(agent) = db.load_agent(id, tenant_id).await?
+        {
+            return Ok(Some(agent));
+        }
+        let default = crate::uar::defaults::default_agent();
+        Ok((default.id == id).then_some(default))
+    }
+
+    /// Token usage and cost of a finished run, from memory or the database.
+    ///
+    /// `Ok(None)` if no usage was recorded for the run.
+    pub async fn run_usage(&self, run_id: &str) -> anyhow::Result<Option<RunUsage>> {
+        if let Some(usage) = self
+            .active_runs
+            .read()
+            .await
+            .get(run_id)
+            .and_then(|(run, _)| run.usage.clone())
+        {
+            return Ok(Some(usage));
+        }
+        match &self.persistence {
+            Some(db) => Ok(db.load_run_usage(run_id).await?),
+            None => Ok(None),
+        }
+    }
+
+    /// Verbatim LLM exchange of a finished run of `tenant_id` (or a shared
+    /// one), when run logging is on.
+    pub async fn run_log(
+        &self,
+        run_id: &str,
+        tenant_id: Option<&str>,
+    ) -> anyhow::Result<Option<RunLog>> {
+        match &self.persistence {
+            Some(db) => Ok(db.get_run_log(run_id, tenant_id).await?),
+            None => Ok(None),
+        }
+    }
+
+    /// Tools available to every run (before skill tools are merged in).
+    pub fn tools(&self) -> &McpRegistry {
+        &self.global_mcp
+    }
+
+    /// Registries started for the MCP servers of skills.
+    pub fn skill_mcp_cache(&self) -> &McpRegistryCache {
+        &self.skill_mcp
+    }
+
+    /// Skills runs are matched against.
+    pub fn skills(&self) -> &Arc<RwLock<SkillRegistry>> {
+        &self.skills
+    }
+
+    /// Sessions runs add their messages to.
+    pub fn sessions(&self) -> &SessionStore {
+        &self.sessions
+    }
+
+    /// Knowledge for the prompt from the agent's KBs (all KBs if none are
+    /// configured or found).
+    ///
+    /// KBs without reranking are searched together for the best `RAG_TOP_K`
+    /// chunks; each reranked KB adds its own `top_n`.
+    async fn retrieve_knowledge(
+        &self,
+        db: &dyn PersistenceLayer,
+        kb_names: &[String],
+        query: &str,
+        query_vec: &[f32],
+        tenant: Option<&str>,
+    ) -> anyhow::Result<Vec<KnowledgeMatch>> {
+        let mut kbs = Vec::new();
+        for name in kb_names {
+            if let Ok(Some(kb)) = db.get_knowledge_base_by_name(name, tenant).await {
+                kbs.push(kb);
+            } else {
+                tracing::warn!("Knowledge base not found: {}", name);
+            }
+        }
+        if kbs.is_empty() {
+            if !kb_names.is_empty() {
+                tracing::warn!("No configured knowledge bases found, searching all");
+            }
+            kbs = db.list_knowledge_bases(tenant).await?;
+        }
+
+        let (reranked, plain): (Vec<&KnowledgeBase>, Vec<&KnowledgeBase>) = kbs
+            .iter()
+            .partition(|kb| self.rerankers.resolve(kb.config.rerank.as_ref()).is_some());
+
+        let mut matches = Vec::new();
+        if !plain.is_empty() {
+            let kb_ids: Vec<&str> = plain.iter().map(|kb| kb.id.as_str()).collect();
+            matches = self
+                .vector_matcher
+                .search_chunks(db, &kb_ids, query_vec, RAG_TOP_K, RAG_MIN_SCORE, None, tenant)
+                .await?;
+        }
+        for kb in reranked {
+            // Not capped by the caller: the KB's `top_n` applies
+            let found = rerank::search_knowledge_base(
+                db,
+                &self.vector_matcher,
+                &self.rerankers,
+                kb,
+                query,
+                query_vec,
+                usize::MAX,
+                RAG_MIN_SCORE,
+                None,
+                tenant,
+            )
+            .await;
+            match found {
+                Ok(found) => matches.extend(found),
+                Err(e) => tracing::error!("Reranked search of KB '{}' failed: {:#}", kb.name, e),
+            }
+        }
+        Ok(matches)
     }
 
     pub async fn subscribe(&self, run_id: &str) -> Option<broadcast::Receiver<NormalizedEvent>> {
@@ -402,8 +1650,133 @@ impl RunManager {
         runs.get(run_id).map(|(_, tx)| tx.subscribe())
     }
 
+    /// The run's events after `last_event_id` that are still kept, followed
+    /// by its live events; `None` if the run is unknown.
+    ///
+    /// Without `last_event_id` every kept event is replayed.
+    pub async fn resume_stream(
+        &self,
+        run_id: &str,
+        last_event_id: Option<u64>,
+    ) -> Option<impl futures::Stream<Item = SequencedEvent> + Send + 'static> {
+        let runs = self.active_runs.read().await;
+        runs.get(run_id).map(|(_, tx)| tx.resume(last_event_id))
+    }
+
+    /// A run that is still in memory: running, or finished within the
+    /// replay grace.
     pub async fn get_run(&self, run_id: &str) -> Option<Run> {
         let runs = self.active_runs.read().await;
         runs.get(run_id).map(|(run, _)| run.clone())
     }
+
+    /// A run from memory, or its archived record once it has left.
+    pub async fn find_run(
+        &self,
+        run_id: &str,
+        tenant_id: Option<&str>,
+    ) -> anyhow::Result<Option<RunView>> {
+        if let Some(run) = self.get_run(run_id).await {
+            let visible = visible_to(run.tenant_id.as_deref(), tenant_id);
+            return Ok(visible.then_some(RunView::Active(run)));
+        }
+        match &self.persistence {
+            Some(db) => Ok(db
+                .get_run_record(run_id, tenant_id)
+                .await?
+                .map(RunView::Archived)),
+            None => Ok(None),
+        }
+    }
+
+    /// Archived runs, most recently finished first; see
+    /// [`PersistenceLayer::list_runs`]. Empty without persistence.
+    pub async fn list_runs(
+        &self,
+        session_id: Option<&str>,
+        agent_id: Option<&str>,
+        after: Option<&str>,
+        limit: usize,
+        tenant_id: Option<&str>,
+    ) -> anyhow::Result<Vec<RunRecord>> {
+        match &self.persistence {
+            Some(db) => Ok(db
+                .list_runs(session_id, agent_id, after, limit, tenant_id)
+                .await?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Archive a finished run and drop it from the active runs.
+    ///
+    /// The run takes `outcome.status` and is saved with its output (when
+    /// persistence is configured). It stays in memory for the replay grace,
+    /// so clients can still reconnect to its stream, and is removed after.
+    pub async fn finalize_run(&self, run_id: &str, outcome: RunOutcome) {
+        let run = {
+            let mut runs = self.active_runs.write().await;
+            let Some((run, _)) = runs.get_mut(run_id) else {
+                return;
+            };
+            run.status = outcome.status.clone();
+            run.clone()
+        };
+
+        if let Some(db) = &self.persistence
+            && let Err(e) = db.save_run_record(&RunRecord::new(run, outcome)).await
+        {
+            tracing::warn!("Failed to archive run {}: {:?}", run_id, e);
+        }
+
+        let active_runs = Arc::clone(&self.active_runs);
+        let run_id = run_id.to_string();
+        let grace = self.replay_grace;
+        tokio::spawn(async move {
+            tokio::time::sleep(grace).await;
+            active_runs.write().await.remove(&run_id);
+        });
+    }
+}
+
+/// Milliseconds since `started`.
+fn elapsed_ms(started: Instant) -> u64 {
+    u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Run ID -> (run metadata, event sender).
+type ActiveRuns = RwLock<HashMap<String, (Run, RunEventSender)>>;
+
+async fn set_run_status(active_runs: &ActiveRuns, run_id: &str, status: RunStatus) {
+    if let Some((run, _)) = active_runs.write().await.get_mut(run_id) {
+        run.status = status;
+    }
+}
+
+/// Wait for a run to finish and return its answer text, or `None` if it
+/// reported an error. Its events other than `RunDone` are copied to
+/// `forward`.
+async fn collect_run_output(
+    rx: &mut broadcast::Receiver<NormalizedEvent>,
+    forward: &RunEventSender,
+) -> Option<String> {
+    let mut output = String::new();
+    let mut failed = false;
+    loop {
+        match rx.recv().await {
+            Ok(event) => {
+                match &event {
+                    NormalizedEvent::ChatDelta { text_delta, .. } => output.push_str(text_delta),
+                    NormalizedEvent::Error { .. } => failed = true,
+                    NormalizedEvent::RunDone { .. } => break,
+                    _ => {}
+                }
+                let _ = forward.send(event);
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!("Chain missed {} events; step output may be incomplete", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+    (!failed).then_some(output)
 }
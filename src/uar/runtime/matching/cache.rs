@@ -0,0 +1,258 @@
+//! Embedding cache for `VectorMatcher`.
+//!
+//! Avoids re-embedding identical text (repeated RAG queries, duplicate chunks)
+//! by keying embeddings on a `seahash` of the input text. Bounded in size with
+//! least-recently-used eviction.
+
+use anyhow::{Result, anyhow};
+use lru::LruCache;
+use std::collections::HashMap;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+/// Prometheus counter for cache hits.
+const CACHE_HITS_METRIC: &str = "uar_embedding_cache_hits_total";
+/// Prometheus counter for cache misses.
+const CACHE_MISSES_METRIC: &str = "uar_embedding_cache_misses_total";
+
+/// Bounded, concurrent embedding cache keyed by text hash.
+#[derive(Debug)]
+pub struct EmbeddingCache {
+    entries: Mutex<LruCache<u64, Vec<f32>>>,
+}
+
+impl EmbeddingCache {
+    /// Default maximum number of cached embeddings.
+    pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+    /// Create a cache holding at most `max_entries` embeddings.
+    pub fn new(max_entries: usize) -> Self {
+        let capacity = NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Hash key for a piece of text.
+    pub fn key(text: &str) -> u64 {
+        seahash::hash(text.as_bytes())
+    }
+
+    /// Hash key for a piece of text embedded by a specific model.
+    ///
+    /// Different models produce different vectors for the same text, so
+    /// providers sharing one cache key their entries by model name.
+    pub fn namespaced_key(namespace: &str, text: &str) -> u64 {
+        if namespace.is_empty() {
+            return Self::key(text);
+        }
+        let mut bytes = Vec::with_capacity(namespace.len() + 1 + text.len());
+        bytes.extend_from_slice(namespace.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(text.as_bytes());
+        seahash::hash(&bytes)
+    }
+
+    /// Look up a cached embedding, recording a hit or miss.
+    pub fn get(&self, text: &str) -> Option<Vec<f32>> {
+        self.get_by_key(Self::key(text))
+    }
+
+    fn get_by_key(&self, key: u64) -> Option<Vec<f32>> {
+        let found = self.entries.lock().unwrap().get(&key).cloned();
+
+        if found.is_some() {
+            metrics::counter!(CACHE_HITS_METRIC).increment(1);
+        } else {
+            metrics::counter!(CACHE_MISSES_METRIC).increment(1);
+        }
+        found
+    }
+
+    /// Insert an embedding, evicting the least recently used entry when full.
+    pub fn insert(&self, text: &str, embedding: Vec<f32>) {
+        self.insert_by_key(Self::key(text), embedding);
+    }
+
+    fn insert_by_key(&self, key: u64, embedding: Vec<f32>) {
+        self.entries.lock().unwrap().put(key, embedding);
+    }
+
+    /// Return embeddings for `texts`, calling `embed` only for cache misses.
+    ///
+    /// Duplicate texts within the batch are embedded once. Results are
+    /// returned in input order.
+    pub async fn get_or_embed<F, Fut>(&self, texts: Vec<String>, embed: F) -> Result<Vec<Vec<f32>>>
+    where
+        F: FnOnce(Vec<String>) -> Fut,
+        Fut: Future<Output = Result<Vec<Vec<f32>>>>,
+    {
+        self.get_or_embed_in("", texts, embed).await
+    }
+
+    /// Like [`Self::get_or_embed`], with entries keyed under `namespace`
+    /// (typically the embedding model name).
+    pub async fn get_or_embed_in<F, Fut>(
+        &self,
+        namespace: &str,
+        texts: Vec<String>,
+        embed: F,
+    ) -> Result<Vec<Vec<f32>>>
+    where
+        F: FnOnce(Vec<String>) -> Fut,
+        Fut: Future<Output = Result<Vec<Vec<f32>>>>,
+    {
+        let mut results: Vec<Option<Vec<f32>>> = Vec::with_capacity(texts.len());
+        let mut misses: Vec<String> = Vec::new();
+        // Miss text -> positions in `results` waiting on it
+        let mut pending: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (i, text) in texts.into_iter().enumerate() {
+            if let Some(positions) = pending.get_mut(&text) {
+                positions.push(i);
+                results.push(None);
+                continue;
+            }
+            match self.get_by_key(Self::namespaced_key(namespace, &text)) {
+                Some(embedding) => results.push(Some(embedding)),
+                None => {
+                    pending.insert(text.clone(), vec![i]);
+                    misses.push(text);
+                    results.push(None);
+                }
+            }
+        }
+
+        if !misses.is_empty() {
+            let embeddings = embed(misses.clone()).await?;
+            if embeddings.len() != misses.len() {
+                return Err(anyhow!(
+                    "Embedding model returned {} vectors for {} inputs",
+                    embeddings.len(),
+                    misses.len()
+                ));
+            }
+
+            for (text, embedding) in misses.into_iter().zip(embeddings) {
+                for &i in pending.get(&text).into_iter().flatten() {
+                    results[i] = Some(embedding.clone());
+                }
+                self.insert_by_key(Self::namespaced_key(namespace, &text), embedding);
+            }
+        }
+
+        results
+            .into_iter()
+            .map(|r| r.ok_or_else(|| anyhow!("Missing embedding after cache fill")))
+            .collect()
+    }
+
+    /// Number of cached embeddings.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all cached embeddings.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+impl Default for EmbeddingCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_ENTRIES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn fake_embed(texts: &[String]) -> Vec<Vec<f32>> {
+        texts.iter().map(|t| vec![t.len() as f32, 1.0]).collect()
+    }
+
+    #[tokio::test]
+    async fn test_same_text_embedded_once() {
+        let cache = EmbeddingCache::new(10);
+        let calls = AtomicUsize::new(0);
+
+        let embed = |texts: Vec<String>| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(fake_embed(&texts)) }
+        };
+
+        let first = cache
+            .get_or_embed(vec!["hello world".to_string()], embed)
+            .await
+            .unwrap();
+        let second = cache
+            .get_or_embed(vec!["hello world".to_string()], embed)
+            .await
+            .unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_duplicates_in_batch() {
+        let cache = EmbeddingCache::new(10);
+        let result = cache
+            .get_or_embed(
+                vec!["a".to_string(), "bb".to_string(), "a".to_string()],
+                |texts: Vec<String>| async move {
+                    assert_eq!(texts.len(), 2);
+                    Ok(fake_embed(&texts))
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result, vec![vec![1.0, 1.0], vec![2.0, 1.0], vec![1.0, 1.0]]);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_are_isolated() {
+        let cache = EmbeddingCache::new(10);
+        cache
+            .get_or_embed_in("model-a", vec!["x".to_string()], |_| async {
+                Ok(vec![vec![1.0]])
+            })
+            .await
+            .unwrap();
+        let other = cache
+            .get_or_embed_in("model-b", vec!["x".to_string()], |_| async {
+                Ok(vec![vec![2.0]])
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(other, vec![vec![2.0]]);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = EmbeddingCache::new(2);
+        cache.insert("a", vec![1.0]);
+        cache.insert("b", vec![2.0]);
+
+        // Touch "a" so "b" becomes least recently used
+        assert!(cache.get("a").is_some());
+        cache.insert("c", vec![3.0]);
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+    }
+}
@@ -0,0 +1,528 @@
+//! HNSW indexes over knowledge chunk embeddings.
+//!
+//! Stores without a vector index of their own compare a query with every
+//! chunk, which stops being usable somewhere past 100k chunks. With
+//! `vector_index.hnsw_enabled`, [`VectorMatcher`](super::VectorMatcher)
+//! searches an approximate nearest neighbor graph per knowledge base instead.
+//! A graph is built on the first search of its knowledge base and dropped when
+//! chunks are saved or deleted, to be rebuilt by the next search. Graphs saved
+//! to `vector_index.index_path` are reused after a restart if they were built
+//! with the same format and parameters from the same chunks.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use instant_distance::{Builder, HnswMap, Point, Search};
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+
+use crate::config::VectorIndexConfig;
+use crate::uar::domain::knowledge::{KnowledgeChunk, KnowledgeMatch};
+use crate::uar::domain::tenant::visible_to;
+use crate::uar::persistence::PersistenceLayer;
+
+/// Seed for graph construction, so the same chunks build the same graph.
+const SEED: u64 = 0x5eed;
+
+/// Version of the index file format; files of other versions are ignored.
+const INDEX_FORMAT_VERSION: u32 = 1;
+
+/// Unit-length embedding; the distance between two is one minus their
+/// cosine similarity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Embedding(Vec<f32>);
+
+impl Embedding {
+    fn normalized(mut vector: Vec<f32>) -> Self {
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            for x in &mut vector {
+                *x /= norm;
+            }
+        }
+        Self(vector)
+    }
+}
+
+impl Point for Embedding {
+    fn distance(&self, other: &Self) -> f32 {
+        let dot: f32 = self.0.iter().zip(&other.0).map(|(a, b)| a * b).sum();
+        1.0 - dot
+    }
+}
+
+/// HNSW graph over the chunks of one knowledge base.
+#[derive(Serialize, Deserialize)]
+pub struct ChunkIndex {
+    /// `None` for a knowledge base without chunks
+    graph: Option<HnswMap<Embedding, usize>>,
+    /// Chunks without their embeddings, indexed by graph value
+    chunks: Vec<KnowledgeChunk>,
+    /// [`fingerprint`] of the chunks the graph was built from
+    fingerprint: u64,
+}
+
+/// Graph parameters an index file was built with.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct IndexParams {
+    m: usize,
+    ef_construction: usize,
+    ef_search: usize,
+}
+
+impl From<&VectorIndexConfig> for IndexParams {
+    fn from(config: &VectorIndexConfig) -> Self {
+        Self {
+            m: config.hnsw_m,
+            ef_construction: config.hnsw_ef_construction,
+            ef_search: config.hnsw_ef_search,
+        }
+    }
+}
+
+/// Contents of an index file. Files written before it had a version read as
+/// version 0.
+#[derive(Serialize, Deserialize)]
+struct IndexFile<I> {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    params: IndexParams,
+    #[serde(default)]
+    indexes: HashMap<String, I>,
+}
+
+/// Hash of the IDs, contents and embeddings of `chunks`, in any order.
+fn fingerprint(chunks: &[KnowledgeChunk]) -> u64 {
+    chunks
+        .iter()
+        .map(|chunk| {
+            let mut hasher = seahash::SeaHasher::new();
+            hasher.write(chunk.id.as_bytes());
+            hasher.write(chunk.content.as_bytes());
+            for x in &chunk.embedding {
+                hasher.write_u32(x.to_bits());
+            }
+            hasher.finish()
+        })
+        .fold(0, u64::wrapping_add)
+}
+
+impl std::fmt::Debug for ChunkIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChunkIndex")
+            .field("chunks", &self.chunks.len())
+            .finish()
+    }
+}
+
+impl ChunkIndex {
+    /// Build the graph of `chunks`. CPU-bound: run it off the async runtime.
+    pub fn build(chunks: Vec<KnowledgeChunk>, config: &VectorIndexConfig) -> Self {
+        let fingerprint = fingerprint(&chunks);
+        let mut points = Vec::with_capacity(chunks.len());
+        let mut stored = Vec::with_capacity(chunks.len());
+        for mut chunk in chunks {
+            points.push(Embedding::normalized(std::mem::take(&mut chunk.embedding)));
+            stored.push(chunk);
+        }
+
+        let values = (0..stored.len()).collect();
+        // Layers thin out by 1/ln(M), as in the HNSW paper
+        let m = u16::try_from(config.hnsw_m.max(2)).unwrap_or(u16::MAX);
+        let graph = (!points.is_empty()).then(|| {
+            Builder::default()
+                .ef_construction(config.hnsw_ef_construction)
+                .ef_search(config.hnsw_ef_search)
+                .ml(1.0 / f32::from(m).ln())
+                .seed(SEED)
+                .build(points, values)
+        });
+        Self {
+            graph,
+            chunks: stored,
+            fingerprint,
+        }
+    }
+
+    /// Number of indexed chunks.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Whether the knowledge base had no chunks.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Up to `limit` chunks nearest to `query` that score at least
+    /// `min_score` and pass `keep`, best first.
+    ///
+    /// Only the graph's `ef_search` nearest chunks are candidates.
+    pub fn search(
+        &self,
+        query: &[f32],
+        limit: usize,
+        min_score: f32,
+        keep: impl Fn(&KnowledgeChunk) -> bool,
+    ) -> Vec<KnowledgeMatch> {
+        let Some(graph) = &self.graph else {
+            return Vec::new();
+        };
+        let query = Embedding::normalized(query.to_vec());
+        let mut search = Search::default();
+        graph
+            .search(&query, &mut search)
+            .map(|item| (&self.chunks[*item.value], 1.0 - item.distance))
+            .filter(|&(chunk, score)| score >= min_score && keep(chunk))
+            .take(limit)
+            .map(|(chunk, score)| KnowledgeMatch {
+                chunk: chunk.clone(),
+                score,
+            })
+            .collect()
+    }
+}
+
+/// Index of a knowledge base, once built; `None` inside when the store
+/// indexes vectors itself.
+type IndexCell = Arc<OnceCell<Option<Arc<ChunkIndex>>>>;
+
+/// [`ChunkIndex`]es by knowledge base, built on first search.
+#[derive(Debug)]
+pub struct ChunkIndexes {
+    config: VectorIndexConfig,
+    indexes: DashMap<String, IndexCell>,
+    /// Indexes read from a file, used by the first search if the chunks
+    /// haven't changed since
+    loaded: DashMap<String, Arc<ChunkIndex>>,
+}
+
+impl ChunkIndexes {
+    pub fn new(config: VectorIndexConfig) -> Self {
+        Self {
+            config,
+            indexes: DashMap::new(),
+            loaded: DashMap::new(),
+        }
+    }
+
+    /// The index of `kb_id`, built from `db` unless it already is or was
+    /// loaded from the same chunks.
+    ///
+    /// `None` if `db` has a vector index of its own.
+    async fn index(
+        &self,
+        db: &dyn PersistenceLayer,
+        kb_id: &str,
+    ) -> Result<Option<Arc<ChunkIndex>>> {
+        let cell = Arc::clone(&self.indexes.entry(kb_id.to_string()).or_default());
+        let index = cell
+            .get_or_try_init(|| async {
+                let Some(chunks) = db.list_chunk_embeddings(kb_id).await? else {
+                    return anyhow::Ok(None);
+                };
+                if let Some((_, index)) = self.loaded.remove(kb_id) {
+                    if index.fingerprint == fingerprint(&chunks) {
+                        return Ok(Some(index));
+                    }
+                    tracing::info!(kb_id, "Saved HNSW index is out of date, rebuilding");
+                }
+                let config = self.config.clone();
+                let started = std::time::Instant::now();
+                let index =
+                    tokio::task::spawn_blocking(move || ChunkIndex::build(chunks, &config)).await?;
+                tracing::info!(
+                    kb_id,
+                    chunks = index.len(),
+                    elapsed_ms = started.elapsed().as_millis(),
+                    "Built HNSW index"
+                );
+                Ok(Some(Arc::new(index)))
+            })
+            .await?;
+        Ok(index.clone())
+    }
+
+    /// [`PersistenceLayer::search_knowledge_scoped`] through the indexes.
+    ///
+    /// `None` when they can't answer: `db` has its own vector index, or
+    /// more than `hnsw_ef_search` results are wanted.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search(
+        &self,
+        db: &dyn PersistenceLayer,
+        kb_ids: &[&str],
+        query_vec: &[f32],
+        limit: usize,
+        min_score: f32,
+        metadata_filter: Option<&serde_json::Value>,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<Vec<KnowledgeMatch>>> {
+        if limit > self.config.hnsw_ef_search {
+            return Ok(None);
+        }
+        let keep = |chunk: &KnowledgeChunk| {
+            visible_to(chunk.tenant_id.as_deref(), tenant_id)
+                && metadata_filter.is_none_or(|filter| chunk.matches_metadata(filter))
+        };
+
+        let mut matches = Vec::new();
+        for kb_id in kb_ids {
+            let Some(index) = self.index(db, kb_id).await? else {
+                return Ok(None);
+            };
+            matches.extend(index.search(query_vec, limit, min_score, keep));
+        }
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(limit);
+        Ok(Some(matches))
+    }
+
+    /// Drop the index of `kb_id`, or every index, so the next search
+    /// rebuilds it.
+    pub fn invalidate(&self, kb_id: Option<&str>) {
+        match kb_id {
+            Some(kb_id) => {
+                self.indexes.remove(kb_id);
+                self.loaded.remove(kb_id);
+            }
+            None => {
+                self.indexes.clear();
+                self.loaded.clear();
+            }
+        }
+    }
+
+    /// Write every built index to `path`, with the format version and graph
+    /// parameters.
+    pub fn serialize_index(&self, path: &Path) -> Result<()> {
+        let built: Vec<(String, Arc<ChunkIndex>)> = self
+            .indexes
+            .iter()
+            .filter_map(|entry| {
+                let index = entry.value().get()?.as_ref()?;
+                Some((entry.key().clone(), Arc::clone(index)))
+            })
+            .collect();
+        let file = IndexFile {
+            version: INDEX_FORMAT_VERSION,
+            params: IndexParams::from(&self.config),
+            indexes: built
+                .iter()
+                .map(|(kb_id, index)| (kb_id.clone(), index.as_ref()))
+                .collect(),
+        };
+
+        let writer = File::create(path)
+            .with_context(|| format!("Failed to create index file {}", path.display()))?;
+        serde_json::to_writer(BufWriter::new(writer), &file)
+            .with_context(|| format!("Failed to write index file {}", path.display()))?;
+        tracing::info!(path = %path.display(), indexes = file.indexes.len(), "Saved HNSW indexes");
+        Ok(())
+    }
+
+    /// Read indexes written by [`Self::serialize_index`].
+    ///
+    /// A file of another format version or built with other graph
+    /// parameters is ignored. The first search of each knowledge base uses
+    /// its loaded index only if its chunks haven't changed since; otherwise
+    /// the index is rebuilt.
+    pub fn load_index(&self, path: &Path) -> Result<()> {
+        let reader = File::open(path)
+            .with_context(|| format!("Failed to open index file {}", path.display()))?;
+        let file: IndexFile<ChunkIndex> = serde_json::from_reader(BufReader::new(reader))
+            .with_context(|| format!("Failed to read index file {}", path.display()))?;
+        if file.version != INDEX_FORMAT_VERSION || file.params != IndexParams::from(&self.config) {
+            tracing::info!(
+                path = %path.display(),
+                version = file.version,
+                "HNSW index file is of another version or configuration, rebuilding on demand"
+            );
+            return Ok(());
+        }
+
+        tracing::info!(path = %path.display(), indexes = file.indexes.len(), "Loaded HNSW indexes");
+        for (kb_id, index) in file.indexes {
+            self.indexes.remove(&kb_id);
+            self.loaded.insert(kb_id, Arc::new(index));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::persistence::testing::InMemoryPersistence;
+    use std::time::{Duration, Instant};
+    use uuid::Uuid;
+
+    fn chunk(kb_id: &str, content: &str, embedding: Vec<f32>) -> KnowledgeChunk {
+        KnowledgeChunk {
+            id: Uuid::new_v4(),
+            kb_id: kb_id.to_string(),
+            document_id: None,
+            content: content.to_string(),
+            metadata: None,
+            embedding,
+            tenant_id: None,
+            created_at: String::new(),
+        }
+    }
+
+    fn config() -> VectorIndexConfig {
+        VectorIndexConfig {
+            hnsw_enabled: true,
+            ..VectorIndexConfig::default()
+        }
+    }
+
+    /// Deterministic pseudo-random unit vectors.
+    fn vectors(seed: u64, count: usize, dimensions: usize) -> Vec<Vec<f32>> {
+        let mut state = seed;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            f32::from(u16::try_from(state >> 48).unwrap()) / f32::from(u16::MAX) - 0.5
+        };
+        (0..count)
+            .map(|_| Embedding::normalized((0..dimensions).map(|_| next()).collect()).0)
+            .collect()
+    }
+
+    fn contents(matches: &[KnowledgeMatch]) -> Vec<&str> {
+        matches.iter().map(|m| m.chunk.content.as_str()).collect()
+    }
+
+    /// Chunks of `kb-1` the indexes find near `[0, 1]`, joined.
+    async fn found(indexes: &ChunkIndexes, db: &InMemoryPersistence) -> String {
+        let found = indexes
+            .search(db, &["kb-1"], &[0.0, 1.0], 5, 0.5, None, None)
+            .await
+            .unwrap()
+            .unwrap();
+        contents(&found).join(",")
+    }
+
+    #[test]
+    fn test_search_finds_nearest_chunks() {
+        let mut secret = chunk("kb-1", "secret", vec![0.9, 0.1, 0.0]);
+        secret.tenant_id = Some("acme".to_string());
+        let index = ChunkIndex::build(
+            vec![
+                chunk("kb-1", "east", vec![1.0, 0.0, 0.0]),
+                chunk("kb-1", "north", vec![0.0, 1.0, 0.0]),
+                chunk("kb-1", "up", vec![0.0, 0.0, 1.0]),
+                secret,
+            ],
+            &config(),
+        );
+        assert_eq!(index.len(), 4);
+
+        let found = index.search(&[2.0, 0.2, 0.0], 2, 0.0, |_| true);
+        assert_eq!(contents(&found), ["secret", "east"]);
+        assert!((found[1].score - 0.995).abs() < 0.01, "{}", found[1].score);
+
+        // Filtered and weak matches are left out
+        let found = index.search(&[2.0, 0.2, 0.0], 5, 0.5, |c| c.tenant_id.is_none());
+        assert_eq!(contents(&found), ["east"]);
+
+        let empty = ChunkIndex::build(Vec::new(), &config());
+        assert!(empty.is_empty());
+        assert!(empty.search(&[1.0], 5, 0.0, |_| true).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_index_is_rebuilt_after_invalidation() {
+        let db = InMemoryPersistence::default();
+        db.save_chunk(&chunk("kb-1", "east", vec![1.0, 0.0])).await.unwrap();
+        let indexes = ChunkIndexes::new(config());
+
+        assert_eq!(found(&indexes, &db).await, "");
+        // The built index doesn't see new chunks until invalidated
+        db.save_chunk(&chunk("kb-1", "north", vec![0.0, 1.0])).await.unwrap();
+        assert_eq!(found(&indexes, &db).await, "");
+        indexes.invalidate(Some("kb-1"));
+        assert_eq!(found(&indexes, &db).await, "north");
+
+        // Indexes survive a restart while their chunks stay the same
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hnsw.json");
+        indexes.serialize_index(&path).unwrap();
+        let reloaded = ChunkIndexes::new(config());
+        reloaded.load_index(&path).unwrap();
+        let saved = Arc::clone(reloaded.loaded.get("kb-1").unwrap().value());
+        let index = reloaded.index(&db, "kb-1").await.unwrap().unwrap();
+        assert!(Arc::ptr_eq(&saved, &index));
+
+        // and are rebuilt once they changed
+        let reloaded = ChunkIndexes::new(config());
+        reloaded.load_index(&path).unwrap();
+        db.save_chunk(&chunk("kb-1", "north again", vec![0.1, 1.0])).await.unwrap();
+        assert_eq!(found(&reloaded, &db).await, "north,north again");
+
+        // A file built with other parameters isn't loaded
+        let other = ChunkIndexes::new(VectorIndexConfig {
+            hnsw_m: 8,
+            ..config()
+        });
+        other.load_index(&path).unwrap();
+        assert!(other.loaded.is_empty());
+
+        // Too many results wanted: left to the store
+        let too_many = indexes
+            .search(&db, &["kb-1"], &[0.0, 1.0], 1_000, 0.5, None, None)
+            .await
+            .unwrap();
+        assert!(too_many.is_none());
+    }
+
+    #[test]
+    #[ignore = "benchmark; run with --release"]
+    fn bench_search_50k_vectors() {
+        const DIMENSIONS: usize = 64;
+        const QUERIES: u32 = 200;
+
+        let data = vectors(0x2545_f491_4f6c_dd1d, 50_000, DIMENSIONS);
+        let chunks = data
+            .iter()
+            .enumerate()
+            .map(|(i, v)| chunk("kb-1", &i.to_string(), v.clone()))
+            .collect();
+        let started = Instant::now();
+        let index = ChunkIndex::build(chunks, &config());
+        tracing::info!(elapsed = ?started.elapsed(), "Built benchmark index");
+
+        let queries = vectors(0x9e37_79b9_7f4a_7c15, QUERIES as usize, DIMENSIONS);
+        let mut hnsw = Duration::ZERO;
+        let mut linear = Duration::ZERO;
+        for query in &queries {
+            let started = Instant::now();
+            let found = index.search(query, 10, -1.0, |_| true);
+            hnsw += started.elapsed();
+            assert_eq!(found.len(), 10);
+
+            let started = Instant::now();
+            let mut scores: Vec<f32> = data
+                .iter()
+                .map(|v| v.iter().zip(query).map(|(a, b)| a * b).sum())
+                .collect();
+            scores.sort_by(|a, b| b.total_cmp(a));
+            linear += started.elapsed();
+            std::hint::black_box(scores);
+        }
+
+        let (hnsw, linear) = (hnsw / QUERIES, linear / QUERIES);
+        tracing::info!(?hnsw, ?linear, "Mean search time");
+        assert!(hnsw < Duration::from_millis(1), "{hnsw:?}");
+        assert!(hnsw < linear);
+    }
+}
@@ -0,0 +1,114 @@
+//! Process-wide cap on concurrent embedding computations.
+//!
+//! Local fastembed inference is CPU-bound. Without a cap, simultaneous
+//! searches, ingestion, and skill indexing can saturate every core and starve
+//! request handling. Local inference acquires a permit from the global
+//! limiter first (remote embedding APIs are I/O-bound and don't); the
+//! underlying semaphore is FIFO, so ingestion batches and interactive queries
+//! are served in arrival order.
+
+use anyhow::Result;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Prometheus histogram for time spent waiting for an embedding permit.
+const QUEUE_WAIT_METRIC: &str = "uar_embedding_queue_wait_seconds";
+
+static GLOBAL: OnceLock<EmbeddingLimiter> = OnceLock::new();
+
+/// Semaphore limiting how many embedding computations run at once.
+#[derive(Debug)]
+pub struct EmbeddingLimiter {
+    semaphore: Arc<Semaphore>,
+    max_concurrent: usize,
+}
+
+impl EmbeddingLimiter {
+    /// Default maximum number of concurrent embedding computations.
+    pub const DEFAULT_MAX_CONCURRENT: usize = 2;
+
+    /// Create a limiter allowing `max_concurrent` computations at once.
+    pub fn new(max_concurrent: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+        }
+    }
+
+    /// Configure the process-wide limiter.
+    ///
+    /// Must be called before the first embedding; returns `false` if the
+    /// global limiter was already initialized.
+    pub fn configure_global(max_concurrent: usize) -> bool {
+        GLOBAL.set(Self::new(max_concurrent)).is_ok()
+    }
+
+    /// The process-wide limiter (default-sized if never configured).
+    pub fn global() -> &'static Self {
+        GLOBAL.get_or_init(|| Self::new(Self::DEFAULT_MAX_CONCURRENT))
+    }
+
+    /// Wait for a permit, recording the queue wait time.
+    ///
+    /// The permit is released when dropped, so hold it for the duration of
+    /// the computation (including inside `spawn_blocking`).
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit> {
+        let started = Instant::now();
+        let permit = Arc::clone(&self.semaphore).acquire_owned().await?;
+        metrics::histogram!(QUEUE_WAIT_METRIC).record(started.elapsed().as_secs_f64());
+        Ok(permit)
+    }
+
+    /// Configured maximum concurrency.
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    /// Permits currently available.
+    pub fn available(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_embeds_respect_cap() {
+        let limiter = Arc::new(EmbeddingLimiter::new(2));
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let limiter = Arc::clone(&limiter);
+                let in_flight = Arc::clone(&in_flight);
+                let peak = Arc::clone(&peak);
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire().await.unwrap();
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(limiter.available(), 2);
+    }
+
+    #[test]
+    fn test_zero_is_clamped() {
+        assert_eq!(EmbeddingLimiter::new(0).max_concurrent(), 1);
+    }
+}
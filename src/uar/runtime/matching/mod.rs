@@ -1,5 +1,11 @@
+pub mod cache;
+pub mod hnsw;
+pub mod limiter;
 pub mod tag;
 pub mod vector;
 
+pub use cache::EmbeddingCache;
+pub use hnsw::ChunkIndexes;
+pub use limiter::EmbeddingLimiter;
 pub use tag::TagMatcher;
 pub use vector::VectorMatcher;
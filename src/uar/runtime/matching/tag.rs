@@ -20,21 +20,21 @@ impl SkillMatcher for TagMatcher {
         let lower_query = query.to_lowercase();
 
         for skill in skills {
-            // Check triggers
-            for keyword in &skill.triggers.keywords {
-                let lower_kw = keyword.to_lowercase();
-                // Simple inclusion or exact? Let's do inclusion logic for now but specific enough
-                // Or maybe we treat triggers as "tags"
+            // Check triggers: keywords by inclusion, then patterns
+            let triggered = skill
+                .triggers
+                .keywords
+                .iter()
+                .any(|keyword| lower_query.contains(&keyword.to_lowercase()))
+                || registry.matches_patterns(&skill, query);
 
-                if lower_query.contains(&lower_kw) {
-                    matches.push(SkillMatch {
-                        skill_id: skill.skill_id.clone(),
-                        score: 1.0, // High confidence
-                        reason: MatchReason::ExplicitTag,
-                        skill: skill.clone(),
-                    });
-                    break;
-                }
+            if triggered {
+                matches.push(SkillMatch {
+                    skill_id: skill.skill_id.clone(),
+                    score: 1.0, // High confidence
+                    reason: MatchReason::ExplicitTag,
+                    skill,
+                });
             }
         }
 
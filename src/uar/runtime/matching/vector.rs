@@ -1,75 +1,269 @@
+use super::cache::EmbeddingCache;
+use super::hnsw::ChunkIndexes;
+use crate::config::VectorIndexConfig;
+use crate::uar::domain::knowledge::{KbConfig, KnowledgeMatch};
 use crate::uar::domain::matching::{MatchReason, SkillMatch, SkillMatcher};
+use crate::uar::persistence::PersistenceLayer;
+use crate::uar::rag::embedding::{EmbeddingProvider, EmbeddingProviderFactory, FastEmbedProvider};
 use crate::uar::runtime::skills::SkillRegistry;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use dashmap::DashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::{Mutex, broadcast};
 use tracing::{info, warn};
 
 pub struct VectorMatcher {
-    model: Arc<Mutex<Option<TextEmbedding>>>,
+    // Default embedding backend (skills, memory, un-scoped RAG)
+    provider: Arc<dyn EmbeddingProvider>,
+    // Providers for knowledge bases configured with a different backend/model
+    kb_providers: DashMap<String, Arc<dyn EmbeddingProvider>>,
     // Cache: skill_id -> embedding
     embeddings: Arc<Mutex<Vec<(String, Vec<f32>)>>>,
     threshold: f32,
+    // Optional text-hash -> embedding cache shared by all embed_batch callers
+    cache: Option<Arc<EmbeddingCache>>,
+    // Optional HNSW indexes searched instead of the store's linear scan
+    chunk_index: Option<ChunkIndexes>,
+    // Set once the default provider initialized successfully
+    ready: AtomicBool,
+    // Outcome of the startup warmup; `None` until `warm_up` ran
+    warmup: RwLock<Option<Result<(), String>>>,
 }
 
 impl std::fmt::Debug for VectorMatcher {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("VectorMatcher")
-            .field("model_loaded", &"Dynamic")
+            .field("provider", &self.provider)
             .field("embeddings_count", &"Dynamic")
             .field("threshold", &self.threshold)
+            .field("cache_entries", &self.cache.as_ref().map(|c| c.len()))
+            .field("chunk_index", &self.chunk_index.is_some())
             .finish()
     }
 }
 
 impl VectorMatcher {
+    /// Create a matcher using the default local fastembed model.
     pub fn new(threshold: f32) -> Self {
+        Self::with_provider(threshold, Arc::new(FastEmbedProvider::default()))
+    }
+
+    /// Create a matcher backed by a specific embedding provider.
+    pub fn with_provider(threshold: f32, provider: Arc<dyn EmbeddingProvider>) -> Self {
         Self {
-            model: Arc::new(Mutex::new(None)),
+            provider,
+            kb_providers: DashMap::new(),
             embeddings: Arc::new(Mutex::new(Vec::new())),
             threshold,
+            cache: None,
+            chunk_index: None,
+            ready: AtomicBool::new(false),
+            warmup: RwLock::new(None),
         }
     }
 
-    pub async fn initialize(&self) -> Result<()> {
-        let mut model_guard = self.model.lock().await;
-        if model_guard.is_none() {
-            info!("Initializing fastembed model (BG-Small-En-V1.5)...");
-            let mut options = InitOptions::new(EmbeddingModel::BGESmallENV15);
-            options.show_download_progress = true;
+    /// Enable the embedding cache, holding at most `capacity` embeddings.
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Arc::new(EmbeddingCache::new(capacity)));
+        self
+    }
+
+    /// The embedding cache, if enabled.
+    pub fn cache(&self) -> Option<&EmbeddingCache> {
+        self.cache.as_deref()
+    }
 
-            let model = TextEmbedding::try_new(options)?;
-            *model_guard = Some(model);
+    /// Search chunks through HNSW indexes built from the store, when it has
+    /// no vector index of its own.
+    pub fn with_chunk_index(mut self, config: VectorIndexConfig) -> Self {
+        self.chunk_index = Some(ChunkIndexes::new(config));
+        self
+    }
+
+    /// Chunks of `kb_ids` most similar to `query_vec`, as
+    /// [`PersistenceLayer::search_knowledge_scoped`] finds them.
+    ///
+    /// With the chunk index enabled the answer is approximate, and comes
+    /// from the index unless the store has its own or more results are
+    /// wanted than one index search returns.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_chunks(
+        &self,
+        db: &dyn PersistenceLayer,
+        kb_ids: &[&str],
+        query_vec: &[f32],
+        limit: usize,
+        min_score: f32,
+        metadata_filter: Option<&serde_json::Value>,
+        tenant_id: Option<&str>,
+    ) -> Result<Vec<KnowledgeMatch>> {
+        if let Some(index) = &self.chunk_index
+            && let Some(matches) = index
+                .search(db, kb_ids, query_vec, limit, min_score, metadata_filter, tenant_id)
+                .await?
+        {
+            return Ok(matches);
         }
+        Ok(db
+            .search_knowledge_scoped(
+                kb_ids,
+                query_vec,
+                limit,
+                min_score,
+                metadata_filter,
+                tenant_id,
+            )
+            .await?)
+    }
+
+    /// Drop the chunk index of `kb_id` (or of every knowledge base) after
+    /// its chunks changed; the next search rebuilds it.
+    pub fn invalidate_chunk_index(&self, kb_id: Option<&str>) {
+        if let Some(index) = &self.chunk_index {
+            index.invalidate(kb_id);
+        }
+    }
+
+    /// Drop the chunk index of every knowledge base ID received on
+    /// `chunks_saved`, for as long as the matcher lives. Falling behind on
+    /// the IDs drops every index.
+    pub fn invalidate_on(self: &Arc<Self>, mut chunks_saved: broadcast::Receiver<String>) {
+        if self.chunk_index.is_none() {
+            return;
+        }
+        let matcher = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                let kb_id = match chunks_saved.recv().await {
+                    Ok(kb_id) => Some(kb_id),
+                    // Some IDs were missed, so any index may be stale
+                    Err(broadcast::error::RecvError::Lagged(_)) => None,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let Some(matcher) = matcher.upgrade() else {
+                    break;
+                };
+                matcher.invalidate_chunk_index(kb_id.as_deref());
+            }
+        });
+    }
+
+    /// Save the built chunk indexes to `path`, if the index is enabled.
+    pub fn serialize_index(&self, path: &Path) -> Result<()> {
+        match &self.chunk_index {
+            Some(index) => index.serialize_index(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Load chunk indexes saved by [`Self::serialize_index`], if the index
+    /// is enabled.
+    pub fn load_index(&self, path: &Path) -> Result<()> {
+        match &self.chunk_index {
+            Some(index) => index.load_index(path),
+            None => Ok(()),
+        }
+    }
+
+    /// The default embedding provider.
+    pub fn provider(&self) -> &Arc<dyn EmbeddingProvider> {
+        &self.provider
+    }
+
+    pub async fn initialize(&self) -> Result<()> {
+        self.provider.initialize().await?;
+        self.ready.store(true, Ordering::Release);
         Ok(())
     }
 
-    pub async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
-        let mut model_guard = self.model.lock().await;
-        if let Some(_) = &mut *model_guard {
-            let mut owned_model = model_guard
-                .take()
-                .context("Model unexpectedly None during embed_batch")?;
-
-            let (embeddings_res, returned_model) = tokio::task::spawn_blocking(move || {
-                let res = owned_model.embed(texts, None);
-                (res, owned_model)
-            })
-            .await?;
-
-            *model_guard = Some(returned_model);
-            embeddings_res.map_err(|e| anyhow::anyhow!(e))
+    /// Whether [`VectorMatcher::initialize`] has succeeded.
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    /// Initialize the provider of every given knowledge base configuration.
+    ///
+    /// Remote providers send a ping, so unreachable endpoints and rejected
+    /// credentials surface at startup. Failures are logged and kept for
+    /// [`VectorMatcher::warmup_result`]; they never abort the warmup.
+    pub async fn warm_up<'a>(&self, configs: impl IntoIterator<Item = &'a KbConfig>) {
+        let mut warmed = std::collections::HashSet::new();
+        let mut failures = Vec::new();
+        for config in configs {
+            let key = EmbeddingProviderFactory::cache_key(config);
+            if !warmed.insert(key.clone()) {
+                continue;
+            }
+            let result = match self.provider_for(config) {
+                Ok(provider) => provider.initialize().await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!(provider = %key, "Embedding provider warmup failed: {:#}", e);
+                failures.push(format!("{key}: {e:#}"));
+            }
+        }
+
+        let outcome = if failures.is_empty() {
+            info!("Warmed up {} embedding provider(s)", warmed.len());
+            Ok(())
         } else {
-            // Try to init? Or fail?
-            // Since init is async and we hold lock? NO, we just checked lock.
-            // We can drop lock and init?
-            // For safety, error out. Usage should ensure init.
-            Err(anyhow::anyhow!("VectorMatcher not initialized"))
+            Err(failures.join("; "))
+        };
+        *self.warmup.write().unwrap() = Some(outcome);
+    }
+
+    /// Outcome of [`VectorMatcher::warm_up`], or `None` if it never ran.
+    pub fn warmup_result(&self) -> Option<Result<(), String>> {
+        self.warmup.read().unwrap().clone()
+    }
+
+    pub async fn embed_batch(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        match &self.cache {
+            Some(cache) => {
+                cache
+                    .get_or_embed(texts, |misses| self.provider.embed(misses))
+                    .await
+            }
+            None => self.provider.embed(texts).await,
         }
     }
 
+    /// Embed texts with the provider a knowledge base is configured for.
+    ///
+    /// KBs using the default model share the default provider (and cache);
+    /// others get a provider built on first use and reused afterwards.
+    pub async fn embed_for_kb(&self, config: &KbConfig, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        if Self::uses_default_model(config) {
+            return self.embed_batch(texts).await;
+        }
+        self.provider_for(config)?.embed(texts).await
+    }
+
+    /// The embedding provider for a knowledge base configuration.
+    pub fn provider_for(&self, config: &KbConfig) -> Result<Arc<dyn EmbeddingProvider>> {
+        if Self::uses_default_model(config) {
+            return Ok(Arc::clone(&self.provider));
+        }
+
+        let key = EmbeddingProviderFactory::cache_key(config);
+        if let Some(provider) = self.kb_providers.get(&key) {
+            return Ok(Arc::clone(&provider));
+        }
+
+        let provider = EmbeddingProviderFactory::create(config, self.cache.clone())?;
+        self.kb_providers.insert(key, Arc::clone(&provider));
+        Ok(provider)
+    }
+
+    fn uses_default_model(config: &KbConfig) -> bool {
+        config.embedding_provider == KbConfig::default_embedding_provider()
+            && config.embedding_model == KbConfig::default_embedding_model()
+    }
+
     pub async fn index_skills(&self, registry: &SkillRegistry) -> Result<()> {
         let skills = registry.list();
         let mut texts = Vec::new();
@@ -86,45 +280,15 @@ impl VectorMatcher {
             return Ok(());
         }
 
-        let mut model_guard = self.model.lock().await; // Lock mutably!
-        if let Some(_) = &mut *model_guard {
-            info!("Generating embeddings for {} skills...", texts.len());
-            // Move model to closure? No, `model` is behind Mutex guard, not Send/static easily?
-            // Actually `TextEmbedding` is Send/Sync.
-            // But we have `&mut` to it from the guard.
-            // We can't move the guard into spawn_blocking easily if we want to return it.
-            // But we can clone the model if it was Arc... it's not.
-            // FastEmbed `TextEmbedding` might be heavy to clone?
-            // "The Model passed to `init` is cheap to clone" - checking fastembed docs...
-            // Actually, we don't have a clone.
-            // If we block here, we block the `await`.
-            // Let's rely on the fact that this is a test.
-            // Maybe just allow blocking? `tokio::test` usually handles some blocking but if it starves...
-
-            // Let's try to just run it. The hang might be `InitOptions` downloading files prompting...
-            // "show_download_progress: true" might be messing with stdout capturing in test?
-            let mut owned_model = model_guard
-                .take()
-                .context("Model unexpectedly None during indexing")?;
-            let (embeddings_res, returned_model) = tokio::task::spawn_blocking(move || {
-                let res = owned_model.embed(texts, None);
-                (res, owned_model)
-            })
-            .await?; // Await the spawn_blocking
-
-            *model_guard = Some(returned_model); // Put the model back
-
-            let embeddings = embeddings_res?; // Unwrap result
-
-            let mut cache = self.embeddings.lock().await;
-            cache.clear();
-            for (i, emb) in embeddings.into_iter().enumerate() {
-                cache.push((ids[i].clone(), emb));
-            }
-            info!("Skill vector index built.");
-        } else {
-            warn!("Model not initialized, skipping indexing.");
+        info!("Generating embeddings for {} skills...", texts.len());
+        let embeddings = self.embed_batch(texts).await?;
+
+        let mut cache = self.embeddings.lock().await;
+        cache.clear();
+        for (i, emb) in embeddings.into_iter().enumerate() {
+            cache.push((ids[i].clone(), emb));
         }
+        info!("Skill vector index built.");
 
         Ok(())
     }
@@ -145,34 +309,18 @@ impl VectorMatcher {
 #[async_trait]
 impl SkillMatcher for VectorMatcher {
     async fn match_skills(&self, query: &str, registry: &SkillRegistry) -> Result<Vec<SkillMatch>> {
-        // Ensure initialized (lazy logic or expect init called?)
-        // Ideally should be initialized at startup.
-
-        let mut model_guard = self.model.lock().await; // Lock mutably
-        let query_embedding = if let Some(_) = &mut *model_guard {
-            let mut owned_model = model_guard.take().context("Model unexpected None")?;
-            let query_owned = query.to_string();
-
-            info!("Embedding query: {}", query);
-            let (embeddings_res, returned_model) = tokio::task::spawn_blocking(move || {
-                let res = owned_model.embed(vec![query_owned], None);
-                (res, owned_model)
-            })
-            .await?;
-            info!("Query embedding generated");
-
-            *model_guard = Some(returned_model);
-            let embeddings = embeddings_res?;
-
-            embeddings
+        info!("Embedding query: {}", query);
+        let query_embedding = match self.embed_batch(vec![query.to_string()]).await {
+            Ok(embeddings) => embeddings
                 .into_iter()
                 .next()
-                .context("No embedding generated")?
-        } else {
-            warn!("VectorMatcher not initialized");
-            return Ok(vec![]);
+                .context("No embedding generated")?,
+            Err(e) => {
+                warn!("Skill query embedding failed: {:?}", e);
+                return Ok(vec![]);
+            }
         };
-        drop(model_guard); // Release lock
+        info!("Query embedding generated");
 
         // Check if indexing is needed
         {
@@ -207,3 +355,105 @@ impl SkillMatcher for VectorMatcher {
         Ok(matches)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uar::domain::knowledge::KnowledgeChunk;
+    use crate::uar::persistence::testing::InMemoryPersistence;
+    use crate::uar::testing::StubEmbedder;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    fn chunk(kb_id: &str, content: &str, embedding: Vec<f32>) -> KnowledgeChunk {
+        KnowledgeChunk {
+            id: Uuid::new_v4(),
+            kb_id: kb_id.to_string(),
+            document_id: None,
+            content: content.to_string(),
+            metadata: None,
+            embedding,
+            tenant_id: None,
+            created_at: String::new(),
+        }
+    }
+
+    /// Contents of the `kb_id` chunks the matcher finds near `[0, 1]`, joined.
+    async fn found(matcher: &VectorMatcher, db: &InMemoryPersistence, kb_id: &str) -> String {
+        let found = matcher
+            .search_chunks(db, &[kb_id], &[0.0, 1.0], 5, -1.0, None, None)
+            .await
+            .unwrap();
+        let contents: Vec<_> = found.iter().map(|m| m.chunk.content.as_str()).collect();
+        contents.join(",")
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_on_drops_only_the_saved_knowledge_base() {
+        let db = InMemoryPersistence::default();
+        let matcher = Arc::new(
+            VectorMatcher::with_provider(0.5, Arc::new(StubEmbedder::default()))
+                .with_chunk_index(VectorIndexConfig {
+                    hnsw_enabled: true,
+                    ..VectorIndexConfig::default()
+                }),
+        );
+        for kb_id in ["kb-1", "kb-2"] {
+            db.save_chunk(&chunk(kb_id, "east", vec![1.0, 0.0])).await.unwrap();
+            assert_eq!(found(&matcher, &db, kb_id).await, "east");
+        }
+        for kb_id in ["kb-1", "kb-2"] {
+            db.save_chunk(&chunk(kb_id, "north", vec![0.0, 1.0])).await.unwrap();
+        }
+
+        let (chunks_saved, receiver) = broadcast::channel(4);
+        matcher.invalidate_on(receiver);
+        chunks_saved.send("kb-1".to_string()).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while found(&matcher, &db, "kb-1").await != "north,east" {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("kb-1 index was not rebuilt");
+        assert_eq!(found(&matcher, &db, "kb-2").await, "east");
+    }
+
+    /// Embeds like [`StubEmbedder`], counting how often it is called.
+    #[derive(Debug, Default)]
+    struct CountingEmbedder {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for CountingEmbedder {
+        async fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            StubEmbedder::default().embed(texts).await
+        }
+
+        fn dimensions(&self) -> usize {
+            2
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_embeddings_skip_the_model() {
+        let embedder = Arc::new(CountingEmbedder::default());
+        let matcher = VectorMatcher::with_provider(0.5, Arc::clone(&embedder) as _).with_cache(10);
+
+        matcher.embed_batch(vec!["hello".to_string()]).await.unwrap();
+        matcher.embed_batch(vec!["hello".to_string()]).await.unwrap();
+        assert_eq!(embedder.calls.load(Ordering::SeqCst), 1);
+
+        // Only the new text goes to the model
+        let both = matcher
+            .embed_batch(vec!["hello".to_string(), "world".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(both.len(), 2);
+        assert_eq!(embedder.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(matcher.cache().unwrap().len(), 2);
+    }
+}
@@ -1,4 +1,14 @@
+pub mod agents;
+pub mod chain;
 pub mod context;
+pub mod eval;
 pub mod manager;
 pub mod matching;
+pub mod partial_usage;
+pub mod phases;
+pub mod pricing;
+pub mod prompt;
+pub mod replay;
+pub mod skill_metrics;
 pub mod skills;
+pub mod webhook;
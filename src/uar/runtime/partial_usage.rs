@@ -0,0 +1,81 @@
+//! Running completion token counts for progress UIs.
+
+use crate::uar::runtime::context::token_service::TokenService;
+use std::time::{Duration, Instant};
+use tiktoken_rs::CoreBPE;
+
+/// Counts completion tokens as a run streams.
+///
+/// Output is re-tokenized at most once per `interval` with the tokenizer the
+/// context manager uses for the model, so counts agree with its budgets.
+#[derive(Debug)]
+pub struct PartialUsageCounter {
+    bpe: &'static CoreBPE,
+    interval: Duration,
+    output: String,
+    last_emit: Option<Instant>,
+    last_count: u32,
+}
+
+impl PartialUsageCounter {
+    pub fn new(model: &str, interval: Duration) -> Self {
+        Self {
+            bpe: TokenService::tokenizer(model),
+            interval,
+            output: String::new(),
+            last_emit: None,
+            last_count: 0,
+        }
+    }
+
+    /// Append a text delta; returns the tokens generated so far when a new
+    /// count is due.
+    pub fn push(&mut self, delta: &str) -> Option<u32> {
+        self.output.push_str(delta);
+        if self.last_emit.is_some_and(|last| last.elapsed() < self.interval) {
+            return None;
+        }
+        self.last_emit = Some(Instant::now());
+
+        let tokens = self.bpe.encode_with_special_tokens(&self.output).len();
+        let count = u32::try_from(tokens).unwrap_or(u32::MAX);
+        // Re-tokenizing can merge a trailing fragment into fewer tokens;
+        // never report a count going backwards
+        if count <= self.last_count {
+            return None;
+        }
+        self.last_count = count;
+        Some(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_counts_increase_monotonically() {
+        let deltas = futures::stream::iter(
+            "The borrow checker enforces that references never outlive the data they point to."
+                .split_inclusive(' ')
+                .map(str::to_string),
+        );
+        let mut counter = PartialUsageCounter::new("gpt-4o", Duration::ZERO);
+
+        let counts: Vec<u32> = deltas
+            .filter_map(|delta| std::future::ready(counter.push(&delta)))
+            .collect()
+            .await;
+
+        assert!(counts.len() > 5);
+        assert!(counts.windows(2).all(|w| w[0] < w[1]), "{counts:?}");
+    }
+
+    #[test]
+    fn test_interval_throttles_counts() {
+        let mut counter = PartialUsageCounter::new("gpt-4o", Duration::from_secs(3600));
+        assert_eq!(counter.push("Hello"), Some(1));
+        assert_eq!(counter.push(" world"), None);
+    }
+}
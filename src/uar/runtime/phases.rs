@@ -0,0 +1,71 @@
+//! Run phase transitions for progress UIs.
+
+use crate::normalized::NormalizedEvent;
+use crate::uar::domain::events::RunPhase;
+
+/// Tracks the phase a run is in, reporting each change once.
+#[derive(Debug, Default)]
+pub struct PhaseTracker {
+    current: Option<RunPhase>,
+}
+
+impl PhaseTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move to `phase`; returns it when the run was in another phase.
+    pub fn enter(&mut self, phase: RunPhase) -> Option<RunPhase> {
+        if self.current == Some(phase) {
+            return None;
+        }
+        self.current = Some(phase);
+        Some(phase)
+    }
+
+    /// Move to the phase `event` shows the run is in, if it shows one.
+    ///
+    /// Output deltas mean the model is generating; completed tool calls and
+    /// their results mean tools are executing.
+    pub fn observe(&mut self, event: &NormalizedEvent) -> Option<RunPhase> {
+        let phase = match event {
+            NormalizedEvent::MessageDelta { .. }
+            | NormalizedEvent::ThinkingDelta { .. }
+            | NormalizedEvent::ReasoningDelta { .. }
+            | NormalizedEvent::RefusalDelta { .. }
+            | NormalizedEvent::ToolCallDelta { .. } => RunPhase::Generating,
+            NormalizedEvent::ToolCallComplete { .. }
+            | NormalizedEvent::ToolApprovalRequest { .. }
+            | NormalizedEvent::ToolResult { .. } => RunPhase::ToolExecution,
+            _ => return None,
+        };
+        self.enter(phase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_each_change_once() {
+        let mut tracker = PhaseTracker::new();
+        assert_eq!(tracker.enter(RunPhase::Generating), Some(RunPhase::Generating));
+
+        let delta = NormalizedEvent::MessageDelta {
+            text: "Hi".to_string(),
+        };
+        assert_eq!(tracker.observe(&delta), None);
+
+        let result = NormalizedEvent::ToolResult {
+            id: "call_1".to_string(),
+            name: "mirror".to_string(),
+            content: "{}".to_string(),
+            success: true,
+        };
+        assert_eq!(tracker.observe(&result), Some(RunPhase::ToolExecution));
+        assert_eq!(tracker.observe(&result), None);
+        assert_eq!(tracker.observe(&delta), Some(RunPhase::Generating));
+        assert_eq!(tracker.observe(&NormalizedEvent::Done), None);
+    }
+}
@@ -0,0 +1,85 @@
+//! Dollar cost of LLM usage.
+
+use crate::config::{ModelPrice, PricingConfig};
+use crate::uar::domain::runs::TokenUsage;
+use std::collections::HashMap;
+
+/// Model prices, looked up by exact model name.
+///
+/// Lookups are case-insensitive and ignore a `provider/` prefix, but are
+/// otherwise exact: `gpt-4o-mini` is never priced as `gpt-4o`.
+#[derive(Debug, Clone, Default)]
+pub struct PricingTable {
+    prices: HashMap<String, ModelPrice>,
+}
+
+impl PricingTable {
+    pub fn new(config: &PricingConfig) -> Self {
+        Self {
+            prices: config
+                .models
+                .iter()
+                .map(|(model, price)| (normalize(model), *price))
+                .collect(),
+        }
+    }
+
+    /// Price of `model`, if configured.
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        self.prices.get(&normalize(model)).copied()
+    }
+
+    /// Cost of `usage` on `model` in USD, or `None` for unpriced models.
+    pub fn estimate_cost(&self, model: &str, usage: &TokenUsage) -> Option<f64> {
+        let price = self.price(model)?;
+        Some(
+            f64::from(usage.prompt_tokens) * price.input_per_million / 1_000_000.0
+                + f64::from(usage.completion_tokens) * price.output_per_million / 1_000_000.0,
+        )
+    }
+}
+
+fn normalize(model: &str) -> String {
+    let model = model.trim().to_ascii_lowercase();
+    match model.rsplit_once('/') {
+        Some((_, name)) => name.to_string(),
+        None => model,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> PricingTable {
+        let mut models = HashMap::new();
+        models.insert(
+            "gpt-4o".to_string(),
+            ModelPrice {
+                input_per_million: 2.5,
+                output_per_million: 10.0,
+            },
+        );
+        PricingTable::new(&PricingConfig { models })
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let usage = TokenUsage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 500_000,
+            total_tokens: 1_500_000,
+        };
+        let cost = table().estimate_cost("gpt-4o", &usage).unwrap();
+        assert!((cost - 7.5).abs() < 1e-9);
+        // Provider prefixes and case don't matter
+        assert!(table().estimate_cost("OpenAI/GPT-4o", &usage).is_some());
+    }
+
+    #[test]
+    fn test_unknown_model_has_no_cost() {
+        let usage = TokenUsage::default();
+        assert_eq!(table().estimate_cost("gpt-4o-mini", &usage), None);
+        assert_eq!(table().estimate_cost("llama3", &usage), None);
+    }
+}
@@ -0,0 +1,141 @@
+//! System prompt templates.
+//!
+//! An agent's `prompt.system` is a Handlebars template rendered for each
+//! run. Built-in variables describe the run; the agent's
+//! `prompt.variables` and then the run request's `variables` are layered
+//! on top, so a client can e.g. pass its user's display name as
+//! `user_name`.
+
+use handlebars::Handlebars;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Error code of runs whose system prompt doesn't render.
+pub const TEMPLATE_ERROR_CODE: &str = "TEMPLATE_ERROR";
+
+/// Variables every system prompt can use.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BuiltinVariables {
+    /// The user the run is for (their ID unless the request names them)
+    pub user_name: String,
+    pub session_id: String,
+    pub run_id: String,
+    /// Today in UTC, as `YYYY-MM-DD`
+    pub current_date: String,
+    /// Knowledge bases the agent retrieves from, comma-separated
+    pub kb_names: String,
+    /// Tools the agent may call, comma-separated
+    pub tool_names: String,
+}
+
+impl BuiltinVariables {
+    /// Variables of a run, dated today.
+    pub fn new(
+        user_name: Option<&str>,
+        session_id: &str,
+        run_id: &str,
+        kb_names: &[String],
+        tool_names: &[String],
+    ) -> Self {
+        Self {
+            user_name: user_name.unwrap_or_default().to_string(),
+            session_id: session_id.to_string(),
+            run_id: run_id.to_string(),
+            current_date: chrono::Utc::now().format("%Y-%m-%d").to_string(),
+            kb_names: kb_names.join(", "),
+            tool_names: tool_names.join(", "),
+        }
+    }
+}
+
+/// Render a system prompt `template`.
+///
+/// `agent_variables` override the built-ins and `request_variables`
+/// override both. Unknown variables are an error rather than rendering as
+/// nothing, and output is not HTML-escaped: the result is a prompt, not
+/// markup.
+pub fn render_system_prompt(
+    template: &str,
+    builtins: &BuiltinVariables,
+    agent_variables: &HashMap<String, String>,
+    request_variables: &HashMap<String, String>,
+) -> Result<String, handlebars::RenderError> {
+    static ENGINE: OnceLock<Handlebars<'static>> = OnceLock::new();
+    let engine = ENGINE.get_or_init(|| {
+        let mut hb = Handlebars::new();
+        hb.register_escape_fn(handlebars::no_escape);
+        hb.set_strict_mode(true);
+        hb
+    });
+
+    let mut context = match serde_json::to_value(builtins) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    for (name, value) in agent_variables.iter().chain(request_variables) {
+        context.insert(name.clone(), serde_json::Value::String(value.clone()));
+    }
+    engine.render_template(template, &context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtins() -> BuiltinVariables {
+        BuiltinVariables {
+            current_date: "2026-10-16".to_string(),
+            ..BuiltinVariables::new(
+                Some("ada"),
+                "sess-1",
+                "run-1",
+                &["docs".to_string(), "faq".to_string()],
+                &["time__now".to_string(), "web__search".to_string()],
+            )
+        }
+    }
+
+    #[test]
+    fn test_builtin_variables() {
+        let template = "User: {{user_name}}\nSession: {{session_id}}\nRun: {{run_id}}\n\
+            Date: {{current_date}}\nKBs: {{kb_names}}\nTools: {{tool_names}}";
+        let prompt =
+            render_system_prompt(template, &builtins(), &HashMap::new(), &HashMap::new()).unwrap();
+        assert_eq!(
+            prompt,
+            "User: ada\nSession: sess-1\nRun: run-1\nDate: 2026-10-16\n\
+             KBs: docs, faq\nTools: time__now, web__search"
+        );
+
+        let today = BuiltinVariables::new(None, "s", "r", &[], &[]).current_date;
+        assert!(chrono::NaiveDate::parse_from_str(&today, "%Y-%m-%d").is_ok(), "{today}");
+    }
+
+    #[test]
+    fn test_request_variables_override_agent_variables() {
+        let agent = HashMap::from([
+            ("company_name".to_string(), "Acme".to_string()),
+            ("tone".to_string(), "formal".to_string()),
+        ]);
+        let request = HashMap::from([
+            ("tone".to_string(), "casual".to_string()),
+            ("user_name".to_string(), "Ada <Lovelace>".to_string()),
+        ]);
+        let prompt = render_system_prompt(
+            "{{company_name}} assistant, {{tone}}, talking to {{user_name}}",
+            &builtins(),
+            &agent,
+            &request,
+        )
+        .unwrap();
+        assert_eq!(prompt, "Acme assistant, casual, talking to Ada <Lovelace>");
+    }
+
+    #[test]
+    fn test_invalid_templates_fail() {
+        let none = HashMap::new();
+        assert!(render_system_prompt("{{#if}}", &builtins(), &none, &none).is_err());
+        assert!(render_system_prompt("Hi {{nickname}}", &builtins(), &none, &none).is_err());
+    }
+}
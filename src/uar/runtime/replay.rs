@@ -0,0 +1,250 @@
+//! Numbered, replayable run events.
+//!
+//! Every event a run sends gets the next ID of that run, and the most recent
+//! ones are kept so an SSE client reconnecting with `Last-Event-ID` can pick
+//! up where it left off. Live subscribers use the plain broadcast channel.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use tokio::sync::broadcast::{self, error::SendError};
+use tokio_stream::wrappers::{BroadcastStream, errors::BroadcastStreamRecvError};
+
+use crate::uar::domain::events::NormalizedEvent;
+
+/// Events kept per run for replay.
+pub const DEFAULT_REPLAY_CAPACITY: usize = 1000;
+/// How long a finished run's events stay available for replay.
+pub const DEFAULT_REPLAY_GRACE: Duration = Duration::from_secs(30);
+/// Events a live subscriber may fall behind before it misses some.
+const CHANNEL_CAPACITY: usize = 100;
+
+/// A run event with its position in the run's stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequencedEvent {
+    pub id: u64,
+    pub event: NormalizedEvent,
+}
+
+/// Sends a run's events, numbering them and keeping the most recent ones.
+#[derive(Debug, Clone)]
+pub struct RunEventSender {
+    tx: broadcast::Sender<NormalizedEvent>,
+    replay: Arc<Mutex<ReplayBuffer>>,
+    /// Added to error events that don't carry a request ID
+    request_id: Option<String>,
+}
+
+#[derive(Debug)]
+struct ReplayBuffer {
+    next_id: u64,
+    events: VecDeque<SequencedEvent>,
+    capacity: usize,
+}
+
+impl Default for RunEventSender {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPLAY_CAPACITY)
+    }
+}
+
+impl RunEventSender {
+    /// Sender keeping the last `capacity` events.
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            tx,
+            replay: Arc::new(Mutex::new(ReplayBuffer {
+                next_id: 1,
+                events: VecDeque::new(),
+                capacity,
+            })),
+            request_id: None,
+        }
+    }
+
+    /// Stamp error events with the ID of the request that started the run.
+    #[must_use]
+    pub fn with_request_id(mut self, request_id: Option<String>) -> Self {
+        self.request_id = request_id;
+        self
+    }
+
+    /// Number, keep and broadcast `event`.
+    ///
+    /// Fails like [`broadcast::Sender::send`] when nobody is subscribed; the
+    /// event is kept for replay either way.
+    pub fn send(&self, mut event: NormalizedEvent) -> Result<usize, SendError<NormalizedEvent>> {
+        if let (Some(id), NormalizedEvent::Error { request_id, .. }) =
+            (&self.request_id, &mut event)
+        {
+            request_id.get_or_insert_with(|| id.clone());
+        }
+        // Broadcast under the lock, so `resume` sees each event exactly once
+        let mut replay = self.replay.lock().unwrap();
+        let id = replay.next_id;
+        replay.next_id += 1;
+        if replay.events.len() == replay.capacity {
+            replay.events.pop_front();
+        }
+        if replay.capacity > 0 {
+            replay.events.push_back(SequencedEvent {
+                id,
+                event: event.clone(),
+            });
+        }
+        self.tx.send(event)
+    }
+
+    /// Receiver of the events sent from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<NormalizedEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Kept events after `last_id` (all of them if `None`), then live ones.
+    ///
+    /// Events that dropped out of the buffer, or that a slow client missed
+    /// on the live channel, are skipped; IDs stay accurate.
+    pub fn resume(
+        &self,
+        last_id: Option<u64>,
+    ) -> impl Stream<Item = SequencedEvent> + Send + 'static {
+        let replay = self.replay.lock().unwrap();
+        let missed: Vec<SequencedEvent> = replay
+            .events
+            .iter()
+            .filter(|e| last_id.is_none_or(|last| e.id > last))
+            .cloned()
+            .collect();
+        let mut next_id = replay.next_id;
+        let live = BroadcastStream::new(self.tx.subscribe());
+        drop(replay);
+
+        let live = live.filter_map(move |received| {
+            let id = next_id;
+            let event = match received {
+                Ok(event) => {
+                    next_id += 1;
+                    Some(SequencedEvent { id, event })
+                }
+                Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                    next_id += skipped;
+                    None
+                }
+            };
+            futures::future::ready(event)
+        });
+        futures::stream::iter(missed).chain(live)
+    }
+
+    /// Drop the events sent so far once `grace` has passed.
+    ///
+    /// Called when a run finishes: clients reconnecting within `grace` still
+    /// get its tail, later events (e.g. of a chain's next step) are kept.
+    pub fn expire_after(&self, grace: Duration) {
+        let replay = Arc::clone(&self.replay);
+        let expired_before = replay.lock().unwrap().next_id;
+        tokio::spawn(async move {
+            tokio::time::sleep(grace).await;
+            let mut replay = replay.lock().unwrap();
+            while replay.events.front().is_some_and(|e| e.id < expired_before) {
+                replay.events.pop_front();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delta(text: &str) -> NormalizedEvent {
+        NormalizedEvent::ChatDelta {
+            run_id: "run-1".to_string(),
+            text_delta: text.to_string(),
+        }
+    }
+
+    async fn take(stream: impl Stream<Item = SequencedEvent>, n: usize) -> Vec<SequencedEvent> {
+        tokio::time::timeout(Duration::from_secs(5), stream.take(n).collect())
+            .await
+            .expect("stream ended early")
+    }
+
+    #[tokio::test]
+    async fn test_resume_replays_only_later_events() {
+        let sender = RunEventSender::new(10);
+        for text in ["a", "b", "c"] {
+            let _ = sender.send(delta(text));
+        }
+
+        let resumed = sender.resume(Some(2));
+        let _ = sender.send(delta("d"));
+        let events = take(resumed, 2).await;
+        assert_eq!(
+            events,
+            [
+                SequencedEvent {
+                    id: 3,
+                    event: delta("c"),
+                },
+                SequencedEvent {
+                    id: 4,
+                    event: delta("d"),
+                },
+            ]
+        );
+
+        // A fresh connection gets everything still kept
+        let ids: Vec<u64> = take(sender.resume(None), 4).await.iter().map(|e| e.id).collect();
+        assert_eq!(ids, [1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_buffer_is_bounded() {
+        let sender = RunEventSender::new(2);
+        for text in ["a", "b", "c"] {
+            let _ = sender.send(delta(text));
+        }
+        let resumed = sender.resume(None);
+        let _ = sender.send(delta("d"));
+        let ids: Vec<u64> = take(resumed, 3).await.iter().map(|e| e.id).collect();
+        assert_eq!(ids, [2, 3, 4]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_finished_run_expires_after_grace() {
+        let sender = RunEventSender::new(10);
+        let _ = sender.send(delta("a"));
+        sender.expire_after(Duration::from_secs(30));
+        let _ = sender.send(delta("b"));
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(sender.replay.lock().unwrap().events.len(), 2);
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        let kept: Vec<u64> = sender.replay.lock().unwrap().events.iter().map(|e| e.id).collect();
+        assert_eq!(kept, [2]);
+    }
+
+    #[tokio::test]
+    async fn test_errors_carry_the_request_id() {
+        let sender = RunEventSender::new(10).with_request_id(Some("req-42".to_string()));
+        let _ = sender.send(delta("a"));
+        let _ = sender.send(NormalizedEvent::Error {
+            run_id: "run-1".to_string(),
+            code: "timeout".to_string(),
+            message: "Run timed out".to_string(),
+            request_id: None,
+        });
+
+        let events = take(sender.resume(None), 2).await;
+        assert_eq!(events[0].event, delta("a"));
+        let NormalizedEvent::Error { request_id, .. } = &events[1].event else {
+            panic!("expected an error, got {:?}", events[1].event);
+        };
+        assert_eq!(request_id.as_deref(), Some("req-42"));
+    }
+}
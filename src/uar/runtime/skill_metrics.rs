@@ -0,0 +1,123 @@
+//! Skill usage telemetry.
+//!
+//! Counts how often each skill is injected into a run and how many bytes
+//! its overlay adds to the system prompt. Kept in memory for
+//! `GET /skills/metrics` and exported to Prometheus as
+//! `uar_skill_invocations_total` and `uar_skill_prompt_overhead_bytes`
+//! (both labelled by `skill_id`).
+
+use dashmap::DashMap;
+use serde::Serialize;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Prometheus counter of runs each skill was injected into.
+const INVOCATIONS_METRIC: &str = "uar_skill_invocations_total";
+/// Prometheus counter of system prompt bytes added by each skill.
+const PROMPT_OVERHEAD_METRIC: &str = "uar_skill_prompt_overhead_bytes";
+/// Prometheus gauge of skills in the registry.
+pub const REGISTERED_COUNT_METRIC: &str = "uar_skill_registered_count";
+
+/// Usage of one skill since startup.
+#[derive(Debug, Default)]
+pub struct SkillMetrics {
+    pub invocations_total: AtomicU64,
+    pub last_triggered_at: RwLock<Option<Instant>>,
+    /// Mean bytes the skill's overlay added to system prompts
+    pub avg_prompt_overhead_bytes: AtomicU64,
+}
+
+impl SkillMetrics {
+    fn record(&self, overhead_bytes: u64) {
+        let n = self.invocations_total.fetch_add(1, Ordering::Relaxed) + 1;
+        // Running mean; `fetch_update` only fails if the closure returns None
+        let _ = self
+            .avg_prompt_overhead_bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |avg| {
+                Some((avg * (n - 1) + overhead_bytes) / n)
+            });
+        *self
+            .last_triggered_at
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(Instant::now());
+    }
+}
+
+/// Usage of a registered skill, as served by the API.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SkillStats {
+    pub title: String,
+    /// Active version
+    pub version: String,
+    pub invocations_total: u64,
+    /// When the skill was last injected into a run (RFC3339)
+    pub last_triggered_at: Option<String>,
+    pub avg_prompt_overhead_bytes: u64,
+}
+
+/// Usage of every skill that has been triggered, keyed by skill ID.
+#[derive(Debug, Default)]
+pub struct SkillMetricsMap {
+    skills: DashMap<String, SkillMetrics>,
+}
+
+impl SkillMetricsMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `skill_id` was injected into a run, adding
+    /// `overhead_bytes` to its system prompt.
+    pub fn record(&self, skill_id: &str, overhead_bytes: usize) {
+        let bytes = u64::try_from(overhead_bytes).unwrap_or(u64::MAX);
+        self.skills
+            .entry(skill_id.to_string())
+            .or_default()
+            .record(bytes);
+        metrics::counter!(INVOCATIONS_METRIC, "skill_id" => skill_id.to_string()).increment(1);
+        metrics::counter!(PROMPT_OVERHEAD_METRIC, "skill_id" => skill_id.to_string())
+            .increment(bytes);
+    }
+
+    /// Usage of `skill_id`, with `title` and `version` left empty.
+    pub fn stats(&self, skill_id: &str) -> SkillStats {
+        let Some(metrics) = self.skills.get(skill_id) else {
+            return SkillStats::default();
+        };
+        let last_triggered_at = metrics
+            .last_triggered_at
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .and_then(|at| chrono::Duration::from_std(at.elapsed()).ok())
+            .map(|ago| (chrono::Utc::now() - ago).to_rfc3339());
+        SkillStats {
+            title: String::new(),
+            version: String::new(),
+            invocations_total: metrics.invocations_total.load(Ordering::Relaxed),
+            last_triggered_at,
+            avg_prompt_overhead_bytes: metrics.avg_prompt_overhead_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_averages_overhead() {
+        let map = SkillMetricsMap::new();
+        assert_eq!(map.stats("summarize"), SkillStats::default());
+
+        map.record("summarize", 100);
+        map.record("summarize", 300);
+        map.record("translate", 10);
+
+        let stats = map.stats("summarize");
+        assert_eq!(stats.invocations_total, 2);
+        assert_eq!(stats.avg_prompt_overhead_bytes, 200);
+        assert!(stats.last_triggered_at.is_some());
+        assert_eq!(map.stats("translate").invocations_total, 1);
+    }
+}
@@ -1,17 +1,128 @@
-use crate::uar::domain::skills::{Skill, SkillManifest};
+use crate::llm::{Message, MessageContent, MessageRole, Orchestrator};
+use crate::uar::domain::matching::{MatchReason, SkillMatch};
+use crate::uar::domain::skills::{Skill, SkillManifest, parse_skill_version};
 use crate::uar::persistence::PersistenceLayer;
 use crate::uar::runtime::matching::vector::VectorMatcher;
-use std::collections::HashMap;
+use crate::uar::runtime::skill_metrics::{REGISTERED_COUNT_METRIC, SkillMetricsMap, SkillStats};
+use regex::Regex;
+use semver::Version;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use tokio::fs;
 use tracing::{error, info, warn};
 
+/// Why a skill could not be registered or rolled back.
+#[derive(Debug, thiserror::Error)]
+pub enum SkillRegistryError {
+    #[error("Skill '{skill_id}' has an invalid version '{version}': {error}")]
+    InvalidVersion {
+        skill_id: String,
+        version: String,
+        error: semver::Error,
+    },
+    /// Registering would replace the active version with a lower one
+    #[error("Skill '{skill_id}' {version} is older than the active version {active}")]
+    Downgrade {
+        skill_id: String,
+        version: Version,
+        active: Version,
+    },
+    #[error("Skill '{0}' not found")]
+    NotFound(String),
+    #[error("Skill '{skill_id}' has no version {version}")]
+    VersionNotFound { skill_id: String, version: Version },
+}
+
+impl SkillRegistryError {
+    /// HTTP status to answer a rejected request with.
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        match self {
+            Self::InvalidVersion { .. } => axum::http::StatusCode::BAD_REQUEST,
+            Self::Downgrade { .. } => axum::http::StatusCode::CONFLICT,
+            Self::NotFound(_) | Self::VersionNotFound { .. } => {
+                axum::http::StatusCode::NOT_FOUND
+            }
+        }
+    }
+}
+
+/// Levels of nested skill dependencies resolved unless configured otherwise.
+pub const DEFAULT_MAX_SKILL_DEPTH: usize = 5;
+/// Error code of runs whose skills' dependencies could not be resolved.
+pub const SKILL_DEPENDENCY_ERROR_CODE: &str = "SKILL_DEPENDENCY_ERROR";
+/// Match scores this close to the best one count as a tie the LLM settles.
+pub const DEFAULT_DISAMBIGUATION_THRESHOLD: f32 = 0.05;
+/// How long the LLM's pick among the same skills for the same input is reused.
+pub const DISAMBIGUATION_CACHE_TTL: Duration = Duration::from_secs(300);
+
+const DISAMBIGUATION_PROMPT: &str = "You route user requests to skills. Given a \
+    request and the candidate skills, answer with the ID of the single skill most \
+    relevant to the request, and nothing else.";
+
+/// Why the dependencies of matched skills could not be resolved.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SkillDependencyError {
+    /// Skill IDs along the cycle, starting and ending with the same skill
+    #[error("Skill dependency cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    #[error("Skill '{skill_id}' depends on unknown skill '{dependency}'")]
+    Missing { skill_id: String, dependency: String },
+    #[error("Skill '{skill_id}' has dependencies nested deeper than {max_depth} levels")]
+    TooDeep { skill_id: String, max_depth: usize },
+}
+
+/// State of [`SkillRegistry::with_dependencies`]' depth-first walk.
+struct DependencyWalk {
+    max_depth: usize,
+    /// Skills being visited, outermost first
+    path: Vec<String>,
+    visited: HashSet<String>,
+    ordered: Vec<Skill>,
+}
+
+/// The `regex_patterns` of `skill` that compile; the rest are logged and
+/// ignored.
+fn compile_patterns(skill: &Skill) -> Vec<Regex> {
+    let patterns = skill.triggers.regex_patterns.as_deref().unwrap_or_default();
+    patterns
+        .iter()
+        .filter_map(|pattern| match Regex::new(pattern) {
+            Ok(regex) => Some(regex),
+            Err(e) => {
+                warn!(
+                    "Ignoring invalid trigger pattern {:?} of skill {}: {}",
+                    pattern, skill.skill_id, e
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+fn parse_version(skill_id: &str, version: &str) -> Result<Version, SkillRegistryError> {
+    parse_skill_version(version).map_err(|error| SkillRegistryError::InvalidVersion {
+        skill_id: skill_id.to_string(),
+        version: version.to_string(),
+        error,
+    })
+}
+
 #[derive(Clone)]
 pub struct SkillRegistry {
+    /// Active version of each skill
     skills: HashMap<String, Skill>,
+    /// Every version registered since startup, per skill
+    history: HashMap<String, BTreeMap<Version, Skill>>,
     persistence: Option<Arc<dyn PersistenceLayer>>,
     vector_matcher: Option<Arc<VectorMatcher>>,
+    /// Usage of each skill, recorded by the runs it is injected into
+    metrics: Arc<SkillMetricsMap>,
+    /// Compiled `regex_patterns` of each skill, by skill ID
+    patterns: Arc<RwLock<HashMap<String, Vec<Regex>>>>,
 }
 
 // Manual Debug implementation to skip generic/Arc fields if needed, or just derive if they implement Debug
@@ -19,6 +130,7 @@ impl std::fmt::Debug for SkillRegistry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("SkillRegistry")
             .field("skills_count", &self.skills.len())
+            .field("versioned_skills", &self.history.len())
             .field("persistence", &self.persistence.is_some())
             .field("vector_matcher", &self.vector_matcher.is_some())
             .finish()
@@ -38,8 +150,11 @@ impl SkillRegistry {
     ) -> Self {
         Self {
             skills: HashMap::new(),
+            history: HashMap::new(),
             persistence,
             vector_matcher,
+            metrics: Arc::new(SkillMetricsMap::new()),
+            patterns: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -109,12 +224,15 @@ impl SkillRegistry {
             triggers: manifest.triggers,
             prompt_overlay: overlay,
             preferred_tools: manifest.tools,
+            tool_choice: manifest.tool_choice,
             mcp_config,
             constraints: Default::default(),
+            upgrade_path: manifest.upgrade_path,
+            dependencies: manifest.dependencies,
         };
 
-        info!("Loaded skill: {}", skill.title);
-        self.register(skill).await;
+        self.register(skill).await?;
+        info!("Loaded skill: {}", skill_id);
         Ok(())
     }
 
@@ -136,7 +254,90 @@ impl SkillRegistry {
         Ok((manifest, body))
     }
 
-    pub async fn register(&mut self, skill: Skill) {
+    /// Register `skill` and make it the active version.
+    ///
+    /// Its version must be valid semver (a missing minor or patch number
+    /// counts as 0) and no lower than the active version's; every version
+    /// is kept for [`Self::get_version_history`].
+    pub async fn register(&mut self, skill: Skill) -> Result<(), SkillRegistryError> {
+        let version = parse_version(&skill.skill_id, &skill.version)?;
+        if let Some(active) = self.skills.get(&skill.skill_id)
+            && let Ok(active) = parse_skill_version(&active.version)
+            && version < active
+        {
+            return Err(SkillRegistryError::Downgrade {
+                skill_id: skill.skill_id,
+                version,
+                active,
+            });
+        }
+
+        if let Some(db) = &self.persistence
+            && let Err(e) = db.save_skill_version(&skill).await
+        {
+            error!("Failed to record version of skill {}: {:?}", skill.skill_id, e);
+        }
+        self.history
+            .entry(skill.skill_id.clone())
+            .or_default()
+            .insert(version, skill.clone());
+        self.activate(skill).await;
+        Ok(())
+    }
+
+    /// Every known version of a skill, newest first.
+    ///
+    /// Includes versions recorded in persistence by earlier runs of the
+    /// server.
+    pub async fn get_version_history(&self, skill_id: &str) -> Vec<(Version, Skill)> {
+        let mut versions = self.history.get(skill_id).cloned().unwrap_or_default();
+        if let Some(db) = &self.persistence {
+            match db.list_skill_versions(skill_id).await {
+                Ok(stored) => {
+                    for skill in stored {
+                        match parse_skill_version(&skill.version) {
+                            Ok(version) => {
+                                versions.entry(version).or_insert(skill);
+                            }
+                            Err(e) => warn!(
+                                "Ignoring stored skill {} with invalid version {}: {}",
+                                skill_id, skill.version, e
+                            ),
+                        }
+                    }
+                }
+                Err(e) => error!("Failed to load versions of skill {}: {:?}", skill_id, e),
+            }
+        }
+        versions.into_iter().rev().collect()
+    }
+
+    /// Make an earlier (or any known) version of a skill the active one.
+    pub async fn rollback(
+        &mut self,
+        skill_id: &str,
+        version: &str,
+    ) -> Result<Skill, SkillRegistryError> {
+        let version = parse_version(skill_id, version)?;
+        let history = self.get_version_history(skill_id).await;
+        if history.is_empty() {
+            return Err(SkillRegistryError::NotFound(skill_id.to_string()));
+        }
+        let (_, skill) = history
+            .into_iter()
+            .find(|(v, _)| *v == version)
+            .ok_or_else(|| SkillRegistryError::VersionNotFound {
+                skill_id: skill_id.to_string(),
+                version,
+            })?;
+
+        info!("Rolled skill {} back to version {}", skill_id, skill.version);
+        self.activate(skill.clone()).await;
+        Ok(skill)
+    }
+
+    /// Make `skill` the version runs match against.
+    async fn activate(&mut self, skill: Skill) {
         // Save to Persistence if available
         if let (Some(db), Some(vm)) = (&self.persistence, &self.vector_matcher) {
             // Generate embedding for "Title: Description"
@@ -158,17 +359,118 @@ impl SkillRegistry {
             }
         }
 
+        let patterns = compile_patterns(&skill);
+        self.patterns
+            .write()
+            .unwrap()
+            .insert(skill.skill_id.clone(), patterns);
         self.skills.insert(skill.skill_id.clone(), skill);
+        metrics::gauge!(REGISTERED_COUNT_METRIC)
+            .set(u32::try_from(self.skills.len()).unwrap_or(u32::MAX));
     }
 
     pub fn get(&self, id: &str) -> Option<&Skill> {
         self.skills.get(id)
     }
 
+    /// `skills` and, recursively, the skills they depend on, each once and
+    /// after all of its dependencies.
+    ///
+    /// Fails on unknown dependencies, cycles, and chains more than
+    /// `max_depth` dependencies deep.
+    pub fn with_dependencies(
+        &self,
+        skills: Vec<Skill>,
+        max_depth: usize,
+    ) -> Result<Vec<Skill>, SkillDependencyError> {
+        let mut walk = DependencyWalk {
+            max_depth,
+            path: Vec::new(),
+            visited: HashSet::new(),
+            ordered: Vec::new(),
+        };
+        for skill in skills {
+            self.visit(skill, &mut walk)?;
+        }
+        Ok(walk.ordered)
+    }
+
+    fn visit(&self, skill: Skill, walk: &mut DependencyWalk) -> Result<(), SkillDependencyError> {
+        if walk.visited.contains(&skill.skill_id) {
+            return Ok(());
+        }
+        if let Some(start) = walk.path.iter().position(|id| *id == skill.skill_id) {
+            let mut cycle = walk.path[start..].to_vec();
+            cycle.push(skill.skill_id);
+            return Err(SkillDependencyError::Cycle(cycle));
+        }
+        // The path holds the matched skill and its dependents so far
+        if walk.path.len() > walk.max_depth {
+            return Err(SkillDependencyError::TooDeep {
+                skill_id: walk.path[0].clone(),
+                max_depth: walk.max_depth,
+            });
+        }
+
+        walk.path.push(skill.skill_id.clone());
+        for dependency in &skill.dependencies {
+            let Some(resolved) = self.skills.get(dependency) else {
+                return Err(SkillDependencyError::Missing {
+                    skill_id: skill.skill_id.clone(),
+                    dependency: dependency.clone(),
+                });
+            };
+            self.visit(resolved.clone(), walk)?;
+        }
+        walk.path.pop();
+
+        walk.visited.insert(skill.skill_id.clone());
+        walk.ordered.push(skill);
+        Ok(())
+    }
+
     pub fn list(&self) -> Vec<Skill> {
         self.skills.values().cloned().collect()
     }
 
+    /// Where runs record which skills they used.
+    pub fn metrics(&self) -> Arc<SkillMetricsMap> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Usage of every registered skill, keyed by skill ID.
+    pub fn stats(&self) -> HashMap<String, SkillStats> {
+        self.skills
+            .values()
+            .map(|skill| {
+                let stats = SkillStats {
+                    title: skill.title.clone(),
+                    version: skill.version.clone(),
+                    ..self.metrics.stats(&skill.skill_id)
+                };
+                (skill.skill_id.clone(), stats)
+            })
+            .collect()
+    }
+
+    /// Whether any of the `regex_patterns` of `skill` matches `input`.
+    pub fn matches_patterns(&self, skill: &Skill, input: &str) -> bool {
+        if skill.triggers.regex_patterns.is_none() {
+            return false;
+        }
+        if let Some(patterns) = self.patterns.read().unwrap().get(&skill.skill_id) {
+            return patterns.iter().any(|pattern| pattern.is_match(input));
+        }
+        // Not registered here; compile once for next time
+        let patterns = compile_patterns(skill);
+        let matched = patterns.iter().any(|pattern| pattern.is_match(input));
+        self.patterns
+            .write()
+            .unwrap()
+            .insert(skill.skill_id.clone(), patterns);
+        matched
+    }
+
     pub async fn find_matches(&self, query: &str) -> Vec<Skill> {
         // If persistence available, use vector search
         if let (Some(db), Some(vm)) = (&self.persistence, &self.vector_matcher) {
@@ -178,7 +480,20 @@ impl SkillRegistry {
                         match db.search_skills(q_vec, 5).await {
                             // Limit 5
                             Ok(matches) => {
-                                return matches.into_iter().map(|m| m.skill).collect();
+                                let mut found: Vec<Skill> =
+                                    matches.into_iter().map(|m| m.skill).collect();
+                                // Patterns trigger their skills whatever the vectors say
+                                let triggered: Vec<Skill> = self
+                                    .skills
+                                    .values()
+                                    .filter(|s| {
+                                        !found.iter().any(|f| f.skill_id == s.skill_id)
+                                            && self.matches_patterns(s, query)
+                                    })
+                                    .cloned()
+                                    .collect();
+                                found.extend(triggered);
+                                return found;
                             }
                             Err(e) => {
                                 error!("Skill search failed: {:?}", e);
@@ -202,8 +517,507 @@ impl SkillRegistry {
                         .keywords
                         .iter()
                         .any(|k| k.to_lowercase().contains(&query.to_lowercase()))
+                    || self.matches_patterns(s, query)
             })
             .cloned()
             .collect()
     }
 }
+
+/// Settles near-ties between matched skills by asking the LLM which one
+/// fits the input, so runs don't inject every skill that scored about the
+/// same.
+///
+/// Only the best-scoring matches are settled: those within the threshold of
+/// the top score are replaced by the model's pick, and clearly lower matches
+/// are kept. If the LLM fails, or names none of the candidates, every match
+/// is kept.
+pub struct SkillDisambiguator {
+    orchestrator: Arc<Orchestrator>,
+    threshold: f32,
+    /// (input hash, sorted candidate IDs) -> when decided, picked skill ID
+    decisions: Mutex<HashMap<(String, Vec<String>), (Instant, String)>>,
+}
+
+impl std::fmt::Debug for SkillDisambiguator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SkillDisambiguator")
+            .field("model", &self.orchestrator.settings().model)
+            .field("threshold", &self.threshold)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SkillDisambiguator {
+    pub fn new(orchestrator: Arc<Orchestrator>) -> Self {
+        Self {
+            orchestrator,
+            threshold: DEFAULT_DISAMBIGUATION_THRESHOLD,
+            decisions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Treat scores within `threshold` of the best one as a tie.
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// `matches` with the skills tied for the best score narrowed down to
+    /// the one the LLM picks for `input`.
+    pub async fn disambiguate(
+        &self,
+        input: &str,
+        mut matches: Vec<SkillMatch>,
+    ) -> Vec<SkillMatch> {
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        let Some(best) = matches.first().map(|m| m.score) else {
+            return matches;
+        };
+        let tied = matches
+            .iter()
+            .take_while(|m| best - m.score <= self.threshold)
+            .count();
+        if tied < 2 {
+            return matches;
+        }
+
+        let mut candidates: Vec<String> =
+            matches[..tied].iter().map(|m| m.skill_id.clone()).collect();
+        candidates.sort();
+        let key = (hex::encode(Sha256::digest(input.as_bytes())), candidates);
+        let cached = self.decision(&key);
+        let picked = match cached.clone() {
+            Some(picked) => picked,
+            None => match self.ask(input, &matches[..tied]).await {
+                Some(picked) => picked,
+                None => return matches,
+            },
+        };
+        tracing::debug!(
+            candidates = ?key.1,
+            selected = %picked,
+            cached = cached.is_some(),
+            "Disambiguated matched skills"
+        );
+        let reasoning = format!("Picked by the LLM among {}", key.1.join(", "));
+        if cached.is_none() {
+            self.remember(key, picked.clone());
+        }
+
+        let rest = matches.split_off(tied);
+        let mut selected: Vec<_> = matches
+            .into_iter()
+            .filter(|m| m.skill_id == picked)
+            .map(|m| SkillMatch {
+                reason: MatchReason::LLMSelected {
+                    reasoning: reasoning.clone(),
+                },
+                ..m
+            })
+            .collect();
+        selected.extend(rest);
+        selected
+    }
+
+    /// ID of the skill the LLM picks among `candidates`; `None` if it failed
+    /// or named none of them.
+    async fn ask(&self, input: &str, candidates: &[SkillMatch]) -> Option<String> {
+        let mut listed = format!("Request: {input}\n\nCandidate skills:\n");
+        for candidate in candidates {
+            let _ = writeln!(
+                listed,
+                "- {}: {}. {}",
+                candidate.skill_id, candidate.skill.title, candidate.skill.description
+            );
+        }
+        let request = vec![
+            text_message(MessageRole::System, DISAMBIGUATION_PROMPT.to_string()),
+            text_message(MessageRole::User, listed),
+        ];
+        let answer = match self.orchestrator.chat_non_streaming(request, None).await {
+            Ok(answer) => answer,
+            Err(e) => {
+                warn!("Skill disambiguation failed, keeping every match: {:?}", e);
+                return None;
+            }
+        };
+
+        let picked = picked_candidate(&answer, candidates);
+        if picked.is_none() {
+            warn!("Skill disambiguation picked no candidate: {:?}", answer);
+        }
+        picked.map(|picked| picked.skill_id.clone())
+    }
+
+    fn decision(&self, key: &(String, Vec<String>)) -> Option<String> {
+        let decisions = self.decisions.lock().unwrap();
+        decisions
+            .get(key)
+            .filter(|(at, _)| at.elapsed() < DISAMBIGUATION_CACHE_TTL)
+            .map(|(_, picked)| picked.clone())
+    }
+
+    fn remember(&self, key: (String, Vec<String>), picked: String) {
+        let mut decisions = self.decisions.lock().unwrap();
+        decisions.retain(|_, (at, _)| at.elapsed() < DISAMBIGUATION_CACHE_TTL);
+        decisions.insert(key, (Instant::now(), picked));
+    }
+}
+
+/// The candidate an LLM answer names: the whole answer, a JSON string or
+/// object with an `id` or `skill_id`, or else the first word, each compared
+/// with the whole skill ID. `None` if that names no candidate, or the answer
+/// also names another one.
+fn picked_candidate<'a>(answer: &str, candidates: &'a [SkillMatch]) -> Option<&'a SkillMatch> {
+    // Models sometimes quote the ID or explain their pick
+    let unquote = |s: &str| {
+        s.trim()
+            .trim_matches(|c: char| matches!(c, '`' | '"' | '\'' | '*' | '.' | ',' | ':' | ';'))
+            .to_string()
+    };
+    let find = |named: &str| {
+        let mut found = candidates
+            .iter()
+            .filter(|c| c.skill_id.eq_ignore_ascii_case(named));
+        let first = found.next();
+        first.filter(|_| found.next().is_none())
+    };
+
+    if let Some(picked) = find(&unquote(answer)) {
+        return Some(picked);
+    }
+    match serde_json::from_str::<serde_json::Value>(answer.trim()) {
+        Ok(serde_json::Value::String(named)) => return find(&unquote(&named)),
+        Ok(value @ serde_json::Value::Object(_)) => {
+            let named = ["skill_id", "id"].iter().find_map(|k| value[*k].as_str())?;
+            return find(&unquote(named));
+        }
+        _ => {}
+    }
+
+    let mut words = answer.split_whitespace().map(unquote);
+    let picked = find(&words.next()?)?;
+    let names_another = words.any(|word| {
+        candidates
+            .iter()
+            .any(|c| c.skill_id != picked.skill_id && c.skill_id.eq_ignore_ascii_case(&word))
+    });
+    (!names_another).then_some(picked)
+}
+
+fn text_message(role: MessageRole, text: String) -> Message {
+    Message {
+        role,
+        content: MessageContent::text(text),
+        tool_call_id: None,
+        tool_calls: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{LlmDriver, LlmRequest};
+    use crate::mcp::registry::McpRegistry;
+    use crate::normalized::NormalizedEvent;
+    use crate::uar::domain::skills::{SkillConstraints, SkillTriggers};
+    use crate::uar::persistence::testing::InMemoryPersistence;
+    use crate::uar::testing::llm_settings;
+    use futures::Stream;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn skill(version: &str) -> Skill {
+        Skill {
+            skill_id: "summarize".to_string(),
+            version: version.to_string(),
+            title: format!("Summarize {version}"),
+            description: "Summarizes documents".to_string(),
+            triggers: SkillTriggers::default(),
+            prompt_overlay: String::new(),
+            preferred_tools: vec![],
+            tool_choice: None,
+            mcp_config: None,
+            constraints: SkillConstraints::default(),
+            upgrade_path: None,
+            dependencies: vec![],
+        }
+    }
+
+    /// `summarize` 1.0 renamed to `id`, depending on `dependencies`.
+    fn dependent(id: &str, dependencies: &[&str]) -> Skill {
+        Skill {
+            skill_id: id.to_string(),
+            dependencies: dependencies.iter().map(ToString::to_string).collect(),
+            ..skill("1.0")
+        }
+    }
+
+    fn ids(skills: &[Skill]) -> Vec<&str> {
+        skills.iter().map(|s| s.skill_id.as_str()).collect()
+    }
+
+    fn versions(history: &[(Version, Skill)]) -> Vec<String> {
+        history.iter().map(|(v, _)| v.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_version_history_is_newest_first() {
+        let mut registry = SkillRegistry::default();
+        for version in ["1.0", "1.1", "2.0"] {
+            registry.register(skill(version)).await.unwrap();
+        }
+
+        let history = registry.get_version_history("summarize").await;
+        assert_eq!(versions(&history), ["2.0.0", "1.1.0", "1.0.0"]);
+        assert_eq!(registry.get("summarize").unwrap().version, "2.0");
+        assert!(registry.get_version_history("unknown").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_register_rejects_downgrade_and_invalid_versions() {
+        let mut registry = SkillRegistry::default();
+        registry.register(skill("1.1")).await.unwrap();
+        // Re-registering the active version is fine
+        registry.register(skill("v1.1.0")).await.unwrap();
+
+        let err = registry.register(skill("1.0")).await.unwrap_err();
+        assert!(matches!(err, SkillRegistryError::Downgrade { .. }), "{err}");
+        assert_eq!(err.status_code(), axum::http::StatusCode::CONFLICT);
+
+        let err = registry.register(skill("latest")).await.unwrap_err();
+        assert_eq!(err.status_code(), axum::http::StatusCode::BAD_REQUEST);
+        assert_eq!(registry.get("summarize").unwrap().version, "v1.1.0");
+    }
+
+    #[tokio::test]
+    async fn test_rollback() {
+        let mut registry = SkillRegistry::default();
+        registry.register(skill("1.0")).await.unwrap();
+        registry.register(skill("2.0")).await.unwrap();
+
+        let active = registry.rollback("summarize", "1.0.0").await.unwrap();
+        assert_eq!(active.title, "Summarize 1.0");
+        assert_eq!(registry.get("summarize").unwrap().version, "1.0");
+        // Rolling back keeps the newer version around
+        assert_eq!(registry.get_version_history("summarize").await.len(), 2);
+
+        let err = registry.rollback("summarize", "3.0").await.unwrap_err();
+        assert!(matches!(err, SkillRegistryError::VersionNotFound { .. }), "{err}");
+        let err = registry.rollback("unknown", "1.0").await.unwrap_err();
+        assert!(matches!(err, SkillRegistryError::NotFound(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_version_history_survives_restart() {
+        let db: Arc<dyn PersistenceLayer> = Arc::new(InMemoryPersistence::new());
+        let mut registry = SkillRegistry::new(Some(Arc::clone(&db)), None);
+        registry.register(skill("1.0")).await.unwrap();
+        registry.register(skill("1.2.3")).await.unwrap();
+
+        let mut restarted = SkillRegistry::new(Some(db), None);
+        let history = restarted.get_version_history("summarize").await;
+        assert_eq!(versions(&history), ["1.2.3", "1.0.0"]);
+        let active = restarted.rollback("summarize", "1.0").await.unwrap();
+        assert_eq!(active.version, "1.0");
+    }
+
+    #[tokio::test]
+    async fn test_dependencies_are_ordered_first() {
+        let mut registry = SkillRegistry::default();
+        for skill in [
+            dependent("style", &[]),
+            dependent("security", &["style"]),
+            dependent("review", &["security", "style"]),
+        ] {
+            registry.register(skill).await.unwrap();
+        }
+
+        let review = registry.get("review").cloned().unwrap();
+        let style = registry.get("style").cloned().unwrap();
+        let resolved = registry.with_dependencies(vec![review, style], 5).unwrap();
+        assert_eq!(ids(&resolved), ["style", "security", "review"]);
+
+        let review = registry.get("review").cloned().unwrap();
+        let err = registry.with_dependencies(vec![review], 1).unwrap_err();
+        assert_eq!(
+            err,
+            SkillDependencyError::TooDeep {
+                skill_id: "review".to_string(),
+                max_depth: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dependency_errors() {
+        let mut registry = SkillRegistry::default();
+        for skill in [
+            dependent("a", &["b"]),
+            dependent("b", &["c"]),
+            dependent("c", &["a"]),
+            dependent("orphan", &["missing"]),
+        ] {
+            registry.register(skill).await.unwrap();
+        }
+
+        let a = registry.get("a").cloned().unwrap();
+        let err = registry.with_dependencies(vec![a], 5).unwrap_err();
+        assert_eq!(err.to_string(), "Skill dependency cycle: a -> b -> c -> a");
+
+        let orphan = registry.get("orphan").cloned().unwrap();
+        let err = registry.with_dependencies(vec![orphan], 5).unwrap_err();
+        assert_eq!(
+            err,
+            SkillDependencyError::Missing {
+                skill_id: "orphan".to_string(),
+                dependency: "missing".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_regex_triggers() {
+        use crate::uar::domain::matching::SkillMatcher;
+        use crate::uar::runtime::matching::TagMatcher;
+
+        let mut registry = SkillRegistry::default();
+        let mut sql = dependent("sql", &[]);
+        sql.triggers.regex_patterns = Some(vec![
+            r"(?i)\bsql\b".to_string(),
+            // Invalid; skipped
+            "(unclosed".to_string(),
+        ]);
+        registry.register(sql).await.unwrap();
+
+        let (input, other) = ("Can you write an SQL query?", "sequential logic");
+        assert_eq!(ids(&registry.find_matches(input).await), ["sql"]);
+        assert!(registry.find_matches(other).await.is_empty());
+
+        let matcher = TagMatcher::new();
+        let matches = matcher.match_skills(input, &registry).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].skill_id, "sql");
+        let matches = matcher.match_skills(other, &registry).await.unwrap();
+        assert!(matches.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_regex_triggers_alongside_vector_search() {
+        use crate::uar::runtime::matching::VectorMatcher;
+        use crate::uar::testing::StubEmbedder;
+
+        // The store's skill search finds nothing
+        let mut registry = SkillRegistry::new(
+            Some(Arc::new(InMemoryPersistence::default())),
+            Some(Arc::new(VectorMatcher::with_provider(0.5, Arc::new(StubEmbedder::default())))),
+        );
+        let mut sql = dependent("sql", &[]);
+        sql.triggers.regex_patterns = Some(vec![r"(?i)\bsql\b".to_string()]);
+        registry.register(sql).await.unwrap();
+
+        assert_eq!(ids(&registry.find_matches("Can you write an SQL query?").await), ["sql"]);
+        assert!(registry.find_matches("sequential logic").await.is_empty());
+    }
+
+    /// Answers every request with `answer`, counting the calls.
+    struct PickingLlm {
+        answer: &'static str,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmDriver for PickingLlm {
+        async fn stream(
+            &self,
+            _req: LlmRequest,
+        ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<NormalizedEvent>> + Send>>>
+        {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let events = vec![
+                NormalizedEvent::MessageDelta {
+                    text: self.answer.to_string(),
+                },
+                NormalizedEvent::Done,
+            ];
+            Ok(Box::pin(futures::stream::iter(events.into_iter().map(Ok))))
+        }
+    }
+
+    fn disambiguator(answer: &'static str) -> (Arc<PickingLlm>, SkillDisambiguator) {
+        let llm = Arc::new(PickingLlm {
+            answer,
+            calls: AtomicUsize::new(0),
+        });
+        let settings = llm_settings("http://localhost", "test-model");
+        let orchestrator = Orchestrator::with_driver(
+            settings,
+            Arc::new(McpRegistry::new_empty()),
+            Arc::clone(&llm) as Arc<dyn LlmDriver>,
+        );
+        (llm, SkillDisambiguator::new(Arc::new(orchestrator)))
+    }
+
+    fn vector_match(id: &str, score: f32) -> SkillMatch {
+        SkillMatch {
+            skill_id: id.to_string(),
+            score,
+            reason: MatchReason::VectorSimilarity(score),
+            skill: dependent(id, &[]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disambiguator_picks_one_of_near_equal_matches() {
+        let (llm, disambiguator) = disambiguator("`sql-helper`");
+        let near_equal = || {
+            vec![
+                vector_match("data-viz", 0.81),
+                vector_match("sql-helper", 0.82),
+                vector_match("summarize", 0.6),
+            ]
+        };
+
+        let input = "Write a query for last month's orders";
+        let picked = disambiguator.disambiguate(input, near_equal()).await;
+        let picked_ids: Vec<_> = picked.iter().map(|m| m.skill_id.as_str()).collect();
+        assert_eq!(picked_ids, ["sql-helper", "summarize"]);
+        assert!(matches!(picked[0].reason, MatchReason::LLMSelected { .. }));
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 1);
+
+        // The decision is reused for the same input and candidates
+        let picked = disambiguator.disambiguate(input, near_equal()).await;
+        assert_eq!(picked[0].skill_id, "sql-helper");
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 1);
+
+        // A clear winner needs no LLM call
+        let clear = vec![vector_match("sql-helper", 0.9), vector_match("data-viz", 0.7)];
+        assert_eq!(disambiguator.disambiguate(input, clear).await.len(), 2);
+        assert_eq!(llm.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_picked_candidate_matches_whole_ids() {
+        let candidates = [vector_match("sql", 0.8), vector_match("sql-helper", 0.8)];
+        let picked = |answer| picked_candidate(answer, &candidates).map(|c| c.skill_id.as_str());
+
+        assert_eq!(picked("sql-helper"), Some("sql-helper"));
+        assert_eq!(picked(" `SQL` "), Some("sql"));
+        assert_eq!(picked(r#"{"skill_id": "sql-helper"}"#), Some("sql-helper"));
+        assert_eq!(picked(r#""sql""#), Some("sql"));
+        assert_eq!(picked("sql-helper, since it writes queries"), Some("sql-helper"));
+        // Mentions inside other words or text don't count
+        assert_eq!(picked("I'd use a sql-helpers skill"), None);
+        assert_eq!(picked("Either sql or sql-helper"), None);
+        assert_eq!(picked("sql, though sql-helper fits too"), None);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_pick_keeps_every_match() {
+        let (_, disambiguator) = disambiguator("I can't tell");
+        let tied = vec![vector_match("data-viz", 0.8), vector_match("sql-helper", 0.8)];
+        let kept = disambiguator.disambiguate("Chart the orders", tied).await;
+        assert_eq!(kept.len(), 2);
+    }
+}
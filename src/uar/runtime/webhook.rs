@@ -0,0 +1,313 @@
+//! HTTP callbacks when a run finishes.
+//!
+//! Agents with `runtime.webhook_url` get a `POST` of a [`WebhookPayload`]
+//! once their run is done, has failed or was cancelled. With
+//! `runtime.webhook_secret` the body is signed:
+//! `X-UAR-Signature-256: sha256=<hex HMAC-SHA256>`.
+//! Failed deliveries are retried with exponential backoff.
+//!
+//! Webhook URLs come from tenants, so hosts resolving to loopback,
+//! private, link-local or other non-public addresses are refused unless
+//! the operator allowed them. Requests go to the addresses checked, so a
+//! second DNS answer can't redirect them.
+
+use anyhow::{Context, bail};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use url::{Host, Url};
+
+use crate::uar::domain::{artifact::AgentRuntimeConfig, runs::RunStatus};
+
+/// Header carrying the body signature.
+pub const SIGNATURE_HEADER: &str = "X-UAR-Signature-256";
+/// Delivery timeout used unless configured otherwise.
+pub const DEFAULT_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Retries after the first failed delivery.
+const MAX_RETRIES: u32 = 3;
+/// Delay before the first retry; doubles on each further one.
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+/// Characters of the answer included in the payload.
+const SUMMARY_CHARS: usize = 500;
+
+/// Body of a run webhook.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookPayload {
+    pub run_id: String,
+    /// `done`, `error` or `cancelled`
+    pub status: RunStatus,
+    pub agent_id: String,
+    pub session_id: Option<String>,
+    /// Start of the run's answer
+    pub summary: String,
+}
+
+impl WebhookPayload {
+    /// Payload for a finished run; `answer` is cut to the summary length.
+    pub fn new(
+        run_id: impl Into<String>,
+        status: RunStatus,
+        agent_id: impl Into<String>,
+        session_id: Option<String>,
+        answer: &str,
+    ) -> Self {
+        Self {
+            run_id: run_id.into(),
+            status,
+            agent_id: agent_id.into(),
+            session_id,
+            summary: answer.chars().take(SUMMARY_CHARS).collect(),
+        }
+    }
+}
+
+/// `sha256=<hex>` HMAC-SHA256 of `body` keyed with `secret`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Whether `ip` is reachable on the public internet, as opposed to
+/// loopback, private, link-local, shared, multicast or reserved ranges.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (b & 0xc0) == 64)
+                // Reserved, 240.0.0.0/4
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_public_ip(IpAddr::V4(v4)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// Delivers run webhooks.
+#[derive(Debug, Clone)]
+pub struct WebhookSender {
+    timeout: Duration,
+    base_backoff: Duration,
+    /// Hosts delivered to whatever addresses they resolve to
+    allowed_hosts: Arc<[String]>,
+}
+
+impl Default for WebhookSender {
+    fn default() -> Self {
+        Self::new(DEFAULT_WEBHOOK_TIMEOUT)
+    }
+}
+
+impl WebhookSender {
+    /// Sender giving each delivery attempt `timeout`.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            base_backoff: BASE_BACKOFF,
+            allowed_hosts: Arc::from([]),
+        }
+    }
+
+    /// Give each delivery attempt `timeout`.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Deliver to `hosts` even on non-public addresses (none by default).
+    #[must_use]
+    pub fn with_allowed_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.allowed_hosts = hosts.into();
+        self
+    }
+
+    /// Client for `url`, bound to its host's addresses once they are known
+    /// to be public.
+    async fn client_for(&self, url: &Url) -> anyhow::Result<reqwest::Client> {
+        let builder = reqwest::Client::builder().timeout(self.timeout);
+        let (Some(host), Some(name)) = (url.host(), url.host_str()) else {
+            bail!("webhook URL has no host");
+        };
+        if self.allowed_hosts.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+            return Ok(builder.build()?);
+        }
+
+        let port = url.port_or_known_default().unwrap_or(80);
+        let (builder, addrs) = match host {
+            Host::Ipv4(ip) => (builder, vec![SocketAddr::new(ip.into(), port)]),
+            Host::Ipv6(ip) => (builder, vec![SocketAddr::new(ip.into(), port)]),
+            Host::Domain(domain) => {
+                let addrs: Vec<SocketAddr> = tokio::net::lookup_host((domain, port))
+                    .await
+                    .with_context(|| format!("failed to resolve webhook host '{domain}'"))?
+                    .collect();
+                (builder.resolve_to_addrs(domain, &addrs), addrs)
+            }
+        };
+        if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+            bail!("webhook host '{name}' is not public ({})", addr.ip());
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Deliver `payload` in the background if `runtime` has a webhook.
+    pub fn spawn(&self, runtime: &AgentRuntimeConfig, payload: WebhookPayload) {
+        let Some(url) = runtime.webhook_url.clone() else {
+            return;
+        };
+        let secret = runtime.webhook_secret.clone();
+        let sender = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = sender.deliver(&url, secret.as_deref(), &payload).await {
+                tracing::error!(
+                    run_id = %payload.run_id,
+                    error = %e,
+                    "Giving up on run webhook"
+                );
+            }
+        });
+    }
+
+    /// `POST` `payload` to `url`, retrying failures and non-2xx answers.
+    pub async fn deliver(
+        &self,
+        url: &str,
+        secret: Option<&str>,
+        payload: &WebhookPayload,
+    ) -> anyhow::Result<()> {
+        let body = serde_json::to_vec(payload)?;
+        let client = self.client_for(&Url::parse(url)?).await?;
+        let mut attempt = 0;
+        loop {
+            let mut request = client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            if let Some(secret) = secret {
+                request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+            }
+
+            let error = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => anyhow::anyhow!("webhook answered {}", response.status()),
+                Err(e) => e.into(),
+            };
+            if attempt == MAX_RETRIES {
+                return Err(error.context(format!("webhook failed {} times", attempt + 1)));
+            }
+            attempt += 1;
+            tracing::warn!(
+                run_id = %payload.run_id,
+                attempt,
+                error = %error,
+                "Run webhook failed, retrying"
+            );
+            tokio::time::sleep(self.base_backoff.saturating_mul(2u32.pow(attempt - 1))).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, http::StatusCode, routing::post};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_sign_matches_known_digest() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_summary_is_truncated_by_characters() {
+        let answer = "é".repeat(600);
+        let payload = WebhookPayload::new("run-1", RunStatus::Done, "agent", None, &answer);
+        assert_eq!(payload.summary.chars().count(), SUMMARY_CHARS);
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_is_retried() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&attempts);
+        let app = Router::new().route(
+            "/hook",
+            post(move || {
+                let counter = Arc::clone(&counter);
+                async move {
+                    // Fail twice, then accept
+                    if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                        StatusCode::BAD_GATEWAY
+                    } else {
+                        StatusCode::OK
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let sender = WebhookSender {
+            base_backoff: Duration::from_millis(1),
+            ..WebhookSender::default()
+        };
+        let payload = WebhookPayload::new("run-1", RunStatus::Error, "agent", None, "");
+        let url = format!("http://{addr}/hook");
+        // Loopback is only reachable once allowed
+        assert!(sender.deliver(&url, None, &payload).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 0);
+
+        let sender = sender.with_allowed_hosts(vec!["127.0.0.1".to_string()]);
+        sender.deliver(&url, None, &payload).await.unwrap();
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_only_public_addresses_are_public() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+}
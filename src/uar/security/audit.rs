@@ -0,0 +1,65 @@
+//! Where audit entries are written to and read back from.
+
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use super::claims::UserContext;
+use crate::uar::domain::audit::{AuditEntry, AuditResource};
+use crate::uar::persistence::PersistenceLayer;
+
+/// Actor recorded for requests made without a token.
+pub const ANONYMOUS_ACTOR: &str = "anonymous";
+
+#[async_trait]
+pub trait AuditSink: Send + Sync + std::fmt::Debug {
+    /// Record one entry.
+    async fn record(&self, entry: AuditEntry) -> anyhow::Result<()>;
+
+    /// Most recent entries first, optionally only for one resource.
+    ///
+    /// `tenant_id` restricts the entries to those recorded in that tenant;
+    /// `None` lists the entries of every tenant.
+    async fn list(
+        &self,
+        resource: Option<AuditResource>,
+        tenant_id: Option<&str>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<AuditEntry>>;
+}
+
+/// Audit sink backed by the persistence layer.
+#[derive(Debug)]
+pub struct PersistentAuditSink {
+    persistence: Arc<dyn PersistenceLayer>,
+}
+
+impl PersistentAuditSink {
+    pub fn new(persistence: Arc<dyn PersistenceLayer>) -> Self {
+        Self { persistence }
+    }
+}
+
+#[async_trait]
+impl AuditSink for PersistentAuditSink {
+    async fn record(&self, entry: AuditEntry) -> anyhow::Result<()> {
+        self.persistence.save_audit_entry(&entry).await?;
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        resource: Option<AuditResource>,
+        tenant_id: Option<&str>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<AuditEntry>> {
+        Ok(self
+            .persistence
+            .list_audit_entries(resource, tenant_id, limit)
+            .await?)
+    }
+}
+
+/// The user a request acts as, for the audit trail.
+pub fn actor(user: Option<&UserContext>) -> String {
+    user.map_or_else(|| ANONYMOUS_ACTOR.to_string(), |u| u.user_id.clone())
+}
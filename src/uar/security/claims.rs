@@ -6,6 +6,21 @@ pub struct UserClaims {
     pub name: Option<String>,
     pub roles: Option<Vec<String>>,
     pub exp: usize, // Expiration time (UNIX timestamp)
+    /// Organization the user belongs to, in multi-tenant deployments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+/// Role allowed to read run logs and other administrative data.
+pub const ADMIN_ROLE: &str = "admin";
+
+impl UserClaims {
+    /// Whether the token grants `role`.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles
+            .as_deref()
+            .is_some_and(|roles| roles.iter().any(|r| r == role))
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -13,3 +28,14 @@ pub struct UserContext {
     pub user_id: String,
     pub claims: UserClaims,
 }
+
+/// Tenant a request acts in, taken from the token's `tenant_id` claim.
+#[derive(Clone, Debug)]
+pub struct TenantContext {
+    pub tenant_id: String,
+}
+
+/// The tenant scope of a request; `None` sees only shared records.
+pub fn tenant_scope(tenant: Option<&TenantContext>) -> Option<&str> {
+    tenant.map(|t| t.tenant_id.as_str())
+}
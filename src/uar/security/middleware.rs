@@ -7,7 +7,7 @@ use axum::{
 };
 use jsonwebtoken::{DecodingKey, Validation, decode};
 
-use super::claims::{UserClaims, UserContext};
+use super::claims::{ADMIN_ROLE, TenantContext, UserClaims, UserContext};
 
 pub async fn auth_middleware(
     State(state): State<AppState>,
@@ -52,3 +52,33 @@ pub async fn auth_middleware(
         Err(_) => Err(StatusCode::UNAUTHORIZED),
     }
 }
+
+/// Inject a [`TenantContext`] for users whose token carries a `tenant_id`.
+///
+/// Runs after [`auth_middleware`]; requests without one act outside any
+/// tenant and only see shared records.
+pub async fn tenant_middleware(mut request: Request, next: Next) -> Response {
+    let tenant_id = request
+        .extensions()
+        .get::<UserContext>()
+        .and_then(|user| user.claims.tenant_id.clone());
+    if let Some(tenant_id) = tenant_id {
+        request.extensions_mut().insert(TenantContext { tenant_id });
+    }
+    next.run(request).await
+}
+
+/// Only let users with the [`ADMIN_ROLE`] through.
+///
+/// Runs after [`auth_middleware`]; requests without a token are rejected
+/// even when tokens are optional.
+pub async fn require_admin(request: Request, next: Next) -> Result<Response, StatusCode> {
+    let is_admin = request
+        .extensions()
+        .get::<UserContext>()
+        .is_some_and(|user| user.claims.has_role(ADMIN_ROLE));
+    if !is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(next.run(request).await)
+}
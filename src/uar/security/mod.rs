@@ -1,3 +1,4 @@
+pub mod audit;
 pub mod claims;
 pub mod middleware;
 pub mod rate_limit;
@@ -1,25 +1,109 @@
+//! Request rate limiting.
+//!
+//! The global limit counts requests per client (the first `X-Forwarded-For`
+//! address, else the peer address) in memory, or in Redis when
+//! `resilience.redis_url` is set so every replica shares the same budget.
+
 use crate::AppState;
+use crate::uar::domain::artifact::AgentRateLimit;
+use async_trait::async_trait;
 use axum::{
-    extract::{Request, State},
+    extract::{ConnectInfo, Request, State},
     http::StatusCode,
     middleware::Next,
     response::Response,
 };
+use dashmap::DashMap;
 use governor::{
-    Quota, RateLimiter,
+    Quota, RateLimiter as Governor,
     clock::DefaultClock,
-    state::{InMemoryState, NotKeyed},
+    state::{InMemoryState, NotKeyed, keyed::DefaultKeyedStateStore},
 };
+use redis::{AsyncCommands, Script, aio::ConnectionManager};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
 use std::num::NonZeroU32;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::warn;
 
-/// Wrapper around Governor Rate Limiter to be stored in AppState
-/// We use a generic non-keyed limiter for global rate limiting as per current design.
-/// (Keyed by IP would require extracting IP which is added complexity).
+/// Prefix of the Redis keys holding each client's request timestamps.
+const REDIS_KEY_PREFIX: &str = "uar:rate_limit:";
+/// How long startup waits for Redis before limiting in memory.
+const REDIS_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sliding-window limit over a sorted set of request timestamps.
+///
+/// Drops timestamps older than the window, then records the request if
+/// fewer than the limit remain. Returns `{allowed, count}`.
+const SLIDING_WINDOW_SCRIPT: &str = r"
+local now = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+local count = redis.call('ZCARD', KEYS[1])
+if count >= tonumber(ARGV[3]) then
+    return {0, count}
+end
+redis.call('ZADD', KEYS[1], now, ARGV[4])
+redis.call('PEXPIRE', KEYS[1], window)
+return {1, count + 1}
+";
+
+/// Decides whether a client's request may proceed.
+#[async_trait]
+pub trait RateLimiter: Send + Sync + std::fmt::Debug {
+    /// Where counts are kept, e.g. `memory` or `redis`.
+    fn backend(&self) -> &'static str;
+
+    /// Count a request from `key`; returns whether it is within the limit.
+    async fn check(&self, key: &str) -> bool;
+
+    /// Requests each client made in the current window, keyed by client.
+    async fn counts(&self) -> anyhow::Result<BTreeMap<String, u64>>;
+}
+
+/// Client a request is counted against, set by [`rate_limit_middleware`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientKey(pub String);
+
+impl ClientKey {
+    fn of(req: &Request) -> Self {
+        let forwarded = req
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .map(str::trim)
+            .filter(|ip| !ip.is_empty());
+        let key = match forwarded {
+            Some(ip) => ip.to_string(),
+            None => req
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map_or_else(|| "unknown".to_string(), |info| info.0.ip().to_string()),
+        };
+        Self(key)
+    }
+}
+
+/// Period over which `burst_size` requests are allowed, so the sustained
+/// rate is `requests_per_second`.
+fn window(requests_per_second: f32, burst_size: u32) -> Duration {
+    Duration::try_from_secs_f64(f64::from(burst_size.max(1)) / f64::from(requests_per_second))
+        .unwrap_or(Duration::from_secs(1))
+}
+
+type KeyedLimiter = Governor<String, DefaultKeyedStateStore<String>, DefaultClock>;
+
+/// In-memory rate limiter: a token bucket per client.
+///
+/// Counts are lost on restart and not shared between replicas.
 #[derive(Debug, Clone)]
 pub struct AppRateLimiter {
-    limiter: Arc<RateLimiter<NotKeyed, InMemoryState, DefaultClock>>,
+    limiter: Arc<KeyedLimiter>,
+    window: Duration,
+    /// Allowed requests per client: (start of the current window, count)
+    requests: Arc<DashMap<String, (Instant, u64)>>,
 }
 
 impl AppRateLimiter {
@@ -38,26 +122,342 @@ impl AppRateLimiter {
         let quota = Quota::per_second(rps).allow_burst(burst);
 
         Self {
-            limiter: Arc::new(RateLimiter::direct(quota)),
+            limiter: Arc::new(Governor::keyed(quota)),
+            window: window(requests_per_second, burst_size),
+            requests: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Take one request from `key`'s budget.
+    pub fn check(&self, key: &str) -> bool {
+        if self.limiter.check_key(&key.to_string()).is_err() {
+            return false;
+        }
+        let mut entry = self
+            .requests
+            .entry(key.to_string())
+            .or_insert((Instant::now(), 0));
+        if entry.0.elapsed() >= self.window {
+            *entry = (Instant::now(), 0);
         }
+        entry.1 += 1;
+        true
+    }
+}
+
+#[async_trait]
+impl RateLimiter for AppRateLimiter {
+    fn backend(&self) -> &'static str {
+        "memory"
     }
 
-    pub fn check(&self) -> bool {
-        self.limiter.check().is_ok()
+    async fn check(&self, key: &str) -> bool {
+        AppRateLimiter::check(self, key)
+    }
+
+    async fn counts(&self) -> anyhow::Result<BTreeMap<String, u64>> {
+        // Forget clients that have been idle for a while
+        self.requests
+            .retain(|_, (start, _)| start.elapsed() < self.window * 2);
+        self.limiter.retain_recent();
+        Ok(self
+            .requests
+            .iter()
+            .filter(|entry| entry.value().0.elapsed() < self.window)
+            .map(|entry| (entry.key().clone(), entry.value().1))
+            .collect())
+    }
+}
+
+/// Rate limiter shared by every replica, keeping a sliding window of each
+/// client's request timestamps in Redis.
+///
+/// Falls back to an in-memory limiter while Redis can't be reached.
+#[derive(Clone)]
+pub struct RedisRateLimiter {
+    conn: ConnectionManager,
+    script: Arc<Script>,
+    limit: u32,
+    window: Duration,
+    fallback: AppRateLimiter,
+}
+
+impl std::fmt::Debug for RedisRateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisRateLimiter")
+            .field("limit", &self.limit)
+            .field("window", &self.window)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RedisRateLimiter {
+    /// Connect to the Redis server at `url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `url` is invalid or the server does not answer
+    /// within a few seconds.
+    pub async fn connect(
+        url: &str,
+        requests_per_second: f32,
+        burst_size: u32,
+    ) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url)?;
+        let conn = tokio::time::timeout(REDIS_CONNECT_TIMEOUT, ConnectionManager::new(client))
+            .await
+            .map_err(|_elapsed| anyhow::anyhow!("timed out connecting to Redis"))??;
+        Ok(Self {
+            conn,
+            script: Arc::new(Script::new(SLIDING_WINDOW_SCRIPT)),
+            limit: burst_size.max(1),
+            window: window(requests_per_second, burst_size),
+            fallback: AppRateLimiter::new(requests_per_second, burst_size),
+        })
+    }
+
+    fn window_ms(&self) -> u64 {
+        u64::try_from(self.window.as_millis()).unwrap_or(u64::MAX)
+    }
+}
+
+#[async_trait]
+impl RateLimiter for RedisRateLimiter {
+    fn backend(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn check(&self, key: &str) -> bool {
+        let now = chrono::Utc::now().timestamp_millis();
+        let result: redis::RedisResult<(u8, u64)> = self
+            .script
+            .key(format!("{REDIS_KEY_PREFIX}{key}"))
+            .arg(now)
+            .arg(self.window_ms())
+            .arg(self.limit)
+            // Unique, so simultaneous requests are all counted
+            .arg(format!("{now}-{}", uuid::Uuid::new_v4()))
+            .invoke_async(&mut self.conn.clone())
+            .await;
+        match result {
+            Ok((allowed, _)) => allowed == 1,
+            Err(e) => {
+                warn!("Redis rate limiter unavailable, limiting in memory: {}", e);
+                self.fallback.check(key)
+            }
+        }
+    }
+
+    async fn counts(&self) -> anyhow::Result<BTreeMap<String, u64>> {
+        let mut conn = self.conn.clone();
+        let mut keys = Vec::new();
+        {
+            let mut iter = conn
+                .scan_match::<_, String>(format!("{REDIS_KEY_PREFIX}*"))
+                .await?;
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+        }
+
+        let window_start = chrono::Utc::now().timestamp_millis()
+            - i64::try_from(self.window_ms()).unwrap_or(i64::MAX);
+        let mut counts = BTreeMap::new();
+        for key in keys {
+            let count: u64 = conn.zcount(&key, window_start, "+inf").await?;
+            if count > 0 {
+                let client = key.strip_prefix(REDIS_KEY_PREFIX).unwrap_or(&key);
+                counts.insert(client.to_string(), count);
+            }
+        }
+        Ok(counts)
+    }
+}
+
+/// Rate limiter for the global limit: Redis-backed when `redis_url` is set
+/// and reachable, in memory otherwise.
+pub async fn connect_rate_limiter(
+    redis_url: Option<&str>,
+    requests_per_second: f32,
+    burst_size: u32,
+) -> Arc<dyn RateLimiter> {
+    if let Some(url) = redis_url {
+        match RedisRateLimiter::connect(url, requests_per_second, burst_size).await {
+            Ok(limiter) => {
+                tracing::info!("Rate limiting with Redis");
+                return Arc::new(limiter);
+            }
+            Err(e) => warn!("Failed to connect to Redis, rate limiting in memory: {:?}", e),
+        }
+    }
+    Arc::new(AppRateLimiter::new(requests_per_second, burst_size))
+}
+
+type DirectLimiter = Governor<NotKeyed, InMemoryState, DefaultClock>;
+
+/// Per-agent run limits from `policy.rate_limit`.
+///
+/// Buckets are created on first use and rebuilt when an agent's limit
+/// changes (e.g. the artifact was re-imported).
+#[derive(Debug, Default)]
+pub struct AgentRateLimiter {
+    buckets: DashMap<String, (AgentRateLimit, Arc<DirectLimiter>)>,
+}
+
+impl AgentRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take one run from the agent's budget.
+    ///
+    /// Returns a description of the exhausted limit when the run is not
+    /// allowed.
+    pub fn check(
+        &self,
+        agent_id: &str,
+        user_id: Option<&str>,
+        limit: &AgentRateLimit,
+    ) -> Result<(), String> {
+        let key = if limit.per_user {
+            format!("{agent_id}\u{0}{}", user_id.unwrap_or_default())
+        } else {
+            agent_id.to_string()
+        };
+
+        let limiter = {
+            let mut bucket = self
+                .buckets
+                .entry(key)
+                .or_insert_with(|| (limit.clone(), Arc::new(Self::build(limit))));
+            if bucket.0 != *limit {
+                *bucket = (limit.clone(), Arc::new(Self::build(limit)));
+            }
+            Arc::clone(&bucket.1)
+        };
+
+        if limiter.check().is_ok() {
+            return Ok(());
+        }
+        Err(format!(
+            "agent '{}' allows {} runs per minute{}",
+            agent_id,
+            limit.requests_per_minute,
+            if limit.per_user { " per user" } else { "" }
+        ))
+    }
+
+    fn build(limit: &AgentRateLimit) -> DirectLimiter {
+        let rpm = NonZeroU32::new(limit.requests_per_minute).unwrap_or(NonZeroU32::MIN);
+        let burst = limit.burst.and_then(NonZeroU32::new).unwrap_or(rpm);
+        Governor::direct(Quota::per_minute(rpm).allow_burst(burst))
     }
 }
 
 /// Middleware to enforce rate limits
 pub async fn rate_limit_middleware(
     State(state): State<AppState>,
-    req: Request,
+    mut req: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
+    let client = ClientKey::of(&req);
     if state.config.resilience.rate_limit_enabled {
-        if !state.rate_limiter.check() {
-            warn!("Rate limit exceeded");
+        if !state.rate_limiter.check(&client.0).await {
+            warn!(client = %client.0, "Rate limit exceeded");
             return Err(StatusCode::TOO_MANY_REQUESTS);
         }
     }
+    req.extensions_mut().insert(client);
     Ok(next.run(req).await)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(requests_per_minute: u32) -> AgentRateLimit {
+        AgentRateLimit {
+            requests_per_minute,
+            burst: None,
+            per_user: false,
+        }
+    }
+
+    #[test]
+    fn test_strict_agent_throttled_lenient_agent_not() {
+        let limiter = AgentRateLimiter::new();
+        let strict = limit(1);
+        let lenient = limit(100);
+
+        assert!(limiter.check("gpt4o-agent", Some("alice"), &strict).is_ok());
+        let err = limiter
+            .check("gpt4o-agent", Some("alice"), &strict)
+            .unwrap_err();
+        assert_eq!(err, "agent 'gpt4o-agent' allows 1 runs per minute");
+
+        for _ in 0..10 {
+            assert!(limiter.check("local-agent", Some("alice"), &lenient).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_per_user_buckets() {
+        let limiter = AgentRateLimiter::new();
+        let strict = AgentRateLimit {
+            per_user: true,
+            ..limit(1)
+        };
+
+        assert!(limiter.check("agent", Some("alice"), &strict).is_ok());
+        assert!(limiter.check("agent", Some("bob"), &strict).is_ok());
+        assert!(limiter.check("agent", Some("alice"), &strict).is_err());
+    }
+
+    #[test]
+    fn test_clients_have_separate_budgets() {
+        let limiter = AppRateLimiter::new(1.0, 2);
+        assert!(limiter.check("10.0.0.1"));
+        assert!(limiter.check("10.0.0.1"));
+        assert!(!limiter.check("10.0.0.1"));
+        assert!(limiter.check("10.0.0.2"));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_redis_falls_back_to_memory() {
+        let limiter = connect_rate_limiter(Some("redis://127.0.0.1:1"), 1.0, 1).await;
+        assert_eq!(limiter.backend(), "memory");
+        assert!(limiter.check("10.0.0.1").await);
+        assert!(!limiter.check("10.0.0.1").await);
+        assert_eq!(limiter.counts().await.unwrap()["10.0.0.1"], 1);
+
+        let limiter = connect_rate_limiter(Some("not a url"), 1.0, 1).await;
+        assert_eq!(limiter.backend(), "memory");
+    }
+
+    #[test]
+    fn test_client_key_prefers_forwarded_address() {
+        let request = |forwarded: Option<&str>| {
+            let mut request = Request::builder().extension(ConnectInfo(SocketAddr::from((
+                [192, 168, 1, 5],
+                4000,
+            ))));
+            if let Some(forwarded) = forwarded {
+                request = request.header("x-forwarded-for", forwarded);
+            }
+            request.body(axum::body::Body::empty()).unwrap()
+        };
+        assert_eq!(
+            ClientKey::of(&request(Some("203.0.113.7, 10.0.0.1"))),
+            ClientKey("203.0.113.7".to_string())
+        );
+        assert_eq!(ClientKey::of(&request(None)), ClientKey("192.168.1.5".to_string()));
+    }
+
+    #[test]
+    fn test_changed_limit_rebuilds_bucket() {
+        let limiter = AgentRateLimiter::new();
+        assert!(limiter.check("agent", None, &limit(1)).is_ok());
+        assert!(limiter.check("agent", None, &limit(1)).is_err());
+        assert!(limiter.check("agent", None, &limit(5)).is_ok());
+    }
+}
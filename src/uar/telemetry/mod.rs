@@ -1,15 +1,57 @@
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Request};
+use axum::{middleware::Next, response::Response};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use std::sync::OnceLock;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+use uuid::Uuid;
+
+static PROMETHEUS: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Collector spans are exported to; OTLP export is off without it.
+const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+/// `grpc` or `http/protobuf` (the default).
+const OTLP_PROTOCOL_ENV: &str = "OTEL_EXPORTER_OTLP_PROTOCOL";
+/// Name spans are reported under.
+const SERVICE_NAME: &str = "axum-leptos-htmx-wc";
+/// Header correlating a request with its logs, runs and error events.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+/// Longest request ID accepted from a client; longer ones are replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Keeps the OTLP exporter of [`init`] alive; [`Self::shutdown`] flushes
+/// the spans not exported yet.
+#[derive(Debug, Default)]
+#[must_use = "spans are only flushed by `TelemetryGuard::shutdown`"]
+pub struct TelemetryGuard {
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+impl TelemetryGuard {
+    /// Export the remaining spans and stop the exporter.
+    pub fn shutdown(self) {
+        if let Some(provider) = self.tracer_provider
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to shut down OpenTelemetry export: {e}");
+        }
+    }
+}
 
 /// Initialize application telemetry (Logging, Tracing, Metrics).
 ///
 /// Currently configures:
 /// - `tracing-subscriber::fmt` for structured logging.
 /// - `EnvFilter` for dynamic log levels (RUST_LOG).
-///
-/// Future:
-/// - OpenTelemetry layer for distributed tracing.
-/// - Prometheus exporter for metrics.
-pub fn init() {
+/// - OpenTelemetry span export over OTLP when `OTEL_EXPORTER_OTLP_ENDPOINT`
+///   is set (gRPC with `OTEL_EXPORTER_OTLP_PROTOCOL=grpc`, HTTP otherwise),
+///   continuing traces of incoming `traceparent` headers.
+pub fn init() -> TelemetryGuard {
     let fmt_layer = tracing_subscriber::fmt::layer()
         .with_target(true)
         .with_thread_ids(true)
@@ -19,9 +61,188 @@ pub fn init() {
     let filter_layer = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new("info,axum_leptos_htmx_wc=debug"));
 
+    // Logging isn't up yet, so a broken exporter is reported on stderr
+    let tracer_provider = otlp_tracer_provider().unwrap_or_else(|e| {
+        eprintln!("OpenTelemetry export disabled: {e}");
+        None
+    });
+    let otel_layer = tracer_provider.as_ref().map(|provider| {
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME))
+    });
+
     tracing_subscriber::registry()
         .with(filter_layer)
         .with(fmt_layer)
-        // .with(opentelemetry_layer) // TODO: Add OTel here
+        .with(otel_layer)
         .init();
+
+    TelemetryGuard { tracer_provider }
+}
+
+/// Tracer provider exporting to the configured OTLP collector, if any.
+fn otlp_tracer_provider()
+-> Result<Option<SdkTracerProvider>, opentelemetry_otlp::ExporterBuildError> {
+    let enabled = std::env::var(OTLP_ENDPOINT_ENV).is_ok_and(|e| !e.trim().is_empty());
+    if !enabled {
+        return Ok(None);
+    }
+
+    // Both exporters read the endpoint from the environment themselves (the
+    // HTTP one appending `/v1/traces`)
+    let exporter = match std::env::var(OTLP_PROTOCOL_ENV).as_deref() {
+        Ok("grpc") => SpanExporter::builder().with_tonic().build()?,
+        _ => SpanExporter::builder().with_http().build()?,
+    };
+    let resource = opentelemetry_sdk::Resource::builder()
+        .with_service_name(SERVICE_NAME)
+        .build();
+    Ok(Some(
+        SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource)
+            .build(),
+    ))
+}
+
+/// ID of the HTTP request being handled, set by [`request_id_middleware`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Keep the request's `X-Request-Id`, or generate one, and echo it on the
+/// response.
+///
+/// Added outside `TraceLayer`, so [`request_span`] records the ID of every
+/// request; handlers read it from the [`RequestId`] extension.
+pub async fn request_id_middleware(mut request: axum::extract::Request, next: Next) -> Response {
+    let value = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .filter(|value| is_valid_request_id(value.as_bytes()))
+        .cloned()
+        .unwrap_or_else(|| {
+            HeaderValue::from_str(&Uuid::new_v4().to_string()).expect("UUIDs are header-safe")
+        });
+    let id = value.to_str().unwrap_or_default().to_string();
+    request.headers_mut().insert(REQUEST_ID_HEADER, value.clone());
+    request.extensions_mut().insert(RequestId(id));
+
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    response
+}
+
+/// Client IDs must be short and visible ASCII to end up in logs as-is.
+fn is_valid_request_id(id: &[u8]) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.iter().all(u8::is_ascii_graphic)
+}
+
+/// Span of an HTTP request for `TraceLayer`, continuing the trace of the
+/// request's `traceparent` header when it has one.
+pub fn request_span<B>(request: &Request<B>) -> tracing::Span {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+        request_id = %request_id,
+    );
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+    // Fails only when no OpenTelemetry layer is installed
+    let _ = span.set_parent(parent);
+    span
+}
+
+/// Reads propagation headers (`traceparent`, `tracestate`) of a request.
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// Prometheus recorder handle, installing the global recorder on first use.
+///
+/// Metrics recorded via the `metrics` macros are rendered by [`render_metrics`].
+pub fn prometheus_handle() -> &'static PrometheusHandle {
+    PROMETHEUS.get_or_init(|| {
+        PrometheusBuilder::new()
+            .install_recorder()
+            .expect("Failed to install Prometheus recorder")
+    })
+}
+
+/// GET /metrics - Prometheus text exposition.
+pub async fn render_metrics() -> String {
+    prometheus_handle().render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::trace::TraceContextExt;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_traceparent_is_extracted() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+        let cx = TraceContextPropagator::new().extract(&HeaderExtractor(&headers));
+        let parent = cx.span().span_context().clone();
+        assert!(parent.is_remote());
+        assert_eq!(parent.trace_id().to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(parent.span_id().to_string(), "00f067aa0ba902b7");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_kept_or_generated_and_echoed() {
+        let app = axum::Router::new()
+            .route(
+                "/",
+                axum::routing::get(|axum::Extension(id): axum::Extension<RequestId>| async move {
+                    id.0
+                }),
+            )
+            .layer(axum::middleware::from_fn(request_id_middleware));
+        let send = |id: Option<&str>| {
+            let mut request = Request::builder().uri("/");
+            if let Some(id) = id {
+                request = request.header(REQUEST_ID_HEADER, id);
+            }
+            app.clone()
+                .oneshot(request.body(axum::body::Body::empty()).unwrap())
+        };
+
+        let response = send(Some("support-1234")).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "support-1234");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "support-1234");
+
+        // Missing and unusable IDs are replaced
+        for id in [None, Some(""), Some("has space")] {
+            let response = send(id).await.unwrap();
+            let echoed = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+            assert!(Uuid::parse_str(echoed).is_ok(), "{echoed}");
+        }
+    }
 }
@@ -0,0 +1,55 @@
+//! Fixtures shared by unit and integration tests.
+
+use crate::llm::{
+    EmptyResponsePolicy, GenerationParams, LlmProtocol, LlmSettings, Provider, UsageFields,
+};
+use crate::uar::rag::embedding::EmbeddingProvider;
+use async_trait::async_trait;
+
+/// Settings of a generic Chat Completions endpoint at `base_url` serving
+/// `model`, reporting empty responses as errors.
+pub fn llm_settings(base_url: impl Into<String>, model: &str) -> LlmSettings {
+    LlmSettings {
+        base_url: base_url.into(),
+        api_key: None,
+        model: model.to_string(),
+        protocol: LlmProtocol::Chat,
+        provider: Provider::Generic,
+        parallel_tool_calls: None,
+        deployment_name: None,
+        api_version: None,
+        empty_response: EmptyResponsePolicy::Error,
+        content_filter_min_severity: None,
+        generation: GenerationParams::default(),
+        usage_fields: UsageFields::default(),
+    }
+}
+
+/// Embeds every text as the same vector, `[1.0, 0.0]` by default.
+#[derive(Debug)]
+pub struct StubEmbedder {
+    vector: Vec<f32>,
+}
+
+impl StubEmbedder {
+    pub fn new(vector: Vec<f32>) -> Self {
+        Self { vector }
+    }
+}
+
+impl Default for StubEmbedder {
+    fn default() -> Self {
+        Self::new(vec![1.0, 0.0])
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for StubEmbedder {
+    async fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|_| self.vector.clone()).collect())
+    }
+
+    fn dimensions(&self) -> usize {
+        self.vector.len()
+    }
+}
@@ -83,6 +83,7 @@ impl NativeTool for MemorySaveTool {
             content: content.to_string(),
             tags,
             embedding,
+            tenant_id: None,
             created_at: chrono::Utc::now().to_rfc3339(),
         };
 
@@ -159,7 +160,7 @@ impl NativeTool for MemoryRecallTool {
 
         let matches = self
             .persistence
-            .search_memory(agent_id, &embedding, limit, 0.0)
+            .search_memory(agent_id, &embedding, limit, 0.0, None)
             .await?;
 
         let results: Vec<serde_json::Value> = matches
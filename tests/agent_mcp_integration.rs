@@ -0,0 +1,175 @@
+//! Agents bringing their own MCP servers: a stdio server declared in the
+//! artifact's `tools.mcp` is started for the run, its tools sit next to the
+//! global ones under the agent's namespace, and it is stopped with the run.
+
+mod common;
+
+use axum::{
+    Json, Router,
+    extract::State,
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use axum_leptos_htmx_wc::mcp::config::{McpConfig, McpServerEntry};
+use axum_leptos_htmx_wc::mcp::registry::McpRegistry;
+use axum_leptos_htmx_wc::uar::{
+    defaults::default_agent,
+    domain::{events::NormalizedEvent, runs::RunOptions},
+    runtime::manager::RunManager,
+};
+use serde_json::Value;
+use std::{path::Path, time::Duration};
+use tokio::sync::mpsc;
+
+/// Namespaced name of the `echo` tool of the agent's `echo` server.
+const AGENT_TOOL: &str = "agent-echo-agent__echo__echo";
+
+/// A stdio MCP server with one tool, `echo`, writing its PID to `$1`.
+const ECHO_SERVER: &str = r#"#!/bin/sh
+echo $$ > "$1"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/^{"jsonrpc":"2.0","id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*)
+      version=$(printf '%s' "$line" | sed -n 's/.*"protocolVersion":"\([^"]*\)".*/\1/p')
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"protocolVersion":"%s","capabilities":{"tools":{}},"serverInfo":{"name":"echo","version":"1.0.0"}}}\n' "$id" "$version"
+      ;;
+    *'"method":"tools/list"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"tools":[{"name":"echo","description":"Echoes its text","inputSchema":{"type":"object","properties":{"text":{"type":"string"}}}}]}}\n' "$id"
+      ;;
+    *'"method":"tools/call"'*)
+      text=$(printf '%s' "$line" | sed -n 's/.*"text":"\([^"]*\)".*/\1/p')
+      printf '{"jsonrpc":"2.0","id":%s,"result":{"content":[{"type":"text","text":"echo: %s"}]}}\n' "$id" "$text"
+      ;;
+    *'"method":"ping"'*)
+      printf '{"jsonrpc":"2.0","id":%s,"result":{}}\n' "$id"
+      ;;
+  esac
+done
+"#;
+
+/// Records the tools it was offered, calls the agent's tool and answers
+/// with its result.
+async fn mock_completion(
+    State(offered): State<mpsc::UnboundedSender<Vec<String>>>,
+    Json(request): Json<Value>,
+) -> Response {
+    let tools = request["tools"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tool| tool["function"]["name"].as_str().map(str::to_string))
+        .collect();
+    let _ = offered.send(tools);
+
+    let last = request["messages"]
+        .as_array()
+        .and_then(|messages| messages.last())
+        .cloned()
+        .unwrap_or_default();
+    if last["role"] == "tool" {
+        common::sse_answer(last["content"].as_str().unwrap_or_default()).into_response()
+    } else {
+        common::sse_tool_call(AGENT_TOOL, r#"{"text":"hi"}"#).into_response()
+    }
+}
+
+async fn manager() -> (RunManager, mpsc::UnboundedReceiver<Vec<String>>) {
+    let (offered_tx, offered) = mpsc::unbounded_channel();
+    let app = Router::new()
+        .route("/v1/chat/completions", post(mock_completion))
+        .with_state(offered_tx);
+    let llm = common::serve(app).await;
+    let mcp = McpRegistry::new_with_test_tool("mirror", "Returns its input");
+    (common::run_manager(llm, mcp).await, offered)
+}
+
+/// Whether process `pid` is still running (zombies count as exited).
+fn is_running(pid: &str) -> bool {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "stat=", "-p", pid])
+        .output()
+        .unwrap();
+    let stat = String::from_utf8_lossy(&output.stdout);
+    !stat.trim().is_empty() && !stat.trim().starts_with('Z')
+}
+
+fn read_pid(path: &Path) -> String {
+    std::fs::read_to_string(path).unwrap().trim().to_string()
+}
+
+#[tokio::test]
+async fn test_agent_mcp_server_serves_its_run_and_stops_with_it() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("echo-server.sh");
+    std::fs::write(&script, ECHO_SERVER).unwrap();
+    let pid_file = dir.path().join("echo-server.pid");
+
+    let mut agent = default_agent();
+    agent.id = "echo-agent".to_string();
+    let server = McpServerEntry::Stdio {
+        command: "sh".to_string(),
+        args: vec![
+            script.to_string_lossy().into_owned(),
+            pid_file.to_string_lossy().into_owned(),
+        ],
+        env: Default::default(),
+        require_approval: vec![],
+        tool_limits: Default::default(),
+    };
+    agent.tools.mcp = Some(McpConfig {
+        mcp_servers: [("echo".to_string(), server)].into(),
+    });
+
+    let (manager, mut offered) = manager().await;
+    let (_, mut events) = manager
+        .start_run_streaming(
+            agent,
+            "Echo 'hi'".to_string(),
+            None,
+            None,
+            None,
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+    let (tools, output) = tokio::time::timeout(Duration::from_secs(30), async {
+        let (mut tools, mut output) = (Vec::new(), None);
+        while let Ok(event) = events.recv().await {
+            match event {
+                NormalizedEvent::ToolStart { tool, .. } => tools.push(tool),
+                NormalizedEvent::ToolEnd { output: out, ok, .. } => {
+                    assert!(ok, "{out}");
+                    output = Some(out);
+                }
+                NormalizedEvent::Error { message, .. } => panic!("run failed: {message}"),
+                NormalizedEvent::RunDone { .. } => break,
+                _ => {}
+            }
+        }
+        (tools, output)
+    })
+    .await
+    .expect("run did not finish within 30 seconds");
+
+    assert_eq!(tools, vec![AGENT_TOOL.to_string()]);
+    let output = output.expect("the agent's tool was not called").to_string();
+    assert!(output.contains("echo: hi"), "{output}");
+
+    // The agent's tools are offered next to the global ones
+    let first = offered.try_recv().expect("the LLM was not called");
+    assert!(first.contains(&AGENT_TOOL.to_string()), "{first:?}");
+    assert!(first.contains(&"test__mirror".to_string()), "{first:?}");
+    // ...without becoming global
+    assert!(manager.tools().tools().iter().all(|(name, _)| name != AGENT_TOOL));
+
+    // The server was stopped with the run
+    let pid = read_pid(&pid_file);
+    let stopped = tokio::time::timeout(Duration::from_secs(5), async {
+        while is_running(&pid) {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await;
+    assert!(stopped.is_ok(), "MCP server {pid} still running after the run");
+}
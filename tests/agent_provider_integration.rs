@@ -0,0 +1,198 @@
+//! Agents running on their own LLM endpoints, against two mock servers.
+
+mod common;
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, header},
+    response::IntoResponse,
+    routing::post,
+};
+use axum_leptos_htmx_wc::llm::{LlmSettings, ModelAliases};
+use axum_leptos_htmx_wc::mcp::registry::McpRegistry;
+use axum_leptos_htmx_wc::uar::{
+    defaults::default_agent,
+    domain::{artifact::AgentArtifact, events::NormalizedEvent, runs::RunOptions},
+    runtime::manager::RunManager,
+};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// What a mock endpoint saw: its name, the requested model and the API key.
+type Seen = (&'static str, String, Option<String>);
+
+#[derive(Clone)]
+struct MockLlm {
+    name: &'static str,
+    seen: mpsc::UnboundedSender<Seen>,
+}
+
+/// Records the request and streams an answer naming the endpoint.
+async fn mock_completion(
+    State(mock): State<MockLlm>,
+    headers: HeaderMap,
+    Json(request): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let model = request["model"].as_str().unwrap_or_default().to_string();
+    let api_key = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let _ = mock.seen.send((mock.name, model, api_key));
+    common::sse_answer(&format!("Hello from {}", mock.name))
+}
+
+/// Serve a mock LLM named `name`, returning its base URL.
+async fn spawn_mock(name: &'static str, seen: mpsc::UnboundedSender<Seen>) -> String {
+    let app = Router::new()
+        .route("/v1/chat/completions", post(mock_completion))
+        .with_state(MockLlm { name, seen });
+    format!("http://{}", common::serve(app).await)
+}
+
+/// Run `agent` and return its answer.
+async fn answer(manager: &RunManager, agent: AgentArtifact) -> String {
+    answer_with(manager, agent, RunOptions::default()).await
+}
+
+/// Run `agent` with `options` and return its answer.
+async fn answer_with(manager: &RunManager, agent: AgentArtifact, options: RunOptions) -> String {
+    let (_, mut events) = manager
+        .start_run_streaming(agent, "Hi".to_string(), None, None, None, options)
+        .await
+        .unwrap();
+    let mut text = String::new();
+    tokio::time::timeout(Duration::from_secs(30), async {
+        while let Ok(event) = events.recv().await {
+            match event {
+                NormalizedEvent::ChatDelta { text_delta, .. } => text.push_str(&text_delta),
+                NormalizedEvent::Error { message, .. } => panic!("run failed: {message}"),
+                NormalizedEvent::RunDone { .. } => break,
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("run did not finish within 30 seconds");
+    text
+}
+
+/// Server settings calling the generic endpoint at `base_url`.
+fn server_settings(base_url: String) -> LlmSettings {
+    LlmSettings {
+        api_key: Some("server-key".to_string()),
+        ..common::llm_settings(base_url, "server-model")
+    }
+}
+
+#[tokio::test]
+async fn test_agents_use_their_own_endpoints() {
+    let (seen_tx, mut seen) = mpsc::unbounded_channel();
+    let server_url = spawn_mock("server", seen_tx.clone()).await;
+    let alpha_url = spawn_mock("alpha", seen_tx.clone()).await;
+    let beta_url = spawn_mock("beta", seen_tx).await;
+
+    let manager =
+        common::run_manager_with(server_settings(server_url), McpRegistry::new_empty()).await;
+
+    // SAFETY: no other test in this binary reads the variable
+    unsafe {
+        std::env::set_var("UAR_TEST_BETA_API_KEY", "beta-key");
+    }
+
+    let mut alpha = default_agent();
+    alpha.id = "alpha-agent".to_string();
+    alpha.policy.provider.default.model = "alpha-model".to_string();
+    alpha.policy.provider.default.base_url = Some(alpha_url);
+
+    let mut beta = default_agent();
+    beta.id = "beta-agent".to_string();
+    beta.policy.provider.default.provider = "generic".to_string();
+    beta.policy.provider.default.model = "beta-model".to_string();
+    beta.policy.provider.default.base_url = Some(beta_url);
+    beta.policy.provider.default.api_key_env = Some("UAR_TEST_BETA_API_KEY".to_string());
+
+    assert_eq!(answer(&manager, alpha).await, "Hello from alpha");
+    assert_eq!(answer(&manager, beta).await, "Hello from beta");
+    assert_eq!(answer(&manager, default_agent()).await, "Hello from server");
+
+    let mut requests = Vec::new();
+    while let Ok(request) = seen.try_recv() {
+        requests.push(request);
+    }
+    assert_eq!(
+        requests,
+        [
+            ("alpha", "alpha-model".to_string(), None),
+            ("beta", "beta-model".to_string(), Some("beta-key".to_string())),
+            ("server", "server-model".to_string(), Some("server-key".to_string())),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_model_aliases_resolve_per_provider() {
+    let (seen_tx, mut seen) = mpsc::unbounded_channel();
+    let server_url = spawn_mock("server", seen_tx).await;
+
+    let aliases: ModelAliases = serde_json::from_value(serde_json::json!({
+        "fast": { "openai": "gpt-4o-mini", "generic": "llama-3.1-8b-instant" },
+        "smart": { "default": "gpt-4o" },
+        "azure-only": { "azure": "mini-prod" },
+    }))
+    .unwrap();
+    let manager = common::run_manager_with(server_settings(server_url), McpRegistry::new_empty())
+        .await
+        .with_model_aliases(aliases);
+
+    let mut agent = default_agent();
+    agent.policy.provider.default.model = "fast".to_string();
+    assert_eq!(answer(&manager, agent.clone()).await, "Hello from server");
+
+    // A request's model replaces the agent's, and may be an alias too
+    let options = RunOptions {
+        model: Some("smart".to_string()),
+        ..RunOptions::default()
+    };
+    assert_eq!(answer_with(&manager, agent.clone(), options).await, "Hello from server");
+
+    let mut requests = Vec::new();
+    while let Ok((_, model, _)) = seen.try_recv() {
+        requests.push(model);
+    }
+    assert_eq!(requests, ["llama-3.1-8b-instant", "gpt-4o"]);
+
+    // An alias the provider has no model for fails the run before any request
+    agent.policy.provider.default.model = "azure-only".to_string();
+    let options = RunOptions {
+        request_id: Some("req-7".to_string()),
+        ..RunOptions::default()
+    };
+    let (_, mut events) = manager
+        .start_run_streaming(agent, "Hi".to_string(), None, None, None, options)
+        .await
+        .unwrap();
+    let (message, request_id) = tokio::time::timeout(Duration::from_secs(30), async {
+        loop {
+            if let Ok(NormalizedEvent::Error {
+                message,
+                request_id,
+                ..
+            }) = events.recv().await
+            {
+                return (message, request_id);
+            }
+        }
+    })
+    .await
+    .expect("run did not fail within 30 seconds");
+    assert!(
+        message.contains("model alias 'azure-only' has no model for provider 'generic'"),
+        "{message}"
+    );
+    // Errors name the request that started the run
+    assert_eq!(request_id.as_deref(), Some("req-7"));
+    assert!(seen.try_recv().is_err());
+}
@@ -0,0 +1,105 @@
+//! `POST /agents/{id}/test`, against a mock LLM that calls a tool when asked
+//! to mirror something and answers directly otherwise.
+
+mod common;
+
+use axum::{
+    Json, Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use axum_leptos_htmx_wc::mcp::registry::McpRegistry;
+use axum_leptos_htmx_wc::uar::api::routes::build_router;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tower::ServiceExt;
+
+/// Calls the `mirror` test tool for inputs mentioning it; cases run
+/// concurrently, so the answer depends on the request alone.
+async fn mock_completion(Json(request): Json<Value>) -> Response {
+    let last = request["messages"]
+        .as_array()
+        .and_then(|messages| messages.last())
+        .cloned()
+        .unwrap_or_default();
+    let asks_for_tool = last["role"] == "user"
+        && last["content"]
+            .as_str()
+            .is_some_and(|content| content.contains("mirror"));
+
+    if asks_for_tool {
+        common::sse_tool_call("test__mirror", r#"{"mirror":"hi"}"#).into_response()
+    } else if last["role"] == "tool" {
+        common::sse_answer("Mirrored").into_response()
+    } else {
+        common::sse_answer("Hello").into_response()
+    }
+}
+
+async fn router() -> Router {
+    let app = Router::new().route("/v1/chat/completions", post(mock_completion));
+    let llm = common::serve(app).await;
+    let mcp = McpRegistry::new_with_test_tool("mirror", "Returns its input");
+    let manager = common::run_manager(llm, mcp).await;
+    build_router().with_state(Arc::new(manager))
+}
+
+async fn test_agent(router: Router, body: Value) -> (StatusCode, Value) {
+    let request = Request::post("/agents/default-agent/test")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = router.oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_case_expecting_a_tool_passes_when_the_agent_calls_it() {
+    let (status, report) = test_agent(
+        router().await,
+        json!({
+            "cases": [
+                {
+                    "input": "Please mirror 'hi'",
+                    "expect_tool": "test__mirror",
+                    "expect_contains": "mirrored"
+                },
+                { "input": "Say hello", "expect_tool": "test__mirror" },
+                { "input": "Say hello", "expect_contains": "hello" }
+            ],
+            "timeout_secs": 30
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["agent_id"], "default-agent");
+    assert_eq!(report["passed"], 2);
+    assert_eq!(report["failed"], 1);
+
+    let results = report["results"].as_array().unwrap();
+    assert_eq!(results[0]["passed"], true, "{}", results[0]);
+    assert_eq!(results[0]["tool_calls"], json!(["test__mirror"]));
+    assert_eq!(results[0]["output"], "Mirrored");
+
+    // Results keep the order of the cases
+    assert_eq!(results[1]["passed"], false);
+    assert_eq!(results[1]["output"], "Hello");
+    assert_eq!(results[1]["tool_calls"], json!([]));
+    assert_eq!(
+        results[1]["failures"],
+        json!(["Expected a call to 'test__mirror'"])
+    );
+    assert_eq!(results[2]["passed"], true, "{}", results[2]);
+}
+
+#[tokio::test]
+async fn test_suites_must_have_cases() {
+    let (status, _) = test_agent(router().await, json!({ "cases": [] })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,69 @@
+//! Round trip against AWS Bedrock.
+//!
+//! Skipped unless AWS credentials are configured (`AWS_ACCESS_KEY_ID` or
+//! `AWS_PROFILE`). The account needs access to `amazon.titan-text-lite-v1`
+//! in `BEDROCK_REGION` (default `us-east-1`).
+
+mod common;
+
+use axum_leptos_htmx_wc::llm::{
+    BedrockDriver, LlmDriver, LlmProtocol, LlmRequest, LlmSettings, Provider,
+};
+use axum_leptos_htmx_wc::normalized::NormalizedEvent;
+use futures::StreamExt;
+use serde_json::json;
+
+const MODEL_ID: &str = "amazon.titan-text-lite-v1";
+
+fn has_credentials() -> bool {
+    ["AWS_ACCESS_KEY_ID", "AWS_PROFILE"]
+        .iter()
+        .any(|var| std::env::var(var).is_ok_and(|v| !v.is_empty()))
+}
+
+#[tokio::test]
+async fn test_titan_round_trip() {
+    if !has_credentials() {
+        eprintln!("Skipping test: AWS credentials not set");
+        return;
+    }
+
+    let region = std::env::var("BEDROCK_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+    let base_url = format!("https://bedrock-runtime.{region}.amazonaws.com");
+    let settings = LlmSettings {
+        provider: Provider::Bedrock {
+            region,
+            model_id: MODEL_ID.to_string(),
+        },
+        protocol: LlmProtocol::Auto,
+        ..common::llm_settings(base_url, MODEL_ID)
+    };
+
+    let request = LlmRequest {
+        messages: vec![json!({ "role": "user", "content": "Reply with the word: hello" })],
+        tools: Vec::new(),
+        response_format: None,
+        tool_choice: None,
+    };
+    let events: Vec<NormalizedEvent> = BedrockDriver::new(settings)
+        .stream(request)
+        .await
+        .expect("Bedrock request failed")
+        .map(|event| event.expect("Bedrock stream failed"))
+        .collect()
+        .await;
+
+    let answer: String = events
+        .iter()
+        .filter_map(|event| match event {
+            NormalizedEvent::MessageDelta { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert!(!answer.trim().is_empty(), "empty answer: {events:?}");
+    assert!(events.iter().any(|event| matches!(
+        event,
+        NormalizedEvent::Usage { completion_tokens, .. } if *completion_tokens > 0
+    )));
+    assert_eq!(events.last(), Some(&NormalizedEvent::Done));
+}
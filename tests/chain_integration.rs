@@ -0,0 +1,115 @@
+//! Chained runs end to end: two steps against a mock LLM that echoes its
+//! input, streamed on the chain's own channel.
+
+mod common;
+
+use axum::{Json, Router, response::IntoResponse, routing::post};
+use axum_leptos_htmx_wc::mcp::registry::McpRegistry;
+use axum_leptos_htmx_wc::uar::{
+    defaults::default_agent,
+    domain::{artifact::ChainStep, events::NormalizedEvent, runs::RunStatus},
+    runtime::manager::RunManager,
+};
+use futures::StreamExt;
+use serde_json::Value;
+use std::time::Duration;
+
+/// Answers `echo(<last user message>)`.
+async fn mock_completion(Json(request): Json<Value>) -> impl IntoResponse {
+    let input = request["messages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .rfind(|message| message["role"] == "user")
+        .and_then(|message| message["content"].as_str())
+        .unwrap_or_default();
+    common::sse_answer(&format!("echo({input})"))
+}
+
+async fn manager() -> RunManager {
+    let app = Router::new().route("/v1/chat/completions", post(mock_completion));
+    let llm = common::serve(app).await;
+    common::run_manager(llm, McpRegistry::new_empty()).await
+}
+
+fn step(template: &str) -> ChainStep {
+    ChainStep {
+        agent_id: default_agent().id,
+        input_template: template.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_two_step_chain_streams_both_steps() {
+    let manager = manager().await;
+    let chain = vec![step("Draft: {{ previous_output }}"), step("Review: {{ previous_output }}")];
+    let chain_id = manager
+        .start_chained_run(chain, "topic".to_string(), None, None)
+        .await
+        .unwrap();
+
+    // The whole stream, as a client connecting now would get it
+    let events: Vec<NormalizedEvent> = tokio::time::timeout(Duration::from_secs(30), async {
+        let stream = manager.resume_stream(&chain_id, None).await.unwrap();
+        let mut stream = Box::pin(stream);
+        let mut events = Vec::new();
+        while let Some(sequenced) = stream.next().await {
+            let done = matches!(&sequenced.event, NormalizedEvent::RunDone { .. });
+            events.push(sequenced.event);
+            if done {
+                break;
+            }
+        }
+        events
+    })
+    .await
+    .expect("chain did not finish within 30 seconds");
+
+    // Each step is announced before any of its events
+    let NormalizedEvent::ChainProgress {
+        step: 1,
+        total: 2,
+        run_id: first_run,
+        ..
+    } = &events[0]
+    else {
+        panic!("chain did not start with its first step: {events:?}");
+    };
+    let NormalizedEvent::RunStart { run_id, .. } = &events[1] else {
+        panic!("first step did not start: {events:?}");
+    };
+    assert_eq!(run_id, first_run);
+    let steps: Vec<usize> = events
+        .iter()
+        .filter_map(|event| match event {
+            NormalizedEvent::ChainProgress { step, .. } => Some(*step),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(steps, [1, 2]);
+
+    // The second step answered the first step's output
+    let text: String = events
+        .iter()
+        .filter_map(|event| match event {
+            NormalizedEvent::ChatDelta { text_delta, .. } => Some(text_delta.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert!(text.ends_with("echo(Review: echo(Draft: topic))"), "{text}");
+
+    // Only the chain's own `RunDone` is streamed, last
+    let done: Vec<&NormalizedEvent> = events
+        .iter()
+        .filter(|event| matches!(event, NormalizedEvent::RunDone { .. }))
+        .collect();
+    assert_eq!(done.len(), 1, "{events:?}");
+    let Some(NormalizedEvent::RunDone { run_id, .. }) = events.last() else {
+        panic!("chain did not end with RunDone: {events:?}");
+    };
+    assert_eq!(*run_id, chain_id);
+
+    // The chain stays registered until it ends, then reports its outcome
+    let run = manager.get_run(&chain_id).await.expect("chain was dropped");
+    assert_eq!(run.status, RunStatus::Done);
+}
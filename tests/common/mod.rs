@@ -0,0 +1,98 @@
+//! Fixtures shared by the integration tests.
+
+// Each test binary uses only some of them
+#![allow(dead_code)]
+
+use axum::{Router, http::header, response::IntoResponse};
+use axum_leptos_htmx_wc::llm::LlmSettings;
+use axum_leptos_htmx_wc::mcp::registry::McpRegistry;
+use axum_leptos_htmx_wc::session::SessionStore;
+use axum_leptos_htmx_wc::uar::{
+    rag::embedding::EmbeddingProvider,
+    runtime::{manager::RunManager, matching::VectorMatcher, skills::SkillRegistry},
+};
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+pub use axum_leptos_htmx_wc::uar::testing::llm_settings;
+
+/// Embeds every text as the zero vector, so skills are only matched by
+/// keyword.
+#[derive(Debug)]
+pub struct NullEmbedder;
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for NullEmbedder {
+    async fn embed(&self, texts: Vec<String>) -> anyhow::Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|_| vec![0.0; 4]).collect())
+    }
+
+    fn dimensions(&self) -> usize {
+        4
+    }
+}
+
+/// One Chat Completions stream chunk carrying `delta`.
+pub fn sse_chunk(delta: Value, finish: Option<&str>) -> String {
+    let choice = json!({ "index": 0, "delta": delta, "finish_reason": finish });
+    format!("data: {}\n\n", json!({ "choices": [choice] }))
+}
+
+/// A Chat Completions stream of `chunks`, ended with `[DONE]`.
+pub fn sse_response(chunks: impl IntoIterator<Item = String>) -> impl IntoResponse {
+    let mut body: String = chunks.into_iter().collect();
+    body.push_str("data: [DONE]\n\n");
+    ([(header::CONTENT_TYPE, "text/event-stream")], body)
+}
+
+/// A streamed answer of `text`.
+pub fn sse_answer(text: &str) -> impl IntoResponse {
+    sse_response([
+        sse_chunk(json!({ "content": text }), None),
+        sse_chunk(json!({}), Some("stop")),
+    ])
+}
+
+/// A streamed call of tool `name` with JSON `arguments`, as `call_1`.
+pub fn sse_tool_call(name: &str, arguments: &str) -> impl IntoResponse {
+    let tool_call = json!({
+        "index": 0,
+        "id": "call_1",
+        "type": "function",
+        "function": { "name": name, "arguments": arguments }
+    });
+    sse_response([
+        sse_chunk(json!({ "tool_calls": [tool_call] }), None),
+        sse_chunk(json!({}), Some("tool_calls")),
+    ])
+}
+
+/// Serve `app` on a free local port, returning its address.
+pub async fn serve(app: Router) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+/// A run manager calling the mock LLM at `llm` for `mock-model`, with the
+/// tools of `mcp` and no skills.
+pub async fn run_manager(llm: SocketAddr, mcp: McpRegistry) -> RunManager {
+    run_manager_with(llm_settings(format!("http://{llm}"), "mock-model"), mcp).await
+}
+
+/// A run manager calling the LLM of `settings`, with the tools of `mcp` and
+/// no skills.
+pub async fn run_manager_with(settings: LlmSettings, mcp: McpRegistry) -> RunManager {
+    RunManager::new(
+        settings,
+        Arc::new(mcp),
+        SessionStore::new(),
+        Arc::new(RwLock::new(SkillRegistry::new(None, None))),
+        Arc::new(VectorMatcher::with_provider(0.75, Arc::new(NullEmbedder))),
+        None,
+    )
+    .await
+}
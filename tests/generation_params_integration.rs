@@ -0,0 +1,84 @@
+//! Generation parameters in the request bodies the drivers send.
+
+mod common;
+
+use axum::{Json, Router, extract::State, response::IntoResponse, routing::post};
+use axum_leptos_htmx_wc::llm::{
+    ChatCompletionsDriver, GenerationParams, LlmDriver, LlmProtocol, LlmRequest, LlmSettings,
+    ResponsesDriver,
+};
+use futures::StreamExt;
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+
+/// Records the request body and ends the stream at once.
+async fn record(
+    State(bodies): State<mpsc::UnboundedSender<Value>>,
+    Json(body): Json<Value>,
+) -> impl IntoResponse {
+    let _ = bodies.send(body);
+    common::sse_response(Vec::new())
+}
+
+/// Serve a mock LLM recording request bodies, returning its base URL.
+async fn spawn_mock(bodies: mpsc::UnboundedSender<Value>) -> String {
+    let app = Router::new()
+        .route("/v1/chat/completions", post(record))
+        .route("/v1/responses", post(record))
+        .with_state(bodies);
+    format!("http://{}", common::serve(app).await)
+}
+
+fn settings(base_url: String, protocol: LlmProtocol) -> LlmSettings {
+    LlmSettings {
+        protocol,
+        generation: GenerationParams {
+            temperature: Some(0.3),
+            top_p: Some(0.8),
+            max_tokens: Some(256),
+            presence_penalty: Some(0.1),
+            frequency_penalty: Some(0.2),
+            stop: vec!["###".to_string()],
+            ..GenerationParams::default()
+        },
+        ..common::llm_settings(base_url, "gpt-4.1")
+    }
+}
+
+fn request() -> LlmRequest {
+    LlmRequest {
+        messages: vec![json!({ "role": "user", "content": "Hi" })],
+        tools: vec![],
+        response_format: None,
+        tool_choice: None,
+    }
+}
+
+/// Send one request through `driver` and return the body the server got.
+async fn sent_body(driver: &dyn LlmDriver, bodies: &mut mpsc::UnboundedReceiver<Value>) -> Value {
+    let stream = driver.stream(request()).await.unwrap();
+    stream.for_each(|_| async {}).await;
+    bodies.recv().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_generation_params_in_request_bodies() {
+    let (tx, mut bodies) = mpsc::unbounded_channel();
+    let base_url = spawn_mock(tx).await;
+
+    let chat = ChatCompletionsDriver::new(settings(base_url.clone(), LlmProtocol::Chat));
+    let body = sent_body(&chat, &mut bodies).await;
+    assert_eq!(body["temperature"], 0.3);
+    assert_eq!(body["top_p"], 0.8);
+    assert_eq!(body["max_tokens"], 256);
+    assert_eq!(body["presence_penalty"], 0.1);
+    assert_eq!(body["frequency_penalty"], 0.2);
+    assert_eq!(body["stop"], json!(["###"]));
+
+    let responses = ResponsesDriver::new(settings(base_url, LlmProtocol::Responses));
+    let body = sent_body(&responses, &mut bodies).await;
+    assert_eq!(body["temperature"], 0.3);
+    assert_eq!(body["top_p"], 0.8);
+    assert_eq!(body["max_output_tokens"], 256);
+    assert!(body.get("stop").is_none());
+}
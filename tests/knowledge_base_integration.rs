@@ -5,6 +5,7 @@
 //! - Document management
 //! - Scoped vector search
 //! - Agent-scoped RAG retrieval
+//! - Cross-encoder reranking of search results
 //!
 //! Requires: DATABASE_URL environment variable pointing to a Postgres instance with pgvector.
 
@@ -13,7 +14,8 @@ use axum_leptos_htmx_wc::uar::{
     domain::knowledge::{
         DocumentStatus, KbConfig, KnowledgeBase, KnowledgeChunk, KnowledgeDocument,
     },
-    persistence::{PersistenceLayer, providers::postgres::PostgresProvider},
+    persistence::{PersistenceError, PersistenceLayer, providers::postgres::PostgresProvider},
+    rag::rerank::{Reranker, search_knowledge_reranked},
 };
 use serial_test::serial;
 use std::sync::Arc;
@@ -47,6 +49,7 @@ fn create_test_kb(suffix: &str) -> KnowledgeBase {
         ),
         description: Some(format!("Test knowledge base for {}", suffix)),
         config: KbConfig::default(),
+        tenant_id: None,
         created_at: now.clone(),
         updated_at: now,
     }
@@ -63,6 +66,10 @@ fn create_test_document(kb_id: &str, filename: &str) -> KnowledgeDocument {
         mime_type: Some("text/plain".to_string()),
         chunk_count: 0,
         status: DocumentStatus::Pending,
+        tenant_id: None,
+        metadata: None,
+        idempotency_key: None,
+        content_hash: None,
         created_at: now.clone(),
         updated_at: now,
     }
@@ -82,6 +89,7 @@ fn create_test_chunk(
         content: content.to_string(),
         metadata: Some(serde_json::json!({"test": true})),
         embedding,
+        tenant_id: None,
         created_at: chrono::Utc::now().to_rfc3339(),
     }
 }
@@ -107,7 +115,7 @@ async fn test_kb_create_and_retrieve() {
 
     // Retrieve by ID
     let retrieved = persistence
-        .get_knowledge_base(&kb.id)
+        .get_knowledge_base(&kb.id, None)
         .await
         .expect("Failed to get KB")
         .expect("KB not found");
@@ -118,16 +126,25 @@ async fn test_kb_create_and_retrieve() {
 
     // Retrieve by name
     let by_name = persistence
-        .get_knowledge_base_by_name(&kb.name)
+        .get_knowledge_base_by_name(&kb.name, None)
         .await
         .expect("Failed to get KB by name")
         .expect("KB not found by name");
 
     assert_eq!(by_name.id, kb.id);
 
+    // A second KB can't take the same name
+    let mut duplicate = create_test_kb("crud");
+    duplicate.name = kb.name.clone();
+    let result = persistence.save_knowledge_base(&duplicate).await;
+    assert!(
+        matches!(&result, Err(PersistenceError::DuplicateName(name)) if *name == kb.name),
+        "expected DuplicateName, got {result:?}"
+    );
+
     // Cleanup
     persistence
-        .delete_knowledge_base(&kb.id)
+        .delete_knowledge_base(&kb.id, None)
         .await
         .expect("Failed to delete KB");
 }
@@ -155,7 +172,7 @@ async fn test_kb_list() {
 
     // List all
     let all_kbs = persistence
-        .list_knowledge_bases()
+        .list_knowledge_bases(None)
         .await
         .expect("Failed to list KBs");
 
@@ -165,11 +182,11 @@ async fn test_kb_list() {
 
     // Cleanup
     persistence
-        .delete_knowledge_base(&kb1.id)
+        .delete_knowledge_base(&kb1.id, None)
         .await
         .expect("Failed to delete KB1");
     persistence
-        .delete_knowledge_base(&kb2.id)
+        .delete_knowledge_base(&kb2.id, None)
         .await
         .expect("Failed to delete KB2");
 }
@@ -199,7 +216,7 @@ async fn test_kb_update() {
 
     // Verify update
     let retrieved = persistence
-        .get_knowledge_base(&kb.id)
+        .get_knowledge_base(&kb.id, None)
         .await
         .expect("Failed to retrieve KB")
         .expect("KB not found");
@@ -211,7 +228,7 @@ async fn test_kb_update() {
 
     // Cleanup
     persistence
-        .delete_knowledge_base(&kb.id)
+        .delete_knowledge_base(&kb.id, None)
         .await
         .expect("Failed to delete KB");
 }
@@ -245,23 +262,41 @@ async fn test_kb_delete_cascade() {
 
     // Delete KB - should cascade to documents and chunks
     persistence
-        .delete_knowledge_base(&kb.id)
+        .delete_knowledge_base(&kb.id, None)
         .await
         .expect("Failed to delete KB");
 
     // Verify KB is gone
     let kb_result = persistence
-        .get_knowledge_base(&kb.id)
+        .get_knowledge_base(&kb.id, None)
         .await
         .expect("Failed to check KB");
     assert!(kb_result.is_none());
 
     // Verify document is gone
     let doc_result = persistence
-        .get_document(&doc.id)
+        .get_document(&doc.id, None)
         .await
         .expect("Failed to check document");
     assert!(doc_result.is_none());
+
+    // Deleting again reports the missing KB
+    let result = persistence.delete_knowledge_base(&kb.id, None).await;
+    assert!(
+        matches!(
+            result,
+            Err(PersistenceError::NotFound {
+                resource_type: "knowledge base",
+                ..
+            })
+        ),
+        "expected NotFound, got {result:?}"
+    );
+    let result = persistence.delete_document(&doc.id, None).await;
+    assert!(
+        matches!(result, Err(PersistenceError::NotFound { .. })),
+        "expected NotFound, got {result:?}"
+    );
 }
 
 // =============================================================================
@@ -292,7 +327,7 @@ async fn test_document_lifecycle() {
 
     // Verify initial status
     let retrieved = persistence
-        .get_document(&doc.id)
+        .get_document(&doc.id, None)
         .await
         .expect("Failed to get document")
         .unwrap();
@@ -305,7 +340,7 @@ async fn test_document_lifecycle() {
         .expect("Failed to update status");
 
     let processing = persistence
-        .get_document(&doc.id)
+        .get_document(&doc.id, None)
         .await
         .expect("Failed to get document")
         .unwrap();
@@ -318,7 +353,7 @@ async fn test_document_lifecycle() {
         .expect("Failed to update status");
 
     let indexed = persistence
-        .get_document(&doc.id)
+        .get_document(&doc.id, None)
         .await
         .expect("Failed to get document")
         .unwrap();
@@ -334,7 +369,7 @@ async fn test_document_lifecycle() {
         .expect("Failed to update status");
 
     let failed = persistence
-        .get_document(&doc.id)
+        .get_document(&doc.id, None)
         .await
         .expect("Failed to get document")
         .unwrap();
@@ -345,7 +380,7 @@ async fn test_document_lifecycle() {
 
     // Cleanup
     persistence
-        .delete_knowledge_base(&kb.id)
+        .delete_knowledge_base(&kb.id, None)
         .await
         .expect("Failed to delete KB");
 }
@@ -385,7 +420,7 @@ async fn test_document_list() {
 
     // List documents in KB
     let docs = persistence
-        .list_documents(&kb.id)
+        .list_documents(&kb.id, None)
         .await
         .expect("Failed to list documents");
     assert_eq!(docs.len(), 3);
@@ -397,7 +432,7 @@ async fn test_document_list() {
 
     // Cleanup
     persistence
-        .delete_knowledge_base(&kb.id)
+        .delete_knowledge_base(&kb.id, None)
         .await
         .expect("Failed to delete KB");
 }
@@ -443,7 +478,7 @@ async fn test_scoped_search_filters_by_kb() {
 
     // Search scoped to KB1 only
     let kb1_results = persistence
-        .search_knowledge_scoped(&[&kb1.id], &embedding, 10, 0.0)
+        .search_knowledge_scoped(&[&kb1.id], &embedding, 10, 0.0, None, None)
         .await
         .expect("Failed to search KB1");
 
@@ -455,7 +490,7 @@ async fn test_scoped_search_filters_by_kb() {
 
     // Search scoped to KB2 only
     let kb2_results = persistence
-        .search_knowledge_scoped(&[&kb2.id], &embedding, 10, 0.0)
+        .search_knowledge_scoped(&[&kb2.id], &embedding, 10, 0.0, None, None)
         .await
         .expect("Failed to search KB2");
 
@@ -466,7 +501,7 @@ async fn test_scoped_search_filters_by_kb() {
 
     // Search across both KBs
     let both_results = persistence
-        .search_knowledge_scoped(&[&kb1.id, &kb2.id], &embedding, 10, 0.0)
+        .search_knowledge_scoped(&[&kb1.id, &kb2.id], &embedding, 10, 0.0, None, None)
         .await
         .expect("Failed to search both KBs");
 
@@ -477,15 +512,66 @@ async fn test_scoped_search_filters_by_kb() {
 
     // Cleanup
     persistence
-        .delete_knowledge_base(&kb1.id)
+        .delete_knowledge_base(&kb1.id, None)
         .await
         .expect("Failed to delete KB1");
     persistence
-        .delete_knowledge_base(&kb2.id)
+        .delete_knowledge_base(&kb2.id, None)
         .await
         .expect("Failed to delete KB2");
 }
 
+#[tokio::test]
+#[serial]
+async fn test_scoped_search_filters_by_metadata() {
+    let Some(persistence) = setup_persistence().await else {
+        eprintln!("Skipping test: DATABASE_URL not set");
+        return;
+    };
+
+    let kb = create_test_kb("metadata-filter");
+    persistence
+        .save_knowledge_base(&kb)
+        .await
+        .expect("Failed to save KB");
+
+    let embedding = vec![0.5f32; 384];
+    for (content, source) in [("From the FAQ", "faq"), ("From the manual", "manual")] {
+        let mut chunk = create_test_chunk(&kb.id, None, content, embedding.clone());
+        chunk.metadata = Some(serde_json::json!({"source": source, "language": "en"}));
+        persistence
+            .save_chunk(&chunk)
+            .await
+            .expect("Failed to save chunk");
+    }
+
+    let filter = serde_json::json!({"source": "faq", "language": "en"});
+    let faq = persistence
+        .search_knowledge_scoped(&[&kb.id], &embedding, 10, 0.0, Some(&filter), None)
+        .await
+        .expect("Failed to search with filter");
+    assert_eq!(faq.len(), 1);
+    assert_eq!(faq[0].chunk.content, "From the FAQ");
+
+    let filter = serde_json::json!({"source": "blog"});
+    let none = persistence
+        .search_knowledge_scoped(&[&kb.id], &embedding, 10, 0.0, Some(&filter), None)
+        .await
+        .expect("Failed to search with filter");
+    assert!(none.is_empty());
+
+    let all = persistence
+        .search_knowledge_scoped(&[&kb.id], &embedding, 10, 0.0, None, None)
+        .await
+        .expect("Failed to search without filter");
+    assert_eq!(all.len(), 2);
+
+    persistence
+        .delete_knowledge_base(&kb.id, None)
+        .await
+        .expect("Failed to delete KB");
+}
+
 // =============================================================================
 // Default KB Initialization Tests
 // =============================================================================
@@ -499,8 +585,8 @@ async fn test_default_kb_initialization() {
     };
 
     // Ensure no default KB exists (may need to clean up from previous tests)
-    if let Ok(Some(existing)) = persistence.get_knowledge_base_by_name("default").await {
-        persistence.delete_knowledge_base(&existing.id).await.ok();
+    if let Ok(Some(existing)) = persistence.get_knowledge_base_by_name("default", None).await {
+        persistence.delete_knowledge_base(&existing.id, None).await.ok();
     }
 
     // First call should create the default KB
@@ -520,7 +606,7 @@ async fn test_default_kb_initialization() {
 
     // Cleanup
     persistence
-        .delete_knowledge_base(&kb1.id)
+        .delete_knowledge_base(&kb1.id, None)
         .await
         .expect("Failed to delete default KB");
 }
@@ -581,7 +667,7 @@ async fn test_chunk_storage_and_global_search() {
     // Search with embedding similar to first chunk
     let query = make_embedding(&[0.85, 0.15, 0.0]);
     let results = persistence
-        .search_knowledge(&query, 10, 0.0) // Low threshold to get all results
+        .search_knowledge(&query, 10, 0.0, None) // Low threshold to get all results
         .await
         .expect("Failed to search");
 
@@ -592,7 +678,100 @@ async fn test_chunk_storage_and_global_search() {
 
     // Cleanup
     persistence
-        .delete_knowledge_base(&kb.id)
+        .delete_knowledge_base(&kb.id, None)
+        .await
+        .expect("Failed to delete KB");
+}
+
+// =============================================================================
+// Reranking Tests
+// =============================================================================
+
+/// Stand-in cross-encoder: scores by how many query terms a chunk answers.
+#[derive(Debug)]
+struct KeywordReranker;
+
+#[async_trait::async_trait]
+impl Reranker for KeywordReranker {
+    async fn score(&self, query: &str, documents: &[String]) -> anyhow::Result<Vec<f32>> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        Ok(documents
+            .iter()
+            .map(|doc| {
+                let doc = doc.to_lowercase();
+                terms.iter().filter(|t| doc.contains(t.as_str())).count() as f32
+            })
+            .collect())
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_reranker_demotes_distracting_chunk() {
+    let Some(persistence) = setup_persistence().await else {
+        eprintln!("Skipping test: DATABASE_URL not set");
+        return;
+    };
+
+    let kb = create_test_kb("rerank");
+    persistence
+        .save_knowledge_base(&kb)
+        .await
+        .expect("Failed to save KB");
+
+    fn make_embedding(pattern: &[f32]) -> Vec<f32> {
+        pattern.iter().cycle().take(384).copied().collect()
+    }
+
+    // The distractor sits closest to the query vector but doesn't answer it
+    let query_vec = make_embedding(&[0.9, 0.1, 0.0]);
+    let distractor = create_test_chunk(
+        &kb.id,
+        None,
+        "Capital letters are used at the start of sentences",
+        make_embedding(&[0.9, 0.1, 0.0]),
+    );
+    let answer = create_test_chunk(
+        &kb.id,
+        None,
+        "Paris is the capital of France",
+        make_embedding(&[0.6, 0.4, 0.0]),
+    );
+    persistence
+        .save_chunk(&distractor)
+        .await
+        .expect("Failed to save distractor");
+    persistence
+        .save_chunk(&answer)
+        .await
+        .expect("Failed to save answer");
+
+    let plain = persistence
+        .search_knowledge_scoped(&[&kb.id], &query_vec, 2, 0.0, None, None)
+        .await
+        .expect("Failed to search");
+    assert_eq!(plain[0].chunk.id, distractor.id, "Distractor should win on cosine");
+
+    let reranked = search_knowledge_reranked(
+        persistence.as_ref(),
+        &KeywordReranker,
+        &[&kb.id],
+        "capital of france",
+        &query_vec,
+        1,
+        0.0,
+        None,
+        None,
+    )
+    .await
+    .expect("Failed to rerank");
+
+    assert_eq!(reranked.len(), 1);
+    assert_eq!(reranked[0].chunk.id, answer.id, "Reranker should lift the true answer");
+
+    // Cleanup
+    persistence
+        .delete_knowledge_base(&kb.id, None)
         .await
         .expect("Failed to delete KB");
 }
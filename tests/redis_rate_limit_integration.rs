@@ -0,0 +1,60 @@
+//! The Redis rate limiter's sliding window, against a real Redis.
+//!
+//! Requires Docker: a Redis container is started for each test. Run with
+//! `cargo test --test redis_rate_limit_integration -- --ignored`.
+
+use axum_leptos_htmx_wc::uar::security::rate_limit::{RateLimiter, RedisRateLimiter};
+use std::time::Duration;
+use testcontainers::{ContainerAsync, runners::AsyncRunner};
+use testcontainers_modules::redis::{REDIS_PORT, Redis};
+
+async fn redis() -> (ContainerAsync<Redis>, String) {
+    let container = Redis::default().start().await.expect("failed to start Redis");
+    let host = container.get_host().await.unwrap();
+    let port = container.get_host_port_ipv4(REDIS_PORT).await.unwrap();
+    (container, format!("redis://{host}:{port}"))
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn test_replicas_share_a_sliding_window() {
+    let (_container, url) = redis().await;
+    // 2 requests per second, in bursts of 2: a one second window
+    let replica_a = RedisRateLimiter::connect(&url, 2.0, 2).await.unwrap();
+    let replica_b = RedisRateLimiter::connect(&url, 2.0, 2).await.unwrap();
+    assert_eq!(replica_a.backend(), "redis");
+
+    assert!(replica_a.check("10.0.0.1").await);
+    assert!(replica_b.check("10.0.0.1").await);
+    assert!(!replica_a.check("10.0.0.1").await);
+    assert!(!replica_b.check("10.0.0.1").await);
+    // Other clients have their own window
+    assert!(replica_b.check("10.0.0.2").await);
+
+    let counts = replica_a.counts().await.unwrap();
+    assert_eq!(counts["10.0.0.1"], 2);
+    assert_eq!(counts["10.0.0.2"], 1);
+
+    // Rejected requests are not counted, so the window slides past the
+    // accepted ones
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert!(replica_a.check("10.0.0.1").await);
+    assert_eq!(replica_b.counts().await.unwrap()["10.0.0.1"], 1);
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn test_window_slides_instead_of_resetting() {
+    let (_container, url) = redis().await;
+    let limiter = RedisRateLimiter::connect(&url, 2.0, 2).await.unwrap();
+
+    assert!(limiter.check("client").await);
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert!(limiter.check("client").await);
+    assert!(!limiter.check("client").await);
+
+    // The first request has left the window, the second has not
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(limiter.check("client").await);
+    assert!(!limiter.check("client").await);
+}
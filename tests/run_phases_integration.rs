@@ -0,0 +1,131 @@
+//! Run phase events, against a mock LLM that calls a tool once and then
+//! answers.
+
+mod common;
+
+use axum::{
+    Router,
+    extract::State,
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use axum_leptos_htmx_wc::mcp::registry::McpRegistry;
+use axum_leptos_htmx_wc::uar::{
+    defaults::default_agent,
+    domain::{
+        events::{NormalizedEvent, RunPhase},
+        runs::RunOptions,
+    },
+    runtime::manager::RunManager,
+};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+use std::time::Duration;
+
+/// Calls the `mirror` test tool on the first request and answers after.
+async fn mock_completion(State(calls): State<Arc<AtomicUsize>>) -> Response {
+    if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+        common::sse_tool_call("test__mirror", r#"{"mirror":"hi"}"#).into_response()
+    } else {
+        common::sse_answer("Mirrored").into_response()
+    }
+}
+
+async fn manager() -> RunManager {
+    let app = Router::new()
+        .route("/v1/chat/completions", post(mock_completion))
+        .with_state(Arc::new(AtomicUsize::new(0)));
+    let llm = common::serve(app).await;
+    let mcp = McpRegistry::new_with_test_tool("mirror", "Returns its input");
+    common::run_manager(llm, mcp).await
+}
+
+/// Every event of a run of the default agent, up to and including `RunDone`.
+async fn run_events(manager: &RunManager) -> Vec<NormalizedEvent> {
+    let (_, mut rx) = manager
+        .start_run_streaming(
+            default_agent(),
+            "Mirror 'hi'".to_string(),
+            None,
+            None,
+            None,
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(30), async {
+        let mut events = Vec::new();
+        while let Ok(event) = rx.recv().await {
+            let done = matches!(event, NormalizedEvent::RunDone { .. });
+            events.push(event);
+            if done {
+                break;
+            }
+        }
+        events
+    })
+    .await
+    .expect("run did not finish within 30 seconds")
+}
+
+fn phases(events: &[NormalizedEvent]) -> Vec<RunPhase> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            NormalizedEvent::RunPhase { phase, .. } => Some(*phase),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_tool_using_run_streams_ordered_phases() {
+    let manager = manager().await.with_run_phases(true);
+    let events = run_events(&manager).await;
+
+    assert!(events.iter().any(|e| matches!(e, NormalizedEvent::ToolEnd { ok: true, .. })));
+    assert_eq!(
+        phases(&events),
+        [
+            RunPhase::SkillMatching,
+            RunPhase::Generating,
+            RunPhase::ToolExecution,
+            RunPhase::Generating,
+            RunPhase::Done,
+        ]
+    );
+    // `done` is the last thing before the run ends
+    assert!(matches!(
+        events[events.len() - 2],
+        NormalizedEvent::RunPhase {
+            phase: RunPhase::Done,
+            ..
+        }
+    ));
+
+    let tool_phase = events
+        .iter()
+        .find(|e| {
+            matches!(
+                e,
+                NormalizedEvent::RunPhase {
+                    phase: RunPhase::ToolExecution,
+                    ..
+                }
+            )
+        })
+        .unwrap();
+    let json = serde_json::to_value(tool_phase).unwrap();
+    assert_eq!(json["type"], "RunPhase");
+    assert_eq!(json["data"]["phase"], "tool_execution");
+}
+
+#[tokio::test]
+async fn test_phases_are_off_by_default() {
+    let manager = manager().await;
+    let events = run_events(&manager).await;
+    assert!(events.iter().any(|e| matches!(e, NormalizedEvent::ToolEnd { .. })));
+    assert!(phases(&events).is_empty());
+}
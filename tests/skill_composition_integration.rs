@@ -0,0 +1,139 @@
+//! Skills building on other skills, against a mock LLM that records the
+//! system prompt it was sent.
+
+mod common;
+
+use axum::{Json, Router, extract::State, response::IntoResponse, routing::post};
+use axum_leptos_htmx_wc::mcp::registry::McpRegistry;
+use axum_leptos_htmx_wc::uar::{
+    defaults::default_agent,
+    domain::{
+        events::NormalizedEvent,
+        runs::RunOptions,
+        skills::{Skill, SkillConstraints, SkillTriggers},
+    },
+    runtime::{manager::RunManager, skills::SKILL_DEPENDENCY_ERROR_CODE},
+};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Records the system prompt and streams a short answer.
+async fn mock_completion(
+    State(prompts): State<mpsc::UnboundedSender<String>>,
+    Json(request): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let system = request["messages"][0]["content"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let _ = prompts.send(system);
+    common::sse_answer("Reviewed")
+}
+
+/// A manager calling a mock LLM whose system prompts arrive on the receiver.
+async fn manager() -> (RunManager, mpsc::UnboundedReceiver<String>) {
+    let (prompts_tx, prompts) = mpsc::unbounded_channel();
+    let app = Router::new()
+        .route("/v1/chat/completions", post(mock_completion))
+        .with_state(prompts_tx);
+    let llm = common::serve(app).await;
+    let manager = common::run_manager(llm, McpRegistry::new_empty()).await;
+    (manager, prompts)
+}
+
+fn skill(skill_id: &str, keywords: &[&str], overlay: &str, dependencies: &[&str]) -> Skill {
+    Skill {
+        skill_id: skill_id.to_string(),
+        version: "1.0".to_string(),
+        title: skill_id.to_string(),
+        description: String::new(),
+        triggers: SkillTriggers {
+            keywords: keywords.iter().map(ToString::to_string).collect(),
+            semantic: None,
+            regex_patterns: None,
+        },
+        prompt_overlay: overlay.to_string(),
+        preferred_tools: vec![],
+        tool_choice: None,
+        mcp_config: None,
+        constraints: SkillConstraints::default(),
+        upgrade_path: None,
+        dependencies: dependencies.iter().map(ToString::to_string).collect(),
+    }
+}
+
+/// Run the default agent on `input`, returning the error event's code and
+/// message if the run failed.
+async fn run(manager: &RunManager, input: &str) -> Option<(String, String)> {
+    let (_, mut events) = manager
+        .start_run_streaming(
+            default_agent(),
+            input.to_string(),
+            None,
+            None,
+            None,
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(30), async {
+        let mut error = None;
+        while let Ok(event) = events.recv().await {
+            match event {
+                NormalizedEvent::Error { code, message, .. } => error = Some((code, message)),
+                NormalizedEvent::RunDone { .. } => break,
+                _ => {}
+            }
+        }
+        error
+    })
+    .await
+    .expect("run did not finish within 30 seconds")
+}
+
+#[tokio::test]
+async fn test_dependency_overlays_precede_the_matched_skill() {
+    let (manager, mut prompts) = manager().await;
+    {
+        let mut skills = manager.skills().write().await;
+        for skill in [
+            skill("code_review", &["review"], "REVIEW STEPS", &["security_analysis"]),
+            skill("security_analysis", &[], "SECURITY CHECKS", &["style_guide"]),
+            skill("style_guide", &[], "STYLE RULES", &[]),
+        ] {
+            skills.register(skill).await.unwrap();
+        }
+    }
+
+    assert_eq!(run(&manager, "Please review my patch").await, None);
+
+    let system = prompts.try_recv().expect("the LLM was not called");
+    let position = |overlay: &str| {
+        system
+            .find(overlay)
+            .unwrap_or_else(|| panic!("{overlay} missing from {system}"))
+    };
+    assert!(position("STYLE RULES") < position("SECURITY CHECKS"), "{system}");
+    assert!(position("SECURITY CHECKS") < position("REVIEW STEPS"), "{system}");
+    assert_eq!(system.matches("[SKILL: ").count(), 3);
+}
+
+#[tokio::test]
+async fn test_dependency_cycle_fails_the_run() {
+    let (manager, mut prompts) = manager().await;
+    {
+        let mut skills = manager.skills().write().await;
+        for skill in [
+            skill("draft", &["draft"], "DRAFT", &["edit"]),
+            skill("edit", &[], "EDIT", &["proofread"]),
+            skill("proofread", &[], "PROOFREAD", &["draft"]),
+        ] {
+            skills.register(skill).await.unwrap();
+        }
+    }
+
+    let (code, message) = run(&manager, "Write a draft").await.expect("the run succeeded");
+    assert_eq!(code, SKILL_DEPENDENCY_ERROR_CODE);
+    assert!(message.contains("draft -> edit -> proofread -> draft"), "{message}");
+    assert!(prompts.try_recv().is_err(), "the LLM was called");
+}
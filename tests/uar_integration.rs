@@ -1,4 +1,6 @@
-use axum_leptos_htmx_wc::llm::{LlmProtocol, LlmSettings, Provider};
+mod common;
+
+use axum_leptos_htmx_wc::llm::{LlmProtocol, LlmSettings, Provider, ToolChoice};
 use axum_leptos_htmx_wc::mcp::registry::McpRegistry;
 use axum_leptos_htmx_wc::session::SessionStore;
 use axum_leptos_htmx_wc::uar;
@@ -15,26 +17,26 @@ use serde_json::json;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-// Helper to construct a REAL Orchestrator from environment
-async fn setup_real_env() -> (Arc<RunManager>, Arc<SessionStore>) {
-    // Load .env if available
+/// Settings of the LLM configured in the environment (or `.env`).
+fn env_settings() -> LlmSettings {
     let _ = dotenv();
 
     let base_url =
         std::env::var("LLM_BASE_URL").expect("LLM_BASE_URL must be set for integration tests");
     let model = std::env::var("LLM_MODEL").expect("LLM_MODEL must be set for integration tests");
-    let api_key = std::env::var("LLM_API_KEY").ok();
-
-    let settings = LlmSettings {
-        base_url: base_url.clone(),
-        api_key,
-        model,
+    LlmSettings {
+        api_key: std::env::var("LLM_API_KEY").ok(),
         protocol: LlmProtocol::Auto,
         provider: Provider::detect_from_url(&base_url),
-        parallel_tool_calls: None,
         deployment_name: std::env::var("AZURE_DEPLOYMENT_NAME").ok(),
         api_version: std::env::var("AZURE_API_VERSION").ok(),
-    };
+        ..common::llm_settings(base_url, &model)
+    }
+}
+
+// Helper to construct a REAL Orchestrator from environment
+async fn setup_real_env() -> (Arc<RunManager>, Arc<SessionStore>) {
+    let settings = env_settings();
 
     let mcp = Arc::new(McpRegistry::new_empty());
     let sessions = SessionStore::new();
@@ -64,24 +66,7 @@ async fn setup_real_env_with_tools() -> (
     Arc<SessionStore>,
     Arc<RwLock<SkillRegistry>>,
 ) {
-    // Load .env if available
-    let _ = dotenv();
-
-    let base_url =
-        std::env::var("LLM_BASE_URL").expect("LLM_BASE_URL must be set for integration tests");
-    let model = std::env::var("LLM_MODEL").expect("LLM_MODEL must be set for integration tests");
-    let api_key = std::env::var("LLM_API_KEY").ok();
-
-    let settings = LlmSettings {
-        base_url: base_url.clone(),
-        api_key,
-        model,
-        protocol: LlmProtocol::Auto,
-        provider: Provider::detect_from_url(&base_url),
-        parallel_tool_calls: None,
-        deployment_name: std::env::var("AZURE_DEPLOYMENT_NAME").ok(),
-        api_version: std::env::var("AZURE_API_VERSION").ok(),
-    };
+    let settings = env_settings();
 
     // Register a test tool "mirror"
     let mcp = Arc::new(McpRegistry::new_with_test_tool(
@@ -202,6 +187,8 @@ async fn test_m2_run_lifecycle() {
             "Say 'Hello UAR Integration' and nothing else.".to_string(),
             Some(session_id.clone()),
             None,
+            None,
+            Default::default(),
         )
         .await;
 
@@ -265,6 +252,8 @@ async fn test_m3_api_flow() {
             prompt,
             Some(session_id),
             None,
+            None,
+            Default::default(),
         )
         .await;
 
@@ -303,7 +292,7 @@ async fn test_m4_tool_execution() {
       "metadata": { "title": "Tool Agent", "description": "Test" },
       "runtime": { "entry": "llm.chat", "protocols": {} },
       "policy": {
-        "provider": { "default": { "provider": "openai", "model": "gpt-4o" } },
+        "provider": { "default": { "provider": "openai", "model": "" } },
         "tools": { "max_concurrent": 1 },
         "skills": { "prefer": [] }
       },
@@ -327,6 +316,8 @@ async fn test_m4_tool_execution() {
             "Please mirror the word 'MAGIC'".to_string(),
             Some(session_id),
             None,
+            None,
+            Default::default(),
         )
         .await;
 
@@ -401,15 +392,20 @@ async fn test_m6_skills_execution() {
                 triggers: SkillTriggers {
                     keywords: vec!["trigger skill".to_string()],
                     semantic: None,
+                    regex_patterns: None,
                 },
                 prompt_overlay:
                     "You MUST use the 'mirror' tool to reflect the user's input exactly."
                         .to_string(),
                 preferred_tools: vec!["mirror".to_string()],
+                tool_choice: Some(ToolChoice::Required),
                 mcp_config: None,
                 constraints: SkillConstraints::default(),
+                upgrade_path: None,
+                dependencies: vec![],
             })
-            .await;
+            .await
+            .unwrap();
     }
 
     // 3. Define Agent
@@ -436,7 +432,7 @@ async fn test_m6_skills_execution() {
         },
         "policy": {
             "provider": {
-                "default": { "provider": "openai", "model": "gpt-4o" }
+                "default": { "provider": "openai", "model": "" }
             },
             "tools": {
                 "allow": ["mirror"]
@@ -464,6 +460,8 @@ async fn test_m6_skills_execution() {
             "trigger skill: please echo 'SKILL_WORKED'".to_string(),
             Some(session_id),
             None,
+            None,
+            Default::default(),
         )
         .await;
 
@@ -0,0 +1,75 @@
+//! Run webhooks, against a mock LLM endpoint and a local webhook receiver.
+
+mod common;
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::post,
+};
+use axum_leptos_htmx_wc::mcp::registry::McpRegistry;
+use axum_leptos_htmx_wc::uar::{
+    defaults::default_agent,
+    domain::runs::RunStatus,
+    runtime::webhook::{SIGNATURE_HEADER, WebhookPayload, sign},
+};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const SECRET: &str = "webhook-test-secret";
+
+/// Streams a fixed answer in Chat Completions SSE format.
+async fn mock_completion() -> impl IntoResponse {
+    common::sse_answer("Hello from the mock")
+}
+
+/// Forwards each webhook's signature header and body to the test.
+async fn receive_webhook(
+    State(received): State<mpsc::UnboundedSender<(Option<String>, Bytes)>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let _ = received.send((signature, body));
+    StatusCode::NO_CONTENT
+}
+
+#[tokio::test]
+async fn test_successful_run_triggers_signed_webhook() {
+    let (tx, mut received) = mpsc::unbounded_channel();
+    let app = Router::new()
+        .route("/v1/chat/completions", post(mock_completion))
+        .route("/hook", post(receive_webhook))
+        .with_state(tx);
+    let addr = common::serve(app).await;
+    let manager = common::run_manager(addr, McpRegistry::new_empty())
+        .await
+        .with_webhook_allowed_hosts(vec!["127.0.0.1".to_string()]);
+
+    let mut agent = default_agent();
+    agent.runtime.webhook_url = Some(format!("http://{addr}/hook"));
+    agent.runtime.webhook_secret = Some(SECRET.to_string());
+    let run_id = manager
+        .start_run(agent, "Say hello".to_string(), None, None, None, Default::default())
+        .await
+        .unwrap();
+
+    let (signature, body) = tokio::time::timeout(Duration::from_secs(30), received.recv())
+        .await
+        .expect("no webhook within 30 seconds")
+        .unwrap();
+    assert_eq!(signature.as_deref(), Some(sign(SECRET, &body).as_str()));
+
+    let payload: WebhookPayload = serde_json::from_slice(&body).unwrap();
+    assert_eq!(payload.run_id, run_id);
+    assert_eq!(payload.status, RunStatus::Done);
+    assert_eq!(payload.agent_id, "default-agent");
+    assert!(payload.session_id.is_some());
+    assert_eq!(payload.summary, "Hello from the mock");
+}
@@ -0,0 +1,147 @@
+//! Runs over the WebSocket endpoint, against a mock LLM endpoint.
+
+mod common;
+
+use axum::{Router, body::Body, extract::State, http::header, response::IntoResponse, routing::post};
+use axum_leptos_htmx_wc::mcp::registry::McpRegistry;
+use axum_leptos_htmx_wc::uar::{
+    self, defaults::default_agent, domain::runs::RunStatus, runtime::manager::RunManager,
+};
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Streams "Hello from the mock", except that the first completion stalls
+/// after its first delta.
+async fn mock_completion(State(calls): State<Arc<AtomicUsize>>) -> impl IntoResponse {
+    let first = calls.fetch_add(1, Ordering::SeqCst) == 0;
+    let head = futures::stream::iter([common::sse_chunk(json!({ "content": "Hello" }), None)])
+        .map(Ok::<_, std::io::Error>);
+    let body = if first {
+        Body::from_stream(head.chain(futures::stream::pending()))
+    } else {
+        let tail = [
+            common::sse_chunk(json!({ "content": " from the mock" }), None),
+            common::sse_chunk(json!({}), Some("stop")),
+            "data: [DONE]\n\n".to_string(),
+        ];
+        Body::from_stream(head.chain(futures::stream::iter(tail).map(Ok)))
+    };
+    ([(header::CONTENT_TYPE, "text/event-stream")], body)
+}
+
+async fn send(socket: &mut Socket, message: Value) {
+    socket.send(Message::text(message.to_string())).await.unwrap();
+}
+
+/// Events up to and including the first one of type `until`.
+async fn events_until(socket: &mut Socket, until: &str) -> Vec<Value> {
+    let mut events = Vec::new();
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(30), socket.next())
+            .await
+            .unwrap_or_else(|_| panic!("no {until} event within 30 seconds"))
+            .unwrap()
+            .unwrap();
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let event: Value = serde_json::from_str(&text).unwrap();
+        let done = event["type"] == until;
+        events.push(event);
+        if done {
+            return events;
+        }
+    }
+}
+
+/// Serve the UAR API over a run manager whose LLM is [`mock_completion`],
+/// counting completions in `calls`.
+async fn serve(calls: &Arc<AtomicUsize>) -> (Arc<RunManager>, std::net::SocketAddr) {
+    let llm = Router::new()
+        .route("/v1/chat/completions", post(mock_completion))
+        .with_state(Arc::clone(calls));
+    let llm_addr = common::serve(llm).await;
+    let manager = Arc::new(common::run_manager(llm_addr, McpRegistry::new_empty()).await);
+
+    let app = Router::new().nest("/api/uar", uar::api::router().with_state(Arc::clone(&manager)));
+    (manager, common::serve(app).await)
+}
+
+#[tokio::test]
+async fn test_cancel_and_follow_up_on_one_socket() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let (manager, addr) = serve(&calls).await;
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/api/uar/ws"))
+        .await
+        .unwrap();
+
+    // Without a previous run there is no agent to continue with
+    send(&mut socket, json!({ "type": "start", "input": "Hi" })).await;
+    let events = events_until(&mut socket, "Error").await;
+    assert_eq!(events[0]["data"]["code"], "missing_artifact");
+
+    // Cancel a run while it streams
+    let start = json!({ "type": "start", "artifact": default_agent(), "input": "Say hello" });
+    send(&mut socket, start).await;
+    let events = events_until(&mut socket, "ChatDelta").await;
+    assert_eq!(events[0]["type"], "RunStart");
+    let first_run = events[0]["data"]["run_id"].as_str().unwrap().to_string();
+
+    send(&mut socket, json!({ "type": "cancel" })).await;
+    let events = events_until(&mut socket, "RunDone").await;
+    assert!(events.iter().any(|e| e["type"] == "RunCancelled"), "{events:?}");
+    let first = manager.get_run(&first_run).await.unwrap();
+    assert_eq!(first.status, RunStatus::Cancelled);
+
+    // A follow-up turn reuses the agent and the conversation
+    send(&mut socket, json!({ "type": "start", "input": "Say it again" })).await;
+    let events = events_until(&mut socket, "RunDone").await;
+    let answer: String = events
+        .iter()
+        .filter(|e| e["type"] == "ChatDelta")
+        .filter_map(|e| e["data"]["text_delta"].as_str())
+        .collect();
+    assert_eq!(answer, "Hello from the mock");
+
+    let second_run = events[0]["data"]["run_id"].as_str().unwrap();
+    assert_ne!(second_run, first_run);
+    let second = manager.get_run(second_run).await.unwrap();
+    assert_eq!(second.agent_id, "default-agent");
+    assert!(first.conversation_id.is_some());
+    assert_eq!(second.conversation_id, first.conversation_id);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_closing_socket_cancels_streaming_run() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let (manager, addr) = serve(&calls).await;
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/api/uar/ws"))
+        .await
+        .unwrap();
+
+    // The first completion stalls, so the run is still streaming
+    let start = json!({ "type": "start", "artifact": default_agent(), "input": "Say hello" });
+    send(&mut socket, start).await;
+    let events = events_until(&mut socket, "ChatDelta").await;
+    let run_id = events[0]["data"]["run_id"].as_str().unwrap().to_string();
+
+    socket.close(None).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while manager.get_run(&run_id).await.unwrap().status != RunStatus::Cancelled {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("run was not cancelled after the socket closed");
+}